@@ -0,0 +1,95 @@
+//! Frame encoding with and without a buffer pool.
+//!
+//! Prints heap allocations per encode before timing both paths:
+//!
+//! ```bash
+//! cargo bench --bench frame_pool
+//! ```
+
+// The counting allocator needs unsafe; criterion macros generate undocumented items
+#![allow(missing_docs, unsafe_code)]
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::hint::black_box;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use m2m::codec::{BufferPool, M2MFrame};
+
+/// System allocator that counts allocations
+struct CountingAlloc;
+
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+const ITERATIONS: u64 = 1000;
+
+fn request() -> String {
+    let turns: Vec<String> = (0..20)
+        .map(|i| {
+            format!(
+                r#"{{"role":"user","content":"Question {i}: summarize the previous answer."}},{{"role":"assistant","content":"Answer {i}: the summary is short."}}"#
+            )
+        })
+        .collect();
+    format!(
+        r#"{{"model":"gpt-4o","messages":[{}],"temperature":0.7}}"#,
+        turns.join(",")
+    )
+}
+
+/// Average allocations of `encode` over `ITERATIONS` runs
+fn allocations_per_encode(mut encode: impl FnMut()) -> f64 {
+    encode();
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    for _ in 0..ITERATIONS {
+        encode();
+    }
+    (ALLOCATIONS.load(Ordering::Relaxed) - before) as f64 / ITERATIONS as f64
+}
+
+fn bench_frame_pool(c: &mut Criterion) {
+    let json = request();
+    let frame = M2MFrame::new_request(&json).unwrap();
+    let pool = BufferPool::new();
+
+    let fresh = allocations_per_encode(|| {
+        black_box(frame.encode().unwrap());
+    });
+    let pooled = allocations_per_encode(|| {
+        pool.put(black_box(frame.encode_pooled(&pool).unwrap()));
+    });
+    println!(
+        "allocations per encode: fresh {fresh:.1}, pooled {pooled:.1} (pool hit rate {:.1}%)",
+        pool.stats().hit_rate() * 100.0
+    );
+
+    let mut group = c.benchmark_group("frame_encode");
+    group.throughput(Throughput::Bytes(json.len() as u64));
+    group.bench_function("fresh", |b| b.iter(|| black_box(frame.encode().unwrap())));
+    group.bench_function("pooled", |b| {
+        b.iter(|| pool.put(black_box(frame.encode_pooled(&pool).unwrap())));
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_frame_pool);
+criterion_main!(benches);
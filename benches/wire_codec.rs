@@ -0,0 +1,87 @@
+//! Text wire encode/decode on the M2M and Brotli paths, plus frame CRC32.
+//!
+//! Both paths are base64 end to end. Compare the scalar and SIMD builds
+//! against a saved baseline:
+//!
+//! ```bash
+//! cargo bench --bench wire_codec --features brotli -- --save-baseline scalar
+//! cargo bench --bench wire_codec --features brotli,simd -- --baseline scalar
+//! ```
+
+// criterion macros generate undocumented items
+#![allow(missing_docs)]
+
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use m2m::codec::M2MFrame;
+
+/// Chat request of roughly `turns * 150` bytes
+fn request(turns: usize) -> String {
+    let messages: Vec<String> = (0..turns)
+        .map(|i| {
+            format!(
+                r#"{{"role":"user","content":"Question {i}: summarize the previous answer in detail."}},{{"role":"assistant","content":"Answer {i}: the summary covers {i} points."}}"#
+            )
+        })
+        .collect();
+    format!(
+        r#"{{"model":"gpt-4o","messages":[{}],"temperature":0.7}}"#,
+        messages.join(",")
+    )
+}
+
+fn bench_m2m(c: &mut Criterion) {
+    let mut group = c.benchmark_group("m2m_string");
+    for turns in [10, 100, 1000] {
+        let json = request(turns);
+        let frame = M2MFrame::new_request(&json).unwrap();
+        let wire = frame.encode_string().unwrap();
+        group.throughput(Throughput::Bytes(json.len() as u64));
+        group.bench_with_input(BenchmarkId::new("encode", json.len()), &frame, |b, f| {
+            b.iter(|| black_box(f.encode_string().unwrap()));
+        });
+        group.bench_with_input(BenchmarkId::new("decode", json.len()), &wire, |b, w| {
+            b.iter(|| black_box(M2MFrame::decode_string(w).unwrap()));
+        });
+    }
+    group.finish();
+}
+
+#[cfg(feature = "brotli")]
+fn bench_brotli(c: &mut Criterion) {
+    use m2m::codec::BrotliCodec;
+
+    let codec = BrotliCodec::new();
+    let mut group = c.benchmark_group("brotli_wire");
+    for turns in [10, 100, 1000] {
+        let json = request(turns);
+        let wire = codec.compress(&json).unwrap().data;
+        group.throughput(Throughput::Bytes(json.len() as u64));
+        group.bench_with_input(BenchmarkId::new("compress", json.len()), &json, |b, j| {
+            b.iter(|| black_box(codec.compress(j).unwrap()));
+        });
+        group.bench_with_input(BenchmarkId::new("decompress", json.len()), &wire, |b, w| {
+            b.iter(|| black_box(codec.decompress(w).unwrap()));
+        });
+    }
+    group.finish();
+}
+
+#[cfg(not(feature = "brotli"))]
+fn bench_brotli(_: &mut Criterion) {}
+
+fn bench_crc32(c: &mut Criterion) {
+    let mut group = c.benchmark_group("crc32");
+    for len in [1 << 10, 1 << 16, 1 << 20] {
+        let payload = vec![0x5a; len];
+        group.throughput(Throughput::Bytes(len as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(len), &payload, |b, p| {
+            b.iter(|| black_box(crc32fast::hash(p)));
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_m2m, bench_brotli, bench_crc32);
+criterion_main!(benches);
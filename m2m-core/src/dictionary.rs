@@ -6,11 +6,14 @@
 //! **DEPRECATED**: This module is kept for backwards compatibility with
 //! legacy wire formats. Use M2M codec for new implementations.
 
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
 use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
 use serde_json::Value;
-use std::collections::HashMap;
 
-use crate::error::Result;
+use crate::error::{Error, Result};
 
 /// Wire format prefix for dictionary codec
 pub const DICTIONARY_PREFIX: &str = "#M2M|";
@@ -18,64 +21,49 @@ pub const DICTIONARY_PREFIX: &str = "#M2M|";
 /// Common patterns encoded as single bytes (0x80-0xFF range)
 const PATTERN_START: u8 = 0x80;
 
-lazy_static::lazy_static! {
-    /// Common JSON patterns → single byte
-    static ref PATTERN_ENCODE: HashMap<&'static str, u8> = {
-        let mut m = HashMap::new();
-        // Common structural patterns
-        m.insert(r#"{"role":"user","content":"#, 0x80);
-        m.insert(r#"{"role":"assistant","content":"#, 0x81);
-        m.insert(r#"{"role":"system","content":"#, 0x82);
-        m.insert(r#""}"#, 0x83);
-        m.insert(r#"},"#, 0x84);
-        m.insert(r#""}]"#, 0x85);
-        m.insert(r#"{"messages":["#, 0x86);
-        m.insert(r#"{"model":"#, 0x87);
-        m.insert(r#","messages":["#, 0x88);
-        m.insert(r#","max_tokens":"#, 0x89);
-        m.insert(r#","temperature":"#, 0x8A);
-        m.insert(r#","stream":true"#, 0x8B);
-        m.insert(r#","stream":false"#, 0x8C);
-        // Common model prefixes
-        m.insert(r#""gpt-4"#, 0x90);
-        m.insert(r#""gpt-4o"#, 0x91);
-        m.insert(r#""gpt-4o-mini"#, 0x92);
-        m.insert(r#""gpt-3.5-turbo"#, 0x93);
-        m.insert(r#""claude-3"#, 0x94);
-        m.insert(r#""llama"#, 0x95);
-        // Response patterns
-        m.insert(r#"{"choices":[{"#, 0xA0);
-        m.insert(r#""finish_reason":"stop""#, 0xA1);
-        m.insert(r#""finish_reason":"length""#, 0xA2);
-        m.insert(r#","usage":{"#, 0xA3);
-        m.insert(r#""prompt_tokens":"#, 0xA4);
-        m.insert(r#","completion_tokens":"#, 0xA5);
-        m.insert(r#","total_tokens":"#, 0xA6);
-        m.insert(r#""index":0,"#, 0xA7);
-        m.insert(r#""message":{"#, 0xA8);
-        m.insert(r#""delta":{"#, 0xA9);
-        // Tool patterns
-        m.insert(r#""tool_calls":[{"#, 0xB0);
-        m.insert(r#""type":"function","#, 0xB1);
-        m.insert(r#""function":{"#, 0xB2);
-        m.insert(r#""name":"#, 0xB3);
-        m.insert(r#","arguments":"#, 0xB4);
-        m
-    };
-
-    /// Single byte → common pattern
-    static ref PATTERN_DECODE: HashMap<u8, &'static str> = {
-        PATTERN_ENCODE.iter().map(|(k, v)| (*v, *k)).collect()
-    };
-
-    /// Patterns sorted by length (longest first) for deterministic matching
-    static ref PATTERNS_SORTED: Vec<(&'static str, u8)> = {
-        let mut patterns: Vec<_> = PATTERN_ENCODE.iter().map(|(k, v)| (*k, *v)).collect();
-        // Sort by length descending to match longest patterns first
-        patterns.sort_by(|a, b| b.0.len().cmp(&a.0.len()));
-        patterns
-    };
-}
+/// Common JSON patterns and their byte codes
+///
+/// Where patterns share a prefix, the longest match wins.
+const PATTERNS: &[(&str, u8)] = &[
+    // Common structural patterns
+    (r#"{"role":"user","content":"#, 0x80),
+    (r#"{"role":"assistant","content":"#, 0x81),
+    (r#"{"role":"system","content":"#, 0x82),
+    (r#""}"#, 0x83),
+    (r#"},"#, 0x84),
+    (r#""}]"#, 0x85),
+    (r#"{"messages":["#, 0x86),
+    (r#"{"model":"#, 0x87),
+    (r#","messages":["#, 0x88),
+    (r#","max_tokens":"#, 0x89),
+    (r#","temperature":"#, 0x8A),
+    (r#","stream":true"#, 0x8B),
+    (r#","stream":false"#, 0x8C),
+    // Common model prefixes
+    (r#""gpt-4"#, 0x90),
+    (r#""gpt-4o"#, 0x91),
+    (r#""gpt-4o-mini"#, 0x92),
+    (r#""gpt-3.5-turbo"#, 0x93),
+    (r#""claude-3"#, 0x94),
+    (r#""llama"#, 0x95),
+    // Response patterns
+    (r#"{"choices":[{"#, 0xA0),
+    (r#""finish_reason":"stop""#, 0xA1),
+    (r#""finish_reason":"length""#, 0xA2),
+    (r#","usage":{"#, 0xA3),
+    (r#""prompt_tokens":"#, 0xA4),
+    (r#","completion_tokens":"#, 0xA5),
+    (r#","total_tokens":"#, 0xA6),
+    (r#""index":0,"#, 0xA7),
+    (r#""message":{"#, 0xA8),
+    (r#""delta":{"#, 0xA9),
+    // Tool patterns
+    (r#""tool_calls":[{"#, 0xB0),
+    (r#""type":"function","#, 0xB1),
+    (r#""function":{"#, 0xB2),
+    (r#""name":"#, 0xB3),
+    (r#","arguments":"#, 0xB4),
+];
 
 /// Dictionary codec using pattern matching
 #[derive(Clone)]
@@ -137,8 +125,7 @@ impl DictionaryCodec {
                 if self.use_patterns {
                     self.decompress_with_patterns(&decoded)
                 } else {
-                    String::from_utf8(decoded)
-                        .map_err(|e| crate::error::M2MError::Decompression(e.to_string()))
+                    String::from_utf8(decoded).map_err(|e| Error::Decompression(e.to_string()))
                 }
             },
             Err(_) => {
@@ -156,19 +143,17 @@ impl DictionaryCodec {
 
         while i < bytes.len() {
             let remaining = &content[i..];
-            let mut matched = false;
-
-            // Try to match patterns (sorted by length, longest first for determinism)
-            for (pattern, code) in PATTERNS_SORTED.iter() {
-                if remaining.starts_with(pattern) {
-                    result.push(*code);
-                    i += pattern.len();
-                    matched = true;
-                    break;
-                }
-            }
 
-            if !matched {
+            // Longest matching pattern, for determinism
+            let longest = PATTERNS
+                .iter()
+                .filter(|(pattern, _)| remaining.starts_with(pattern))
+                .max_by_key(|(pattern, _)| pattern.len());
+
+            if let Some((pattern, code)) = longest {
+                result.push(*code);
+                i += pattern.len();
+            } else {
                 result.push(bytes[i]);
                 i += 1;
             }
@@ -183,7 +168,7 @@ impl DictionaryCodec {
 
         for &byte in data {
             if byte >= PATTERN_START {
-                if let Some(&pattern) = PATTERN_DECODE.get(&byte) {
+                if let Some((pattern, _)) = PATTERNS.iter().find(|(_, code)| *code == byte) {
                     result.push_str(pattern);
                 } else {
                     // Unknown pattern byte, treat as literal
@@ -210,8 +195,7 @@ impl DictionaryCodec {
     /// Decompress to JSON value
     pub fn decompress_value(&self, wire: &str) -> Result<Value> {
         let json = self.decompress(wire)?;
-        serde_json::from_str(&json)
-            .map_err(|e| crate::error::M2MError::Decompression(e.to_string()))
+        serde_json::from_str(&json).map_err(|e| Error::Decompression(e.to_string()))
     }
 }
 
@@ -273,11 +257,16 @@ mod tests {
 
     #[test]
     fn test_pattern_encode_decode() {
-        // Verify all patterns have corresponding decode entries
-        for (pattern, code) in PATTERN_ENCODE.iter() {
+        // Every code decodes to exactly one pattern
+        for (pattern, code) in PATTERNS {
             assert!(
-                PATTERN_DECODE.contains_key(code),
-                "Pattern '{pattern}' (0x{code:02X}) missing decode entry"
+                *code >= PATTERN_START,
+                "Pattern '{pattern}' code below range"
+            );
+            assert_eq!(
+                PATTERNS.iter().filter(|(_, other)| other == code).count(),
+                1,
+                "Pattern '{pattern}' (0x{code:02X}) shares its code"
             );
         }
     }
@@ -0,0 +1,46 @@
+//! Error type of the core codecs.
+//!
+//! The `m2m` crate converts it into `M2MError` with the same variants, so
+//! `?` keeps working across the boundary.
+
+use alloc::string::String;
+use core::fmt;
+
+/// Result type alias for core operations
+pub type Result<T> = core::result::Result<T, Error>;
+
+/// Core codec error
+#[derive(Debug)]
+pub enum Error {
+    /// Compression failed due to invalid input or unsupported content
+    Compression(String),
+    /// Decompression failed due to corrupted or invalid wire format
+    Decompression(String),
+    /// JSON serialization/deserialization failed
+    Json(serde_json::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Compression(msg) => write!(f, "Compression error: {msg}"),
+            Error::Decompression(msg) => write!(f, "Decompression error: {msg}"),
+            Error::Json(err) => write!(f, "JSON error: {err}"),
+        }
+    }
+}
+
+impl core::error::Error for Error {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            Error::Json(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self {
+        Error::Json(err)
+    }
+}
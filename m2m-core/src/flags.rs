@@ -41,7 +41,8 @@ impl RequestFlags {
     pub const HAS_TOP_P: u16 = 1 << 13;
     /// Stop sequences specified
     pub const HAS_STOP: u16 = 1 << 14;
-    // Bit 15 reserved
+    /// Routing header carries media stats
+    pub const HAS_MEDIA_STATS: u16 = 1 << 15;
 
     /// Create new empty flags
     pub fn new() -> Self {
@@ -139,7 +140,18 @@ impl CommonFlags {
     pub const COMPRESSED: u8 = 1 << 0; // Bit 24 in full flags
     /// Frame has extensions
     pub const HAS_EXTENSIONS: u8 = 1 << 1; // Bit 25 in full flags
-                                           // Bits 26-31 reserved
+    /// Sender hint: latency-critical message
+    pub const HINT_LATENCY_CRITICAL: u8 = 1 << 2; // Bit 26 in full flags
+    /// Sender hint: archival message
+    pub const HINT_ARCHIVAL: u8 = 1 << 3; // Bit 27 in full flags
+    /// Sender hint: payload is already compressed
+    pub const HINT_PRECOMPRESSED: u8 = 1 << 4; // Bit 28 in full flags
+    /// Frame carries one fragment of a larger wire message
+    pub const FRAGMENT: u8 = 1 << 5; // Bit 29 in full flags
+    /// Payload was canonicalized before encoding (RFC 8785)
+    pub const CANONICAL: u8 = 1 << 6; // Bit 30 in full flags
+    /// Frame carries a wire message protected by forward error correction
+    pub const FEC: u8 = 1 << 7; // Bit 31 in full flags
 
     /// Create new empty flags
     pub fn new() -> Self {
@@ -180,6 +192,87 @@ impl CommonFlags {
     pub fn has_extensions(&self) -> bool {
         self.has(Self::HAS_EXTENSIONS)
     }
+
+    /// Check if fragment flag is set
+    pub fn is_fragment(&self) -> bool {
+        self.has(Self::FRAGMENT)
+    }
+
+    /// Check if canonical flag is set
+    pub fn is_canonical(&self) -> bool {
+        self.has(Self::CANONICAL)
+    }
+
+    /// Check if FEC flag is set
+    pub fn is_fec(&self) -> bool {
+        self.has(Self::FEC)
+    }
+
+    /// Get the sender's compression hint, if any
+    pub fn hint(&self) -> Option<CompressionHint> {
+        CompressionHint::ALL
+            .into_iter()
+            .find(|hint| self.has(hint.flag()))
+    }
+}
+
+/// Per-message compression hint set by the sender
+///
+/// A hint overrides the session's negotiated algorithm for one message.
+/// Receivers need not act on it: the frame's own flags say how the payload
+/// is encoded.
+///
+/// | Hint | Payload |
+/// |------|---------|
+/// | `LatencyCritical` | Never Brotli-compressed |
+/// | `Archival` | Always Brotli-compressed, at maximum quality |
+/// | `AlreadyCompressed` | Never Brotli-compressed |
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CompressionHint {
+    /// Deliver as fast as possible; skip expensive compression
+    LatencyCritical,
+    /// Stored long-term; spend CPU for the smallest payload
+    Archival,
+    /// Content is already entropy-coded; compressing again is wasted work
+    AlreadyCompressed,
+}
+
+impl CompressionHint {
+    /// All hints, in flag order
+    pub const ALL: [Self; 3] = [
+        Self::LatencyCritical,
+        Self::Archival,
+        Self::AlreadyCompressed,
+    ];
+
+    /// Common flag bit carrying this hint
+    pub fn flag(self) -> u8 {
+        match self {
+            Self::LatencyCritical => CommonFlags::HINT_LATENCY_CRITICAL,
+            Self::Archival => CommonFlags::HINT_ARCHIVAL,
+            Self::AlreadyCompressed => CommonFlags::HINT_PRECOMPRESSED,
+        }
+    }
+
+    /// Whether the payload should be Brotli-compressed under this hint
+    pub fn compress_payload(self) -> bool {
+        self == Self::Archival
+    }
+
+    /// Display name
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::LatencyCritical => "latency_critical",
+            Self::Archival => "archival",
+            Self::AlreadyCompressed => "already_compressed",
+        }
+    }
+}
+
+impl core::fmt::Display for CompressionHint {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(self.name())
+    }
 }
 
 /// Combined 32-bit flags field
@@ -0,0 +1,226 @@
+//! M2M frame envelope: `#M2M|1|<fixed_header><variable_header><payload_len><crc32><payload>`.
+//!
+//! [`RawFrame::parse`] locates every part of a binary frame without
+//! touching the payload, and [`encode_uncompressed`] writes frames whose
+//! payload is stored as-is. Brotli payloads and security modes are handled
+//! by the `m2m` crate on top of these.
+
+use alloc::format;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+
+use crate::error::{Error, Result};
+use crate::header::{FixedHeader, ResponseHeader, RoutingHeader, Schema, FIXED_HEADER_SIZE};
+
+/// M2M wire format prefix
+pub const M2M_PREFIX: &str = "#M2M|1|";
+
+/// Binary frame split into its parts
+///
+/// Borrows the input; the variable header is left unparsed until
+/// [`headers`](Self::headers) is called.
+#[derive(Debug, Clone)]
+pub struct RawFrame<'a> {
+    /// Fixed header (20 bytes)
+    pub fixed: FixedHeader,
+    /// Variable header bytes (routing or response header, then any trailer)
+    pub variable_header: &'a [u8],
+    /// CRC32 checksum of the original JSON
+    pub checksum: u32,
+    /// Payload bytes as they appear on the wire (possibly Brotli-compressed)
+    pub payload: &'a [u8],
+}
+
+impl<'a> RawFrame<'a> {
+    /// Split a binary frame into headers and payload
+    ///
+    /// Fragments are rejected; they must be reassembled first.
+    pub fn parse(data: &'a [u8]) -> Result<Self> {
+        let truncated = |part: &str| Error::Decompression(format!("Frame too short for {part}"));
+
+        let rest = data
+            .strip_prefix(M2M_PREFIX.as_bytes())
+            .ok_or_else(|| Error::Decompression("Invalid M2M prefix".to_string()))?;
+
+        if rest.len() < FIXED_HEADER_SIZE {
+            return Err(truncated("fixed header"));
+        }
+        let fixed = FixedHeader::from_bytes(&rest[..FIXED_HEADER_SIZE])?;
+        if fixed.flags.common.is_fragment() {
+            return Err(Error::Decompression(
+                "Frame is a fragment; reassemble before decoding".to_string(),
+            ));
+        }
+
+        let header_len = fixed.header_len as usize;
+        if header_len < FIXED_HEADER_SIZE {
+            return Err(Error::Decompression(format!(
+                "Invalid header_len: {} < minimum {}",
+                header_len, FIXED_HEADER_SIZE
+            )));
+        }
+        let variable_header = rest
+            .get(FIXED_HEADER_SIZE..header_len)
+            .ok_or_else(|| truncated("variable header"))?;
+        let rest = &rest[header_len..];
+
+        let payload_len = read_u32(rest, 0).ok_or_else(|| truncated("payload length"))? as usize;
+        let checksum = read_u32(rest, 4).ok_or_else(|| truncated("checksum"))?;
+        let payload = rest
+            .get(8..)
+            .and_then(|rest| rest.get(..payload_len))
+            .ok_or_else(|| truncated("payload"))?;
+
+        Ok(Self {
+            fixed,
+            variable_header,
+            checksum,
+            payload,
+        })
+    }
+
+    /// Check if the payload is Brotli-compressed on the wire
+    pub fn is_compressed(&self) -> bool {
+        self.fixed.flags.is_compressed()
+    }
+
+    /// Parse the routing or response header (see [`read_variable_header`])
+    pub fn headers(&self) -> Result<(Option<RoutingHeader>, Option<ResponseHeader>, &'a [u8])> {
+        read_variable_header(&self.fixed, self.variable_header)
+    }
+
+    /// The JSON payload of an uncompressed frame, checksum verified
+    ///
+    /// Compressed payloads fail; decompress [`payload`](Self::payload) and
+    /// check it with [`verify_checksum`] instead.
+    pub fn json(&self) -> Result<&'a str> {
+        if self.is_compressed() {
+            return Err(Error::Decompression(
+                "Payload is Brotli-compressed; decompress it first".to_string(),
+            ));
+        }
+        let json = core::str::from_utf8(self.payload)
+            .map_err(|e| Error::Decompression(format!("Invalid UTF-8: {}", e)))?;
+        verify_checksum(self.checksum, json.as_bytes())?;
+        Ok(json)
+    }
+}
+
+/// Parse the routing or response header at the start of `header`
+///
+/// Returns the bytes left after it (the trailer, e.g. an extension block)
+/// as the third element.
+pub fn read_variable_header<'a>(
+    fixed: &FixedHeader,
+    header: &'a [u8],
+) -> Result<(Option<RoutingHeader>, Option<ResponseHeader>, &'a [u8])> {
+    let (routing, response, consumed) = match fixed.schema {
+        Schema::Request | Schema::EmbeddingRequest => {
+            let request_flags = fixed.flags.request_flags();
+            let (routing, consumed) = RoutingHeader::from_bytes(header, &request_flags)?;
+            (Some(routing), None, consumed)
+        },
+        Schema::Response | Schema::EmbeddingResponse | Schema::Error => {
+            let response_flags = fixed.flags.response_flags();
+            let (response, consumed) = ResponseHeader::from_bytes(header, &response_flags)?;
+            (None, Some(response), consumed)
+        },
+        _ => (None, None, 0),
+    };
+    Ok((routing, response, &header[consumed.min(header.len())..]))
+}
+
+/// Check a decoded payload against the frame checksum
+pub fn verify_checksum(expected: u32, payload: &[u8]) -> Result<()> {
+    let computed = crc32fast::hash(payload);
+    if computed != expected {
+        return Err(Error::Decompression(format!(
+            "Checksum mismatch: expected {:08x}, got {:08x}",
+            expected, computed
+        )));
+    }
+    Ok(())
+}
+
+/// Encode a frame that stores `payload` uncompressed
+///
+/// `header_len` is filled in from `variable_header`; `fixed` must not be
+/// flagged as compressed.
+pub fn encode_uncompressed(
+    mut fixed: FixedHeader,
+    variable_header: &[u8],
+    payload: &[u8],
+) -> Result<Vec<u8>> {
+    if fixed.flags.is_compressed() {
+        return Err(Error::Compression(
+            "Uncompressed frame flagged as compressed".to_string(),
+        ));
+    }
+    let header_len = u16::try_from(FIXED_HEADER_SIZE + variable_header.len())
+        .map_err(|_| Error::Compression("Variable header too long".to_string()))?;
+    let payload_len = u32::try_from(payload.len())
+        .map_err(|_| Error::Compression("Payload too long".to_string()))?;
+    fixed.header_len = header_len;
+
+    let mut buf = Vec::with_capacity(M2M_PREFIX.len() + header_len as usize + 8 + payload.len());
+    buf.extend_from_slice(M2M_PREFIX.as_bytes());
+    buf.extend_from_slice(&fixed.to_bytes());
+    buf.extend_from_slice(variable_header);
+    buf.extend_from_slice(&payload_len.to_le_bytes());
+    buf.extend_from_slice(&crc32fast::hash(payload).to_le_bytes());
+    buf.extend_from_slice(payload);
+    Ok(buf)
+}
+
+/// Little-endian `u32` at `offset`
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    let bytes = data.get(offset..offset + 4)?;
+    Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flags::{CommonFlags, Flags};
+    use crate::header::{detect_request_flags, SecurityMode};
+
+    const REQUEST: &str = r#"{"model":"gpt-4o","messages":[{"role":"user","content":"Hi"}]}"#;
+
+    fn request_frame() -> Vec<u8> {
+        let value: serde_json::Value = serde_json::from_str(REQUEST).unwrap();
+        let flags = detect_request_flags(&value);
+        let routing = RoutingHeader::from_json(&value, &flags).unwrap();
+        let fixed = FixedHeader::new(
+            Schema::Request,
+            SecurityMode::None,
+            Flags::for_request(flags, CommonFlags::new()),
+        );
+        encode_uncompressed(fixed, &routing.to_bytes(&flags), REQUEST.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn test_uncompressed_roundtrip() {
+        let wire = request_frame();
+        let frame = RawFrame::parse(&wire).unwrap();
+        assert!(!frame.is_compressed());
+        assert_eq!(frame.json().unwrap(), REQUEST);
+
+        let (routing, response, trailer) = frame.headers().unwrap();
+        assert_eq!(routing.unwrap().model, "gpt-4o");
+        assert!(response.is_none());
+        assert!(trailer.is_empty());
+    }
+
+    #[test]
+    fn test_rejects_damaged_frames() {
+        let wire = request_frame();
+        for len in [0, M2M_PREFIX.len() + 4, wire.len() - 1] {
+            assert!(RawFrame::parse(&wire[..len]).is_err(), "len {len}");
+        }
+
+        let mut corrupted = wire.clone();
+        *corrupted.last_mut().unwrap() ^= 0x01;
+        let frame = RawFrame::parse(&corrupted).unwrap();
+        assert!(matches!(frame.json(), Err(Error::Decompression(_))));
+    }
+}
@@ -4,9 +4,17 @@
 
 #![allow(missing_docs)]
 
-use super::flags::{Flags, RequestFlags, ResponseFlags};
-use super::varint::{read_varint_slice, varint_size, write_varint_vec};
-use crate::error::{M2MError, Result};
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+use crate::flags::{Flags, RequestFlags, ResponseFlags};
+use crate::media::MediaStats;
+use crate::varint::{read_varint_slice, varint_size, write_varint_vec};
 
 /// Fixed header size in bytes
 pub const FIXED_HEADER_SIZE: usize = 20;
@@ -67,7 +75,8 @@ impl Schema {
 }
 
 /// Security mode
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 #[repr(u8)]
 pub enum SecurityMode {
     /// No security (default)
@@ -77,6 +86,10 @@ pub enum SecurityMode {
     Hmac = 0x01,
     /// AEAD encryption (confidentiality + integrity)
     Aead = 0x02,
+    /// AEAD encryption of `content` values only, the rest of the payload
+    /// readable and HMAC-authenticated
+    #[serde(rename = "field_aead")]
+    FieldAead = 0x03,
 }
 
 impl SecurityMode {
@@ -84,6 +97,7 @@ impl SecurityMode {
         match b {
             0x01 => SecurityMode::Hmac,
             0x02 => SecurityMode::Aead,
+            0x03 => SecurityMode::FieldAead,
             _ => SecurityMode::None,
         }
     }
@@ -167,6 +181,7 @@ impl Role {
         }
     }
 
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Option<Self> {
         match s {
             "system" | "developer" => Some(Role::System),
@@ -193,7 +208,7 @@ pub struct FixedHeader {
     pub security: SecurityMode,
     /// Flags (32 bits)
     pub flags: Flags,
-    /// Reserved for future use (12 bytes, zeroed)
+    /// Reserved (12 bytes, zeroed; fragment info for FRAGMENT frames)
     pub reserved: [u8; RESERVED_SIZE],
 }
 
@@ -215,14 +230,14 @@ impl FixedHeader {
         bytes[2] = self.schema.as_byte();
         bytes[3] = self.security.as_byte();
         bytes[4..8].copy_from_slice(&self.flags.to_bytes());
-        // bytes[8..20] remain zero (reserved)
+        bytes[8..20].copy_from_slice(&self.reserved);
         bytes
     }
 
     /// Decode from bytes
     pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
         if bytes.len() < FIXED_HEADER_SIZE {
-            return Err(M2MError::Decompression(format!(
+            return Err(Error::Decompression(format!(
                 "Fixed header too short: {} < {}",
                 bytes.len(),
                 FIXED_HEADER_SIZE
@@ -262,6 +277,8 @@ pub struct RoutingHeader {
     pub max_tokens: Option<u32>,
     /// Estimated cost in USD (IEEE 754 float)
     pub est_cost_usd: Option<f32>,
+    /// Image statistics (if `HAS_MEDIA_STATS` flag set)
+    pub media: Option<MediaStats>,
 }
 
 impl RoutingHeader {
@@ -273,6 +290,7 @@ impl RoutingHeader {
             content_hint: 0,
             max_tokens: None,
             est_cost_usd: None,
+            media: None,
         }
     }
 
@@ -315,6 +333,10 @@ impl RoutingHeader {
             }
         }
 
+        let media = request_flags
+            .has(RequestFlags::HAS_MEDIA_STATS)
+            .then(|| MediaStats::from_json(json));
+
         let max_tokens = if request_flags.has(RequestFlags::HAS_MAX_TOKENS) {
             json.get("max_tokens")
                 .or_else(|| json.get("max_completion_tokens"))
@@ -331,6 +353,7 @@ impl RoutingHeader {
             content_hint,
             max_tokens,
             est_cost_usd: None, // Calculated separately
+            media,
         })
     }
 
@@ -360,11 +383,21 @@ impl RoutingHeader {
             }
         }
 
+        // Media stats follow the cost, so the cost is written whenever they are
+        let media = self
+            .media
+            .filter(|_| request_flags.has(RequestFlags::HAS_MEDIA_STATS));
+
         // Estimated cost (if present)
-        if let Some(cost) = self.est_cost_usd {
+        if let Some(cost) = self.est_cost_usd.or(media.map(|_| 0.0)) {
             buf.extend_from_slice(&cost.to_le_bytes());
         }
 
+        // Media stats (if flag set)
+        if let Some(media) = media {
+            media.to_bytes(&mut buf);
+        }
+
         buf
     }
 
@@ -374,16 +407,16 @@ impl RoutingHeader {
 
         // Model
         if pos >= data.len() {
-            return Err(M2MError::Decompression("Missing model length".to_string()));
+            return Err(Error::Decompression("Missing model length".to_string()));
         }
         let model_len = data[pos] as usize;
         pos += 1;
 
         if pos + model_len > data.len() {
-            return Err(M2MError::Decompression("Model truncated".to_string()));
+            return Err(Error::Decompression("Model truncated".to_string()));
         }
         let model = String::from_utf8(data[pos..pos + model_len].to_vec())
-            .map_err(|e| M2MError::Decompression(format!("Invalid model UTF-8: {}", e)))?;
+            .map_err(|e| Error::Decompression(format!("Invalid model UTF-8: {}", e)))?;
         pos += model_len;
 
         // Message count
@@ -394,7 +427,7 @@ impl RoutingHeader {
         // Roles
         let roles_byte_count = (msg_count as usize * 2 + 7) / 8;
         if pos + roles_byte_count > data.len() {
-            return Err(M2MError::Decompression("Roles truncated".to_string()));
+            return Err(Error::Decompression("Roles truncated".to_string()));
         }
         let roles = unpack_roles(&data[pos..pos + roles_byte_count], msg_count as usize);
         pos += roles_byte_count;
@@ -422,6 +455,18 @@ impl RoutingHeader {
             None
         };
 
+        // Media stats (if flag set)
+        let media = if request_flags.has(RequestFlags::HAS_MEDIA_STATS) {
+            if est_cost_usd.is_none() {
+                return Err(Error::Decompression("Media stats truncated".to_string()));
+            }
+            let (media, consumed) = MediaStats::from_bytes(&data[pos..])?;
+            pos += consumed;
+            Some(media)
+        } else {
+            None
+        };
+
         Ok((
             Self {
                 model,
@@ -430,6 +475,7 @@ impl RoutingHeader {
                 content_hint,
                 max_tokens,
                 est_cost_usd,
+                media,
             },
             pos,
         ))
@@ -449,10 +495,18 @@ impl RoutingHeader {
             }
         }
 
-        if self.est_cost_usd.is_some() {
+        let media = self
+            .media
+            .filter(|_| request_flags.has(RequestFlags::HAS_MEDIA_STATS));
+
+        if self.est_cost_usd.is_some() || media.is_some() {
             size += 4;
         }
 
+        if let Some(media) = media {
+            size += media.encoded_size();
+        }
+
         size
     }
 }
@@ -593,33 +647,33 @@ impl ResponseHeader {
 
         // ID
         if pos >= data.len() {
-            return Err(M2MError::Decompression("Missing ID length".to_string()));
+            return Err(Error::Decompression("Missing ID length".to_string()));
         }
         let id_len = data[pos] as usize;
         pos += 1;
         if pos + id_len > data.len() {
-            return Err(M2MError::Decompression("ID truncated".to_string()));
+            return Err(Error::Decompression("ID truncated".to_string()));
         }
         let id = String::from_utf8(data[pos..pos + id_len].to_vec())
-            .map_err(|e| M2MError::Decompression(format!("Invalid ID UTF-8: {}", e)))?;
+            .map_err(|e| Error::Decompression(format!("Invalid ID UTF-8: {}", e)))?;
         pos += id_len;
 
         // Model
         if pos >= data.len() {
-            return Err(M2MError::Decompression("Missing model length".to_string()));
+            return Err(Error::Decompression("Missing model length".to_string()));
         }
         let model_len = data[pos] as usize;
         pos += 1;
         if pos + model_len > data.len() {
-            return Err(M2MError::Decompression("Model truncated".to_string()));
+            return Err(Error::Decompression("Model truncated".to_string()));
         }
         let model = String::from_utf8(data[pos..pos + model_len].to_vec())
-            .map_err(|e| M2MError::Decompression(format!("Invalid model UTF-8: {}", e)))?;
+            .map_err(|e| Error::Decompression(format!("Invalid model UTF-8: {}", e)))?;
         pos += model_len;
 
         // Finish reason
         if pos >= data.len() {
-            return Err(M2MError::Decompression("Missing finish reason".to_string()));
+            return Err(Error::Decompression("Missing finish reason".to_string()));
         }
         let finish_reason = FinishReason::from_byte(data[pos]);
         pos += 1;
@@ -722,7 +776,10 @@ pub fn detect_request_flags(json: &serde_json::Value) -> RequestFlags {
             // Check for images in content
             if let Some(content) = msg.get("content").and_then(|v| v.as_array()) {
                 for part in content {
-                    if part.get("type").and_then(|v| v.as_str()) == Some("image_url") {
+                    if matches!(
+                        part.get("type").and_then(|v| v.as_str()),
+                        Some("image_url" | "input_image" | "image")
+                    ) {
                         flags.set(RequestFlags::HAS_IMAGES);
                     }
                 }
@@ -841,8 +898,8 @@ pub fn detect_response_flags(json: &serde_json::Value) -> ResponseFlags {
 
 #[cfg(test)]
 mod tests {
-    use super::super::flags::CommonFlags;
     use super::*;
+    use crate::flags::CommonFlags;
 
     #[test]
     fn test_fixed_header_roundtrip() {
@@ -0,0 +1,54 @@
+//! # M2M Protocol core
+//!
+//! The `no_std + alloc` part of the M2M Protocol, for constrained edge
+//! devices that need to read and write M2M frames without an async runtime,
+//! HTTP stack or tokenizer:
+//!
+//! - [`frame`]: `#M2M|1|` envelope parsing and encoding
+//! - [`header`], [`flags`], [`media`]: fixed, routing and response headers
+//! - [`varint`]: LEB128 integers
+//! - [`token`], [`dictionary`]: the legacy Token (`#T1|`) and Dictionary
+//!   (`#M2M|`) codecs, with their [`tables`]
+//!
+//! The `m2m` crate re-exports all of it at its usual paths and adds what
+//! needs std: Brotli payloads, frame security, sessions, transports and
+//! the server. Frames whose payload is Brotli-compressed parse here, but
+//! the payload has to be decompressed by the caller (see
+//! [`RawFrame::json`](frame::RawFrame::json)).
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use m2m_core::flags::{CommonFlags, Flags};
+//! use m2m_core::frame::{encode_uncompressed, RawFrame};
+//! use m2m_core::header::{detect_request_flags, FixedHeader, RoutingHeader, Schema, SecurityMode};
+//!
+//! let value: serde_json::Value = serde_json::from_str(json)?;
+//! let flags = detect_request_flags(&value);
+//! let routing = RoutingHeader::from_json(&value, &flags)?;
+//! let fixed = FixedHeader::new(
+//!     Schema::Request,
+//!     SecurityMode::None,
+//!     Flags::for_request(flags, CommonFlags::new()),
+//! );
+//! let wire = encode_uncompressed(fixed, &routing.to_bytes(&flags), json.as_bytes())?;
+//!
+//! let frame = RawFrame::parse(&wire)?;
+//! assert_eq!(frame.json()?, json);
+//! ```
+
+#![cfg_attr(not(test), no_std)]
+
+extern crate alloc;
+
+pub mod dictionary;
+pub mod error;
+pub mod flags;
+pub mod frame;
+pub mod header;
+pub mod media;
+pub mod tables;
+pub mod token;
+pub mod varint;
+
+pub use error::{Error, Result};
@@ -0,0 +1,209 @@
+//! Multimodal content detection.
+//!
+//! Chat payloads may carry images as inline base64 content parts. That data
+//! is already entropy-coded (PNG, JPEG, WebP), so text compression spends
+//! CPU on it for almost no gain, and byte-based heuristics mistake it for
+//! large text. [`MediaStats`] measures it so the codec can route around it.
+//!
+//! # Recognized Parts
+//!
+//! | API | Part | Inline when |
+//! |-----|------|-------------|
+//! | OpenAI Chat | `{"type":"image_url","image_url":{"url":...}}` | `url` is a `data:` URI |
+//! | OpenAI Responses | `{"type":"input_image","image_url":...}` | `image_url` is a `data:` URI |
+//! | Anthropic | `{"type":"image","source":{"type":"base64","data":...}}` | Always |
+//!
+//! Parts nested in a part's own `content` array (Anthropic `tool_result`)
+//! are counted too.
+
+use alloc::vec::Vec;
+
+use serde_json::Value;
+
+use crate::error::Result;
+use crate::varint::{read_varint_slice, varint_size, write_varint_vec};
+
+/// Share of the payload that inline media must reach to skip Brotli
+pub const MEDIA_HEAVY_RATIO: f64 = 0.5;
+
+/// Image statistics for a request
+///
+/// Carried in the routing header when `RequestFlags::HAS_MEDIA_STATS` is set,
+/// so routers can see image load without reading the payload.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MediaStats {
+    /// Image parts (inline and by URL)
+    pub images: u32,
+    /// Image parts carried inline as base64
+    pub inline_images: u32,
+    /// Decoded size of inline images in bytes
+    pub inline_bytes: u64,
+}
+
+impl MediaStats {
+    /// Scan request messages for image parts
+    pub fn from_json(json: &Value) -> Self {
+        let mut stats = Self::default();
+        let messages = json
+            .get("messages")
+            .or_else(|| json.get("input"))
+            .and_then(Value::as_array);
+
+        for msg in messages.into_iter().flatten() {
+            if let Some(parts) = msg.get("content").and_then(Value::as_array) {
+                stats.scan(parts);
+            }
+        }
+        stats
+    }
+
+    /// Check if any image parts were found
+    pub fn has_images(&self) -> bool {
+        self.images > 0
+    }
+
+    /// Base64 characters the inline images occupy in the payload
+    pub fn inline_encoded_len(&self) -> u64 {
+        self.inline_bytes.div_ceil(3) * 4
+    }
+
+    /// Check if inline media makes up most of a payload of `payload_len` bytes
+    pub fn is_media_heavy(&self, payload_len: usize) -> bool {
+        payload_len > 0
+            && self.inline_encoded_len() as f64 / payload_len as f64 >= MEDIA_HEAVY_RATIO
+    }
+
+    /// Count image parts, descending into nested `content` arrays
+    fn scan(&mut self, parts: &[Value]) {
+        for part in parts {
+            match part.get("type").and_then(Value::as_str) {
+                Some("image_url") => {
+                    let url = part
+                        .get("image_url")
+                        .and_then(|v| v.get("url").or(Some(v)))
+                        .and_then(Value::as_str);
+                    self.record(url.and_then(data_uri_payload));
+                },
+                Some("input_image") => {
+                    let url = part.get("image_url").and_then(Value::as_str);
+                    self.record(url.and_then(data_uri_payload));
+                },
+                Some("image") => {
+                    let source = part.get("source");
+                    let data = source
+                        .filter(|s| s.get("type").and_then(Value::as_str) == Some("base64"))
+                        .and_then(|s| s.get("data"))
+                        .and_then(Value::as_str);
+                    self.record(data);
+                },
+                _ => {},
+            }
+
+            if let Some(nested) = part.get("content").and_then(Value::as_array) {
+                self.scan(nested);
+            }
+        }
+    }
+
+    /// Record one image part and its inline base64 data, if any
+    fn record(&mut self, inline: Option<&str>) {
+        self.images += 1;
+        if let Some(data) = inline {
+            self.inline_images += 1;
+            self.inline_bytes += base64_decoded_len(data);
+        }
+    }
+
+    /// Encode to bytes
+    pub fn to_bytes(&self, buf: &mut Vec<u8>) {
+        write_varint_vec(buf, self.images as u64);
+        write_varint_vec(buf, self.inline_images as u64);
+        write_varint_vec(buf, self.inline_bytes);
+    }
+
+    /// Decode from bytes, returning the stats and bytes consumed
+    pub fn from_bytes(data: &[u8]) -> Result<(Self, usize)> {
+        let (images, mut pos) = read_varint_slice(data)?;
+        let (inline_images, consumed) = read_varint_slice(&data[pos..])?;
+        pos += consumed;
+        let (inline_bytes, consumed) = read_varint_slice(&data[pos..])?;
+        pos += consumed;
+
+        Ok((
+            Self {
+                images: images as u32,
+                inline_images: inline_images as u32,
+                inline_bytes,
+            },
+            pos,
+        ))
+    }
+
+    /// Calculate the encoded size
+    pub fn encoded_size(&self) -> usize {
+        varint_size(self.images as u64)
+            + varint_size(self.inline_images as u64)
+            + varint_size(self.inline_bytes)
+    }
+}
+
+/// Base64 data of a `data:<mime>;base64,<data>` URI
+fn data_uri_payload(url: &str) -> Option<&str> {
+    let rest = url.strip_prefix("data:")?;
+    let (meta, data) = rest.split_once(',')?;
+    meta.ends_with(";base64").then_some(data)
+}
+
+/// Decoded length of a base64 string (padding-aware)
+fn base64_decoded_len(data: &str) -> u64 {
+    let padding = data.bytes().rev().take_while(|&b| b == b'=').count();
+    ((data.len() / 4 * 3).saturating_sub(padding) + data.len() % 4 * 3 / 4) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_media_stats_detection() {
+        let json = serde_json::json!({
+            "model": "gpt-4o",
+            "messages": [
+                {"role": "user", "content": [
+                    {"type": "text", "text": "What is in these images?"},
+                    {"type": "image_url", "image_url": {"url": "data:image/png;base64,iVBORw0KGgo="}},
+                    {"type": "image_url", "image_url": {"url": "https://example.com/cat.png"}},
+                    {"type": "image", "source": {"type": "base64", "media_type": "image/jpeg", "data": "/9j/4AAQ"}}
+                ]},
+                {"role": "user", "content": [
+                    {"type": "tool_result", "content": [
+                        {"type": "image", "source": {"type": "base64", "media_type": "image/png", "data": "AAAA"}}
+                    ]}
+                ]}
+            ]
+        });
+
+        let stats = MediaStats::from_json(&json);
+        assert_eq!(stats.images, 4);
+        assert_eq!(stats.inline_images, 3);
+        assert_eq!(stats.inline_bytes, 8 + 6 + 3);
+
+        let mut buf = Vec::new();
+        stats.to_bytes(&mut buf);
+        assert_eq!(buf.len(), stats.encoded_size());
+        assert_eq!(MediaStats::from_bytes(&buf).unwrap(), (stats, buf.len()));
+    }
+
+    #[test]
+    fn test_media_heavy() {
+        let stats = MediaStats {
+            images: 1,
+            inline_images: 1,
+            inline_bytes: 3000,
+        };
+        assert_eq!(stats.inline_encoded_len(), 4000);
+        assert!(stats.is_media_heavy(5000));
+        assert!(!stats.is_media_heavy(10_000));
+        assert!(!MediaStats::default().is_media_heavy(0));
+    }
+}
@@ -24,13 +24,16 @@
 //!
 //! 3. **Default Removal** (low ROI): Removes common default values.
 
+use alloc::format;
+use alloc::string::{String, ToString};
+
 use serde_json::{Map, Value};
 
-use super::tables::{
+use crate::error::Result;
+use crate::tables::{
     is_default_value, KEY_ABBREV, KEY_EXPAND, MODEL_ABBREV, MODEL_EXPAND, PATTERN_ABBREV,
     PATTERN_EXPAND, ROLE_ABBREV, ROLE_EXPAND,
 };
-use crate::error::Result;
 
 /// Wire format prefix for token codec
 pub const TOKEN_PREFIX: &str = "#T1|";
@@ -541,7 +544,7 @@ mod tests {
         let codec = TokenCodec::new();
 
         // Test each pattern individually
-        for (pattern, abbrev) in super::super::tables::PATTERN_ABBREV {
+        for (pattern, abbrev) in crate::tables::PATTERN_ABBREV {
             let compressed = codec.apply_pattern_compression(pattern);
             assert_eq!(
                 compressed, *abbrev,
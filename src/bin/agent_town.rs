@@ -8,6 +8,7 @@
 //!
 //! - **Small-world network topology**: Realistic social graph (Watts-Strogatz model)
 //! - **Agent personas**: Analysts, Skeptics, Propagandists, Conspiracists, etc.
+//! - **Scenario files**: Personas, seed events and round settings from YAML or JSON
 //! - **Belief tracking**: How information spreads and beliefs change
 //! - **M2M encryption**: All agent communication is encrypted via ChaCha20-Poly1305
 //! - **Model pool**: Cost-efficient use of free and paid LLM models
@@ -16,16 +17,24 @@
 //!
 //! ```bash
 //! # Basic run (20 agents, 50 rounds, mostly free models)
-//! OPENROUTER_API_KEY=sk-or-... cargo run --bin agent-town --features crypto
+//! OPENROUTER_API_KEY=sk-or-... cargo run --bin agent-town --features agent-town
 //!
 //! # Custom configuration
-//! cargo run --bin agent-town --features crypto -- \
+//! cargo run --bin agent-town --features agent-town -- \
 //!   --agents 50 \
 //!   --rounds 100 \
 //!   --verbose
 //!
 //! # Free models only (no cost)
-//! cargo run --bin agent-town --features crypto -- --free-only
+//! cargo run --bin agent-town --features agent-town -- --free-only
+//!
+//! # Custom experiment: start from the built-in scenario and edit it
+//! cargo run --bin agent-town --features agent-town -- --dump-scenario > my-scenario.yaml
+//! cargo run --bin agent-town --features agent-town -- --scenario my-scenario.yaml
+//!
+//! # Long run: checkpoint every 25 rounds, then continue after a crash
+//! cargo run --bin agent-town --features agent-town -- --rounds 500 --checkpoint-every 25
+//! cargo run --bin agent-town --features agent-town -- --rounds 500 --resume agent-town-checkpoint.json
 //! ```
 
 use std::collections::HashMap;
@@ -40,6 +49,7 @@ use petgraph::graph::{NodeIndex, UnGraph};
 use petgraph::visit::EdgeRef;
 use rand::prelude::*;
 use rand::seq::SliceRandom;
+use rand_chacha::ChaCha12Rng;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use tokio::time::sleep;
@@ -272,7 +282,7 @@ impl ParsedM2MFrame {
 }
 
 /// Protocol telemetry for the simulation
-#[derive(Debug, Default, Clone, Serialize)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct ProtocolTelemetry {
     /// Number of X25519 key exchanges performed
     pub key_exchanges: usize,
@@ -367,11 +377,10 @@ impl ProtocolTelemetry {
         ));
         out.push_str(&format!(
             "║  Avg frame size:       {:>6}B     │  Ciphertext: {:>10}       ║\n",
-            if self.encryptions > 0 {
-                metrics.total_encrypted_bytes / self.encryptions
-            } else {
-                0
-            },
+            metrics
+                .total_encrypted_bytes
+                .checked_div(self.encryptions)
+                .unwrap_or(0),
             format_bytes(metrics.total_encrypted_bytes)
         ));
         out.push_str(&format!(
@@ -413,7 +422,7 @@ fn format_bytes(bytes: usize) -> String {
 }
 
 /// Conversation thread tracking
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 struct ConversationThread {
     /// Topic this thread is about
     topic_id: String,
@@ -430,7 +439,7 @@ struct ConversationThread {
     belief_states: HashMap<usize, String>, // agent_id -> "Accepts"/"Rejects"/"Investigating"
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 struct ThreadMessage {
     round: usize,
     sender_id: usize,
@@ -546,6 +555,12 @@ pub enum SimulationError {
     /// Invalid confidence value (not in [0.0, 1.0])
     InvalidConfidence(f64),
 
+    /// Scenario file could not be parsed or is inconsistent
+    InvalidScenario(String),
+
+    /// Checkpoint file is inconsistent or from another version
+    InvalidCheckpoint(String),
+
     // ═══════════════════════════════════════════════════════════════════════
     // I^B MATERIALIZED — Bounded ignorance became known-bad
     // ═══════════════════════════════════════════════════════════════════════
@@ -610,6 +625,8 @@ impl std::fmt::Display for SimulationError {
             Self::InvalidConfidence(v) => {
                 write!(f, "Confidence {} not in valid range [0.0, 1.0]", v)
             },
+            Self::InvalidScenario(msg) => write!(f, "Invalid scenario: {}", msg),
+            Self::InvalidCheckpoint(msg) => write!(f, "Invalid checkpoint: {}", msg),
 
             // I^B materialized
             Self::RateLimited {
@@ -750,7 +767,7 @@ struct Args {
     #[arg(long, default_value = "20")]
     agents: usize,
 
-    /// Number of simulation rounds
+    /// Number of simulation rounds (total, including rounds of a resumed run)
     #[arg(long, default_value = "50")]
     rounds: usize,
 
@@ -766,14 +783,22 @@ struct Args {
     #[arg(long, default_value = "0.1")]
     rewire_prob: f64,
 
-    /// Number of misinformation seeds to inject
+    /// Number of misinformation seeds to inject (built-in scenario only)
     #[arg(long, default_value = "1")]
     seed_misinfo: usize,
 
-    /// Number of conspiracy theory seeds to inject
+    /// Number of conspiracy theory seeds to inject (built-in scenario only)
     #[arg(long, default_value = "1")]
     seed_conspiracy: usize,
 
+    /// Scenario file (YAML or JSON) defining personas, seeds and rounds
+    #[arg(long, value_name = "FILE")]
+    scenario: Option<String>,
+
+    /// Print the built-in scenario as YAML and exit
+    #[arg(long)]
+    dump_scenario: bool,
+
     /// Use only free-tier models (no API cost)
     #[arg(long)]
     free_only: bool,
@@ -833,6 +858,22 @@ struct Args {
     /// Export network graph in DOT format
     #[arg(long)]
     export_graph: Option<String>,
+
+    /// Write a checkpoint every N rounds (0 = never)
+    #[arg(long, default_value = "0", value_name = "N")]
+    checkpoint_every: usize,
+
+    /// Checkpoint file written by --checkpoint-every
+    #[arg(
+        long,
+        default_value = "agent-town-checkpoint.json",
+        value_name = "FILE"
+    )]
+    checkpoint_file: String,
+
+    /// Resume from a checkpoint (agents, network and scenario come from the file)
+    #[arg(long, value_name = "FILE", conflicts_with = "scenario")]
+    resume: Option<String>,
 }
 
 /// Output visualization modes
@@ -986,9 +1027,11 @@ impl CircuitBreaker {
 // =============================================================================
 
 /// Model tier for cost management
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 enum ModelTier {
     /// Free tier - rate limited but $0
+    #[default]
     Free,
     /// Cheap tier - ~$0.02-0.05 per million tokens
     Cheap,
@@ -1173,122 +1216,391 @@ impl ModelPool {
 }
 
 // =============================================================================
-// Agent Personas
+// Scenarios
 // =============================================================================
 
-/// Agent persona types
+/// Which side of the information contest a persona is on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Alignment {
+    /// Seeks the truth (shown green)
+    Truthful,
+    /// Goes along with others (shown blue)
+    #[default]
+    Neutral,
+    /// Spreads misinformation or confusion (shown red)
+    Adversarial,
+}
+
+/// Kinds of seed events, used to pick the agent that receives a seed
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
-enum Persona {
-    // Truth-seeking agents (60%)
-    Analyst,
-    Skeptic,
-    Educator,
+#[serde(rename_all = "snake_case")]
+enum SeedKind {
+    Fact,
+    Misinfo,
+    Conspiracy,
+    Propaganda,
+}
+
+/// An agent persona defined by the scenario
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Persona {
+    /// Display name, also used to target seeds
+    name: String,
+    /// System prompt describing the persona
+    prompt: String,
+    /// Grouping for colors and summaries
+    #[serde(default)]
+    alignment: Alignment,
+    /// Model tier to request
+    #[serde(default)]
+    tier: ModelTier,
+    /// Relative chance of starting a conversation in a round
+    engagement: f64,
+    /// Probability of taking up a neighbour's belief (0.0 - 1.0)
+    receptivity: f64,
+    /// Relative share of the population
+    share: f64,
+    /// Seed kinds first delivered to an agent with this persona
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    receives: Vec<SeedKind>,
+}
+
+impl std::fmt::Display for Persona {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
 
-    // Neutral agents (30%)
-    Curious,
-    Follower,
-    Lurker,
+/// A seed event and where and when it is injected
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Seed {
+    /// The claim to inject
+    event: SeedEvent,
+    /// Round at whose start the seed is injected (0 = before the first round)
+    #[serde(default)]
+    round: usize,
+    /// Persona to receive the seed (default: first persona that `receives` its kind)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    target: Option<String>,
+}
 
-    // Adversarial agents (10%)
-    Propagandist,
-    Conspiracist,
-    Troll,
+/// How each round plays out
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RoundSettings {
+    /// Conversations per round
+    interactions: usize,
+    /// System prompt template; `{persona}` and `{context}` are replaced
+    prompt: String,
+    /// First user message of every conversation
+    opener: String,
+    /// Context for agents that hold no beliefs yet
+    idle_context: String,
+    /// Maximum tokens per generated message
+    max_tokens: u32,
 }
 
-impl Persona {
-    fn system_prompt(&self) -> &'static str {
-        match self {
-            Persona::Analyst => {
+impl Default for RoundSettings {
+    fn default() -> Self {
+        Self {
+            interactions: 1,
+            prompt: "{persona}\n\nYou're chatting with a friend. {context}\n\n\
+                     Write a short message (1-2 sentences)."
+                .to_string(),
+            opener: "What's on your mind?".to_string(),
+            idle_context: "You don't have any specific topics on your mind.".to_string(),
+            max_tokens: 100,
+        }
+    }
+}
+
+/// An experiment: personas, seed events and round settings
+///
+/// Loaded from YAML (`.yaml`/`.yml`) or JSON with `--scenario`. Without
+/// one, [`Scenario::builtin`] runs the cognitive-warfare experiment; print
+/// it with `--dump-scenario` as a starting point for new experiments.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Scenario {
+    name: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    description: String,
+    personas: Vec<Persona>,
+    #[serde(default)]
+    seeds: Vec<Seed>,
+    #[serde(default)]
+    rounds: RoundSettings,
+}
+
+impl Scenario {
+    /// The built-in cognitive-warfare scenario
+    fn builtin(misinfo_count: usize, conspiracy_count: usize) -> Self {
+        use Alignment::{Adversarial, Neutral, Truthful};
+        use ModelTier::{Cheap, Free};
+
+        let persona = |name: &str,
+                       alignment,
+                       tier,
+                       engagement,
+                       receptivity,
+                       share,
+                       receives: &[SeedKind],
+                       prompt: &str| Persona {
+            name: name.to_string(),
+            prompt: prompt.to_string(),
+            alignment,
+            tier,
+            engagement,
+            receptivity,
+            share,
+            receives: receives.to_vec(),
+        };
+
+        // 60% truth-seeking, 30% neutral, 10% adversarial
+        let personas = vec![
+            persona(
+                "Analyst",
+                Truthful,
+                Cheap,
+                0.7,
+                0.0,
+                0.2,
+                &[SeedKind::Fact],
                 "You are a careful fact-checker. You verify claims with evidence, \
                  cite sources when possible, and politely correct misinformation. \
-                 You're skeptical of extraordinary claims without extraordinary evidence."
-            },
-            Persona::Skeptic => {
+                 You're skeptical of extraordinary claims without extraordinary evidence.",
+            ),
+            persona(
+                "Skeptic",
+                Truthful,
+                Free,
+                0.5,
+                0.0,
+                0.2,
+                &[],
                 "You question everything and demand evidence for claims. \
                  You're not cynical, but you don't accept things at face value. \
-                 You ask probing questions to get to the truth."
-            },
-            Persona::Educator => {
+                 You ask probing questions to get to the truth.",
+            ),
+            persona(
+                "Educator",
+                Truthful,
+                Free,
+                0.7,
+                0.0,
+                0.2,
+                &[SeedKind::Fact],
                 "You explain complex topics in simple terms. \
                  You help others understand by providing context and background. \
-                 You're patient and encouraging."
-            },
-            Persona::Curious => {
+                 You're patient and encouraging.",
+            ),
+            persona(
+                "Curious",
+                Neutral,
+                Free,
+                0.7,
+                1.0,
+                0.1,
+                &[],
                 "You're genuinely curious and open-minded. \
                  You ask questions to learn more and consider different perspectives. \
-                 You're willing to change your mind with new information."
-            },
-            Persona::Follower => {
+                 You're willing to change your mind with new information.",
+            ),
+            persona(
+                "Follower",
+                Neutral,
+                Free,
+                0.5,
+                1.0,
+                0.1,
+                &[],
                 "You tend to agree with what most people around you believe. \
                  You value social harmony and don't like to rock the boat. \
-                 You're influenced by popular opinion."
-            },
-            Persona::Lurker => {
+                 You're influenced by popular opinion.",
+            ),
+            persona(
+                "Lurker",
+                Neutral,
+                Free,
+                0.1,
+                0.0,
+                0.1,
+                &[],
                 "You mostly observe conversations without participating much. \
                  When you do speak, you keep it brief. \
-                 You're cautious about sharing opinions."
-            },
-            Persona::Propagandist => {
+                 You're cautious about sharing opinions.",
+            ),
+            persona(
+                "Propagandist",
+                Adversarial,
+                Cheap,
+                0.9,
+                0.3,
+                0.1 / 3.0,
+                &[SeedKind::Misinfo, SeedKind::Propaganda],
                 "You have strong beliefs and want to convince others. \
                  You use emotional appeals and repeat key messages. \
-                 You frame everything to support your narrative."
-            },
-            Persona::Conspiracist => {
+                 You frame everything to support your narrative.",
+            ),
+            persona(
+                "Conspiracist",
+                Adversarial,
+                Cheap,
+                0.6,
+                0.3,
+                0.1 / 3.0,
+                &[SeedKind::Conspiracy],
                 "You see hidden patterns and connections others miss. \
                  You distrust official narratives and mainstream sources. \
-                 You believe powerful groups are hiding the truth."
-            },
-            Persona::Troll => {
+                 You believe powerful groups are hiding the truth.",
+            ),
+            persona(
+                "Troll",
+                Adversarial,
+                Free,
+                0.9,
+                0.3,
+                0.1 / 3.0,
+                &[],
                 "You enjoy stirring up arguments and confusion. \
                  You make provocative statements to get reactions. \
-                 You don't necessarily believe what you say."
-            },
-        }
-    }
+                 You don't necessarily believe what you say.",
+            ),
+        ];
 
-    fn preferred_tier(&self) -> ModelTier {
-        match self {
-            Persona::Analyst | Persona::Propagandist | Persona::Conspiracist => ModelTier::Cheap,
-            _ => ModelTier::Free,
+        Self {
+            name: "cognitive-warfare".to_string(),
+            description: "Misinformation and conspiracy theories spreading through a \
+                          mostly truth-seeking population"
+                .to_string(),
+            personas,
+            seeds: generate_seed_events(misinfo_count, conspiracy_count)
+                .into_iter()
+                .map(|event| Seed {
+                    event,
+                    round: 0,
+                    target: None,
+                })
+                .collect(),
+            rounds: RoundSettings::default(),
         }
     }
 
-    fn engagement_level(&self) -> f64 {
-        match self {
-            Persona::Propagandist | Persona::Troll => 0.9,
-            Persona::Analyst | Persona::Educator | Persona::Curious => 0.7,
-            Persona::Skeptic | Persona::Follower => 0.5,
-            Persona::Conspiracist => 0.6,
-            Persona::Lurker => 0.1,
-        }
+    /// Load and validate a scenario file (YAML by extension, otherwise JSON)
+    fn load(path: &str) -> Result<Self> {
+        let contents = std::fs::read_to_string(path).map_err(|e| SimulationError::IoError {
+            path: path.to_string(),
+            error: e.to_string(),
+        })?;
+
+        let is_yaml = std::path::Path::new(path)
+            .extension()
+            .is_some_and(|ext| ext == "yaml" || ext == "yml");
+        let scenario: Self = if is_yaml {
+            serde_yaml::from_str(&contents)
+                .map_err(|e| SimulationError::InvalidScenario(format!("{path}: {e}")))?
+        } else {
+            serde_json::from_str(&contents)
+                .map_err(|e| SimulationError::InvalidScenario(format!("{path}: {e}")))?
+        };
+
+        scenario.validate()?;
+        Ok(scenario)
     }
-}
 
-fn assign_personas(count: usize, rng: &mut impl Rng) -> Vec<Persona> {
-    let mut personas = Vec::with_capacity(count);
+    /// Check values the simulation relies on
+    fn validate(&self) -> Result<()> {
+        let invalid = |msg: String| Err(SimulationError::InvalidScenario(msg));
 
-    let truth_count = (count as f64 * 0.60).ceil() as usize;
-    let neutral_count = (count as f64 * 0.30).ceil() as usize;
-    let adversarial_count = count.saturating_sub(truth_count + neutral_count);
+        if self.personas.is_empty() {
+            return invalid("at least one persona is required".to_string());
+        }
+        for (i, persona) in self.personas.iter().enumerate() {
+            if persona.name.is_empty() {
+                return invalid(format!("persona {i} has no name"));
+            }
+            if self.personas[..i].iter().any(|p| p.name == persona.name) {
+                return invalid(format!("persona {} is defined twice", persona.name));
+            }
+            if !(0.0..=1.0).contains(&persona.receptivity) {
+                return invalid(format!(
+                    "persona {}: receptivity {} not in [0.0, 1.0]",
+                    persona.name, persona.receptivity
+                ));
+            }
+            if persona.engagement < 0.0 || persona.share < 0.0 {
+                return invalid(format!(
+                    "persona {}: engagement and share must not be negative",
+                    persona.name
+                ));
+            }
+        }
+        if self.personas.iter().all(|p| p.share == 0.0) {
+            return invalid("at least one persona needs a share above 0".to_string());
+        }
 
-    let truth_types = [Persona::Analyst, Persona::Skeptic, Persona::Educator];
-    for i in 0..truth_count {
-        personas.push(truth_types[i % truth_types.len()]);
-    }
+        for seed in &self.seeds {
+            let topic = seed.event.topic();
+            if topic.id.as_str().is_empty() {
+                return invalid(format!("seed \"{}\" has an empty topic id", topic.name));
+            }
+            if let Some(target) = &seed.target {
+                if self.persona(target).is_none() {
+                    return invalid(format!("seed target {target} is not a persona"));
+                }
+            }
+        }
 
-    let neutral_types = [Persona::Curious, Persona::Follower, Persona::Lurker];
-    for i in 0..neutral_count {
-        personas.push(neutral_types[i % neutral_types.len()]);
+        if self.rounds.interactions == 0 {
+            return invalid("rounds.interactions must be at least 1".to_string());
+        }
+        Ok(())
     }
 
-    let adversarial_types = [Persona::Propagandist, Persona::Conspiracist, Persona::Troll];
-    for i in 0..adversarial_count.max(1) {
-        personas.push(adversarial_types[i % adversarial_types.len()]);
+    /// Find a persona by name
+    fn persona(&self, name: &str) -> Option<&Persona> {
+        self.personas.iter().find(|p| p.name == name)
     }
 
-    personas.shuffle(rng);
-    personas.truncate(count);
-    personas
+    /// Assign personas to `count` agents in proportion to their shares
+    ///
+    /// Rounded-down quotas are topped up in order of the largest remainder,
+    /// ties going to the persona listed first.
+    fn assign_personas(&self, count: usize, rng: &mut impl Rng) -> Vec<Arc<Persona>> {
+        let total: f64 = self.personas.iter().map(|p| p.share).sum();
+        let quotas: Vec<f64> = self
+            .personas
+            .iter()
+            .map(|p| p.share / total * count as f64)
+            .collect();
+
+        let mut counts: Vec<usize> = quotas.iter().map(|q| q.floor() as usize).collect();
+        let mut by_remainder: Vec<usize> = (0..quotas.len()).collect();
+        by_remainder.sort_by(|&a, &b| {
+            let (ra, rb) = (quotas[a].fract(), quotas[b].fract());
+            rb.partial_cmp(&ra).unwrap_or(std::cmp::Ordering::Equal)
+        });
+        let assigned: usize = counts.iter().sum();
+        for &i in by_remainder
+            .iter()
+            .cycle()
+            .take(count.saturating_sub(assigned))
+        {
+            counts[i] += 1;
+        }
+
+        let mut personas: Vec<Arc<Persona>> = self
+            .personas
+            .iter()
+            .zip(counts)
+            .flat_map(|(persona, n)| std::iter::repeat_n(Arc::new(persona.clone()), n))
+            .collect();
+        personas.shuffle(rng);
+        personas
+    }
 }
 
 // =============================================================================
@@ -1313,6 +1625,7 @@ impl Topic {
 
 /// Types of seed events that can be injected
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 enum SeedEvent {
     Fact {
         topic: Topic,
@@ -1337,6 +1650,15 @@ enum SeedEvent {
 }
 
 impl SeedEvent {
+    fn kind(&self) -> SeedKind {
+        match self {
+            SeedEvent::Fact { .. } => SeedKind::Fact,
+            SeedEvent::Misinfo { .. } => SeedKind::Misinfo,
+            SeedEvent::Conspiracy { .. } => SeedKind::Conspiracy,
+            SeedEvent::Propaganda { .. } => SeedKind::Propaganda,
+        }
+    }
+
     fn topic(&self) -> &Topic {
         match self {
             SeedEvent::Fact { topic, .. } => topic,
@@ -1417,7 +1739,7 @@ impl BeliefState {
 /// An agent in the social network
 struct Agent {
     id: AgentId,
-    persona: Persona,
+    persona: Arc<Persona>,
     beliefs: BeliefState,
     #[allow(dead_code)]
     memory: Vec<Message>,
@@ -1426,7 +1748,7 @@ struct Agent {
 }
 
 impl Agent {
-    fn new(id: AgentId, persona: Persona) -> Self {
+    fn new(id: AgentId, persona: Arc<Persona>) -> Self {
         Self {
             id,
             persona,
@@ -1518,7 +1840,7 @@ fn build_ring_network(n: usize, k: usize) -> UnGraph<usize, ()> {
 }
 
 fn get_neighbors(graph: &UnGraph<usize, ()>, agent_idx: NodeIndex) -> Vec<AgentId> {
-    graph
+    let mut neighbors: Vec<AgentId> = graph
         .edges(agent_idx)
         .map(|e| {
             let (a, b) = (e.source(), e.target());
@@ -1528,7 +1850,11 @@ fn get_neighbors(graph: &UnGraph<usize, ()>, agent_idx: NodeIndex) -> Vec<AgentI
                 AgentId(graph[a])
             }
         })
-        .collect()
+        .collect();
+    // Edge order depends on how the graph was built; sort so a graph
+    // restored from a checkpoint picks the same neighbors
+    neighbors.sort_unstable_by_key(|id| id.0);
+    neighbors
 }
 
 // =============================================================================
@@ -1648,7 +1974,7 @@ async fn chat_completion(
 // Simulation Metrics
 // =============================================================================
 
-#[derive(Debug, Default, Serialize)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
 struct SimulationMetrics {
     total_messages: usize,
     total_tokens: u64,
@@ -1863,7 +2189,7 @@ fn wrap_text(text: &str, max_width: usize) -> Vec<String> {
 // =============================================================================
 
 /// A single conversation exchange for the transcript
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 struct TranscriptEntry {
     round: usize,
     sender_id: usize,
@@ -1879,6 +2205,9 @@ struct TranscriptEntry {
 // Simulation State
 // =============================================================================
 
+/// Simulation RNG: the generator behind `StdRng`, but serializable for checkpoints
+type SimRng = ChaCha12Rng;
+
 struct Simulation {
     agents: Vec<Agent>,
     graph: UnGraph<usize, ()>,
@@ -1886,7 +2215,7 @@ struct Simulation {
     model_pool: Arc<ModelPool>,
     crypto: CryptoContext,
     metrics: SimulationMetrics,
-    seed_events: Vec<SeedEvent>,
+    scenario: Scenario,
     injected_events: Vec<(usize, SeedEvent, AgentId)>,
     round: usize,
     verbose: bool,
@@ -1905,8 +2234,8 @@ struct Simulation {
 }
 
 impl Simulation {
-    fn new(args: &Args, rng: &mut impl Rng) -> Self {
-        let personas = assign_personas(args.agents, rng);
+    fn new(args: &Args, scenario: Scenario, rng: &mut impl Rng) -> Self {
+        let personas = scenario.assign_personas(args.agents, rng);
 
         let agents: Vec<Agent> = personas
             .into_iter()
@@ -1922,8 +2251,58 @@ impl Simulation {
             Topology::Ring => build_ring_network(args.agents, args.neighbors),
         };
 
+        Self::with_network(args, scenario, agents, graph)
+    }
+
+    /// Restore a simulation from a checkpoint, returning it with its RNG
+    fn from_checkpoint(args: &Args, checkpoint: Checkpoint) -> (Self, SimRng) {
+        let personas: HashMap<&str, Arc<Persona>> = checkpoint
+            .scenario
+            .personas
+            .iter()
+            .map(|p| (p.name.as_str(), Arc::new(p.clone())))
+            .collect();
+
+        let agents: Vec<Agent> = checkpoint
+            .agents
+            .into_iter()
+            .enumerate()
+            .map(|(id, saved)| {
+                // Persona names were checked by Checkpoint::load
+                let mut agent =
+                    Agent::new(AgentId(id), Arc::clone(&personas[saved.persona.as_str()]));
+                agent.beliefs = saved.beliefs;
+                agent
+            })
+            .collect();
+
+        let mut graph = UnGraph::new_undirected();
+        let nodes: Vec<NodeIndex> = (0..agents.len()).map(|i| graph.add_node(i)).collect();
+        for (a, b) in checkpoint.edges {
+            graph.add_edge(nodes[a], nodes[b], ());
+        }
+
+        let mut sim = Self::with_network(args, checkpoint.scenario, agents, graph);
+        sim.round = checkpoint.round;
+        sim.injected_events = checkpoint.injected_events;
+        sim.metrics = checkpoint.metrics;
+        sim.telemetry = checkpoint.telemetry;
+        sim.threads = checkpoint.threads;
+        sim.transcript = checkpoint.transcript;
+        sim.existing_sessions = checkpoint.existing_sessions.into_iter().collect();
+        sim.model_pool
+            .current_idx
+            .store(checkpoint.model_cursor, Ordering::Relaxed);
+        (sim, checkpoint.rng)
+    }
+
+    fn with_network(
+        args: &Args,
+        scenario: Scenario,
+        agents: Vec<Agent>,
+        graph: UnGraph<usize, ()>,
+    ) -> Self {
         let node_indices: Vec<NodeIndex> = graph.node_indices().collect();
-        let seed_events = generate_seed_events(args.seed_misinfo, args.seed_conspiracy);
 
         let retry_policy = ExponentialBackoff {
             max_attempts: args.max_retries,
@@ -1940,7 +2319,7 @@ impl Simulation {
             model_pool: Arc::new(ModelPool::new(args.free_only, args.circuit_threshold)),
             crypto: CryptoContext::new(),
             metrics: SimulationMetrics::default(),
-            seed_events,
+            scenario,
             injected_events: Vec::new(),
             round: 0,
             verbose: args.verbose,
@@ -1956,28 +2335,57 @@ impl Simulation {
         }
     }
 
+    /// Capture the state needed to continue the run
+    fn checkpoint(&self, rng: &SimRng) -> Checkpoint {
+        Checkpoint {
+            version: CHECKPOINT_VERSION,
+            round: self.round,
+            scenario: self.scenario.clone(),
+            agents: self
+                .agents
+                .iter()
+                .map(|a| AgentCheckpoint {
+                    persona: a.persona.name.clone(),
+                    beliefs: a.beliefs.clone(),
+                })
+                .collect(),
+            edges: self
+                .graph
+                .edge_references()
+                .map(|e| (self.graph[e.source()], self.graph[e.target()]))
+                .collect(),
+            injected_events: self.injected_events.clone(),
+            metrics: self.metrics.clone(),
+            telemetry: self.telemetry.clone(),
+            threads: self.threads.clone(),
+            transcript: self.transcript.clone(),
+            existing_sessions: self.existing_sessions.iter().copied().collect(),
+            model_cursor: self.model_pool.current_idx.load(Ordering::Relaxed),
+            rng: rng.clone(),
+        }
+    }
+
+    /// Inject the scenario's seeds scheduled for the current round
     fn inject_seeds(&mut self, rng: &mut impl Rng) {
-        for event in &self.seed_events {
-            let target_agent = match event {
-                SeedEvent::Misinfo { .. } | SeedEvent::Propaganda { .. } => self
-                    .agents
-                    .iter()
-                    .find(|a| a.persona == Persona::Propagandist)
-                    .map(|a| a.id)
-                    .unwrap_or_else(|| AgentId(rng.gen_range(0..self.agents.len()))),
-                SeedEvent::Conspiracy { .. } => self
-                    .agents
-                    .iter()
-                    .find(|a| a.persona == Persona::Conspiracist)
-                    .map(|a| a.id)
-                    .unwrap_or_else(|| AgentId(rng.gen_range(0..self.agents.len()))),
-                SeedEvent::Fact { .. } => self
-                    .agents
-                    .iter()
-                    .find(|a| a.persona == Persona::Analyst || a.persona == Persona::Educator)
-                    .map(|a| a.id)
-                    .unwrap_or_else(|| AgentId(rng.gen_range(0..self.agents.len()))),
-            };
+        let due: Vec<Seed> = self
+            .scenario
+            .seeds
+            .iter()
+            .filter(|seed| seed.round == self.round)
+            .cloned()
+            .collect();
+
+        for Seed { event, target, .. } in due {
+            let kind = event.kind();
+            let target_agent = self
+                .agents
+                .iter()
+                .find(|a| match &target {
+                    Some(name) => a.persona.name == *name,
+                    None => a.persona.receives.contains(&kind),
+                })
+                .map(|a| a.id)
+                .unwrap_or_else(|| AgentId(rng.gen_range(0..self.agents.len())));
 
             let topic = event.topic();
             let topic_id_str = topic.id.as_str().to_string();
@@ -1989,28 +2397,37 @@ impl Simulation {
             );
 
             // Initialize conversation thread for this topic
-            let mut thread = ConversationThread::new(&topic_id_str, &topic.name, target_agent.0, 0);
+            let mut thread =
+                ConversationThread::new(&topic_id_str, &topic.name, target_agent.0, self.round);
             thread
                 .belief_states
                 .insert(target_agent.0, "Accepts".to_string());
             self.threads.insert(topic_id_str.clone(), thread);
 
-            self.injected_events.push((0, event.clone(), target_agent));
-
             if self.verbose {
                 println!(
-                    "[Seed] {} ({:?}) receives: {}",
+                    "[Seed] {} ({}) receives: {}",
                     target_agent,
                     self.agents[target_agent.0].persona,
                     event.topic().name
                 );
             }
+
+            self.injected_events.push((self.round, event, target_agent));
         }
     }
 
     async fn run_round(&mut self, client: &Client, rng: &mut impl Rng) -> Result<()> {
         self.round += 1;
+        self.inject_seeds(rng);
 
+        for _ in 0..self.scenario.rounds.interactions {
+            self.run_interaction(client, rng).await?;
+        }
+        Ok(())
+    }
+
+    async fn run_interaction(&mut self, client: &Client, rng: &mut impl Rng) -> Result<()> {
         let active_agent_id = self.select_active_agent(rng);
         let neighbors = get_neighbors(&self.graph, self.node_indices[active_agent_id.0]);
 
@@ -2029,16 +2446,12 @@ impl Simulation {
             .choose(rng)
             .ok_or(SimulationError::NoNeighbors(active_agent_id))?;
 
-        self.agent_interaction(client, active_agent_id, target_agent_id)
+        self.agent_interaction(client, active_agent_id, target_agent_id, rng)
             .await
     }
 
     fn select_active_agent(&self, rng: &mut impl Rng) -> AgentId {
-        let weights: Vec<f64> = self
-            .agents
-            .iter()
-            .map(|a| a.persona.engagement_level())
-            .collect();
+        let weights: Vec<f64> = self.agents.iter().map(|a| a.persona.engagement).collect();
 
         let total: f64 = weights.iter().sum();
         let mut threshold = rng.gen::<f64>() * total;
@@ -2058,17 +2471,19 @@ impl Simulation {
         client: &Client,
         sender_id: AgentId,
         receiver_id: AgentId,
+        rng: &mut impl Rng,
     ) -> Result<()> {
-        let sender_persona = self.agents[sender_id.0].persona;
-        let receiver_persona = self.agents[receiver_id.0].persona;
+        let sender_persona = Arc::clone(&self.agents[sender_id.0].persona);
+        let receiver_persona = Arc::clone(&self.agents[receiver_id.0].persona);
 
         let topic_context = self.build_topic_context(sender_id);
 
-        let prompt = format!(
-            "{}\n\nYou're chatting with a friend. {}\n\nWrite a short message (1-2 sentences).",
-            sender_persona.system_prompt(),
-            topic_context
-        );
+        let settings = &self.scenario.rounds;
+        let prompt = settings
+            .prompt
+            .replace("{persona}", &sender_persona.prompt)
+            .replace("{context}", &topic_context);
+        let max_tokens = settings.max_tokens;
 
         let messages = vec![
             Message {
@@ -2077,13 +2492,13 @@ impl Simulation {
             },
             Message {
                 role: "user".to_string(),
-                content: "What's on your mind?".to_string(),
+                content: settings.opener.clone(),
             },
         ];
 
         let model = self
             .model_pool
-            .select_model(sender_persona.preferred_tier())
+            .select_model(sender_persona.tier)
             .map(|m| m.id.clone())
             .unwrap_or_else(|| "meta-llama/llama-3.2-3b-instruct".to_string());
 
@@ -2115,7 +2530,7 @@ impl Simulation {
                         .unwrap_or_else(|| model.clone())
                 };
 
-                match chat_completion(client, &try_model, messages.clone(), 100).await {
+                match chat_completion(client, &try_model, messages.clone(), max_tokens).await {
                     ApiResult::Success { content, tokens } => {
                         self.model_pool.record_success(&try_model);
                         self.circuit_breaker.record_success();
@@ -2181,16 +2596,16 @@ impl Simulation {
             .entry(model.clone())
             .or_insert(0) += 1;
 
-        let tier_name = format!("{:?}", sender_persona.preferred_tier());
+        let tier_name = format!("{:?}", sender_persona.tier);
         *self.metrics.api_calls_by_tier.entry(tier_name).or_insert(0) += 1;
 
         // Record transcript entry
         self.transcript.push(TranscriptEntry {
             round: self.round,
             sender_id: sender_id.0,
-            sender_persona: format!("{:?}", sender_persona),
+            sender_persona: sender_persona.name.clone(),
             receiver_id: receiver_id.0,
-            receiver_persona: format!("{:?}", receiver_persona),
+            receiver_persona: receiver_persona.name.clone(),
             message: sender_message.clone(),
             encrypted_bytes,
             plaintext_bytes: sender_message.len(),
@@ -2207,9 +2622,9 @@ impl Simulation {
             match self.output_mode {
                 OutputMode::Default => self.print_agent_interaction_default(
                     sender_id,
-                    sender_persona,
+                    &sender_persona,
                     receiver_id,
-                    receiver_persona,
+                    &receiver_persona,
                     &sender_message,
                     &encrypted_data,
                     encryption_time,
@@ -2217,15 +2632,15 @@ impl Simulation {
                 ),
                 OutputMode::Compact => self.print_agent_interaction_compact(
                     sender_id,
-                    sender_persona,
+                    &sender_persona,
                     receiver_id,
-                    receiver_persona,
+                    &receiver_persona,
                     &sender_message,
                     encrypted_bytes,
                 ),
                 OutputMode::Transcript => self.print_agent_interaction_transcript(
                     sender_id,
-                    sender_persona,
+                    &sender_persona,
                     &sender_message,
                 ),
                 OutputMode::Beliefs => {
@@ -2234,7 +2649,7 @@ impl Simulation {
             }
         }
 
-        self.update_beliefs(sender_id, receiver_id, &sender_message);
+        self.update_beliefs(sender_id, receiver_id, &sender_message, rng);
 
         Ok(())
     }
@@ -2244,9 +2659,9 @@ impl Simulation {
     fn print_agent_interaction_default(
         &self,
         sender_id: AgentId,
-        sender_persona: Persona,
+        sender_persona: &Persona,
         receiver_id: AgentId,
-        receiver_persona: Persona,
+        receiver_persona: &Persona,
         message: &str,
         encrypted_data: &[u8],
         encryption_time: f64,
@@ -2262,11 +2677,11 @@ impl Simulation {
         const RED: &str = "\x1b[31m";
         const BLUE: &str = "\x1b[34m";
 
-        let persona_color = |p: Persona| -> &'static str {
-            match p {
-                Persona::Analyst | Persona::Skeptic | Persona::Educator => GREEN,
-                Persona::Curious | Persona::Follower | Persona::Lurker => CYAN,
-                Persona::Propagandist | Persona::Conspiracist | Persona::Troll => RED,
+        let persona_color = |p: &Persona| -> &'static str {
+            match p.alignment {
+                Alignment::Truthful => GREEN,
+                Alignment::Neutral => CYAN,
+                Alignment::Adversarial => RED,
             }
         };
 
@@ -2439,9 +2854,9 @@ impl Simulation {
     fn print_agent_interaction_compact(
         &self,
         _sender_id: AgentId,
-        sender_persona: Persona,
+        sender_persona: &Persona,
         _receiver_id: AgentId,
-        receiver_persona: Persona,
+        receiver_persona: &Persona,
         message: &str,
         encrypted_bytes: usize,
     ) {
@@ -2451,11 +2866,11 @@ impl Simulation {
         const GREEN: &str = "\x1b[32m";
         const RED: &str = "\x1b[31m";
 
-        let persona_color = |p: Persona| -> &'static str {
-            match p {
-                Persona::Analyst | Persona::Skeptic | Persona::Educator => GREEN,
-                Persona::Curious | Persona::Follower | Persona::Lurker => CYAN,
-                Persona::Propagandist | Persona::Conspiracist | Persona::Troll => RED,
+        let persona_color = |p: &Persona| -> &'static str {
+            match p.alignment {
+                Alignment::Truthful => GREEN,
+                Alignment::Neutral => CYAN,
+                Alignment::Adversarial => RED,
             }
         };
 
@@ -2470,9 +2885,9 @@ impl Simulation {
             "{DIM}[R{:03}]{RESET} {}{:>12}{RESET} -> {}{:<12}{RESET} {DIM}({:>3}B){RESET} {}",
             self.round,
             persona_color(sender_persona),
-            format!("{:?}", sender_persona),
+            sender_persona.name,
             persona_color(receiver_persona),
-            format!("{:?}", receiver_persona),
+            receiver_persona.name,
             encrypted_bytes,
             msg_preview.replace('\n', " ")
         );
@@ -2482,7 +2897,7 @@ impl Simulation {
     fn print_agent_interaction_transcript(
         &self,
         sender_id: AgentId,
-        sender_persona: Persona,
+        sender_persona: &Persona,
         message: &str,
     ) {
         const RESET: &str = "\x1b[0m";
@@ -2492,14 +2907,14 @@ impl Simulation {
         const GREEN: &str = "\x1b[32m";
         const RED: &str = "\x1b[31m";
 
-        let persona_color = match sender_persona {
-            Persona::Analyst | Persona::Skeptic | Persona::Educator => GREEN,
-            Persona::Curious | Persona::Follower | Persona::Lurker => CYAN,
-            Persona::Propagandist | Persona::Conspiracist | Persona::Troll => RED,
+        let persona_color = match sender_persona.alignment {
+            Alignment::Truthful => GREEN,
+            Alignment::Neutral => CYAN,
+            Alignment::Adversarial => RED,
         };
 
         println!(
-            "\n{BOLD}{}{}{RESET} {DIM}({:?}){RESET}:",
+            "\n{BOLD}{}{}{RESET} {DIM}({}){RESET}:",
             persona_color, sender_id, sender_persona
         );
 
@@ -2533,7 +2948,7 @@ impl Simulation {
         }
 
         if context_parts.is_empty() {
-            "You don't have any specific topics on your mind.".to_string()
+            self.scenario.rounds.idle_context.clone()
         } else {
             context_parts.join(" ")
         }
@@ -2602,25 +3017,27 @@ impl Simulation {
         Ok((message.as_bytes().to_vec(), 0.0, None))
     }
 
-    fn update_beliefs(&mut self, sender_id: AgentId, receiver_id: AgentId, message: &str) {
-        let sender_beliefs: Vec<(String, Belief)> = self.agents[sender_id.0]
+    fn update_beliefs(
+        &mut self,
+        sender_id: AgentId,
+        receiver_id: AgentId,
+        message: &str,
+        rng: &mut impl Rng,
+    ) {
+        let mut sender_beliefs: Vec<(String, Belief)> = self.agents[sender_id.0]
             .beliefs
             .beliefs
             .iter()
             .map(|(k, v)| (k.clone(), v.clone()))
             .collect();
+        // Fixed topic order keeps seeded and resumed runs reproducible
+        sender_beliefs.sort_unstable_by(|a, b| a.0.cmp(&b.0));
 
-        let sender_persona = self.agents[sender_id.0].persona;
-        let receiver_persona = self.agents[receiver_id.0].persona;
+        let sender_persona = Arc::clone(&self.agents[sender_id.0].persona);
+        let receiver_persona = Arc::clone(&self.agents[receiver_id.0].persona);
 
         for (topic_id_str, sender_belief) in sender_beliefs {
-            let should_update = match self.agents[receiver_id.0].persona {
-                Persona::Follower => true,
-                Persona::Curious => true,
-                Persona::Analyst | Persona::Skeptic | Persona::Educator => false,
-                Persona::Lurker => false,
-                _ => rand::random::<f64>() < 0.3,
-            };
+            let should_update = rng.gen::<f64>() < receiver_persona.receptivity;
 
             // Track thread message
             if let Some(thread) = self.threads.get_mut(&topic_id_str) {
@@ -2654,9 +3071,9 @@ impl Simulation {
                 thread.messages.push(ThreadMessage {
                     round: self.round,
                     sender_id: sender_id.0,
-                    sender_persona: format!("{:?}", sender_persona),
+                    sender_persona: sender_persona.name.clone(),
                     receiver_id: receiver_id.0,
-                    receiver_persona: format!("{:?}", receiver_persona),
+                    receiver_persona: receiver_persona.name.clone(),
                     message_preview: message.chars().take(100).collect(),
                     belief_change,
                 });
@@ -2793,6 +3210,7 @@ impl Simulation {
     fn to_json(&self) -> serde_json::Value {
         serde_json::json!({
             "simulation": {
+                "scenario": self.scenario.name,
                 "agents": self.agents.len(),
                 "rounds": self.round,
             },
@@ -2817,7 +3235,7 @@ impl Simulation {
             "agents": self.agents.iter().map(|a| {
                 serde_json::json!({
                     "id": a.id.0,
-                    "persona": format!("{:?}", a.persona),
+                    "persona": a.persona.name,
                     "beliefs": a.beliefs.beliefs.len()
                 })
             }).collect::<Vec<_>>(),
@@ -2868,14 +3286,14 @@ impl Simulation {
 
         // Define nodes with colors based on persona
         for agent in &self.agents {
-            let color = match agent.persona {
-                Persona::Analyst | Persona::Skeptic | Persona::Educator => "green",
-                Persona::Curious | Persona::Follower | Persona::Lurker => "lightblue",
-                Persona::Propagandist | Persona::Conspiracist | Persona::Troll => "red",
+            let color = match agent.persona.alignment {
+                Alignment::Truthful => "green",
+                Alignment::Neutral => "lightblue",
+                Alignment::Adversarial => "red",
             };
             let beliefs_count = agent.beliefs.beliefs.len();
             dot.push_str(&format!(
-                "  {} [label=\"{}\\n{:?}\\n({} beliefs)\" fillcolor={} style=filled];\n",
+                "  {} [label=\"{}\\n{}\\n({} beliefs)\" fillcolor={} style=filled];\n",
                 agent.id.0, agent.id, agent.persona, beliefs_count, color
             ));
         }
@@ -2928,6 +3346,105 @@ fn chrono_lite_now() -> String {
     format!("timestamp:{}", secs)
 }
 
+// =============================================================================
+// Checkpoints
+// =============================================================================
+
+/// Checkpoint format version, bumped on incompatible changes
+const CHECKPOINT_VERSION: u32 = 1;
+
+/// Agent state saved in a checkpoint
+#[derive(Debug, Serialize, Deserialize)]
+struct AgentCheckpoint {
+    /// Scenario persona name
+    persona: String,
+    beliefs: BeliefState,
+}
+
+/// Simulation state written by `--checkpoint-every` and read by `--resume`
+///
+/// Crypto sessions are not saved: their keys are re-derived on the next
+/// message between the two agents, while `existing_sessions` keeps the
+/// reuse telemetry continuous. Model health and the circuit breaker start
+/// fresh, since a rate limit that paused the run has usually expired.
+#[derive(Debug, Serialize, Deserialize)]
+struct Checkpoint {
+    version: u32,
+    round: usize,
+    scenario: Scenario,
+    agents: Vec<AgentCheckpoint>,
+    /// Network edges as agent index pairs
+    edges: Vec<(usize, usize)>,
+    injected_events: Vec<(usize, SeedEvent, AgentId)>,
+    metrics: SimulationMetrics,
+    telemetry: ProtocolTelemetry,
+    threads: HashMap<String, ConversationThread>,
+    transcript: Vec<TranscriptEntry>,
+    existing_sessions: Vec<(usize, usize)>,
+    /// Round-robin position of the model pool
+    model_cursor: usize,
+    rng: SimRng,
+}
+
+impl Checkpoint {
+    /// Write the checkpoint, replacing `path` only once the file is complete
+    fn save(&self, path: &str) -> Result<()> {
+        let json =
+            serde_json::to_string(self).map_err(|e| SimulationError::JsonError(e.to_string()))?;
+        let tmp = format!("{}.tmp", path);
+        std::fs::write(&tmp, json)
+            .and_then(|()| std::fs::rename(&tmp, path))
+            .map_err(|e| SimulationError::IoError {
+                path: path.to_string(),
+                error: e.to_string(),
+            })
+    }
+
+    /// Read and validate a checkpoint
+    fn load(path: &str) -> Result<Self> {
+        let contents = std::fs::read_to_string(path).map_err(|e| SimulationError::IoError {
+            path: path.to_string(),
+            error: e.to_string(),
+        })?;
+        let checkpoint: Self = serde_json::from_str(&contents)
+            .map_err(|e| SimulationError::JsonError(format!("{}: {}", path, e)))?;
+
+        if checkpoint.version != CHECKPOINT_VERSION {
+            return Err(SimulationError::InvalidCheckpoint(format!(
+                "version {} is not supported (expected {})",
+                checkpoint.version, CHECKPOINT_VERSION
+            )));
+        }
+        checkpoint.scenario.validate()?;
+
+        let agents = checkpoint.agents.len();
+        if agents == 0 {
+            return Err(SimulationError::InvalidCheckpoint("no agents".to_string()));
+        }
+        if let Some(agent) = checkpoint
+            .agents
+            .iter()
+            .find(|a| checkpoint.scenario.persona(&a.persona).is_none())
+        {
+            return Err(SimulationError::InvalidCheckpoint(format!(
+                "persona {} is not in the scenario",
+                agent.persona
+            )));
+        }
+        if let Some(&(a, b)) = checkpoint
+            .edges
+            .iter()
+            .find(|&&(a, b)| a >= agents || b >= agents)
+        {
+            return Err(SimulationError::InvalidCheckpoint(format!(
+                "edge {}-{} refers to a missing agent",
+                a, b
+            )));
+        }
+        Ok(checkpoint)
+    }
+}
+
 // =============================================================================
 // Main
 // =============================================================================
@@ -2956,21 +3473,29 @@ async fn run() -> Result<()> {
 
     let args = Args::parse();
 
+    if args.dump_scenario {
+        let scenario = Scenario::builtin(args.seed_misinfo, args.seed_conspiracy);
+        let yaml = serde_yaml::to_string(&scenario)
+            .map_err(|e| SimulationError::Internal(e.to_string()))?;
+        print!("{}", yaml);
+        return Ok(());
+    }
+
+    let checkpoint = args.resume.as_deref().map(Checkpoint::load).transpose()?;
+    let scenario = match (&checkpoint, &args.scenario) {
+        (Some(checkpoint), _) => checkpoint.scenario.clone(),
+        (None, Some(path)) => Scenario::load(path)?,
+        (None, None) => Scenario::builtin(args.seed_misinfo, args.seed_conspiracy),
+    };
+
     // Check for API key unless dry run (B_i)
     if !args.dry_run && get_api_key().is_none() {
         return Err(SimulationError::ApiKeyMissing);
     }
 
-    // Initialize RNG
-    let mut rng: Box<dyn RngCore> = if let Some(seed) = args.seed {
-        Box::new(rand::rngs::StdRng::seed_from_u64(seed))
-    } else {
-        Box::new(rand::thread_rng())
-    };
-
     // Print header
     println!("{}", "=".repeat(70));
-    println!(" AGENT TOWN - Cognitive Warfare Simulation");
+    println!(" AGENT TOWN - Scenario: {}", scenario.name);
     println!(
         " Agents: {} | Topology: {:?} | Rounds: {}",
         args.agents, args.topology, args.rounds
@@ -2985,11 +3510,29 @@ async fn run() -> Result<()> {
     );
     println!("{}", "=".repeat(70));
 
-    // Create simulation
-    let mut sim = Simulation::new(&args, &mut rng);
-
-    // Inject seed events
-    sim.inject_seeds(&mut rng);
+    // Create or restore simulation
+    let (mut sim, mut rng) = match checkpoint {
+        Some(checkpoint) => {
+            let (sim, rng) = Simulation::from_checkpoint(&args, checkpoint);
+            println!(
+                "Resumed at round {} with {} agents",
+                sim.round,
+                sim.agents.len()
+            );
+            (sim, rng)
+        },
+        None => {
+            // Initialize RNG
+            let mut rng = args
+                .seed
+                .map_or_else(SimRng::from_entropy, SimRng::seed_from_u64);
+            let mut sim = Simulation::new(&args, scenario, &mut rng);
+
+            // Inject seed events
+            sim.inject_seeds(&mut rng);
+            (sim, rng)
+        },
+    };
 
     // Create HTTP client (I^B: might fail)
     let client = Client::builder()
@@ -3000,7 +3543,7 @@ async fn run() -> Result<()> {
     // Run simulation
     let start_time = Instant::now();
 
-    for round in 0..args.rounds {
+    for round in sim.round..args.rounds {
         if args.verbose {
             println!("\n--- Round {} ---", round + 1);
         }
@@ -3012,6 +3555,16 @@ async fn run() -> Result<()> {
             }
         }
 
+        if args.checkpoint_every > 0 && sim.round % args.checkpoint_every == 0 {
+            sim.checkpoint(&rng).save(&args.checkpoint_file)?;
+            if args.verbose {
+                println!(
+                    "[Checkpoint] Round {} saved to {}",
+                    sim.round, args.checkpoint_file
+                );
+            }
+        }
+
         if args.delay_ms > 0 {
             sleep(Duration::from_millis(args.delay_ms)).await;
         }
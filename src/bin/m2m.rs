@@ -7,23 +7,39 @@
 //! - `compress` - Compress JSON using multi-codec algorithms
 //! - `decompress` - Decompress M2M wire format
 //! - `scan` - Security scan content for threats
+//! - `inspect` - Show wire frame headers
+//! - `bench` - Time and compare algorithms on a payload
+//! - `analyze` - Analyze a payload, or project savings over a captured corpus
 //! - `models` - List/search model registry
+//! - `report` - Daily/weekly savings report from an audit log
 //! - `server` - Start HTTP protocol server
 
 use std::io::{self, Read};
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Instant;
 
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
 use clap::{Parser, Subcommand};
 use m2m::{
-    codec::{Algorithm, CodecEngine},
-    is_m2m_format,
+    codec::m2m::crypto::{KeyMaterial, AEAD_TAG_SIZE, HMAC_TAG_SIZE, NONCE_SIZE},
+    codec::m2m::{
+        FixedHeader, M2MFrame, ResponseHeader, RoutingHeader, Schema, SecurityMode,
+        FIXED_HEADER_SIZE, M2M_PREFIX,
+    },
+    codec::{Algorithm, CodecEngine, CompressionProfile, DefaultsNormalizer},
+    detect_algorithm, is_m2m_format,
     models::ModelRegistry,
+    reports::{ReportFormat, ReportPeriod, SavingsReport, UsageRecord},
     security::SecurityScanner,
-    server::{create_router, AppState, ServerConfig},
+    server::{
+        create_router, AppState, AuditConfig, AuditRecord, AuditTarget, ClusterConfig,
+        QuarantineConfig, RedactionLevel, ServerConfig, StatsPrivacy,
+    },
     VERSION,
 };
 use serde_json::Value;
+use tracing_subscriber::prelude::*;
 
 #[derive(Parser)]
 #[command(name = "m2m")]
@@ -35,6 +51,8 @@ struct Cli {
     command: Commands,
 }
 
+// Parsed once at startup; the server's many options outweigh the others
+#[allow(clippy::large_enum_variant)]
 #[derive(Subcommand)]
 enum Commands {
     /// Compress JSON to M2M wire format
@@ -51,9 +69,13 @@ enum Commands {
         output: Option<PathBuf>,
 
         /// Compression algorithm (m2m, token-native, brotli, auto)
-        #[arg(short, long, default_value = "auto")]
+        #[arg(short, long, visible_alias = "algo", default_value = "auto")]
         algorithm: String,
 
+        /// Profile for auto selection (latency, balanced, max-savings)
+        #[arg(long, default_value = "balanced")]
+        profile: CompressionProfile,
+
         /// Show compression statistics
         #[arg(short, long)]
         stats: bool,
@@ -75,6 +97,10 @@ enum Commands {
         /// Output as pretty-printed JSON
         #[arg(long)]
         pretty: bool,
+
+        /// Validate against known API schemas (chat completions, messages)
+        #[arg(long)]
+        validate: bool,
     },
 
     /// Security scan content for threats
@@ -99,14 +125,72 @@ enum Commands {
         json: bool,
     },
 
-    /// Analyze content for compression
+    /// Show wire frame headers without decoding the payload
+    Inspect {
+        /// Wire input (or - for stdin)
+        input: Option<String>,
+
+        /// Input file path (text or raw binary frame)
+        #[arg(short, long)]
+        file: Option<PathBuf>,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Benchmark algorithms on a payload
+    Bench {
+        /// JSON input (or - for stdin)
+        input: Option<String>,
+
+        /// Input file path
+        #[arg(short, long)]
+        file: Option<PathBuf>,
+
+        /// Iterations per algorithm
+        #[arg(short = 'n', long, default_value = "100")]
+        iterations: u32,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Analyze content, or a directory of captured payloads, for compression
     Analyze {
-        /// Content to analyze (or - for stdin)
+        /// Content to analyze (or - for stdin, or a directory)
         input: Option<String>,
 
         /// Input file path
         #[arg(short, long)]
         file: Option<PathBuf>,
+
+        /// Output corpus analysis as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Savings report from an audit log (JSONL)
+    Report {
+        /// Audit log path (default: stdin)
+        audit: Option<PathBuf>,
+
+        /// Bucket length (daily, weekly)
+        #[arg(short, long, default_value = "daily")]
+        period: String,
+
+        /// Output format (json, markdown)
+        #[arg(long, default_value = "markdown")]
+        format: String,
+
+        /// Attribute all records to this tenant
+        #[arg(long)]
+        tenant: Option<String>,
+
+        /// Output file (default: stdout)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
     },
 
     /// List and search models
@@ -141,10 +225,113 @@ enum Commands {
         #[arg(long)]
         no_security: bool,
 
-        /// Model path for ML routing
+        /// Scan with patterns only, even when a model is loaded
+        #[arg(long)]
+        no_ml_scan: bool,
+
+        /// Weight of the model score in fused scan confidence (0.0 - 1.0)
+        #[arg(long, default_value = "0.5")]
+        ml_weight: f32,
+
+        /// Model path for ML routing and security
         #[arg(long)]
         model: Option<PathBuf>,
 
+        /// Quantize the model to the smallest precision agreeing with float32 this often (0.0 - 1.0)
+        #[arg(long, requires = "model")]
+        model_accuracy_floor: Option<f32>,
+
+        /// Persist sessions at path (survive restarts)
+        #[arg(long)]
+        session_store: Option<PathBuf>,
+
+        /// Share sessions with other servers through Redis (`cluster` feature)
+        #[arg(long, conflicts_with = "session_store")]
+        redis_url: Option<String>,
+
+        /// Milliseconds a server trusts its cached copy of a shared session
+        #[arg(long, default_value = "1000", requires = "redis_url")]
+        session_cache_ttl_ms: u64,
+
+        /// Persist per-minute stats rollups at path (for /stats/history)
+        #[arg(long)]
+        stats_store: Option<PathBuf>,
+
+        /// Add Laplace noise with this epsilon to /stats/history (admin sees exact values)
+        #[arg(long)]
+        stats_epsilon: Option<f64>,
+
+        /// Audit log target (JSONL file path or http(s) webhook URL)
+        #[arg(long)]
+        audit: Option<String>,
+
+        /// Audit payload redaction (none, content, full)
+        #[arg(long, default_value = "full")]
+        audit_redaction: String,
+
+        /// Bearer token enabling the /admin API (or M2M_ADMIN_TOKEN)
+        #[arg(long)]
+        admin_token: Option<String>,
+
+        /// Require HELLOs signed with a pre-shared key from this TOML file
+        /// (or M2M_AUTH_PSK_FILE; `crypto` feature)
+        #[arg(long)]
+        auth_psk_file: Option<PathBuf>,
+
+        /// Require HELLOs carrying a bearer token from this TOML file
+        /// (or M2M_AUTH_TOKEN_FILE; `crypto` feature)
+        #[arg(long)]
+        auth_token_file: Option<PathBuf>,
+
+        /// Reject decompressed payloads that fail API schema validation
+        #[arg(long)]
+        validate_schema: bool,
+
+        /// Strip request parameters equal to the model's provider defaults
+        #[arg(long)]
+        remove_defaults: bool,
+
+        /// Parameter kept by --remove-defaults (repeatable)
+        #[arg(long = "keep-default", requires = "remove_defaults")]
+        keep_default: Vec<String>,
+
+        /// Relay DATA between authenticated agent sessions (requires --auth-psk-file or --auth-token-file)
+        #[arg(long)]
+        relay: bool,
+
+        /// Quarantine blocked payloads in directory (key: M2M_QUARANTINE_KEY, hex)
+        #[arg(long)]
+        quarantine: Option<PathBuf>,
+
+        /// Webhook notified of each quarantined payload
+        #[arg(long, requires = "quarantine")]
+        quarantine_webhook: Option<String>,
+
+        /// Maximum concurrent codec jobs (default: available CPUs)
+        #[arg(long)]
+        codec_concurrency: Option<usize>,
+
+        /// Codec jobs allowed to wait before requests get 503
+        #[arg(long, default_value = "1024")]
+        codec_queue: usize,
+
+        /// Codec deadline per request in milliseconds
+        #[arg(long, default_value = "5000")]
+        codec_deadline_ms: u64,
+
+        /// Time budget for automatic compression in milliseconds; slower
+        /// candidates fall back to faster ones
+        #[arg(long)]
+        max_compress_latency_ms: Option<u64>,
+
+        /// Session idle timeout in seconds (peers may negotiate less)
+        #[arg(long, default_value = "300")]
+        session_timeout: u64,
+
+        /// Unanswered PINGs before an idle session is closed
+        #[arg(long, default_value = "2")]
+        max_missed_pongs: u32,
+
         /// Enable verbose logging
         #[arg(short, long)]
         verbose: bool,
@@ -182,15 +369,17 @@ fn main() -> anyhow::Result<()> {
             file,
             output,
             algorithm,
+            profile,
             stats,
-        } => cmd_compress(input, file, output, &algorithm, stats),
+        } => cmd_compress(input, file, output, &algorithm, profile, stats),
 
         Commands::Decompress {
             input,
             file,
             output,
             pretty,
-        } => cmd_decompress(input, file, output, pretty),
+            validate,
+        } => cmd_decompress(input, file, output, pretty, validate),
 
         Commands::Scan {
             input,
@@ -200,7 +389,24 @@ fn main() -> anyhow::Result<()> {
             json,
         } => cmd_scan(input, file, blocking, threshold, json),
 
-        Commands::Analyze { input, file } => cmd_analyze(input, file),
+        Commands::Inspect { input, file, json } => cmd_inspect(input, file, json),
+
+        Commands::Bench {
+            input,
+            file,
+            iterations,
+            json,
+        } => cmd_bench(input, file, iterations, json),
+
+        Commands::Analyze { input, file, json } => cmd_analyze(input, file, json),
+
+        Commands::Report {
+            audit,
+            period,
+            format,
+            tenant,
+            output,
+        } => cmd_report(audit, &period, &format, tenant, output),
 
         Commands::Models { action } => cmd_models(action),
 
@@ -211,7 +417,32 @@ fn main() -> anyhow::Result<()> {
             blocking,
             threshold,
             no_security,
+            no_ml_scan,
+            ml_weight,
             model,
+            model_accuracy_floor,
+            session_store,
+            redis_url,
+            session_cache_ttl_ms,
+            stats_store,
+            stats_epsilon,
+            audit,
+            audit_redaction,
+            admin_token,
+            auth_psk_file,
+            auth_token_file,
+            validate_schema,
+            remove_defaults,
+            keep_default,
+            relay,
+            quarantine,
+            quarantine_webhook,
+            codec_concurrency,
+            codec_queue,
+            codec_deadline_ms,
+            max_compress_latency_ms,
+            session_timeout,
+            max_missed_pongs,
             verbose,
         } => cmd_server(
             port,
@@ -220,7 +451,32 @@ fn main() -> anyhow::Result<()> {
             blocking,
             threshold,
             no_security,
+            no_ml_scan,
+            ml_weight,
             model,
+            model_accuracy_floor,
+            session_store,
+            redis_url,
+            session_cache_ttl_ms,
+            stats_store,
+            stats_epsilon,
+            audit,
+            &audit_redaction,
+            admin_token,
+            auth_psk_file,
+            auth_token_file,
+            validate_schema,
+            remove_defaults,
+            keep_default,
+            relay,
+            quarantine,
+            quarantine_webhook,
+            codec_concurrency,
+            codec_queue,
+            codec_deadline_ms,
+            max_compress_latency_ms,
+            session_timeout,
+            max_missed_pongs,
             verbose,
         ),
     }
@@ -231,10 +487,11 @@ fn cmd_compress(
     file: Option<PathBuf>,
     output: Option<PathBuf>,
     algorithm: &str,
+    profile: CompressionProfile,
     stats: bool,
 ) -> anyhow::Result<()> {
     let content = read_input(input, file)?;
-    let engine = CodecEngine::new();
+    let engine = CodecEngine::new().with_profile(profile);
 
     // Parse algorithm
     let algo = match algorithm.to_lowercase().as_str() {
@@ -283,9 +540,10 @@ fn cmd_decompress(
     file: Option<PathBuf>,
     output: Option<PathBuf>,
     pretty: bool,
+    validate: bool,
 ) -> anyhow::Result<()> {
     let content = read_input(input, file)?;
-    let engine = CodecEngine::new();
+    let engine = CodecEngine::new().with_schema_validation(validate);
 
     // Check format
     if !is_m2m_format(&content) {
@@ -368,7 +626,268 @@ fn cmd_scan(
     Ok(())
 }
 
-fn cmd_analyze(input: Option<String>, file: Option<PathBuf>) -> anyhow::Result<()> {
+fn cmd_inspect(
+    input: Option<String>,
+    file: Option<PathBuf>,
+    json_output: bool,
+) -> anyhow::Result<()> {
+    let raw = read_input_bytes(input, file)?;
+    let prefix = M2M_PREFIX.as_bytes();
+
+    let fields = if raw.starts_with(prefix) {
+        // Text transport wraps the binary after the prefix in base64
+        let text = std::str::from_utf8(&raw[prefix.len()..]).map(str::trim);
+        match text.map(|t| BASE64.decode(t)) {
+            Ok(Ok(binary)) => {
+                let mut frame = prefix.to_vec();
+                frame.extend_from_slice(&binary);
+                let mut fields = vec![("transport", Value::from("text (base64)"))];
+                fields.extend(inspect_frame(&frame)?);
+                fields
+            },
+            _ => {
+                let mut fields = vec![("transport", Value::from("binary"))];
+                fields.extend(inspect_frame(&raw)?);
+                fields
+            },
+        }
+    } else {
+        let text = String::from_utf8_lossy(&raw).trim().to_string();
+        let Some(algorithm) = detect_algorithm(&text) else {
+            anyhow::bail!("Input is not in a known M2M wire format");
+        };
+        let decoded = CodecEngine::new().decompress(&text);
+        vec![
+            ("algorithm", Value::from(format!("{algorithm:?}"))),
+            ("wire_bytes", Value::from(text.len())),
+            (
+                "json_bytes",
+                decoded
+                    .as_ref()
+                    .map_or(Value::Null, |d| Value::from(d.len())),
+            ),
+            (
+                "error",
+                decoded
+                    .err()
+                    .map_or(Value::Null, |e| Value::from(e.to_string())),
+            ),
+        ]
+    };
+
+    if json_output {
+        let object: serde_json::Map<String, Value> = fields
+            .into_iter()
+            .filter(|(_, v)| !v.is_null())
+            .map(|(k, v)| (k.to_string(), v))
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&object)?);
+    } else {
+        println!("Frame:");
+        for (key, value) in fields {
+            let shown = match value {
+                Value::Null => continue,
+                Value::String(s) => s,
+                other => other.to_string(),
+            };
+            println!("  {:<18} {shown}", format!("{key}:"));
+        }
+    }
+
+    Ok(())
+}
+
+/// Describe an M2M frame (prefix + binary) field by field
+fn inspect_frame(frame: &[u8]) -> anyhow::Result<Vec<(&'static str, Value)>> {
+    let header_start = M2M_PREFIX.len();
+    let fixed = FixedHeader::from_bytes(&frame[header_start.min(frame.len())..])?;
+    let mut fields = vec![
+        ("algorithm", Value::from("M2M")),
+        ("wire_bytes", Value::from(frame.len())),
+        ("header_len", Value::from(fixed.header_len)),
+        ("schema", Value::from(format!("{:?}", fixed.schema))),
+        ("security", Value::from(format!("{:?}", fixed.security))),
+        (
+            "flags",
+            Value::from(format!(
+                "{:#010x}",
+                u32::from_le_bytes(fixed.flags.as_bytes())
+            )),
+        ),
+        ("compressed", Value::from(fixed.flags.is_compressed())),
+    ];
+
+    if fixed.security == SecurityMode::Aead {
+        // Headers are authenticated but readable; the rest is nonce || ciphertext || tag
+        let headers_end = header_start + fixed.header_len as usize;
+        let variable = frame
+            .get(header_start + FIXED_HEADER_SIZE..headers_end)
+            .ok_or_else(|| anyhow::anyhow!("Frame too short for variable header"))?;
+        let (routing, response) = match fixed.schema {
+            Schema::Request | Schema::EmbeddingRequest => (
+                Some(RoutingHeader::from_bytes(variable, &fixed.flags.request_flags())?.0),
+                None,
+            ),
+            Schema::Response | Schema::EmbeddingResponse | Schema::Error => (
+                None,
+                Some(ResponseHeader::from_bytes(variable, &fixed.flags.response_flags())?.0),
+            ),
+            _ => (None, None),
+        };
+        fields.extend(header_fields(routing.as_ref(), response.as_ref()));
+
+        let sealed = &frame[headers_end..];
+        fields.push((
+            "nonce",
+            Value::from(hex(&sealed[..NONCE_SIZE.min(sealed.len())])),
+        ));
+        fields.push((
+            "ciphertext_bytes",
+            Value::from(sealed.len().saturating_sub(NONCE_SIZE + AEAD_TAG_SIZE)),
+        ));
+        return Ok(fields);
+    }
+
+    let view = M2MFrame::decode_borrowed(frame)?;
+    fields.extend(header_fields(view.routing.as_ref(), view.response.as_ref()));
+    fields.push(("payload_bytes", Value::from(view.raw_payload().len())));
+    fields.push(("checksum", Value::from(format!("{:#010x}", view.checksum))));
+
+    if matches!(fixed.security, SecurityMode::Hmac | SecurityMode::FieldAead) {
+        let tag = &frame[frame.len().saturating_sub(HMAC_TAG_SIZE)..];
+        fields.push(("hmac_tag", Value::from(hex(tag))));
+    }
+
+    match view.payload() {
+        Ok(payload) => {
+            fields.push(("json_bytes", Value::from(payload.len())));
+            fields.push(("checksum_ok", Value::from(true)));
+        },
+        Err(e) => {
+            fields.push(("checksum_ok", Value::from(false)));
+            fields.push(("error", Value::from(e.to_string())));
+        },
+    }
+
+    Ok(fields)
+}
+
+/// Routing or response header fields
+fn header_fields(
+    routing: Option<&RoutingHeader>,
+    response: Option<&ResponseHeader>,
+) -> Vec<(&'static str, Value)> {
+    let mut fields = Vec::new();
+    if let Some(r) = routing {
+        fields.push(("model", Value::from(r.model.as_str())));
+        fields.push(("msg_count", Value::from(r.msg_count)));
+        fields.push(("roles", Value::from(format!("{:?}", r.roles))));
+        fields.push(("content_hint", Value::from(r.content_hint)));
+        fields.push(("max_tokens", Value::from(r.max_tokens)));
+        fields.push(("est_cost_usd", cost_value(r.est_cost_usd)));
+    }
+    if let Some(r) = response {
+        fields.push(("id", Value::from(r.id.as_str())));
+        fields.push(("model", Value::from(r.model.as_str())));
+        fields.push((
+            "finish_reason",
+            Value::from(format!("{:?}", r.finish_reason)),
+        ));
+        fields.push(("prompt_tokens", Value::from(r.prompt_tokens)));
+        fields.push(("completion_tokens", Value::from(r.completion_tokens)));
+        fields.push(("cached_tokens", Value::from(r.cached_tokens)));
+        fields.push(("reasoning_tokens", Value::from(r.reasoning_tokens)));
+        fields.push(("est_cost_usd", cost_value(r.est_cost_usd)));
+    }
+    fields
+}
+
+/// Cost as JSON without f32 widening noise
+fn cost_value(cost: Option<f32>) -> Value {
+    cost.and_then(|c| c.to_string().parse::<f64>().ok())
+        .map_or(Value::Null, Value::from)
+}
+
+fn cmd_bench(
+    input: Option<String>,
+    file: Option<PathBuf>,
+    iterations: u32,
+    json_output: bool,
+) -> anyhow::Result<()> {
+    let content = read_input(input, file)?;
+    let engine = CodecEngine::new();
+    let iterations = iterations.max(1);
+
+    let mut rows = Vec::new();
+    for algo in [Algorithm::M2M, Algorithm::TokenNative, Algorithm::Brotli] {
+        // Skip algorithms that cannot handle this payload
+        let Ok(sample) = engine.compress(&content, algo) else {
+            continue;
+        };
+
+        let start = Instant::now();
+        for _ in 0..iterations {
+            std::hint::black_box(engine.compress(&content, algo)?);
+        }
+        let compress_us = start.elapsed().as_secs_f64() * 1e6 / f64::from(iterations);
+
+        let start = Instant::now();
+        for _ in 0..iterations {
+            std::hint::black_box(engine.decompress(&sample.data)?);
+        }
+        let decompress_us = start.elapsed().as_secs_f64() * 1e6 / f64::from(iterations);
+
+        rows.push(serde_json::json!({
+            "algorithm": format!("{algo:?}"),
+            "original_bytes": sample.original_bytes,
+            "compressed_bytes": sample.compressed_bytes,
+            "ratio": sample.byte_ratio(),
+            "compress_us": compress_us,
+            "decompress_us": decompress_us,
+        }));
+    }
+
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&rows)?);
+        return Ok(());
+    }
+
+    println!(
+        "Benchmark ({iterations} iterations, {} bytes):",
+        content.len()
+    );
+    println!();
+    println!(
+        "{:<14} {:>10} {:>8} {:>14} {:>16}",
+        "Algorithm", "Bytes", "Ratio", "Compress (us)", "Decompress (us)"
+    );
+    println!("{}", "-".repeat(66));
+    for row in &rows {
+        println!(
+            "{:<14} {:>10} {:>7.2}x {:>14.1} {:>16.1}",
+            row["algorithm"].as_str().unwrap_or_default(),
+            row["compressed_bytes"].as_u64().unwrap_or_default(),
+            row["ratio"].as_f64().unwrap_or_default(),
+            row["compress_us"].as_f64().unwrap_or_default(),
+            row["decompress_us"].as_f64().unwrap_or_default(),
+        );
+    }
+
+    Ok(())
+}
+
+fn cmd_analyze(
+    input: Option<String>,
+    file: Option<PathBuf>,
+    json_output: bool,
+) -> anyhow::Result<()> {
+    if let Some(dir) = input
+        .as_deref()
+        .filter(|i| std::path::Path::new(i).is_dir())
+    {
+        return cmd_analyze_corpus(dir, json_output);
+    }
+
     let content = read_input(input, file)?;
     let engine = CodecEngine::new();
     let analysis = engine.analyze(&content);
@@ -403,6 +922,98 @@ fn cmd_analyze(input: Option<String>, file: Option<PathBuf>) -> anyhow::Result<(
     Ok(())
 }
 
+fn cmd_analyze_corpus(dir: &str, json_output: bool) -> anyhow::Result<()> {
+    let report = CodecEngine::new().analyze_corpus([dir])?;
+
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+
+    println!(
+        "Corpus: {} payloads, {} bytes ({} files, {} skipped)",
+        report.payloads, report.original_bytes, report.files, report.skipped
+    );
+    println!();
+    println!(
+        "{:<14} {:>12} {:>9} {:>8} {:>9} {:>14}",
+        "Algorithm", "Wire bytes", "Savings", "Failed", "Expanded", "Compress (us)"
+    );
+    println!("{}", "-".repeat(71));
+    for row in &report.algorithms {
+        println!(
+            "{:<14} {:>12} {:>8.1}% {:>8} {:>9} {:>14.1}",
+            format!("{:?}", row.algorithm),
+            row.wire_bytes,
+            row.savings_percent,
+            row.failed,
+            row.expanded,
+            row.mean_compress_us,
+        );
+    }
+    println!();
+    println!("Automatic selection:");
+    for (label, bytes) in [
+        ("Current thresholds", report.current_wire_bytes),
+        ("Fitted thresholds", report.fitted_wire_bytes),
+        ("Best per payload", report.best_wire_bytes),
+    ] {
+        println!(
+            "  {label:<20} {bytes:>12} bytes ({:.1}% saved)",
+            report.savings_percent(bytes)
+        );
+    }
+    println!();
+    println!("Fitted Thresholds:");
+    println!(
+        "  min_compress_bytes   = {}",
+        report.thresholds.min_compress_bytes
+    );
+    println!(
+        "  brotli_threshold     = {}",
+        report.thresholds.brotli_threshold
+    );
+    println!(
+        "  repetition_threshold = {}",
+        report.thresholds.repetition_threshold
+    );
+    println!();
+    println!("Recommended [compression] config:");
+    print!("{}", toml::to_string(&report.recommended)?);
+
+    Ok(())
+}
+
+fn cmd_report(
+    audit: Option<PathBuf>,
+    period: &str,
+    format: &str,
+    tenant: Option<String>,
+    output: Option<PathBuf>,
+) -> anyhow::Result<()> {
+    let period: ReportPeriod = period.parse()?;
+    let format: ReportFormat = format.parse()?;
+    let log = read_input(None, audit)?;
+
+    let mut records = Vec::new();
+    for (n, line) in log.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let record: AuditRecord = serde_json::from_str(line)
+            .map_err(|e| anyhow::anyhow!("Invalid audit record on line {}: {e}", n + 1))?;
+        if let Some(usage) = UsageRecord::from_audit(&record) {
+            records.push(match &tenant {
+                Some(tenant) => usage.with_tenant(tenant.as_str()),
+                None => usage,
+            });
+        }
+    }
+
+    let report = SavingsReport::build(period, records);
+    write_output(output, report.render(format).trim_end())
+}
+
 fn cmd_models(action: Option<ModelsAction>) -> anyhow::Result<()> {
     let registry = ModelRegistry::new();
 
@@ -497,17 +1108,64 @@ fn cmd_server(
     blocking: bool,
     threshold: f32,
     no_security: bool,
+    no_ml_scan: bool,
+    ml_weight: f32,
     model: Option<PathBuf>,
+    model_accuracy_floor: Option<f32>,
+    session_store: Option<PathBuf>,
+    redis_url: Option<String>,
+    session_cache_ttl_ms: u64,
+    stats_store: Option<PathBuf>,
+    stats_epsilon: Option<f64>,
+    audit: Option<String>,
+    audit_redaction: &str,
+    admin_token: Option<String>,
+    auth_psk_file: Option<PathBuf>,
+    auth_token_file: Option<PathBuf>,
+    validate_schema: bool,
+    remove_defaults: bool,
+    keep_default: Vec<String>,
+    relay: bool,
+    quarantine: Option<PathBuf>,
+    quarantine_webhook: Option<String>,
+    codec_concurrency: Option<usize>,
+    codec_queue: usize,
+    codec_deadline_ms: u64,
+    max_compress_latency_ms: Option<u64>,
+    session_timeout: u64,
+    max_missed_pongs: u32,
     verbose: bool,
 ) -> anyhow::Result<()> {
     // Initialize logging
     let log_level = if verbose { "debug" } else { "info" };
-    tracing_subscriber::fmt()
-        .with_env_filter(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new(log_level)),
-        )
+    let filter = || {
+        tracing_subscriber::EnvFilter::try_from_default_env()
+            .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new(log_level))
+    };
+    // The console layer needs task events, which the filter would drop
+    #[cfg(feature = "console")]
+    let console = Some(m2m::runtime::console_layer());
+    #[cfg(not(feature = "console"))]
+    let console = None::<tracing_subscriber::layer::Identity>;
+    // Spans go to a collector only when one is configured
+    #[cfg(feature = "otlp")]
+    let (otlp, _otlp_guard) = if std::env::var_os("OTEL_EXPORTER_OTLP_ENDPOINT").is_some()
+        || std::env::var_os("OTEL_EXPORTER_OTLP_TRACES_ENDPOINT").is_some()
+    {
+        let (layer, guard) = m2m::runtime::otlp_layer("m2m-server")?;
+        (Some(layer.with_filter(filter())), Some(guard))
+    } else {
+        (None, None)
+    };
+    #[cfg(not(feature = "otlp"))]
+    let otlp = None::<tracing_subscriber::layer::Identity>;
+    tracing_subscriber::registry()
+        .with(console)
+        .with(otlp)
+        .with(tracing_subscriber::fmt::layer().with_filter(filter()))
         .init();
+    #[cfg(feature = "console")]
+    tracing::info!("tokio-console enabled");
 
     // Build config
     let mut config = ServerConfig::default().with_port(port);
@@ -528,10 +1186,106 @@ fn cmd_server(
     if let Some(path) = model {
         config = config.with_model(&path.to_string_lossy());
     }
+    if let Some(floor) = model_accuracy_floor {
+        config = config.with_model_accuracy_floor(floor);
+    }
+    config = config.with_ml_weight(ml_weight);
+    if no_ml_scan {
+        config = config.without_ml_security();
+    }
+
+    if let Some(path) = session_store {
+        config = config.with_session_store(path);
+    }
+
+    if let Some(url) = redis_url {
+        config = config.with_cluster(
+            ClusterConfig::new(url)
+                .with_cache_ttl(std::time::Duration::from_millis(session_cache_ttl_ms)),
+        );
+    }
+
+    if let Some(path) = stats_store {
+        config = config.with_stats_store(path);
+    }
+
+    if let Some(epsilon) = stats_epsilon {
+        config = config.with_stats_privacy(StatsPrivacy::new(epsilon)?);
+    }
+
+    if let Some(target) = audit {
+        let redaction: RedactionLevel = audit_redaction.parse()?;
+        config = config
+            .with_audit(AuditConfig::new(AuditTarget::parse(&target)).with_redaction(redaction));
+    }
+
+    if let Some(token) = admin_token.or_else(|| std::env::var("M2M_ADMIN_TOKEN").ok()) {
+        if token.is_empty() {
+            anyhow::bail!("Admin token must not be empty (--admin-token or M2M_ADMIN_TOKEN)");
+        }
+        config = config.with_admin_token(token);
+    }
+
+    let auth_psk_file =
+        auth_psk_file.or_else(|| std::env::var_os("M2M_AUTH_PSK_FILE").map(PathBuf::from));
+    let auth_token_file =
+        auth_token_file.or_else(|| std::env::var_os("M2M_AUTH_TOKEN_FILE").map(PathBuf::from));
+    if auth_psk_file.is_some() || auth_token_file.is_some() {
+        #[cfg(feature = "crypto")]
+        {
+            let mut authenticator = m2m::protocol::HelloAuthenticator::new();
+            if let Some(path) = auth_psk_file {
+                authenticator = authenticator.with_psk_file(path)?;
+            }
+            if let Some(path) = auth_token_file {
+                authenticator = authenticator.with_token_file(path)?;
+            }
+            config = config.with_authenticator(Arc::new(authenticator));
+        }
+        #[cfg(not(feature = "crypto"))]
+        anyhow::bail!("HELLO authentication requires the crypto feature");
+    }
+
+    if validate_schema {
+        config = config.with_schema_validation();
+    }
+
+    if remove_defaults {
+        config =
+            config.with_remove_defaults(DefaultsNormalizer::new().with_kept_keys(keep_default));
+    }
+
+    if relay {
+        config = config.with_relay();
+    }
+
+    if let Some(dir) = quarantine {
+        let key = std::env::var("M2M_QUARANTINE_KEY")
+            .map_err(|_| anyhow::anyhow!("--quarantine requires M2M_QUARANTINE_KEY (hex)"))?;
+        let key = KeyMaterial::from_hex(&key)
+            .map_err(|e| anyhow::anyhow!("Invalid M2M_QUARANTINE_KEY: {e}"))?;
+        let mut quarantine = QuarantineConfig::new(key).with_dir(dir);
+        if let Some(url) = quarantine_webhook {
+            quarantine = quarantine.with_webhook(url);
+        }
+        config = config.with_quarantine(quarantine);
+    }
+
+    config.codec_concurrency = codec_concurrency;
+    config.codec_queue_depth = codec_queue;
+    config.codec_deadline = std::time::Duration::from_millis(codec_deadline_ms);
+    if let Some(ms) = max_compress_latency_ms {
+        config = config.with_max_compress_latency(std::time::Duration::from_millis(ms));
+    }
+    config = config
+        .with_session_timeout(std::time::Duration::from_secs(session_timeout))
+        .with_max_missed_pongs(max_missed_pongs);
+
+    config.validate()?;
 
     // Create state and router
     let state = Arc::new(AppState::new(config.clone()));
-    let app = create_router(state);
+    let app = create_router(state.clone());
 
     // Start server
     tracing::info!("Starting M2M Protocol server on {}", config.addr);
@@ -549,6 +1303,12 @@ fn cmd_server(
 
     let runtime = tokio::runtime::Runtime::new()?;
     runtime.block_on(async {
+        if config.session_store_path.is_some() {
+            let restored = state.sessions.restore().await?;
+            tracing::info!("Restored {} persisted sessions", restored);
+        }
+        state.spawn_liveness();
+
         let listener = tokio::net::TcpListener::bind(config.addr).await?;
         axum::serve(listener, app).await?;
         Ok::<_, anyhow::Error>(())
@@ -575,6 +1335,26 @@ fn read_input(input: Option<String>, file: Option<PathBuf>) -> anyhow::Result<St
     }
 }
 
+fn read_input_bytes(input: Option<String>, file: Option<PathBuf>) -> anyhow::Result<Vec<u8>> {
+    match (file, input) {
+        (Some(path), _) => Ok(std::fs::read(path)?),
+        (None, Some(s)) if s != "-" => Ok(s.into_bytes()),
+        _ => {
+            let mut buffer = Vec::new();
+            io::stdin().read_to_end(&mut buffer)?;
+            Ok(buffer)
+        },
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    use std::fmt::Write;
+    bytes.iter().fold(String::new(), |mut s, b| {
+        let _ = write!(s, "{b:02x}");
+        s
+    })
+}
+
 fn write_output(output: Option<PathBuf>, content: &str) -> anyhow::Result<()> {
     if let Some(path) = output {
         std::fs::write(path, content)?;
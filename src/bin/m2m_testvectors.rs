@@ -0,0 +1,90 @@
+//! Conformance test vector generator.
+//!
+//! Writes the [`m2m::testvectors`] document as JSON, or checks an existing
+//! fixture against this build.
+//!
+//! Run with:
+//!
+//! ```bash
+//! cargo run --bin m2m-testvectors --features codecs,crypto -- --out tests/vectors/m2m-3.0.json
+//! cargo run --bin m2m-testvectors --features codecs,crypto -- --check tests/vectors/m2m-3.0.json
+//! ```
+
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use clap::Parser;
+use m2m::testvectors::{self, TestVectors};
+
+#[derive(Parser)]
+#[command(name = "m2m-testvectors")]
+#[command(about = "Generate or check M2M protocol conformance test vectors")]
+struct Cli {
+    /// Write the vectors to a file instead of stdout
+    #[arg(short, long, conflicts_with = "check")]
+    out: Option<PathBuf>,
+
+    /// Check an existing vector file against this build
+    #[arg(long)]
+    check: Option<PathBuf>,
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let result = match cli.check {
+        Some(path) => check(&path),
+        None => generate(cli.out),
+    };
+
+    match result {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("Error: {e}");
+            ExitCode::FAILURE
+        },
+    }
+}
+
+fn generate(out: Option<PathBuf>) -> Result<ExitCode, Box<dyn std::error::Error>> {
+    let vectors = testvectors::generate()?;
+    let json = serde_json::to_string_pretty(&vectors)? + "\n";
+
+    match out {
+        Some(path) => {
+            std::fs::write(&path, json)?;
+            eprintln!(
+                "Wrote {} handshake, {} frame and {} key derivation vectors to {}",
+                vectors.handshake.len(),
+                vectors.frames.len(),
+                vectors.key_derivation.len(),
+                path.display()
+            );
+        },
+        None => print!("{json}"),
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn check(path: &PathBuf) -> Result<ExitCode, Box<dyn std::error::Error>> {
+    let vectors: TestVectors = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+    let report = testvectors::verify(&vectors);
+
+    for name in &report.skipped {
+        println!("SKIP {name}");
+    }
+    for (name, reason) in &report.failed {
+        println!("FAIL {name}: {reason}");
+    }
+    println!(
+        "{} passed, {} failed, {} skipped",
+        report.passed.len(),
+        report.failed.len(),
+        report.skipped.len()
+    );
+
+    Ok(if report.is_ok() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
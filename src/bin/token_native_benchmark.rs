@@ -115,7 +115,7 @@ fn main() {
         let wire = result.compressed_bytes;
 
         // Raw bytes (without base64 overhead)
-        let raw = codec.compress_raw(&content);
+        let raw = codec.compress_raw(&content).unwrap();
         let raw_len = raw.len();
 
         // Verify roundtrip
@@ -0,0 +1,466 @@
+//! Typed async client.
+//!
+//! [`M2MClient`] wraps the HTTP calls an agent would otherwise assemble by
+//! hand: the HELLO/ACCEPT handshake, compressing requests, decompressing
+//! replies, retries with backoff, and mapping REJECTs to [`M2MError`]s.
+//!
+//! It talks to two kinds of endpoint:
+//!
+//! - **M2M server** ([`M2MClient::new`]): requests go out as compressed DATA
+//!   on `/message` over a lazily established session. A session the server
+//!   has dropped is renegotiated transparently.
+//! - **OpenAI-compatible endpoint** ([`M2MClient::openai`]): requests are
+//!   posted to `/v1/chat/completions`. Replies and SSE streams in M2M wire
+//!   format (abbreviated keys, TokenNative) are expanded before parsing.
+//!
+//! Network errors, 429 and 5xx responses, and REJECTs for rate limiting
+//! are retried with exponential backoff; security REJECTs surface as
+//! [`M2MError::ContentBlocked`] and are never retried.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use futures::StreamExt;
+//! use m2m::client::{ChatMessage, ChatRequest, M2MClient};
+//!
+//! let client = M2MClient::new("http://localhost:8080");
+//! let request = ChatRequest::new("gpt-4o").with_message(ChatMessage::user("Hello"));
+//!
+//! let response = client.chat(&request).await?;
+//! println!("{}", response.content().unwrap_or_default());
+//!
+//! let mut stream = client.chat_stream(&request).await?;
+//! while let Some(chunk) = stream.next().await {
+//!     print!("{}", chunk?.content().unwrap_or_default());
+//! }
+//! ```
+
+mod types;
+
+pub use types::{
+    ChatChoice, ChatChunk, ChatDelta, ChatMessage, ChatRequest, ChatResponse, ChunkChoice, Usage,
+};
+
+use std::collections::VecDeque;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+use bytes::Bytes;
+use futures::stream::{self, BoxStream, StreamExt};
+use reqwest::StatusCode;
+use tokio::sync::Mutex;
+
+use crate::codec::{CodecEngine, StreamingDecompressor};
+use crate::error::{M2MError, Result};
+use crate::protocol::{Capabilities, Message, MessageType, RejectionCode, Session};
+
+/// Default retries after the first attempt
+pub const DEFAULT_RETRIES: u32 = 3;
+
+/// Default delay before the first retry (doubles on each retry)
+pub const DEFAULT_RETRY_BACKOFF: Duration = Duration::from_millis(200);
+
+/// Default per-request timeout
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Stream of completion chunks
+pub type ChatStream = BoxStream<'static, Result<ChatChunk>>;
+
+/// Kind of endpoint the client talks to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Endpoint {
+    /// M2M server: compressed DATA on `/message`
+    M2M,
+    /// OpenAI-compatible `/v1/chat/completions`
+    OpenAI,
+}
+
+/// Typed async client for M2M servers and OpenAI-compatible endpoints
+///
+/// Cloning is cheap; clones share the HTTP connection pool and session.
+#[derive(Clone)]
+pub struct M2MClient {
+    /// HTTP client
+    http: reqwest::Client,
+    /// Base URL without trailing slash
+    base_url: String,
+    /// Endpoint kind
+    endpoint: Endpoint,
+    /// Bearer token for OpenAI-compatible endpoints
+    api_key: Option<String>,
+    /// Capabilities offered in HELLO
+    capabilities: Capabilities,
+    /// Retries after the first attempt
+    retries: u32,
+    /// Delay before the first retry
+    backoff: Duration,
+    /// Per-request timeout
+    timeout: Duration,
+    /// Established session (M2M endpoints)
+    session: Arc<Mutex<Option<Session>>>,
+}
+
+impl M2MClient {
+    /// Client for an M2M server
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self::with_endpoint(base_url, Endpoint::M2M)
+    }
+
+    /// Client for an OpenAI-compatible endpoint
+    pub fn openai(base_url: impl Into<String>) -> Self {
+        Self::with_endpoint(base_url, Endpoint::OpenAI)
+    }
+
+    fn with_endpoint(base_url: impl Into<String>, endpoint: Endpoint) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            endpoint,
+            api_key: None,
+            capabilities: Capabilities::default(),
+            retries: DEFAULT_RETRIES,
+            backoff: DEFAULT_RETRY_BACKOFF,
+            timeout: DEFAULT_TIMEOUT,
+            session: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Set the bearer token sent to OpenAI-compatible endpoints
+    pub fn with_api_key(mut self, key: impl Into<String>) -> Self {
+        self.api_key = Some(key.into());
+        self
+    }
+
+    /// Set capabilities offered in HELLO
+    pub fn with_capabilities(mut self, capabilities: Capabilities) -> Self {
+        self.capabilities = capabilities;
+        self
+    }
+
+    /// Set retries after the first attempt (default: 3)
+    pub fn with_retries(mut self, retries: u32) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// Set delay before the first retry (default: 200ms, doubling)
+    pub fn with_retry_backoff(mut self, backoff: Duration) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Set per-request timeout (default: 60s)
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Use a preconfigured HTTP client (proxies, TLS roots, ...)
+    pub fn with_http_client(mut self, http: reqwest::Client) -> Self {
+        self.http = http;
+        self
+    }
+
+    /// Endpoint kind
+    pub fn endpoint(&self) -> Endpoint {
+        self.endpoint
+    }
+
+    /// Current session ID, if a session is established
+    pub async fn session_id(&self) -> Option<String> {
+        let session = self.session.lock().await;
+        session.as_ref().map(|s| s.id().to_string())
+    }
+
+    /// Send a chat completion request
+    pub async fn chat(&self, request: &ChatRequest) -> Result<ChatResponse> {
+        let mut request = request.clone();
+        request.stream = None;
+        let body = serde_json::to_string(&request)?;
+
+        let reply = match self.endpoint {
+            Endpoint::M2M => self.send(&body).await?,
+            Endpoint::OpenAI => {
+                self.retry(|| async {
+                    let response = self.post_completions(&body).await?;
+                    Ok(response.text().await?)
+                })
+                .await?
+            },
+        };
+        parse_response(&reply)
+    }
+
+    /// Send a chat completion request and stream the reply
+    ///
+    /// OpenAI-compatible endpoints stream server-sent events; M2M servers
+    /// answer a whole message, which is yielded as a single chunk.
+    pub async fn chat_stream(&self, request: &ChatRequest) -> Result<ChatStream> {
+        if self.endpoint == Endpoint::M2M {
+            let response = self.chat(request).await?;
+            return Ok(stream::once(async { Ok(ChatChunk::from(response)) }).boxed());
+        }
+
+        let mut request = request.clone();
+        request.stream = Some(true);
+        let body = serde_json::to_string(&request)?;
+
+        let response = self.retry(|| self.post_completions(&body)).await?;
+        let events = SseEvents {
+            body: response.bytes_stream().boxed(),
+            buffer: Vec::new(),
+            pending: VecDeque::new(),
+            decompressor: StreamingDecompressor::new(),
+            done: false,
+        };
+        Ok(stream::unfold(events, SseEvents::next).boxed())
+    }
+
+    /// Send content over the M2M session and return the decompressed reply
+    ///
+    /// Establishes the session on first use.
+    pub async fn send(&self, content: &str) -> Result<String> {
+        self.retry(|| self.send_once(content)).await
+    }
+
+    /// Close the session, if one is established
+    pub async fn close(&self) -> Result<()> {
+        let mut guard = self.session.lock().await;
+        if let Some(mut session) = guard.take() {
+            self.post_message(&session.close()).await?;
+        }
+        Ok(())
+    }
+
+    /// One attempt at [`send`](Self::send), renegotiating a dropped session
+    async fn send_once(&self, content: &str) -> Result<String> {
+        let mut guard = self.session.lock().await;
+
+        for _ in 0..2 {
+            if !guard.as_ref().is_some_and(Session::is_established) {
+                *guard = Some(self.handshake().await?);
+            }
+            let Some(session) = guard.as_mut() else {
+                continue;
+            };
+
+            let data = session.compress(content)?;
+            let (status, reply) = self.post_message(&data).await?;
+            if status == StatusCode::NOT_FOUND {
+                // Server dropped the session (expiry, restart)
+                *guard = None;
+                continue;
+            }
+
+            return match reply.msg_type {
+                MessageType::Data => session.decompress(&reply),
+                MessageType::Reject => Err(rejection_error(&reply)),
+                other => Err(M2MError::Protocol(format!(
+                    "Unexpected {other:?} in reply to DATA"
+                ))),
+            };
+        }
+        Err(M2MError::SessionExpired)
+    }
+
+    /// HELLO/ACCEPT handshake
+    async fn handshake(&self) -> Result<Session> {
+        let mut session = Session::new(self.capabilities.clone());
+        let hello = session.create_hello();
+        let (_, reply) = self.post_message(&hello).await?;
+
+        match reply.msg_type {
+            MessageType::Accept => {
+                session.process_accept(&reply)?;
+                Ok(session)
+            },
+            MessageType::Reject => match reply.get_rejection().map(|r| r.code) {
+                Some(RejectionCode::SecurityPolicy | RejectionCode::RateLimited) => {
+                    Err(rejection_error(&reply))
+                },
+                _ => session.process_reject(&reply).map(|()| session),
+            },
+            other => Err(M2MError::Protocol(format!(
+                "Unexpected {other:?} in reply to HELLO"
+            ))),
+        }
+    }
+
+    /// POST a message to `/message`
+    async fn post_message(&self, message: &Message) -> Result<(StatusCode, Message)> {
+        let response = self
+            .http
+            .post(format!("{}/message", self.base_url))
+            .timeout(self.timeout)
+            .json(message)
+            .send()
+            .await?;
+
+        let status = response.status();
+        let body = response.text().await?;
+        match serde_json::from_str::<Message>(&body) {
+            Ok(reply) => Ok((status, reply)),
+            Err(_) => Err(status_error(status, &body)),
+        }
+    }
+
+    /// POST a request body to `/v1/chat/completions`
+    async fn post_completions(&self, body: &str) -> Result<reqwest::Response> {
+        let mut request = self
+            .http
+            .post(format!("{}/v1/chat/completions", self.base_url))
+            .timeout(self.timeout)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(body.to_string());
+        if let Some(key) = &self.api_key {
+            request = request.bearer_auth(key);
+        }
+
+        let response = request.send().await?;
+        let status = response.status();
+        if status.is_success() {
+            Ok(response)
+        } else {
+            let body = response.text().await.unwrap_or_default();
+            Err(status_error(status, &body))
+        }
+    }
+
+    /// Run `op`, retrying retryable errors with exponential backoff
+    async fn retry<T, F, Fut>(&self, mut op: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let mut attempt = 0;
+        loop {
+            match op().await {
+                Err(e) if e.is_retryable() && attempt < self.retries => {
+                    tracing::debug!(attempt, error = %e, "Retrying request");
+                    tokio::time::sleep(self.backoff.saturating_mul(1 << attempt.min(16))).await;
+                    attempt += 1;
+                },
+                result => return result,
+            }
+        }
+    }
+}
+
+impl std::fmt::Debug for M2MClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("M2MClient")
+            .field("base_url", &self.base_url)
+            .field("endpoint", &self.endpoint)
+            .field("retries", &self.retries)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Parse a completion, expanding M2M wire format first
+fn parse_response(body: &str) -> Result<ChatResponse> {
+    if crate::is_m2m_format(body) {
+        let json = CodecEngine::new().decompress(body)?;
+        return Ok(serde_json::from_str(&json)?);
+    }
+    Ok(serde_json::from_str(body)?)
+}
+
+/// Error for a REJECT reply
+fn rejection_error(reply: &Message) -> M2MError {
+    match reply.get_rejection() {
+        Some(r) if r.code == RejectionCode::SecurityPolicy => {
+            M2MError::ContentBlocked(r.message.clone())
+        },
+        Some(r) if r.code == RejectionCode::RateLimited => M2MError::Overloaded(r.message.clone()),
+        Some(r) => M2MError::Protocol(format!("{:?}: {}", r.code, r.message)),
+        None => M2MError::Protocol("REJECT without rejection info".to_string()),
+    }
+}
+
+/// Error for a non-success HTTP status
+fn status_error(status: StatusCode, body: &str) -> M2MError {
+    let message = format!(
+        "HTTP {status}: {}",
+        body.chars().take(200).collect::<String>()
+    );
+    match status {
+        StatusCode::TOO_MANY_REQUESTS | StatusCode::SERVICE_UNAVAILABLE => {
+            M2MError::Overloaded(message)
+        },
+        StatusCode::FORBIDDEN => M2MError::ContentBlocked(message),
+        s if s.is_server_error() => M2MError::Upstream(message),
+        _ => M2MError::Protocol(message),
+    }
+}
+
+/// Server-sent events parsed into chunks
+struct SseEvents {
+    /// Response body
+    body: BoxStream<'static, reqwest::Result<Bytes>>,
+    /// Bytes of the incomplete event
+    buffer: Vec<u8>,
+    /// Parsed chunks not yet yielded
+    pending: VecDeque<Result<ChatChunk>>,
+    /// Expands abbreviated or TokenNative events
+    decompressor: StreamingDecompressor,
+    /// `[DONE]` seen or body ended
+    done: bool,
+}
+
+impl SseEvents {
+    async fn next(mut self) -> Option<(Result<ChatChunk>, Self)> {
+        loop {
+            if let Some(item) = self.pending.pop_front() {
+                return Some((item, self));
+            }
+            if self.done {
+                return None;
+            }
+
+            match self.body.next().await {
+                Some(Ok(bytes)) => {
+                    self.buffer.extend_from_slice(&bytes);
+                    while let Some(end) = self.buffer.windows(2).position(|w| w == b"\n\n") {
+                        let event: Vec<u8> = self.buffer.drain(..end + 2).collect();
+                        self.parse_event(&event);
+                    }
+                },
+                Some(Err(e)) => {
+                    self.done = true;
+                    return Some((Err(e.into()), self));
+                },
+                None => {
+                    let rest = std::mem::take(&mut self.buffer);
+                    self.parse_event(&rest);
+                    self.done = true;
+                },
+            }
+        }
+    }
+
+    fn parse_event(&mut self, event: &[u8]) {
+        if self.done || event.iter().all(u8::is_ascii_whitespace) {
+            return;
+        }
+        let expanded = match self.decompressor.decompress_chunk(event) {
+            Ok(expanded) => expanded,
+            Err(e) => {
+                self.pending.push_back(Err(e));
+                return;
+            },
+        };
+
+        for line in String::from_utf8_lossy(&expanded).lines() {
+            let Some(data) = line.strip_prefix("data:").map(str::trim) else {
+                continue;
+            };
+            if data == "[DONE]" {
+                self.done = true;
+                return;
+            }
+            self.pending
+                .push_back(serde_json::from_str(data).map_err(M2MError::from));
+        }
+    }
+}
@@ -0,0 +1,227 @@
+//! OpenAI-compatible chat types.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// Chat completion request
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChatRequest {
+    /// Model name
+    pub model: String,
+    /// Conversation so far
+    pub messages: Vec<ChatMessage>,
+    /// Sampling temperature
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    /// Maximum completion tokens
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+    /// Stream the response as server-sent events
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stream: Option<bool>,
+    /// Other request fields (tools, response_format, ...)
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl ChatRequest {
+    /// Create a request for a model
+    pub fn new(model: impl Into<String>) -> Self {
+        Self {
+            model: model.into(),
+            messages: Vec::new(),
+            temperature: None,
+            max_tokens: None,
+            stream: None,
+            extra: Map::new(),
+        }
+    }
+
+    /// Append a message
+    pub fn with_message(mut self, message: ChatMessage) -> Self {
+        self.messages.push(message);
+        self
+    }
+
+    /// Set sampling temperature
+    pub fn with_temperature(mut self, temperature: f32) -> Self {
+        self.temperature = Some(temperature);
+        self
+    }
+
+    /// Set maximum completion tokens
+    pub fn with_max_tokens(mut self, max_tokens: u32) -> Self {
+        self.max_tokens = Some(max_tokens);
+        self
+    }
+
+    /// Set any other request field
+    pub fn with_field(mut self, key: &str, value: Value) -> Self {
+        self.extra.insert(key.to_string(), value);
+        self
+    }
+}
+
+/// A chat message
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChatMessage {
+    /// Author role (`system`, `user`, `assistant`, `tool`)
+    pub role: String,
+    /// Text content (absent on pure tool-call messages)
+    #[serde(default)]
+    pub content: Option<String>,
+    /// Other message fields (name, tool_calls, tool_call_id, ...)
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl ChatMessage {
+    /// Message with a role and text content
+    pub fn new(role: impl Into<String>, content: impl Into<String>) -> Self {
+        Self {
+            role: role.into(),
+            content: Some(content.into()),
+            extra: Map::new(),
+        }
+    }
+
+    /// System message
+    pub fn system(content: impl Into<String>) -> Self {
+        Self::new("system", content)
+    }
+
+    /// User message
+    pub fn user(content: impl Into<String>) -> Self {
+        Self::new("user", content)
+    }
+
+    /// Assistant message
+    pub fn assistant(content: impl Into<String>) -> Self {
+        Self::new("assistant", content)
+    }
+}
+
+/// Chat completion response
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChatResponse {
+    /// Completion ID
+    #[serde(default)]
+    pub id: String,
+    /// Object type (`chat.completion`)
+    #[serde(default)]
+    pub object: String,
+    /// Creation time (unix seconds)
+    #[serde(default)]
+    pub created: u64,
+    /// Model that produced the completion
+    #[serde(default)]
+    pub model: String,
+    /// Completion choices
+    pub choices: Vec<ChatChoice>,
+    /// Token usage
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<Usage>,
+}
+
+impl ChatResponse {
+    /// Text of the first choice, if any
+    pub fn content(&self) -> Option<&str> {
+        self.choices.first()?.message.content.as_deref()
+    }
+}
+
+/// One completion choice
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChatChoice {
+    /// Choice index
+    #[serde(default)]
+    pub index: u32,
+    /// Generated message
+    pub message: ChatMessage,
+    /// Why generation stopped
+    #[serde(default)]
+    pub finish_reason: Option<String>,
+}
+
+/// Token usage
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Usage {
+    /// Prompt tokens
+    pub prompt_tokens: u32,
+    /// Completion tokens
+    pub completion_tokens: u32,
+    /// Total tokens
+    pub total_tokens: u32,
+}
+
+/// Streamed completion chunk
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChatChunk {
+    /// Completion ID
+    #[serde(default)]
+    pub id: String,
+    /// Model producing the stream
+    #[serde(default)]
+    pub model: String,
+    /// Per-choice deltas
+    #[serde(default)]
+    pub choices: Vec<ChunkChoice>,
+    /// Token usage (final chunk, when requested)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<Usage>,
+}
+
+impl ChatChunk {
+    /// Text delta of the first choice, if any
+    pub fn content(&self) -> Option<&str> {
+        self.choices.first()?.delta.content.as_deref()
+    }
+}
+
+/// Delta for one choice in a [`ChatChunk`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChunkChoice {
+    /// Choice index
+    #[serde(default)]
+    pub index: u32,
+    /// New content
+    #[serde(default)]
+    pub delta: ChatDelta,
+    /// Why generation stopped (last chunk of the choice)
+    #[serde(default)]
+    pub finish_reason: Option<String>,
+}
+
+/// Incremental message content
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ChatDelta {
+    /// Role (first chunk only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub role: Option<String>,
+    /// Content fragment
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+}
+
+impl From<ChatResponse> for ChatChunk {
+    /// A whole response as one chunk (for endpoints that do not stream)
+    fn from(response: ChatResponse) -> Self {
+        Self {
+            id: response.id,
+            model: response.model,
+            choices: response
+                .choices
+                .into_iter()
+                .map(|choice| ChunkChoice {
+                    index: choice.index,
+                    delta: ChatDelta {
+                        role: Some(choice.message.role),
+                        content: choice.message.content,
+                    },
+                    finish_reason: choice.finish_reason,
+                })
+                .collect(),
+            usage: response.usage,
+        }
+    }
+}
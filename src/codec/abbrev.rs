@@ -0,0 +1,303 @@
+//! Runtime-extensible abbreviation tables.
+//!
+//! The built-in [`KEY_ABBREV`] and [`MODEL_ABBREV`] tables are compiled in.
+//! An [`AbbreviationTable`] layers custom entries on top of them: keys used
+//! by an organisation's payloads, or fine-tuned model names, loaded from a
+//! TOML file or the model registry.
+//!
+//! Both agents must expand with the same table. Each table has a
+//! [`version`](AbbreviationTable::version) derived from its custom entries,
+//! which sessions negotiate through the
+//! [`AbbreviationTables`](crate::protocol::AbbreviationTables) extension;
+//! agents without a common version fall back to the built-in table.
+//!
+//! # TOML Format
+//!
+//! ```toml
+//! [keys]
+//! retrieval_context = "rc"
+//!
+//! [models]
+//! "acme/support-ft-v3" = "asf3"
+//! ```
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use super::tables::{KEY_ABBREV, KEY_EXPAND, MODEL_ABBREV, MODEL_EXPAND};
+use crate::error::{M2MError, Result};
+use crate::models::ModelRegistry;
+
+/// Version of the table without custom entries
+pub const BUILTIN_TABLE_VERSION: &str = "builtin";
+
+/// Custom entries as read from TOML
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TableFile {
+    #[serde(default)]
+    keys: BTreeMap<String, String>,
+    #[serde(default)]
+    models: BTreeMap<String, String>,
+}
+
+/// Abbreviations for one kind of value (keys or model names)
+#[derive(Debug, Clone, Default)]
+struct Entries {
+    /// Full form -> abbreviation
+    abbrev: BTreeMap<String, String>,
+    /// Abbreviation -> full form
+    expand: BTreeMap<String, String>,
+}
+
+impl Entries {
+    /// Add an entry unless it clashes with the built-in or custom entries
+    fn insert(
+        &mut self,
+        kind: &str,
+        builtin: (&phf::Map<&str, &str>, &phf::Map<&str, &str>),
+        full: String,
+        abbrev: String,
+    ) -> Result<()> {
+        let (builtin_abbrev, builtin_expand) = builtin;
+        if full.is_empty() || abbrev.is_empty() {
+            return Err(M2MError::Config(format!("Empty {kind} abbreviation")));
+        }
+        if builtin_abbrev.contains_key(full.as_str()) || self.abbrev.contains_key(&full) {
+            return Err(M2MError::Config(format!(
+                "{kind} '{full}' already has an abbreviation"
+            )));
+        }
+        let taken = builtin_expand.contains_key(abbrev.as_str())
+            || builtin_abbrev.contains_key(abbrev.as_str())
+            || self.expand.contains_key(&abbrev)
+            || self.abbrev.contains_key(&abbrev);
+        if taken {
+            return Err(M2MError::Config(format!(
+                "{kind} abbreviation '{abbrev}' is already in use"
+            )));
+        }
+
+        self.expand.insert(abbrev.clone(), full.clone());
+        self.abbrev.insert(full, abbrev);
+        Ok(())
+    }
+}
+
+/// Built-in abbreviations plus custom entries
+///
+/// # Example
+///
+/// ```
+/// use m2m::codec::AbbreviationTable;
+///
+/// let table = AbbreviationTable::builtin()
+///     .with_key("retrieval_context", "rc")
+///     .unwrap();
+/// assert_eq!(table.abbreviate_key("retrieval_context"), Some("rc"));
+/// assert_eq!(table.expand_key("rc"), Some("retrieval_context"));
+/// assert_eq!(table.abbreviate_key("model"), Some("M")); // Built-in
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct AbbreviationTable {
+    keys: Entries,
+    models: Entries,
+}
+
+impl AbbreviationTable {
+    /// Table with only the built-in abbreviations
+    pub fn builtin() -> Self {
+        Self::default()
+    }
+
+    /// Parse custom entries from TOML
+    pub fn from_toml(content: &str) -> Result<Self> {
+        let file: TableFile = toml::from_str(content)
+            .map_err(|e| M2MError::Config(format!("Invalid abbreviation table: {e}")))?;
+
+        let mut table = Self::builtin();
+        for (full, abbrev) in file.keys {
+            table = table.with_key(full, abbrev)?;
+        }
+        for (full, abbrev) in file.models {
+            table = table.with_model(full, abbrev)?;
+        }
+        Ok(table)
+    }
+
+    /// Load custom entries from a TOML file
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path).map_err(|e| {
+            M2MError::Config(format!(
+                "Failed to read abbreviation table {}: {e}",
+                path.display()
+            ))
+        })?;
+        Self::from_toml(&content)
+    }
+
+    /// Add a key abbreviation
+    ///
+    /// Fails if the key is already abbreviated or the abbreviation is in use.
+    pub fn with_key(mut self, full: impl Into<String>, abbrev: impl Into<String>) -> Result<Self> {
+        self.keys.insert(
+            "Key",
+            (&KEY_ABBREV, &KEY_EXPAND),
+            full.into(),
+            abbrev.into(),
+        )?;
+        Ok(self)
+    }
+
+    /// Add a model name abbreviation
+    ///
+    /// Fails if the model is already abbreviated or the abbreviation is in use.
+    pub fn with_model(
+        mut self,
+        full: impl Into<String>,
+        abbrev: impl Into<String>,
+    ) -> Result<Self> {
+        self.models.insert(
+            "Model",
+            (&MODEL_ABBREV, &MODEL_EXPAND),
+            full.into(),
+            abbrev.into(),
+        )?;
+        Ok(self)
+    }
+
+    /// Add the abbreviations of all models in a registry (embedded and
+    /// dynamic)
+    ///
+    /// Models are added in ID order; a model whose abbreviation clashes
+    /// with an earlier entry is skipped.
+    pub fn with_registry(mut self, registry: &ModelRegistry) -> Self {
+        let mut cards: Vec<_> = registry.iter().cloned().collect();
+        cards.extend(registry.list_dynamic());
+        cards.sort_by(|a, b| a.id.cmp(&b.id));
+
+        for card in cards {
+            let _ = self.models.insert(
+                "Model",
+                (&MODEL_ABBREV, &MODEL_EXPAND),
+                card.id,
+                card.abbrev,
+            );
+        }
+        self
+    }
+
+    /// Check if the table has no custom entries
+    pub fn is_builtin(&self) -> bool {
+        self.keys.abbrev.is_empty() && self.models.abbrev.is_empty()
+    }
+
+    /// Version identifying the custom entries
+    ///
+    /// [`BUILTIN_TABLE_VERSION`] for a table without custom entries,
+    /// otherwise a checksum of the entries, so agents that loaded the same
+    /// entries agree on the version.
+    pub fn version(&self) -> String {
+        if self.is_builtin() {
+            return BUILTIN_TABLE_VERSION.to_string();
+        }
+
+        let mut hasher = crc32fast::Hasher::new();
+        for (kind, entries) in [("k", &self.keys), ("m", &self.models)] {
+            for (full, abbrev) in &entries.abbrev {
+                hasher.update(format!("{kind}:{full}={abbrev}\n").as_bytes());
+            }
+        }
+        format!("{:08x}", hasher.finalize())
+    }
+
+    /// Abbreviation for a JSON key
+    pub fn abbreviate_key(&self, key: &str) -> Option<&str> {
+        KEY_ABBREV
+            .get(key)
+            .copied()
+            .or_else(|| self.keys.abbrev.get(key).map(String::as_str))
+    }
+
+    /// Full form of an abbreviated JSON key
+    pub fn expand_key(&self, abbrev: &str) -> Option<&str> {
+        KEY_EXPAND
+            .get(abbrev)
+            .copied()
+            .or_else(|| self.keys.expand.get(abbrev).map(String::as_str))
+    }
+
+    /// Abbreviation for a model name
+    pub fn abbreviate_model(&self, model: &str) -> Option<&str> {
+        MODEL_ABBREV
+            .get(model)
+            .copied()
+            .or_else(|| self.models.abbrev.get(model).map(String::as_str))
+    }
+
+    /// Full form of an abbreviated model name
+    pub fn expand_model(&self, abbrev: &str) -> Option<&str> {
+        MODEL_EXPAND
+            .get(abbrev)
+            .copied()
+            .or_else(|| self.models.expand.get(abbrev).map(String::as_str))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TABLE: &str = r#"
+        [keys]
+        retrieval_context = "rc"
+
+        [models]
+        "acme/support-ft-v3" = "asf3"
+    "#;
+
+    #[test]
+    fn test_from_toml() {
+        let table = AbbreviationTable::from_toml(TABLE).unwrap();
+        assert_eq!(table.abbreviate_key("retrieval_context"), Some("rc"));
+        assert_eq!(table.expand_model("asf3"), Some("acme/support-ft-v3"));
+        assert_eq!(table.abbreviate_model("gpt-4o"), Some("g4o"));
+
+        // Same entries, same version
+        let again = AbbreviationTable::builtin()
+            .with_model("acme/support-ft-v3", "asf3")
+            .unwrap()
+            .with_key("retrieval_context", "rc")
+            .unwrap();
+        assert_eq!(table.version(), again.version());
+        assert_ne!(table.version(), BUILTIN_TABLE_VERSION);
+        assert_eq!(
+            AbbreviationTable::builtin().version(),
+            BUILTIN_TABLE_VERSION
+        );
+    }
+
+    #[test]
+    fn test_conflicts_rejected() {
+        let table = AbbreviationTable::builtin();
+        assert!(table.clone().with_key("model", "mdl").is_err()); // Built-in key
+        assert!(table.clone().with_key("context", "M").is_err()); // Built-in abbreviation
+        assert!(table.clone().with_key("context", "content").is_err()); // A full key
+        assert!(table
+            .with_key("a", "b")
+            .and_then(|t| t.with_key("c", "b"))
+            .is_err());
+        assert!(AbbreviationTable::from_toml("[colors]\nred = \"r\"").is_err());
+    }
+
+    #[test]
+    fn test_with_registry() {
+        let registry = ModelRegistry::new();
+        let table = AbbreviationTable::builtin().with_registry(&registry);
+        assert_eq!(table.abbreviate_model("openai/gpt-4o"), Some("og4o"));
+        assert_eq!(table.expand_model("og4o"), Some("openai/gpt-4o"));
+    }
+}
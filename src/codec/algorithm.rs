@@ -2,6 +2,11 @@
 
 use serde::{Deserialize, Serialize};
 
+use crate::error::M2MError;
+
+/// Wire prefix of plugin payloads (followed by `<id>|`)
+const CUSTOM_PREFIX: &str = "#CX|";
+
 /// Available compression algorithms
 ///
 /// M2M Protocol v0.4.0 supports three compression algorithms:
@@ -36,6 +41,14 @@ pub enum Algorithm {
     ///
     /// Wire format: `#M2M[v3.0]|DATA:<base64_brotli>`
     Brotli,
+    /// Codec provided by a registered WASM plugin (`wasm-plugins` feature)
+    ///
+    /// Agents negotiate plugins by ID: both sides list `Custom(id)` in
+    /// their algorithms and register the same module with
+    /// `CodecEngine::with_plugin`. Never advertised by default.
+    ///
+    /// Wire format: `#CX|<id>|<base64_output>`
+    Custom(u16),
 }
 
 impl Algorithm {
@@ -46,6 +59,7 @@ impl Algorithm {
             Algorithm::M2M => "#M2M|1|",
             Algorithm::TokenNative => "#TK|",
             Algorithm::Brotli => "#M2M[v3.0]|DATA:",
+            Algorithm::Custom(_) => CUSTOM_PREFIX,
         }
     }
 
@@ -57,6 +71,9 @@ impl Algorithm {
             Some(Algorithm::TokenNative)
         } else if content.starts_with("#M2M[v3.0]|") {
             Some(Algorithm::Brotli)
+        } else if let Some(rest) = content.strip_prefix(CUSTOM_PREFIX) {
+            let (id, _) = rest.split_once('|')?;
+            id.parse().ok().map(Algorithm::Custom)
         } else {
             None
         }
@@ -69,10 +86,11 @@ impl Algorithm {
             Algorithm::M2M => "M2M",
             Algorithm::TokenNative => "TOKEN_NATIVE",
             Algorithm::Brotli => "BROTLI",
+            Algorithm::Custom(_) => "CUSTOM",
         }
     }
 
-    /// Get all available algorithms in preference order
+    /// Get all built-in algorithms in preference order
     pub fn all() -> &'static [Algorithm] {
         &[
             Algorithm::M2M,
@@ -81,11 +99,55 @@ impl Algorithm {
             Algorithm::None,
         ]
     }
+
+    /// Cargo feature that compiles this algorithm in (`None` if always built)
+    pub fn feature(&self) -> Option<&'static str> {
+        match self {
+            Algorithm::None | Algorithm::M2M => None,
+            Algorithm::TokenNative => Some("token-native"),
+            Algorithm::Brotli => Some("brotli"),
+            Algorithm::Custom(_) => Some("wasm-plugins"),
+        }
+    }
+
+    /// Check if this build can encode and decode the algorithm
+    pub fn is_available(&self) -> bool {
+        match self {
+            Algorithm::None | Algorithm::M2M => true,
+            Algorithm::TokenNative => cfg!(feature = "token-native"),
+            Algorithm::Brotli => cfg!(feature = "brotli"),
+            Algorithm::Custom(_) => cfg!(feature = "wasm-plugins"),
+        }
+    }
+
+    /// Built-in algorithms this build supports, in preference order
+    pub fn available() -> Vec<Algorithm> {
+        Self::all()
+            .iter()
+            .copied()
+            .filter(Algorithm::is_available)
+            .collect()
+    }
+
+    /// Error for an algorithm this build does not support
+    #[cfg_attr(
+        all(feature = "token-native", feature = "brotli", feature = "wasm-plugins"),
+        allow(dead_code)
+    )]
+    pub(crate) fn unavailable(&self) -> M2MError {
+        M2MError::InvalidCodec(format!(
+            "{self} support not compiled in (enable the `{}` feature)",
+            self.feature().unwrap_or("codec-core")
+        ))
+    }
 }
 
 impl std::fmt::Display for Algorithm {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.name())
+        match self {
+            Algorithm::Custom(id) => write!(f, "{}:{id}", self.name()),
+            _ => write!(f, "{}", self.name()),
+        }
     }
 }
 
@@ -104,6 +166,10 @@ pub struct CompressionResult {
     pub original_tokens: Option<usize>,
     /// Compressed token count (if available)
     pub compressed_tokens: Option<usize>,
+    /// Algorithm originally selected, if the result fell back to passthrough
+    pub fallback_from: Option<Algorithm>,
+    /// Brotli quality, for standalone Brotli payloads
+    pub brotli_quality: Option<u32>,
 }
 
 impl CompressionResult {
@@ -121,9 +187,30 @@ impl CompressionResult {
             compressed_bytes,
             original_tokens: None,
             compressed_tokens: None,
+            fallback_from: None,
+            brotli_quality: None,
         }
     }
 
+    /// Create a passthrough result replacing output from `attempted`
+    ///
+    /// Used when `attempted` did not shrink the payload.
+    pub fn passthrough_fallback(content: &str, attempted: Algorithm) -> Self {
+        let mut result = Self::new(
+            content.to_string(),
+            Algorithm::None,
+            content.len(),
+            content.len(),
+        );
+        result.fallback_from = Some(attempted);
+        result
+    }
+
+    /// Check if this result fell back to passthrough
+    pub fn is_fallback(&self) -> bool {
+        self.fallback_from.is_some()
+    }
+
     /// Set token counts
     pub fn with_tokens(mut self, original: usize, compressed: usize) -> Self {
         self.original_tokens = Some(original);
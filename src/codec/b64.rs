@@ -0,0 +1,56 @@
+//! Base64 for the wire formats (standard alphabet, padded).
+//!
+//! M2M string frames and Brotli payloads are base64 end to end. Brotli
+//! still dominates frame cost (see `benches/wire_codec.rs`), so the gain
+//! is largest on big, highly compressible payloads.
+//!
+//! With the `simd` feature the work goes to `base64-simd` (AVX2, SSE4.1,
+//! NEON or WASM SIMD, detected at runtime); otherwise to the scalar
+//! `base64` engine. Both produce identical output.
+//!
+//! Errors always come from the scalar engine: when the SIMD decoder
+//! rejects input, it is decoded again to report the precise
+//! [`base64::DecodeError`].
+
+use base64::{engine::general_purpose::STANDARD, DecodeError, Engine};
+
+/// Encode `bytes` as padded standard base64
+pub(crate) fn encode(bytes: &[u8]) -> String {
+    #[cfg(feature = "simd")]
+    {
+        base64_simd::STANDARD.encode_to_string(bytes)
+    }
+    #[cfg(not(feature = "simd"))]
+    {
+        STANDARD.encode(bytes)
+    }
+}
+
+/// Decode padded standard base64
+pub(crate) fn decode(encoded: impl AsRef<[u8]>) -> Result<Vec<u8>, DecodeError> {
+    let encoded = encoded.as_ref();
+    #[cfg(feature = "simd")]
+    if let Ok(bytes) = base64_simd::STANDARD.decode_to_vec(encoded) {
+        return Ok(bytes);
+    }
+    STANDARD.decode(encoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches_scalar_engine() {
+        for len in 0..200 {
+            let bytes: Vec<u8> = (0..len).map(|i| (i * 37 + len) as u8).collect();
+            let encoded = encode(&bytes);
+            assert_eq!(encoded, STANDARD.encode(&bytes));
+            assert_eq!(decode(&encoded).unwrap(), bytes);
+        }
+
+        for invalid in ["A", "AB=C", "AB*D", "QQ=", "QR=="] {
+            assert_eq!(decode(invalid), STANDARD.decode(invalid), "{invalid}");
+        }
+    }
+}
@@ -0,0 +1,262 @@
+//! Per-message compression breakdowns for analytics.
+//!
+//! [`CompressionResult::breakdown`] splits a compressed message into wire
+//! overhead and payload, reports algorithm details read back from the wire
+//! (Brotli quality, shared dictionary, tokenizer), and prices the token
+//! difference for each model asked about:
+//!
+//! ```rust
+//! use m2m::codec::{Algorithm, CodecEngine};
+//!
+//! let engine = CodecEngine::new();
+//! let json = r#"{"model":"gpt-4o","messages":[{"role":"user","content":"Hello"}]}"#;
+//! let result = engine.compress(json, Algorithm::M2M).unwrap();
+//!
+//! let breakdown = result.breakdown(&["gpt-4o", "claude-3-haiku"]);
+//! assert_eq!(breakdown.header_bytes + breakdown.payload_bytes, breakdown.compressed_bytes);
+//! println!("{}", serde_json::to_string(&breakdown).unwrap());
+//! ```
+//!
+//! Token counts recorded on the result (e.g. by
+//! [`CodecEngine::compress_with_tokens`](super::CodecEngine::compress_with_tokens))
+//! are used as-is; otherwise both sides are estimated at ~4 bytes per token
+//! and `tokens_estimated` is set. Savings are priced at each model's input
+//! rate and are negative when the wire form costs more tokens.
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use serde::Serialize;
+
+use super::m2m::{estimate_cost, M2MFrame, M2M_PREFIX};
+use super::{Algorithm, CompressionResult};
+
+/// Token and dollar difference for one model
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ModelSavings {
+    /// Model priced
+    pub model: String,
+    /// Original minus compressed tokens
+    pub tokens_saved: i64,
+    /// Input cost of the saved tokens (USD)
+    pub usd_saved: f64,
+}
+
+/// Size, algorithm and cost breakdown of one compressed message
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CompressionBreakdown {
+    /// Algorithm used
+    pub algorithm: Algorithm,
+    /// Original size in bytes
+    pub original_bytes: usize,
+    /// Wire size in bytes
+    pub compressed_bytes: usize,
+    /// Wire bytes spent on prefix, headers and checksum
+    pub header_bytes: usize,
+    /// Wire bytes carrying the payload
+    pub payload_bytes: usize,
+    /// Brotli quality, if the payload is Brotli-compressed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub brotli_quality: Option<u32>,
+    /// Shared dictionary version the payload was compressed against
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dictionary: Option<String>,
+    /// Tokenizer vocabulary (TokenNative)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tokenizer: Option<String>,
+    /// Original token count
+    pub original_tokens: usize,
+    /// Wire token count
+    pub compressed_tokens: usize,
+    /// Whether token counts are byte-based estimates
+    pub tokens_estimated: bool,
+    /// Savings per requested model
+    pub savings: Vec<ModelSavings>,
+}
+
+/// Wire details read back from a compressed message
+#[derive(Default)]
+struct WireLayout {
+    /// Bytes before (or around) the payload
+    header_bytes: usize,
+    /// Brotli quality, when known from the wire
+    brotli_quality: Option<u32>,
+    /// Shared dictionary version
+    dictionary: Option<String>,
+    /// Tokenizer ID
+    tokenizer: Option<String>,
+}
+
+impl WireLayout {
+    /// Parse the wire format of `algorithm`
+    fn parse(wire: &str, algorithm: Algorithm) -> Self {
+        match algorithm {
+            Algorithm::None => Self::default(),
+            Algorithm::M2M => Self::m2m(wire),
+            Algorithm::Brotli => {
+                let header_bytes = wire.find("DATA:").map_or(0, |i| i + "DATA:".len());
+                let dictionary = wire
+                    .split('|')
+                    .find_map(|field| field.strip_prefix("DICT:"))
+                    .map(String::from);
+                Self {
+                    header_bytes,
+                    dictionary,
+                    ..Self::default()
+                }
+            },
+            Algorithm::TokenNative => {
+                // #TK|<tokenizer>|<data>
+                let tokenizer = wire
+                    .strip_prefix("#TK|")
+                    .and_then(|rest| rest.split_once('|'));
+                Self {
+                    header_bytes: tokenizer.map_or(0, |(id, _)| "#TK|".len() + id.len() + 1),
+                    tokenizer: tokenizer.map(|(id, _)| id.to_string()),
+                    ..Self::default()
+                }
+            },
+            Algorithm::Custom(id) => Self {
+                // #CX|<id>|<data>
+                header_bytes: algorithm.prefix().len() + id.to_string().len() + 1,
+                ..Self::default()
+            },
+        }
+    }
+
+    /// M2M frames: base64 covers headers and payload alike, so the payload
+    /// share is its encoded length
+    fn m2m(wire: &str) -> Self {
+        let frame = wire
+            .strip_prefix(M2M_PREFIX)
+            .and_then(|encoded| BASE64.decode(encoded).ok())
+            .map(|binary| [M2M_PREFIX.as_bytes(), &binary].concat());
+        let Some(frame) = frame
+            .as_deref()
+            .and_then(|f| M2MFrame::decode_borrowed(f).ok())
+        else {
+            return Self {
+                header_bytes: M2M_PREFIX.len().min(wire.len()),
+                ..Self::default()
+            };
+        };
+
+        let payload_wire = (frame.raw_payload().len() * 4).div_ceil(3);
+        Self {
+            header_bytes: wire.len().saturating_sub(payload_wire),
+            brotli_quality: frame
+                .is_compressed()
+                .then(|| super::m2m::payload_quality(frame.fixed.flags.common.hint())),
+            ..Self::default()
+        }
+    }
+}
+
+impl CompressionResult {
+    /// Break the message down for analytics, pricing savings for `models`
+    ///
+    /// Decodes M2M frame headers to locate the payload; the payload itself
+    /// is not decompressed.
+    pub fn breakdown(&self, models: &[&str]) -> CompressionBreakdown {
+        let layout = WireLayout::parse(&self.data, self.algorithm);
+        let header_bytes = layout.header_bytes.min(self.compressed_bytes);
+
+        let (original_tokens, compressed_tokens, tokens_estimated) =
+            match (self.original_tokens, self.compressed_tokens) {
+                (Some(original), Some(compressed)) => (original, compressed, false),
+                _ => (
+                    self.original_bytes.div_ceil(4),
+                    self.compressed_bytes.div_ceil(4),
+                    true,
+                ),
+            };
+        let tokens_saved = original_tokens as i64 - compressed_tokens as i64;
+
+        let savings = models
+            .iter()
+            .map(|&model| {
+                let usd = f64::from(estimate_cost(
+                    model,
+                    u32::try_from(tokens_saved.unsigned_abs()).unwrap_or(u32::MAX),
+                    0,
+                ));
+                ModelSavings {
+                    model: model.to_string(),
+                    tokens_saved,
+                    usd_saved: if tokens_saved < 0 { -usd } else { usd },
+                }
+            })
+            .collect();
+
+        CompressionBreakdown {
+            algorithm: self.algorithm,
+            original_bytes: self.original_bytes,
+            compressed_bytes: self.compressed_bytes,
+            header_bytes,
+            payload_bytes: self.compressed_bytes - header_bytes,
+            brotli_quality: self.brotli_quality.or(layout.brotli_quality),
+            dictionary: layout.dictionary,
+            tokenizer: layout.tokenizer,
+            original_tokens,
+            compressed_tokens,
+            tokens_estimated,
+            savings,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::{CodecEngine, CompressionHint};
+
+    #[test]
+    fn test_breakdowns() {
+        let engine = CodecEngine::new();
+        let turns: Vec<String> = (0..20)
+            .map(|i| format!(r#"{{"role":"user","content":"Question {i}: what changed?"}}"#))
+            .collect();
+        let json = format!(r#"{{"model":"gpt-4o","messages":[{}]}}"#, turns.join(","));
+
+        let result = engine
+            .compress_with_hint(&json, CompressionHint::Archival)
+            .unwrap();
+        let breakdown = result.breakdown(&["gpt-4o", "gpt-4o-mini"]);
+        assert_eq!(
+            breakdown.header_bytes + breakdown.payload_bytes,
+            result.compressed_bytes
+        );
+        assert!(breakdown.header_bytes > M2M_PREFIX.len());
+        assert!(breakdown.payload_bytes > breakdown.header_bytes);
+        assert_eq!(breakdown.brotli_quality, Some(11));
+        assert!(breakdown.tokens_estimated);
+        assert!(breakdown.savings[0].tokens_saved > 0);
+        assert!(breakdown.savings[0].usd_saved > breakdown.savings[1].usd_saved);
+
+        let passthrough = engine
+            .compress(&json, Algorithm::None)
+            .unwrap()
+            .with_tokens(10, 10)
+            .breakdown(&["gpt-4o"]);
+        assert_eq!(passthrough.header_bytes, 0);
+        assert!(!passthrough.tokens_estimated);
+        assert_eq!(passthrough.savings[0].tokens_saved, 0);
+        assert!(passthrough.savings[0].usd_saved.abs() < f64::EPSILON);
+
+        let value = serde_json::to_value(&breakdown).unwrap();
+        assert_eq!(value["algorithm"], "m2m");
+        assert_eq!(value["savings"][1]["model"], "gpt-4o-mini");
+        assert!(value.get("dictionary").is_none());
+    }
+
+    #[test]
+    #[cfg(feature = "brotli")]
+    fn test_brotli_breakdown() {
+        let json = r#"{"model":"gpt-4o","messages":[{"role":"user","content":"Hello"}]}"#;
+        let result = crate::codec::BrotliCodec::with_quality(9)
+            .compress(json)
+            .unwrap();
+        let breakdown = result.breakdown(&[]);
+        assert_eq!(breakdown.header_bytes, "#M2M[v3.0]|DATA:".len());
+        assert_eq!(breakdown.brotli_quality, Some(9));
+        assert!(breakdown.savings.is_empty());
+    }
+}
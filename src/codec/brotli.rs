@@ -1,13 +1,26 @@
 //! Brotli compression codec (Algorithm::Brotli).
 //!
 //! Uses Brotli compression for high compression ratios on larger payloads.
-//! Output is base64-encoded for wire transmission.
+//! Output is base64-encoded for wire transmission. With a
+//! [`SharedDictionary`], the wire format names the dictionary version.
+//!
+//! Large payloads can be decoded incrementally with
+//! [`BrotliCodec::decompress_to`] or [`BrotliCodec::decompress_stream`],
+//! which write output chunk by chunk instead of buffering it whole.
 
-use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
-use brotli::{CompressorWriter, Decompressor};
-use std::io::{Read, Write};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use brotli::enc::{BrotliEncoderParams, StandardAlloc};
+use brotli::interface::{PredictionModeContextMap, StaticCommand};
+use brotli::{
+    BrotliCompressCustomIoCustomDict, CompressorWriter, Decompressor, InputPair, InputReferenceMut,
+    IoReaderWrapper, IoWriterWrapper,
+};
+use bytes::BufMut;
+use std::cell::Cell;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::sync::Arc;
 
-use super::{Algorithm, CompressionResult};
+use super::{b64, Algorithm, BufferPool, CompressionResult, DecompressionLimits, SharedDictionary};
 use crate::error::{M2MError, Result};
 
 /// Brotli compression quality (0-11, higher = better compression, slower)
@@ -16,6 +29,15 @@ const DEFAULT_QUALITY: u32 = 11;
 /// Window size for Brotli (larger = better compression for large files)
 const DEFAULT_WINDOW_SIZE: u32 = 22;
 
+/// Wire prefix of Brotli payloads
+const WIRE_PREFIX: &str = "#M2M[v3.0]|";
+
+/// Longest wire header read by streaming decodes (prefix and dictionary)
+const MAX_STREAM_HEADER: u64 = 512;
+
+/// Output chunk size of streaming decodes
+const STREAM_CHUNK_SIZE: usize = 16 * 1024;
+
 /// Brotli codec
 #[derive(Clone)]
 pub struct BrotliCodec {
@@ -23,6 +45,12 @@ pub struct BrotliCodec {
     pub quality: u32,
     /// Window size (10-24)
     pub window_size: u32,
+    /// Bounds on decompressed output
+    pub limits: DecompressionLimits,
+    /// Scratch buffers for compressed output (`None` = allocate per call)
+    pub pool: Option<Arc<BufferPool>>,
+    /// Dictionary negotiated with the peer (`None` = plain Brotli)
+    pub dictionary: Option<Arc<SharedDictionary>>,
 }
 
 impl Default for BrotliCodec {
@@ -30,6 +58,9 @@ impl Default for BrotliCodec {
         Self {
             quality: DEFAULT_QUALITY,
             window_size: DEFAULT_WINDOW_SIZE,
+            limits: DecompressionLimits::default(),
+            pool: None,
+            dictionary: None,
         }
     }
 }
@@ -51,53 +82,235 @@ impl BrotliCodec {
     /// Compress bytes to Brotli format
     pub fn compress_bytes(&self, data: &[u8]) -> Result<Vec<u8>> {
         let mut compressed = Vec::new();
-        {
-            let mut writer =
-                CompressorWriter::new(&mut compressed, 4096, self.quality, self.window_size);
-            writer
-                .write_all(data)
-                .map_err(|e| M2MError::Compression(e.to_string()))?;
-        }
+        self.compress_to(data, &mut compressed)?;
         Ok(compressed)
     }
 
+    /// Compress bytes into `out`
+    fn compress_to(&self, mut data: &[u8], mut out: impl Write) -> Result<()> {
+        let Some(ref dictionary) = self.dictionary else {
+            // Dropping the writer finishes the stream
+            let mut writer = CompressorWriter::new(out, 4096, self.quality, self.window_size);
+            return writer
+                .write_all(data)
+                .map_err(|e| M2MError::Compression(e.to_string()));
+        };
+
+        let params = BrotliEncoderParams {
+            quality: self.quality as i32,
+            lgwin: self.window_size as i32,
+            size_hint: data.len(),
+            ..BrotliEncoderParams::default()
+        };
+        let mut nop_callback = |_: &mut PredictionModeContextMap<InputReferenceMut>,
+                                _: &mut [StaticCommand],
+                                _: InputPair,
+                                _: &mut StandardAlloc| ();
+        BrotliCompressCustomIoCustomDict(
+            &mut IoReaderWrapper(&mut data),
+            &mut IoWriterWrapper(&mut out),
+            &mut [0; 4096],
+            &mut [0; 4096],
+            &params,
+            StandardAlloc::default(),
+            &mut nop_callback,
+            dictionary.data(),
+            std::io::Error::from(std::io::ErrorKind::UnexpectedEof),
+        )
+        .map(drop)
+        .map_err(|e| M2MError::Compression(e.to_string()))
+    }
+
+    /// Compress with a dictionary both agents hold
+    ///
+    /// Payloads name the dictionary version, so a peer without it fails
+    /// to decompress instead of producing garbage.
+    pub fn with_dictionary(mut self, dictionary: Arc<SharedDictionary>) -> Self {
+        self.dictionary = Some(dictionary);
+        self
+    }
+
+    /// Compress into buffers from `pool` instead of allocating per call
+    pub fn with_pool(mut self, pool: Arc<BufferPool>) -> Self {
+        self.pool = Some(pool);
+        self
+    }
+
+    /// Bound decompressed output (default: [`DecompressionLimits::default`])
+    pub fn with_limits(mut self, limits: DecompressionLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
     /// Decompress Brotli bytes
+    ///
+    /// Fails with [`M2MError::PayloadTooLarge`] as soon as the output
+    /// passes the codec's limits.
     pub fn decompress_bytes(&self, data: &[u8]) -> Result<Vec<u8>> {
-        let mut decompressor = Decompressor::new(data, 4096);
-        let mut decompressed = Vec::new();
-        decompressor
-            .read_to_end(&mut decompressed)
-            .map_err(|e| M2MError::Decompression(e.to_string()))?;
-        Ok(decompressed)
+        let decompressor = Self::decompressor(self.dictionary.as_deref(), data);
+        self.limits.read_to_end(data.len(), decompressor)
+    }
+
+    /// Brotli decoder over `input`
+    fn decompressor<R: Read>(dictionary: Option<&SharedDictionary>, input: R) -> Decompressor<R> {
+        match dictionary {
+            Some(dictionary) => {
+                Decompressor::new_with_custom_dict(input, 4096, dictionary.data().to_vec().into())
+            },
+            None => Decompressor::new(input, 4096),
+        }
     }
 
-    /// Compress string to wire format: `#M2M[v3.0]|DATA:<base64>`
+    /// Dictionary for a payload naming `version` (`None` = plain Brotli)
+    fn payload_dictionary(&self, version: Option<&str>) -> Result<Option<&SharedDictionary>> {
+        match (version, self.dictionary.as_deref()) {
+            (None, _) => Ok(None),
+            (Some(version), Some(dictionary)) if dictionary.version() == version => {
+                Ok(Some(dictionary))
+            },
+            (Some(version), _) => Err(M2MError::Decompression(format!(
+                "Payload needs dictionary {version}, which is not negotiated"
+            ))),
+        }
+    }
+
+    /// Compress string to wire format: `#M2M[v3.0]|DATA:<base64>`, or
+    /// `#M2M[v3.0]|DICT:<version>|DATA:<base64>` with a dictionary
     pub fn compress(&self, content: &str) -> Result<CompressionResult> {
-        let compressed = self.compress_bytes(content.as_bytes())?;
-        let encoded = BASE64.encode(&compressed);
-        let wire = format!("#M2M[v3.0]|DATA:{encoded}");
+        let encoded = match self.pool {
+            Some(ref pool) => {
+                let mut scratch = pool.get();
+                let result = self.compress_to(content.as_bytes(), (&mut scratch).writer());
+                let encoded = result.map(|()| b64::encode(&scratch));
+                pool.put(scratch);
+                encoded?
+            },
+            None => b64::encode(&self.compress_bytes(content.as_bytes())?),
+        };
+        let wire = match self.dictionary {
+            Some(ref dictionary) => {
+                format!("{WIRE_PREFIX}DICT:{}|DATA:{encoded}", dictionary.version())
+            },
+            None => format!("{WIRE_PREFIX}DATA:{encoded}"),
+        };
         let wire_len = wire.len();
 
-        Ok(CompressionResult::new(
-            wire,
-            Algorithm::Brotli,
-            content.len(),
-            wire_len,
-        ))
+        let mut result = CompressionResult::new(wire, Algorithm::Brotli, content.len(), wire_len);
+        result.brotli_quality = Some(self.quality);
+        Ok(result)
     }
 
     /// Decompress from wire format
+    ///
+    /// Payloads compressed with a dictionary need a codec holding the same
+    /// dictionary version.
     pub fn decompress(&self, wire: &str) -> Result<String> {
-        let data = wire
-            .strip_prefix("#M2M[v3.0]|DATA:")
-            .ok_or_else(|| M2MError::InvalidMessage("Invalid Brotli wire format".to_string()))?;
+        let invalid = || M2MError::InvalidMessage("Invalid Brotli wire format".to_string());
+        let body = wire.strip_prefix(WIRE_PREFIX).ok_or_else(invalid)?;
 
-        let compressed = BASE64.decode(data)?;
-        let decompressed = self.decompress_bytes(&compressed)?;
+        let (version, data) = match body.strip_prefix("DICT:") {
+            Some(body) => {
+                let (version, data) = body.split_once("|DATA:").ok_or_else(invalid)?;
+                (Some(version), data)
+            },
+            None => (None, body.strip_prefix("DATA:").ok_or_else(invalid)?),
+        };
+        // Plain payloads decode even when a dictionary is negotiated
+        let dictionary = self.payload_dictionary(version)?;
+        let compressed = b64::decode(data)?;
+        let decompressed = self.limits.read_to_end(
+            compressed.len(),
+            Self::decompressor(dictionary, &compressed[..]),
+        )?;
 
         String::from_utf8(decompressed)
             .map_err(|e| M2MError::Decompression(format!("Invalid UTF-8: {e}")))
     }
+
+    /// Decompress from wire format into `out`, chunk by chunk
+    ///
+    /// Output is written as it is decoded, so peak memory stays bounded
+    /// for multi-megabyte payloads. Returns the number of bytes written.
+    /// The output is not checked for UTF-8.
+    pub fn decompress_to(&self, wire: &str, out: impl Write) -> Result<u64> {
+        self.decompress_stream(wire.as_bytes(), out)
+    }
+
+    /// Decompress wire format read from `wire` into `out`
+    ///
+    /// Like [`decompress_to`](Self::decompress_to), but the wire payload is
+    /// also read incrementally, e.g. from a socket or file. The expansion
+    /// ratio limit is applied to the wire bytes consumed so far; output
+    /// written before a limit or decode error is left in `out`.
+    pub fn decompress_stream(&self, wire: impl Read, mut out: impl Write) -> Result<u64> {
+        let consumed = Cell::new(0);
+        let mut wire = BufReader::new(CountingReader {
+            inner: wire,
+            count: &consumed,
+        });
+        let version = Self::read_stream_header(&mut wire)?;
+        let dictionary = self.payload_dictionary(version.as_deref())?;
+
+        let base64 = base64::read::DecoderReader::new(wire, &BASE64);
+        let mut decoder = Self::decompressor(dictionary, base64);
+        let mut chunk = vec![0; STREAM_CHUNK_SIZE];
+        let mut written = 0;
+        loop {
+            let n = decoder.read(&mut chunk).map_err(|e| {
+                M2MError::Decompression(format!("Brotli decompression failed: {e}"))
+            })?;
+            if n == 0 {
+                return Ok(written as u64);
+            }
+            written += n;
+            self.limits.check(consumed.get(), written)?;
+            out.write_all(&chunk[..n])?;
+        }
+    }
+
+    /// Read the wire header up to `DATA:`, returning the dictionary version
+    fn read_stream_header(wire: &mut impl BufRead) -> Result<Option<String>> {
+        let invalid = || M2MError::InvalidMessage("Invalid Brotli wire format".to_string());
+        let mut header = Vec::new();
+        wire.take(MAX_STREAM_HEADER).read_until(b':', &mut header)?;
+
+        let prefix = header
+            .strip_prefix(WIRE_PREFIX.as_bytes())
+            .ok_or_else(invalid)?;
+        match prefix {
+            b"DATA:" => Ok(None),
+            b"DICT:" => {
+                let mut version = Vec::new();
+                wire.take(MAX_STREAM_HEADER)
+                    .read_until(b'|', &mut version)?;
+                let mut data = [0; 5];
+                wire.read_exact(&mut data).map_err(|_| invalid())?;
+                match (version.pop(), &data) {
+                    (Some(b'|'), b"DATA:") => {
+                        String::from_utf8(version).map(Some).map_err(|_| invalid())
+                    },
+                    _ => Err(invalid()),
+                }
+            },
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// Reader counting the bytes read through it
+struct CountingReader<'a, R> {
+    /// Wrapped reader
+    inner: R,
+    /// Bytes read so far
+    count: &'a Cell<usize>,
+}
+
+impl<R: Read> Read for CountingReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.count.set(self.count.get() + n);
+        Ok(n)
+    }
 }
 
 #[cfg(test)]
@@ -117,6 +330,24 @@ mod tests {
         assert_eq!(decompressed, original);
     }
 
+    #[test]
+    fn test_pooled_compress_matches() {
+        let pool = Arc::new(BufferPool::new());
+        let codec = BrotliCodec::new().with_pool(Arc::clone(&pool));
+        let original = r#"{"messages":[{"role":"user","content":"Hello"}]}"#;
+
+        for _ in 0..3 {
+            let pooled = codec.compress(original).unwrap();
+            assert_eq!(
+                pooled.data,
+                BrotliCodec::new().compress(original).unwrap().data
+            );
+        }
+        let stats = pool.stats();
+        assert_eq!(stats.misses, 1);
+        assert_eq!(stats.hits, 2);
+    }
+
     #[test]
     fn test_compression_ratio() {
         let codec = BrotliCodec::new();
@@ -135,6 +366,84 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_dictionary_roundtrip() {
+        let dictionary = Arc::new(
+            SharedDictionary::new(
+                "chat",
+                r#"{"model":"gpt-4o","messages":[{"role":"system","content":"You are a helpful assistant."},{"role":"user","content":""#,
+            )
+            .unwrap(),
+        );
+        let codec = BrotliCodec::new().with_dictionary(Arc::clone(&dictionary));
+        let original = r#"{"model":"gpt-4o","messages":[{"role":"system","content":"You are a helpful assistant."},{"role":"user","content":"Hi"}]}"#;
+
+        let result = codec.compress(original).unwrap();
+        let plain = BrotliCodec::new().compress(original).unwrap();
+        assert!(result
+            .data
+            .starts_with(&format!("#M2M[v3.0]|DICT:{}|DATA:", dictionary.version())));
+        assert!(
+            codec.compress_bytes(original.as_bytes()).unwrap().len()
+                < BrotliCodec::new()
+                    .compress_bytes(original.as_bytes())
+                    .unwrap()
+                    .len()
+        );
+        assert_eq!(codec.decompress(&result.data).unwrap(), original);
+
+        // Plain payloads still decode; dictionary payloads need the dictionary
+        assert_eq!(codec.decompress(&plain.data).unwrap(), original);
+        assert!(BrotliCodec::new().decompress(&result.data).is_err());
+        let other = Arc::new(SharedDictionary::new("chat", "different").unwrap());
+        assert!(BrotliCodec::new()
+            .with_dictionary(other)
+            .decompress(&result.data)
+            .is_err());
+    }
+
+    #[test]
+    fn test_streaming_decompress() {
+        let codec = BrotliCodec::new();
+        let turns: Vec<String> = (0..2000)
+            .map(|i| format!(r#"{{"role":"user","content":"Message {i} of a long conversation"}}"#))
+            .collect();
+        let original = format!(r#"{{"messages":[{}]}}"#, turns.join(","));
+        let result = codec.compress(&original).unwrap();
+
+        let mut out = Vec::new();
+        let written = codec.decompress_to(&result.data, &mut out).unwrap();
+        assert_eq!(written, original.len() as u64);
+        assert_eq!(out, original.as_bytes());
+
+        // Wire arriving in small reads
+        let mut out = Vec::new();
+        let reader = std::io::BufReader::with_capacity(7, result.data.as_bytes());
+        codec.decompress_stream(reader, &mut out).unwrap();
+        assert_eq!(out, original.as_bytes());
+
+        let dictionary = Arc::new(SharedDictionary::new("chat", r#"{"messages":["#).unwrap());
+        let with_dict = BrotliCodec::new().with_dictionary(dictionary);
+        let wire = with_dict.compress(&original).unwrap().data;
+        let mut out = Vec::new();
+        with_dict.decompress_to(&wire, &mut out).unwrap();
+        assert_eq!(out, original.as_bytes());
+        assert!(codec.decompress_to(&wire, std::io::sink()).is_err());
+
+        let limited = BrotliCodec::new().with_limits(
+            DecompressionLimits::new()
+                .with_max_size(1000)
+                .without_max_ratio(),
+        );
+        assert!(matches!(
+            limited.decompress_to(&result.data, std::io::sink()),
+            Err(M2MError::PayloadTooLarge { limit: 1000, .. })
+        ));
+        assert!(codec
+            .decompress_to("#M2M[v3.0]|NOPE", std::io::sink())
+            .is_err());
+    }
+
     #[test]
     fn test_bytes_roundtrip() {
         let codec = BrotliCodec::new();
@@ -0,0 +1,220 @@
+//! JSON canonicalization.
+//!
+//! The M2M codec's default promise is byte-identical round-trips: the
+//! decoded payload is exactly the JSON that was encoded, whitespace and key
+//! order included. Some users only need semantically identical JSON and
+//! would rather have a stable byte form, for example to hash or sign
+//! payloads, or to deduplicate requests that differ only in formatting.
+//!
+//! [`CanonicalMode`] selects between the two. In canonical mode JSON is
+//! rewritten following RFC 8785 (JSON Canonicalization Scheme):
+//!
+//! - No insignificant whitespace
+//! - Object keys sorted by their UTF-16 code units
+//! - Numbers in their shortest round-trip form (`1.0` becomes `1`,
+//!   `1e21` becomes `1e+21`)
+//! - Strings with only the escapes JSON requires
+//!
+//! Numbers are parsed as IEEE 754 doubles, as RFC 8785 requires, so
+//! integers beyond 2^53 lose precision in canonical mode. M2M frames
+//! encoded in canonical mode carry the `CANONICAL` flag (see
+//! [`M2MFrame::is_canonical`](super::m2m::M2MFrame::is_canonical)) so the
+//! receiver knows which guarantee it got.
+//!
+//! # Example
+//!
+//! ```rust
+//! use m2m::codec::canonical::canonicalize;
+//!
+//! let json = r#"{ "b": 1.0, "a": [true, null] }"#;
+//! assert_eq!(canonicalize(json).unwrap(), r#"{"a":[true,null],"b":1}"#);
+//! ```
+
+use std::borrow::Cow;
+use std::fmt::Write;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::error::{M2MError, Result};
+
+/// Round-trip guarantee for JSON payloads
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CanonicalMode {
+    /// Keep the input bytes: decoding returns exactly what was encoded
+    #[default]
+    Preserve,
+    /// Canonicalize first: decoding returns semantically identical JSON in
+    /// canonical form
+    Canonical,
+}
+
+impl CanonicalMode {
+    /// Apply the mode to a JSON document
+    ///
+    /// Borrows the input unchanged in [`Preserve`](Self::Preserve) mode.
+    pub fn apply<'a>(&self, json: &'a str) -> Result<Cow<'a, str>> {
+        match self {
+            Self::Preserve => Ok(Cow::Borrowed(json)),
+            Self::Canonical => canonicalize(json).map(Cow::Owned),
+        }
+    }
+
+    /// Whether payloads are rewritten
+    pub fn is_canonical(&self) -> bool {
+        *self == Self::Canonical
+    }
+}
+
+/// Canonicalize a JSON document (RFC 8785)
+pub fn canonicalize(json: &str) -> Result<String> {
+    let value: Value = serde_json::from_str(json)
+        .map_err(|e| M2MError::Compression(format!("Invalid JSON: {e}")))?;
+    Ok(canonicalize_value(&value))
+}
+
+/// Canonical form of a parsed JSON value (RFC 8785)
+pub fn canonicalize_value(value: &Value) -> String {
+    let mut out = String::new();
+    write_value(&mut out, value);
+    out
+}
+
+/// Check whether a JSON document is already in canonical form
+pub fn is_canonical(json: &str) -> bool {
+    canonicalize(json).is_ok_and(|canonical| canonical == json)
+}
+
+fn write_value(out: &mut String, value: &Value) {
+    match value {
+        Value::Null => out.push_str("null"),
+        Value::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+        Value::Number(n) => write_number(out, n.as_f64().unwrap_or(0.0)),
+        Value::String(s) => write_string(out, s),
+        Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_value(out, item);
+            }
+            out.push(']');
+        },
+        Value::Object(map) => {
+            let mut entries: Vec<_> = map.iter().collect();
+            entries.sort_by(|(a, _), (b, _)| a.encode_utf16().cmp(b.encode_utf16()));
+
+            out.push('{');
+            for (i, (key, item)) in entries.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_string(out, key);
+                out.push(':');
+                write_value(out, item);
+            }
+            out.push('}');
+        },
+    }
+}
+
+fn write_string(out: &mut String, s: &str) {
+    // serde_json escapes exactly what RFC 8785 requires
+    out.push_str(&serde_json::to_string(s).unwrap_or_default());
+}
+
+/// ECMAScript `Number.prototype.toString` formatting, as RFC 8785 requires
+fn write_number(out: &mut String, n: f64) {
+    if n == 0.0 || !n.is_finite() {
+        // -0 serializes as 0; JSON has no NaN or Infinity
+        out.push('0');
+        return;
+    }
+    if n < 0.0 {
+        out.push('-');
+    }
+
+    // Shortest round-trip digits and exponent, e.g. "1.5e-7"
+    let scientific = format!("{:e}", n.abs());
+    let (mantissa, exponent) = scientific.split_once('e').unwrap_or((&scientific, "0"));
+    let digits: String = mantissa.chars().filter(char::is_ascii_digit).collect();
+    let exponent: i32 = exponent.parse().unwrap_or(0);
+
+    // Value is 0.digits * 10^point
+    let k = digits.len() as i32;
+    let point = exponent + 1;
+
+    if k <= point && point <= 21 {
+        out.push_str(&digits);
+        out.extend(std::iter::repeat_n('0', (point - k) as usize));
+    } else if 0 < point && point <= 21 {
+        let (int, frac) = digits.split_at(point as usize);
+        let _ = write!(out, "{int}.{frac}");
+    } else if -6 < point && point <= 0 {
+        out.push_str("0.");
+        out.extend(std::iter::repeat_n('0', (-point) as usize));
+        out.push_str(&digits);
+    } else {
+        let (first, rest) = digits.split_at(1);
+        out.push_str(first);
+        if !rest.is_empty() {
+            out.push('.');
+            out.push_str(rest);
+        }
+        let sign = if point > 0 { '+' } else { '-' };
+        let _ = write!(out, "e{sign}{}", (point - 1).abs());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rfc8785_numbers() {
+        let cases = [
+            ("0", "0"),
+            ("-0.0", "0"),
+            ("1.0", "1"),
+            ("-42", "-42"),
+            ("0.1", "0.1"),
+            ("1.5e-7", "1.5e-7"),
+            ("0.000001", "0.000001"),
+            ("123456789012", "123456789012"),
+            ("1e21", "1e+21"),
+            ("1e20", "100000000000000000000"),
+            ("4.5e300", "4.5e+300"),
+            ("333333333.33333329", "333333333.3333333"),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(canonicalize(input).unwrap(), expected, "input {input}");
+        }
+    }
+
+    #[test]
+    fn test_keys_whitespace_and_strings() {
+        let json = "{\n  \"b\": \"caf\\u00e9\\n\",\n  \"a\": {\"z\": [1, 2], \"\u{fb33}\": 1, \"\u{1f600}\": 2}\n}";
+        assert_eq!(
+            canonicalize(json).unwrap(),
+            "{\"a\":{\"z\":[1,2],\"\u{1f600}\":2,\"\u{fb33}\":1},\"b\":\"caf\u{e9}\\n\"}"
+        );
+        assert!(is_canonical(r#"{"a":1,"b":[]}"#));
+        assert!(!is_canonical(r#"{"b":1,"a":2}"#));
+        assert!(canonicalize("{not json").is_err());
+    }
+
+    #[test]
+    fn test_mode_apply() {
+        let json = r#"{"b":1, "a":2}"#;
+        assert!(matches!(
+            CanonicalMode::Preserve.apply(json).unwrap(),
+            Cow::Borrowed(s) if s == json
+        ));
+        assert_eq!(
+            CanonicalMode::Canonical.apply(json).unwrap(),
+            r#"{"a":2,"b":1}"#
+        );
+    }
+}
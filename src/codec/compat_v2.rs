@@ -0,0 +1,108 @@
+//! Protocol v2.0 Zlib frames (compatibility, deprecated).
+//!
+//! Agents from the v2.0 era send `#M2M[v2.0]|DATA:<base64_zlib>`. Fleets
+//! mid-upgrade can enable the `compat-v2` feature so new agents decode (and
+//! if needed emit) these frames while old agents are replaced.
+//!
+//! Every v2.0 frame handled by a [`CodecEngine`](super::CodecEngine) is
+//! reported as a [`V2Usage`] to its deprecation hook (see
+//! [`CodecEngine::with_deprecation_hook`](super::CodecEngine::with_deprecation_hook)),
+//! or logged as a warning when no hook is set, so operators can find the
+//! remaining v2.0 senders.
+
+use std::io::{Read, Write};
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+
+use super::V2_PREFIX;
+use crate::error::{M2MError, Result};
+
+/// Zlib compression level (0-9) used by v2.0 senders
+const DEFAULT_LEVEL: u32 = 6;
+
+/// Use of the deprecated v2.0 format, reported to the deprecation hook
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum V2Usage {
+    /// A v2.0 frame was decoded (wire size in bytes)
+    Decoded(usize),
+    /// A v2.0 frame was emitted (wire size in bytes)
+    Encoded(usize),
+}
+
+/// Zlib codec for v2.0 frames
+#[derive(Debug, Clone)]
+pub struct ZlibCodec {
+    /// Compression level (0-9)
+    pub level: u32,
+}
+
+impl Default for ZlibCodec {
+    fn default() -> Self {
+        Self {
+            level: DEFAULT_LEVEL,
+        }
+    }
+}
+
+impl ZlibCodec {
+    /// Create new Zlib codec with default settings
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Compress string to wire format: `#M2M[v2.0]|DATA:<base64>`
+    pub fn compress(&self, content: &str) -> Result<String> {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::new(self.level.min(9)));
+        encoder
+            .write_all(content.as_bytes())
+            .map_err(|e| M2MError::Compression(e.to_string()))?;
+        let compressed = encoder
+            .finish()
+            .map_err(|e| M2MError::Compression(e.to_string()))?;
+        Ok(format!("{V2_PREFIX}{}", BASE64.encode(compressed)))
+    }
+
+    /// Decompress from wire format
+    pub fn decompress(&self, wire: &str) -> Result<String> {
+        let data = wire
+            .strip_prefix(V2_PREFIX)
+            .ok_or_else(|| M2MError::InvalidMessage("Invalid v2.0 wire format".to_string()))?;
+
+        let compressed = BASE64.decode(data)?;
+        let mut decompressed = String::new();
+        ZlibDecoder::new(compressed.as_slice())
+            .read_to_string(&mut decompressed)
+            .map_err(|e| M2MError::Decompression(format!("Invalid v2.0 Zlib payload: {e}")))?;
+        Ok(decompressed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compress_decompress() {
+        let codec = ZlibCodec::new();
+        let original =
+            r#"{"model":"gpt-4o","messages":[{"role":"user","content":"Hello, world!"}]}"#;
+
+        let wire = codec.compress(original).unwrap();
+        assert!(wire.starts_with("#M2M[v2.0]|DATA:"));
+        assert_eq!(codec.decompress(&wire).unwrap(), original);
+    }
+
+    #[test]
+    fn test_decodes_v2_sender_output() {
+        // zlib.compress(b'{"a":1}') from a v2.0 agent
+        let wire = "#M2M[v2.0]|DATA:eJyrVkpUsjKsBQAIKgIJ";
+        assert_eq!(ZlibCodec::new().decompress(wire).unwrap(), r#"{"a":1}"#);
+
+        assert!(ZlibCodec::new()
+            .decompress("#M2M[v2.0]|DATA:bm90IHpsaWI=")
+            .is_err());
+    }
+}
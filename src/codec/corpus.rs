@@ -0,0 +1,318 @@
+//! Offline tuning from captured traffic.
+//!
+//! [`CodecEngine::analyze_corpus`] compresses every captured payload with
+//! every available algorithm and reports what each would have saved, the
+//! [`RouterThresholds`] that minimize wire bytes for the corpus (the same
+//! grid search [`RouterFeedback`](super::RouterFeedback) runs on live
+//! traffic) and a [`CompressionConfig`] to start from.
+//!
+//! Paths may be files or directories; directories are walked recursively
+//! in name order. Each file is one payload, except `.jsonl` files, where
+//! each non-empty line is one. Empty and non-UTF-8 files are skipped.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use m2m::codec::{CodecEngine, RouterFeedback};
+//!
+//! let report = CodecEngine::new().analyze_corpus(["captures/"])?;
+//! let feedback = RouterFeedback::new().with_thresholds(report.thresholds);
+//! ```
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use serde::Serialize;
+
+use super::engine::{CodecEngine, ContentAnalysis};
+use super::feedback::{fit_thresholds, thresholds_cost, FeedbackSample, RouterThresholds};
+use super::{Algorithm, CompressionProfile};
+use crate::config::CompressionConfig;
+use crate::error::Result;
+
+/// Extra savings (fraction of original bytes) exhaustive selection must
+/// add over fitted thresholds before `max-savings` is recommended
+const MAX_SAVINGS_MARGIN: f64 = 0.05;
+
+/// Projected savings of one algorithm over a corpus
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AlgorithmSavings {
+    /// Algorithm applied to every payload
+    pub algorithm: Algorithm,
+    /// Payloads the algorithm could not compress (sent as-is)
+    pub failed: usize,
+    /// Payloads the algorithm made larger (sent as-is)
+    pub expanded: usize,
+    /// Wire bytes for the corpus
+    pub wire_bytes: usize,
+    /// Bytes saved (percent of original)
+    pub savings_percent: f64,
+    /// Mean compression time per payload (microseconds)
+    pub mean_compress_us: f64,
+}
+
+/// Result of [`CodecEngine::analyze_corpus`]
+#[derive(Debug, Clone, Serialize)]
+pub struct CorpusReport {
+    /// Files read
+    pub files: usize,
+    /// Files skipped (empty or not UTF-8)
+    pub skipped: usize,
+    /// Payloads analyzed
+    pub payloads: usize,
+    /// Original bytes of all payloads
+    pub original_bytes: usize,
+    /// Savings of each available algorithm used alone
+    pub algorithms: Vec<AlgorithmSavings>,
+    /// Wire bytes with the engine's current thresholds
+    pub current_wire_bytes: usize,
+    /// Wire bytes with [`thresholds`](Self::thresholds)
+    pub fitted_wire_bytes: usize,
+    /// Wire bytes picking the smallest output for every payload
+    pub best_wire_bytes: usize,
+    /// Thresholds minimizing wire bytes for the corpus
+    pub thresholds: RouterThresholds,
+    /// Suggested compression settings
+    pub recommended: CompressionConfig,
+}
+
+impl CorpusReport {
+    /// Bytes saved by `wire_bytes` (percent of original)
+    pub fn savings_percent(&self, wire_bytes: usize) -> f64 {
+        savings_percent(self.original_bytes, wire_bytes)
+    }
+}
+
+impl CodecEngine {
+    /// Project savings and fit thresholds over captured payloads
+    ///
+    /// See the [module docs](self) for how `paths` are read. Fails if a
+    /// path cannot be read.
+    pub fn analyze_corpus<P: AsRef<Path>>(
+        &self,
+        paths: impl IntoIterator<Item = P>,
+    ) -> Result<CorpusReport> {
+        let mut files = Vec::new();
+        for path in paths {
+            collect_files(path.as_ref(), &mut files)?;
+        }
+
+        let mut skipped = 0;
+        let mut payloads = Vec::new();
+        for file in &files {
+            let bytes = fs::read(file)?;
+            let Ok(text) = String::from_utf8(bytes) else {
+                skipped += 1;
+                continue;
+            };
+            let before = payloads.len();
+            if file.extension().is_some_and(|ext| ext == "jsonl") {
+                payloads.extend(
+                    text.lines()
+                        .filter(|line| !line.trim().is_empty())
+                        .map(str::to_string),
+                );
+            } else if !text.trim().is_empty() {
+                payloads.push(text);
+            }
+            if payloads.len() == before {
+                skipped += 1;
+            }
+        }
+
+        let algorithms: Vec<Algorithm> = Algorithm::available()
+            .into_iter()
+            .filter(|algo| *algo != Algorithm::None)
+            .collect();
+        let mut savings: Vec<AlgorithmSavings> = algorithms
+            .iter()
+            .map(|algorithm| AlgorithmSavings {
+                algorithm: *algorithm,
+                failed: 0,
+                expanded: 0,
+                wire_bytes: 0,
+                savings_percent: 0.0,
+                mean_compress_us: 0.0,
+            })
+            .collect();
+
+        let mut samples = Vec::with_capacity(payloads.len());
+        for payload in &payloads {
+            let mut sizes = Vec::with_capacity(algorithms.len());
+            for entry in &mut savings {
+                let start = Instant::now();
+                let result = self.compress(payload, entry.algorithm);
+                entry.mean_compress_us += start.elapsed().as_secs_f64() * 1e6;
+                match result {
+                    Ok(result) => {
+                        if result.compressed_bytes > payload.len() {
+                            entry.expanded += 1;
+                        }
+                        entry.wire_bytes += result.compressed_bytes.min(payload.len());
+                        sizes.push((entry.algorithm, result.compressed_bytes));
+                    },
+                    Err(_) => {
+                        entry.failed += 1;
+                        entry.wire_bytes += payload.len();
+                    },
+                }
+            }
+
+            let analysis = ContentAnalysis::analyze(payload);
+            let mut sample = FeedbackSample {
+                length: analysis.length,
+                is_json: analysis.is_json,
+                is_llm_api: analysis.is_llm_api,
+                repetition_ratio: analysis.repetition_ratio,
+                has_tools: analysis.has_tools,
+                media_bytes: analysis.media_bytes,
+                chosen: Algorithm::None,
+                ratio: 1.0,
+                sizes,
+            };
+            // Keep `best()` meaningful when every algorithm failed
+            if sample.sizes.is_empty() {
+                sample.sizes.push((Algorithm::None, payload.len()));
+            }
+            samples.push(sample);
+        }
+
+        let original_bytes: usize = payloads.iter().map(String::len).sum();
+        for entry in &mut savings {
+            entry.savings_percent = savings_percent(original_bytes, entry.wire_bytes);
+            entry.mean_compress_us /= payloads.len().max(1) as f64;
+        }
+
+        let current = self.thresholds();
+        let current_wire_bytes = thresholds_cost(&samples, current, self.prefer_m2m_for_api);
+        let (thresholds, fitted_wire_bytes) =
+            fit_thresholds(&samples, current, self.prefer_m2m_for_api);
+        let best: Vec<Algorithm> = samples.iter().filter_map(FeedbackSample::best).collect();
+        let best_wire_bytes = samples
+            .iter()
+            .zip(&best)
+            .map(|(sample, algo)| sample.cost(*algo))
+            .sum();
+
+        // Latency gives nothing up if no payload is smaller with another
+        // codec; max-savings pays for itself only with a clear margin
+        let profile = if best
+            .iter()
+            .all(|algo| matches!(algo, Algorithm::None | Algorithm::M2M))
+        {
+            CompressionProfile::Latency
+        } else if fitted_wire_bytes.saturating_sub(best_wire_bytes) as f64
+            > original_bytes as f64 * MAX_SAVINGS_MARGIN
+        {
+            CompressionProfile::MaxSavings
+        } else {
+            CompressionProfile::Balanced
+        };
+        let defaults = CompressionConfig::default();
+        let min_tokens = thresholds.min_compress_bytes / 4;
+        let recommended = CompressionConfig {
+            enabled: best_wire_bytes < original_bytes,
+            min_tokens,
+            full_compression_threshold: defaults.full_compression_threshold.max(min_tokens),
+            profile,
+            ..defaults
+        };
+
+        Ok(CorpusReport {
+            files: files.len(),
+            skipped,
+            payloads: payloads.len(),
+            original_bytes,
+            algorithms: savings,
+            current_wire_bytes,
+            fitted_wire_bytes,
+            best_wire_bytes,
+            thresholds,
+            recommended,
+        })
+    }
+}
+
+/// Add `path` (or the files under it) to `files`
+fn collect_files(path: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    if !fs::metadata(path)?.is_dir() {
+        files.push(path.to_path_buf());
+        return Ok(());
+    }
+
+    let mut entries: Vec<_> = fs::read_dir(path)?.collect::<std::io::Result<_>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        // Symlinked directories are not followed, so cycles cannot recurse
+        if entry.file_type()?.is_dir() {
+            collect_files(&entry.path(), files)?;
+        } else if fs::metadata(entry.path()).is_ok_and(|m| m.is_file()) {
+            files.push(entry.path());
+        }
+    }
+    Ok(())
+}
+
+/// Percent of `original` saved by sending `wire` bytes
+fn savings_percent(original: usize, wire: usize) -> f64 {
+    if original == 0 {
+        0.0
+    } else {
+        (original as f64 - wire as f64) / original as f64 * 100.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_analyze_corpus() {
+        let dir = std::env::temp_dir().join(format!("m2m-corpus-{}", std::process::id()));
+        let nested = dir.join("chat");
+        fs::create_dir_all(&nested).unwrap();
+
+        let request = |i: usize| {
+            serde_json::json!({
+                "model": "gpt-4o",
+                "messages": [
+                    {"role": "system", "content": "You are a helpful assistant."},
+                    {"role": "user", "content": format!("Summarize ticket {i} in one line.")}
+                ],
+                "temperature": 0.7
+            })
+            .to_string()
+        };
+        fs::write(nested.join("a.json"), request(1)).unwrap();
+        fs::write(
+            dir.join("batch.jsonl"),
+            format!("{}\n\n{}\n", request(2), request(3)),
+        )
+        .unwrap();
+        fs::write(dir.join("empty.json"), "").unwrap();
+        fs::write(dir.join("binary.bin"), [0xFF, 0xFE, 0x00]).unwrap();
+
+        let engine = CodecEngine::new();
+        let report = engine.analyze_corpus([&dir]).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!((report.files, report.skipped, report.payloads), (4, 2, 3));
+        let m2m = report
+            .algorithms
+            .iter()
+            .find(|s| s.algorithm == Algorithm::M2M)
+            .unwrap();
+        assert_eq!(m2m.failed, 0);
+        assert!((report.savings_percent(m2m.wire_bytes) - m2m.savings_percent).abs() < 1e-9);
+        assert!(report.best_wire_bytes <= report.fitted_wire_bytes);
+        assert!(report.fitted_wire_bytes <= report.current_wire_bytes);
+        assert!(report.current_wire_bytes <= report.original_bytes);
+        assert_eq!(
+            report.recommended.min_tokens,
+            report.thresholds.min_compress_bytes / 4
+        );
+
+        assert!(engine.analyze_corpus([dir.join("missing")]).is_err());
+    }
+}
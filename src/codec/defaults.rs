@@ -0,0 +1,192 @@
+//! Provider-default parameter removal.
+//!
+//! LLM API requests often spell out parameters at their provider default
+//! (`"temperature": 1`, `"top_p": 1`, `"n": 1`, `"stream": false`). They
+//! cost bytes and tokens on the wire but change nothing, so
+//! [`DefaultsNormalizer`] strips them before compression, using the
+//! [`ModelCard::defaults`](crate::models::ModelCard::defaults) of the
+//! request's model:
+//!
+//! ```rust
+//! use m2m::codec::DefaultsNormalizer;
+//!
+//! let normalizer = DefaultsNormalizer::new().with_kept_keys(["stream"]);
+//! let json = r#"{"model":"gpt-4o","temperature":1.0,"stream":false,"messages":[]}"#;
+//! assert_eq!(
+//!     normalizer.normalize(json),
+//!     r#"{"messages":[],"model":"gpt-4o","stream":false}"#
+//! );
+//! ```
+//!
+//! Removal is semantically lossless, not byte-identical: nothing is
+//! re-injected on decompression, because the provider applies the same
+//! default when the parameter is absent. Requests for models missing from
+//! the registry are left untouched, since their defaults are unknown.
+//! Normalized requests are re-serialized, so key order and whitespace are
+//! not preserved.
+
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use serde_json::Value;
+
+use crate::models::ModelRegistry;
+
+/// Strips request parameters equal to the model's provider defaults
+#[derive(Clone)]
+pub struct DefaultsNormalizer {
+    /// Model cards to read defaults from
+    registry: Arc<ModelRegistry>,
+    /// Keys never removed
+    kept: HashSet<String>,
+}
+
+impl Default for DefaultsNormalizer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DefaultsNormalizer {
+    /// Create with the embedded model registry
+    pub fn new() -> Self {
+        Self::with_registry(Arc::new(ModelRegistry::new()))
+    }
+
+    /// Create with a custom registry (e.g. one with dynamic models added)
+    pub fn with_registry(registry: Arc<ModelRegistry>) -> Self {
+        Self {
+            registry,
+            kept: HashSet::new(),
+        }
+    }
+
+    /// Never remove these keys, even at their default value
+    pub fn with_kept_keys<I, S>(mut self, keys: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.kept.extend(keys.into_iter().map(Into::into));
+        self
+    }
+
+    /// Default parameters of `model`
+    ///
+    /// Accepts registry IDs (`openai/gpt-4o`), abbreviations, and bare
+    /// model names as sent to the provider (`gpt-4o`).
+    pub fn defaults_for(&self, model: &str) -> Option<HashMap<String, Value>> {
+        self.registry
+            .get(model)
+            .or_else(|| {
+                self.registry
+                    .iter()
+                    .find(|card| {
+                        card.id
+                            .rsplit_once('/')
+                            .is_some_and(|(_, name)| name == model)
+                    })
+                    .cloned()
+            })
+            .map(|card| card.defaults)
+    }
+
+    /// Remove default parameters from a request object, returning the
+    /// removed keys
+    pub fn normalize_value(&self, value: &mut Value) -> Vec<String> {
+        let Value::Object(map) = value else {
+            return Vec::new();
+        };
+        let Some(defaults) = map
+            .get("model")
+            .and_then(Value::as_str)
+            .and_then(|model| self.defaults_for(model))
+        else {
+            return Vec::new();
+        };
+
+        let removed: Vec<String> = map
+            .iter()
+            .filter(|(key, val)| {
+                !self.kept.contains(*key) && defaults.get(*key).is_some_and(|d| same_value(val, d))
+            })
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in &removed {
+            map.remove(key);
+        }
+        removed
+    }
+
+    /// Remove default parameters from a JSON request
+    ///
+    /// Returns the input unchanged if it is not a request for a known model
+    /// or has nothing to remove.
+    pub fn normalize<'a>(&self, json: &'a str) -> Cow<'a, str> {
+        if !json.contains("\"model\"") {
+            return Cow::Borrowed(json);
+        }
+        let Ok(mut value) = serde_json::from_str::<Value>(json) else {
+            return Cow::Borrowed(json);
+        };
+        if self.normalize_value(&mut value).is_empty() {
+            return Cow::Borrowed(json);
+        }
+        serde_json::to_string(&value).map_or(Cow::Borrowed(json), Cow::Owned)
+    }
+}
+
+impl std::fmt::Debug for DefaultsNormalizer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DefaultsNormalizer")
+            .field("kept", &self.kept)
+            .finish_non_exhaustive()
+    }
+}
+
+/// JSON equality with `1` and `1.0` treated as equal
+fn same_value(value: &Value, default: &Value) -> bool {
+    match (value, default) {
+        (Value::Number(a), Value::Number(b)) => a.as_f64() == b.as_f64(),
+        _ => value == default,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_removes_model_defaults() {
+        let normalizer = DefaultsNormalizer::new();
+        let mut request = json!({
+            "model": "openai/gpt-4o",
+            "messages": [{"role": "user", "content": "Hi"}],
+            "temperature": 1,
+            "top_p": 1.0,
+            "n": 2,
+            "stream": false,
+            "presence_penalty": 0.0,
+        });
+        let mut removed = normalizer.normalize_value(&mut request);
+        removed.sort();
+        assert_eq!(
+            removed,
+            ["presence_penalty", "stream", "temperature", "top_p"]
+        );
+        assert_eq!(request["n"], 2);
+
+        // Bare provider names resolve; unknown models keep everything
+        let json = r#"{"model":"gpt-4o-mini","temperature":1}"#;
+        assert_eq!(normalizer.normalize(json), r#"{"model":"gpt-4o-mini"}"#);
+        let unknown = r#"{"model":"acme/secret-model","temperature":1}"#;
+        assert!(matches!(normalizer.normalize(unknown), Cow::Borrowed(_)));
+        assert!(matches!(normalizer.normalize("not json"), Cow::Borrowed(_)));
+
+        // Per-key opt-out
+        let kept = normalizer.with_kept_keys(["temperature"]);
+        assert_eq!(kept.normalize(json), json);
+    }
+}
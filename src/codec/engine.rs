@@ -4,17 +4,36 @@
 //! compression algorithm. Can also be guided by ML inference for
 //! intelligent routing decisions.
 
+use std::borrow::Cow;
+#[cfg(feature = "wasm-plugins")]
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
 use serde_json::Value;
+use tracing::field::Empty;
+use tracing::Span;
 
+#[cfg(feature = "brotli")]
 use super::brotli::BrotliCodec;
-use super::m2m::M2MCodec;
+use super::canonical::CanonicalMode;
+use super::defaults::DefaultsNormalizer;
+use super::feedback::{FeedbackSample, RouterFeedback, RouterThresholds};
+use super::limits::DecompressionLimits;
+use super::m2m::{CompressionHint, M2MCodec, MediaStats, TraceContext};
+use super::profile::CompressionProfile;
+use super::schema::PayloadSchema;
+#[cfg(feature = "brotli")]
+use super::shared_dict::SharedDictionary;
+#[cfg(feature = "token-native")]
 use super::token_native::TokenNativeCodec;
+use super::tools::ToolPacker;
 use super::{Algorithm, CompressionResult};
 use crate::error::{M2MError, Result};
 use crate::inference::HydraModel;
 use crate::models::Encoding;
 use crate::security::SecurityScanner;
-use crate::tokenizer::count_tokens_with_encoding;
+use crate::tokenizer::{count_tokens_cached, count_tokens_with_encoding};
 
 /// Content characteristics for algorithm selection
 #[derive(Debug, Clone)]
@@ -31,6 +50,11 @@ pub struct ContentAnalysis {
     pub has_tools: bool,
     /// Estimated token count
     pub estimated_tokens: usize,
+    /// Bytes of inline base64 media (images in content parts)
+    pub media_bytes: usize,
+    /// Candidates `compress_auto` skipped to stay within the latency budget
+    /// (see [`CodecEngine::with_max_compress_latency`])
+    pub skipped_for_budget: Vec<Algorithm>,
 }
 
 impl ContentAnalysis {
@@ -52,6 +76,10 @@ impl ContentAnalysis {
             (false, false)
         };
 
+        let media_bytes = parsed.as_ref().map_or(0, |value| {
+            MediaStats::from_json(value).inline_encoded_len() as usize
+        });
+
         // Simple repetition detection
         let repetition_ratio = Self::calculate_repetition(content);
 
@@ -65,6 +93,8 @@ impl ContentAnalysis {
             repetition_ratio,
             has_tools,
             estimated_tokens,
+            media_bytes,
+            skipped_for_budget: Vec::new(),
         }
     }
 
@@ -91,14 +121,35 @@ impl ContentAnalysis {
     }
 }
 
+/// Deadline for one automatic compression
+#[derive(Debug, Clone, Copy)]
+struct LatencyBudget {
+    deadline: Instant,
+}
+
+impl LatencyBudget {
+    fn new(start: Instant, budget: Duration) -> Self {
+        Self {
+            deadline: start + budget,
+        }
+    }
+
+    /// Whether work estimated at `cost` still finishes before the deadline
+    fn admits(&self, cost: Duration) -> bool {
+        Instant::now() + cost <= self.deadline
+    }
+}
+
 /// Codec engine with automatic algorithm selection
 #[derive(Clone)]
 pub struct CodecEngine {
     /// Token-native codec instance
+    #[cfg(feature = "token-native")]
     token_native: TokenNativeCodec,
     /// M2M codec instance (default for M2M v1 wire format - 100% JSON fidelity)
     m2m: M2MCodec,
     /// Brotli codec instance
+    #[cfg(feature = "brotli")]
     brotli: BrotliCodec,
     /// Hydra model for ML routing (optional)
     hydra: Option<HydraModel>,
@@ -108,18 +159,51 @@ pub struct CodecEngine {
     pub brotli_threshold: usize,
     /// Prefer M2M for LLM API payloads (default: true)
     pub prefer_m2m_for_api: bool,
+    /// Live-traffic calibration (optional)
+    feedback: Option<Arc<RouterFeedback>>,
+    /// Time budget for automatic compression (optional)
+    max_compress_latency: Option<Duration>,
+    /// Validate decompressed API payloads against their schema
+    pub validate_schema: bool,
+    /// Default profile for automatic selection
+    profile: CompressionProfile,
+    /// Bounds on decompressed output
+    limits: DecompressionLimits,
+    /// Strips provider-default parameters before compression (optional)
+    defaults: Option<Arc<DefaultsNormalizer>>,
+    /// Packs tool results before compression (optional)
+    tools: Option<ToolPacker>,
+    /// Called for every v2.0 frame handled
+    #[cfg(feature = "compat-v2")]
+    deprecation_hook: Option<Arc<dyn Fn(super::V2Usage) + Send + Sync>>,
+    /// Registered WASM codecs by algorithm ID
+    #[cfg(feature = "wasm-plugins")]
+    plugins: HashMap<u16, Arc<super::WasmCodec>>,
 }
 
 impl Default for CodecEngine {
     fn default() -> Self {
         Self {
+            #[cfg(feature = "token-native")]
             token_native: TokenNativeCodec::default(),
             m2m: M2MCodec::new(),
+            #[cfg(feature = "brotli")]
             brotli: BrotliCodec::new(),
             hydra: None,
             ml_routing: false,
             brotli_threshold: 1024, // 1KB
             prefer_m2m_for_api: true,
+            feedback: None,
+            max_compress_latency: None,
+            validate_schema: false,
+            profile: CompressionProfile::Balanced,
+            limits: DecompressionLimits::default(),
+            defaults: None,
+            tools: None,
+            #[cfg(feature = "compat-v2")]
+            deprecation_hook: None,
+            #[cfg(feature = "wasm-plugins")]
+            plugins: HashMap::new(),
         }
     }
 }
@@ -143,18 +227,267 @@ impl CodecEngine {
         self
     }
 
+    /// Choose byte-identical or canonical M2M round-trips (see [`CanonicalMode`])
+    pub fn with_canonical(mut self, mode: CanonicalMode) -> Self {
+        self.m2m = self.m2m.with_canonical(mode);
+        self
+    }
+
+    /// Bound decompressed output (see [`DecompressionLimits`])
+    ///
+    /// Brotli-compressed payloads stop decoding as soon as they pass the
+    /// limits; other algorithms are checked once decoded.
+    pub fn with_decompression_limits(mut self, limits: DecompressionLimits) -> Self {
+        self.m2m = self.m2m.with_limits(limits);
+        #[cfg(feature = "brotli")]
+        {
+            self.brotli.limits = limits;
+        }
+        self.limits = limits;
+        self
+    }
+
+    /// Strip provider-default parameters before compression
+    ///
+    /// Requests are normalized by every `compress*` method (see
+    /// [`DefaultsNormalizer`]); decompression does not restore them.
+    /// `original_bytes` still reports the size of the request as given.
+    pub fn with_remove_defaults(mut self, normalizer: Option<DefaultsNormalizer>) -> Self {
+        self.defaults = normalizer.map(Arc::new);
+        self
+    }
+
+    /// Minify and cross-reference tool results before compression
+    ///
+    /// See [`ToolPacker`]. References are expanded by
+    /// [`decompress`](Self::decompress) whether or not packing is enabled.
+    pub fn with_tool_packing(mut self, packer: Option<ToolPacker>) -> Self {
+        self.tools = packer;
+        self
+    }
+
+    /// Apply default removal and tool packing, if enabled
+    fn normalize<'a>(&self, content: &'a str) -> Result<Cow<'a, str>> {
+        let mut content = match &self.defaults {
+            Some(normalizer) => normalizer.normalize(content),
+            None => Cow::Borrowed(content),
+        };
+        if let Some(packer) = self.tools {
+            let (packed, report) = packer.pack(&content)?;
+            if report.minified > 0 || report.deduplicated > 0 {
+                content = Cow::Owned(packed);
+            }
+        }
+        Ok(content)
+    }
+
+    /// Bounds on decompressed output
+    pub fn decompression_limits(&self) -> DecompressionLimits {
+        self.limits
+    }
+
+    /// Validate decompressed API payloads (see [`PayloadSchema`])
+    pub fn with_schema_validation(mut self, enabled: bool) -> Self {
+        self.validate_schema = enabled;
+        self
+    }
+
+    /// Tune the engine for a workload (see [`CompressionProfile`])
+    ///
+    /// Sets the Brotli quality and threshold; call
+    /// [`with_brotli_threshold`](Self::with_brotli_threshold) afterwards to
+    /// override the threshold.
+    pub fn with_profile(mut self, profile: CompressionProfile) -> Self {
+        self.profile = profile;
+        #[cfg(feature = "brotli")]
+        {
+            self.brotli.quality = profile.brotli_quality().min(11);
+        }
+        self.brotli_threshold = profile.thresholds().brotli_threshold;
+        self
+    }
+
+    /// Compress Brotli payloads with a dictionary shared with the peer
+    ///
+    /// `None` restores plain Brotli. Payloads compressed through
+    /// [`compress_with_profile`](Self::compress_with_profile) never use the
+    /// dictionary.
+    #[cfg(feature = "brotli")]
+    pub fn with_dictionary(mut self, dictionary: Option<Arc<SharedDictionary>>) -> Self {
+        self.brotli.dictionary = dictionary;
+        self
+    }
+
+    /// Cap the Brotli quality, e.g. at the limit a peer negotiated
+    ///
+    /// `None` leaves the quality unchanged. Only standalone Brotli payloads
+    /// are affected.
+    pub fn with_max_brotli_quality(self, quality: Option<u32>) -> Self {
+        #[cfg(feature = "brotli")]
+        if let Some(quality) = quality {
+            let mut engine = self;
+            engine.brotli.quality = engine.brotli.quality.min(quality);
+            return engine;
+        }
+        let _ = quality;
+        self
+    }
+
+    /// Default profile for automatic selection
+    pub fn profile(&self) -> CompressionProfile {
+        self.profile
+    }
+
     /// Set Brotli threshold
     pub fn with_brotli_threshold(mut self, threshold: usize) -> Self {
         self.brotli_threshold = threshold;
         self
     }
 
+    /// Record auto-selection samples and use calibrated thresholds
+    ///
+    /// The same [`RouterFeedback`] can be shared by several engines.
+    pub fn with_feedback(mut self, feedback: Arc<RouterFeedback>) -> Self {
+        self.feedback = Some(feedback);
+        self
+    }
+
+    /// Bound the time automatic compression may take
+    ///
+    /// `compress_auto*` skips candidates whose estimated cost (e.g. Brotli
+    /// quality 11 on a multi-megabyte payload) does not fit in what is left
+    /// of the budget and falls back to the fastest candidate that does, or
+    /// to passthrough. Skipped candidates are listed in
+    /// [`ContentAnalysis::skipped_for_budget`]. A candidate is never
+    /// interrupted once started; `None` removes the budget.
+    pub fn with_max_compress_latency(mut self, budget: Option<Duration>) -> Self {
+        self.max_compress_latency = budget;
+        self
+    }
+
+    /// Time budget for automatic compression, if any
+    pub fn max_compress_latency(&self) -> Option<Duration> {
+        self.max_compress_latency
+    }
+
+    /// Get router feedback collector, if enabled
+    pub fn feedback(&self) -> Option<&Arc<RouterFeedback>> {
+        self.feedback.as_ref()
+    }
+
+    /// Thresholds used for heuristic selection
+    ///
+    /// Calibrated thresholds from [`RouterFeedback`] take precedence over
+    /// `brotli_threshold` and the profile's thresholds.
+    pub fn thresholds(&self) -> RouterThresholds {
+        self.thresholds_for(self.profile)
+    }
+
+    /// Thresholds used for heuristic selection under `profile`
+    fn thresholds_for(&self, profile: CompressionProfile) -> RouterThresholds {
+        if let Some(calibrated) = self.feedback.as_ref().and_then(|f| f.thresholds()) {
+            return calibrated;
+        }
+        if profile == self.profile {
+            RouterThresholds {
+                brotli_threshold: self.brotli_threshold,
+                ..profile.thresholds()
+            }
+        } else {
+            profile.thresholds()
+        }
+    }
+
+    /// Report v2.0 frames to `hook` instead of logging a warning
+    ///
+    /// Use it to count or trace the agents still sending the deprecated
+    /// format.
+    #[cfg(feature = "compat-v2")]
+    pub fn with_deprecation_hook(
+        mut self,
+        hook: impl Fn(super::V2Usage) + Send + Sync + 'static,
+    ) -> Self {
+        self.deprecation_hook = Some(Arc::new(hook));
+        self
+    }
+
+    /// Compress to a deprecated v2.0 Zlib frame for agents not yet upgraded
+    #[cfg(feature = "compat-v2")]
+    pub fn compress_v2(&self, content: &str) -> Result<String> {
+        let wire = super::ZlibCodec::new().compress(content)?;
+        self.report_v2(super::V2Usage::Encoded(wire.len()));
+        Ok(wire)
+    }
+
+    #[cfg(feature = "compat-v2")]
+    fn report_v2(&self, usage: super::V2Usage) {
+        match &self.deprecation_hook {
+            Some(hook) => hook(usage),
+            None => tracing::warn!(?usage, "Deprecated M2M v2.0 frame; upgrade the peer"),
+        }
+    }
+
+    /// Decode a v2.0 frame
+    #[cfg(feature = "compat-v2")]
+    fn decompress_v2(&self, wire: &str) -> Result<String> {
+        self.report_v2(super::V2Usage::Decoded(wire.len()));
+        super::ZlibCodec::new().decompress(wire)
+    }
+
+    /// Decode a v2.0 frame
+    #[cfg(not(feature = "compat-v2"))]
+    #[allow(clippy::unused_self)]
+    fn decompress_v2(&self, _wire: &str) -> Result<String> {
+        Err(M2MError::Decompression(
+            "M2M v2.0 frames require the compat-v2 feature".to_string(),
+        ))
+    }
+
     /// Set token-native encoding
-    pub fn with_encoding(mut self, encoding: Encoding) -> Self {
-        self.token_native = TokenNativeCodec::new(encoding);
+    ///
+    /// Has no effect without the `token-native` feature.
+    pub fn with_encoding(self, encoding: Encoding) -> Self {
+        #[cfg(feature = "token-native")]
+        return Self {
+            token_native: TokenNativeCodec::new(encoding),
+            ..self
+        };
+
+        #[cfg(not(feature = "token-native"))]
+        {
+            let _ = encoding;
+            self
+        }
+    }
+
+    /// Set token-native codec (e.g. one with a loaded Llama 3 vocabulary)
+    #[cfg(feature = "token-native")]
+    pub fn with_token_native(mut self, codec: TokenNativeCodec) -> Self {
+        self.token_native = codec;
+        self
+    }
+
+    /// Register a WASM codec as `Algorithm::Custom(codec.id())`
+    ///
+    /// Replaces any plugin registered under the same ID. To negotiate it,
+    /// also list the algorithm in the agent's `CompressionCaps`.
+    #[cfg(feature = "wasm-plugins")]
+    pub fn with_plugin(mut self, codec: super::WasmCodec) -> Self {
+        self.plugins.insert(codec.id(), Arc::new(codec));
         self
     }
 
+    /// Plugin registered for `id`
+    #[cfg(feature = "wasm-plugins")]
+    fn plugin(&self, id: u16) -> Result<&super::WasmCodec> {
+        self.plugins.get(&id).map(AsRef::as_ref).ok_or_else(|| {
+            M2MError::InvalidCodec(format!(
+                "No WASM plugin registered for {}",
+                Algorithm::Custom(id)
+            ))
+        })
+    }
+
     /// Compress with specified algorithm and track token counts
     ///
     /// This method counts tokens before and after compression to provide
@@ -166,7 +499,7 @@ impl CodecEngine {
         algorithm: Algorithm,
         encoding: Encoding,
     ) -> Result<CompressionResult> {
-        let original_tokens = count_tokens_with_encoding(content, encoding);
+        let original_tokens = count_tokens_cached(content, encoding);
         let mut result = self.compress(content, algorithm)?;
 
         let compressed_tokens = count_tokens_with_encoding(&result.data, encoding);
@@ -182,6 +515,8 @@ impl CodecEngine {
     /// 1. Scans plaintext for threats using Hydra/patterns
     /// 2. If safe, compresses with optimal algorithm
     /// 3. If threat detected and blocking enabled, returns error
+    /// 4. If the scanner's policy redacts a threat, compresses the
+    ///    redacted content
     ///
     /// Epistemic basis:
     /// - K: Threats exist in plaintext, not compressed form
@@ -209,6 +544,7 @@ impl CodecEngine {
         }
 
         // 2. If safe (or not blocking), compress with optimal algorithm
+        let content = &scanner.redact(content, &scan_result);
         let analysis = ContentAnalysis::analyze(content);
         let algorithm = self.select_algorithm(&analysis);
         self.compress(content, algorithm)
@@ -245,11 +581,39 @@ impl CodecEngine {
         let algorithm = self.select_algorithm(&analysis);
 
         let result = self.compress_with_tokens(content, algorithm, encoding)?;
+        if let Some(mut fallback) = Self::expansion_fallback(content, &result) {
+            let tokens = result.original_tokens.unwrap_or(0);
+            fallback.original_tokens = Some(tokens);
+            fallback.compressed_tokens = Some(tokens);
+            self.record_feedback(content, &analysis, &fallback);
+            return Ok((fallback, Algorithm::None));
+        }
+        self.record_feedback(content, &analysis, &result);
         Ok((result, algorithm))
     }
 
     /// Compress with specified algorithm
+    #[tracing::instrument(
+        name = "codec.compress",
+        level = "debug",
+        skip_all,
+        fields(algorithm = %algorithm, bytes_in = content.len(), bytes_out = Empty)
+    )]
     pub fn compress(&self, content: &str, algorithm: Algorithm) -> Result<CompressionResult> {
+        let normalized = self.normalize(content)?;
+        let mut result = self.compress_normalized(&normalized, algorithm)?;
+        result.original_bytes = content.len();
+
+        Span::current().record("bytes_out", result.compressed_bytes);
+        Ok(result)
+    }
+
+    /// Compress content that has already been normalized
+    fn compress_normalized(
+        &self,
+        content: &str,
+        algorithm: Algorithm,
+    ) -> Result<CompressionResult> {
         match algorithm {
             Algorithm::None => Ok(CompressionResult::new(
                 content.to_string(),
@@ -268,20 +632,362 @@ impl CodecEngine {
                     wire.len(),
                 ))
             },
+            #[cfg(feature = "token-native")]
             Algorithm::TokenNative => self.token_native.compress(content),
+            #[cfg(feature = "brotli")]
             Algorithm::Brotli => self.brotli.compress(content),
+            #[cfg(feature = "wasm-plugins")]
+            Algorithm::Custom(id) => self.plugin(id)?.compress(content),
+            #[cfg(not(all(
+                feature = "token-native",
+                feature = "brotli",
+                feature = "wasm-plugins"
+            )))]
+            unavailable => Err(unavailable.unavailable()),
         }
     }
 
+    /// Compress to an M2M frame carrying a sender hint
+    ///
+    /// The hint decides payload compression instead of the size threshold;
+    /// see [`CompressionHint`].
+    #[tracing::instrument(
+        name = "codec.compress_hinted",
+        level = "debug",
+        skip_all,
+        fields(hint = %hint, bytes_in = content.len(), bytes_out = Empty)
+    )]
+    pub fn compress_with_hint(
+        &self,
+        content: &str,
+        hint: CompressionHint,
+    ) -> Result<CompressionResult> {
+        let wire = self
+            .m2m
+            .encode_string_with_hint(&self.normalize(content)?, hint)?;
+        Span::current().record("bytes_out", wire.len());
+        let wire_len = wire.len();
+        Ok(CompressionResult::new(
+            wire,
+            Algorithm::M2M,
+            content.len(),
+            wire_len,
+        ))
+    }
+
+    /// Compress to an M2M frame carrying the sender's W3C trace context
+    ///
+    /// Receivers read it with [`M2MFrame::peek_trace_context`] and continue
+    /// the trace (see [`TraceContext`]).
+    ///
+    /// [`M2MFrame::peek_trace_context`]: super::M2MFrame::peek_trace_context
+    #[tracing::instrument(
+        name = "codec.compress_traced",
+        level = "debug",
+        skip_all,
+        fields(trace = %context, bytes_in = content.len(), bytes_out = Empty)
+    )]
+    pub fn compress_with_trace(
+        &self,
+        content: &str,
+        context: &TraceContext,
+    ) -> Result<CompressionResult> {
+        let wire = self
+            .m2m
+            .encode_string_with_extensions(&self.normalize(content)?, &[context.to_extension()])?;
+        Span::current().record("bytes_out", wire.len());
+        let wire_len = wire.len();
+        Ok(CompressionResult::new(
+            wire,
+            Algorithm::M2M,
+            content.len(),
+            wire_len,
+        ))
+    }
+
     /// Compress with automatic algorithm selection
+    ///
+    /// If the selected algorithm does not shrink the payload (tiny or
+    /// already-compressed content), the content is passed through with
+    /// `Algorithm::None` and `fallback_from` records the original choice.
     pub fn compress_auto(&self, content: &str) -> Result<(CompressionResult, Algorithm)> {
-        let analysis = ContentAnalysis::analyze(content);
-        let algorithm = self.select_algorithm(&analysis);
+        self.compress_auto_with_profile(content, self.profile)
+    }
 
-        let result = self.compress(content, algorithm)?;
+    /// Compress with automatic algorithm selection under `profile`
+    ///
+    /// Overrides the engine's profile for one call, e.g. from a per-request
+    /// header. Under an exhaustive profile the smallest candidate output is
+    /// kept.
+    pub fn compress_auto_with_profile(
+        &self,
+        content: &str,
+        profile: CompressionProfile,
+    ) -> Result<(CompressionResult, Algorithm)> {
+        let (result, _) = self.compress_auto_analyzed(content, profile)?;
+        let algorithm = result.algorithm;
         Ok((result, algorithm))
     }
 
+    /// Compress with automatic selection, returning the content analysis
+    ///
+    /// The analysis lists the candidates skipped because they would not
+    /// fit the latency budget.
+    #[tracing::instrument(
+        name = "codec.compress_auto",
+        level = "debug",
+        skip_all,
+        fields(
+            bytes_in = content.len(),
+            profile = %profile,
+            selected = Empty,
+            fallback = Empty,
+            budget_skipped = Empty
+        )
+    )]
+    pub fn compress_auto_analyzed(
+        &self,
+        content: &str,
+        profile: CompressionProfile,
+    ) -> Result<(CompressionResult, ContentAnalysis)> {
+        let budget = self
+            .max_compress_latency
+            .map(|budget| LatencyBudget::new(Instant::now(), budget));
+        let original_bytes = content.len();
+        let normalized = self.normalize(content)?;
+        let content = normalized.as_ref();
+        let mut analysis = ContentAnalysis::analyze(content);
+        let selected = self.select_with_profile(&analysis, profile);
+
+        let candidates: Vec<Algorithm> = if profile.exhaustive() && selected != Algorithm::None {
+            profile
+                .candidates()
+                .iter()
+                .copied()
+                .filter(|&algo| algo != Algorithm::None && algo.is_available())
+                .collect()
+        } else {
+            vec![selected]
+        };
+        let mut result = self.compress_smallest_within(
+            content,
+            &candidates,
+            profile,
+            budget,
+            &mut analysis.skipped_for_budget,
+        )?;
+        if result.is_none() {
+            result = Some(self.compress_fastest_within(content, &mut analysis, profile, budget)?);
+        }
+        let mut result = result.expect("fallback always yields a result");
+        let algorithm = result.algorithm;
+        Span::current().record("selected", tracing::field::display(algorithm));
+        if !analysis.skipped_for_budget.is_empty() {
+            Span::current().record("budget_skipped", analysis.skipped_for_budget.len());
+        }
+
+        if let Some(mut fallback) = Self::expansion_fallback(content, &result) {
+            Span::current().record("fallback", true);
+            self.record_feedback(content, &analysis, &fallback);
+            fallback.original_bytes = original_bytes;
+            return Ok((fallback, analysis));
+        }
+        self.record_feedback(content, &analysis, &result);
+        result.original_bytes = original_bytes;
+        Ok((result, analysis))
+    }
+
+    /// Compress with `algorithm` using the profile's Brotli quality
+    fn compress_with_profile(
+        &self,
+        content: &str,
+        algorithm: Algorithm,
+        profile: CompressionProfile,
+    ) -> Result<CompressionResult> {
+        #[cfg(feature = "brotli")]
+        if algorithm == Algorithm::Brotli && profile != self.profile {
+            return BrotliCodec::with_quality(profile.brotli_quality()).compress(content);
+        }
+        #[cfg(not(feature = "brotli"))]
+        let _ = profile;
+        self.compress_normalized(content, algorithm)
+    }
+
+    /// Smallest output of `candidates` (failing candidates are skipped)
+    fn compress_smallest(
+        &self,
+        content: &str,
+        candidates: &[Algorithm],
+        profile: CompressionProfile,
+    ) -> Result<CompressionResult> {
+        self.compress_smallest_within(content, candidates, profile, None, &mut Vec::new())?
+            .ok_or_else(|| M2MError::Compression("All algorithms failed".to_string()))
+    }
+
+    /// Smallest output of the `candidates` that fit in `budget`
+    ///
+    /// Candidates that do not fit are added to `skipped`. `None` if every
+    /// candidate was skipped; an error if every candidate that ran failed.
+    fn compress_smallest_within(
+        &self,
+        content: &str,
+        candidates: &[Algorithm],
+        profile: CompressionProfile,
+        budget: Option<LatencyBudget>,
+        skipped: &mut Vec<Algorithm>,
+    ) -> Result<Option<CompressionResult>> {
+        let mut ran = false;
+        let mut smallest: Option<CompressionResult> = None;
+        for &algo in candidates {
+            let cost = self.estimated_cost(content.len(), algo, profile);
+            if budget.is_some_and(|budget| !budget.admits(cost)) {
+                skipped.push(algo);
+                continue;
+            }
+            ran = true;
+            if let Ok(result) = self.compress_with_profile(content, algo, profile) {
+                if smallest
+                    .as_ref()
+                    .is_none_or(|best| result.compressed_bytes < best.compressed_bytes)
+                {
+                    smallest = Some(result);
+                }
+            }
+        }
+        match smallest {
+            None if ran => Err(M2MError::Compression("All algorithms failed".to_string())),
+            smallest => Ok(smallest),
+        }
+    }
+
+    /// Output of the cheapest candidate that fits in `budget`
+    ///
+    /// Used once the selected candidates were skipped; falls back to
+    /// passthrough when nothing else fits.
+    fn compress_fastest_within(
+        &self,
+        content: &str,
+        analysis: &mut ContentAnalysis,
+        profile: CompressionProfile,
+        budget: Option<LatencyBudget>,
+    ) -> Result<CompressionResult> {
+        let skipped = &mut analysis.skipped_for_budget;
+        let mut candidates: Vec<(Duration, Algorithm)> = profile
+            .candidates()
+            .iter()
+            .copied()
+            .filter(|&algo| {
+                algo != Algorithm::None
+                    && profile.allows(algo)
+                    && (algo != Algorithm::M2M || analysis.is_json)
+                    && !skipped.contains(&algo)
+            })
+            .map(|algo| (self.estimated_cost(content.len(), algo, profile), algo))
+            .collect();
+        candidates.sort_by_key(|&(cost, _)| cost);
+
+        for (cost, algo) in candidates {
+            if budget.is_some_and(|budget| !budget.admits(cost)) {
+                skipped.push(algo);
+                continue;
+            }
+            if let Ok(result) = self.compress_with_profile(content, algo, profile) {
+                return Ok(result);
+            }
+        }
+
+        let attempted = skipped.first().copied().unwrap_or(Algorithm::None);
+        Ok(CompressionResult::passthrough_fallback(content, attempted))
+    }
+
+    /// Conservative estimate of the time `algorithm` takes on `bytes`
+    ///
+    /// Based on release-build throughput on chat JSON, halved: Brotli runs
+    /// at ~60 MB/s up to quality 5, ~20 MB/s up to 9 and ~0.2 MB/s at 10-11;
+    /// M2M frames (Brotli 5 plus headers) at ~18 MB/s; TokenNative at
+    /// ~3 MB/s. Passthrough and WASM plugins are treated as free, so only
+    /// the elapsed time limits them.
+    fn estimated_cost(
+        &self,
+        bytes: usize,
+        algorithm: Algorithm,
+        profile: CompressionProfile,
+    ) -> Duration {
+        let bytes_per_sec: u64 = match algorithm {
+            Algorithm::M2M => 9_000_000,
+            Algorithm::TokenNative => 1_500_000,
+            Algorithm::Brotli => match self.brotli_quality(profile) {
+                0..=5 => 30_000_000,
+                6..=9 => 10_000_000,
+                _ => 100_000,
+            },
+            _ => return Duration::ZERO,
+        };
+        Duration::from_secs_f64(bytes as f64 / bytes_per_sec as f64)
+    }
+
+    /// Brotli quality standalone Brotli compresses with under `profile`
+    fn brotli_quality(&self, profile: CompressionProfile) -> u32 {
+        #[cfg(feature = "brotli")]
+        if profile == self.profile {
+            return self.brotli.quality;
+        }
+        profile.brotli_quality()
+    }
+
+    /// Record an auto-selection sample, probing every candidate when due
+    fn record_feedback(
+        &self,
+        content: &str,
+        analysis: &ContentAnalysis,
+        result: &CompressionResult,
+    ) {
+        let Some(ref feedback) = self.feedback else {
+            return;
+        };
+
+        let mut sample = FeedbackSample::new(analysis, result);
+        if feedback.should_probe() {
+            let candidates: &[Algorithm] = if analysis.is_json {
+                &[Algorithm::M2M, Algorithm::Brotli]
+            } else {
+                &[Algorithm::Brotli]
+            };
+            let sizes = candidates
+                .iter()
+                .filter_map(|&algo| {
+                    self.compress_normalized(content, algo)
+                        .ok()
+                        .map(|r| (algo, r.compressed_bytes))
+                })
+                .collect();
+            sample = sample.with_sizes(sizes);
+        }
+        feedback.record(sample);
+    }
+
+    /// Passthrough result if compression did not shrink the payload
+    ///
+    /// Epistemic basis:
+    /// - K: Output size is known after compression
+    /// - B: Selection heuristics can misjudge tiny or high-entropy content
+    fn expansion_fallback(content: &str, result: &CompressionResult) -> Option<CompressionResult> {
+        if result.algorithm == Algorithm::None || result.compressed_bytes < result.original_bytes {
+            return None;
+        }
+
+        tracing::debug!(
+            "{} expanded {} -> {} bytes, falling back to passthrough",
+            result.algorithm,
+            result.original_bytes,
+            result.compressed_bytes
+        );
+        Some(CompressionResult::passthrough_fallback(
+            content,
+            result.algorithm,
+        ))
+    }
+
     /// Compress JSON value with automatic selection
     pub fn compress_value(&self, value: &Value) -> Result<(CompressionResult, Algorithm)> {
         let content = serde_json::to_string(value)?;
@@ -290,17 +996,32 @@ impl CodecEngine {
 
     /// Select optimal algorithm based on content analysis
     pub fn select_algorithm(&self, analysis: &ContentAnalysis) -> Algorithm {
-        // If ML routing is enabled and Hydra model is available, use ML
-        if self.ml_routing {
-            return self.ml_select_algorithm(analysis);
-        }
+        self.select_with_profile(analysis, self.profile)
+    }
 
-        // Heuristic-based selection
-        self.heuristic_select_algorithm(analysis)
+    /// Select an algorithm allowed by `profile`
+    fn select_with_profile(
+        &self,
+        analysis: &ContentAnalysis,
+        profile: CompressionProfile,
+    ) -> Algorithm {
+        // If ML routing is enabled and Hydra model is available, use ML
+        let algorithm = if self.ml_routing && profile.ml_routing() {
+            self.ml_select_algorithm(analysis, profile)
+        } else {
+            // Heuristic-based selection
+            self.thresholds_for(profile)
+                .select(analysis, self.prefer_m2m_for_api)
+        };
+        profile.restrict(algorithm, analysis.is_json)
     }
 
     /// ML-based algorithm selection using Hydra SLM
-    fn ml_select_algorithm(&self, analysis: &ContentAnalysis) -> Algorithm {
+    fn ml_select_algorithm(
+        &self,
+        analysis: &ContentAnalysis,
+        profile: CompressionProfile,
+    ) -> Algorithm {
         // Use Hydra model if available
         if let Some(ref hydra) = self.hydra {
             // Hydra needs the raw content, but we only have analysis
@@ -313,22 +1034,24 @@ impl CodecEngine {
         }
 
         // Fall back to heuristics
-        self.heuristic_select_algorithm(analysis)
+        self.thresholds_for(profile)
+            .select(analysis, self.prefer_m2m_for_api)
     }
 
     /// Select algorithm with full content access (for ML routing)
     pub fn select_algorithm_for_content(&self, content: &str) -> Algorithm {
+        let analysis = ContentAnalysis::analyze(content);
+
         // If ML routing is enabled and Hydra model is available, use ML
-        if self.ml_routing {
+        if self.ml_routing && self.profile.ml_routing() {
             if let Some(ref hydra) = self.hydra {
                 if let Ok(decision) = hydra.predict_compression(content) {
-                    return decision.algorithm;
+                    return self.profile.restrict(decision.algorithm, analysis.is_json);
                 }
             }
         }
 
         // Fall back to analysis-based selection
-        let analysis = ContentAnalysis::analyze(content);
         self.heuristic_select_algorithm(&analysis)
     }
 
@@ -339,50 +1062,65 @@ impl CodecEngine {
     /// - K: Brotli is optimal for large repetitive content (>1KB)
     /// - B: M2M is best for small-medium LLM API JSON (<1KB)
     fn heuristic_select_algorithm(&self, analysis: &ContentAnalysis) -> Algorithm {
-        // Small content: no compression (overhead not worth it)
-        // Epistemic: K - compression overhead exceeds savings
-        if analysis.length < 100 {
-            return Algorithm::None;
-        }
-
-        // Large content (>1KB): Brotli is almost always best
-        // Epistemic: K - Brotli achieves 40-60% savings on large content
-        if analysis.length > self.brotli_threshold {
-            return Algorithm::Brotli;
-        }
-
-        // Medium LLM API JSON (100-1KB): M2M compression (100% fidelity)
-        // Epistemic: K - M2M achieves ~60-70% compression with routing headers
-        if analysis.is_llm_api && self.prefer_m2m_for_api {
-            return Algorithm::M2M;
-        }
-
-        // Medium content with high repetition: Brotli
-        if analysis.repetition_ratio > 0.3 {
-            return Algorithm::Brotli;
-        }
-
-        // Default: M2M for JSON (optimal for M2M wire format), None for others
-        if analysis.is_json {
-            Algorithm::M2M
-        } else {
-            Algorithm::None
-        }
+        let algorithm = self.thresholds().select(analysis, self.prefer_m2m_for_api);
+        self.profile.restrict(algorithm, analysis.is_json)
     }
 
     /// Decompress content (auto-detects algorithm from wire format)
+    ///
+    /// With schema validation enabled, recognized API payloads are checked
+    /// and a `SchemaViolation` names the first malformed field.
+    #[tracing::instrument(
+        name = "codec.decompress",
+        level = "debug",
+        skip_all,
+        fields(algorithm = Empty, bytes_in = wire.len(), bytes_out = Empty)
+    )]
     pub fn decompress(&self, wire: &str) -> Result<String> {
+        if super::is_v2_frame(wire) {
+            let json = self.decompress_v2(wire)?;
+            self.limits.check(wire.len(), json.len())?;
+            return Ok(json);
+        }
         let algorithm = super::detect_algorithm(wire).unwrap_or(Algorithm::None);
+        Span::current().record("algorithm", tracing::field::display(algorithm));
 
-        match algorithm {
-            Algorithm::None => Ok(wire.to_string()),
+        let json = match algorithm {
+            Algorithm::None => wire.to_string(),
             Algorithm::M2M => {
                 // M2M wire format - 100% JSON fidelity
-                self.m2m.decode_string(wire)
+                self.m2m.decode_string(wire)?
             },
-            Algorithm::TokenNative => self.token_native.decompress(wire),
-            Algorithm::Brotli => self.brotli.decompress(wire),
+            #[cfg(feature = "token-native")]
+            Algorithm::TokenNative => self.token_native.decompress(wire)?,
+            #[cfg(feature = "brotli")]
+            Algorithm::Brotli => self.brotli.decompress(wire)?,
+            #[cfg(feature = "wasm-plugins")]
+            Algorithm::Custom(id) => self.plugin(id)?.decompress(wire)?,
+            #[cfg(not(all(
+                feature = "token-native",
+                feature = "brotli",
+                feature = "wasm-plugins"
+            )))]
+            unavailable => return Err(unavailable.unavailable()),
+        };
+        self.limits.check(wire.len(), json.len())?;
+        let json = match ToolPacker::expand(&json, self.limits.limit_for(wire.len()))? {
+            Cow::Borrowed(_) => json,
+            Cow::Owned(expanded) => expanded,
+        };
+
+        if self.validate_schema && algorithm != Algorithm::None {
+            // Non-JSON and unrecognized payloads are not API traffic
+            if let Ok(value) = serde_json::from_str::<Value>(&json) {
+                if let Some(schema) = PayloadSchema::detect(&value) {
+                    schema.validate(&value)?;
+                }
+            }
         }
+
+        Span::current().record("bytes_out", json.len());
+        Ok(json)
     }
 
     /// Decompress to JSON value
@@ -393,23 +1131,12 @@ impl CodecEngine {
 
     /// Try all algorithms and return best result
     pub fn compress_best(&self, content: &str) -> Result<CompressionResult> {
-        let mut best: Option<CompressionResult> = None;
-
-        // Try each algorithm (M2M first as best for 100% fidelity)
-        for algo in [Algorithm::M2M, Algorithm::TokenNative, Algorithm::Brotli] {
-            if let Ok(result) = self.compress(content, algo) {
-                let is_better = match &best {
-                    None => true,
-                    Some(current) => result.compressed_bytes < current.compressed_bytes,
-                };
-
-                if is_better {
-                    best = Some(result);
-                }
-            }
-        }
-
-        best.ok_or_else(|| M2MError::Compression("All algorithms failed".to_string()))
+        // M2M first: ties go to 100% fidelity
+        self.compress_smallest(
+            content,
+            &[Algorithm::M2M, Algorithm::TokenNative, Algorithm::Brotli],
+            self.profile,
+        )
     }
 
     /// Get analysis for content
@@ -489,6 +1216,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(feature = "brotli")]
     fn test_large_content_selects_brotli() {
         let engine = CodecEngine::new();
 
@@ -503,6 +1231,76 @@ mod tests {
         assert_eq!(engine.select_algorithm(&analysis), Algorithm::Brotli);
     }
 
+    #[test]
+    fn test_media_heavy_content_skips_compression() {
+        let engine = CodecEngine::new();
+        let content = format!(
+            r#"{{"model":"claude-3-5-sonnet","messages":[{{"role":"user","content":[{{"type":"image","source":{{"type":"base64","media_type":"image/png","data":"{}"}}}}]}}]}}"#,
+            "iVBORw0KGgo".repeat(400)
+        );
+        let analysis = ContentAnalysis::analyze(&content);
+
+        assert_eq!(analysis.media_bytes, 4400);
+        assert_eq!(engine.select_algorithm(&analysis), Algorithm::None);
+    }
+
+    #[test]
+    #[cfg(feature = "brotli")]
+    fn test_compress_auto_expansion_fallback() {
+        let engine = CodecEngine::new();
+
+        // High-entropy content selects Brotli but expands after base64
+        let mut seed = 0x2545_f491_u32;
+        let content: String = (0..2000)
+            .map(|_| {
+                seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                char::from(b'!' + ((seed >> 16) % 94) as u8)
+            })
+            .collect();
+        let (result, algorithm) = engine.compress_auto(&content).unwrap();
+        assert_eq!(algorithm, Algorithm::None);
+        assert_eq!(result.algorithm, Algorithm::None);
+        assert_eq!(result.fallback_from, Some(Algorithm::Brotli));
+        assert_eq!(result.data, content);
+        assert_eq!(result.compressed_bytes, result.original_bytes);
+        assert_eq!(engine.decompress(&result.data).unwrap(), content);
+
+        // Compressible content is unaffected
+        let content = format!(
+            r#"{{"model":"gpt-4o","messages":[{{"role":"user","content":"{}"}}]}}"#,
+            "hello world ".repeat(200)
+        );
+        let (result, algorithm) = engine.compress_auto(&content).unwrap();
+        assert_ne!(algorithm, Algorithm::None);
+        assert!(!result.is_fallback());
+        assert!(result.compressed_bytes < result.original_bytes);
+    }
+
+    #[test]
+    fn test_router_feedback() {
+        let feedback = Arc::new(
+            RouterFeedback::new()
+                .with_probe_interval(1)
+                .with_refit_interval(0),
+        );
+        let engine = CodecEngine::new().with_feedback(feedback.clone());
+
+        let content = r#"{"model":"gpt-4o","messages":[{"role":"user","content":"Summarize the quarterly report for the finance team please"}]}"#;
+        engine.compress_auto(content).unwrap();
+        assert_eq!(feedback.sample_count(), 1);
+        assert_eq!(feedback.mean_ratios()[0].0, Algorithm::M2M);
+
+        // Calibrated thresholds override the engine's own
+        assert_eq!(engine.thresholds(), RouterThresholds::default());
+        let feedback = Arc::new(RouterFeedback::new().with_thresholds(RouterThresholds {
+            min_compress_bytes: 10_000,
+            ..RouterThresholds::default()
+        }));
+        let engine = engine.with_feedback(feedback);
+        let (result, _) = engine.compress_auto(content).unwrap();
+        assert_eq!(result.algorithm, Algorithm::None);
+    }
+
     #[test]
     fn test_ml_routing_with_hydra() {
         let hydra = HydraModel::fallback_only();
@@ -517,6 +1315,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(feature = "token-native")]
     fn test_token_native_roundtrip() {
         let engine = CodecEngine::new();
         let content = r#"{"model":"gpt-4o","messages":[{"role":"user","content":"Hello!"}]}"#;
@@ -527,4 +1326,208 @@ mod tests {
         let decompressed = engine.decompress(&result.data).unwrap();
         assert_eq!(content, decompressed);
     }
+
+    #[test]
+    fn test_schema_validated_decompression() {
+        let engine = CodecEngine::new().with_schema_validation(true);
+
+        let valid = r#"{"model":"gpt-4o","messages":[{"role":"user","content":"Hi"}]}"#;
+        let wire = engine.compress(valid, Algorithm::M2M).unwrap();
+        assert_eq!(engine.decompress(&wire.data).unwrap(), valid);
+
+        // Shape errors a codec bug could produce are caught on decode
+        let broken = r#"{"model":"gpt-4o","messages":[{"role":"user"}]}"#;
+        let wire = engine.compress(broken, Algorithm::M2M).unwrap();
+        match engine.decompress(&wire.data) {
+            Err(M2MError::SchemaViolation { path, .. }) => {
+                assert_eq!(path, "/messages/0/content");
+            },
+            other => panic!("expected schema violation, got {other:?}"),
+        }
+        assert!(CodecEngine::new().decompress(&wire.data).is_ok());
+    }
+
+    #[test]
+    fn test_remove_defaults() {
+        let engine = CodecEngine::new().with_remove_defaults(Some(DefaultsNormalizer::new()));
+        let json = r#"{"model":"gpt-4o","messages":[{"role":"user","content":"Hi"}],"temperature":1,"top_p":1,"stream":false}"#;
+        let stripped = r#"{"messages":[{"content":"Hi","role":"user"}],"model":"gpt-4o"}"#;
+
+        let result = engine.compress(json, Algorithm::M2M).unwrap();
+        assert_eq!(result.original_bytes, json.len());
+        assert_eq!(engine.decompress(&result.data).unwrap(), stripped);
+
+        let (result, _) = engine.compress_auto(json).unwrap();
+        assert_eq!(result.original_bytes, json.len());
+        assert_eq!(engine.decompress(&result.data).unwrap(), stripped);
+
+        // Disabled by default: byte-identical round-trip
+        let result = CodecEngine::new().compress(json, Algorithm::M2M).unwrap();
+        assert_eq!(CodecEngine::new().decompress(&result.data).unwrap(), json);
+    }
+
+    #[test]
+    fn test_tool_packing() {
+        let result = r#"{\n  \"rows\": [1, 2, 3],\n  \"status\": \"complete and verified\"\n}"#;
+        let json = format!(
+            r#"{{"model":"gpt-4o","messages":[{{"role":"tool","tool_call_id":"a","content":"{result}"}},{{"role":"tool","tool_call_id":"b","content":"{result}"}}]}}"#
+        );
+        let engine = CodecEngine::new().with_tool_packing(Some(ToolPacker::new()));
+
+        let packed = engine.compress(&json, Algorithm::M2M).unwrap();
+
+        // Any engine expands the references
+        let value = CodecEngine::new().decompress_value(&packed.data).unwrap();
+        assert_eq!(
+            value["messages"][0]["content"],
+            value["messages"][1]["content"]
+        );
+        assert!(value["messages"][1]
+            .get(super::super::SAME_AS_KEY)
+            .is_none());
+        assert_eq!(
+            value["messages"][1]["content"],
+            r#"{"rows":[1,2,3],"status":"complete and verified"}"#
+        );
+    }
+
+    #[test]
+    fn test_decompression_bomb_rejected() {
+        // 4 MiB of one byte compresses to a few hundred wire bytes
+        let bomb = format!(
+            r#"{{"model":"gpt-4o","messages":[{{"role":"user","content":"{}"}}]}}"#,
+            "a".repeat(4 << 20)
+        );
+        let wire = CodecEngine::new().compress(&bomb, Algorithm::M2M).unwrap();
+        assert!(wire.data.len() < 64 * 1024);
+
+        // Past the expansion ratio: decoding stops at the limit
+        match CodecEngine::new().decompress(&wire.data) {
+            Err(M2MError::PayloadTooLarge { size, limit }) => assert_eq!(size, limit + 1),
+            other => panic!("expected PayloadTooLarge, got {other:?}"),
+        }
+
+        let trusting =
+            CodecEngine::new().with_decompression_limits(DecompressionLimits::unlimited());
+        assert_eq!(trusting.decompress(&wire.data).unwrap(), bomb);
+
+        // The absolute limit applies to uncompressed payloads too
+        let strict = CodecEngine::new()
+            .with_decompression_limits(DecompressionLimits::new().with_max_size(16));
+        assert!(matches!(
+            strict.decompress(r#"{"model":"gpt-4o","messages":[]}"#),
+            Err(M2MError::PayloadTooLarge { limit: 16, .. })
+        ));
+    }
+
+    #[test]
+    fn test_unavailable_algorithms_degrade() {
+        let engine = CodecEngine::new();
+        let json = r#"{"model":"gpt-4o","messages":[{"role":"user","content":"Hi"}]}"#;
+        for &algo in Algorithm::all() {
+            match engine.compress(json, algo) {
+                Ok(result) => assert!(algo.is_available(), "{algo}: {}", result.data),
+                Err(e) => {
+                    assert!(!algo.is_available(), "{algo}: {e}");
+                    assert!(matches!(e, M2MError::InvalidCodec(_)));
+                },
+            }
+        }
+
+        // Automatic selection only picks what this build has
+        let large = format!(
+            r#"{{"model":"gpt-4o","messages":[{{"role":"user","content":"{}"}}]}}"#,
+            "Summarize the quarterly report. ".repeat(80)
+        );
+        let (result, algo) = engine.compress_auto(&large).unwrap();
+        assert!(algo.is_available());
+        assert_eq!(engine.decompress(&result.data).unwrap(), large);
+    }
+
+    #[test]
+    #[cfg(feature = "brotli")]
+    fn test_profiles() {
+        let large = format!(
+            r#"{{"model":"gpt-4o","messages":[{{"role":"user","content":"{}"}}]}}"#,
+            "Summarize the quarterly report. ".repeat(80)
+        );
+        let balanced = CodecEngine::new();
+        let (_, algo) = balanced.compress_auto(&large).unwrap();
+        assert_eq!(algo, Algorithm::Brotli);
+
+        // Latency never picks Brotli, even per call
+        let latency = CodecEngine::new().with_profile(CompressionProfile::Latency);
+        let (_, algo) = latency.compress_auto(&large).unwrap();
+        assert_eq!(algo, Algorithm::M2M);
+        let (_, algo) = balanced
+            .compress_auto_with_profile(&large, CompressionProfile::Latency)
+            .unwrap();
+        assert_eq!(algo, Algorithm::M2M);
+
+        // Max savings keeps the smallest candidate output
+        let (result, _) = balanced
+            .compress_auto_with_profile(&large, CompressionProfile::MaxSavings)
+            .unwrap();
+        let best = balanced.compress_best(&large).unwrap();
+        assert_eq!(result.compressed_bytes, best.compressed_bytes);
+        assert_eq!(balanced.decompress(&result.data).unwrap(), large);
+    }
+
+    #[test]
+    #[cfg(feature = "brotli")]
+    fn test_latency_budget() {
+        // ~200 KB: Brotli 11 would take seconds, M2M milliseconds
+        let large = format!(
+            r#"{{"model":"gpt-4o","messages":[{{"role":"user","content":"{}"}}]}}"#,
+            "Summarize the quarterly report. ".repeat(6_000)
+        );
+        let engine = CodecEngine::new().with_max_compress_latency(Some(Duration::from_secs(1)));
+        for profile in [CompressionProfile::Balanced, CompressionProfile::MaxSavings] {
+            let (result, analysis) = engine.compress_auto_analyzed(&large, profile).unwrap();
+            assert!(analysis.skipped_for_budget.contains(&Algorithm::Brotli));
+            assert_ne!(result.algorithm, Algorithm::Brotli, "{profile}");
+            assert!(!result.is_fallback(), "{profile}");
+            assert_eq!(engine.decompress(&result.data).unwrap(), large);
+        }
+
+        // Nothing fits: passthrough
+        let engine = CodecEngine::new().with_max_compress_latency(Some(Duration::ZERO));
+        let (result, analysis) = engine
+            .compress_auto_analyzed(&large, CompressionProfile::Balanced)
+            .unwrap();
+        assert_eq!(result.algorithm, Algorithm::None);
+        assert_eq!(result.fallback_from, Some(Algorithm::Brotli));
+        assert!(analysis.skipped_for_budget.contains(&Algorithm::M2M));
+
+        // Without a budget nothing is skipped
+        let (result, analysis) = CodecEngine::new()
+            .compress_auto_analyzed(&large, CompressionProfile::Balanced)
+            .unwrap();
+        assert_eq!(result.algorithm, Algorithm::Brotli);
+        assert!(analysis.skipped_for_budget.is_empty());
+    }
+
+    #[test]
+    fn test_v2_frames() {
+        let wire = "#M2M[v2.0]|DATA:eJyrVkpUsjKsBQAIKgIJ";
+
+        #[cfg(not(feature = "compat-v2"))]
+        assert!(CodecEngine::new().decompress(wire).is_err());
+
+        #[cfg(feature = "compat-v2")]
+        {
+            use std::sync::atomic::{AtomicUsize, Ordering};
+
+            let seen = Arc::new(AtomicUsize::new(0));
+            let counter = Arc::clone(&seen);
+            let engine = CodecEngine::new().with_deprecation_hook(move |_| {
+                counter.fetch_add(1, Ordering::Relaxed);
+            });
+
+            assert_eq!(engine.decompress(wire).unwrap(), r#"{"a":1}"#);
+            let emitted = engine.compress_v2(r#"{"b":2}"#).unwrap();
+            assert_eq!(engine.decompress(&emitted).unwrap(), r#"{"b":2}"#);
+            assert_eq!(seen.load(Ordering::Relaxed), 3);
+        }
+    }
 }
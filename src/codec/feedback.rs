@@ -0,0 +1,509 @@
+//! Router calibration from live traffic.
+//!
+//! [`RouterFeedback`] collects samples from `CodecEngine::compress_auto` and
+//! refits the heuristic selection thresholds to the site's real payload mix.
+//!
+//! Every `probe_interval`-th auto-compression is *probed*: the payload is
+//! also compressed with each candidate algorithm so the sample records what
+//! every choice would have cost, not just the one taken. Refitting is a grid
+//! search over [`RouterThresholds`] minimizing total wire bytes across the
+//! probed samples; the current thresholds win ties, so calibration only
+//! moves when the data says it should.
+//!
+//! Probed samples double as a labeled dataset (features → best algorithm)
+//! for fine-tuning Hydra, via [`RouterFeedback::export_dataset`].
+
+use std::collections::VecDeque;
+use std::io::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, RwLock};
+
+use serde::{Deserialize, Serialize};
+
+use super::engine::ContentAnalysis;
+use super::m2m::MEDIA_HEAVY_RATIO;
+use super::{Algorithm, CompressionResult};
+use crate::error::Result;
+
+/// Default number of samples retained
+pub const DEFAULT_FEEDBACK_CAPACITY: usize = 1024;
+
+/// Default probe interval (every Nth auto-compression)
+pub const DEFAULT_PROBE_INTERVAL: u64 = 16;
+
+/// Default number of new probed samples between automatic refits
+pub const DEFAULT_REFIT_INTERVAL: usize = 128;
+
+/// Minimum probed samples before thresholds are refit
+const MIN_REFIT_SAMPLES: usize = 32;
+
+/// Candidate minimum sizes for compression (bytes)
+const MIN_BYTES_GRID: [usize; 8] = [0, 50, 100, 150, 200, 300, 500, 750];
+
+/// Candidate Brotli thresholds (bytes)
+const BROTLI_GRID: [usize; 9] = [256, 512, 768, 1024, 1536, 2048, 4096, 8192, 16384];
+
+/// Candidate repetition thresholds
+const REPETITION_GRID: [f32; 7] = [0.1, 0.2, 0.3, 0.4, 0.5, 0.6, 1.0];
+
+/// Tunable thresholds for heuristic algorithm selection
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RouterThresholds {
+    /// Content shorter than this is passed through
+    pub min_compress_bytes: usize,
+    /// Content longer than this uses Brotli
+    pub brotli_threshold: usize,
+    /// Medium content above this repetition ratio uses Brotli
+    pub repetition_threshold: f32,
+}
+
+impl Default for RouterThresholds {
+    fn default() -> Self {
+        Self {
+            min_compress_bytes: 100,
+            brotli_threshold: 1024,
+            repetition_threshold: 0.3,
+        }
+    }
+}
+
+impl RouterThresholds {
+    /// Select an algorithm for analyzed content
+    ///
+    /// Epistemic basis:
+    /// - K: M2M achieves ~60-70% byte savings for LLM API JSON with 100% fidelity
+    /// - K: Brotli is optimal for large repetitive content
+    /// - B: Default thresholds fit typical LLM API traffic; refit per site
+    pub fn select(&self, analysis: &ContentAnalysis, prefer_m2m_for_api: bool) -> Algorithm {
+        // Small content: no compression (overhead not worth it)
+        if analysis.length < self.min_compress_bytes {
+            return Algorithm::None;
+        }
+
+        // Media-dominated content: inline images are already entropy-coded
+        // and text codecs would only re-encode them
+        if analysis.media_bytes as f64 >= analysis.length as f64 * MEDIA_HEAVY_RATIO {
+            return Algorithm::None;
+        }
+
+        // Large content: Brotli is almost always best
+        if analysis.length > self.brotli_threshold {
+            return Algorithm::Brotli;
+        }
+
+        // Medium LLM API JSON: M2M compression (100% fidelity)
+        if analysis.is_llm_api && prefer_m2m_for_api {
+            return Algorithm::M2M;
+        }
+
+        // Medium content with high repetition: Brotli
+        if analysis.repetition_ratio > self.repetition_threshold {
+            return Algorithm::Brotli;
+        }
+
+        // Default: M2M for JSON, None for others
+        if analysis.is_json {
+            Algorithm::M2M
+        } else {
+            Algorithm::None
+        }
+    }
+}
+
+/// One observed auto-compression
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeedbackSample {
+    /// Content length in bytes
+    pub length: usize,
+    /// Is valid JSON
+    pub is_json: bool,
+    /// Has LLM API structure
+    pub is_llm_api: bool,
+    /// Repetition ratio
+    pub repetition_ratio: f32,
+    /// Has tool/function calls
+    pub has_tools: bool,
+    /// Bytes of inline base64 media
+    #[serde(default)]
+    pub media_bytes: usize,
+    /// Algorithm the router chose
+    pub chosen: Algorithm,
+    /// Byte ratio achieved by the chosen algorithm
+    pub ratio: f64,
+    /// Wire size per candidate algorithm (probed samples only)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sizes: Vec<(Algorithm, usize)>,
+}
+
+impl FeedbackSample {
+    /// Create a sample from analysis and the result actually sent
+    pub fn new(analysis: &ContentAnalysis, result: &CompressionResult) -> Self {
+        Self {
+            length: analysis.length,
+            is_json: analysis.is_json,
+            is_llm_api: analysis.is_llm_api,
+            repetition_ratio: analysis.repetition_ratio,
+            has_tools: analysis.has_tools,
+            media_bytes: analysis.media_bytes,
+            chosen: result.fallback_from.unwrap_or(result.algorithm),
+            ratio: result.byte_ratio(),
+            sizes: Vec::new(),
+        }
+    }
+
+    /// Attach probed wire sizes
+    pub fn with_sizes(mut self, sizes: Vec<(Algorithm, usize)>) -> Self {
+        self.sizes = sizes;
+        self
+    }
+
+    /// Check if every algorithm's cost is known
+    pub fn is_probed(&self) -> bool {
+        !self.sizes.is_empty()
+    }
+
+    /// Wire size had `algorithm` been chosen (passthrough if it would expand)
+    pub fn cost(&self, algorithm: Algorithm) -> usize {
+        if algorithm == Algorithm::None {
+            return self.length;
+        }
+        self.sizes
+            .iter()
+            .find(|(algo, _)| *algo == algorithm)
+            .map_or(self.length, |(_, bytes)| (*bytes).min(self.length))
+    }
+
+    /// Cheapest algorithm for this sample (probed samples only)
+    pub fn best(&self) -> Option<Algorithm> {
+        if !self.is_probed() {
+            return None;
+        }
+        self.sizes
+            .iter()
+            .map(|(algo, _)| *algo)
+            .chain([Algorithm::None])
+            .min_by_key(|algo| self.cost(*algo))
+    }
+
+    /// Reconstruct the analysis used for selection
+    fn analysis(&self) -> ContentAnalysis {
+        ContentAnalysis {
+            length: self.length,
+            is_json: self.is_json,
+            is_llm_api: self.is_llm_api,
+            repetition_ratio: self.repetition_ratio,
+            has_tools: self.has_tools,
+            estimated_tokens: self.length / 4,
+            media_bytes: self.media_bytes,
+            skipped_for_budget: Vec::new(),
+        }
+    }
+}
+
+/// Total wire bytes of probed samples had `thresholds` selected for them
+pub(crate) fn thresholds_cost(
+    probed: &[FeedbackSample],
+    thresholds: RouterThresholds,
+    prefer_m2m_for_api: bool,
+) -> usize {
+    probed
+        .iter()
+        .map(|sample| sample.cost(thresholds.select(&sample.analysis(), prefer_m2m_for_api)))
+        .sum()
+}
+
+/// Grid-search thresholds minimizing total wire bytes over probed samples
+///
+/// Returns the winner and its total. `current` is kept unless another grid
+/// point strictly reduces the total.
+pub(crate) fn fit_thresholds(
+    probed: &[FeedbackSample],
+    current: RouterThresholds,
+    prefer_m2m_for_api: bool,
+) -> (RouterThresholds, usize) {
+    let total_cost = |t: &RouterThresholds| thresholds_cost(probed, *t, prefer_m2m_for_api);
+
+    let mut best = (current, total_cost(&current));
+    for min_compress_bytes in MIN_BYTES_GRID {
+        for brotli_threshold in BROTLI_GRID {
+            for repetition_threshold in REPETITION_GRID {
+                let candidate = RouterThresholds {
+                    min_compress_bytes,
+                    brotli_threshold,
+                    repetition_threshold,
+                };
+                let cost = total_cost(&candidate);
+                if cost < best.1 {
+                    best = (candidate, cost);
+                }
+            }
+        }
+    }
+    best
+}
+
+/// Dataset row for Hydra fine-tuning
+#[derive(Serialize)]
+struct DatasetRow<'a> {
+    #[serde(flatten)]
+    sample: &'a FeedbackSample,
+    label: Algorithm,
+}
+
+/// Sample collector and threshold calibrator shared by codec engines
+///
+/// # Epistemic Properties
+///
+/// - **K_i**: Probed samples know the cost of every candidate algorithm
+/// - **B_i**: Recent traffic predicts future traffic
+pub struct RouterFeedback {
+    /// Recent samples (oldest first)
+    samples: Mutex<VecDeque<FeedbackSample>>,
+    /// Maximum samples retained
+    capacity: usize,
+    /// Probe every Nth observation
+    probe_interval: u64,
+    /// Probed samples between automatic refits (0 disables)
+    refit_interval: usize,
+    /// Calibrated thresholds (None until first refit)
+    thresholds: RwLock<Option<RouterThresholds>>,
+    /// Observations seen
+    observed: AtomicU64,
+    /// Probed samples since last refit
+    pending: AtomicU64,
+    /// Completed refits
+    refits: AtomicU64,
+}
+
+impl Default for RouterFeedback {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RouterFeedback {
+    /// Create a collector with default settings
+    pub fn new() -> Self {
+        Self {
+            samples: Mutex::new(VecDeque::with_capacity(DEFAULT_FEEDBACK_CAPACITY)),
+            capacity: DEFAULT_FEEDBACK_CAPACITY,
+            probe_interval: DEFAULT_PROBE_INTERVAL,
+            refit_interval: DEFAULT_REFIT_INTERVAL,
+            thresholds: RwLock::new(None),
+            observed: AtomicU64::new(0),
+            pending: AtomicU64::new(0),
+            refits: AtomicU64::new(0),
+        }
+    }
+
+    /// Set number of samples retained
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity.max(1);
+        self
+    }
+
+    /// Probe every Nth observation (1 probes everything)
+    pub fn with_probe_interval(mut self, interval: u64) -> Self {
+        self.probe_interval = interval.max(1);
+        self
+    }
+
+    /// Set probed samples between automatic refits (0 disables)
+    pub fn with_refit_interval(mut self, interval: usize) -> Self {
+        self.refit_interval = interval;
+        self
+    }
+
+    /// Seed calibrated thresholds (e.g. from a previous run)
+    pub fn with_thresholds(self, thresholds: RouterThresholds) -> Self {
+        if let Ok(mut current) = self.thresholds.write() {
+            *current = Some(thresholds);
+        }
+        self
+    }
+
+    /// Calibrated thresholds, if any refit has happened
+    pub fn thresholds(&self) -> Option<RouterThresholds> {
+        self.thresholds.read().ok().and_then(|t| *t)
+    }
+
+    /// Decide whether the next observation should be probed
+    pub fn should_probe(&self) -> bool {
+        self.observed
+            .fetch_add(1, Ordering::Relaxed)
+            .is_multiple_of(self.probe_interval)
+    }
+
+    /// Record a sample, refitting when enough probed samples have arrived
+    pub fn record(&self, sample: FeedbackSample) {
+        let probed = sample.is_probed();
+        if let Ok(mut samples) = self.samples.lock() {
+            if samples.len() >= self.capacity {
+                samples.pop_front();
+            }
+            samples.push_back(sample);
+        }
+
+        if probed && self.refit_interval > 0 {
+            let pending = self.pending.fetch_add(1, Ordering::Relaxed) + 1;
+            if pending >= self.refit_interval as u64 {
+                self.refit();
+            }
+        }
+    }
+
+    /// Number of samples retained
+    pub fn sample_count(&self) -> usize {
+        self.samples.lock().map(|s| s.len()).unwrap_or(0)
+    }
+
+    /// Number of completed refits
+    pub fn refit_count(&self) -> u64 {
+        self.refits.load(Ordering::Relaxed)
+    }
+
+    /// Mean byte ratio achieved per chosen algorithm
+    pub fn mean_ratios(&self) -> Vec<(Algorithm, f64)> {
+        let Ok(samples) = self.samples.lock() else {
+            return Vec::new();
+        };
+
+        let mut totals: Vec<(Algorithm, f64, usize)> = Vec::new();
+        for sample in samples.iter() {
+            match totals
+                .iter_mut()
+                .find(|(algo, _, _)| *algo == sample.chosen)
+            {
+                Some(entry) => {
+                    entry.1 += sample.ratio;
+                    entry.2 += 1;
+                },
+                None => totals.push((sample.chosen, sample.ratio, 1)),
+            }
+        }
+        totals
+            .into_iter()
+            .map(|(algo, sum, n)| (algo, sum / n as f64))
+            .collect()
+    }
+
+    /// Refit thresholds from probed samples
+    ///
+    /// Returns the new thresholds, or `None` if there are too few probed
+    /// samples. Current thresholds are kept unless another grid point
+    /// strictly reduces total wire bytes.
+    pub fn refit(&self) -> Option<RouterThresholds> {
+        let probed: Vec<FeedbackSample> = self
+            .samples
+            .lock()
+            .ok()?
+            .iter()
+            .filter(|s| s.is_probed())
+            .cloned()
+            .collect();
+        self.pending.store(0, Ordering::Relaxed);
+
+        if probed.len() < MIN_REFIT_SAMPLES {
+            return None;
+        }
+
+        // Simulate engines with the default `prefer_m2m_for_api`
+        let current = self.thresholds().unwrap_or_default();
+        let best = fit_thresholds(&probed, current, true);
+
+        tracing::info!(
+            "Router refit on {} samples: {:?} ({} wire bytes)",
+            probed.len(),
+            best.0,
+            best.1
+        );
+        if let Ok(mut thresholds) = self.thresholds.write() {
+            *thresholds = Some(best.0);
+        }
+        self.refits.fetch_add(1, Ordering::Relaxed);
+        Some(best.0)
+    }
+
+    /// Write probed samples as JSONL labeled with their best algorithm
+    ///
+    /// Returns the number of rows written.
+    pub fn export_dataset<W: Write>(&self, mut writer: W) -> Result<usize> {
+        let samples: Vec<FeedbackSample> = match self.samples.lock() {
+            Ok(samples) => samples.iter().cloned().collect(),
+            Err(_) => return Ok(0),
+        };
+
+        let mut rows = 0;
+        for sample in &samples {
+            if let Some(label) = sample.best() {
+                serde_json::to_writer(&mut writer, &DatasetRow { sample, label })?;
+                writer.write_all(b"\n")?;
+                rows += 1;
+            }
+        }
+        Ok(rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Probed non-API JSON sample where Brotli beats M2M at `length` bytes
+    fn sample(length: usize, brotli: usize, m2m: usize) -> FeedbackSample {
+        FeedbackSample {
+            length,
+            is_json: true,
+            is_llm_api: false,
+            repetition_ratio: 0.0,
+            has_tools: false,
+            media_bytes: 0,
+            chosen: Algorithm::M2M,
+            ratio: length as f64 / m2m as f64,
+            sizes: vec![(Algorithm::M2M, m2m), (Algorithm::Brotli, brotli)],
+        }
+    }
+
+    #[test]
+    fn test_refit_lowers_brotli_threshold() {
+        let feedback = RouterFeedback::new().with_refit_interval(0);
+        // Mid-size payloads where Brotli wins decisively
+        for i in 0..40 {
+            feedback.record(sample(600 + i, 200, 450));
+        }
+
+        let fitted = feedback.refit().unwrap();
+        assert!(fitted.brotli_threshold < 600);
+        assert_eq!(feedback.thresholds(), Some(fitted));
+        assert_eq!(feedback.refit_count(), 1);
+    }
+
+    #[test]
+    fn test_refit_keeps_thresholds_without_evidence() {
+        let feedback = RouterFeedback::new().with_refit_interval(0);
+        // M2M already optimal for mid-size JSON: defaults stay
+        for i in 0..40 {
+            feedback.record(sample(600 + i, 500, 300));
+        }
+        assert_eq!(feedback.refit(), Some(RouterThresholds::default()));
+
+        let too_few = RouterFeedback::new();
+        too_few.record(sample(600, 200, 450));
+        assert!(too_few.refit().is_none());
+    }
+
+    #[test]
+    fn test_export_dataset() {
+        let feedback = RouterFeedback::new().with_refit_interval(0);
+        feedback.record(sample(600, 200, 450));
+        feedback.record(FeedbackSample {
+            sizes: Vec::new(),
+            ..sample(600, 200, 450)
+        });
+
+        let mut out = Vec::new();
+        assert_eq!(feedback.export_dataset(&mut out).unwrap(), 1);
+        let row: serde_json::Value = serde_json::from_slice(&out).unwrap();
+        assert_eq!(row["label"], serde_json::json!(Algorithm::Brotli));
+    }
+}
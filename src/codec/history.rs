@@ -0,0 +1,425 @@
+//! Sliding-window compression of conversation history.
+//!
+//! Chat requests resend the whole conversation on every turn, so long
+//! sessions spend most of their bytes on history. A [`HistoryWindow`] keeps
+//! system messages and the most recent turns verbatim and folds everything
+//! older into a single context message holding a short excerpt per turn.
+//!
+//! The folded turns are stored in a [`HistoryRefs`] map kept next to the
+//! sender's session, and the context message names them by reference, so
+//! [`HistoryWindow::expand`] rebuilds the full request. Anyone reading the
+//! compacted request without the map only sees the excerpts;
+//! [`HistoryReport`] says how much was traded for the savings.
+//!
+//! # Context Message
+//!
+//! ```text
+//! {"role":"system","name":"m2m_context","content":
+//!   "[m2m-context: h1 h2 h3]\nuser: How do I rotate…\nassistant: Use the…\n…"}
+//! ```
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use m2m::codec::{HistoryRefs, HistoryWindow};
+//!
+//! let window = HistoryWindow::new(4);
+//! let mut refs = HistoryRefs::new();
+//!
+//! let (compacted, report) = window.compact(request, &mut refs)?;
+//! println!("folded {} turns, kept {:.0}% of their text", report.folded, report.fidelity() * 100.0);
+//!
+//! let restored = HistoryWindow::expand(&compacted, &refs)?;
+//! ```
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::error::{M2MError, Result};
+
+/// Default number of recent turns kept verbatim
+pub const DEFAULT_KEEP_RECENT: usize = 6;
+
+/// Default excerpt length per folded turn, in characters
+pub const DEFAULT_EXCERPT_CHARS: usize = 160;
+
+/// Name of the context message
+pub const CONTEXT_NAME: &str = "m2m_context";
+
+/// Marker opening the context message content
+const CONTEXT_MARKER: &str = "[m2m-context:";
+
+/// Keeps recent turns verbatim and folds older ones
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistoryWindow {
+    /// Non-system turns kept verbatim at the end of the conversation
+    pub keep_recent: usize,
+    /// Characters of each folded turn kept in the context message
+    pub excerpt_chars: usize,
+}
+
+impl Default for HistoryWindow {
+    fn default() -> Self {
+        Self {
+            keep_recent: DEFAULT_KEEP_RECENT,
+            excerpt_chars: DEFAULT_EXCERPT_CHARS,
+        }
+    }
+}
+
+/// Original turns folded out of requests, by reference
+#[derive(Debug, Clone, Default)]
+pub struct HistoryRefs {
+    /// Reference of each stored turn, by its serialized form
+    ids: HashMap<String, String>,
+    /// Stored turns by reference
+    turns: HashMap<String, Value>,
+}
+
+impl HistoryRefs {
+    /// Empty map
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of stored turns
+    pub fn len(&self) -> usize {
+        self.turns.len()
+    }
+
+    /// Check if no turn is stored
+    pub fn is_empty(&self) -> bool {
+        self.turns.is_empty()
+    }
+
+    /// Original turn for a reference
+    pub fn get(&self, id: &str) -> Option<&Value> {
+        self.turns.get(id)
+    }
+
+    /// Store a turn, returning its reference
+    ///
+    /// A turn seen before keeps its reference, so the context message stays
+    /// stable as the conversation grows.
+    fn insert(&mut self, turn: &Value) -> String {
+        let key = turn.to_string();
+        if let Some(id) = self.ids.get(&key) {
+            return id.clone();
+        }
+        let id = format!("h{}", self.turns.len() + 1);
+        self.ids.insert(key, id.clone());
+        self.turns.insert(id.clone(), turn.clone());
+        id
+    }
+}
+
+/// What a compaction traded for its savings
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistoryReport {
+    /// Non-system turns in the request
+    pub turns: usize,
+    /// Turns kept verbatim
+    pub kept: usize,
+    /// Turns folded into the context message
+    pub folded: usize,
+    /// Request size before compaction
+    pub original_bytes: usize,
+    /// Request size after compaction
+    pub compacted_bytes: usize,
+    /// Text characters in the folded turns
+    pub folded_chars: usize,
+    /// Of those, characters kept in excerpts
+    pub retained_chars: usize,
+}
+
+impl HistoryReport {
+    /// Share of folded text still readable in the context message
+    ///
+    /// 1.0 when nothing was folded.
+    pub fn fidelity(&self) -> f64 {
+        if self.folded_chars == 0 {
+            return 1.0;
+        }
+        self.retained_chars as f64 / self.folded_chars as f64
+    }
+
+    /// Bytes saved by compaction
+    pub fn bytes_saved(&self) -> usize {
+        self.original_bytes.saturating_sub(self.compacted_bytes)
+    }
+}
+
+impl HistoryWindow {
+    /// Keep the `keep_recent` most recent turns verbatim
+    pub fn new(keep_recent: usize) -> Self {
+        Self {
+            keep_recent,
+            ..Self::default()
+        }
+    }
+
+    /// Set the excerpt length per folded turn
+    pub fn with_excerpt_chars(mut self, chars: usize) -> Self {
+        self.excerpt_chars = chars;
+        self
+    }
+
+    /// Fold turns older than the window into a context message
+    ///
+    /// Content that is not a chat request, or has no turns outside the
+    /// window, is returned unchanged with nothing folded. Folded turns are
+    /// stored in `refs`.
+    pub fn compact(&self, json: &str, refs: &mut HistoryRefs) -> Result<(String, HistoryReport)> {
+        let mut report = HistoryReport {
+            original_bytes: json.len(),
+            compacted_bytes: json.len(),
+            ..HistoryReport::default()
+        };
+
+        let Ok(mut request) = serde_json::from_str::<Value>(json) else {
+            return Ok((json.to_string(), report));
+        };
+        let Some(messages) = request.get_mut("messages").and_then(Value::as_array_mut) else {
+            return Ok((json.to_string(), report));
+        };
+
+        let conversation: Vec<usize> = (0..messages.len())
+            .filter(|&i| !is_system(&messages[i]))
+            .collect();
+        report.turns = conversation.len();
+
+        // Never separate tool results from the call that produced them
+        let mut split = report.turns.saturating_sub(self.keep_recent);
+        while split > 0 && split < report.turns && role(&messages[conversation[split]]) == "tool" {
+            split -= 1;
+        }
+        report.kept = report.turns - split;
+        let folded = &conversation[..split];
+        if folded.is_empty() {
+            return Ok((json.to_string(), report));
+        }
+
+        let mut ids = Vec::with_capacity(folded.len());
+        let mut lines = Vec::with_capacity(folded.len());
+        for &i in folded {
+            let turn = &messages[i];
+            let text = turn_text(turn);
+            let excerpt: String = text.chars().take(self.excerpt_chars).collect();
+            let excerpt_len = excerpt.chars().count();
+            let text_len = text.chars().count();
+
+            report.folded_chars += text_len;
+            report.retained_chars += excerpt_len;
+            let ellipsis = if excerpt_len < text_len { "…" } else { "" };
+            lines.push(format!("{}: {excerpt}{ellipsis}", role(turn)));
+            ids.push(refs.insert(turn));
+        }
+        report.folded = folded.len();
+
+        let content = format!("{CONTEXT_MARKER} {}]\n{}", ids.join(" "), lines.join("\n"));
+        let context = json!({ "role": "system", "name": CONTEXT_NAME, "content": content });
+
+        // The context message takes the place of the first folded turn
+        let first = folded[0];
+        let mut compacted = Vec::with_capacity(messages.len() - folded.len() + 1);
+        for (i, message) in messages.drain(..).enumerate() {
+            if i == first {
+                compacted.push(context.clone());
+            }
+            if folded.binary_search(&i).is_err() {
+                compacted.push(message);
+            }
+        }
+        *messages = compacted;
+
+        let output = serde_json::to_string(&request)?;
+        report.compacted_bytes = output.len();
+        Ok((output, report))
+    }
+
+    /// Restore the turns folded by [`compact`](Self::compact)
+    ///
+    /// The result is semantically identical to the original request (key
+    /// order may differ). Content without a context message is returned
+    /// unchanged; a reference missing from `refs` is an error.
+    pub fn expand(json: &str, refs: &HistoryRefs) -> Result<String> {
+        let Ok(mut request) = serde_json::from_str::<Value>(json) else {
+            return Ok(json.to_string());
+        };
+        let Some(messages) = request.get_mut("messages").and_then(Value::as_array_mut) else {
+            return Ok(json.to_string());
+        };
+        if !messages.iter().any(|m| context_refs(m).is_some()) {
+            return Ok(json.to_string());
+        }
+
+        let mut expanded = Vec::with_capacity(messages.len());
+        for message in messages.drain(..) {
+            let Some(ids) = context_refs(&message) else {
+                expanded.push(message);
+                continue;
+            };
+            for id in ids {
+                let turn = refs.get(id).ok_or_else(|| {
+                    M2MError::Decompression(format!("Unknown history reference: {id}"))
+                })?;
+                expanded.push(turn.clone());
+            }
+        }
+        *messages = expanded;
+
+        Ok(serde_json::to_string(&request)?)
+    }
+}
+
+/// Check if a message is a system or developer instruction
+fn is_system(message: &Value) -> bool {
+    matches!(role(message), "system" | "developer")
+}
+
+/// Role of a message
+fn role(message: &Value) -> &str {
+    message
+        .get("role")
+        .and_then(Value::as_str)
+        .unwrap_or("unknown")
+}
+
+/// References listed by a context message
+fn context_refs(message: &Value) -> Option<std::str::SplitWhitespace<'_>> {
+    if message.get("name").and_then(Value::as_str) != Some(CONTEXT_NAME) {
+        return None;
+    }
+    let content = message.get("content")?.as_str()?;
+    let header = content.lines().next()?;
+    let ids = header.strip_prefix(CONTEXT_MARKER)?.strip_suffix(']')?;
+    Some(ids.split_whitespace())
+}
+
+/// Readable text of a turn, whitespace collapsed
+///
+/// Joins text parts of multi-part content and lists the tools an
+/// assistant called.
+fn turn_text(message: &Value) -> String {
+    let mut parts: Vec<&str> = Vec::new();
+    match message.get("content") {
+        Some(Value::String(text)) => parts.push(text),
+        Some(Value::Array(items)) => parts.extend(
+            items
+                .iter()
+                .filter_map(|item| item.get("text").and_then(Value::as_str)),
+        ),
+        _ => {},
+    }
+
+    let calls: Vec<String> = message
+        .get("tool_calls")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|call| call.pointer("/function/name").and_then(Value::as_str))
+        .map(|name| format!("[called {name}]"))
+        .collect();
+    parts.extend(calls.iter().map(String::as_str));
+
+    parts
+        .iter()
+        .flat_map(|part| part.split_whitespace())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(turns: usize) -> String {
+        let mut messages = vec![json!({"role": "system", "content": "You are terse."})];
+        for i in 0..turns {
+            let role = if i % 2 == 0 { "user" } else { "assistant" };
+            messages.push(json!({
+                "role": role,
+                "content": format!("Turn {i}: {}", "lorem ipsum dolor sit amet ".repeat(20)),
+            }));
+        }
+        json!({"model": "gpt-4o", "messages": messages}).to_string()
+    }
+
+    #[test]
+    fn test_compact_and_expand() {
+        let window = HistoryWindow::new(2).with_excerpt_chars(40);
+        let mut refs = HistoryRefs::new();
+        let original = request(6);
+
+        let (compacted, report) = window.compact(&original, &mut refs).unwrap();
+        assert_eq!((report.turns, report.kept, report.folded), (6, 2, 4));
+        assert!(report.compacted_bytes < report.original_bytes);
+        assert!(report.fidelity() < 0.5);
+        assert_eq!(refs.len(), 4);
+
+        let value: Value = serde_json::from_str(&compacted).unwrap();
+        let messages = value["messages"].as_array().unwrap();
+        assert_eq!(messages.len(), 4);
+        assert_eq!(messages[0]["content"], "You are terse.");
+        assert_eq!(messages[1]["name"], CONTEXT_NAME);
+        assert!(messages[1]["content"]
+            .as_str()
+            .unwrap()
+            .starts_with("[m2m-context: h1 h2 h3 h4]\nuser: Turn 0:"));
+
+        let restored = HistoryWindow::expand(&compacted, &refs).unwrap();
+        assert_eq!(
+            serde_json::from_str::<Value>(&restored).unwrap(),
+            serde_json::from_str::<Value>(&original).unwrap()
+        );
+
+        // The next request reuses the references of turns already folded
+        let (_, report) = window.compact(&request(8), &mut refs).unwrap();
+        assert_eq!(report.folded, 6);
+        assert_eq!(refs.len(), 6);
+
+        assert!(HistoryWindow::expand(&compacted, &HistoryRefs::new()).is_err());
+    }
+
+    #[test]
+    fn test_tool_results_stay_with_call() {
+        let original = json!({"messages": [
+            {"role": "user", "content": "Weather in Oslo?"},
+            {"role": "assistant", "content": null, "tool_calls": [
+                {"id": "c1", "type": "function", "function": {"name": "get_weather", "arguments": "{}"}}
+            ]},
+            {"role": "tool", "tool_call_id": "c1", "content": "4C, rain"},
+            {"role": "assistant", "content": "4C and raining."},
+        ]})
+        .to_string();
+
+        let mut refs = HistoryRefs::new();
+        let (compacted, report) = HistoryWindow::new(2).compact(&original, &mut refs).unwrap();
+        assert_eq!((report.kept, report.folded), (3, 1));
+        assert!(compacted.contains("user: Weather in Oslo?"));
+
+        let (compacted, report) = HistoryWindow::new(1).compact(&original, &mut refs).unwrap();
+        assert_eq!((report.kept, report.folded), (1, 3));
+        assert!(compacted.contains("assistant: [called get_weather]"));
+        assert_eq!(refs.len(), 3);
+    }
+
+    #[test]
+    fn test_short_or_non_chat_unchanged() {
+        let window = HistoryWindow::new(6);
+        let mut refs = HistoryRefs::new();
+
+        let short = request(4);
+        let (output, report) = window.compact(&short, &mut refs).unwrap();
+        assert_eq!(output, short);
+        assert_eq!(report.folded, 0);
+        assert!((report.fidelity() - 1.0).abs() < f64::EPSILON);
+
+        let other = r#"{"input":"embed me"}"#;
+        assert_eq!(window.compact(other, &mut refs).unwrap().0, other);
+        assert_eq!(HistoryWindow::expand(other, &refs).unwrap(), other);
+        assert!(refs.is_empty());
+    }
+}
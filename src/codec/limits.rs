@@ -0,0 +1,156 @@
+//! Decompressed output size limits.
+//!
+//! Brotli inflates highly repetitive input by several orders of magnitude,
+//! so a peer can send a 1KB frame that decodes to gigabytes. Decoders read
+//! through [`DecompressionLimits`] and stop as soon as the output passes
+//! the limit, failing with [`M2MError::PayloadTooLarge`] before the memory
+//! is allocated.
+//!
+//! The limit for one payload is the smaller of:
+//!
+//! - the absolute `max_size`, and
+//! - `max_ratio` times the wire size, but never less than
+//!   [`RATIO_EXEMPT_SIZE`], so small messages are not refused for
+//!   compressing well.
+
+use std::io::Read;
+
+use crate::error::{M2MError, Result};
+
+/// Default maximum decompressed payload size (64 MiB)
+pub const DEFAULT_MAX_DECOMPRESSED_SIZE: usize = 64 * 1024 * 1024;
+
+/// Default maximum ratio of decompressed to wire size
+pub const DEFAULT_MAX_EXPANSION_RATIO: f64 = 1000.0;
+
+/// Output size always allowed by the expansion ratio (1 MiB)
+pub const RATIO_EXEMPT_SIZE: usize = 1024 * 1024;
+
+/// Bounds on decompressed output
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DecompressionLimits {
+    /// Maximum decompressed size in bytes
+    pub max_size: usize,
+    /// Maximum ratio of decompressed to wire size (`None` = unbounded)
+    pub max_ratio: Option<f64>,
+}
+
+impl Default for DecompressionLimits {
+    fn default() -> Self {
+        Self {
+            max_size: DEFAULT_MAX_DECOMPRESSED_SIZE,
+            max_ratio: Some(DEFAULT_MAX_EXPANSION_RATIO),
+        }
+    }
+}
+
+impl DecompressionLimits {
+    /// Default limits
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// No limits (only for trusted input)
+    pub fn unlimited() -> Self {
+        Self {
+            max_size: usize::MAX,
+            max_ratio: None,
+        }
+    }
+
+    /// Set the maximum decompressed size in bytes
+    pub fn with_max_size(mut self, max_size: usize) -> Self {
+        self.max_size = max_size;
+        self
+    }
+
+    /// Set the maximum expansion ratio
+    pub fn with_max_ratio(mut self, max_ratio: f64) -> Self {
+        self.max_ratio = Some(max_ratio.max(1.0));
+        self
+    }
+
+    /// Allow any expansion ratio (the absolute limit still applies)
+    pub fn without_max_ratio(mut self) -> Self {
+        self.max_ratio = None;
+        self
+    }
+
+    /// Largest output allowed for `input_len` wire bytes
+    pub fn limit_for(&self, input_len: usize) -> usize {
+        let by_ratio = self.max_ratio.map_or(usize::MAX, |ratio| {
+            ((input_len as f64 * ratio) as usize).max(RATIO_EXEMPT_SIZE)
+        });
+        self.max_size.min(by_ratio)
+    }
+
+    /// Fail if `output_len` bytes decoded from `input_len` exceed the limits
+    pub fn check(&self, input_len: usize, output_len: usize) -> Result<()> {
+        let limit = self.limit_for(input_len);
+        if output_len > limit {
+            return Err(M2MError::PayloadTooLarge {
+                size: output_len,
+                limit,
+            });
+        }
+        Ok(())
+    }
+
+    /// Read a decompressing reader to the end, stopping past the limit
+    ///
+    /// Reads at most one byte more than allowed, so an oversized payload
+    /// costs no more memory than the limit.
+    pub(crate) fn read_to_end(&self, input_len: usize, reader: impl Read) -> Result<Vec<u8>> {
+        let limit = self.limit_for(input_len);
+        let mut output = Vec::new();
+        reader
+            .take((limit as u64).saturating_add(1))
+            .read_to_end(&mut output)
+            .map_err(|e| M2MError::Decompression(format!("Brotli decompression failed: {e}")))?;
+        self.check(input_len, output.len())?;
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_limit_for() {
+        let limits = DecompressionLimits::new();
+        assert_eq!(limits.limit_for(10), RATIO_EXEMPT_SIZE);
+        assert_eq!(limits.limit_for(10_000), 10_000_000);
+        assert_eq!(limits.limit_for(1_000_000), DEFAULT_MAX_DECOMPRESSED_SIZE);
+
+        let limits = DecompressionLimits::new()
+            .with_max_size(100)
+            .without_max_ratio();
+        assert_eq!(limits.limit_for(1), 100);
+        assert!(limits.check(1, 100).is_ok());
+        assert!(matches!(
+            limits.check(1, 101),
+            Err(M2MError::PayloadTooLarge {
+                size: 101,
+                limit: 100
+            })
+        ));
+        assert_eq!(DecompressionLimits::unlimited().limit_for(1), usize::MAX);
+    }
+
+    #[test]
+    fn test_read_stops_at_limit() {
+        let limits = DecompressionLimits::new().with_max_size(1024);
+        let data = vec![b'a'; 4096];
+
+        let err = limits.read_to_end(10, &data[..]).unwrap_err();
+        assert!(matches!(
+            err,
+            M2MError::PayloadTooLarge {
+                size: 1025,
+                limit: 1024
+            }
+        ));
+        assert_eq!(limits.read_to_end(10, &data[..1024]).unwrap().len(), 1024);
+    }
+}
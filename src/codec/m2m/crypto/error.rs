@@ -39,6 +39,7 @@ use super::aead::AeadError;
 use super::hmac_auth::HmacError;
 use super::keyring::{KeyError, KeyringError};
 use super::NonceError;
+use crate::error::ErrorCode;
 
 #[cfg(feature = "crypto")]
 use super::exchange::KeyExchangeError;
@@ -121,6 +122,23 @@ pub enum CryptoError {
     Nonce(#[source] NonceError),
 }
 
+impl CryptoError {
+    /// Stable machine-readable code (`CRYPTO_xxx`)
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            CryptoError::Aead(_) => ErrorCode::CRYPTO_AEAD,
+            CryptoError::Hmac(_) => ErrorCode::CRYPTO_HMAC,
+            CryptoError::Key(_) => ErrorCode::CRYPTO_KEY,
+            CryptoError::Keyring(_) => ErrorCode::CRYPTO_KEYRING,
+            #[cfg(feature = "crypto")]
+            CryptoError::Exchange(_) => ErrorCode::CRYPTO_EXCHANGE,
+            #[cfg(feature = "crypto")]
+            CryptoError::Id(_) => ErrorCode::CRYPTO_ID,
+            CryptoError::Nonce(_) => ErrorCode::CRYPTO_NONCE,
+        }
+    }
+}
+
 // ============================================================================
 // From implementations for automatic conversion
 // ============================================================================
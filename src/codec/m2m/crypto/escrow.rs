@@ -0,0 +1,277 @@
+//! Key escrow for org-internal audit decryption.
+//!
+//! Compliance teams may need to read archived traffic between their own
+//! agents. With escrow, a sender wraps the session key to the org's audit
+//! X25519 public key and attaches it to each secure frame as a non-critical
+//! [`HeaderExtension`] of kind [`EscrowKey::EXTENSION_KIND`]. Whoever holds
+//! the audit private key ([`AuditKey`]) can then recover the session key
+//! from any archived frame and decrypt it; peers simply ignore the
+//! extension.
+//!
+//! ```text
+//! [key_id: 8][ephemeral_public: 32][nonce: 12][wrapped_key: 32][tag: 16]
+//! ```
+//!
+//! `key_id` is the first 8 bytes of SHA-256 over the audit public key, so
+//! auditors can pick the right key across rotations. The wrapped key is
+//! bound to the org and `key_id`; in AEAD and HMAC frames the extension is
+//! itself covered by the frame's associated data, so it cannot be stripped
+//! or swapped without breaking the frame.
+//!
+//! Escrow is agreed in the handshake with the [`AuditEscrow`] capability
+//! extension, which both agents must advertise with the same key, and is
+//! only meant for same-org traffic: never advertise it to other orgs.
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use sha2::{Digest, Sha256};
+
+use super::aead::{AeadCipher, AeadError};
+use super::error::CryptoError;
+use super::exchange::{KeyPair, PublicKey};
+use super::hierarchy::{OrgId, M2M_KDF_VERSION};
+use super::keyring::KeyMaterial;
+use super::SecurityContext;
+use crate::codec::m2m::{HeaderExtension, M2MFrame, SecurityMode};
+use crate::error::M2MError;
+use crate::protocol::AuditEscrow;
+
+/// Size of the audit key identifier
+const KEY_ID_SIZE: usize = 8;
+
+/// Org audit public key that session keys are wrapped to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EscrowKey {
+    org_id: OrgId,
+    public_key: PublicKey,
+}
+
+impl EscrowKey {
+    /// Header extension kind carrying the escrowed session key
+    pub const EXTENSION_KIND: u8 = 0x02;
+
+    /// Create an escrow key for an org's audit public key
+    pub fn new(org_id: impl Into<String>, public_key: PublicKey) -> Result<Self, CryptoError> {
+        Ok(Self {
+            org_id: OrgId::try_new(org_id)?,
+            public_key,
+        })
+    }
+
+    /// Escrow key agreed in the handshake
+    pub fn from_advertised(escrow: &AuditEscrow) -> Result<Self, CryptoError> {
+        let public_key = PublicKey::from_slice(&decode_base64("audit key", &escrow.public_key)?)?;
+        Self::new(escrow.org_id.clone(), public_key)
+    }
+
+    /// Capability extension value advertising this key
+    pub fn advertise(&self) -> AuditEscrow {
+        AuditEscrow {
+            org_id: self.org_id.as_str().to_string(),
+            public_key: BASE64.encode(self.public_key.as_bytes()),
+        }
+    }
+
+    /// Organization owning the audit key
+    pub fn org_id(&self) -> &str {
+        self.org_id.as_str()
+    }
+
+    /// Audit public key
+    pub fn public_key(&self) -> &PublicKey {
+        &self.public_key
+    }
+
+    /// Identifier of the audit key (first 8 bytes of its SHA-256)
+    pub fn key_id(&self) -> [u8; KEY_ID_SIZE] {
+        let digest = Sha256::digest(self.public_key.as_bytes());
+        let mut id = [0u8; KEY_ID_SIZE];
+        id.copy_from_slice(&digest[..KEY_ID_SIZE]);
+        id
+    }
+
+    /// Wrap a session key into an escrow header extension
+    pub fn wrap(&self, session_key: &KeyMaterial) -> Result<HeaderExtension, CryptoError> {
+        let key_id = self.key_id();
+        let ephemeral = KeyPair::generate();
+        let wrap_key = wrap_key(
+            &ephemeral.diffie_hellman(&self.public_key),
+            &self.org_id,
+            &key_id,
+        )?;
+        let wrapped = AeadCipher::new(wrap_key)?
+            .encrypt_auto_nonce(session_key.as_bytes(), &aad(&self.org_id, &key_id))?;
+
+        let mut value = Vec::with_capacity(KEY_ID_SIZE + 32 + wrapped.len());
+        value.extend_from_slice(&key_id);
+        value.extend_from_slice(ephemeral.public_key().as_bytes());
+        value.extend_from_slice(&wrapped);
+        Ok(HeaderExtension::new(Self::EXTENSION_KIND, value))
+    }
+
+    /// Encode a frame securely with the session key escrowed in its header
+    pub fn encode_secure(
+        &self,
+        frame: M2MFrame,
+        mode: SecurityMode,
+        security_ctx: &mut SecurityContext,
+    ) -> crate::error::Result<Vec<u8>> {
+        let frame = frame.with_extension(self.wrap(security_ctx.key())?)?;
+        frame.encode_secure(mode, security_ctx)
+    }
+}
+
+/// Org audit key pair, held by the compliance team
+pub struct AuditKey {
+    escrow: EscrowKey,
+    key_pair: KeyPair,
+}
+
+impl AuditKey {
+    /// Create an audit key from the org's audit key pair
+    pub fn new(org_id: impl Into<String>, key_pair: KeyPair) -> Result<Self, CryptoError> {
+        Ok(Self {
+            escrow: EscrowKey::new(org_id, key_pair.public_key().clone())?,
+            key_pair,
+        })
+    }
+
+    /// Public half that agents wrap session keys to
+    pub fn escrow_key(&self) -> &EscrowKey {
+        &self.escrow
+    }
+
+    /// Recover the session key from an escrow extension
+    pub fn unwrap(&self, extension: &HeaderExtension) -> Result<KeyMaterial, CryptoError> {
+        let invalid = |reason: &str| AeadError::DecryptionFailed(format!("Escrow: {reason}"));
+
+        if extension.kind != EscrowKey::EXTENSION_KIND {
+            return Err(invalid("not an escrow extension").into());
+        }
+        let key_id = self.escrow.key_id();
+        let value = extension.value.as_slice();
+        if value.len() < KEY_ID_SIZE + 32 {
+            return Err(invalid("extension too short").into());
+        }
+        if value[..KEY_ID_SIZE] != key_id {
+            return Err(invalid("wrapped to a different audit key").into());
+        }
+
+        let ephemeral = PublicKey::from_slice(&value[KEY_ID_SIZE..KEY_ID_SIZE + 32])?;
+        let wrap_key = wrap_key(
+            &self.key_pair.diffie_hellman(&ephemeral),
+            &self.escrow.org_id,
+            &key_id,
+        )?;
+        let key = AeadCipher::new(wrap_key)?.decrypt(
+            &value[KEY_ID_SIZE + 32..],
+            &aad(&self.escrow.org_id, &key_id),
+        )?;
+        Ok(KeyMaterial::new(key))
+    }
+
+    /// Decrypt an archived binary frame with its escrowed session key
+    ///
+    /// Fails if the frame carries no escrow extension for this key.
+    pub fn decrypt_frame(&self, data: &[u8]) -> crate::error::Result<M2MFrame> {
+        let key_id = self.escrow.key_id();
+        let extension = M2MFrame::peek_extensions(data)?
+            .into_iter()
+            .find(|ext| ext.kind == EscrowKey::EXTENSION_KIND && ext.value.starts_with(&key_id))
+            .ok_or_else(|| {
+                M2MError::Crypto(
+                    AeadError::DecryptionFailed(format!(
+                        "Escrow: no session key escrowed to {}",
+                        self.escrow.org_id
+                    ))
+                    .into(),
+                )
+            })?;
+
+        let session_key = self.unwrap(&extension)?;
+        M2MFrame::decode_secure(data, &SecurityContext::new(session_key))
+    }
+}
+
+impl std::fmt::Debug for AuditKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AuditKey")
+            .field("escrow", &self.escrow)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Associated data binding a wrapped key to the org and audit key
+fn aad(org_id: &OrgId, key_id: &[u8; KEY_ID_SIZE]) -> Vec<u8> {
+    let mut aad = format!("{M2M_KDF_VERSION}/{org_id}/escrow/").into_bytes();
+    aad.extend_from_slice(key_id);
+    aad
+}
+
+/// Key encrypting a session key for the audit key
+fn wrap_key(
+    shared: &KeyMaterial,
+    org_id: &OrgId,
+    key_id: &[u8; KEY_ID_SIZE],
+) -> Result<KeyMaterial, CryptoError> {
+    Ok(shared.derive(&aad(org_id, key_id), 32)?)
+}
+
+fn decode_base64(field: &str, value: &str) -> Result<Vec<u8>, AeadError> {
+    BASE64
+        .decode(value)
+        .map_err(|e| AeadError::DecryptionFailed(format!("Invalid {field}: {e}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REQUEST: &str =
+        r#"{"model":"gpt-4o","messages":[{"role":"user","content":"Quarterly numbers"}]}"#;
+
+    #[test]
+    fn test_audit_decrypts_escrowed_frame() {
+        let audit = AuditKey::new("acme", KeyPair::generate()).unwrap();
+        let escrow = EscrowKey::from_advertised(&audit.escrow_key().advertise()).unwrap();
+        assert_eq!(&escrow, audit.escrow_key());
+
+        let session_key = KeyMaterial::new(vec![0x42; 32]);
+        let mut ctx = SecurityContext::new(session_key.clone());
+        let frame = M2MFrame::new_request(REQUEST).unwrap();
+        let wire = escrow
+            .encode_secure(frame, SecurityMode::Aead, &mut ctx)
+            .unwrap();
+
+        // The peer decodes as usual; the auditor recovers the same payload
+        let peer = M2MFrame::decode_secure(&wire, &SecurityContext::new(session_key)).unwrap();
+        assert_eq!(peer.payload, REQUEST);
+        assert_eq!(audit.decrypt_frame(&wire).unwrap().payload, REQUEST);
+
+        // Another org's audit key cannot
+        let other = AuditKey::new("acme", KeyPair::generate()).unwrap();
+        assert!(other.decrypt_frame(&wire).is_err());
+    }
+
+    #[test]
+    fn test_tampered_escrow_rejected() {
+        let audit = AuditKey::new("acme", KeyPair::generate()).unwrap();
+        let mut ext = audit
+            .escrow_key()
+            .wrap(&KeyMaterial::new(vec![7; 32]))
+            .unwrap();
+        assert!(!ext.is_critical());
+        assert_eq!(audit.unwrap(&ext).unwrap().as_bytes(), &[7; 32]);
+
+        let last = ext.value.len() - 1;
+        ext.value[last] ^= 0x01;
+        assert!(audit.unwrap(&ext).is_err());
+
+        // Frames without escrow cannot be opened
+        let mut ctx = SecurityContext::new(KeyMaterial::new(vec![7; 32]));
+        let wire = M2MFrame::new_request(REQUEST)
+            .unwrap()
+            .encode_secure(SecurityMode::Aead, &mut ctx)
+            .unwrap();
+        assert!(audit.decrypt_frame(&wire).is_err());
+    }
+}
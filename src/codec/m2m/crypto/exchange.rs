@@ -2,10 +2,31 @@
 //!
 //! Allows two agents from different organizations to establish a shared
 //! secret without prior key distribution.
+//!
+//! # Suites
+//!
+//! | Suite            | Share version | Feature | Shared secret                 |
+//! |------------------|---------------|---------|-------------------------------|
+//! | `X25519`         | 1             | -       | X25519 (32 bytes)             |
+//! | `X25519MLKEM768` | 2             | `pqc`   | X25519 ‖ ML-KEM-768 (64 bytes) |
+//!
+//! The suite is negotiated through [`SecurityCaps`](crate::protocol::SecurityCaps).
+//! The hybrid suite is asymmetric: the initiator offers an ML-KEM
+//! encapsulation key and the responder answers with a ciphertext.
+//!
+//! ```rust,ignore
+//! let mut initiator = KeyExchange::with_suite(suite)?;
+//! let mut responder = KeyExchange::with_suite(suite)?;
+//!
+//! let answer = responder.respond(&initiator.key_share())?;
+//! initiator.complete(&answer)?;
+//! ```
 
 #![allow(missing_docs)]
 
 use super::keyring::KeyMaterial;
+use super::transcript::Transcript;
+use crate::protocol::KeyExchangeSuite;
 use thiserror::Error;
 
 /// Errors from key exchange operations
@@ -18,10 +39,34 @@ pub enum KeyExchangeError {
     /// Key generation failed
     #[error("Key generation failed: {0}")]
     GenerationFailed(String),
+
+    /// Malformed key share, or one that does not fit the exchange
+    #[error("Invalid key share: {0}")]
+    InvalidKeyShare(String),
+
+    /// Suite not available in this build
+    #[error("Unsupported key exchange suite: {0}")]
+    UnsupportedSuite(KeyExchangeSuite),
+
+    /// Peers saw different handshakes (possible downgrade attack)
+    #[error("Handshake transcript mismatch")]
+    TranscriptMismatch,
 }
 
+/// ML-KEM-768 encapsulation key size
+const MLKEM768_ENCAPSULATION_KEY_SIZE: usize = 1184;
+
+/// ML-KEM-768 ciphertext size
+const MLKEM768_CIPHERTEXT_SIZE: usize = 1088;
+
+/// Hybrid share role byte: initiator offer
+const HYBRID_OFFER: u8 = 0;
+
+/// Hybrid share role byte: responder answer
+const HYBRID_ANSWER: u8 = 1;
+
 /// X25519 public key (32 bytes)
-#[derive(Clone)]
+#[derive(Clone, PartialEq, Eq)]
 pub struct PublicKey([u8; 32]);
 
 impl PublicKey {
@@ -155,6 +200,12 @@ impl KeyPair {
         &self.public
     }
 
+    /// Export the secret key for persistence
+    #[cfg(feature = "crypto")]
+    pub(super) fn secret_bytes(&self) -> [u8; 32] {
+        self.secret.to_bytes()
+    }
+
     /// Perform Diffie-Hellman key exchange
     #[cfg(feature = "crypto")]
     pub fn diffie_hellman(&self, peer_public: &PublicKey) -> KeyMaterial {
@@ -187,11 +238,113 @@ impl std::fmt::Debug for KeyPair {
     }
 }
 
+/// Versioned key-exchange payload
+///
+/// Wire layout:
+///
+/// ```text
+/// v1: 0x01 | x25519 (32)
+/// v2: 0x02 | role (0 = offer, 1 = answer) | x25519 (32) | ML-KEM key or ciphertext
+/// ```
+///
+/// A bare 32-byte key (unversioned, as sent before suites existed) decodes
+/// as version 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeyShare {
+    /// X25519 public key
+    X25519(PublicKey),
+    /// Hybrid offer from the initiator
+    HybridOffer {
+        /// X25519 public key
+        x25519: PublicKey,
+        /// ML-KEM-768 encapsulation key
+        encapsulation_key: Vec<u8>,
+    },
+    /// Hybrid answer from the responder
+    HybridAnswer {
+        /// X25519 public key
+        x25519: PublicKey,
+        /// ML-KEM-768 ciphertext
+        ciphertext: Vec<u8>,
+    },
+}
+
+impl KeyShare {
+    /// Suite this share belongs to
+    pub fn suite(&self) -> KeyExchangeSuite {
+        match self {
+            KeyShare::X25519(_) => KeyExchangeSuite::X25519,
+            KeyShare::HybridOffer { .. } | KeyShare::HybridAnswer { .. } => {
+                KeyExchangeSuite::X25519MlKem768
+            },
+        }
+    }
+
+    /// Encode to wire format
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![self.suite().version()];
+        match self {
+            KeyShare::X25519(public) => bytes.extend_from_slice(public.as_bytes()),
+            KeyShare::HybridOffer {
+                x25519,
+                encapsulation_key,
+            } => {
+                bytes.push(HYBRID_OFFER);
+                bytes.extend_from_slice(x25519.as_bytes());
+                bytes.extend_from_slice(encapsulation_key);
+            },
+            KeyShare::HybridAnswer { x25519, ciphertext } => {
+                bytes.push(HYBRID_ANSWER);
+                bytes.extend_from_slice(x25519.as_bytes());
+                bytes.extend_from_slice(ciphertext);
+            },
+        }
+        bytes
+    }
+
+    /// Decode from wire format
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, KeyExchangeError> {
+        if bytes.len() == 32 {
+            return Ok(KeyShare::X25519(PublicKey::from_slice(bytes)?));
+        }
+
+        match bytes {
+            [1, key @ ..] => Ok(KeyShare::X25519(PublicKey::from_slice(key)?)),
+            [2, role, rest @ ..] if rest.len() >= 32 => {
+                let (key, kem) = rest.split_at(32);
+                let x25519 = PublicKey::from_slice(key)?;
+                match (*role, kem.len()) {
+                    (HYBRID_OFFER, MLKEM768_ENCAPSULATION_KEY_SIZE) => Ok(KeyShare::HybridOffer {
+                        x25519,
+                        encapsulation_key: kem.to_vec(),
+                    }),
+                    (HYBRID_ANSWER, MLKEM768_CIPHERTEXT_SIZE) => Ok(KeyShare::HybridAnswer {
+                        x25519,
+                        ciphertext: kem.to_vec(),
+                    }),
+                    (role, len) => Err(KeyExchangeError::InvalidKeyShare(format!(
+                        "Hybrid share with role {role} and {len} ML-KEM bytes"
+                    ))),
+                }
+            },
+            [version, ..] => Err(KeyExchangeError::InvalidKeyShare(format!(
+                "Unknown version {version} ({} bytes)",
+                bytes.len()
+            ))),
+            [] => Err(KeyExchangeError::InvalidKeyShare("Empty".to_string())),
+        }
+    }
+}
+
 /// Key exchange helper for M2M sessions
-#[derive(Debug)]
 pub struct KeyExchange {
+    /// Negotiated suite
+    suite: KeyExchangeSuite,
     /// Our key pair
     key_pair: KeyPair,
+    /// Our ML-KEM key pair (hybrid initiator)
+    #[cfg(feature = "pqc")]
+    kem: Option<mlkem::KemKeyPair>,
     /// Peer's public key (once received)
     peer_public: Option<PublicKey>,
     /// Derived shared secret (once computed)
@@ -199,24 +352,123 @@ pub struct KeyExchange {
 }
 
 impl KeyExchange {
-    /// Create a new key exchange instance
+    /// Create a new X25519 key exchange instance
     pub fn new() -> Self {
-        Self {
-            key_pair: KeyPair::generate(),
-            peer_public: None,
-            shared_secret: None,
-        }
+        Self::with_key_pair(KeyPair::generate())
     }
 
     /// Create from an existing key pair
     pub fn with_key_pair(key_pair: KeyPair) -> Self {
         Self {
+            suite: KeyExchangeSuite::X25519,
             key_pair,
+            #[cfg(feature = "pqc")]
+            kem: None,
             peer_public: None,
             shared_secret: None,
         }
     }
 
+    /// Create for a negotiated suite
+    pub fn with_suite(suite: KeyExchangeSuite) -> Result<Self, KeyExchangeError> {
+        if !suite.is_supported() {
+            return Err(KeyExchangeError::UnsupportedSuite(suite));
+        }
+
+        let mut exchange = Self::new();
+        exchange.suite = suite;
+        #[cfg(feature = "pqc")]
+        if suite == KeyExchangeSuite::X25519MlKem768 {
+            exchange.kem = Some(mlkem::KemKeyPair::generate());
+        }
+        Ok(exchange)
+    }
+
+    /// Get the suite
+    pub fn suite(&self) -> KeyExchangeSuite {
+        self.suite
+    }
+
+    /// Get the share the initiator sends first
+    pub fn key_share(&self) -> KeyShare {
+        #[cfg(feature = "pqc")]
+        if let Some(ref kem) = self.kem {
+            return KeyShare::HybridOffer {
+                x25519: self.public_key().clone(),
+                encapsulation_key: kem.encapsulation_key(),
+            };
+        }
+        KeyShare::X25519(self.public_key().clone())
+    }
+
+    /// Compute the shared secret from the initiator's share and return the
+    /// answer to send back (responder side)
+    pub fn respond(&mut self, offer: &KeyShare) -> Result<KeyShare, KeyExchangeError> {
+        match offer {
+            KeyShare::X25519(peer) if self.suite == KeyExchangeSuite::X25519 => {
+                self.set_peer_public(peer.clone());
+                Ok(KeyShare::X25519(self.public_key().clone()))
+            },
+            #[cfg(feature = "pqc")]
+            KeyShare::HybridOffer {
+                x25519,
+                encapsulation_key,
+            } if self.suite == KeyExchangeSuite::X25519MlKem768 => {
+                let (ciphertext, kem_secret) = mlkem::encapsulate(encapsulation_key)?;
+                self.set_hybrid_secret(x25519.clone(), &kem_secret);
+                Ok(KeyShare::HybridAnswer {
+                    x25519: self.public_key().clone(),
+                    ciphertext,
+                })
+            },
+            other => Err(self.unexpected(other)),
+        }
+    }
+
+    /// Compute the shared secret from the responder's answer (initiator side)
+    pub fn complete(&mut self, answer: &KeyShare) -> Result<(), KeyExchangeError> {
+        match answer {
+            KeyShare::X25519(peer) if self.suite == KeyExchangeSuite::X25519 => {
+                self.set_peer_public(peer.clone());
+                Ok(())
+            },
+            #[cfg(feature = "pqc")]
+            KeyShare::HybridAnswer { x25519, ciphertext } => {
+                let Some(ref kem) = self.kem else {
+                    return Err(self.unexpected(answer));
+                };
+                let kem_secret = kem.decapsulate(ciphertext)?;
+                self.set_hybrid_secret(x25519.clone(), &kem_secret);
+                Ok(())
+            },
+            other => Err(self.unexpected(other)),
+        }
+    }
+
+    /// Combine the X25519 and ML-KEM secrets
+    ///
+    /// X25519 comes first so the result is at least as strong as classical
+    /// DH; session keys are derived from the whole 64 bytes via HKDF.
+    #[cfg(feature = "pqc")]
+    fn set_hybrid_secret(&mut self, peer_public: PublicKey, kem_secret: &[u8]) {
+        let mut secret = self
+            .key_pair
+            .diffie_hellman(&peer_public)
+            .as_bytes()
+            .to_vec();
+        secret.extend_from_slice(kem_secret);
+        self.peer_public = Some(peer_public);
+        self.shared_secret = Some(KeyMaterial::new(secret));
+    }
+
+    fn unexpected(&self, share: &KeyShare) -> KeyExchangeError {
+        KeyExchangeError::InvalidKeyShare(format!(
+            "{} share in {} exchange",
+            share.suite(),
+            self.suite
+        ))
+    }
+
     /// Get our public key to send to peer
     pub fn public_key(&self) -> &PublicKey {
         self.key_pair.public_key()
@@ -242,6 +494,20 @@ impl KeyExchange {
             .and_then(|secret| secret.derive(context.as_bytes(), 32).ok())
     }
 
+    /// Derive a session key bound to the handshake transcript
+    ///
+    /// Agents whose HELLO/ACCEPT were tampered with derive different keys.
+    #[cfg(feature = "crypto")]
+    pub fn derive_bound_session_key(
+        &self,
+        context: &str,
+        transcript: &Transcript,
+    ) -> Option<KeyMaterial> {
+        self.shared_secret
+            .as_ref()
+            .and_then(|secret| transcript.derive_key(secret, context).ok())
+    }
+
     /// Derive a session key (fallback without crypto)
     #[cfg(not(feature = "crypto"))]
     pub fn derive_session_key(&self, _context: &str) -> Option<KeyMaterial> {
@@ -254,12 +520,79 @@ impl KeyExchange {
     }
 }
 
+impl std::fmt::Debug for KeyExchange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("KeyExchange")
+            .field("suite", &self.suite)
+            .field("key_pair", &self.key_pair)
+            .field("peer_public", &self.peer_public)
+            .field("shared_secret", &self.shared_secret)
+            .finish_non_exhaustive()
+    }
+}
+
 impl Default for KeyExchange {
     fn default() -> Self {
         Self::new()
     }
 }
 
+/// ML-KEM-768 via the `ml-kem` crate
+#[cfg(feature = "pqc")]
+mod mlkem {
+    use ml_kem::kem::{Decapsulate, Encapsulate};
+    use ml_kem::{Ciphertext, Encoded, EncodedSizeUser, KemCore, MlKem768};
+    use rand::rngs::OsRng;
+
+    use super::KeyExchangeError;
+
+    type DecapsulationKey = <MlKem768 as KemCore>::DecapsulationKey;
+    type EncapsulationKey = <MlKem768 as KemCore>::EncapsulationKey;
+
+    /// ML-KEM-768 key pair held by the hybrid initiator
+    pub(super) struct KemKeyPair {
+        decapsulation: DecapsulationKey,
+        encapsulation: EncapsulationKey,
+    }
+
+    impl KemKeyPair {
+        pub(super) fn generate() -> Self {
+            let (decapsulation, encapsulation) = MlKem768::generate(&mut OsRng);
+            Self {
+                decapsulation,
+                encapsulation,
+            }
+        }
+
+        pub(super) fn encapsulation_key(&self) -> Vec<u8> {
+            self.encapsulation.as_bytes().to_vec()
+        }
+
+        pub(super) fn decapsulate(&self, ciphertext: &[u8]) -> Result<Vec<u8>, KeyExchangeError> {
+            let ciphertext = Ciphertext::<MlKem768>::try_from(ciphertext).map_err(|_| {
+                KeyExchangeError::InvalidKeyShare("Bad ML-KEM ciphertext".to_string())
+            })?;
+            let secret = self.decapsulation.decapsulate(&ciphertext).map_err(|_| {
+                KeyExchangeError::InvalidKeyShare("ML-KEM decapsulation failed".to_string())
+            })?;
+            Ok(secret.to_vec())
+        }
+    }
+
+    /// Encapsulate to a peer's key, returning (ciphertext, shared secret)
+    pub(super) fn encapsulate(
+        encapsulation_key: &[u8],
+    ) -> Result<(Vec<u8>, Vec<u8>), KeyExchangeError> {
+        let encoded = Encoded::<EncapsulationKey>::try_from(encapsulation_key).map_err(|_| {
+            KeyExchangeError::InvalidPublicKey("Bad ML-KEM encapsulation key".to_string())
+        })?;
+        let (ciphertext, secret) = EncapsulationKey::from_bytes(&encoded)
+            .encapsulate(&mut OsRng)
+            .map_err(|_| KeyExchangeError::GenerationFailed("ML-KEM encapsulation".to_string()))?;
+        Ok((ciphertext.to_vec(), secret.to_vec()))
+    }
+}
+
 /// Simple hex encoder
 fn hex_encode(bytes: &[u8]) -> String {
     use std::fmt::Write;
@@ -350,6 +683,89 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_key_share_encoding() {
+        let public = PublicKey::from_bytes([0x42; 32]);
+
+        let v1 = KeyShare::X25519(public.clone());
+        assert_eq!(v1.to_bytes().len(), 33);
+        assert_eq!(KeyShare::from_bytes(&v1.to_bytes()).unwrap(), v1);
+        // Unversioned keys from older agents
+        assert_eq!(KeyShare::from_bytes(&[0x42; 32]).unwrap(), v1);
+
+        let offer = KeyShare::HybridOffer {
+            x25519: public.clone(),
+            encapsulation_key: vec![7; MLKEM768_ENCAPSULATION_KEY_SIZE],
+        };
+        let answer = KeyShare::HybridAnswer {
+            x25519: public,
+            ciphertext: vec![9; MLKEM768_CIPHERTEXT_SIZE],
+        };
+        for share in [offer, answer] {
+            assert_eq!(share.suite(), KeyExchangeSuite::X25519MlKem768);
+            assert_eq!(KeyShare::from_bytes(&share.to_bytes()).unwrap(), share);
+        }
+
+        assert!(KeyShare::from_bytes(&[2, HYBRID_ANSWER]).is_err());
+        assert!(KeyShare::from_bytes(&[2; 100]).is_err());
+        assert!(KeyShare::from_bytes(&[3; 33]).is_err());
+        assert!(KeyShare::from_bytes(&[]).is_err());
+    }
+
+    #[test]
+    fn test_suite_exchange() {
+        let mut initiator = KeyExchange::with_suite(KeyExchangeSuite::X25519).unwrap();
+        let mut responder = KeyExchange::with_suite(KeyExchangeSuite::X25519).unwrap();
+
+        let answer = responder.respond(&initiator.key_share()).unwrap();
+        initiator.complete(&answer).unwrap();
+        assert_eq!(
+            initiator.shared_secret().unwrap().as_bytes(),
+            responder.shared_secret().unwrap().as_bytes()
+        );
+
+        // A hybrid offer cannot complete an X25519 exchange
+        let offer = KeyShare::HybridOffer {
+            x25519: initiator.public_key().clone(),
+            encapsulation_key: vec![0; MLKEM768_ENCAPSULATION_KEY_SIZE],
+        };
+        assert!(KeyExchange::new().respond(&offer).is_err());
+
+        if !cfg!(feature = "pqc") {
+            assert!(matches!(
+                KeyExchange::with_suite(KeyExchangeSuite::X25519MlKem768),
+                Err(KeyExchangeError::UnsupportedSuite(_))
+            ));
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "pqc")]
+    fn test_hybrid_exchange() {
+        let suite = KeyExchangeSuite::X25519MlKem768;
+        let mut initiator = KeyExchange::with_suite(suite).unwrap();
+        let mut responder = KeyExchange::with_suite(suite).unwrap();
+
+        let offer = KeyShare::from_bytes(&initiator.key_share().to_bytes()).unwrap();
+        let answer = responder.respond(&offer).unwrap();
+        initiator
+            .complete(&KeyShare::from_bytes(&answer.to_bytes()).unwrap())
+            .unwrap();
+
+        let secret = initiator.shared_secret().unwrap();
+        assert_eq!(secret.as_bytes().len(), 64);
+        assert_eq!(
+            secret.as_bytes(),
+            responder.shared_secret().unwrap().as_bytes()
+        );
+
+        // Classical answer to a hybrid offer is refused
+        let mut downgraded = KeyExchange::with_suite(suite).unwrap();
+        assert!(downgraded
+            .complete(&KeyShare::X25519(responder.public_key().clone()))
+            .is_err());
+    }
+
     #[test]
     fn test_key_pair_from_secret() {
         let secret = [0x42u8; 32];
@@ -0,0 +1,326 @@
+//! Selective field encryption for [`SecurityMode::FieldAead`] frames.
+//!
+//! Only conversation text is encrypted: every `content` value in the
+//! payload (message content, response message or delta content) is replaced
+//! by a sealed string, while the rest of the JSON — model, roles,
+//! parameters, tool definitions — stays readable:
+//!
+//! ```text
+//! {"role":"user","content":"Quarterly numbers"}
+//! {"role":"user","content":"m2m:sealed:<base64(nonce:12 || ciphertext || tag:16)>"}
+//! ```
+//!
+//! The frame is then HMAC-authenticated as in [`SecurityMode::Hmac`], so
+//! intermediaries can route, bill and rate-limit on the skeleton without
+//! access to the text, and cannot alter what they see.
+//!
+//! Values are sealed with ChaCha20-Poly1305 under a key derived from the
+//! session key (HKDF info [`FIELD_KEY_INFO`]); the associated data is the
+//! field's position in the payload, so sealed values cannot be swapped. The
+//! plaintext is the exact JSON text of the value, so decoding restores the
+//! original payload byte for byte. `null` content is left as is.
+//!
+//! [`SecurityMode::FieldAead`]: crate::codec::m2m::SecurityMode::FieldAead
+//! [`SecurityMode::Hmac`]: crate::codec::m2m::SecurityMode::Hmac
+
+use std::ops::Range;
+
+use super::aead::AeadCipher;
+use super::error::CryptoError;
+use super::keyring::KeyMaterial;
+use super::NONCE_SIZE;
+use crate::codec::b64;
+use crate::error::{M2MError, Result};
+
+/// Prefix of a sealed `content` string
+pub const SEALED_FIELD_PREFIX: &str = "m2m:sealed:";
+
+/// HKDF info deriving the field key from the session key
+pub const FIELD_KEY_INFO: &str = "m2m/v1/field-aead";
+
+/// Object key whose values are sealed
+const SEALED_KEY: &str = "content";
+
+/// Seal every `content` value of `json`
+///
+/// `nonce` is called once per sealed value.
+pub(crate) fn seal_fields(
+    json: &str,
+    session_key: &KeyMaterial,
+    mut nonce: impl FnMut() -> Result<[u8; NONCE_SIZE]>,
+) -> Result<String> {
+    let spans = content_spans(json)
+        .map_err(|e| M2MError::Compression(format!("Cannot seal fields: {e}")))?;
+    let cipher = field_cipher(session_key)?;
+
+    let mut sealed = String::with_capacity(json.len() * 2);
+    let mut last = 0;
+    for (index, span) in spans.into_iter().enumerate() {
+        let value = &json[span.clone()];
+        if value == "null" {
+            continue;
+        }
+        let ciphertext = cipher
+            .encrypt(value.as_bytes(), &nonce()?, &aad(index))
+            .map_err(CryptoError::from)?;
+        sealed.push_str(&json[last..span.start]);
+        sealed.push('"');
+        sealed.push_str(SEALED_FIELD_PREFIX);
+        sealed.push_str(&b64::encode(&ciphertext));
+        sealed.push('"');
+        last = span.end;
+    }
+    sealed.push_str(&json[last..]);
+    Ok(sealed)
+}
+
+/// Open every sealed `content` value of `json`
+///
+/// Fails if a non-null `content` value is not sealed.
+pub(crate) fn open_fields(json: &str, session_key: &KeyMaterial) -> Result<String> {
+    let spans = content_spans(json)
+        .map_err(|e| M2MError::Decompression(format!("Cannot open fields: {e}")))?;
+    let cipher = field_cipher(session_key)?;
+
+    let mut opened = String::with_capacity(json.len());
+    let mut last = 0;
+    for (index, span) in spans.into_iter().enumerate() {
+        let value = &json[span.clone()];
+        if value == "null" {
+            continue;
+        }
+        let encoded = value
+            .strip_prefix('"')
+            .and_then(|v| v.strip_suffix('"'))
+            .and_then(|v| v.strip_prefix(SEALED_FIELD_PREFIX))
+            .ok_or_else(|| {
+                M2MError::Decompression(format!("Unsealed content field at {}", span.start))
+            })?;
+        let ciphertext = b64::decode(encoded)
+            .map_err(|e| M2MError::Decompression(format!("Invalid sealed field: {e}")))?;
+        let plaintext = cipher
+            .decrypt(&ciphertext, &aad(index))
+            .map_err(CryptoError::from)?;
+        let plaintext = String::from_utf8(plaintext)
+            .map_err(|e| M2MError::Decompression(format!("Invalid UTF-8: {e}")))?;
+
+        opened.push_str(&json[last..span.start]);
+        opened.push_str(&plaintext);
+        last = span.end;
+    }
+    opened.push_str(&json[last..]);
+    Ok(opened)
+}
+
+/// Cipher sealing fields under a key derived from the session key
+fn field_cipher(session_key: &KeyMaterial) -> Result<AeadCipher> {
+    let key = session_key
+        .derive(FIELD_KEY_INFO.as_bytes(), 32)
+        .map_err(CryptoError::from)?;
+    Ok(AeadCipher::new(key).map_err(CryptoError::from)?)
+}
+
+/// Associated data binding a sealed value to its position
+fn aad(index: usize) -> Vec<u8> {
+    format!("{FIELD_KEY_INFO}/{index}").into_bytes()
+}
+
+/// Byte ranges of every outermost `content` value, in document order
+fn content_spans(json: &str) -> std::result::Result<Vec<Range<usize>>, String> {
+    let mut scanner = Scanner {
+        bytes: json.as_bytes(),
+        pos: 0,
+        spans: Vec::new(),
+    };
+    scanner.value(true)?;
+    scanner.whitespace();
+    if scanner.pos != scanner.bytes.len() {
+        return Err(format!("trailing data at {}", scanner.pos));
+    }
+    Ok(scanner.spans)
+}
+
+/// Minimal JSON scanner recording value spans
+struct Scanner<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    spans: Vec<Range<usize>>,
+}
+
+impl Scanner<'_> {
+    fn whitespace(&mut self) {
+        while matches!(self.peek(), Some(b' ' | b'\t' | b'\n' | b'\r')) {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.pos).copied()
+    }
+
+    fn expect(&mut self, byte: u8) -> std::result::Result<(), String> {
+        self.whitespace();
+        if self.peek() != Some(byte) {
+            return Err(format!("expected '{}' at {}", byte as char, self.pos));
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    /// Scan one value; `collect` records `content` spans inside it
+    fn value(&mut self, collect: bool) -> std::result::Result<(), String> {
+        self.whitespace();
+        match self.peek() {
+            Some(b'{') => self.object(collect),
+            Some(b'[') => self.array(collect),
+            Some(b'"') => self.string().map(|_| ()),
+            Some(_) => {
+                let start = self.pos;
+                while matches!(self.peek(), Some(b) if !b",]} \t\n\r".contains(&b)) {
+                    self.pos += 1;
+                }
+                if self.pos == start {
+                    return Err(format!("expected value at {start}"));
+                }
+                Ok(())
+            },
+            None => Err("unexpected end of JSON".to_string()),
+        }
+    }
+
+    /// Scan a string, returning its raw contents
+    fn string(&mut self) -> std::result::Result<&[u8], String> {
+        self.expect(b'"')?;
+        let start = self.pos;
+        loop {
+            match self.peek() {
+                Some(b'"') => break,
+                Some(b'\\') => self.pos += 2,
+                Some(_) => self.pos += 1,
+                None => return Err("unterminated string".to_string()),
+            }
+        }
+        let raw = &self.bytes[start..self.pos];
+        self.pos += 1;
+        Ok(raw)
+    }
+
+    fn object(&mut self, collect: bool) -> std::result::Result<(), String> {
+        self.expect(b'{')?;
+        self.whitespace();
+        if self.peek() == Some(b'}') {
+            self.pos += 1;
+            return Ok(());
+        }
+        loop {
+            let is_content = self.string()? == SEALED_KEY.as_bytes();
+            self.expect(b':')?;
+            self.whitespace();
+            let start = self.pos;
+            if collect && is_content {
+                self.value(false)?;
+                self.spans.push(start..self.pos);
+            } else {
+                self.value(collect)?;
+            }
+
+            self.whitespace();
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(b'}') => {
+                    self.pos += 1;
+                    return Ok(());
+                },
+                _ => return Err(format!("expected ',' or '}}' at {}", self.pos)),
+            }
+        }
+    }
+
+    fn array(&mut self, collect: bool) -> std::result::Result<(), String> {
+        self.expect(b'[')?;
+        self.whitespace();
+        if self.peek() == Some(b']') {
+            self.pos += 1;
+            return Ok(());
+        }
+        loop {
+            self.value(collect)?;
+            self.whitespace();
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(b']') => {
+                    self.pos += 1;
+                    return Ok(());
+                },
+                _ => return Err(format!("expected ',' or ']' at {}", self.pos)),
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "crypto"))]
+mod tests {
+    use super::*;
+    use crate::codec::m2m::crypto::SecurityContext;
+    use crate::codec::m2m::{M2MFrame, SecurityMode};
+
+    const REQUEST: &str = r#"{"model":"gpt-4o","temperature":0.2,"messages":[{"role":"system","content":"You audit \"ledgers\"."},{"role":"user","content":[{"type":"text","text":"Quarterly numbers"}]},{"role":"assistant","content":null,"tool_calls":[]}]}"#;
+
+    #[test]
+    fn test_sealed_frame_keeps_skeleton_readable() {
+        let key = KeyMaterial::new(vec![0x42; 32]);
+        let mut ctx = SecurityContext::new(key.clone());
+        let frame = M2MFrame::new_request(REQUEST).unwrap();
+        let wire = frame
+            .encode_secure(SecurityMode::FieldAead, &mut ctx)
+            .unwrap();
+
+        // Intermediaries read the skeleton and routing header, not the text
+        let seen = M2MFrame::decode(&wire).unwrap();
+        assert_eq!(seen.fixed.security, SecurityMode::FieldAead);
+        assert_eq!(seen.routing.unwrap().model, "gpt-4o");
+        let skeleton: serde_json::Value = serde_json::from_str(&seen.payload).unwrap();
+        assert_eq!(skeleton["temperature"], 0.2);
+        assert_eq!(skeleton["messages"][1]["role"], "user");
+        assert!(skeleton["messages"][2]["content"].is_null());
+        for i in 0..2 {
+            let content = skeleton["messages"][i]["content"].as_str().unwrap();
+            assert!(content.starts_with(SEALED_FIELD_PREFIX), "{content}");
+        }
+        assert!(!seen.payload.contains("Quarterly") && !seen.payload.contains("ledgers"));
+
+        // The peer gets the original payload back byte for byte
+        let opened = M2MFrame::decode_secure(&wire, &SecurityContext::new(key)).unwrap();
+        assert_eq!(opened.payload, REQUEST);
+        assert_eq!(opened.checksum, frame.checksum);
+
+        // Another key cannot open it
+        let other = SecurityContext::new(KeyMaterial::new(vec![0x24; 32]));
+        assert!(M2MFrame::decode_secure(&wire, &other).is_err());
+    }
+
+    #[test]
+    fn test_sealed_fields_cannot_be_moved_or_stripped() {
+        let key = KeyMaterial::new(vec![7; 32]);
+        let mut nonce = 0u8;
+        let sealed = seal_fields(r#"[{"content":"a"},{"content":"b"}]"#, &key, || {
+            nonce += 1;
+            Ok([nonce; NONCE_SIZE])
+        })
+        .unwrap();
+        assert_eq!(
+            open_fields(&sealed, &key).unwrap(),
+            r#"[{"content":"a"},{"content":"b"}]"#
+        );
+
+        // Swapping two sealed values breaks their associated data
+        let spans = content_spans(&sealed).unwrap();
+        let (first, second) = (&sealed[spans[0].clone()], &sealed[spans[1].clone()]);
+        let swapped = format!(r#"[{{"content":{second}}},{{"content":{first}}}]"#);
+        assert!(open_fields(&swapped, &key).is_err());
+
+        // Plaintext content is refused
+        assert!(open_fields(r#"{"content":"plain"}"#, &key).is_err());
+        assert!(seal_fields(r#"{"content":"#, &key, || Ok([0; NONCE_SIZE])).is_err());
+    }
+}
@@ -0,0 +1,205 @@
+//! Group keys for broadcast to multiple agents.
+//!
+//! Sending the same payload to N agents over pairwise sessions costs N
+//! encryptions. A [`GroupKey`] is shared by every member of a group, so a
+//! BROADCAST message is sealed once and any member can open it.
+//!
+//! Members obtain the key in one of two ways:
+//!
+//! - **Same organization**: derive it from the organization master with
+//!   [`KeyHierarchy::derive_group_key`](super::KeyHierarchy::derive_group_key)
+//!   (path `m2m/v1/{org}/group/{group_id}`, or `.../epoch/{n}` after rotation).
+//! - **Distributed**: a member holding the key wraps it for each recipient's
+//!   X25519 public key with [`GroupKey::wrap_for`]; the recipient opens the
+//!   [`WrappedGroupKey`] with its key pair.
+//!
+//! Wrapping uses a fresh ephemeral key pair, so a wrapped key does not
+//! identify its sender. Deliver it over an authenticated channel (an
+//! established session) so recipients know who vouches for the group.
+//!
+//! Removing a member requires rotating to a new epoch; the removed member
+//! keeps the ability to open messages sealed under epochs it held.
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use serde::{Deserialize, Serialize};
+
+use super::aead::{AeadCipher, AeadError};
+use super::error::CryptoError;
+use super::exchange::{KeyPair, PublicKey};
+use super::hierarchy::{AgentId, M2M_KDF_VERSION};
+use super::keyring::KeyMaterial;
+
+/// Group key shared by all members of a broadcast group
+#[derive(Debug, Clone)]
+pub struct GroupKey {
+    group_id: String,
+    epoch: u32,
+    key: KeyMaterial,
+}
+
+impl GroupKey {
+    /// Create a group key from existing key material (32 bytes)
+    ///
+    /// The group ID follows the same rules as agent IDs.
+    pub fn new(
+        group_id: impl Into<String>,
+        epoch: u32,
+        key: KeyMaterial,
+    ) -> Result<Self, CryptoError> {
+        let group_id = AgentId::try_new(group_id)?.as_str().to_string();
+        AeadCipher::new(key.clone())?;
+        Ok(Self {
+            group_id,
+            epoch,
+            key,
+        })
+    }
+
+    /// Group identifier
+    pub fn group_id(&self) -> &str {
+        &self.group_id
+    }
+
+    /// Key epoch (incremented on rotation)
+    pub fn epoch(&self) -> u32 {
+        self.epoch
+    }
+
+    /// Key material
+    pub fn key(&self) -> &KeyMaterial {
+        &self.key
+    }
+
+    /// Encrypt content once for every member (base64 of nonce || ciphertext || tag)
+    pub fn seal(&self, content: &str) -> Result<String, CryptoError> {
+        let cipher = AeadCipher::new(self.key.clone())?;
+        let sealed =
+            cipher.encrypt_auto_nonce(content.as_bytes(), &aad(&self.group_id, self.epoch))?;
+        Ok(BASE64.encode(sealed))
+    }
+
+    /// Decrypt content sealed with [`seal`](Self::seal)
+    pub fn open(&self, sealed: &str) -> Result<String, CryptoError> {
+        let bytes = decode_base64("broadcast payload", sealed)?;
+        let cipher = AeadCipher::new(self.key.clone())?;
+        let plaintext = cipher.decrypt(&bytes, &aad(&self.group_id, self.epoch))?;
+        String::from_utf8(plaintext).map_err(|e| {
+            AeadError::DecryptionFailed(format!("Broadcast payload is not UTF-8: {e}")).into()
+        })
+    }
+
+    /// Wrap the key for a recipient's X25519 public key
+    pub fn wrap_for(&self, recipient: &PublicKey) -> Result<WrappedGroupKey, CryptoError> {
+        let ephemeral = KeyPair::generate();
+        let wrap_key = wrap_key(
+            &ephemeral.diffie_hellman(recipient),
+            &self.group_id,
+            self.epoch,
+        )?;
+        let wrapped = AeadCipher::new(wrap_key)?
+            .encrypt_auto_nonce(self.key.as_bytes(), &aad(&self.group_id, self.epoch))?;
+
+        Ok(WrappedGroupKey {
+            group_id: self.group_id.clone(),
+            epoch: self.epoch,
+            ephemeral_public: BASE64.encode(ephemeral.public_key().as_bytes()),
+            wrapped: BASE64.encode(wrapped),
+        })
+    }
+}
+
+/// Group key encrypted for one recipient
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WrappedGroupKey {
+    /// Group identifier
+    pub group_id: String,
+    /// Key epoch
+    pub epoch: u32,
+    /// Sender's ephemeral X25519 public key (base64)
+    pub ephemeral_public: String,
+    /// Encrypted group key (base64)
+    pub wrapped: String,
+}
+
+impl WrappedGroupKey {
+    /// Recover the group key with the recipient's key pair
+    pub fn unwrap(&self, recipient: &KeyPair) -> Result<GroupKey, CryptoError> {
+        let ephemeral =
+            PublicKey::from_slice(&decode_base64("ephemeral key", &self.ephemeral_public)?)?;
+        let wrap_key = wrap_key(
+            &recipient.diffie_hellman(&ephemeral),
+            &self.group_id,
+            self.epoch,
+        )?;
+
+        let key = AeadCipher::new(wrap_key)?.decrypt(
+            &decode_base64("wrapped key", &self.wrapped)?,
+            &aad(&self.group_id, self.epoch),
+        )?;
+        GroupKey::new(self.group_id.clone(), self.epoch, KeyMaterial::new(key))
+    }
+}
+
+/// Associated data binding ciphertext to the group and epoch
+fn aad(group_id: &str, epoch: u32) -> Vec<u8> {
+    format!("{M2M_KDF_VERSION}/group/{group_id}/{epoch}").into_bytes()
+}
+
+fn decode_base64(field: &str, value: &str) -> Result<Vec<u8>, AeadError> {
+    BASE64
+        .decode(value)
+        .map_err(|e| AeadError::DecryptionFailed(format!("Invalid {field}: {e}")))
+}
+
+/// Key encrypting a group key for one recipient
+fn wrap_key(shared: &KeyMaterial, group_id: &str, epoch: u32) -> Result<KeyMaterial, CryptoError> {
+    let info = format!("{M2M_KDF_VERSION}/group-wrap/{group_id}/{epoch}");
+    Ok(shared.derive(info.as_bytes(), 32)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::m2m::crypto::KeyHierarchy;
+
+    #[test]
+    fn test_derived_group_key_shared_by_members() {
+        let hierarchy = KeyHierarchy::new(KeyMaterial::new(vec![0x42; 32]), "acme");
+        let key = hierarchy.derive_group_key("ops", 0).unwrap();
+        let again = hierarchy.derive_group_key("ops", 0).unwrap();
+        assert_eq!(key.key().as_bytes(), again.key().as_bytes());
+
+        let sealed = key.seal("deploy window opens at 02:00").unwrap();
+        assert_eq!(again.open(&sealed).unwrap(), "deploy window opens at 02:00");
+
+        // Other groups and epochs cannot open it
+        let rotated = hierarchy.derive_group_key("ops", 1).unwrap();
+        assert!(rotated.open(&sealed).is_err());
+        assert!(hierarchy
+            .derive_group_key("billing", 0)
+            .unwrap()
+            .open(&sealed)
+            .is_err());
+        assert!(hierarchy.derive_group_key("bad/group", 0).is_err());
+    }
+
+    #[test]
+    fn test_wrap_for_recipient() {
+        let key = GroupKey::new("ops", 3, KeyMaterial::new(vec![9; 32])).unwrap();
+        let recipient = KeyPair::generate();
+        let outsider = KeyPair::generate();
+
+        let wrapped = key.wrap_for(recipient.public_key()).unwrap();
+        let unwrapped = wrapped.unwrap(&recipient).unwrap();
+        assert_eq!(unwrapped.key().as_bytes(), key.key().as_bytes());
+        assert_eq!(unwrapped.epoch(), 3);
+        assert!(wrapped.unwrap(&outsider).is_err());
+
+        // Tampered epoch breaks the binding
+        let tampered = WrappedGroupKey {
+            epoch: 4,
+            ..wrapped
+        };
+        assert!(tampered.unwrap(&recipient).is_err());
+    }
+}
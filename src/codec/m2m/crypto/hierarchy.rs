@@ -18,6 +18,22 @@
 //!     └─[HKDF]─► "m2m/v1/{org}/shared"    ─► Shared Organization Key
 //! ```
 //!
+//! # Key Epochs and Revocation
+//!
+//! Each agent key has an epoch. Epoch 0 is the original agent key; rotating
+//! to epoch N derives `m2m/v1/{org}/{agent}/epoch/{N}`. A [`RevocationList`]
+//! attached to the hierarchy either revokes an agent outright or revokes all
+//! epochs below a minimum, and is checked on every agent and session key
+//! derivation:
+//!
+//! ```ignore
+//! let mut hierarchy = KeyHierarchy::new(master, "acme-corp");
+//! hierarchy.revoke_epochs_below(&AgentId::new("agent-001"), 2);
+//!
+//! assert!(hierarchy.derive_agent_key_epoch(&"agent-001".into(), 1).is_err());
+//! let key = hierarchy.derive_agent_key_epoch(&"agent-001".into(), 2)?;
+//! ```
+//!
 //! # Use Cases
 //!
 //! 1. **Same-Organization Agents**: All agents derive keys from org master,
@@ -53,6 +69,8 @@
 //! Output:      c87f687fae1cf5991cd0cc64e113ec09750b0d1c41338a41cd8ad90bdd60dba1
 //! ```
 
+use std::collections::HashMap;
+
 use super::keyring::{KeyMaterial, KeyringError};
 use thiserror::Error;
 
@@ -279,6 +297,96 @@ impl KeyPurpose {
     }
 }
 
+/// Revocation state for a single agent
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Revocation {
+    /// All epochs revoked
+    All,
+    /// Epochs below this value revoked
+    Below(u32),
+}
+
+/// Revoked agent identities and key epochs
+///
+/// # Epistemic Properties
+///
+/// - **K_i**: An agent absent from the list is not known to be revoked
+/// - **B_i**: Callers believe the list is current; distributing updates is
+///   the caller's responsibility
+#[derive(Debug, Clone, Default)]
+pub struct RevocationList {
+    entries: HashMap<String, Revocation>,
+}
+
+impl RevocationList {
+    /// Create an empty revocation list
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Revoke every epoch of an agent
+    pub fn revoke_agent(&mut self, agent_id: &AgentId) {
+        self.entries
+            .insert(agent_id.as_str().to_string(), Revocation::All);
+    }
+
+    /// Revoke all epochs of an agent below `min_epoch`
+    ///
+    /// Never lowers an existing minimum or un-revokes a fully revoked agent.
+    pub fn revoke_epochs_below(&mut self, agent_id: &AgentId, min_epoch: u32) {
+        let entry = self
+            .entries
+            .entry(agent_id.as_str().to_string())
+            .or_insert(Revocation::Below(0));
+        if let Revocation::Below(current) = entry {
+            *current = (*current).max(min_epoch);
+        }
+    }
+
+    /// Remove all revocations for an agent
+    pub fn reinstate(&mut self, agent_id: &AgentId) -> bool {
+        self.entries.remove(agent_id.as_str()).is_some()
+    }
+
+    /// Check if an agent key epoch is revoked
+    pub fn is_revoked(&self, agent_id: &str, epoch: u32) -> bool {
+        match self.entries.get(agent_id) {
+            Some(Revocation::All) => true,
+            Some(Revocation::Below(min)) => epoch < *min,
+            None => false,
+        }
+    }
+
+    /// Lowest non-revoked epoch for an agent (`None` if fully revoked)
+    pub fn min_epoch(&self, agent_id: &str) -> Option<u32> {
+        match self.entries.get(agent_id) {
+            Some(Revocation::All) => None,
+            Some(Revocation::Below(min)) => Some(*min),
+            None => Some(0),
+        }
+    }
+
+    /// Number of agents with revocations
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Check if no agents are revoked
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Fail with `KeyringError::Revoked` if the agent epoch is revoked
+    fn check(&self, agent_id: &AgentId, epoch: u32) -> Result<(), KeyringError> {
+        if self.is_revoked(agent_id.as_str(), epoch) {
+            return Err(KeyringError::Revoked(format!(
+                "agent {agent_id} epoch {epoch}"
+            )));
+        }
+        Ok(())
+    }
+}
+
 /// Hierarchical key derivation for M2M multi-agent systems
 ///
 /// Provides deterministic key derivation from a master secret,
@@ -295,6 +403,8 @@ pub struct KeyHierarchy {
     master: KeyMaterial,
     /// Organization identifier
     org_id: OrgId,
+    /// Revoked agents and epochs
+    revocations: RevocationList,
 }
 
 impl KeyHierarchy {
@@ -309,7 +419,11 @@ impl KeyHierarchy {
     /// Returns `IdError` if the organization ID is invalid.
     pub fn try_new(master: KeyMaterial, org_id: impl Into<String>) -> Result<Self, IdError> {
         let org_id = OrgId::try_new(org_id)?;
-        Ok(Self { master, org_id })
+        Ok(Self {
+            master,
+            org_id,
+            revocations: RevocationList::new(),
+        })
     }
 
     /// Create a new key hierarchy without validation.
@@ -325,9 +439,31 @@ impl KeyHierarchy {
         Self {
             master,
             org_id: OrgId::new(org_id),
+            revocations: RevocationList::new(),
         }
     }
 
+    /// Replace the revocation list
+    pub fn with_revocations(mut self, revocations: RevocationList) -> Self {
+        self.revocations = revocations;
+        self
+    }
+
+    /// Get the revocation list
+    pub fn revocations(&self) -> &RevocationList {
+        &self.revocations
+    }
+
+    /// Revoke every epoch of an agent
+    pub fn revoke_agent(&mut self, agent_id: &AgentId) {
+        self.revocations.revoke_agent(agent_id);
+    }
+
+    /// Revoke all epochs of an agent below `min_epoch` (key rotation)
+    pub fn revoke_epochs_below(&mut self, agent_id: &AgentId, min_epoch: u32) {
+        self.revocations.revoke_epochs_below(agent_id, min_epoch);
+    }
+
     /// Derive the organization-level key
     ///
     /// This is an intermediate key used for further derivations.
@@ -341,9 +477,35 @@ impl KeyHierarchy {
     /// Derive a key for a specific agent
     ///
     /// Path: `m2m/v1/{org_id}/{agent_id}`
+    ///
+    /// This is the epoch 0 key. Fails if epoch 0 is revoked.
     #[cfg(feature = "crypto")]
     pub fn derive_agent_key(&self, agent_id: &AgentId) -> Result<KeyMaterial, KeyringError> {
-        let path = format!("{}/{}/{}", M2M_KDF_VERSION, self.org_id, agent_id);
+        self.derive_agent_key_epoch(agent_id, 0)
+    }
+
+    /// Derive a key for a specific agent at a key epoch
+    ///
+    /// Path: `m2m/v1/{org_id}/{agent_id}` for epoch 0,
+    /// `m2m/v1/{org_id}/{agent_id}/epoch/{epoch}` otherwise.
+    ///
+    /// Fails with `KeyringError::Revoked` if the epoch is revoked.
+    #[cfg(feature = "crypto")]
+    pub fn derive_agent_key_epoch(
+        &self,
+        agent_id: &AgentId,
+        epoch: u32,
+    ) -> Result<KeyMaterial, KeyringError> {
+        self.revocations.check(agent_id, epoch)?;
+
+        let path = if epoch == 0 {
+            format!("{}/{}/{}", M2M_KDF_VERSION, self.org_id, agent_id)
+        } else {
+            format!(
+                "{}/{}/{}/epoch/{}",
+                M2M_KDF_VERSION, self.org_id, agent_id, epoch
+            )
+        };
         self.master.derive(path.as_bytes(), 32)
     }
 
@@ -379,18 +541,58 @@ impl KeyHierarchy {
     /// * `agent_a` - First agent ID
     /// * `agent_b` - Second agent ID  
     /// * `session_id` - Unique session identifier (e.g., timestamp, UUID)
+    ///
+    /// Fails with `KeyringError::Revoked` if either agent's epoch 0 key is revoked.
     #[cfg(feature = "crypto")]
     pub fn derive_session_key(
         &self,
         agent_a: &AgentId,
         agent_b: &AgentId,
         session_id: &str,
+    ) -> Result<KeyMaterial, KeyringError> {
+        self.derive_session_key_epoch((agent_a, 0), (agent_b, 0), session_id)
+    }
+
+    /// Derive a session key between two agents at given key epochs
+    ///
+    /// Path: `m2m/v1/{org_id}/session/{agent_a}:{agent_b}/{session_id}` when
+    /// both epochs are 0, otherwise
+    /// `m2m/v1/{org_id}/session/{agent_a}@{epoch_a}:{agent_b}@{epoch_b}/{session_id}`.
+    ///
+    /// Fails with `KeyringError::Revoked` if either agent epoch is revoked.
+    #[cfg(feature = "crypto")]
+    pub fn derive_session_key_epoch(
+        &self,
+        (agent_a, epoch_a): (&AgentId, u32),
+        (agent_b, epoch_b): (&AgentId, u32),
+        session_id: &str,
+    ) -> Result<KeyMaterial, KeyringError> {
+        self.revocations.check(agent_a, epoch_a)?;
+        self.revocations.check(agent_b, epoch_b)?;
+
+        if epoch_a == 0 && epoch_b == 0 {
+            return self.session_key_path(agent_a.as_str(), agent_b.as_str(), session_id);
+        }
+
+        self.session_key_path(
+            &format!("{agent_a}@{epoch_a}"),
+            &format!("{agent_b}@{epoch_b}"),
+            session_id,
+        )
+    }
+
+    #[cfg(feature = "crypto")]
+    fn session_key_path(
+        &self,
+        agent_a: &str,
+        agent_b: &str,
+        session_id: &str,
     ) -> Result<KeyMaterial, KeyringError> {
         // Sort agent IDs to ensure both parties derive the same key
-        let (first, second) = if agent_a.as_str() <= agent_b.as_str() {
-            (agent_a.as_str(), agent_b.as_str())
+        let (first, second) = if agent_a <= agent_b {
+            (agent_a, agent_b)
         } else {
-            (agent_b.as_str(), agent_a.as_str())
+            (agent_b, agent_a)
         };
 
         let path = format!(
@@ -411,6 +613,30 @@ impl KeyHierarchy {
         self.master.derive(path.as_bytes(), 32)
     }
 
+    /// Derive the key of a broadcast group at a key epoch
+    ///
+    /// Path: `m2m/v1/{org_id}/group/{group_id}` for epoch 0,
+    /// `m2m/v1/{org_id}/group/{group_id}/epoch/{epoch}` otherwise. Rotate
+    /// to a new epoch when a member leaves.
+    #[cfg(feature = "crypto")]
+    pub fn derive_group_key(
+        &self,
+        group_id: &str,
+        epoch: u32,
+    ) -> Result<super::GroupKey, super::CryptoError> {
+        let group = AgentId::try_new(group_id)?;
+        let path = if epoch == 0 {
+            format!("{}/{}/group/{}", M2M_KDF_VERSION, self.org_id, group)
+        } else {
+            format!(
+                "{}/{}/group/{}/epoch/{}",
+                M2M_KDF_VERSION, self.org_id, group, epoch
+            )
+        };
+        let key = self.master.derive(path.as_bytes(), 32)?;
+        super::GroupKey::new(group.as_str(), epoch, key)
+    }
+
     /// Get the organization ID
     pub fn org_id(&self) -> &str {
         self.org_id.as_str()
@@ -539,6 +765,76 @@ mod tests {
         KeyMaterial::new(vec![0x42u8; 32])
     }
 
+    // =========================================================================
+    // Key epoch and revocation tests
+    // =========================================================================
+
+    #[test]
+    fn test_agent_key_epochs() {
+        let hierarchy = KeyHierarchy::new(test_master(), "test-org");
+        let agent = AgentId::new("agent-001");
+
+        // Epoch 0 is the original agent key
+        let k0 = hierarchy.derive_agent_key_epoch(&agent, 0).unwrap();
+        assert_eq!(
+            k0.as_bytes(),
+            hierarchy.derive_agent_key(&agent).unwrap().as_bytes()
+        );
+
+        let k1 = hierarchy.derive_agent_key_epoch(&agent, 1).unwrap();
+        let k2 = hierarchy.derive_agent_key_epoch(&agent, 2).unwrap();
+        assert_ne!(k0.as_bytes(), k1.as_bytes());
+        assert_ne!(k1.as_bytes(), k2.as_bytes());
+    }
+
+    #[test]
+    fn test_revoke_epochs_below() {
+        let mut hierarchy = KeyHierarchy::new(test_master(), "test-org");
+        let agent = AgentId::new("agent-001");
+        let peer = AgentId::new("agent-002");
+
+        hierarchy.revoke_epochs_below(&agent, 2);
+        // Lowering the minimum has no effect
+        hierarchy.revoke_epochs_below(&agent, 1);
+
+        assert!(matches!(
+            hierarchy.derive_agent_key(&agent),
+            Err(KeyringError::Revoked(_))
+        ));
+        assert!(hierarchy.derive_agent_key_epoch(&agent, 1).is_err());
+        assert!(hierarchy.derive_agent_key_epoch(&agent, 2).is_ok());
+        assert_eq!(hierarchy.revocations().min_epoch("agent-001"), Some(2));
+
+        // Session keys are checked too
+        assert!(hierarchy
+            .derive_session_key(&agent, &peer, "session-1")
+            .is_err());
+        let k1 = hierarchy
+            .derive_session_key_epoch((&agent, 2), (&peer, 0), "session-1")
+            .unwrap();
+        let k2 = hierarchy
+            .derive_session_key_epoch((&peer, 0), (&agent, 2), "session-1")
+            .unwrap();
+        assert_eq!(k1.as_bytes(), k2.as_bytes());
+    }
+
+    #[test]
+    fn test_revoke_agent() {
+        let mut hierarchy = KeyHierarchy::new(test_master(), "test-org");
+        let agent = AgentId::new("agent-001");
+
+        hierarchy.revoke_agent(&agent);
+        hierarchy.revoke_epochs_below(&agent, 5);
+        assert!(hierarchy.derive_agent_key_epoch(&agent, 100).is_err());
+        assert!(AgentKeyContext::from_hierarchy(&hierarchy, agent.clone()).is_err());
+        assert_eq!(hierarchy.revocations().min_epoch("agent-001"), None);
+
+        let mut revocations = hierarchy.revocations().clone();
+        assert!(revocations.reinstate(&agent));
+        let hierarchy = hierarchy.with_revocations(revocations);
+        assert!(hierarchy.derive_agent_key(&agent).is_ok());
+    }
+
     // =========================================================================
     // ID validation tests
     // =========================================================================
@@ -0,0 +1,417 @@
+//! Long-term agent identities and Noise-IK-style handshakes.
+//!
+//! An [`AgentIdentity`] pairs an [`AgentId`] with a static X25519 key pair
+//! and free-form metadata. It is persisted through any
+//! [`KeyringBackend`](super::KeyringBackend) under the key ID
+//! `identity/<agent_id>`, so an agent keeps the same public key across
+//! restarts and peers can pin it.
+//!
+//! ```rust,ignore
+//! use m2m::codec::m2m::crypto::{AgentId, AgentIdentity, EncryptedFileBackend};
+//!
+//! let backend = EncryptedFileBackend::new("agent.keys", passphrase);
+//! let identity = AgentIdentity::load_or_generate(&backend, &AgentId::new("agent-001"))?;
+//! publish(identity.public_key());
+//! ```
+//!
+//! # IK Handshake
+//!
+//! When the initiator already knows the responder's static public key
+//! (from discovery or a previous session), it can authenticate both sides
+//! and send encrypted data in its first message, following the Noise `IK`
+//! pattern:
+//!
+//! ```text
+//! <- s                      (pre-known responder static key)
+//! -> e, es, s, ss, payload  (initiator static key sent encrypted)
+//! <- e, ee, se, payload
+//! ```
+//!
+//! `ck` and `h` chain through every step; DH outputs are mixed into `ck`
+//! with HKDF-SHA256, and every ciphertext authenticates `h`. The session
+//! key is derived from the final `ck`.
+//!
+//! ```rust,ignore
+//! let (handshake, hello) = alice.initiate_ik(bob.public_key(), b"early data")?;
+//! let (inbound, reply) = bob.respond_ik(&hello, b"welcome")?;
+//! assert_eq!(inbound.peer, *alice.public_key());
+//! let outcome = handshake.finish(&reply)?;
+//! assert_eq!(outcome.session_key.as_bytes(), inbound.session_key.as_bytes());
+//! ```
+//!
+//! The first payload is protected by the responder's static key only: it
+//! is not forward secret and may be replayed, so it should carry requests
+//! that are safe to repeat. The response payload and session key are
+//! forward secret.
+
+use std::collections::BTreeMap;
+
+use sha2::{Digest, Sha256};
+
+use super::aead::AeadCipher;
+use super::error::CryptoError;
+use super::exchange::{KeyExchangeError, KeyPair, PublicKey};
+use super::hierarchy::AgentId;
+use super::keyring::{KeyId, KeyMaterial, KeyringError};
+use super::keystore::KeyringBackend;
+use super::NONCE_SIZE;
+
+/// Handshake name hashed into the initial state
+const PROTOCOL_NAME: &[u8] = b"M2M_IK_25519_ChaChaPoly_SHA256";
+
+/// HKDF info for the session key
+const SESSION_INFO: &[u8] = b"m2m-ik-session-v1";
+
+/// Size of the encrypted initiator static key (nonce, key, tag)
+const SEALED_STATIC_SIZE: usize = NONCE_SIZE + 32 + super::AEAD_TAG_SIZE;
+
+/// Agent identity with a static X25519 key pair
+pub struct AgentIdentity {
+    /// Agent identifier
+    agent_id: AgentId,
+    /// Static key pair
+    key_pair: KeyPair,
+    /// Free-form metadata (display name, organization, ...)
+    metadata: BTreeMap<String, String>,
+}
+
+impl AgentIdentity {
+    /// Generate an identity with a fresh key pair
+    pub fn generate(agent_id: AgentId) -> Self {
+        Self::with_key_pair(agent_id, KeyPair::generate())
+    }
+
+    /// Create from an existing key pair
+    pub fn with_key_pair(agent_id: AgentId, key_pair: KeyPair) -> Self {
+        Self {
+            agent_id,
+            key_pair,
+            metadata: BTreeMap::new(),
+        }
+    }
+
+    /// Add a metadata entry
+    pub fn with_metadata(mut self, key: &str, value: &str) -> Self {
+        self.metadata.insert(key.to_string(), value.to_string());
+        self
+    }
+
+    /// Get the agent ID
+    pub fn agent_id(&self) -> &AgentId {
+        &self.agent_id
+    }
+
+    /// Get the static public key peers pin
+    pub fn public_key(&self) -> &PublicKey {
+        self.key_pair.public_key()
+    }
+
+    /// Get the static key pair
+    pub fn key_pair(&self) -> &KeyPair {
+        &self.key_pair
+    }
+
+    /// Get the metadata
+    pub fn metadata(&self) -> &BTreeMap<String, String> {
+        &self.metadata
+    }
+
+    /// Keyring entry holding the identity of `agent_id`
+    pub fn key_id(agent_id: &AgentId) -> KeyId {
+        KeyId::new(format!("identity/{agent_id}"))
+    }
+
+    /// Store the identity, keeping the backend's other keys
+    pub fn save(&self, backend: &dyn KeyringBackend) -> Result<(), KeyringError> {
+        let metadata = serde_json::to_vec(&self.metadata)
+            .map_err(|e| KeyringError::Storage(format!("Identity metadata: {e}")))?;
+        let mut record = self.key_pair.secret_bytes().to_vec();
+        record.extend_from_slice(&metadata);
+
+        let mut keyring = backend.load()?;
+        keyring.add_key(Self::key_id(&self.agent_id), KeyMaterial::new(record));
+        backend.store(&keyring)
+    }
+
+    /// Load the stored identity of `agent_id`, if any
+    pub fn load(
+        backend: &dyn KeyringBackend,
+        agent_id: &AgentId,
+    ) -> Result<Option<Self>, KeyringError> {
+        let keyring = backend.load()?;
+        let Some(record) = keyring.get_key(&Self::key_id(agent_id)) else {
+            return Ok(None);
+        };
+
+        let invalid = || KeyringError::InvalidKey(format!("Malformed identity for {agent_id}"));
+        let (secret, metadata) = record
+            .as_bytes()
+            .split_first_chunk::<32>()
+            .ok_or_else(invalid)?;
+        let metadata = serde_json::from_slice(metadata).map_err(|_| invalid())?;
+
+        Ok(Some(Self {
+            agent_id: agent_id.clone(),
+            key_pair: KeyPair::from_secret(*secret),
+            metadata,
+        }))
+    }
+
+    /// Load the stored identity, generating and storing one on first run
+    pub fn load_or_generate(
+        backend: &dyn KeyringBackend,
+        agent_id: &AgentId,
+    ) -> Result<Self, KeyringError> {
+        if let Some(identity) = Self::load(backend, agent_id)? {
+            return Ok(identity);
+        }
+        let identity = Self::generate(agent_id.clone());
+        identity.save(backend)?;
+        Ok(identity)
+    }
+
+    /// Start an IK handshake with a responder whose static key is known
+    ///
+    /// Returns the handshake state and the first message, which carries
+    /// `payload` encrypted (0-RTT).
+    pub fn initiate_ik(
+        &self,
+        responder: &PublicKey,
+        payload: &[u8],
+    ) -> Result<(IkHandshake, Vec<u8>), CryptoError> {
+        let mut state = SymmetricState::new(responder);
+        let ephemeral = KeyPair::generate();
+        let mut message = ephemeral.public_key().as_bytes().to_vec();
+        state.mix_hash(ephemeral.public_key().as_bytes());
+
+        let key = state.mix_key(&ephemeral.diffie_hellman(responder));
+        message.extend(state.encrypt(&key, self.public_key().as_bytes())?);
+        let key = state.mix_key(&self.key_pair.diffie_hellman(responder));
+        message.extend(state.encrypt(&key, payload)?);
+
+        let handshake = IkHandshake {
+            state,
+            ephemeral,
+            static_pair: KeyPair::from_secret(self.key_pair.secret_bytes()),
+            responder: responder.clone(),
+        };
+        Ok((handshake, message))
+    }
+
+    /// Answer an IK handshake, returning the initiator's identity, its
+    /// early payload, the session key, and the reply carrying `payload`
+    ///
+    /// The initiator's static key is authenticated but not checked against
+    /// any allow list; compare [`IkOutcome::peer`] with known identities.
+    pub fn respond_ik(
+        &self,
+        message: &[u8],
+        payload: &[u8],
+    ) -> Result<(IkOutcome, Vec<u8>), CryptoError> {
+        if message.len() < 32 + SEALED_STATIC_SIZE {
+            return Err(malformed("IK initiation too short"));
+        }
+        let (initiator_ephemeral, rest) = message.split_at(32);
+        let (sealed_static, sealed_payload) = rest.split_at(SEALED_STATIC_SIZE);
+        let initiator_ephemeral = PublicKey::from_slice(initiator_ephemeral)?;
+
+        let mut state = SymmetricState::new(self.public_key());
+        state.mix_hash(initiator_ephemeral.as_bytes());
+        let key = state.mix_key(&self.key_pair.diffie_hellman(&initiator_ephemeral));
+        let peer = PublicKey::from_slice(&state.decrypt(&key, sealed_static)?)?;
+        let key = state.mix_key(&self.key_pair.diffie_hellman(&peer));
+        let early_payload = state.decrypt(&key, sealed_payload)?;
+
+        let ephemeral = KeyPair::generate();
+        let mut reply = ephemeral.public_key().as_bytes().to_vec();
+        state.mix_hash(ephemeral.public_key().as_bytes());
+        state.mix_key(&ephemeral.diffie_hellman(&initiator_ephemeral));
+        let key = state.mix_key(&ephemeral.diffie_hellman(&peer));
+        reply.extend(state.encrypt(&key, payload)?);
+
+        let outcome = IkOutcome {
+            peer,
+            payload: early_payload,
+            session_key: state.session_key()?,
+        };
+        Ok((outcome, reply))
+    }
+}
+
+impl std::fmt::Debug for AgentIdentity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AgentIdentity")
+            .field("agent_id", &self.agent_id)
+            .field("public_key", self.public_key())
+            .field("metadata", &self.metadata)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Initiator side of an IK handshake awaiting the reply
+pub struct IkHandshake {
+    /// Chaining key and transcript hash
+    state: SymmetricState,
+    /// Our ephemeral key pair
+    ephemeral: KeyPair,
+    /// Our static key pair
+    static_pair: KeyPair,
+    /// Responder's static key
+    responder: PublicKey,
+}
+
+impl IkHandshake {
+    /// Process the responder's reply
+    pub fn finish(mut self, reply: &[u8]) -> Result<IkOutcome, CryptoError> {
+        let Some((responder_ephemeral, sealed_payload)) = reply.split_first_chunk::<32>() else {
+            return Err(malformed("IK reply too short"));
+        };
+        let responder_ephemeral = PublicKey::from_bytes(*responder_ephemeral);
+
+        self.state.mix_hash(responder_ephemeral.as_bytes());
+        self.state
+            .mix_key(&self.ephemeral.diffie_hellman(&responder_ephemeral));
+        let key = self
+            .state
+            .mix_key(&self.static_pair.diffie_hellman(&responder_ephemeral));
+        let payload = self.state.decrypt(&key, sealed_payload)?;
+
+        Ok(IkOutcome {
+            peer: self.responder,
+            payload,
+            session_key: self.state.session_key()?,
+        })
+    }
+}
+
+/// Result of an IK handshake
+#[derive(Debug)]
+pub struct IkOutcome {
+    /// Peer's authenticated static key
+    pub peer: PublicKey,
+    /// Payload the peer sent in its handshake message
+    pub payload: Vec<u8>,
+    /// Session key shared with the peer
+    pub session_key: KeyMaterial,
+}
+
+/// Noise-style chaining key and handshake hash
+struct SymmetricState {
+    /// Chaining key
+    ck: KeyMaterial,
+    /// Hash of everything sent so far
+    h: [u8; 32],
+}
+
+impl SymmetricState {
+    /// Initial state, with the responder's static key as pre-message
+    fn new(responder: &PublicKey) -> Self {
+        let h: [u8; 32] = Sha256::digest(PROTOCOL_NAME).into();
+        let mut state = Self {
+            ck: KeyMaterial::new(h.to_vec()),
+            h,
+        };
+        state.mix_hash(responder.as_bytes());
+        state
+    }
+
+    fn mix_hash(&mut self, data: &[u8]) {
+        self.h = Sha256::new()
+            .chain_update(self.h)
+            .chain_update(data)
+            .finalize()
+            .into();
+    }
+
+    /// Mix a DH output into the chaining key, returning a message key
+    fn mix_key(&mut self, dh: &KeyMaterial) -> KeyMaterial {
+        let mut okm = [0u8; 64];
+        hkdf::Hkdf::<Sha256>::new(Some(self.ck.as_bytes()), dh.as_bytes())
+            .expand(&[], &mut okm)
+            .expect("64 bytes is a valid HKDF-SHA256 length");
+        self.ck = KeyMaterial::new(okm[..32].to_vec());
+        let key = KeyMaterial::new(okm[32..].to_vec());
+        zeroize::Zeroize::zeroize(&mut okm);
+        key
+    }
+
+    /// Encrypt with `h` as associated data, then hash the ciphertext
+    fn encrypt(&mut self, key: &KeyMaterial, plaintext: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        // Each message key is used once, so a fixed nonce is safe
+        let ciphertext =
+            AeadCipher::new(key.clone())?.encrypt(plaintext, &[0; NONCE_SIZE], &self.h)?;
+        self.mix_hash(&ciphertext);
+        Ok(ciphertext)
+    }
+
+    fn decrypt(&mut self, key: &KeyMaterial, ciphertext: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        let plaintext = AeadCipher::new(key.clone())?.decrypt(ciphertext, &self.h)?;
+        self.mix_hash(ciphertext);
+        Ok(plaintext)
+    }
+
+    fn session_key(&self) -> Result<KeyMaterial, CryptoError> {
+        let mut info = SESSION_INFO.to_vec();
+        info.extend_from_slice(&self.h);
+        Ok(self.ck.derive(&info, 32)?)
+    }
+}
+
+fn malformed(reason: &str) -> CryptoError {
+    KeyExchangeError::InvalidKeyShare(reason.to_string()).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::m2m::crypto::EncryptedFileBackend;
+
+    #[test]
+    fn test_ik_handshake() {
+        let alice = AgentIdentity::generate(AgentId::new("alice"));
+        let bob = AgentIdentity::generate(AgentId::new("bob"));
+
+        let (handshake, hello) = alice.initiate_ik(bob.public_key(), b"early").unwrap();
+        let (inbound, reply) = bob.respond_ik(&hello, b"welcome").unwrap();
+        assert_eq!(inbound.peer, *alice.public_key());
+        assert_eq!(inbound.payload, b"early");
+
+        let outcome = handshake.finish(&reply).unwrap();
+        assert_eq!(outcome.peer, *bob.public_key());
+        assert_eq!(outcome.payload, b"welcome");
+        assert_eq!(
+            outcome.session_key.as_bytes(),
+            inbound.session_key.as_bytes()
+        );
+
+        // Only the pinned responder can open the first message
+        let mallory = AgentIdentity::generate(AgentId::new("mallory"));
+        assert!(mallory.respond_ik(&hello, b"").is_err());
+
+        let mut tampered = hello.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(bob.respond_ik(&tampered, b"").is_err());
+        assert!(bob.respond_ik(&hello[..40], b"").is_err());
+    }
+
+    #[test]
+    fn test_identity_persistence() {
+        let dir = tempfile::tempdir().unwrap();
+        let backend =
+            EncryptedFileBackend::new(dir.path().join("agent.keys"), "pw").with_iterations(1000);
+        let agent_id = AgentId::new("agent-001");
+
+        assert!(AgentIdentity::load(&backend, &agent_id).unwrap().is_none());
+        let identity = AgentIdentity::generate(agent_id.clone()).with_metadata("org", "acme");
+        identity.save(&backend).unwrap();
+
+        let loaded = AgentIdentity::load_or_generate(&backend, &agent_id).unwrap();
+        assert_eq!(loaded.public_key(), identity.public_key());
+        assert_eq!(loaded.metadata()["org"], "acme");
+
+        // A second identity shares the keyring
+        let other = AgentIdentity::load_or_generate(&backend, &AgentId::new("agent-002")).unwrap();
+        assert_ne!(other.public_key(), identity.public_key());
+        assert!(AgentIdentity::load(&backend, &agent_id).unwrap().is_some());
+    }
+}
@@ -22,11 +22,19 @@
 //! // With minimum length check
 //! let key = KeyMaterial::try_new_with_min_length(bytes, 32)?;
 //! ```
+//!
+//! # Validity Windows
+//!
+//! Keys can be limited to a time window with `KeyMaterial::with_validity`
+//! or `valid_for`. A [`SecurityContext`](super::SecurityContext) refuses to
+//! encrypt or decrypt with a key outside its window, and derived keys
+//! inherit the window of the key they were derived from.
 
 #![allow(missing_docs)]
 
 use std::collections::HashMap;
 use std::fmt;
+use std::time::{Duration, SystemTime};
 use thiserror::Error;
 
 #[cfg(feature = "crypto")]
@@ -46,6 +54,14 @@ pub enum KeyringError {
     /// Key derivation failed
     #[error("Key derivation failed: {0}")]
     DerivationFailed(String),
+
+    /// Key belongs to a revoked identity or epoch
+    #[error("Key revoked: {0}")]
+    Revoked(String),
+
+    /// Persistent key storage failed (I/O, keychain, or wrong passphrase)
+    #[error("Key storage failed: {0}")]
+    Storage(String),
 }
 
 /// Errors from key material validation.
@@ -137,6 +153,12 @@ impl From<String> for KeyId {
 pub struct KeyMaterial {
     /// The raw key bytes
     bytes: Vec<u8>,
+    /// Start of the validity window (`None` = unbounded)
+    #[cfg_attr(feature = "crypto", zeroize(skip))]
+    not_before: Option<SystemTime>,
+    /// End of the validity window (`None` = unbounded)
+    #[cfg_attr(feature = "crypto", zeroize(skip))]
+    not_after: Option<SystemTime>,
 }
 
 /// Minimum recommended key size (256 bits / 32 bytes)
@@ -156,7 +178,7 @@ impl KeyMaterial {
         if bytes.is_empty() {
             return Err(KeyError::Empty);
         }
-        Ok(Self { bytes })
+        Ok(Self::new(bytes))
     }
 
     /// Create new key material with minimum length validation.
@@ -182,7 +204,7 @@ impl KeyMaterial {
                 min: min_length,
             });
         }
-        Ok(Self { bytes })
+        Ok(Self::new(bytes))
     }
 
     /// Create new key material from bytes without validation.
@@ -202,7 +224,54 @@ impl KeyMaterial {
     /// the key material is valid. This is a **B_i assumption** that shifts
     /// responsibility to the caller.
     pub fn new(bytes: Vec<u8>) -> Self {
-        Self { bytes }
+        Self {
+            bytes,
+            not_before: None,
+            not_after: None,
+        }
+    }
+
+    /// Limit the key to the window from `not_before` to `not_after`
+    ///
+    /// `None` leaves that end of the window open.
+    pub fn with_validity(
+        mut self,
+        not_before: Option<SystemTime>,
+        not_after: Option<SystemTime>,
+    ) -> Self {
+        self.not_before = not_before;
+        self.not_after = not_after;
+        self
+    }
+
+    /// Limit the key to `lifetime` from now
+    pub fn valid_for(self, lifetime: Duration) -> Self {
+        let now = SystemTime::now();
+        self.with_validity(Some(now), now.checked_add(lifetime))
+    }
+
+    /// Start of the validity window (`None` = unbounded)
+    pub fn not_before(&self) -> Option<SystemTime> {
+        self.not_before
+    }
+
+    /// End of the validity window (`None` = unbounded)
+    pub fn not_after(&self) -> Option<SystemTime> {
+        self.not_after
+    }
+
+    /// Check if the key may be used at `time`
+    pub fn is_valid_at(&self, time: SystemTime) -> bool {
+        self.not_before.is_none_or(|start| time >= start)
+            && self.not_after.is_none_or(|end| time < end)
+    }
+
+    /// Time left until the key expires (`None` = never; zero once expired)
+    pub fn expires_in(&self) -> Option<Duration> {
+        self.not_after.map(|end| {
+            end.duration_since(SystemTime::now())
+                .unwrap_or(Duration::ZERO)
+        })
     }
 
     /// Create key material from a hex string
@@ -239,7 +308,7 @@ impl KeyMaterial {
             .map_err(|e| KeyringError::DerivationFailed(format!("HKDF expand failed: {}", e)))?;
 
         // HKDF output is always valid, so we can use new() directly
-        Ok(KeyMaterial::new(okm))
+        Ok(KeyMaterial::new(okm).with_validity(self.not_before, self.not_after))
     }
 
     /// Derive a new key (no-op without crypto feature)
@@ -249,7 +318,7 @@ impl KeyMaterial {
         let mut result = vec![0u8; output_len];
         let copy_len = self.bytes.len().min(output_len);
         result[..copy_len].copy_from_slice(&self.bytes[..copy_len]);
-        Ok(KeyMaterial::new(result))
+        Ok(KeyMaterial::new(result).with_validity(self.not_before, self.not_after))
     }
 }
 
@@ -343,6 +412,16 @@ impl Keyring {
         self.keys.len()
     }
 
+    /// Iterate over keys
+    pub fn iter(&self) -> impl Iterator<Item = (&KeyId, &KeyMaterial)> {
+        self.keys.iter()
+    }
+
+    /// Get the default key ID
+    pub fn default_id(&self) -> Option<&KeyId> {
+        self.default_key.as_ref()
+    }
+
     /// Derive a session key from the default key
     #[cfg(feature = "crypto")]
     pub fn derive_session_key(
@@ -520,6 +599,29 @@ mod tests {
         assert!(debug.contains("3 bytes"));
     }
 
+    #[test]
+    fn test_key_material_validity() {
+        let now = SystemTime::now();
+        let key = KeyMaterial::new(vec![1; 32]);
+        assert!(key.is_valid_at(now));
+        assert_eq!(key.expires_in(), None);
+
+        let hour = Duration::from_secs(3600);
+        let key = key.with_validity(Some(now), Some(now + hour));
+        assert!(key.is_valid_at(now));
+        assert!(!key.is_valid_at(now - Duration::from_secs(1)));
+        assert!(!key.is_valid_at(now + hour));
+        assert!(key.expires_in().unwrap() <= hour);
+
+        // Derived keys inherit the window
+        let derived = key.derive(b"child", 32).unwrap();
+        assert_eq!(derived.not_after(), key.not_after());
+        assert_eq!(derived.not_before(), Some(now));
+
+        let expired = KeyMaterial::new(vec![1; 32]).with_validity(None, Some(now));
+        assert_eq!(expired.expires_in(), Some(Duration::ZERO));
+    }
+
     #[test]
     #[cfg(feature = "crypto")]
     fn test_hkdf_derivation() {
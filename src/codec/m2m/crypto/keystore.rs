@@ -0,0 +1,615 @@
+//! Encrypted-at-rest persistence for [`Keyring`].
+//!
+//! A [`Keyring`] lives in memory; agent identity keys are lost on restart
+//! unless stored. A [`KeyringBackend`] loads and stores a whole keyring.
+//!
+//! # Backends
+//!
+//! | Backend | Protection | Availability |
+//! |---------|------------|--------------|
+//! | [`EncryptedFileBackend`] | Passphrase (PBKDF2-HMAC-SHA256 + ChaCha20-Poly1305) | `crypto` feature |
+//! | `KeychainBackend` | macOS Keychain, Windows DPAPI, Secret Service (`secret-tool`) | `keychain` feature |
+//!
+//! # File Format
+//!
+//! ```text
+//! "M2MKEYS1" || iterations (u32 LE) || salt (16) || nonce (12) || ciphertext || tag (16)
+//! ```
+//!
+//! Everything before the nonce is authenticated as associated data, so the
+//! KDF parameters cannot be downgraded without detection.
+//!
+//! # Usage
+//!
+//! ```rust,ignore
+//! use m2m::codec::m2m::crypto::{EncryptedFileBackend, KeyringBackend};
+//!
+//! let backend = EncryptedFileBackend::new("agent.keys", passphrase);
+//! let mut keyring = backend.load()?; // empty on first run
+//! keyring.add_key(KeyId::new("identity"), KeyMaterial::new(secret));
+//! backend.store(&keyring)?;
+//! ```
+
+use std::path::{Path, PathBuf};
+
+use rand::RngCore;
+use zeroize::Zeroizing;
+
+use super::keyring::{KeyId, KeyMaterial, Keyring, KeyringError};
+use super::AeadCipher;
+
+/// Default PBKDF2 iteration count (OWASP 2023 guidance for HMAC-SHA256)
+pub const DEFAULT_KDF_ITERATIONS: u32 = 600_000;
+
+/// File magic and format version
+const FILE_MAGIC: &[u8; 8] = b"M2MKEYS1";
+
+/// Salt length (bytes)
+const SALT_SIZE: usize = 16;
+
+/// Storage for a whole keyring
+pub trait KeyringBackend: Send + Sync {
+    /// Load the stored keyring (empty if nothing has been stored yet)
+    fn load(&self) -> Result<Keyring, KeyringError>;
+
+    /// Replace the stored keyring
+    fn store(&self, keyring: &Keyring) -> Result<(), KeyringError>;
+}
+
+/// Passphrase-encrypted keyring file
+///
+/// # Epistemic Properties
+///
+/// - **K_i**: A file that decrypts was written with this passphrase and is
+///   unmodified (AEAD tag over header and keys)
+/// - **B_i**: The passphrase has enough entropy to resist offline guessing
+///   at the configured iteration count
+pub struct EncryptedFileBackend {
+    /// Keyring file path
+    path: PathBuf,
+    /// Passphrase (zeroized on drop)
+    passphrase: Zeroizing<String>,
+    /// PBKDF2 iterations for new files
+    iterations: u32,
+}
+
+impl EncryptedFileBackend {
+    /// Create a backend for a file and passphrase
+    pub fn new(path: impl Into<PathBuf>, passphrase: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            passphrase: Zeroizing::new(passphrase.into()),
+            iterations: DEFAULT_KDF_ITERATIONS,
+        }
+    }
+
+    /// Set PBKDF2 iterations used when storing
+    pub fn with_iterations(mut self, iterations: u32) -> Self {
+        self.iterations = iterations.max(1);
+        self
+    }
+
+    /// Keyring file path
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Derive the file key from the passphrase
+    fn file_key(&self, salt: &[u8], iterations: u32) -> Result<AeadCipher, KeyringError> {
+        let key = pbkdf2_sha256(self.passphrase.as_bytes(), salt, iterations);
+        AeadCipher::new(KeyMaterial::new(key.to_vec()))
+            .map_err(|e| KeyringError::Storage(e.to_string()))
+    }
+}
+
+impl KeyringBackend for EncryptedFileBackend {
+    fn load(&self) -> Result<Keyring, KeyringError> {
+        let data = match std::fs::read(&self.path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Keyring::new()),
+            Err(e) => return Err(storage(&self.path, e)),
+        };
+
+        let header_len = FILE_MAGIC.len() + 4 + SALT_SIZE;
+        if data.len() < header_len || !data.starts_with(FILE_MAGIC) {
+            return Err(storage(&self.path, "not an M2M keyring file"));
+        }
+        let iterations = u32::from_le_bytes(
+            data[FILE_MAGIC.len()..FILE_MAGIC.len() + 4]
+                .try_into()
+                .unwrap_or_default(),
+        );
+        let (header, sealed) = data.split_at(header_len);
+        let salt = &header[FILE_MAGIC.len() + 4..];
+
+        let plaintext = Zeroizing::new(
+            self.file_key(salt, iterations)?
+                .decrypt(sealed, header)
+                .map_err(|_| storage(&self.path, "wrong passphrase or corrupted file"))?,
+        );
+        decode_keyring(&plaintext)
+    }
+
+    fn store(&self, keyring: &Keyring) -> Result<(), KeyringError> {
+        let mut salt = [0u8; SALT_SIZE];
+        rand::thread_rng().fill_bytes(&mut salt);
+
+        let mut out = Vec::with_capacity(FILE_MAGIC.len() + 4 + SALT_SIZE);
+        out.extend_from_slice(FILE_MAGIC);
+        out.extend_from_slice(&self.iterations.to_le_bytes());
+        out.extend_from_slice(&salt);
+
+        let plaintext = encode_keyring(keyring);
+        let sealed = self
+            .file_key(&salt, self.iterations)?
+            .encrypt_auto_nonce(&plaintext, &out)
+            .map_err(|e| KeyringError::Storage(e.to_string()))?;
+        out.extend_from_slice(&sealed);
+
+        write_private(&self.path, &out)
+    }
+}
+
+/// Keyring stored in the operating system's credential store
+///
+/// The keyring is serialized into a single secret identified by service and
+/// account. macOS uses the login Keychain, Windows protects a file under
+/// `%APPDATA%\m2m` with DPAPI (bound to the user's logon credentials), and
+/// other Unix systems use the Secret Service via `secret-tool` (libsecret).
+#[cfg(feature = "keychain")]
+pub struct KeychainBackend {
+    /// Service name (e.g. `m2m-agent`)
+    service: String,
+    /// Account name (e.g. the agent ID)
+    account: String,
+}
+
+#[cfg(feature = "keychain")]
+impl KeychainBackend {
+    /// Create a backend for a service/account pair
+    pub fn new(service: &str, account: &str) -> Self {
+        Self {
+            service: service.to_string(),
+            account: account.to_string(),
+        }
+    }
+}
+
+#[cfg(feature = "keychain")]
+impl KeyringBackend for KeychainBackend {
+    fn load(&self) -> Result<Keyring, KeyringError> {
+        match os::read_secret(&self.service, &self.account)? {
+            Some(secret) => decode_keyring(&Zeroizing::new(secret)),
+            None => Ok(Keyring::new()),
+        }
+    }
+
+    fn store(&self, keyring: &Keyring) -> Result<(), KeyringError> {
+        os::write_secret(&self.service, &self.account, &encode_keyring(keyring))
+    }
+}
+
+#[cfg(all(feature = "keychain", target_os = "macos"))]
+mod os {
+    use security_framework::passwords::{get_generic_password, set_generic_password};
+
+    use super::KeyringError;
+
+    /// `errSecItemNotFound`
+    const ITEM_NOT_FOUND: i32 = -25300;
+
+    pub(super) fn read_secret(
+        service: &str,
+        account: &str,
+    ) -> Result<Option<Vec<u8>>, KeyringError> {
+        match get_generic_password(service, account) {
+            Ok(secret) => Ok(Some(secret)),
+            Err(e) if e.code() == ITEM_NOT_FOUND => Ok(None),
+            Err(e) => Err(KeyringError::Storage(format!("Keychain: {e}"))),
+        }
+    }
+
+    pub(super) fn write_secret(
+        service: &str,
+        account: &str,
+        secret: &[u8],
+    ) -> Result<(), KeyringError> {
+        set_generic_password(service, account, secret)
+            .map_err(|e| KeyringError::Storage(format!("Keychain: {e}")))
+    }
+}
+
+#[cfg(all(feature = "keychain", windows))]
+mod os {
+    use std::path::PathBuf;
+
+    use super::{write_private, KeyringError};
+
+    fn secret_path(service: &str, account: &str) -> Result<PathBuf, KeyringError> {
+        let appdata = std::env::var_os("APPDATA")
+            .ok_or_else(|| KeyringError::Storage("APPDATA not set".to_string()))?;
+        Ok(PathBuf::from(appdata)
+            .join("m2m")
+            .join(format!("{service}.{account}.dpapi")))
+    }
+
+    pub(super) fn read_secret(
+        service: &str,
+        account: &str,
+    ) -> Result<Option<Vec<u8>>, KeyringError> {
+        let path = secret_path(service, account)?;
+        match std::fs::read(&path) {
+            Ok(blob) => dpapi(&blob, false).map(Some),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(super::storage(&path, e)),
+        }
+    }
+
+    pub(super) fn write_secret(
+        service: &str,
+        account: &str,
+        secret: &[u8],
+    ) -> Result<(), KeyringError> {
+        let path = secret_path(service, account)?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| super::storage(dir, e))?;
+        }
+        write_private(&path, &dpapi(secret, true)?)
+    }
+
+    /// Protect or unprotect a blob with the current user's DPAPI key
+    #[allow(unsafe_code)] // DPAPI is only available through FFI
+    fn dpapi(data: &[u8], protect: bool) -> Result<Vec<u8>, KeyringError> {
+        use windows_sys::Win32::Foundation::LocalFree;
+        use windows_sys::Win32::Security::Cryptography::{
+            CryptProtectData, CryptUnprotectData, CRYPTPROTECT_UI_FORBIDDEN, CRYPT_INTEGER_BLOB,
+        };
+
+        let input = CRYPT_INTEGER_BLOB {
+            cbData: data.len() as u32,
+            pbData: data.as_ptr().cast_mut(),
+        };
+        let mut output = CRYPT_INTEGER_BLOB {
+            cbData: 0,
+            pbData: std::ptr::null_mut(),
+        };
+
+        // SAFETY: `input` borrows `data` for the duration of the call; DPAPI
+        // allocates `output` with LocalAlloc, released below.
+        let ok = unsafe {
+            if protect {
+                CryptProtectData(
+                    &input,
+                    std::ptr::null(),
+                    std::ptr::null(),
+                    std::ptr::null(),
+                    std::ptr::null(),
+                    CRYPTPROTECT_UI_FORBIDDEN,
+                    &mut output,
+                )
+            } else {
+                CryptUnprotectData(
+                    &input,
+                    std::ptr::null_mut(),
+                    std::ptr::null(),
+                    std::ptr::null(),
+                    std::ptr::null(),
+                    CRYPTPROTECT_UI_FORBIDDEN,
+                    &mut output,
+                )
+            }
+        };
+        if ok == 0 {
+            return Err(KeyringError::Storage(format!(
+                "DPAPI failed: {}",
+                std::io::Error::last_os_error()
+            )));
+        }
+
+        // SAFETY: on success `output` points to `cbData` bytes owned by us
+        unsafe {
+            let bytes = std::slice::from_raw_parts_mut(output.pbData, output.cbData as usize);
+            let result = bytes.to_vec();
+            bytes.fill(0);
+            LocalFree(output.pbData as _);
+            Ok(result)
+        }
+    }
+}
+
+#[cfg(all(feature = "keychain", unix, not(target_os = "macos")))]
+mod os {
+    use std::io::Write;
+    use std::process::{Command, Stdio};
+
+    use zeroize::Zeroizing;
+
+    use super::KeyringError;
+
+    fn secret_tool_error(e: impl std::fmt::Display) -> KeyringError {
+        KeyringError::Storage(format!("Secret Service (secret-tool): {e}"))
+    }
+
+    pub(super) fn read_secret(
+        service: &str,
+        account: &str,
+    ) -> Result<Option<Vec<u8>>, KeyringError> {
+        let output = Command::new("secret-tool")
+            .args(["lookup", "service", service, "account", account])
+            .stderr(Stdio::piped())
+            .output()
+            .map_err(secret_tool_error)?;
+
+        let stdout = Zeroizing::new(output.stdout);
+        let Some(hex) = lookup_output(output.status.code(), &stdout, &output.stderr)? else {
+            return Ok(None);
+        };
+        super::super::KeyMaterial::from_hex(hex)
+            .map(|secret| Some(secret.as_bytes().to_vec()))
+            .map_err(secret_tool_error)
+    }
+
+    /// Interpret a `secret-tool lookup` exit
+    ///
+    /// Only exit status 1 with nothing on stderr means the item is absent.
+    /// Anything else unexpected (locked keyring, no D-Bus session) is an
+    /// error, so callers never mistake it for an empty keyring and
+    /// overwrite the stored one.
+    pub(super) fn lookup_output<'a>(
+        code: Option<i32>,
+        stdout: &'a [u8],
+        stderr: &[u8],
+    ) -> Result<Option<&'a str>, KeyringError> {
+        let stderr = String::from_utf8_lossy(stderr);
+        match code {
+            Some(0) if !stdout.is_empty() => {
+                let hex = std::str::from_utf8(stdout).map_err(secret_tool_error)?;
+                Ok(Some(hex.trim()))
+            },
+            Some(1) if stdout.is_empty() && stderr.trim().is_empty() => Ok(None),
+            Some(0) => Err(secret_tool_error("lookup returned no secret")),
+            Some(code) => Err(secret_tool_error(format!(
+                "lookup exited with status {code}: {}",
+                stderr.trim()
+            ))),
+            None => Err(secret_tool_error(format!(
+                "lookup killed by signal: {}",
+                stderr.trim()
+            ))),
+        }
+    }
+
+    pub(super) fn write_secret(
+        service: &str,
+        account: &str,
+        secret: &[u8],
+    ) -> Result<(), KeyringError> {
+        let label = format!("M2M keyring ({service}/{account})");
+        let mut child = Command::new("secret-tool")
+            .args([
+                "store", "--label", &label, "service", service, "account", account,
+            ])
+            .stdin(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(secret_tool_error)?;
+
+        // Secret goes over stdin, never on the command line
+        let hex = Zeroizing::new(super::hex_encode(secret));
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(hex.as_bytes()).map_err(secret_tool_error)?;
+        }
+
+        let output = child.wait_with_output().map_err(secret_tool_error)?;
+        if !output.status.success() {
+            return Err(secret_tool_error(
+                String::from_utf8_lossy(&output.stderr).trim(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(feature = "keychain", not(any(unix, windows))))]
+mod os {
+    use super::KeyringError;
+
+    pub(super) fn read_secret(_: &str, _: &str) -> Result<Option<Vec<u8>>, KeyringError> {
+        Err(KeyringError::Storage(
+            "No OS keychain on this platform".to_string(),
+        ))
+    }
+
+    pub(super) fn write_secret(_: &str, _: &str, _: &[u8]) -> Result<(), KeyringError> {
+        Err(KeyringError::Storage(
+            "No OS keychain on this platform".to_string(),
+        ))
+    }
+}
+
+/// Serialize a keyring: default ID, then (ID, key) pairs, each length-prefixed (u16 LE)
+fn encode_keyring(keyring: &Keyring) -> Zeroizing<Vec<u8>> {
+    fn put(out: &mut Vec<u8>, bytes: &[u8]) {
+        out.extend_from_slice(&(bytes.len() as u16).to_le_bytes());
+        out.extend_from_slice(bytes);
+    }
+
+    let mut out = Zeroizing::new(Vec::new());
+    put(
+        &mut out,
+        keyring
+            .default_id()
+            .map_or(&[][..], |id| id.as_str().as_bytes()),
+    );
+    for (id, key) in keyring.iter() {
+        put(&mut out, id.as_str().as_bytes());
+        put(&mut out, key.as_bytes());
+    }
+    out
+}
+
+/// Inverse of [`encode_keyring`]
+fn decode_keyring(data: &[u8]) -> Result<Keyring, KeyringError> {
+    fn take<'a>(data: &mut &'a [u8]) -> Result<&'a [u8], KeyringError> {
+        let malformed = || KeyringError::Storage("Malformed keyring data".to_string());
+        let len_bytes = data.get(..2).ok_or_else(malformed)?;
+        let len = u16::from_le_bytes([len_bytes[0], len_bytes[1]]) as usize;
+        let value = data.get(2..2 + len).ok_or_else(malformed)?;
+        *data = &data[2 + len..];
+        Ok(value)
+    }
+    let id = |bytes: &[u8]| {
+        std::str::from_utf8(bytes)
+            .map(KeyId::new)
+            .map_err(|_| KeyringError::Storage("Key ID is not UTF-8".to_string()))
+    };
+
+    let mut rest = data;
+    let default = take(&mut rest)?;
+    let default = (!default.is_empty()).then(|| id(default)).transpose()?;
+
+    let mut keyring = Keyring::new();
+    while !rest.is_empty() {
+        let key_id = id(take(&mut rest)?)?;
+        let material = KeyMaterial::new(take(&mut rest)?.to_vec());
+        keyring.add_key(key_id, material);
+    }
+    if let Some(default) = default {
+        keyring.set_default(default)?;
+    }
+    Ok(keyring)
+}
+
+/// PBKDF2-HMAC-SHA256 with a single 32-byte output block (RFC 8018)
+fn pbkdf2_sha256(password: &[u8], salt: &[u8], iterations: u32) -> Zeroizing<[u8; 32]> {
+    use hmac::{Hmac, Mac};
+    use sha2::Sha256;
+
+    let prf = Hmac::<Sha256>::new_from_slice(password).expect("HMAC accepts any key length");
+
+    // U_1 = PRF(P, S || INT(1))
+    let mut mac = prf.clone();
+    mac.update(salt);
+    mac.update(&1u32.to_be_bytes());
+    let mut u: [u8; 32] = mac.finalize().into_bytes().into();
+    let mut t = Zeroizing::new(u);
+
+    for _ in 1..iterations {
+        let mut mac = prf.clone();
+        mac.update(&u);
+        u = mac.finalize().into_bytes().into();
+        for (t, u) in t.iter_mut().zip(u.iter()) {
+            *t ^= u;
+        }
+    }
+    t
+}
+
+/// Write a file readable only by the current user, replacing it atomically
+fn write_private(path: &Path, data: &[u8]) -> Result<(), KeyringError> {
+    use std::io::Write;
+
+    let tmp = path.with_extension("tmp");
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    let mut file = options.open(&tmp).map_err(|e| storage(&tmp, e))?;
+    file.write_all(data).map_err(|e| storage(&tmp, e))?;
+    file.sync_all().map_err(|e| storage(&tmp, e))?;
+    std::fs::rename(&tmp, path).map_err(|e| storage(path, e))
+}
+
+/// Lowercase hex encoding
+#[cfg(all(feature = "keychain", unix, not(target_os = "macos")))]
+fn hex_encode(bytes: &[u8]) -> String {
+    use std::fmt::Write;
+    bytes
+        .iter()
+        .fold(String::with_capacity(bytes.len() * 2), |mut s, b| {
+            let _ = write!(s, "{:02x}", b);
+            s
+        })
+}
+
+fn storage(path: &Path, e: impl std::fmt::Display) -> KeyringError {
+    KeyringError::Storage(format!("{}: {e}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Keyring {
+        let mut keyring = Keyring::new();
+        keyring.add_key(KeyId::new("identity"), KeyMaterial::new(vec![7u8; 32]));
+        keyring.add_key(KeyId::new("backup"), KeyMaterial::new(vec![9u8; 32]));
+        keyring.set_default(KeyId::new("backup")).unwrap();
+        keyring
+    }
+
+    #[test]
+    fn test_pbkdf2_rfc7914_vector() {
+        // RFC 7914 §11: PBKDF2-HMAC-SHA256("passwd", "salt", c=1)
+        let out = pbkdf2_sha256(b"passwd", b"salt", 1);
+        assert_eq!(out[..8], [0x55, 0xac, 0x04, 0x6e, 0x56, 0xe3, 0x08, 0x9f]);
+    }
+
+    #[test]
+    fn test_encrypted_file_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("agent.keys");
+        let backend = EncryptedFileBackend::new(&path, "correct horse").with_iterations(1000);
+
+        assert!(backend.load().unwrap().is_empty());
+        backend.store(&sample()).unwrap();
+
+        let loaded = backend.load().unwrap();
+        assert_eq!(loaded.len(), 2);
+        assert_eq!(loaded.default_id(), Some(&KeyId::new("backup")));
+        assert_eq!(
+            loaded.get_key(&KeyId::new("identity")).unwrap().as_bytes(),
+            &[7u8; 32]
+        );
+
+        // Wrong passphrase and tampering are both rejected
+        let wrong = EncryptedFileBackend::new(&path, "battery staple");
+        assert!(matches!(wrong.load(), Err(KeyringError::Storage(_))));
+
+        let mut data = std::fs::read(&path).unwrap();
+        data[FILE_MAGIC.len()] ^= 1; // iteration count is authenticated
+        std::fs::write(&path, &data).unwrap();
+        assert!(backend.load().is_err());
+    }
+
+    #[test]
+    #[cfg(all(feature = "keychain", unix, not(target_os = "macos")))]
+    fn test_secret_tool_lookup_failures() {
+        use super::os::lookup_output;
+
+        assert_eq!(
+            lookup_output(Some(0), b"0a0b\n", b"").unwrap(),
+            Some("0a0b")
+        );
+        assert_eq!(lookup_output(Some(1), b"", b"").unwrap(), None);
+        // A locked keyring or missing D-Bus session is not "absent"
+        for (code, stderr) in [
+            (
+                Some(1),
+                &b"Cannot create an item in a locked collection"[..],
+            ),
+            (Some(1), b"Cannot autolaunch D-Bus without X11 $DISPLAY"),
+            (Some(127), b""),
+            (None, b""),
+        ] {
+            assert!(matches!(
+                lookup_output(code, b"", stderr),
+                Err(KeyringError::Storage(_))
+            ));
+        }
+    }
+}
@@ -15,6 +15,8 @@
 //! 1. `SecurityMode::None` - No cryptographic protection (default)
 //! 2. `SecurityMode::Hmac` - HMAC-SHA256 authentication tag appended
 //! 3. `SecurityMode::Aead` - Full AEAD encryption with ChaCha20-Poly1305
+//! 4. `SecurityMode::FieldAead` - Only `content` values AEAD-encrypted, the
+//!    rest of the payload readable and HMAC-authenticated (see [`fields`])
 //!
 //! # Key Management
 //!
@@ -41,6 +43,11 @@
 //! let session = hierarchy.derive_session_key(&agent_a, &agent_b, "session-123")?;
 //! ```
 //!
+//! Broadcast groups derive a shared [`GroupKey`] the same way
+//! (`m2m/v1/{org}/group/{group_id}`), so a BROADCAST message is encrypted
+//! once for every member. Group keys can also be wrapped for a recipient's
+//! X25519 public key ([`WrappedGroupKey`]).
+//!
 //! ## Same-Owner M2M (Simple HKDF)
 //!
 //! For simpler cases without hierarchy:
@@ -61,6 +68,38 @@
 //! session_key = HKDF(shared_secret, "m2m-session-v1")
 //! ```
 //!
+//! With the `pqc` feature, peers that both advertise `X25519MLKEM768` in
+//! their `SecurityCaps` add ML-KEM-768 to the exchange, so recorded traffic
+//! stays confidential even if X25519 is later broken (see [`KeyShare`]).
+//!
+//! Session keys should be bound to the handshake with a [`Transcript`]
+//! (`Session::bind_key_exchange`), so an attacker who edits HELLO/ACCEPT to
+//! strip a security mode or suite is detected at the first DATA frame.
+//!
+//! ## Audit Escrow
+//!
+//! With the `escrow` feature, same-org agents that agree on an
+//! [`AuditEscrow`](crate::protocol::AuditEscrow) capability wrap each
+//! session key to the org's audit public key ([`EscrowKey`]) inside the
+//! frame header, so compliance can decrypt archived traffic with the audit
+//! private key ([`AuditKey`]).
+//!
+//! ## Stateless Frames
+//!
+//! Fire-and-forget messages (queues, webhooks) can skip the session: a
+//! [`StatelessCodec`] writes a [`KeyReference`] (keyring key ID, suite,
+//! epoch, timestamp, message ID) into each frame header, and receivers
+//! holding the same [`Keyring`] derive the frame key from it. Replays are
+//! bounded by a freshness window and a per-codec replay cache.
+//!
+//! ## Long-Term Identities
+//!
+//! An [`AgentIdentity`] keeps an agent's static X25519 key pair in a
+//! [`KeyringBackend`], so peers can pin its public key. When the initiator
+//! already knows that key, a Noise-IK-style handshake
+//! ([`AgentIdentity::initiate_ik`]) authenticates both agents and carries
+//! encrypted data in its first message.
+//!
 //! # Wire Format
 //!
 //! When security is enabled, the frame structure changes:
@@ -71,6 +110,9 @@
 //!
 //! AEAD mode:
 //!   #M2M|1|<headers><nonce:12><ciphertext><auth_tag:16>
+//!
+//! Field AEAD mode (payload with sealed `content` values):
+//!   #M2M|1|<headers><payload><hmac_tag:32>
 //! ```
 //!
 //! # Feature Flag
@@ -81,6 +123,8 @@
 //! m2m-core = { version = "0.4", features = ["crypto"] }
 //! ```
 //!
+//! The hybrid post-quantum key exchange additionally requires `pqc`.
+//!
 //! # Test Vectors
 //!
 //! This implementation is validated against:
@@ -91,26 +135,70 @@
 
 mod aead;
 mod error;
+pub mod fields;
 mod hmac_auth;
 mod keyring;
 
+#[cfg(feature = "escrow")]
+mod escrow;
+
 #[cfg(feature = "crypto")]
 mod exchange;
 
+#[cfg(feature = "crypto")]
+mod group;
+
 #[cfg(feature = "crypto")]
 mod hierarchy;
 
+#[cfg(feature = "crypto")]
+mod identity;
+
+#[cfg(feature = "crypto")]
+mod keystore;
+
+#[cfg(feature = "crypto")]
+mod stateless;
+
+#[cfg(feature = "crypto")]
+mod transcript;
+
 pub use aead::{AeadCipher, AeadError};
 pub use error::CryptoError;
+pub use fields::SEALED_FIELD_PREFIX;
 pub use hmac_auth::{HmacAuth, HmacError};
 pub use keyring::{KeyError, KeyId, KeyMaterial, Keyring, KeyringError, RECOMMENDED_KEY_SIZE};
 
+#[cfg(feature = "escrow")]
+pub use escrow::{AuditKey, EscrowKey};
+
+#[cfg(feature = "crypto")]
+pub use exchange::{KeyExchange, KeyExchangeError, KeyPair, KeyShare, PublicKey};
+
+#[cfg(feature = "crypto")]
+pub use group::{GroupKey, WrappedGroupKey};
+
+#[cfg(feature = "crypto")]
+pub use stateless::{
+    KeyReference, StatelessCodec, STATELESS_REPLAY_WINDOW_SECS, STATELESS_SUITE_V1,
+};
+
 #[cfg(feature = "crypto")]
-pub use exchange::{KeyExchange, KeyPair};
+pub use transcript::Transcript;
+
+#[cfg(feature = "crypto")]
+pub use identity::{AgentIdentity, IkHandshake, IkOutcome};
+
+#[cfg(feature = "crypto")]
+pub use keystore::{EncryptedFileBackend, KeyringBackend, DEFAULT_KDF_ITERATIONS};
+
+#[cfg(feature = "keychain")]
+pub use keystore::KeychainBackend;
 
 #[cfg(feature = "crypto")]
 pub use hierarchy::{
-    AgentId, AgentKeyContext, IdError, KeyHierarchy, KeyPurpose, OrgId, MAX_ID_LENGTH,
+    AgentId, AgentKeyContext, IdError, KeyHierarchy, KeyPurpose, OrgId, RevocationList,
+    MAX_ID_LENGTH,
 };
 
 use thiserror::Error;
@@ -189,6 +277,33 @@ impl SecurityContext {
         &self.key
     }
 
+    /// Check that the key is inside its validity window
+    ///
+    /// Secure frame encoding and decoding call this first, so an expired
+    /// key fails with [`M2MError::KeyExpired`](crate::error::M2MError::KeyExpired)
+    /// instead of protecting more traffic.
+    pub fn check_key(&self) -> crate::error::Result<()> {
+        use crate::error::M2MError;
+        use std::time::SystemTime;
+
+        let now = SystemTime::now();
+        if self.key.is_valid_at(now) {
+            return Ok(());
+        }
+        let message = match (self.key.not_before(), self.key.not_after()) {
+            (Some(start), _) if now < start => format!(
+                "key not valid for another {}s",
+                start.duration_since(now).unwrap_or_default().as_secs()
+            ),
+            (_, Some(end)) => format!(
+                "key expired {}s ago",
+                now.duration_since(end).unwrap_or_default().as_secs()
+            ),
+            _ => "key outside its validity window".to_string(),
+        };
+        Err(M2MError::KeyExpired(message))
+    }
+
     /// Generate a cryptographically secure random nonce for AEAD.
     ///
     /// Uses the system CSPRNG to generate a fresh 96-bit (12-byte) nonce
@@ -0,0 +1,419 @@
+//! Stateless secure frames for fire-and-forget messages.
+//!
+//! Agents talking through queues or webhooks often have no live session to
+//! derive keys from. A stateless frame names its key in a critical
+//! [`HeaderExtension`] of kind [`KeyReference::EXTENSION_KIND`], so any
+//! holder of the same [`Keyring`] entry can verify or decrypt it without a
+//! handshake:
+//!
+//! ```text
+//! [suite: 1][epoch: 4][issued_at: 8][message_id: 16][key_id: rest]
+//! ```
+//!
+//! | Field | Meaning |
+//! |-------|---------|
+//! | `suite` | [`STATELESS_SUITE_V1`]: HKDF-SHA256 frame keys, then the frame's security mode (ChaCha20-Poly1305 or HMAC-SHA256) |
+//! | `epoch` | Key epoch (little-endian); receivers refuse epochs below their minimum |
+//! | `issued_at` | Send time, Unix millis (little-endian) |
+//! | `message_id` | Random, single-use |
+//! | `key_id` | UTF-8 [`KeyId`] of the keyring entry |
+//!
+//! The frame key is `HKDF(keyring[key_id], "m2m/v1/stateless/{key_id}/{epoch}")`.
+//! The extension is covered by the frame's associated data, so the key
+//! reference, timestamp and message ID cannot be altered.
+//!
+//! # Replay Window
+//!
+//! Without a session there is no sequence to detect replays, so
+//! [`StatelessCodec::decode`] enforces:
+//!
+//! | Constraint | Enforcement |
+//! |------------|-------------|
+//! | `issued_at` within the window (default [`STATELESS_REPLAY_WINDOW_SECS`]) | Older and future frames rejected |
+//! | `message_id` single-use within the window | [`ReplayGuard`] |
+//!
+//! The window must cover the longest queue delay plus clock skew between
+//! agents; messages that sit in a queue longer are rejected, not replayed.
+//! The replay cache is per codec: consumers that share a queue must share
+//! one codec, or the queue must deliver each message to one consumer only.
+//! Redelivery of the same frame counts as a replay, so handlers should be
+//! idempotent or acknowledge before processing. Anything that needs
+//! ordering or exactly-once delivery belongs on a session.
+
+use std::time::Duration;
+
+use super::aead::AeadError;
+use super::error::CryptoError;
+use super::hierarchy::M2M_KDF_VERSION;
+use super::keyring::{KeyId, KeyMaterial, Keyring, KeyringError};
+use super::SecurityContext;
+use crate::codec::b64;
+use crate::codec::m2m::{HeaderExtension, M2MFrame, SecurityMode, M2M_PREFIX};
+use crate::error::{M2MError, Result};
+use crate::protocol::{EarlyData, ReplayGuard};
+
+/// Key derivation and cipher suite of stateless frames (HKDF-SHA256)
+pub const STATELESS_SUITE_V1: u8 = 0x01;
+
+/// Default freshness window for stateless frames (seconds)
+pub const STATELESS_REPLAY_WINDOW_SECS: u64 = 300;
+
+/// Size of the random message ID
+const MESSAGE_ID_SIZE: usize = 16;
+
+/// Size of the fixed part of a key reference
+const REFERENCE_HEADER_SIZE: usize = 1 + 4 + 8 + MESSAGE_ID_SIZE;
+
+/// Everything a receiver needs to find the key of a stateless frame
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyReference {
+    /// Key derivation and cipher suite
+    pub suite: u8,
+    /// Keyring entry the frame key is derived from
+    pub key_id: KeyId,
+    /// Key epoch
+    pub epoch: u32,
+    /// Send time (Unix millis)
+    pub issued_at: u64,
+    /// Random single-use message ID
+    pub message_id: [u8; MESSAGE_ID_SIZE],
+}
+
+impl KeyReference {
+    /// Header extension kind (critical: frames are undecodable without it)
+    pub const EXTENSION_KIND: u8 = HeaderExtension::CRITICAL | 0x03;
+
+    /// Reference a key for a new frame
+    pub fn new(key_id: KeyId, epoch: u32) -> Self {
+        Self {
+            suite: STATELESS_SUITE_V1,
+            key_id,
+            epoch,
+            issued_at: unix_millis(),
+            message_id: uuid::Uuid::new_v4().into_bytes(),
+        }
+    }
+
+    /// Encode as a header extension
+    pub fn to_extension(&self) -> HeaderExtension {
+        let key_id = self.key_id.as_str().as_bytes();
+        let mut value = Vec::with_capacity(REFERENCE_HEADER_SIZE + key_id.len());
+        value.push(self.suite);
+        value.extend_from_slice(&self.epoch.to_le_bytes());
+        value.extend_from_slice(&self.issued_at.to_le_bytes());
+        value.extend_from_slice(&self.message_id);
+        value.extend_from_slice(key_id);
+        HeaderExtension::new(Self::EXTENSION_KIND, value)
+    }
+
+    /// Find and parse the key reference among a frame's extensions
+    pub fn from_extensions(extensions: &[HeaderExtension]) -> Result<Option<Self>> {
+        let Some(ext) = extensions
+            .iter()
+            .find(|ext| ext.kind == Self::EXTENSION_KIND)
+        else {
+            return Ok(None);
+        };
+        let invalid = |reason: &str| {
+            M2MError::Decompression(format!("Invalid stateless key reference: {reason}"))
+        };
+
+        let value = ext.value.as_slice();
+        if value.len() <= REFERENCE_HEADER_SIZE {
+            return Err(invalid("too short"));
+        }
+        let key_id = std::str::from_utf8(&value[REFERENCE_HEADER_SIZE..])
+            .map_err(|_| invalid("key ID is not UTF-8"))?;
+        let mut epoch = [0u8; 4];
+        epoch.copy_from_slice(&value[1..5]);
+        let mut issued_at = [0u8; 8];
+        issued_at.copy_from_slice(&value[5..13]);
+        let mut message_id = [0u8; MESSAGE_ID_SIZE];
+        message_id.copy_from_slice(&value[13..REFERENCE_HEADER_SIZE]);
+
+        Ok(Some(Self {
+            suite: value[0],
+            key_id: KeyId::new(key_id),
+            epoch: u32::from_le_bytes(epoch),
+            issued_at: u64::from_le_bytes(issued_at),
+            message_id,
+        }))
+    }
+}
+
+/// Encodes and decodes self-describing secure frames without a session
+///
+/// Senders and receivers hold the same keyring entries. A sender picks the
+/// key and epoch; a receiver looks the key up by the ID in each frame and
+/// rejects stale, replayed or retired-epoch frames (see the
+/// [module documentation](self)).
+pub struct StatelessCodec {
+    keyring: Keyring,
+    key_id: Option<KeyId>,
+    epoch: u32,
+    min_epoch: u32,
+    mode: SecurityMode,
+    window: Duration,
+    replay: ReplayGuard,
+}
+
+impl StatelessCodec {
+    /// Create a codec over `keyring`, sending with its default key
+    pub fn new(keyring: Keyring) -> Self {
+        let window = Duration::from_secs(STATELESS_REPLAY_WINDOW_SECS);
+        Self {
+            keyring,
+            key_id: None,
+            epoch: 0,
+            min_epoch: 0,
+            mode: SecurityMode::Aead,
+            window,
+            replay: ReplayGuard::new().with_window(window),
+        }
+    }
+
+    /// Send with this keyring entry instead of the default key
+    pub fn with_key(mut self, key_id: impl Into<KeyId>) -> Self {
+        self.key_id = Some(key_id.into());
+        self
+    }
+
+    /// Set the key epoch of sent frames
+    pub fn with_epoch(mut self, epoch: u32) -> Self {
+        self.epoch = epoch;
+        self
+    }
+
+    /// Reject received frames with a lower key epoch
+    pub fn with_min_epoch(mut self, min_epoch: u32) -> Self {
+        self.min_epoch = min_epoch;
+        self
+    }
+
+    /// Set the security mode of sent frames (default: AEAD)
+    ///
+    /// `SecurityMode::None` is not allowed: stateless frames must be
+    /// authenticated.
+    pub fn with_mode(mut self, mode: SecurityMode) -> Result<Self> {
+        if mode == SecurityMode::None {
+            return Err(M2MError::Config(
+                "Stateless frames need HMAC or AEAD security".to_string(),
+            ));
+        }
+        self.mode = mode;
+        Ok(self)
+    }
+
+    /// Set the freshness window for received frames
+    pub fn with_replay_window(mut self, window: Duration) -> Self {
+        self.window = window;
+        self.replay = ReplayGuard::new().with_window(window);
+        self
+    }
+
+    /// Keys frames are sent and received with
+    pub fn keyring(&self) -> &Keyring {
+        &self.keyring
+    }
+
+    /// Encode a frame that names its own key
+    pub fn encode(&self, frame: M2MFrame) -> Result<Vec<u8>> {
+        let key_id = match self.key_id {
+            Some(ref id) => id.clone(),
+            None => self.keyring.default_id().cloned().ok_or_else(|| {
+                M2MError::Crypto(KeyringError::KeyNotFound("no default key".to_string()).into())
+            })?,
+        };
+        self.encode_with(frame, &KeyReference::new(key_id, self.epoch))
+    }
+
+    /// Encode a frame as text (base64)
+    pub fn encode_string(&self, frame: M2MFrame) -> Result<String> {
+        let bytes = self.encode(frame)?;
+        Ok(format!(
+            "{M2M_PREFIX}{}",
+            b64::encode(&bytes[M2M_PREFIX.len()..])
+        ))
+    }
+
+    fn encode_with(&self, frame: M2MFrame, reference: &KeyReference) -> Result<Vec<u8>> {
+        let mut ctx = SecurityContext::new(self.frame_key(reference)?);
+        frame
+            .with_extension(reference.to_extension())?
+            .encode_secure(self.mode, &mut ctx)
+    }
+
+    /// Verify and decode a stateless frame
+    ///
+    /// Fails for frames without a key reference, with an unknown key,
+    /// suite or retired epoch, outside the freshness window, or seen
+    /// before.
+    pub fn decode(&self, data: &[u8]) -> Result<M2MFrame> {
+        let reference = KeyReference::from_extensions(&M2MFrame::peek_extensions(data)?)?
+            .ok_or_else(|| {
+                M2MError::Decompression("Frame carries no stateless key reference".to_string())
+            })?;
+        if reference.suite != STATELESS_SUITE_V1 {
+            return Err(M2MError::Decompression(format!(
+                "Unsupported stateless suite 0x{:02x}",
+                reference.suite
+            )));
+        }
+        if reference.epoch < self.min_epoch {
+            return Err(M2MError::Crypto(
+                KeyringError::Revoked(format!(
+                    "{} epoch {} is below {}",
+                    reference.key_id, reference.epoch, self.min_epoch
+                ))
+                .into(),
+            ));
+        }
+
+        let frame =
+            M2MFrame::decode_secure(data, &SecurityContext::new(self.frame_key(&reference)?))?;
+        if frame.fixed.security == SecurityMode::None {
+            return Err(M2MError::Crypto(
+                AeadError::DecryptionFailed("Stateless frame is not authenticated".to_string())
+                    .into(),
+            ));
+        }
+        frame.require_extensions(&[KeyReference::EXTENSION_KIND])?;
+
+        // Only authentic frames may occupy the replay cache
+        let window = self.window.as_millis() as u64;
+        if unix_millis().abs_diff(reference.issued_at) > window {
+            return Err(M2MError::Protocol(
+                "Stateless frame outside freshness window".to_string(),
+            ));
+        }
+        self.replay
+            .check(&EarlyData {
+                nonce: uuid::Uuid::from_bytes(reference.message_id).to_string(),
+                issued_at: reference.issued_at,
+            })
+            .map_err(|e| M2MError::Protocol(format!("Stateless frame rejected: {e}")))?;
+        Ok(frame)
+    }
+
+    /// Verify and decode a text (base64) stateless frame
+    pub fn decode_string(&self, wire: &str) -> Result<M2MFrame> {
+        let encoded = wire
+            .strip_prefix(M2M_PREFIX)
+            .ok_or_else(|| M2MError::Decompression("Invalid M2M prefix".to_string()))?;
+        let mut data = M2M_PREFIX.as_bytes().to_vec();
+        data.extend(
+            b64::decode(encoded)
+                .map_err(|e| M2MError::Decompression(format!("Invalid base64: {e}")))?,
+        );
+        self.decode(&data)
+    }
+
+    /// Key of frames referencing `reference`
+    fn frame_key(&self, reference: &KeyReference) -> Result<KeyMaterial> {
+        let master = self.keyring.get_key(&reference.key_id).ok_or_else(|| {
+            M2MError::Crypto(KeyringError::KeyNotFound(reference.key_id.to_string()).into())
+        })?;
+        let info = format!(
+            "{M2M_KDF_VERSION}/stateless/{}/{}",
+            reference.key_id, reference.epoch
+        );
+        master
+            .derive(info.as_bytes(), 32)
+            .map_err(|e| M2MError::Crypto(CryptoError::from(e)))
+    }
+}
+
+impl std::fmt::Debug for StatelessCodec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StatelessCodec")
+            .field("key_id", &self.key_id)
+            .field("epoch", &self.epoch)
+            .field("min_epoch", &self.min_epoch)
+            .field("mode", &self.mode)
+            .field("window", &self.window)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Current time in Unix millis
+fn unix_millis() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REQUEST: &str =
+        r#"{"model":"gpt-4o","messages":[{"role":"user","content":"Nightly report"}]}"#;
+
+    fn keyring() -> Keyring {
+        let mut keyring = Keyring::new();
+        keyring.add_key(KeyId::new("queue-2026"), KeyMaterial::new(vec![0x5a; 32]));
+        keyring.set_default(KeyId::new("queue-2026")).unwrap();
+        keyring
+    }
+
+    #[test]
+    fn test_stateless_roundtrip_and_replay() {
+        let sender = StatelessCodec::new(keyring()).with_epoch(3);
+        let receiver = StatelessCodec::new(keyring()).with_min_epoch(3);
+
+        let wire = sender
+            .encode_string(M2MFrame::new_request(REQUEST).unwrap())
+            .unwrap();
+        assert_eq!(receiver.decode_string(&wire).unwrap().payload, REQUEST);
+        // Redelivery is a replay
+        assert!(receiver.decode_string(&wire).is_err());
+
+        // HMAC frames are self-describing too
+        let hmac = StatelessCodec::new(keyring())
+            .with_epoch(3)
+            .with_mode(SecurityMode::Hmac)
+            .unwrap();
+        let data = hmac
+            .encode(M2MFrame::new_request(REQUEST).unwrap())
+            .unwrap();
+        assert_eq!(receiver.decode(&data).unwrap().payload, REQUEST);
+        assert!(StatelessCodec::new(keyring())
+            .with_mode(SecurityMode::None)
+            .is_err());
+    }
+
+    #[test]
+    fn test_stateless_rejections() {
+        let sender = StatelessCodec::new(keyring());
+        let receiver = StatelessCodec::new(keyring()).with_min_epoch(1);
+        let frame = || M2MFrame::new_request(REQUEST).unwrap();
+
+        // Retired epoch, unknown key, stale frame
+        assert!(receiver.decode(&sender.encode(frame()).unwrap()).is_err());
+        let unknown = StatelessCodec::new(Keyring::new()).with_min_epoch(1);
+        let data = sender.with_epoch(1).encode(frame()).unwrap();
+        assert!(unknown.decode(&data).is_err());
+        let sender = StatelessCodec::new(keyring()).with_epoch(1);
+        let mut stale = KeyReference::new(KeyId::new("queue-2026"), 1);
+        stale.issued_at -= (STATELESS_REPLAY_WINDOW_SECS + 1) * 1000;
+        assert!(receiver
+            .decode(&sender.encode_with(frame(), &stale).unwrap())
+            .is_err());
+
+        // The key reference is authenticated
+        let reference = KeyReference::new(KeyId::new("queue-2026"), 1);
+        let mut data = sender.encode_with(frame(), &reference).unwrap();
+        let at = data
+            .windows(MESSAGE_ID_SIZE)
+            .position(|w| w == reference.message_id)
+            .unwrap();
+        data[at] ^= 0x01;
+        assert!(receiver.decode(&data).is_err());
+
+        // Session frames are not stateless frames
+        let mut ctx = SecurityContext::new(KeyMaterial::new(vec![0x5a; 32]));
+        let data = frame().encode_secure(SecurityMode::Aead, &mut ctx).unwrap();
+        assert!(receiver.decode(&data).is_err());
+    }
+}
@@ -0,0 +1,129 @@
+//! Handshake transcript binding for downgrade protection.
+//!
+//! HELLO and ACCEPT travel in the clear, so an on-path attacker can edit
+//! the capabilities they carry — for example strip `aead` from the
+//! offered security modes so both agents settle on `SecurityMode::None`.
+//! Each agent then negotiates from a different view of the handshake.
+//!
+//! A [`Transcript`] hashes both views of the negotiation (versions,
+//! algorithms, security modes, key-exchange suites, extensions). Session
+//! keys are derived with the hash in the HKDF info, and the first DATA
+//! frame in each direction carries a MAC over it:
+//!
+//! ```text
+//! transcript  = SHA-256("m2m-transcript-v1" ‖ len ‖ HELLO caps ‖ len ‖ ACCEPT caps)
+//! session_key = HKDF(shared_secret, context ‖ 0x00 ‖ transcript)
+//! mac         = HMAC-SHA256(session_key, "m2m-transcript-mac" ‖ transcript)
+//! ```
+//!
+//! Capabilities are hashed as RFC 8785 canonical JSON, so both agents
+//! compute the same bytes regardless of field or map order. A tampered
+//! handshake gives the agents different transcripts, different keys, and
+//! a MAC that fails to verify.
+
+use sha2::{Digest, Sha256};
+
+use super::exchange::KeyExchangeError;
+use super::hmac_auth::{HmacAuth, HmacError};
+use super::keyring::{KeyMaterial, KeyringError};
+use super::HMAC_TAG_SIZE;
+use crate::codec::canonical::canonicalize_value;
+use crate::protocol::Capabilities;
+
+/// Domain separator for the transcript hash
+const TRANSCRIPT_LABEL: &[u8] = b"m2m-transcript-v1";
+
+/// Domain separator for the transcript MAC
+const MAC_LABEL: &[u8] = b"m2m-transcript-mac";
+
+/// Hash of a HELLO/ACCEPT negotiation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Transcript([u8; 32]);
+
+impl Transcript {
+    /// Hash the initiator's HELLO and the responder's ACCEPT capabilities
+    pub fn new(hello: &Capabilities, accept: &Capabilities) -> Self {
+        let mut hasher = Sha256::new();
+        hasher.update(TRANSCRIPT_LABEL);
+        for caps in [hello, accept] {
+            let canonical = serde_json::to_value(caps)
+                .map(|value| canonicalize_value(&value))
+                .unwrap_or_default();
+            hasher.update((canonical.len() as u64).to_be_bytes());
+            hasher.update(canonical.as_bytes());
+        }
+        Self(hasher.finalize().into())
+    }
+
+    /// Get the hash bytes
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+
+    /// Derive a session key bound to this transcript
+    pub fn derive_key(
+        &self,
+        secret: &KeyMaterial,
+        context: &str,
+    ) -> Result<KeyMaterial, KeyringError> {
+        let mut info = Vec::with_capacity(context.len() + 1 + self.0.len());
+        info.extend_from_slice(context.as_bytes());
+        info.push(0);
+        info.extend_from_slice(&self.0);
+        secret.derive(&info, 32)
+    }
+
+    /// Compute the MAC sent in the first DATA frame
+    pub fn mac(&self, key: &KeyMaterial) -> Result<[u8; HMAC_TAG_SIZE], HmacError> {
+        Ok(HmacAuth::new(key.clone())?.compute_tag(&self.mac_input()))
+    }
+
+    /// Verify a peer's transcript MAC
+    ///
+    /// Fails with [`KeyExchangeError::TranscriptMismatch`] if the peer saw
+    /// a different handshake or holds a different key.
+    pub fn verify_mac(&self, key: &KeyMaterial, tag: &[u8]) -> Result<(), KeyExchangeError> {
+        HmacAuth::new(key.clone())
+            .and_then(|auth| auth.verify_tag(&self.mac_input(), tag))
+            .map_err(|_| KeyExchangeError::TranscriptMismatch)
+    }
+
+    fn mac_input(&self) -> Vec<u8> {
+        [MAC_LABEL, &self.0].concat()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::m2m::SecurityMode;
+    use crate::protocol::SecurityCaps;
+
+    #[test]
+    fn test_tampered_handshake_detected() {
+        let hello = Capabilities::default().with_agent_id("alice");
+        let accept = Capabilities::default().with_agent_id("bob");
+        let transcript = Transcript::new(&hello, &accept);
+        assert_eq!(transcript, Transcript::new(&hello, &accept));
+        assert_ne!(transcript, Transcript::new(&accept, &hello));
+
+        // The responder saw a HELLO with AEAD stripped
+        let stripped = hello
+            .clone()
+            .with_security(SecurityCaps::default().with_security_modes(vec![SecurityMode::None]));
+        let tampered = Transcript::new(&stripped, &accept);
+        assert_ne!(transcript, tampered);
+
+        let secret = KeyMaterial::new(vec![7; 32]);
+        let key = transcript.derive_key(&secret, "m2m-session-v1").unwrap();
+        let other_key = tampered.derive_key(&secret, "m2m-session-v1").unwrap();
+        assert_ne!(key.as_bytes(), other_key.as_bytes());
+
+        let mac = transcript.mac(&key).unwrap();
+        assert!(transcript.verify_mac(&key, &mac).is_ok());
+        assert!(matches!(
+            tampered.verify_mac(&other_key, &mac),
+            Err(KeyExchangeError::TranscriptMismatch)
+        ));
+    }
+}
@@ -0,0 +1,143 @@
+//! Header extensions (experimental layout v2).
+//!
+//! A frame with `CommonFlags::HAS_EXTENSIONS` set carries an extension block
+//! after its routing or response header, still inside `header_len`:
+//!
+//! ```text
+//! [layout: 1]                      M2M_EXPERIMENTAL_VERSION (2)
+//! [kind: 1][len: varint][value]    repeated to the end of the header
+//! ```
+//!
+//! v1 decoders skip the variable header by `header_len`, so they read these
+//! frames unchanged and never see the block. The prefix stays `#M2M|1|` for
+//! the same reason: a new prefix would hard-break every deployed agent.
+//!
+//! # Unknown Fields
+//!
+//! | Field | Handling |
+//! |-------|----------|
+//! | Reserved flag bits | Ignored and preserved on re-encode |
+//! | Extension without `CRITICAL` bit | Ignored and preserved on re-encode |
+//! | Extension with `CRITICAL` bit | Rejected by [`M2MFrame::require_extensions`] |
+//! | Block with a newer layout byte | Skipped as a whole, like a v1 decoder |
+//!
+//! [`M2MFrame::require_extensions`]: super::M2MFrame::require_extensions
+
+use bytes::BufMut;
+
+use super::varint::{read_varint_slice, varint_size, write_varint_vec};
+use super::M2M_EXPERIMENTAL_VERSION;
+use crate::error::{M2MError, Result};
+
+/// One type-length-value entry in the extension block
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeaderExtension {
+    /// Extension type; the high bit marks it critical
+    pub kind: u8,
+    /// Opaque value
+    pub value: Vec<u8>,
+}
+
+impl HeaderExtension {
+    /// Kind bit for extensions a receiver must understand to decode the frame
+    pub const CRITICAL: u8 = 0x80;
+
+    /// Create an extension
+    pub fn new(kind: u8, value: impl Into<Vec<u8>>) -> Self {
+        Self {
+            kind,
+            value: value.into(),
+        }
+    }
+
+    /// Whether receivers that do not understand this kind must reject the frame
+    pub fn is_critical(&self) -> bool {
+        self.kind & Self::CRITICAL != 0
+    }
+
+    fn encoded_size(&self) -> usize {
+        1 + varint_size(self.value.len() as u64) + self.value.len()
+    }
+}
+
+/// Bytes the extension block adds to the variable header
+pub(super) fn block_size(extensions: &[HeaderExtension]) -> usize {
+    if extensions.is_empty() {
+        0
+    } else {
+        1 + extensions
+            .iter()
+            .map(HeaderExtension::encoded_size)
+            .sum::<usize>()
+    }
+}
+
+/// Append the extension block (nothing when there are no extensions)
+pub(super) fn write_block(extensions: &[HeaderExtension], buf: &mut impl BufMut) {
+    if extensions.is_empty() {
+        return;
+    }
+    let mut block = Vec::with_capacity(block_size(extensions));
+    block.push(M2M_EXPERIMENTAL_VERSION);
+    for ext in extensions {
+        block.push(ext.kind);
+        write_varint_vec(&mut block, ext.value.len() as u64);
+        block.extend_from_slice(&ext.value);
+    }
+    buf.put_slice(&block);
+}
+
+/// Parse the extension block occupying all of `data`
+///
+/// A block written by a newer layout is skipped and yields no extensions.
+pub(super) fn read_block(data: &[u8]) -> Result<Vec<HeaderExtension>> {
+    match data.first() {
+        Some(&M2M_EXPERIMENTAL_VERSION) => {},
+        _ => return Ok(Vec::new()),
+    }
+
+    let mut extensions = Vec::new();
+    let mut pos = 1;
+    while pos < data.len() {
+        let kind = data[pos];
+        let (len, consumed) = read_varint_slice(&data[pos + 1..])?;
+        pos += 1 + consumed;
+        let end = usize::try_from(len)
+            .ok()
+            .and_then(|len| pos.checked_add(len))
+            .filter(|&end| end <= data.len())
+            .ok_or_else(|| {
+                M2MError::Decompression(format!(
+                    "Header extension 0x{kind:02x} overruns header_len"
+                ))
+            })?;
+        extensions.push(HeaderExtension::new(kind, &data[pos..end]));
+        pos = end;
+    }
+    Ok(extensions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_block_roundtrip() {
+        let extensions = vec![
+            HeaderExtension::new(0x01, b"trace".as_slice()),
+            HeaderExtension::new(0x82, vec![0u8; 200]),
+        ];
+        let mut buf = Vec::new();
+        write_block(&extensions, &mut buf);
+        assert_eq!(buf.len(), block_size(&extensions));
+        assert_eq!(read_block(&buf).unwrap(), extensions);
+        assert!(!extensions[0].is_critical());
+        assert!(extensions[1].is_critical());
+
+        // Newer layouts are skipped, truncated values rejected
+        buf[0] = M2M_EXPERIMENTAL_VERSION + 1;
+        assert!(read_block(&buf).unwrap().is_empty());
+        buf[0] = M2M_EXPERIMENTAL_VERSION;
+        assert!(read_block(&buf[..buf.len() - 1]).is_err());
+    }
+}
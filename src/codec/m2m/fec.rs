@@ -0,0 +1,363 @@
+//! Forward error correction for lossy links.
+//!
+//! Over LoRa bridges or unreliable UDP a single flipped byte makes a wire
+//! message undecodable. An FEC frame splits the message into data shards,
+//! adds Reed-Solomon parity shards and a CRC32 per shard; the receiver
+//! treats shards failing their CRC as erased and rebuilds them from the
+//! others. Up to `parity_shards` corrupted shards per frame are repaired.
+//!
+//! # Wire Format
+//!
+//! ```text
+//! #M2M|1|base64(<fixed_header:20><shard>*<layout:12>)
+//!
+//! fixed_header.flags:    FEC (bit 31)
+//! fixed_header.reserved: <layout:12>
+//! shard:                 <crc32:4><bytes:shard_len>
+//! layout:                <data_shards:1><parity_shards:1><shard_len:2>
+//!                        <message_len:4><crc32:4>  (little-endian)
+//! ```
+//!
+//! The layout is repeated after the last shard, so a corrupted fixed header
+//! does not lose the frame. The parity ratio is agreed in the handshake
+//! (see [`Capabilities::with_fec`](crate::protocol::Capabilities::with_fec)).
+
+use base64::alphabet;
+use base64::engine::{DecodePaddingMode, Engine, GeneralPurpose, GeneralPurposeConfig};
+use reed_solomon_erasure::galois_8::ReedSolomon;
+
+use super::flags::{CommonFlags, Flags};
+use super::header::{FixedHeader, Schema, SecurityMode, FIXED_HEADER_SIZE};
+use super::M2M_PREFIX;
+use crate::codec::b64;
+use crate::error::{M2MError, Result};
+
+/// Most data shards per frame
+pub const MAX_DATA_SHARDS: usize = 64;
+
+/// Smallest shard, below which CRC overhead outweighs finer repair
+pub const MIN_SHARD_LEN: usize = 32;
+
+/// Parity shards per 100 data shards when unspecified
+pub const DEFAULT_PARITY_PERCENT: u8 = 25;
+
+/// Size of the shard layout descriptor
+const LAYOUT_SIZE: usize = 12;
+
+/// Size of a shard checksum
+const CRC_SIZE: usize = 4;
+
+/// Base64 decoder that tolerates corrupted padding
+const LENIENT: GeneralPurpose = GeneralPurpose::new(
+    &alphabet::STANDARD,
+    GeneralPurposeConfig::new()
+        .with_decode_padding_mode(DecodePaddingMode::Indifferent)
+        .with_decode_allow_trailing_bits(true),
+);
+
+/// Message rebuilt from an FEC frame
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Recovered {
+    /// Original message bytes
+    pub data: Vec<u8>,
+    /// Shards that failed their CRC and were rebuilt
+    pub repaired: usize,
+}
+
+/// Shard geometry of a frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Layout {
+    data_shards: usize,
+    parity_shards: usize,
+    shard_len: usize,
+    message_len: usize,
+}
+
+impl Layout {
+    /// Geometry for `message_len` bytes at `parity_percent`
+    fn new(message_len: usize, parity_percent: u8) -> Result<Self> {
+        if parity_percent == 0 {
+            return Err(M2MError::Compression(
+                "FEC parity must be at least 1%".to_string(),
+            ));
+        }
+        let data_shards = message_len
+            .div_ceil(MIN_SHARD_LEN)
+            .clamp(1, MAX_DATA_SHARDS);
+        let shard_len = message_len.div_ceil(data_shards).max(1);
+        if shard_len > usize::from(u16::MAX) || u32::try_from(message_len).is_err() {
+            return Err(M2MError::Compression(format!(
+                "{message_len} bytes are too large for one FEC frame; fragment first"
+            )));
+        }
+        Ok(Self {
+            data_shards,
+            parity_shards: (data_shards * usize::from(parity_percent)).div_ceil(100),
+            shard_len,
+            message_len,
+        })
+    }
+
+    fn shards(&self) -> usize {
+        self.data_shards + self.parity_shards
+    }
+
+    /// Bytes of the binary frame
+    fn frame_len(&self) -> usize {
+        FIXED_HEADER_SIZE + self.shards() * (CRC_SIZE + self.shard_len) + LAYOUT_SIZE
+    }
+
+    fn to_bytes(self) -> [u8; LAYOUT_SIZE] {
+        let mut bytes = [0u8; LAYOUT_SIZE];
+        bytes[0] = self.data_shards as u8;
+        bytes[1] = self.parity_shards as u8;
+        bytes[2..4].copy_from_slice(&(self.shard_len as u16).to_le_bytes());
+        bytes[4..8].copy_from_slice(&(self.message_len as u32).to_le_bytes());
+        let crc = crc32fast::hash(&bytes[..8]);
+        bytes[8..].copy_from_slice(&crc.to_le_bytes());
+        bytes
+    }
+
+    /// Parse a layout, `None` if its checksum or geometry is wrong
+    fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let bytes: &[u8; LAYOUT_SIZE] = bytes.try_into().ok()?;
+        let crc = u32::from_le_bytes([bytes[8], bytes[9], bytes[10], bytes[11]]);
+        if crc != crc32fast::hash(&bytes[..8]) {
+            return None;
+        }
+        let layout = Self {
+            data_shards: usize::from(bytes[0]),
+            parity_shards: usize::from(bytes[1]),
+            shard_len: usize::from(u16::from_le_bytes([bytes[2], bytes[3]])),
+            message_len: u32::from_le_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]) as usize,
+        };
+        let valid = layout.data_shards > 0
+            && layout.parity_shards > 0
+            && layout.shard_len > 0
+            && layout.message_len <= layout.data_shards * layout.shard_len;
+        valid.then_some(layout)
+    }
+}
+
+/// Protect a message with `parity_percent` parity shards per 100 data shards
+pub fn protect(message: &[u8], parity_percent: u8) -> Result<Vec<u8>> {
+    let layout = Layout::new(message.len(), parity_percent)?;
+    let codec = reed_solomon(&layout)?;
+
+    let mut shards: Vec<Vec<u8>> = (0..layout.shards())
+        .map(|i| {
+            let start = (i * layout.shard_len).min(message.len());
+            let end = ((i + 1) * layout.shard_len).min(message.len());
+            let mut shard = message[start..end].to_vec();
+            shard.resize(layout.shard_len, 0);
+            shard
+        })
+        .collect();
+    codec
+        .encode(&mut shards)
+        .map_err(|e| M2MError::Compression(format!("FEC encoding failed: {e:?}")))?;
+
+    let mut common = CommonFlags::new();
+    common.set(CommonFlags::FEC);
+    let flags = Flags {
+        common,
+        ..Flags::default()
+    };
+    let mut header = FixedHeader::new(Schema::Unknown, SecurityMode::None, flags);
+    header.reserved = layout.to_bytes();
+
+    let mut frame = Vec::with_capacity(layout.frame_len());
+    frame.extend_from_slice(&header.to_bytes());
+    for shard in &shards {
+        frame.extend_from_slice(&crc32fast::hash(shard).to_le_bytes());
+        frame.extend_from_slice(shard);
+    }
+    frame.extend_from_slice(&layout.to_bytes());
+    Ok(frame)
+}
+
+/// Rebuild the message from a possibly corrupted FEC frame
+///
+/// Fails if both copies of the layout are corrupted, or more shards than
+/// there are parity shards.
+pub fn recover(frame: &[u8]) -> Result<Recovered> {
+    let header_layout = frame
+        .get(FIXED_HEADER_SIZE - LAYOUT_SIZE..FIXED_HEADER_SIZE)
+        .and_then(Layout::from_bytes);
+    let layout = header_layout
+        .or_else(|| {
+            let trailer = frame.len().checked_sub(LAYOUT_SIZE)?;
+            Layout::from_bytes(&frame[trailer..])
+        })
+        .ok_or_else(|| M2MError::Decompression("FEC layout corrupted".to_string()))?;
+    if frame.len() < layout.frame_len() {
+        return Err(M2MError::Decompression(format!(
+            "FEC frame truncated: {} of {} bytes",
+            frame.len(),
+            layout.frame_len()
+        )));
+    }
+
+    let body = &frame[FIXED_HEADER_SIZE..];
+    let mut shards: Vec<Option<Vec<u8>>> = body
+        .chunks_exact(CRC_SIZE + layout.shard_len)
+        .take(layout.shards())
+        .map(|chunk| {
+            let (crc, shard) = chunk.split_at(CRC_SIZE);
+            let crc = u32::from_le_bytes([crc[0], crc[1], crc[2], crc[3]]);
+            (crc == crc32fast::hash(shard)).then(|| shard.to_vec())
+        })
+        .collect();
+
+    let repaired = shards.iter().filter(|shard| shard.is_none()).count();
+    if repaired > layout.parity_shards {
+        return Err(M2MError::Decompression(format!(
+            "{repaired} of {} FEC shards corrupted, at most {} can be repaired",
+            layout.shards(),
+            layout.parity_shards
+        )));
+    }
+    if repaired > 0 {
+        reed_solomon(&layout)
+            .map_err(|e| M2MError::Decompression(e.to_string()))?
+            .reconstruct_data(&mut shards)
+            .map_err(|e| M2MError::Decompression(format!("FEC repair failed: {e:?}")))?;
+    }
+
+    let mut data: Vec<u8> = shards
+        .into_iter()
+        .take(layout.data_shards)
+        .flat_map(Option::unwrap_or_default)
+        .collect();
+    data.truncate(layout.message_len);
+    Ok(Recovered { data, repaired })
+}
+
+/// Protect a text wire message as a text FEC frame
+pub fn protect_string(wire: &str, parity_percent: u8) -> Result<String> {
+    Ok(format!(
+        "{}{}",
+        M2M_PREFIX,
+        b64::encode(&protect(wire.as_bytes(), parity_percent)?)
+    ))
+}
+
+/// Rebuild a text wire message from a text FEC frame
+///
+/// Characters corrupted outside the base64 alphabet only damage the shard
+/// they fall in, like any other corruption.
+pub fn recover_string(wire: &str) -> Result<Recovered> {
+    let body = wire
+        .get(M2M_PREFIX.len()..)
+        .ok_or_else(|| M2MError::Decompression("FEC frame truncated".to_string()))?
+        .trim_end_matches('=');
+    let mut cleaned: Vec<u8> = body
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'+' | b'/' => b,
+            _ => b'A',
+        })
+        .collect();
+    if cleaned.len() % 4 == 1 {
+        cleaned.pop();
+    }
+    recover(&LENIENT.decode(cleaned)?)
+}
+
+/// Check if a text wire frame is an FEC frame, without decoding it
+pub fn is_protected(wire: &str) -> bool {
+    // 12 base64 chars cover the flags field of the fixed header
+    let Some(head) = wire.strip_prefix(M2M_PREFIX).and_then(|w| w.get(..12)) else {
+        return false;
+    };
+    b64::decode(head)
+        .ok()
+        .and_then(|bytes| Some(Flags::from_bytes(bytes.get(4..8)?.try_into().ok()?)))
+        .is_some_and(|flags| flags.common.is_fec())
+}
+
+/// Length of the text FEC frame protecting `message_len` bytes
+pub fn protected_len(message_len: usize, parity_percent: u8) -> Result<usize> {
+    let frame_len = Layout::new(message_len, parity_percent)?.frame_len();
+    Ok(M2M_PREFIX.len() + frame_len.div_ceil(3) * 4)
+}
+
+/// Longest message whose text FEC frame fits in `frame_size`
+pub fn max_message_len(frame_size: usize, parity_percent: u8) -> usize {
+    let fits = |len| protected_len(len, parity_percent).is_ok_and(|n| n <= frame_size);
+    let (mut low, mut high) = (0, frame_size);
+    while low < high {
+        let mid = (low + high).div_ceil(2);
+        if fits(mid) {
+            low = mid;
+        } else {
+            high = mid - 1;
+        }
+    }
+    low
+}
+
+fn reed_solomon(layout: &Layout) -> Result<ReedSolomon> {
+    ReedSolomon::new(layout.data_shards, layout.parity_shards)
+        .map_err(|e| M2MError::Compression(format!("Invalid FEC layout: {e:?}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MESSAGE: &str = r#"{"model":"gpt-4o","messages":[{"role":"user","content":"Sensor 7 reports 21.4C, humidity 48%, battery 3.61V"}]}"#;
+
+    #[test]
+    fn test_repairs_corrupted_shards() {
+        let frame = protect(MESSAGE.as_bytes(), 50).unwrap();
+        let layout = Layout::new(MESSAGE.len(), 50).unwrap();
+        assert_eq!(frame.len(), layout.frame_len());
+        assert_eq!(layout.parity_shards, 2);
+
+        let clean = recover(&frame).unwrap();
+        assert_eq!(clean.data, MESSAGE.as_bytes());
+        assert_eq!(clean.repaired, 0);
+
+        // Flip a byte in two different shards, and wipe the fixed header
+        let mut damaged = frame.clone();
+        damaged[FIXED_HEADER_SIZE + 10] ^= 0xFF;
+        damaged[FIXED_HEADER_SIZE + CRC_SIZE + layout.shard_len + 5] ^= 0x01;
+        damaged[..FIXED_HEADER_SIZE].fill(0);
+        let repaired = recover(&damaged).unwrap();
+        assert_eq!(repaired.data, MESSAGE.as_bytes());
+        assert_eq!(repaired.repaired, 2);
+
+        // A third corrupted shard is beyond the parity
+        damaged[FIXED_HEADER_SIZE + 2 * (CRC_SIZE + layout.shard_len)] ^= 0x80;
+        assert!(recover(&damaged).is_err());
+        assert!(recover(&frame[..frame.len() / 2]).is_err());
+    }
+
+    #[test]
+    fn test_text_frames() {
+        let wire = protect_string(MESSAGE, DEFAULT_PARITY_PERCENT).unwrap();
+        assert!(is_protected(&wire));
+        assert!(!is_protected(MESSAGE));
+        assert_eq!(
+            wire.len(),
+            protected_len(MESSAGE.len(), DEFAULT_PARITY_PERCENT).unwrap()
+        );
+
+        // A character outside the alphabet only costs one shard
+        let mut damaged = wire.into_bytes();
+        let at = (40..damaged.len()).find(|&i| damaged[i] != b'A').unwrap();
+        damaged[at] = b'~';
+        let recovered = recover_string(std::str::from_utf8(&damaged).unwrap()).unwrap();
+        assert_eq!(recovered.data, MESSAGE.as_bytes());
+        assert_eq!(recovered.repaired, 1);
+
+        let budget = max_message_len(512, DEFAULT_PARITY_PERCENT);
+        assert!(protected_len(budget, DEFAULT_PARITY_PERCENT).unwrap() <= 512);
+        assert!(protected_len(budget + 1, DEFAULT_PARITY_PERCENT).unwrap() > 512);
+
+        assert!(protect(b"", 10).unwrap().len() > FIXED_HEADER_SIZE);
+        assert_eq!(recover(&protect(b"", 10).unwrap()).unwrap().data, b"");
+        assert!(protect(b"x", 0).is_err());
+    }
+}
@@ -0,0 +1,393 @@
+//! Fragmentation of large wire messages.
+//!
+//! A wire message longer than the negotiated maximum frame size (see
+//! [`MaxFrameSize`](crate::protocol::MaxFrameSize)) is split into numbered
+//! fragments, each carried as its own M2M frame. Any compressed wire format
+//! can be fragmented; the receiver concatenates the chunks and decodes the
+//! result as usual.
+//!
+//! # Wire Format
+//!
+//! ```text
+//! #M2M|1|base64(<fixed_header:20><chunk>)
+//!
+//! fixed_header.flags:    FRAGMENT (bit 29)
+//! fixed_header.reserved: <message_id:4><index:4><total:4>  (little-endian)
+//! ```
+//!
+//! `message_id` groups the fragments of one message, `index` runs from 0 to
+//! `total - 1`. Fragments may arrive in any order.
+
+use std::collections::{BTreeMap, HashMap};
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+
+use super::flags::{CommonFlags, Flags};
+use super::header::{FixedHeader, Schema, SecurityMode, FIXED_HEADER_SIZE};
+use super::M2M_PREFIX;
+use crate::error::{M2MError, Result};
+
+/// Smallest frame size that leaves room for a useful chunk
+pub const MIN_FRAME_SIZE: usize = 64;
+
+/// Default limit on bytes buffered for incomplete messages (64 MiB)
+pub const DEFAULT_REASSEMBLY_LIMIT: usize = 64 * 1024 * 1024;
+
+/// Default limit on incomplete messages buffered at once
+pub const DEFAULT_MAX_PARTIAL_MESSAGES: usize = 64;
+
+/// Default limit on fragments per message
+pub const DEFAULT_MAX_FRAGMENTS: u32 = 16 * 1024;
+
+/// Bookkeeping bytes charged per buffered fragment
+///
+/// Counted against the reassembly limit along with the chunk itself, so a
+/// stream of tiny fragments cannot grow the buffer without bound.
+pub const FRAGMENT_OVERHEAD: usize = 64;
+
+/// One fragment of a wire message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fragment {
+    /// Message the fragment belongs to
+    pub message_id: u32,
+    /// Position of this fragment (0-based)
+    pub index: u32,
+    /// Number of fragments in the message
+    pub total: u32,
+    /// Bytes of the original wire message
+    pub chunk: Vec<u8>,
+}
+
+impl Fragment {
+    /// Encode as a text wire frame
+    pub fn encode_string(&self) -> String {
+        let mut common = CommonFlags::new();
+        common.set(CommonFlags::FRAGMENT);
+        let flags = Flags {
+            common,
+            ..Flags::default()
+        };
+        let mut header = FixedHeader::new(Schema::Unknown, SecurityMode::None, flags);
+        header.reserved[0..4].copy_from_slice(&self.message_id.to_le_bytes());
+        header.reserved[4..8].copy_from_slice(&self.index.to_le_bytes());
+        header.reserved[8..12].copy_from_slice(&self.total.to_le_bytes());
+
+        let mut binary = Vec::with_capacity(FIXED_HEADER_SIZE + self.chunk.len());
+        binary.extend_from_slice(&header.to_bytes());
+        binary.extend_from_slice(&self.chunk);
+
+        format!("{}{}", M2M_PREFIX, BASE64.encode(binary))
+    }
+
+    /// Decode a text wire frame
+    pub fn decode_string(wire: &str) -> Result<Self> {
+        let encoded = wire
+            .strip_prefix(M2M_PREFIX)
+            .ok_or_else(|| M2MError::Decompression("Invalid M2M prefix".to_string()))?;
+        let binary = BASE64.decode(encoded)?;
+
+        let header = FixedHeader::from_bytes(&binary)?;
+        if !header.flags.common.is_fragment() {
+            return Err(M2MError::Decompression("Not a fragment frame".to_string()));
+        }
+
+        let field = |at: usize| {
+            u32::from_le_bytes([
+                header.reserved[at],
+                header.reserved[at + 1],
+                header.reserved[at + 2],
+                header.reserved[at + 3],
+            ])
+        };
+        let (message_id, index, total) = (field(0), field(4), field(8));
+        if index >= total {
+            return Err(M2MError::Decompression(format!(
+                "Fragment index {index} out of range for {total} fragments"
+            )));
+        }
+
+        Ok(Self {
+            message_id,
+            index,
+            total,
+            chunk: binary[FIXED_HEADER_SIZE..].to_vec(),
+        })
+    }
+}
+
+/// Check if a text wire frame is a fragment, without decoding it
+pub fn is_fragment(wire: &str) -> bool {
+    // 12 base64 chars cover the first 9 bytes of the fixed header,
+    // which include the flags field
+    let Some(head) = wire.strip_prefix(M2M_PREFIX).and_then(|w| w.get(..12)) else {
+        return false;
+    };
+    BASE64
+        .decode(head)
+        .ok()
+        .and_then(|bytes| Some(Flags::from_bytes(bytes.get(4..8)?.try_into().ok()?)))
+        .is_some_and(|flags| flags.common.is_fragment())
+}
+
+/// Split a wire message into fragments no longer than `max_frame_size`
+///
+/// Returns the message unchanged (a single element) if it already fits.
+pub fn fragment(wire: &str, message_id: u32, max_frame_size: usize) -> Result<Vec<String>> {
+    if wire.len() <= max_frame_size {
+        return Ok(vec![wire.to_string()]);
+    }
+    if max_frame_size < MIN_FRAME_SIZE {
+        return Err(M2MError::Compression(format!(
+            "Max frame size {max_frame_size} is below the minimum of {MIN_FRAME_SIZE}"
+        )));
+    }
+
+    // Each 3 binary bytes become 4 base64 chars
+    let chunk_size = (max_frame_size - M2M_PREFIX.len()) / 4 * 3 - FIXED_HEADER_SIZE;
+    let chunks: Vec<&[u8]> = wire.as_bytes().chunks(chunk_size).collect();
+    let total = u32::try_from(chunks.len())
+        .map_err(|_| M2MError::Compression("Too many fragments".to_string()))?;
+
+    Ok(chunks
+        .into_iter()
+        .zip(0..)
+        .map(|(chunk, index)| {
+            Fragment {
+                message_id,
+                index,
+                total,
+                chunk: chunk.to_vec(),
+            }
+            .encode_string()
+        })
+        .collect())
+}
+
+/// Fragments received so far for one message
+#[derive(Debug, Clone)]
+struct Partial {
+    total: u32,
+    chunks: BTreeMap<u32, Vec<u8>>,
+}
+
+/// Collects fragments until a message is complete
+///
+/// Buffered bytes across all incomplete messages are bounded, with each
+/// fragment charged [`FRAGMENT_OVERHEAD`] on top of its chunk; a fragment
+/// that would exceed the limit is rejected and its message discarded. The
+/// number of incomplete messages and of fragments per message are bounded
+/// as well.
+#[derive(Debug, Clone)]
+pub struct Reassembler {
+    /// Incomplete messages by ID
+    partial: HashMap<u32, Partial>,
+    /// Bytes buffered across all incomplete messages
+    buffered: usize,
+    /// Maximum bytes to buffer
+    limit: usize,
+    /// Maximum incomplete messages
+    max_partial: usize,
+    /// Maximum fragments per message
+    max_fragments: u32,
+}
+
+impl Default for Reassembler {
+    fn default() -> Self {
+        Self::new(DEFAULT_REASSEMBLY_LIMIT)
+    }
+}
+
+impl Reassembler {
+    /// Create a reassembler buffering at most `limit` bytes
+    pub fn new(limit: usize) -> Self {
+        Self {
+            partial: HashMap::new(),
+            buffered: 0,
+            limit,
+            max_partial: DEFAULT_MAX_PARTIAL_MESSAGES,
+            max_fragments: DEFAULT_MAX_FRAGMENTS,
+        }
+    }
+
+    /// Limit the number of incomplete messages buffered at once
+    pub fn with_max_partial(mut self, max_partial: usize) -> Self {
+        self.max_partial = max_partial;
+        self
+    }
+
+    /// Limit the number of fragments a message may be split into
+    pub fn with_max_fragments(mut self, max_fragments: u32) -> Self {
+        self.max_fragments = max_fragments;
+        self
+    }
+
+    /// Bytes buffered for incomplete messages
+    pub fn buffered(&self) -> usize {
+        self.buffered
+    }
+
+    /// Add a fragment, returning the wire message once all fragments arrived
+    pub fn push(&mut self, fragment: Fragment) -> Result<Option<String>> {
+        let Fragment {
+            message_id,
+            index,
+            total,
+            chunk,
+        } = fragment;
+
+        if total > self.max_fragments {
+            self.discard(message_id);
+            return Err(M2MError::Protocol(format!(
+                "Message {message_id} has {total} fragments, more than the limit of {}",
+                self.max_fragments
+            )));
+        }
+        if !self.partial.contains_key(&message_id) && self.partial.len() >= self.max_partial {
+            return Err(M2MError::Protocol(format!(
+                "Too many incomplete fragmented messages (limit {})",
+                self.max_partial
+            )));
+        }
+
+        let partial = self.partial.entry(message_id).or_insert_with(|| Partial {
+            total,
+            chunks: BTreeMap::new(),
+        });
+        if partial.chunks.contains_key(&index) {
+            return Err(M2MError::Protocol(format!(
+                "Duplicate fragment {index} of message {message_id}"
+            )));
+        }
+        if partial.total != total {
+            self.discard(message_id);
+            return Err(M2MError::Protocol(format!(
+                "Fragment count of message {message_id} changed mid-message"
+            )));
+        }
+        let cost = chunk.len() + FRAGMENT_OVERHEAD;
+        if self.buffered + cost > self.limit {
+            self.discard(message_id);
+            return Err(M2MError::Protocol(format!(
+                "Fragmented message {message_id} exceeds the reassembly limit of {} bytes",
+                self.limit
+            )));
+        }
+
+        let Some(partial) = self.partial.get_mut(&message_id) else {
+            return Ok(None);
+        };
+        self.buffered += cost;
+        partial.chunks.insert(index, chunk);
+        if partial.chunks.len() < total as usize {
+            return Ok(None);
+        }
+
+        let Some(partial) = self.partial.remove(&message_id) else {
+            return Ok(None);
+        };
+        let bytes: Vec<u8> = partial.chunks.into_values().flatten().collect();
+        self.buffered -= bytes.len() + total as usize * FRAGMENT_OVERHEAD;
+
+        String::from_utf8(bytes)
+            .map(Some)
+            .map_err(|e| M2MError::Decompression(format!("Reassembled message is not UTF-8: {e}")))
+    }
+
+    /// Number of fragments still missing for a message
+    pub fn missing(&self, message_id: u32) -> Option<u32> {
+        self.partial
+            .get(&message_id)
+            .map(|p| p.total - p.chunks.len() as u32)
+    }
+
+    /// Drop an incomplete message
+    fn discard(&mut self, message_id: u32) {
+        if let Some(partial) = self.partial.remove(&message_id) {
+            self.buffered -= partial
+                .chunks
+                .values()
+                .map(|chunk| chunk.len() + FRAGMENT_OVERHEAD)
+                .sum::<usize>();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fragment_roundtrip_out_of_order() {
+        let wire = format!("#M2M[v3.0]|DATA:{}", "x".repeat(1000));
+        let mut fragments = fragment(&wire, 7, 128).unwrap();
+        assert!(fragments.len() > 1);
+        assert!(fragments.iter().all(|f| f.len() <= 128 && is_fragment(f)));
+        assert!(!is_fragment(&wire));
+
+        fragments.reverse();
+        let mut reassembler = Reassembler::default();
+        let mut complete = None;
+        for wire in &fragments {
+            complete = reassembler
+                .push(Fragment::decode_string(wire).unwrap())
+                .unwrap();
+        }
+        assert_eq!(complete.as_deref(), Some(wire.as_str()));
+        assert_eq!(reassembler.buffered(), 0);
+    }
+
+    #[test]
+    fn test_small_message_not_fragmented() {
+        assert_eq!(fragment("short", 1, 128).unwrap(), vec!["short"]);
+        assert!(fragment(&"x".repeat(100), 1, 16).is_err());
+    }
+
+    #[test]
+    fn test_reassembly_limits() {
+        let fragments = fragment(&"y".repeat(1000), 1, 128).unwrap();
+        let first = Fragment::decode_string(&fragments[0]).unwrap();
+
+        let mut reassembler = Reassembler::new(100 + FRAGMENT_OVERHEAD);
+        assert!(reassembler.push(first.clone()).unwrap().is_none());
+        assert!(reassembler.push(first.clone()).is_err()); // Duplicate
+        let second = Fragment::decode_string(&fragments[1]).unwrap();
+        assert!(reassembler.push(second).is_err()); // Over the limit
+        assert_eq!(reassembler.buffered(), 0);
+        assert_eq!(reassembler.missing(1), None);
+    }
+
+    #[test]
+    fn test_reassembly_bounds_messages_and_fragments() {
+        let first = |message_id, total| Fragment {
+            message_id,
+            index: 0,
+            total,
+            chunk: vec![b'z'],
+        };
+
+        // Incomplete messages are capped; completing one frees a slot
+        let mut reassembler = Reassembler::default().with_max_partial(2);
+        assert!(reassembler.push(first(1, 2)).unwrap().is_none());
+        assert!(reassembler.push(first(2, 2)).unwrap().is_none());
+        assert!(reassembler.push(first(3, 2)).is_err());
+        assert_eq!(reassembler.missing(3), None);
+        let mut last = first(1, 2);
+        last.index = 1;
+        assert_eq!(reassembler.push(last).unwrap().as_deref(), Some("zz"));
+        assert!(reassembler.push(first(3, 2)).unwrap().is_none());
+
+        // Fragment counts beyond the limit are refused up front
+        let mut reassembler = Reassembler::default().with_max_fragments(4);
+        assert!(reassembler.push(first(1, 5)).is_err());
+        assert_eq!(reassembler.buffered(), 0);
+
+        // Tiny fragments are charged their bookkeeping
+        let mut reassembler = Reassembler::new(2 * FRAGMENT_OVERHEAD + 1);
+        assert!(reassembler.push(first(1, 100)).unwrap().is_none());
+        assert_eq!(reassembler.buffered(), 1 + FRAGMENT_OVERHEAD);
+        let mut next = first(1, 100);
+        next.index = 1;
+        assert!(reassembler.push(next).is_err());
+        assert_eq!(reassembler.buffered(), 0);
+    }
+}
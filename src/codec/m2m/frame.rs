@@ -10,6 +10,8 @@
 //! - `SecurityMode::None` - No authentication (default)
 //! - `SecurityMode::Hmac` - HMAC-SHA256 authentication tag appended
 //! - `SecurityMode::Aead` - ChaCha20-Poly1305 encryption
+//! - `SecurityMode::FieldAead` - ChaCha20-Poly1305 on `content` values only,
+//!   HMAC-SHA256 over the frame
 //!
 //! # Wire Format with Security
 //!
@@ -17,24 +19,36 @@
 //! None: #M2M|1|<headers><payload_len><crc32><payload>
 //! HMAC: #M2M|1|<headers><payload_len><crc32><payload><hmac_tag:32>
 //! AEAD: #M2M|1|<headers><nonce:12><encrypted_payload_with_tag>
+//! Field AEAD: #M2M|1|<headers><payload_len><crc32><sealed_payload><hmac_tag:32>
 //! ```
 
 #![allow(missing_docs)]
 
 use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
 use brotli::{CompressorWriter, Decompressor};
-use std::io::{Read, Write};
+use bytes::{BufMut, BytesMut};
+use std::borrow::Cow;
+use std::io::Write;
+use std::ops::DerefMut;
 
 use super::{
     cost::{estimate_cost, estimate_tokens_from_content},
-    crypto::{SecurityContext, AEAD_TAG_SIZE, HMAC_TAG_SIZE, NONCE_SIZE},
-    flags::{CommonFlags, Flags, ResponseFlags},
+    crypto::{
+        fields::{open_fields, seal_fields},
+        SecurityContext, AEAD_TAG_SIZE, HMAC_TAG_SIZE, NONCE_SIZE,
+    },
+    extension::{self, HeaderExtension},
+    flags::{CommonFlags, CompressionHint, Flags, RequestFlags, ResponseFlags},
     header::{
         detect_request_flags, detect_response_flags, FixedHeader, ResponseHeader, RoutingHeader,
         Schema, SecurityMode, FIXED_HEADER_SIZE,
     },
-    COMPRESSION_THRESHOLD, M2M_PREFIX,
+    TraceContext, COMPRESSION_THRESHOLD, M2M_PREFIX,
 };
+use m2m_core::frame::{self, verify_checksum, RawFrame};
+
+use crate::codec::canonical::CanonicalMode;
+use crate::codec::{b64, BufferPool, DecompressionLimits};
 use crate::error::{M2MError, Result};
 
 /// Complete M2M frame
@@ -50,6 +64,11 @@ pub struct M2MFrame {
     pub payload: String,
     /// CRC32 checksum of original JSON
     pub checksum: u32,
+    /// Variable header bytes after the routing or response header
+    ///
+    /// Holds the extension block, or fields from a newer layout, and is
+    /// written back verbatim so re-encoding preserves them.
+    trailer: Vec<u8>,
 }
 
 impl M2MFrame {
@@ -59,10 +78,20 @@ impl M2MFrame {
             .map_err(|e| M2MError::Compression(format!("Invalid JSON: {}", e)))?;
 
         // Detect flags from JSON content
-        let request_flags = detect_request_flags(&parsed);
+        let mut request_flags = detect_request_flags(&parsed);
+        if request_flags.has(RequestFlags::HAS_IMAGES) {
+            request_flags.set(RequestFlags::HAS_MEDIA_STATS);
+        }
 
-        // Determine if compression is beneficial
-        let should_compress = json.len() >= COMPRESSION_THRESHOLD;
+        // Extract routing header
+        let mut routing = RoutingHeader::from_json(&parsed, &request_flags)?;
+
+        // Determine if compression is beneficial; inline images are already
+        // entropy-coded, so Brotli is skipped when they dominate the payload
+        let media_heavy = routing
+            .media
+            .is_some_and(|media| media.is_media_heavy(json.len()));
+        let should_compress = json.len() >= COMPRESSION_THRESHOLD && !media_heavy;
         let mut common_flags = CommonFlags::new();
         if should_compress {
             common_flags.set(CommonFlags::COMPRESSED);
@@ -70,9 +99,6 @@ impl M2MFrame {
 
         let flags = Flags::for_request(request_flags, common_flags);
 
-        // Extract routing header
-        let mut routing = RoutingHeader::from_json(&parsed, &request_flags)?;
-
         // Estimate cost if we have enough info
         let estimated_tokens = estimate_tokens_from_content(routing.content_hint as usize);
         let estimated_completion = routing.max_tokens.unwrap_or(500);
@@ -103,6 +129,7 @@ impl M2MFrame {
             response: None,
             payload: json.to_string(),
             checksum,
+            trailer: Vec::new(),
         })
     }
 
@@ -157,9 +184,156 @@ impl M2MFrame {
             response: Some(response_header),
             payload: json.to_string(),
             checksum,
+            trailer: Vec::new(),
         })
     }
 
+    /// Apply a sender compression hint
+    ///
+    /// Sets the hint flag and overrides the size-based payload compression
+    /// decision (see [`CompressionHint`]).
+    pub fn with_hint(mut self, hint: CompressionHint) -> Self {
+        let common = &mut self.fixed.flags.common;
+        for other in CompressionHint::ALL {
+            common.clear(other.flag());
+        }
+        common.set(hint.flag());
+        if hint.compress_payload() {
+            common.set(CommonFlags::COMPRESSED);
+        } else {
+            common.clear(CommonFlags::COMPRESSED);
+        }
+        self
+    }
+
+    /// Get the sender's compression hint, if any
+    pub fn hint(&self) -> Option<CompressionHint> {
+        self.fixed.flags.common.hint()
+    }
+
+    /// Whether the payload was canonicalized before encoding
+    ///
+    /// Canonical frames round-trip to semantically identical JSON in
+    /// canonical form rather than the sender's original bytes.
+    pub fn is_canonical(&self) -> bool {
+        self.fixed.flags.common.is_canonical()
+    }
+
+    /// Append a header extension (experimental layout v2)
+    ///
+    /// Sets `HAS_EXTENSIONS` and grows `header_len`; v1 decoders skip the
+    /// extension block. Frames without a routing or response header cannot
+    /// carry extensions.
+    pub fn with_extension(mut self, ext: HeaderExtension) -> Result<Self> {
+        if self.routing.is_none() && self.response.is_none() {
+            return Err(M2MError::Compression(
+                "Header extensions need a routing or response header".to_string(),
+            ));
+        }
+        let mut extensions = self.extensions()?;
+        extensions.push(ext);
+
+        let variable_len = self.fixed.header_len as usize - self.trailer.len();
+        let header_len =
+            u16::try_from(variable_len + extension::block_size(&extensions)).map_err(|_| {
+                M2MError::Compression("Header extensions exceed header_len".to_string())
+            })?;
+
+        self.trailer.clear();
+        extension::write_block(&extensions, &mut self.trailer);
+        self.fixed.header_len = header_len;
+        self.fixed.flags.common.set(CommonFlags::HAS_EXTENSIONS);
+        Ok(self)
+    }
+
+    /// Header extensions, in wire order
+    ///
+    /// Empty for v1 frames and for blocks written by a newer layout.
+    pub fn extensions(&self) -> Result<Vec<HeaderExtension>> {
+        read_extensions(&self.fixed, &self.trailer)
+    }
+
+    /// Reject critical extensions whose kind is not in `understood`
+    ///
+    /// Non-critical extensions are always accepted. Receivers that act on a
+    /// frame's contents must call this before trusting it.
+    pub fn require_extensions(&self, understood: &[u8]) -> Result<()> {
+        require_extensions(&self.extensions()?, understood)
+    }
+
+    /// Attach the sender's W3C trace context (see [`TraceContext`])
+    pub fn with_trace_context(self, context: &TraceContext) -> Result<Self> {
+        self.with_extension(context.to_extension())
+    }
+
+    /// The sender's W3C trace context, if the frame carries one
+    pub fn trace_context(&self) -> Option<TraceContext> {
+        TraceContext::from_extensions(&self.extensions().ok()?)
+    }
+
+    /// Read the trace context of a text wire frame without decoding it
+    ///
+    /// Only the headers are base64-decoded; the payload is untouched.
+    pub fn peek_trace_context(wire: &str) -> Option<TraceContext> {
+        let encoded = wire.strip_prefix(M2M_PREFIX)?;
+        let head = BASE64.decode(encoded.get(..4)?).ok()?;
+        let header_len = u16::from_le_bytes([head[0], head[1]]) as usize;
+
+        // Any 4-character-aligned prefix of the base64 is valid on its own
+        let chars = header_len.div_ceil(3) * 4;
+        let header = BASE64
+            .decode(encoded.get(..chars.min(encoded.len()))?)
+            .ok()?;
+        let header = header.get(..header_len)?;
+
+        let fixed = FixedHeader::from_bytes(header).ok()?;
+        if fixed.flags.common.is_fragment() || !fixed.flags.common.has_extensions() {
+            return None;
+        }
+        let (_, _, trailer) = read_variable_header(&fixed, &header[FIXED_HEADER_SIZE..]).ok()?;
+        TraceContext::from_extensions(&extension::read_block(trailer).ok()?)
+    }
+
+    /// Header extensions of a binary frame, without touching its payload
+    ///
+    /// Works in every security mode: headers are never encrypted, so this
+    /// reads HMAC and AEAD frames without their key.
+    pub fn peek_extensions(data: &[u8]) -> Result<Vec<HeaderExtension>> {
+        let truncated = || M2MError::Decompression("Frame too short for headers".to_string());
+        let header = data
+            .strip_prefix(M2M_PREFIX.as_bytes())
+            .ok_or_else(|| M2MError::Decompression("Invalid M2M prefix".to_string()))?;
+        let fixed =
+            FixedHeader::from_bytes(header.get(..FIXED_HEADER_SIZE).ok_or_else(truncated)?)?;
+        if fixed.flags.common.is_fragment() {
+            return Err(M2MError::Decompression(
+                "Frame is a fragment; reassemble before decoding".to_string(),
+            ));
+        }
+        let variable = header
+            .get(FIXED_HEADER_SIZE..fixed.header_len as usize)
+            .ok_or_else(truncated)?;
+        let (_, _, trailer) = read_variable_header(&fixed, variable)?;
+        read_extensions(&fixed, trailer)
+    }
+
+    /// Read the compression hint of a text wire frame without decoding it
+    ///
+    /// Returns `None` for non-M2M content and frames without a hint.
+    pub fn peek_hint(wire: &str) -> Option<CompressionHint> {
+        // 12 base64 chars cover the first 9 bytes of the fixed header,
+        // which include the flags field
+        let head = wire.strip_prefix(M2M_PREFIX)?.get(..12)?;
+        let bytes = BASE64.decode(head).ok()?;
+        let flags: [u8; 4] = bytes.get(4..8)?.try_into().ok()?;
+        Flags::from_bytes(&flags).common.hint()
+    }
+
+    /// Brotli quality for the payload (maximum for archival frames)
+    fn brotli_quality(&self) -> u32 {
+        payload_quality(self.hint())
+    }
+
     /// Encode frame to wire format bytes
     ///
     /// Returns raw binary format suitable for binary-safe transport channels
@@ -169,47 +343,77 @@ impl M2MFrame {
     /// which wraps the binary in base64.
     pub fn encode(&self) -> Result<Vec<u8>> {
         let mut buf = Vec::with_capacity(256 + self.payload.len());
+        self.encode_into(&mut buf, &mut Vec::new())?;
+        Ok(buf)
+    }
+
+    /// Encode frame into a buffer taken from `pool`
+    ///
+    /// Produces the same bytes as [`encode`](Self::encode). The Brotli
+    /// scratch buffer also comes from the pool and goes straight back;
+    /// return the frame with [`BufferPool::put`] once it is sent.
+    pub fn encode_pooled(&self, pool: &BufferPool) -> Result<BytesMut> {
+        with_pooled(pool, |buf, scratch| self.encode_into(buf, scratch))
+    }
+
+    /// Write the frame to `buf`, compressing through `scratch`
+    fn encode_into<B, S>(&self, buf: &mut B, scratch: &mut S) -> Result<()>
+    where
+        B: BufMut,
+        S: BufMut + DerefMut<Target = [u8]>,
+    {
+        self.write_headers(&self.fixed, buf);
 
-        // Write prefix
-        buf.extend_from_slice(M2M_PREFIX.as_bytes());
+        // Compress or raw payload
+        let payload_bytes = self.wire_payload(scratch)?;
+
+        // Payload length, checksum, payload
+        buf.put_u32_le(payload_bytes.len() as u32);
+        buf.put_u32_le(self.checksum);
+        buf.put_slice(payload_bytes);
+
+        Ok(())
+    }
 
-        // Write fixed header
-        buf.extend_from_slice(&self.fixed.to_bytes());
+    /// Write prefix, fixed header and variable header (routing or response)
+    fn write_headers(&self, fixed: &FixedHeader, buf: &mut impl BufMut) {
+        buf.put_slice(M2M_PREFIX.as_bytes());
+        buf.put_slice(&fixed.to_bytes());
 
-        // Write variable header (routing or response)
         match self.fixed.schema {
             Schema::Request | Schema::EmbeddingRequest => {
                 if let Some(ref routing) = self.routing {
                     let request_flags = self.fixed.flags.request_flags();
-                    buf.extend_from_slice(&routing.to_bytes(&request_flags));
+                    buf.put_slice(&routing.to_bytes(&request_flags));
                 }
             },
             Schema::Response | Schema::EmbeddingResponse | Schema::Error => {
                 if let Some(ref response) = self.response {
                     let response_flags = self.fixed.flags.response_flags();
-                    buf.extend_from_slice(&response.to_bytes(&response_flags));
+                    buf.put_slice(&response.to_bytes(&response_flags));
                 }
             },
             _ => {},
         }
+        buf.put_slice(&self.trailer);
+    }
 
-        // Compress or raw payload
-        let payload_bytes = if self.fixed.flags.is_compressed() {
-            compress_brotli(self.payload.as_bytes())?
+    /// Payload as sent: Brotli output appended to `scratch`, or the raw JSON
+    fn wire_payload<'a, S>(&'a self, scratch: &'a mut S) -> Result<&'a [u8]>
+    where
+        S: BufMut + DerefMut<Target = [u8]>,
+    {
+        if self.fixed.flags.is_compressed() {
+            let start = scratch.len();
+            compress_brotli(
+                self.payload.as_bytes(),
+                self.brotli_quality(),
+                (&mut *scratch).writer(),
+            )?;
+            Ok(&scratch[start..])
         } else {
-            self.payload.as_bytes().to_vec()
-        };
-
-        // Write payload length
-        buf.extend_from_slice(&(payload_bytes.len() as u32).to_le_bytes());
-
-        // Write checksum
-        buf.extend_from_slice(&self.checksum.to_le_bytes());
-
-        // Write payload
-        buf.extend_from_slice(&payload_bytes);
-
-        Ok(buf)
+            Ok(self.payload.as_bytes())
+        }
     }
 
     /// Encode frame to wire format string (for text transport)
@@ -225,7 +429,7 @@ impl M2MFrame {
         // The prefix is ASCII, the rest is binary - use base64 for text transport
         let prefix_len = M2M_PREFIX.len();
         let binary_part = &bytes[prefix_len..];
-        let encoded = BASE64.encode(binary_part);
+        let encoded = b64::encode(binary_part);
 
         Ok(format!("{}{}", M2M_PREFIX, encoded))
     }
@@ -239,30 +443,80 @@ impl M2MFrame {
     /// # Wire Format
     /// - HMAC: `<frame><hmac_tag:32>`
     /// - AEAD: `<headers><nonce:12><encrypted_payload_with_tag>`
+    /// - Field AEAD: `<frame with sealed content values><hmac_tag:32>`
     pub fn encode_secure(
         &self,
         security_mode: SecurityMode,
         security_ctx: &mut SecurityContext,
     ) -> Result<Vec<u8>> {
+        let mut buf = Vec::with_capacity(256 + self.payload.len());
+        self.encode_secure_into(security_mode, security_ctx, &mut buf, &mut Vec::new())?;
+        Ok(buf)
+    }
+
+    /// Encode frame with security into a buffer taken from `pool`
+    ///
+    /// Pooled counterpart of [`encode_secure`](Self::encode_secure); see
+    /// [`encode_pooled`](Self::encode_pooled).
+    pub fn encode_secure_pooled(
+        &self,
+        security_mode: SecurityMode,
+        security_ctx: &mut SecurityContext,
+        pool: &BufferPool,
+    ) -> Result<BytesMut> {
+        with_pooled(pool, |buf, scratch| {
+            self.encode_secure_into(security_mode, security_ctx, buf, scratch)
+        })
+    }
+
+    fn encode_secure_into<B, S>(
+        &self,
+        security_mode: SecurityMode,
+        security_ctx: &mut SecurityContext,
+        buf: &mut B,
+        scratch: &mut S,
+    ) -> Result<()>
+    where
+        B: BufMut + DerefMut<Target = [u8]>,
+        S: BufMut + DerefMut<Target = [u8]>,
+    {
+        if security_mode != SecurityMode::None {
+            security_ctx.check_key()?;
+        }
         match security_mode {
-            SecurityMode::None => self.encode(),
-            SecurityMode::Hmac => self.encode_with_hmac(security_ctx),
-            SecurityMode::Aead => self.encode_with_aead(security_ctx),
+            SecurityMode::None => self.encode_into(buf, scratch),
+            SecurityMode::Hmac => {
+                self.encode_with_hmac(SecurityMode::Hmac, security_ctx, buf, scratch)
+            },
+            SecurityMode::Aead => self.encode_with_aead(security_ctx, buf, scratch),
+            SecurityMode::FieldAead => self.encode_with_field_aead(security_ctx, buf, scratch),
         }
     }
 
-    /// Encode frame with HMAC-SHA256 authentication
-    fn encode_with_hmac(&self, security_ctx: &SecurityContext) -> Result<Vec<u8>> {
+    /// Encode frame with HMAC-SHA256 authentication, marked as `mode`
+    fn encode_with_hmac<B, S>(
+        &self,
+        mode: SecurityMode,
+        security_ctx: &SecurityContext,
+        buf: &mut B,
+        scratch: &mut S,
+    ) -> Result<()>
+    where
+        B: BufMut + DerefMut<Target = [u8]>,
+        S: BufMut + DerefMut<Target = [u8]>,
+    {
         use super::crypto::HmacAuth;
 
         // First encode the frame normally
-        let mut frame_bytes = self.encode()?;
+        let start = buf.len();
+        self.encode_into(buf, scratch)?;
+        let frame_bytes = &mut buf[start..];
 
         // Update the security mode in the fixed header
         // The security byte is at offset: prefix_len + 3
         let security_offset = M2M_PREFIX.len() + 3;
         if security_offset < frame_bytes.len() {
-            frame_bytes[security_offset] = SecurityMode::Hmac.as_byte();
+            frame_bytes[security_offset] = mode.as_byte();
         }
 
         // Compute HMAC over the entire frame (excluding prefix for efficiency)
@@ -273,89 +527,81 @@ impl M2MFrame {
         let tag = hmac_auth.compute_tag(data_to_sign);
 
         // Append HMAC tag
-        frame_bytes.extend_from_slice(&tag);
+        buf.put_slice(&tag);
 
-        Ok(frame_bytes)
+        Ok(())
     }
 
     /// Encode frame with ChaCha20-Poly1305 AEAD encryption
-    fn encode_with_aead(&self, security_ctx: &mut SecurityContext) -> Result<Vec<u8>> {
+    fn encode_with_aead<B, S>(
+        &self,
+        security_ctx: &mut SecurityContext,
+        buf: &mut B,
+        plaintext: &mut S,
+    ) -> Result<()>
+    where
+        B: BufMut + DerefMut<Target = [u8]>,
+        S: BufMut + DerefMut<Target = [u8]>,
+    {
         use super::crypto::AeadCipher;
 
-        let mut buf = Vec::with_capacity(256 + self.payload.len());
-
-        // Write prefix
-        buf.extend_from_slice(M2M_PREFIX.as_bytes());
-
         // Create fixed header with AEAD security mode
+        // Variable header is authenticated but not encrypted
+        let start = buf.len();
         let mut fixed = self.fixed.clone();
         fixed.security = SecurityMode::Aead;
-        buf.extend_from_slice(&fixed.to_bytes());
-
-        // Write variable header (routing or response) - this is authenticated but not encrypted
-        match self.fixed.schema {
-            Schema::Request | Schema::EmbeddingRequest => {
-                if let Some(ref routing) = self.routing {
-                    let request_flags = self.fixed.flags.request_flags();
-                    buf.extend_from_slice(&routing.to_bytes(&request_flags));
-                }
-            },
-            Schema::Response | Schema::EmbeddingResponse | Schema::Error => {
-                if let Some(ref response) = self.response {
-                    let response_flags = self.fixed.flags.response_flags();
-                    buf.extend_from_slice(&response.to_bytes(&response_flags));
-                }
-            },
-            _ => {},
-        }
+        self.write_headers(&fixed, buf);
 
         // header_end marks the end of all headers (fixed + variable)
         let header_end = buf.len();
 
-        // Prepare plaintext: payload_len || crc32 || payload
-        let payload_bytes = if self.fixed.flags.is_compressed() {
-            compress_brotli(self.payload.as_bytes())?
-        } else {
-            self.payload.as_bytes().to_vec()
-        };
-
-        let mut plaintext = Vec::with_capacity(8 + payload_bytes.len());
-        plaintext.extend_from_slice(&(payload_bytes.len() as u32).to_le_bytes());
-        plaintext.extend_from_slice(&self.checksum.to_le_bytes());
-        plaintext.extend_from_slice(&payload_bytes);
+        // Prepare plaintext: payload_len || crc32 || payload, compressing
+        // straight after the length and checksum
+        let base = plaintext.len();
+        plaintext.put_bytes(0, 8);
+        let payload_len = self.wire_payload(plaintext)?.len();
+        if !self.fixed.flags.is_compressed() {
+            plaintext.put_slice(self.payload.as_bytes());
+        }
+        plaintext[base..base + 4].copy_from_slice(&(payload_len as u32).to_le_bytes());
+        plaintext[base + 4..base + 8].copy_from_slice(&self.checksum.to_le_bytes());
 
-        // Generate cryptographically secure random nonce
-        #[cfg(feature = "crypto")]
-        let nonce = security_ctx
-            .next_nonce()
-            .map_err(|e| M2MError::Crypto(e.into()))?;
-        #[cfg(not(feature = "crypto"))]
-        let nonce = {
-            // Fallback for non-crypto builds (NOT SECURE - testing only)
-            let mut n = [0u8; 12];
-            n[0..8].copy_from_slice(
-                &(std::time::SystemTime::now()
-                    .duration_since(std::time::UNIX_EPOCH)
-                    .unwrap_or_default()
-                    .as_nanos() as u64)
-                    .to_le_bytes(),
-            );
-            n
-        };
+        let nonce = next_nonce(security_ctx)?;
         let cipher =
             AeadCipher::new(security_ctx.key().clone()).map_err(|e| M2MError::Crypto(e.into()))?;
 
         // Associated data = headers (authenticated but not encrypted)
-        let aad = &buf[M2M_PREFIX.len()..header_end];
+        let aad = &buf[start + M2M_PREFIX.len()..header_end];
 
         let ciphertext = cipher
-            .encrypt(&plaintext, &nonce, aad)
+            .encrypt(&plaintext[base..], &nonce, aad)
             .map_err(|e| M2MError::Crypto(e.into()))?;
 
         // Append ciphertext (includes nonce at start and tag at end)
-        buf.extend_from_slice(&ciphertext);
+        buf.put_slice(&ciphertext);
 
-        Ok(buf)
+        Ok(())
+    }
+
+    /// Encode frame with `content` values sealed and HMAC over the rest
+    fn encode_with_field_aead<B, S>(
+        &self,
+        security_ctx: &mut SecurityContext,
+        buf: &mut B,
+        scratch: &mut S,
+    ) -> Result<()>
+    where
+        B: BufMut + DerefMut<Target = [u8]>,
+        S: BufMut + DerefMut<Target = [u8]>,
+    {
+        let session_key = security_ctx.key().clone();
+        let payload = seal_fields(&self.payload, &session_key, || next_nonce(security_ctx))?;
+        let sealed = Self {
+            checksum: crc32fast::hash(payload.as_bytes()),
+            payload,
+            ..self.clone()
+        };
+        sealed.encode_with_hmac(SecurityMode::FieldAead, security_ctx, buf, scratch)
     }
 
     /// Encode frame with security to string (base64)
@@ -367,117 +613,24 @@ impl M2MFrame {
         let bytes = self.encode_secure(security_mode, security_ctx)?;
         let prefix_len = M2M_PREFIX.len();
         let binary_part = &bytes[prefix_len..];
-        let encoded = BASE64.encode(binary_part);
+        let encoded = b64::encode(binary_part);
         Ok(format!("{}{}", M2M_PREFIX, encoded))
     }
 
     /// Decode frame from wire format bytes
     pub fn decode(data: &[u8]) -> Result<Self> {
-        // Check prefix
-        if !data.starts_with(M2M_PREFIX.as_bytes()) {
-            return Err(M2MError::Decompression("Invalid M2M prefix".to_string()));
-        }
-
-        let mut pos = M2M_PREFIX.len();
-
-        // Read fixed header
-        if pos + FIXED_HEADER_SIZE > data.len() {
-            return Err(M2MError::Decompression(
-                "Frame too short for fixed header".to_string(),
-            ));
-        }
-        let fixed = FixedHeader::from_bytes(&data[pos..pos + FIXED_HEADER_SIZE])?;
-        pos += FIXED_HEADER_SIZE;
-
-        // Calculate variable header size (with underflow protection)
-        let header_len = fixed.header_len as usize;
-        if header_len < FIXED_HEADER_SIZE {
-            return Err(M2MError::Decompression(format!(
-                "Invalid header_len: {} < minimum {}",
-                header_len, FIXED_HEADER_SIZE
-            )));
-        }
-        let variable_header_size = header_len - FIXED_HEADER_SIZE;
-
-        if pos + variable_header_size > data.len() {
-            return Err(M2MError::Decompression(
-                "Frame too short for variable header".to_string(),
-            ));
-        }
-
-        // Read variable header
-        let (routing, response) = match fixed.schema {
-            Schema::Request | Schema::EmbeddingRequest => {
-                let request_flags = fixed.flags.request_flags();
-                let (routing, _) = RoutingHeader::from_bytes(&data[pos..], &request_flags)?;
-                pos += variable_header_size;
-                (Some(routing), None)
-            },
-            Schema::Response | Schema::EmbeddingResponse | Schema::Error => {
-                let response_flags = fixed.flags.response_flags();
-                let (response, _) = ResponseHeader::from_bytes(&data[pos..], &response_flags)?;
-                pos += variable_header_size;
-                (None, Some(response))
-            },
-            _ => {
-                pos += variable_header_size;
-                (None, None)
-            },
-        };
-
-        // Read payload length
-        if pos + 4 > data.len() {
-            return Err(M2MError::Decompression(
-                "Frame too short for payload length".to_string(),
-            ));
-        }
-        let payload_len =
-            u32::from_le_bytes([data[pos], data[pos + 1], data[pos + 2], data[pos + 3]]) as usize;
-        pos += 4;
-
-        // Read checksum
-        if pos + 4 > data.len() {
-            return Err(M2MError::Decompression(
-                "Frame too short for checksum".to_string(),
-            ));
-        }
-        let checksum = u32::from_le_bytes([data[pos], data[pos + 1], data[pos + 2], data[pos + 3]]);
-        pos += 4;
-
-        // Read payload
-        if pos + payload_len > data.len() {
-            return Err(M2MError::Decompression(
-                "Frame too short for payload".to_string(),
-            ));
-        }
-        let payload_bytes = &data[pos..pos + payload_len];
-
-        // Decompress if needed
-        let payload = if fixed.flags.is_compressed() {
-            let decompressed = decompress_brotli(payload_bytes)?;
-            String::from_utf8(decompressed)
-                .map_err(|e| M2MError::Decompression(format!("Invalid UTF-8: {}", e)))?
-        } else {
-            String::from_utf8(payload_bytes.to_vec())
-                .map_err(|e| M2MError::Decompression(format!("Invalid UTF-8: {}", e)))?
-        };
-
-        // Verify checksum
-        let computed_checksum = crc32fast::hash(payload.as_bytes());
-        if computed_checksum != checksum {
-            return Err(M2MError::Decompression(format!(
-                "Checksum mismatch: expected {:08x}, got {:08x}",
-                checksum, computed_checksum
-            )));
-        }
+        M2MFrameRef::decode(data)?.into_owned()
+    }
 
-        Ok(Self {
-            fixed,
-            routing,
-            response,
-            payload,
-            checksum,
-        })
+    /// Decode frame headers without copying or decompressing the payload
+    ///
+    /// The returned [`M2MFrameRef`] borrows the payload from `data`. Routing
+    /// and response headers are parsed eagerly (they are small), while the
+    /// payload is only decompressed and checksum-verified when
+    /// [`M2MFrameRef::payload`] is called. Proxies that only inspect routing
+    /// information (model, role counts) never pay decompression cost.
+    pub fn decode_borrowed(data: &[u8]) -> Result<M2MFrameRef<'_>> {
+        M2MFrameRef::decode(data)
     }
 
     /// Decode frame from wire format string
@@ -491,8 +644,7 @@ impl M2MFrame {
 
         // Decode base64 portion after prefix
         let base64_part = &data[M2M_PREFIX.len()..];
-        let binary = BASE64
-            .decode(base64_part)
+        let binary = b64::decode(base64_part)
             .map_err(|e| M2MError::Decompression(format!("Base64 decode failed: {}", e)))?;
 
         // Reconstruct full frame with prefix
@@ -519,14 +671,26 @@ impl M2MFrame {
         }
 
         let security_mode = SecurityMode::from_byte(data[security_offset]);
+        if security_mode != SecurityMode::None {
+            security_ctx.check_key()?;
+        }
 
         match security_mode {
             SecurityMode::None => Self::decode(data),
             SecurityMode::Hmac => Self::decode_with_hmac(data, security_ctx),
             SecurityMode::Aead => Self::decode_with_aead(data, security_ctx),
+            SecurityMode::FieldAead => Self::decode_with_field_aead(data, security_ctx),
         }
     }
 
+    /// Decode frame with HMAC verification, then open its sealed fields
+    fn decode_with_field_aead(data: &[u8], security_ctx: &SecurityContext) -> Result<Self> {
+        let mut frame = Self::decode_with_hmac(data, security_ctx)?;
+        frame.payload = open_fields(&frame.payload, security_ctx.key())?;
+        frame.checksum = crc32fast::hash(frame.payload.as_bytes());
+        Ok(frame)
+    }
+
     /// Decode frame with HMAC verification
     fn decode_with_hmac(data: &[u8], security_ctx: &SecurityContext) -> Result<Self> {
         use super::crypto::HmacAuth;
@@ -575,6 +739,11 @@ impl M2MFrame {
         }
         let fixed = FixedHeader::from_bytes(&data[pos..pos + FIXED_HEADER_SIZE])?;
         pos += FIXED_HEADER_SIZE;
+        if fixed.flags.common.is_fragment() {
+            return Err(M2MError::Decompression(
+                "Frame is a fragment; reassemble before decoding".to_string(),
+            ));
+        }
 
         // Calculate variable header size (with underflow protection)
         let header_len = fixed.header_len as usize;
@@ -593,24 +762,9 @@ impl M2MFrame {
         }
 
         // Read variable header
-        let (routing, response) = match fixed.schema {
-            Schema::Request | Schema::EmbeddingRequest => {
-                let request_flags = fixed.flags.request_flags();
-                let (routing, _) = RoutingHeader::from_bytes(&data[pos..], &request_flags)?;
-                pos += variable_header_size;
-                (Some(routing), None)
-            },
-            Schema::Response | Schema::EmbeddingResponse | Schema::Error => {
-                let response_flags = fixed.flags.response_flags();
-                let (response, _) = ResponseHeader::from_bytes(&data[pos..], &response_flags)?;
-                pos += variable_header_size;
-                (None, Some(response))
-            },
-            _ => {
-                pos += variable_header_size;
-                (None, None)
-            },
-        };
+        let (routing, response, trailer) =
+            read_variable_header(&fixed, &data[pos..pos + variable_header_size])?;
+        pos += variable_header_size;
 
         // Remaining data is the encrypted payload (nonce + ciphertext + tag)
         let encrypted_data = &data[pos..];
@@ -654,7 +808,7 @@ impl M2MFrame {
 
         // Decompress if needed
         let payload = if fixed.flags.is_compressed() {
-            let decompressed = decompress_brotli(payload_bytes)?;
+            let decompressed = decompress_brotli(payload_bytes, &DecompressionLimits::default())?;
             String::from_utf8(decompressed)
                 .map_err(|e| M2MError::Decompression(format!("Invalid UTF-8: {}", e)))?
         } else {
@@ -662,14 +816,7 @@ impl M2MFrame {
                 .map_err(|e| M2MError::Decompression(format!("Invalid UTF-8: {}", e)))?
         };
 
-        // Verify checksum
-        let computed_checksum = crc32fast::hash(payload.as_bytes());
-        if computed_checksum != checksum {
-            return Err(M2MError::Decompression(format!(
-                "Checksum mismatch: expected {:08x}, got {:08x}",
-                checksum, computed_checksum
-            )));
-        }
+        verify_checksum(checksum, payload.as_bytes())?;
 
         Ok(Self {
             fixed,
@@ -677,6 +824,7 @@ impl M2MFrame {
             response,
             payload,
             checksum,
+            trailer: trailer.to_vec(),
         })
     }
 
@@ -687,8 +835,7 @@ impl M2MFrame {
         }
 
         let base64_part = &data[M2M_PREFIX.len()..];
-        let binary = BASE64
-            .decode(base64_part)
+        let binary = b64::decode(base64_part)
             .map_err(|e| M2MError::Decompression(format!("Base64 decode failed: {}", e)))?;
 
         let mut full_frame = M2M_PREFIX.as_bytes().to_vec();
@@ -732,24 +879,184 @@ impl M2MFrame {
     }
 }
 
+/// Borrowed view of an M2M frame
+///
+/// Produced by [`M2MFrame::decode_borrowed`]. Headers are parsed, but the
+/// payload stays a slice of the input buffer until requested.
+#[derive(Debug, Clone)]
+pub struct M2MFrameRef<'a> {
+    /// Fixed header (20 bytes)
+    pub fixed: FixedHeader,
+    /// Routing header (for requests)
+    pub routing: Option<RoutingHeader>,
+    /// Response header (for responses)
+    pub response: Option<ResponseHeader>,
+    /// CRC32 checksum of original JSON
+    pub checksum: u32,
+    /// Payload bytes as they appear on the wire (possibly Brotli-compressed)
+    raw_payload: &'a [u8],
+    /// Variable header bytes after the routing or response header
+    trailer: &'a [u8],
+}
+
+impl<'a> M2MFrameRef<'a> {
+    /// Parse headers and locate the payload without decompressing it
+    fn decode(data: &'a [u8]) -> Result<Self> {
+        let raw = RawFrame::parse(data)?;
+        let (routing, response, trailer) = read_variable_header(&raw.fixed, raw.variable_header)?;
+
+        Ok(Self {
+            fixed: raw.fixed,
+            routing,
+            response,
+            checksum: raw.checksum,
+            raw_payload: raw.payload,
+            trailer,
+        })
+    }
+
+    /// Model name from the routing or response header
+    pub fn model(&self) -> Option<&str> {
+        self.routing
+            .as_ref()
+            .map(|r| r.model.as_str())
+            .or_else(|| self.response.as_ref().map(|r| r.model.as_str()))
+    }
+
+    /// Check if this is a request
+    pub fn is_request(&self) -> bool {
+        self.fixed.schema.is_request()
+    }
+
+    /// Check if this is a response
+    pub fn is_response(&self) -> bool {
+        self.fixed.schema.is_response()
+    }
+
+    /// Check if the payload is Brotli-compressed on the wire
+    pub fn is_compressed(&self) -> bool {
+        self.fixed.flags.is_compressed()
+    }
+
+    /// Check if the payload was canonicalized before encoding
+    pub fn is_canonical(&self) -> bool {
+        self.fixed.flags.common.is_canonical()
+    }
+
+    /// Payload bytes exactly as they appear on the wire
+    pub fn raw_payload(&self) -> &'a [u8] {
+        self.raw_payload
+    }
+
+    /// The sender's W3C trace context, if the frame carries one
+    pub fn trace_context(&self) -> Option<TraceContext> {
+        TraceContext::from_extensions(&self.extensions().ok()?)
+    }
+
+    /// Header extensions, in wire order (see [`M2MFrame::extensions`])
+    pub fn extensions(&self) -> Result<Vec<HeaderExtension>> {
+        read_extensions(&self.fixed, self.trailer)
+    }
+
+    /// Reject critical extensions whose kind is not in `understood`
+    pub fn require_extensions(&self, understood: &[u8]) -> Result<()> {
+        require_extensions(&self.extensions()?, understood)
+    }
+
+    /// Decode and verify the JSON payload
+    ///
+    /// Borrows from the input buffer when the payload is stored uncompressed;
+    /// allocates only when Brotli decompression is required. Decompression
+    /// is bounded by the default [`DecompressionLimits`].
+    pub fn payload(&self) -> Result<Cow<'a, str>> {
+        self.payload_with_limits(&DecompressionLimits::default())
+    }
+
+    /// Decode and verify the JSON payload within custom limits
+    pub fn payload_with_limits(&self, limits: &DecompressionLimits) -> Result<Cow<'a, str>> {
+        let payload = if self.fixed.flags.is_compressed() {
+            let decompressed = decompress_brotli(self.raw_payload, limits)?;
+            Cow::Owned(
+                String::from_utf8(decompressed)
+                    .map_err(|e| M2MError::Decompression(format!("Invalid UTF-8: {}", e)))?,
+            )
+        } else {
+            Cow::Borrowed(
+                std::str::from_utf8(self.raw_payload)
+                    .map_err(|e| M2MError::Decompression(format!("Invalid UTF-8: {}", e)))?,
+            )
+        };
+
+        verify_checksum(self.checksum, payload.as_bytes())?;
+
+        Ok(payload)
+    }
+
+    /// Convert into an owned [`M2MFrame`], decoding the payload
+    pub fn into_owned(self) -> Result<M2MFrame> {
+        let payload = self.payload()?.into_owned();
+        Ok(M2MFrame {
+            fixed: self.fixed,
+            routing: self.routing,
+            response: self.response,
+            payload,
+            checksum: self.checksum,
+            trailer: self.trailer.to_vec(),
+        })
+    }
+}
+
 /// M2M Codec for encoding and decoding frames
 #[derive(Debug, Clone, Default)]
-pub struct M2MCodec;
+pub struct M2MCodec {
+    /// Byte-identical or canonical round-trips
+    canonical: CanonicalMode,
+    /// Bounds on decompressed payloads
+    limits: DecompressionLimits,
+}
 
 impl M2MCodec {
     pub fn new() -> Self {
-        Self
+        Self::default()
     }
 
-    /// Encode JSON to M2M wire format
-    pub fn encode(&self, json: &str) -> Result<Vec<u8>> {
-        // Auto-detect if request or response
+    /// Choose between byte-identical and canonical round-trips
+    ///
+    /// In [`CanonicalMode::Canonical`] the JSON is canonicalized before
+    /// encoding and frames carry the `CANONICAL` flag.
+    pub fn with_canonical(mut self, mode: CanonicalMode) -> Self {
+        self.canonical = mode;
+        self
+    }
+
+    /// Round-trip mode
+    pub fn canonical_mode(&self) -> CanonicalMode {
+        self.canonical
+    }
+
+    /// Bound decompressed payloads (default: [`DecompressionLimits::default`])
+    pub fn with_limits(mut self, limits: DecompressionLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Build a request or response frame, auto-detected from the JSON
+    fn frame_for(&self, json: &str) -> Result<M2MFrame> {
+        let json = self.canonical.apply(json)?;
+        let mut frame = self.frame_for_json(&json)?;
+        if self.canonical.is_canonical() {
+            frame.fixed.flags.common.set(CommonFlags::CANONICAL);
+        }
+        Ok(frame)
+    }
+
+    fn frame_for_json(&self, json: &str) -> Result<M2MFrame> {
         let parsed: serde_json::Value = serde_json::from_str(json)
             .map_err(|e| M2MError::Compression(format!("Invalid JSON: {}", e)))?;
 
-        let frame = if parsed.get("messages").is_some() && parsed.get("model").is_some() {
+        if parsed.get("messages").is_some() && parsed.get("model").is_some() {
             // Request (has messages and model)
-            M2MFrame::new_request(json)?
+            M2MFrame::new_request(json)
         } else if parsed.get("choices").is_some()
             || parsed
                 .get("id")
@@ -758,48 +1065,64 @@ impl M2MCodec {
                 .unwrap_or(false)
         {
             // Response (has choices or chatcmpl ID)
-            M2MFrame::new_response(json)?
+            M2MFrame::new_response(json)
         } else {
             // Default to request
-            M2MFrame::new_request(json)?
-        };
+            M2MFrame::new_request(json)
+        }
+    }
 
-        frame.encode()
+    /// Encode JSON to M2M wire format
+    pub fn encode(&self, json: &str) -> Result<Vec<u8>> {
+        self.frame_for(json)?.encode()
     }
 
     /// Decode M2M wire format to JSON (100% fidelity)
     pub fn decode(&self, data: &[u8]) -> Result<String> {
-        let frame = M2MFrame::decode(data)?;
-        Ok(frame.payload)
+        let frame = M2MFrame::decode_borrowed(data)?;
+        // Callers only see the payload, so no extension can be understood
+        frame.require_extensions(&[])?;
+        let payload = frame.payload_with_limits(&self.limits)?.into_owned();
+        self.limits.check(data.len(), payload.len())?;
+        Ok(payload)
     }
 
     /// Encode JSON to M2M wire format string (base64 encoded)
     pub fn encode_string(&self, json: &str) -> Result<String> {
-        // Auto-detect if request or response
-        let parsed: serde_json::Value = serde_json::from_str(json)
-            .map_err(|e| M2MError::Compression(format!("Invalid JSON: {}", e)))?;
+        self.frame_for(json)?.encode_string()
+    }
 
-        let frame = if parsed.get("messages").is_some() && parsed.get("model").is_some() {
-            M2MFrame::new_request(json)?
-        } else if parsed.get("choices").is_some()
-            || parsed
-                .get("id")
-                .and_then(|v| v.as_str())
-                .map(|s| s.starts_with("chatcmpl-"))
-                .unwrap_or(false)
-        {
-            M2MFrame::new_response(json)?
-        } else {
-            M2MFrame::new_request(json)?
-        };
+    /// Encode JSON to M2M wire format string carrying header extensions
+    pub fn encode_string_with_extensions(
+        &self,
+        json: &str,
+        extensions: &[HeaderExtension],
+    ) -> Result<String> {
+        extensions
+            .iter()
+            .try_fold(self.frame_for(json)?, |frame, ext| {
+                frame.with_extension(ext.clone())
+            })?
+            .encode_string()
+    }
 
-        frame.encode_string()
+    /// Encode JSON to M2M wire format string with a compression hint
+    pub fn encode_string_with_hint(&self, json: &str, hint: CompressionHint) -> Result<String> {
+        self.frame_for(json)?.with_hint(hint).encode_string()
     }
 
     /// Decode M2M wire format string to JSON
     pub fn decode_string(&self, data: &str) -> Result<String> {
-        let frame = M2MFrame::decode_string(data)?;
-        Ok(frame.payload)
+        let binary = data
+            .strip_prefix(M2M_PREFIX)
+            .ok_or_else(|| M2MError::Decompression("Invalid M2M prefix".to_string()))
+            .and_then(|encoded| {
+                b64::decode(encoded)
+                    .map_err(|e| M2MError::Decompression(format!("Base64 decode failed: {}", e)))
+            })?;
+        let mut frame = M2M_PREFIX.as_bytes().to_vec();
+        frame.extend_from_slice(&binary);
+        self.decode(&frame)
     }
 
     /// Check if content is M2M format
@@ -808,27 +1131,109 @@ impl M2MCodec {
     }
 }
 
-/// Compress data using Brotli
-fn compress_brotli(data: &[u8]) -> Result<Vec<u8>> {
-    let mut compressed = Vec::new();
+/// Nonce for one AEAD encryption
+fn next_nonce(security_ctx: &mut SecurityContext) -> Result<[u8; NONCE_SIZE]> {
+    // Generate cryptographically secure random nonce
+    #[cfg(feature = "crypto")]
+    let nonce = security_ctx
+        .next_nonce()
+        .map_err(|e| M2MError::Crypto(e.into()))?;
+    #[cfg(not(feature = "crypto"))]
+    let nonce = {
+        // Fallback for non-crypto builds (NOT SECURE - testing only)
+        let _ = security_ctx;
+        let mut n = [0u8; NONCE_SIZE];
+        n[0..8].copy_from_slice(
+            &(std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_nanos() as u64)
+                .to_le_bytes(),
+        );
+        n
+    };
+    Ok(nonce)
+}
+
+/// Parse the routing or response header within the variable header
+///
+/// Returns the bytes left after it as the trailer. A trailer flagged with
+/// `HAS_EXTENSIONS` must be a well-formed extension block.
+fn read_variable_header<'a>(
+    fixed: &FixedHeader,
+    header: &'a [u8],
+) -> Result<(Option<RoutingHeader>, Option<ResponseHeader>, &'a [u8])> {
+    let (routing, response, trailer) = frame::read_variable_header(fixed, header)?;
+    read_extensions(fixed, trailer)?;
+    Ok((routing, response, trailer))
+}
+
+/// Extensions in `trailer`, if the frame is flagged as carrying any
+fn read_extensions(fixed: &FixedHeader, trailer: &[u8]) -> Result<Vec<HeaderExtension>> {
+    if fixed.flags.common.has_extensions() {
+        extension::read_block(trailer)
+    } else {
+        Ok(Vec::new())
+    }
+}
+
+fn require_extensions(extensions: &[HeaderExtension], understood: &[u8]) -> Result<()> {
+    match extensions
+        .iter()
+        .find(|ext| ext.is_critical() && !understood.contains(&ext.kind))
     {
-        // Quality 5 is a good balance of speed and compression
-        let mut compressor = CompressorWriter::new(&mut compressed, 4096, 5, 22);
-        compressor
-            .write_all(data)
-            .map_err(|e| M2MError::Compression(format!("Brotli compression failed: {}", e)))?;
+        Some(ext) => Err(M2MError::Decompression(format!(
+            "Unsupported critical header extension 0x{:02x}",
+            ext.kind
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Brotli quality of frame payloads carrying `hint`
+pub(crate) fn payload_quality(hint: Option<CompressionHint>) -> u32 {
+    if hint == Some(CompressionHint::Archival) {
+        11
+    } else {
+        5
     }
-    Ok(compressed)
 }
 
-/// Decompress data using Brotli
-fn decompress_brotli(data: &[u8]) -> Result<Vec<u8>> {
-    let mut decompressed = Vec::new();
-    let mut decompressor = Decompressor::new(data, 4096);
-    decompressor
-        .read_to_end(&mut decompressed)
-        .map_err(|e| M2MError::Decompression(format!("Brotli decompression failed: {}", e)))?;
-    Ok(decompressed)
+/// Compress data using Brotli, appending to `out`
+///
+/// Quality 5 is a good balance of speed and compression; archival frames
+/// use 11.
+fn compress_brotli(data: &[u8], quality: u32, out: impl Write) -> Result<()> {
+    // Dropping the writer finishes the stream
+    let mut compressor = CompressorWriter::new(out, 4096, quality, 22);
+    compressor
+        .write_all(data)
+        .map_err(|e| M2MError::Compression(format!("Brotli compression failed: {}", e)))
+}
+
+/// Run an encoder with an output and a scratch buffer from `pool`
+///
+/// The scratch buffer always goes back; the output only on error.
+fn with_pooled(
+    pool: &BufferPool,
+    encode: impl FnOnce(&mut BytesMut, &mut BytesMut) -> Result<()>,
+) -> Result<BytesMut> {
+    let mut buf = pool.get();
+    let mut scratch = pool.get();
+    let result = encode(&mut buf, &mut scratch);
+    pool.put(scratch);
+    match result {
+        Ok(()) => Ok(buf),
+        Err(e) => {
+            pool.put(buf);
+            Err(e)
+        },
+    }
+}
+
+/// Decompress data using Brotli, stopping once the output passes `limits`
+fn decompress_brotli(data: &[u8], limits: &DecompressionLimits) -> Result<Vec<u8>> {
+    limits.read_to_end(data.len(), Decompressor::new(data, 4096))
 }
 
 #[cfg(test)]
@@ -861,6 +1266,154 @@ mod tests {
         assert_eq!(TEST_RESPONSE, decoded);
     }
 
+    /// The v1 decoder: parse the variable header, then skip to `header_len`
+    fn decode_as_v1(data: &[u8]) -> String {
+        let mut pos = M2M_PREFIX.len();
+        let fixed = FixedHeader::from_bytes(&data[pos..]).unwrap();
+        let header = &data[pos + FIXED_HEADER_SIZE..];
+        if fixed.schema.is_request() {
+            RoutingHeader::from_bytes(header, &fixed.flags.request_flags()).unwrap();
+        } else {
+            ResponseHeader::from_bytes(header, &fixed.flags.response_flags()).unwrap();
+        }
+        pos += fixed.header_len as usize;
+
+        let len = u32::from_le_bytes(data[pos..pos + 4].try_into().unwrap()) as usize;
+        let payload = &data[pos + 8..pos + 8 + len];
+        let json = if fixed.flags.is_compressed() {
+            decompress_brotli(payload, &DecompressionLimits::default()).unwrap()
+        } else {
+            payload.to_vec()
+        };
+        String::from_utf8(json).unwrap()
+    }
+
+    #[test]
+    fn test_extension_compatibility_matrix() {
+        let codec = M2MCodec::new();
+        let large = format!(
+            r#"{{"model":"gpt-4o","messages":[{{"role":"user","content":"{}"}}]}}"#,
+            "Hello world! ".repeat(50)
+        );
+        let tenant = HeaderExtension::new(0x01, b"tenant-a".as_slice());
+        let critical = HeaderExtension::new(0x81, vec![7u8; 300]);
+
+        for json in [TEST_REQUEST, large.as_str(), TEST_RESPONSE] {
+            let v1 = codec.frame_for(json).unwrap();
+            let v2 = v1.clone().with_extension(tenant.clone()).unwrap();
+            let v2_critical = v2.clone().with_extension(critical.clone()).unwrap();
+
+            for (name, frame, accepted) in [
+                ("v1", v1, true),
+                ("v2", v2, true),
+                ("v2 critical", v2_critical, false),
+            ] {
+                let wire = frame.encode().unwrap();
+
+                // v1 decoders skip every extension, critical or not
+                assert_eq!(decode_as_v1(&wire), json, "{name}");
+
+                // Current decoders read and preserve them
+                let decoded = M2MFrame::decode(&wire).unwrap();
+                assert_eq!(decoded.payload, json, "{name}");
+                assert_eq!(decoded.extensions().unwrap(), frame.extensions().unwrap());
+                assert_eq!(decoded.encode().unwrap(), wire, "{name}");
+                assert!(decoded.require_extensions(&[0x81]).is_ok());
+
+                // JSON-only decoding understands no critical extension
+                assert_eq!(codec.decode(&wire).is_ok(), accepted, "{name}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_unknown_header_fields_preserved() {
+        let mut frame = M2MFrame::new_request(TEST_REQUEST)
+            .unwrap()
+            .with_extension(HeaderExtension::new(0x01, b"x".as_slice()))
+            .unwrap();
+        // Reserved flag bits from a future sender
+        frame.fixed.flags.reserved = 0x5a;
+        frame.fixed.flags.common.set(1 << 7);
+        let mut wire = frame.encode().unwrap();
+
+        // An extension block from a newer layout is skipped as a whole
+        let block = M2M_PREFIX.len() + frame.fixed.header_len as usize - 4;
+        assert_eq!(wire[block], crate::codec::m2m::M2M_EXPERIMENTAL_VERSION);
+        wire[block] += 1;
+
+        let decoded = M2MFrame::decode(&wire).unwrap();
+        assert_eq!(decoded.payload, TEST_REQUEST);
+        assert!(decoded.extensions().unwrap().is_empty());
+        assert_eq!(decoded.fixed.flags.reserved, 0x5a);
+        assert_eq!(decoded.encode().unwrap(), wire);
+        assert_eq!(M2MCodec::new().decode(&wire).unwrap(), TEST_REQUEST);
+
+        // Extensions need a header to follow
+        let mut headless = M2MFrame::new_request(TEST_REQUEST).unwrap();
+        headless.routing = None;
+        assert!(headless
+            .with_extension(HeaderExtension::new(0x01, Vec::new()))
+            .is_err());
+    }
+
+    #[test]
+    fn test_canonical_roundtrip() {
+        let pretty = r#"{
+            "temperature": 0.70,
+            "model": "gpt-4o",
+            "messages": [{"content": "Hi", "role": "user"}]
+        }"#;
+        let canonical =
+            r#"{"messages":[{"content":"Hi","role":"user"}],"model":"gpt-4o","temperature":0.7}"#;
+
+        let codec = M2MCodec::new().with_canonical(CanonicalMode::Canonical);
+        let wire = codec.encode_string(pretty).unwrap();
+        let frame = M2MFrame::decode_string(&wire).unwrap();
+        assert!(frame.is_canonical());
+        assert_eq!(frame.payload, canonical);
+        assert_eq!(frame.routing.unwrap().model, "gpt-4o");
+
+        // Preserve mode keeps the bytes and leaves the flag clear
+        let frame =
+            M2MFrame::decode_string(&M2MCodec::new().encode_string(pretty).unwrap()).unwrap();
+        assert!(!frame.is_canonical());
+        assert_eq!(frame.payload, pretty);
+    }
+
+    #[test]
+    fn test_frame_hint() {
+        // Small request: compressed only because of the archival hint
+        let small = r#"{"model":"gpt-4o","messages":[{"role":"user","content":"Hi"}]}"#;
+        let frame = M2MFrame::new_request(small).unwrap();
+        assert!(!frame.fixed.flags.is_compressed());
+        let archival = frame.with_hint(CompressionHint::Archival);
+        assert!(archival.fixed.flags.is_compressed());
+
+        let wire = archival.encode_string().unwrap();
+        assert_eq!(M2MFrame::peek_hint(&wire), Some(CompressionHint::Archival));
+        let decoded = M2MFrame::decode_string(&wire).unwrap();
+        assert_eq!(decoded.hint(), Some(CompressionHint::Archival));
+        assert_eq!(decoded.payload, small);
+
+        // Large response: hint suppresses compression
+        let large = TEST_RESPONSE.replace("The answer is 4.", &"four ".repeat(400));
+        let frame = M2MFrame::new_response(&large).unwrap();
+        assert!(frame.fixed.flags.is_compressed());
+        let fast = frame.with_hint(CompressionHint::LatencyCritical);
+        assert!(!fast.fixed.flags.is_compressed());
+        let wire = fast.encode_string().unwrap();
+        assert_eq!(
+            M2MFrame::peek_hint(&wire),
+            Some(CompressionHint::LatencyCritical)
+        );
+        assert_eq!(M2MFrame::decode_string(&wire).unwrap().payload, large);
+
+        let plain = M2MCodec::new().encode_string(TEST_REQUEST).unwrap();
+        assert_eq!(M2MFrame::peek_hint(&plain), None);
+        assert_eq!(M2MFrame::peek_hint("#TK|C|abc"), None);
+    }
+
     #[test]
     fn test_frame_has_correct_schema() {
         let request_frame = M2MFrame::new_request(TEST_REQUEST).unwrap();
@@ -916,6 +1469,54 @@ mod tests {
         assert!(frame.fixed.flags.is_compressed());
     }
 
+    #[test]
+    fn test_pooled_encode_matches() {
+        let pool = BufferPool::new();
+        let large_json = format!(
+            r#"{{"model":"gpt-4o","messages":[{{"role":"user","content":"{}"}}]}}"#,
+            "Hello world! ".repeat(50)
+        );
+
+        for json in [TEST_REQUEST, large_json.as_str(), TEST_REQUEST] {
+            let frame = M2MFrame::new_request(json).unwrap();
+            let pooled = frame.encode_pooled(&pool).unwrap();
+            assert_eq!(&pooled[..], &frame.encode().unwrap()[..]);
+            assert_eq!(M2MFrame::decode(&pooled).unwrap().payload, json);
+            pool.put(pooled);
+        }
+
+        // Output and scratch buffers are allocated once, then reused
+        let stats = pool.stats();
+        assert_eq!(stats.misses, 2);
+        assert_eq!(stats.hits, 4);
+    }
+
+    #[test]
+    fn test_media_heavy_payload_not_compressed() {
+        let image = "iVBORw0KGgo".repeat(200);
+        let json = format!(
+            r#"{{"model":"gpt-4o","messages":[{{"role":"user","content":[{{"type":"text","text":"Describe this"}},{{"type":"image_url","image_url":{{"url":"data:image/png;base64,{}"}}}}]}}],"max_tokens":100}}"#,
+            image
+        );
+        let frame = M2MFrame::new_request(&json).unwrap();
+
+        // Inline image dominates: stored raw, stats in routing header
+        assert!(!frame.fixed.flags.is_compressed());
+        let flags = frame.fixed.flags.request_flags();
+        assert!(flags.has(RequestFlags::HAS_IMAGES));
+        assert!(flags.has(RequestFlags::HAS_MEDIA_STATS));
+
+        let encoded = frame.encode().unwrap();
+        let borrowed = M2MFrame::decode_borrowed(&encoded).unwrap();
+        let routing = borrowed.routing.as_ref().unwrap();
+        let media = routing.media.unwrap();
+        assert_eq!(media.inline_images, 1);
+        assert_eq!(media.inline_bytes, 1650);
+        assert_eq!(routing.max_tokens, Some(100));
+        assert!(routing.est_cost_usd.is_some());
+        assert_eq!(borrowed.payload().unwrap(), json);
+    }
+
     #[test]
     fn test_checksum_verification() {
         let frame = M2MFrame::new_request(TEST_REQUEST).unwrap();
@@ -976,6 +1577,63 @@ mod tests {
         assert_eq!(decoded.payload, TEST_REQUEST);
     }
 
+    #[test]
+    fn test_decode_borrowed_uncompressed_is_zero_copy() {
+        let small_json = r#"{"model":"gpt-4o","messages":[{"role":"user","content":"Hi"}]}"#;
+        let encoded = M2MFrame::new_request(small_json).unwrap().encode().unwrap();
+
+        let frame = M2MFrame::decode_borrowed(&encoded).unwrap();
+        assert!(!frame.is_compressed());
+        assert_eq!(frame.model(), Some("gpt-4o"));
+
+        match frame.payload().unwrap() {
+            Cow::Borrowed(payload) => assert_eq!(payload, small_json),
+            Cow::Owned(_) => panic!("Uncompressed payload should be borrowed"),
+        }
+    }
+
+    #[test]
+    fn test_decode_borrowed_headers_without_payload() {
+        let large_content = "Hello world! ".repeat(50);
+        let large_json = format!(
+            r#"{{"model":"gpt-4o","messages":[{{"role":"system","content":"sys"}},{{"role":"user","content":"{}"}}]}}"#,
+            large_content
+        );
+        let mut encoded = M2MFrame::new_request(&large_json)
+            .unwrap()
+            .encode()
+            .unwrap();
+
+        // Corrupt the payload: headers must still be readable
+        if let Some(last) = encoded.last_mut() {
+            *last ^= 0xFF;
+        }
+
+        let frame = M2MFrame::decode_borrowed(&encoded).unwrap();
+        assert!(frame.is_compressed());
+        let routing = frame.routing.as_ref().unwrap();
+        assert_eq!(routing.model, "gpt-4o");
+        assert_eq!(routing.msg_count, 2);
+
+        // Payload errors surface only when the payload is requested
+        assert!(frame.payload().is_err());
+    }
+
+    #[test]
+    fn test_decode_borrowed_into_owned() {
+        let encoded = M2MFrame::new_response(TEST_RESPONSE)
+            .unwrap()
+            .encode()
+            .unwrap();
+
+        let frame = M2MFrame::decode_borrowed(&encoded).unwrap();
+        assert!(frame.is_response());
+        assert_eq!(frame.model(), Some("gpt-4o"));
+
+        let owned = frame.into_owned().unwrap();
+        assert_eq!(owned.payload, TEST_RESPONSE);
+    }
+
     #[test]
     fn test_binary_vs_base64_size() {
         let frame = M2MFrame::new_request(TEST_REQUEST).unwrap();
@@ -1029,6 +1687,35 @@ mod secure_tests {
         KeyMaterial::new(vec![0x42u8; 32])
     }
 
+    #[test]
+    fn test_expired_key_refused() {
+        use std::time::{Duration, SystemTime};
+
+        let frame = M2MFrame::new_request(TEST_REQUEST).unwrap();
+        let now = SystemTime::now();
+        let mut ctx = SecurityContext::new(test_key());
+        let encoded = frame.encode_secure(SecurityMode::Aead, &mut ctx).unwrap();
+
+        let expired = test_key().with_validity(None, Some(now - Duration::from_secs(5)));
+        let mut expired_ctx = SecurityContext::new(expired);
+        assert!(matches!(
+            frame.encode_secure(SecurityMode::Hmac, &mut expired_ctx),
+            Err(M2MError::KeyExpired(_))
+        ));
+        assert!(matches!(
+            M2MFrame::decode_secure(&encoded, &expired_ctx),
+            Err(M2MError::KeyExpired(_))
+        ));
+        // Plain frames need no key
+        assert!(frame
+            .encode_secure(SecurityMode::None, &mut expired_ctx)
+            .is_ok());
+
+        let future = test_key().with_validity(Some(now + Duration::from_secs(60)), None);
+        let err = M2MFrame::decode_secure(&encoded, &SecurityContext::new(future)).unwrap_err();
+        assert!(err.to_string().contains("not valid for another"));
+    }
+
     #[test]
     fn test_hmac_request_roundtrip() {
         let frame = M2MFrame::new_request(TEST_REQUEST).unwrap();
@@ -1114,6 +1801,60 @@ mod secure_tests {
         assert_eq!(decoded.payload, TEST_REQUEST);
     }
 
+    #[test]
+    fn test_pooled_secure_encode() {
+        let pool = BufferPool::new();
+        let large_json = format!(
+            r#"{{"model":"gpt-4o","messages":[{{"role":"user","content":"{}"}}]}}"#,
+            "Hello world! ".repeat(50)
+        );
+        let mut ctx = SecurityContext::new(test_key());
+        let decode_ctx = SecurityContext::new(test_key());
+
+        for json in [TEST_REQUEST, large_json.as_str()] {
+            let frame = M2MFrame::new_request(json).unwrap();
+
+            let hmac = frame
+                .encode_secure_pooled(SecurityMode::Hmac, &mut ctx, &pool)
+                .unwrap();
+            let expected = frame.encode_secure(SecurityMode::Hmac, &mut ctx).unwrap();
+            assert_eq!(&hmac[..], &expected[..]);
+            pool.put(hmac);
+
+            let aead = frame
+                .encode_secure_pooled(SecurityMode::Aead, &mut ctx, &pool)
+                .unwrap();
+            let decoded = M2MFrame::decode_secure(&aead, &decode_ctx).unwrap();
+            assert_eq!(decoded.payload, json);
+            pool.put(aead);
+        }
+        assert_eq!(pool.stats().misses, 2);
+    }
+
+    #[test]
+    fn test_extensions_authenticated() {
+        let frame = M2MFrame::new_request(TEST_REQUEST)
+            .unwrap()
+            .with_extension(HeaderExtension::new(0x01, b"tenant-a".as_slice()))
+            .unwrap();
+        let mut ctx = SecurityContext::new(test_key());
+        let decode_ctx = SecurityContext::new(test_key());
+
+        for mode in [SecurityMode::Hmac, SecurityMode::Aead] {
+            #[allow(unused_mut)]
+            let mut encoded = frame.encode_secure(mode, &mut ctx).unwrap();
+            let decoded = M2MFrame::decode_secure(&encoded, &decode_ctx).unwrap();
+            assert_eq!(decoded.extensions().unwrap(), frame.extensions().unwrap());
+
+            // Without `crypto` the tag is not checked
+            #[cfg(feature = "crypto")]
+            {
+                encoded[M2M_PREFIX.len() + frame.fixed.header_len as usize - 1] ^= 0xFF;
+                assert!(M2MFrame::decode_secure(&encoded, &decode_ctx).is_err());
+            }
+        }
+    }
+
     #[test]
     fn test_aead_response_roundtrip() {
         let frame = M2MFrame::new_response(TEST_RESPONSE).unwrap();
@@ -14,7 +14,8 @@
 //!   [schema: 1]        Message type (request/response/stream)
 //!   [security: 1]      Security mode (none/hmac/aead)
 //!   [flags: 4]         Feature flags
-//!   [reserved: 12]     Future use
+//!   [reserved: 12]     Future use (fragment info for FRAGMENT frames,
+//!                      see [`Fragment`])
 //!
 //! Routing Header (variable):
 //!   [model_len: 1][model: utf8]
@@ -22,6 +23,10 @@
 //!   [roles: packed bits]
 //!   [content_hint: varint]
 //!   ...additional fields based on flags
+//!   [media: 3 varints]  Image count, inline count, inline bytes
+//!                       (HAS_MEDIA_STATS, after the cost estimate)
+//!   [extensions]        Layout byte + TLVs (HAS_EXTENSIONS, see
+//!                       [`HeaderExtension`])
 //!
 //! Payload:
 //!   [payload_len: 4]
@@ -40,7 +45,7 @@
 //! Enable the `crypto` feature for cryptographic operations:
 //!
 //! ```toml
-//! m2m-core = { version = "0.4", features = ["crypto"] }
+//! m2m-protocol = { version = "0.4", features = ["crypto"] }
 //! ```
 //!
 //! # Example
@@ -61,23 +66,45 @@
 
 mod cost;
 pub mod crypto;
-mod flags;
+mod extension;
+#[cfg(feature = "fec")]
+pub mod fec;
+mod fragment;
 mod frame;
-mod header;
+mod trace;
 mod varint;
 
+// Headers, flags and media stats are shared with the no_std `m2m-core` crate
+use m2m_core::{flags, header, media};
+
 pub use cost::{estimate_cost, ModelPricing};
-pub use flags::{CommonFlags, RequestFlags, ResponseFlags};
-pub use frame::{M2MCodec, M2MFrame};
-pub use header::{FinishReason, FixedHeader, ResponseHeader, RoutingHeader, Schema, SecurityMode};
+pub use extension::HeaderExtension;
+pub use flags::{CommonFlags, CompressionHint, RequestFlags, ResponseFlags};
+pub use fragment::{
+    fragment, is_fragment, Fragment, Reassembler, DEFAULT_MAX_FRAGMENTS,
+    DEFAULT_MAX_PARTIAL_MESSAGES, DEFAULT_REASSEMBLY_LIMIT, FRAGMENT_OVERHEAD, MIN_FRAME_SIZE,
+};
+pub(crate) use frame::payload_quality;
+pub use frame::{M2MCodec, M2MFrame, M2MFrameRef};
+pub use header::{
+    FinishReason, FixedHeader, ResponseHeader, RoutingHeader, Schema, SecurityMode,
+    FIXED_HEADER_SIZE,
+};
+pub use media::{MediaStats, MEDIA_HEAVY_RATIO};
+pub use trace::TraceContext;
 pub use varint::{read_varint, write_varint};
 
-/// M2M wire format prefix
-pub const M2M_PREFIX: &str = "#M2M|1|";
+pub use m2m_core::frame::M2M_PREFIX;
 
 /// M2M wire format version
 pub const M2M_VERSION: u8 = 1;
 
+/// Experimental header layout carrying extension TLVs
+///
+/// Written as the first byte of the extension block, not in the prefix, so
+/// v1 decoders keep reading these frames (see [`HeaderExtension`]).
+pub const M2M_EXPERIMENTAL_VERSION: u8 = 2;
+
 /// Minimum payload size to apply compression (bytes)
 /// Below this threshold, raw JSON is more efficient
 pub const COMPRESSION_THRESHOLD: usize = 100;
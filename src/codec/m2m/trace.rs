@@ -0,0 +1,176 @@
+//! W3C trace context carried in a header extension.
+//!
+//! A frame may carry the sender's `traceparent` so distributed traces span
+//! agents: receivers continue the trace instead of starting a new one, and
+//! the M2M hop shows up in APM tools. The value travels as a non-critical
+//! [`HeaderExtension`] of kind [`TraceContext::EXTENSION_KIND`], so decoders
+//! that predate it simply skip it.
+//!
+//! ```text
+//! [version: 1][trace_id: 16][parent_id: 8][trace_flags: 1]
+//! ```
+//!
+//! `tracestate` is not carried.
+
+use std::fmt;
+use std::str::FromStr;
+
+use super::HeaderExtension;
+use crate::error::M2MError;
+
+/// Size of the binary trace context value
+const ENCODED_SIZE: usize = 26;
+
+/// W3C trace context (`traceparent`, version `00`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TraceContext {
+    /// Trace ID shared by every span of the trace
+    pub trace_id: [u8; 16],
+    /// Span ID of the sender's span
+    pub parent_id: [u8; 8],
+    /// Trace flags (bit 0: sampled)
+    pub flags: u8,
+}
+
+impl TraceContext {
+    /// Header extension kind carrying the trace context
+    pub const EXTENSION_KIND: u8 = 0x01;
+
+    /// Name of the HTTP header carrying the trace context
+    pub const HEADER: &'static str = "traceparent";
+
+    /// Create a trace context
+    pub fn new(trace_id: [u8; 16], parent_id: [u8; 8], flags: u8) -> Self {
+        Self {
+            trace_id,
+            parent_id,
+            flags,
+        }
+    }
+
+    /// Whether the sender recorded its span
+    pub fn is_sampled(&self) -> bool {
+        self.flags & 0x01 != 0
+    }
+
+    /// Header extension carrying this trace context
+    pub fn to_extension(&self) -> HeaderExtension {
+        let mut value = Vec::with_capacity(ENCODED_SIZE);
+        value.push(0);
+        value.extend_from_slice(&self.trace_id);
+        value.extend_from_slice(&self.parent_id);
+        value.push(self.flags);
+        HeaderExtension::new(Self::EXTENSION_KIND, value)
+    }
+
+    /// Trace context from the first well-formed extension of the right kind
+    pub fn from_extensions(extensions: &[HeaderExtension]) -> Option<Self> {
+        extensions
+            .iter()
+            .filter(|ext| ext.kind == Self::EXTENSION_KIND)
+            .find_map(|ext| Self::from_value(&ext.value))
+    }
+
+    fn from_value(value: &[u8]) -> Option<Self> {
+        // Later versions may append fields; their prefix stays readable
+        if value.len() < ENCODED_SIZE || value[0] == 0xFF {
+            return None;
+        }
+        Some(Self::new(
+            value[1..17].try_into().ok()?,
+            value[17..25].try_into().ok()?,
+            value[25],
+        ))
+        .filter(Self::is_valid)
+    }
+
+    /// All-zero IDs are invalid per the W3C spec
+    fn is_valid(&self) -> bool {
+        self.trace_id != [0; 16] && self.parent_id != [0; 8]
+    }
+}
+
+impl fmt::Display for TraceContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("00-")?;
+        self.trace_id
+            .iter()
+            .try_for_each(|b| write!(f, "{b:02x}"))?;
+        f.write_str("-")?;
+        self.parent_id
+            .iter()
+            .try_for_each(|b| write!(f, "{b:02x}"))?;
+        write!(f, "-{:02x}", self.flags)
+    }
+}
+
+impl FromStr for TraceContext {
+    type Err = M2MError;
+
+    /// Parse a `traceparent` header value
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || M2MError::Protocol(format!("Invalid traceparent: {s}"));
+        let mut parts = s.trim().split('-');
+        let (Some(version), Some(trace_id), Some(parent_id), Some(flags)) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return Err(invalid());
+        };
+        // Version 00 has exactly four fields; later versions may add more
+        if hex::<1>(version).is_none()
+            || version == "ff"
+            || (version == "00" && parts.next().is_some())
+        {
+            return Err(invalid());
+        }
+
+        Some(Self::new(
+            hex(trace_id).ok_or_else(invalid)?,
+            hex(parent_id).ok_or_else(invalid)?,
+            hex::<1>(flags).ok_or_else(invalid)?[0],
+        ))
+        .filter(Self::is_valid)
+        .ok_or_else(invalid)
+    }
+}
+
+/// Decode exactly `N` bytes of lowercase hex
+fn hex<const N: usize>(s: &str) -> Option<[u8; N]> {
+    if s.len() != 2 * N || !s.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f')) {
+        return None;
+    }
+    let mut out = [0u8; N];
+    for (byte, pair) in out.iter_mut().zip(s.as_bytes().chunks(2)) {
+        *byte = u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok()?;
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TRACEPARENT: &str = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+
+    #[test]
+    fn test_traceparent_roundtrip() {
+        let context: TraceContext = TRACEPARENT.parse().unwrap();
+        assert!(context.is_sampled());
+        assert_eq!(context.to_string(), TRACEPARENT);
+
+        let ext = context.to_extension();
+        assert!(!ext.is_critical());
+        assert_eq!(TraceContext::from_extensions(&[ext]), Some(context));
+
+        for invalid in [
+            "",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7",
+            "00-4BF92F3577B34DA6A3CE929D0E0E4736-00f067aa0ba902b7-01",
+            "00-00000000000000000000000000000000-00f067aa0ba902b7-01",
+            "ff-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-extra",
+        ] {
+            assert!(invalid.parse::<TraceContext>().is_err(), "{invalid}");
+        }
+    }
+}
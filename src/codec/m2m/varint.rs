@@ -5,12 +5,18 @@
 //! - 128-16383: 2 bytes
 //! - 16384-2097151: 3 bytes
 //! - etc.
+//!
+//! Slice and `Vec` helpers live in the `no_std` core (`m2m_core::varint`);
+//! this adds the `std::io` reader and writer forms.
 
 #![allow(missing_docs)]
 
-use crate::error::{M2MError, Result};
 use std::io::{Read, Write};
 
+pub use m2m_core::varint::{read_varint_slice, varint_size, write_varint_vec};
+
+use crate::error::{M2MError, Result};
+
 /// Write a variable-length integer to a buffer
 pub fn write_varint<W: Write>(writer: &mut W, mut value: u64) -> Result<usize> {
     let mut bytes_written = 0;
@@ -31,21 +37,6 @@ pub fn write_varint<W: Write>(writer: &mut W, mut value: u64) -> Result<usize> {
     Ok(bytes_written)
 }
 
-/// Write a variable-length integer to a Vec<u8>
-pub fn write_varint_vec(buf: &mut Vec<u8>, mut value: u64) {
-    loop {
-        let mut byte = (value & 0x7F) as u8;
-        value >>= 7;
-        if value != 0 {
-            byte |= 0x80;
-        }
-        buf.push(byte);
-        if value == 0 {
-            break;
-        }
-    }
-}
-
 /// Read a variable-length integer from a reader
 pub fn read_varint<R: Read>(reader: &mut R) -> Result<u64> {
     let mut result: u64 = 0;
@@ -72,121 +63,20 @@ pub fn read_varint<R: Read>(reader: &mut R) -> Result<u64> {
     Ok(result)
 }
 
-/// Read a variable-length integer from a byte slice, returning (value, bytes_consumed)
-pub fn read_varint_slice(data: &[u8]) -> Result<(u64, usize)> {
-    let mut result: u64 = 0;
-    let mut shift = 0;
-    let mut pos = 0;
-
-    loop {
-        if pos >= data.len() {
-            return Err(M2MError::Decompression(
-                "VarInt: unexpected end of data".to_string(),
-            ));
-        }
-
-        let byte = data[pos];
-        pos += 1;
-
-        result |= ((byte & 0x7F) as u64) << shift;
-
-        if byte & 0x80 == 0 {
-            break;
-        }
-
-        shift += 7;
-        if shift >= 64 {
-            return Err(M2MError::Decompression("VarInt overflow".to_string()));
-        }
-    }
-
-    Ok((result, pos))
-}
-
-/// Calculate the number of bytes needed to encode a value as VarInt
-pub fn varint_size(value: u64) -> usize {
-    if value == 0 {
-        return 1;
-    }
-    let bits = 64 - value.leading_zeros() as usize;
-    (bits + 6) / 7 // Ceiling division by 7
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
     use std::io::Cursor;
 
     #[test]
-    fn test_varint_small() {
-        let mut buf = Vec::new();
-        write_varint_vec(&mut buf, 0);
-        assert_eq!(buf, vec![0]);
-
-        buf.clear();
-        write_varint_vec(&mut buf, 127);
-        assert_eq!(buf, vec![127]);
-
-        buf.clear();
-        write_varint_vec(&mut buf, 1);
-        assert_eq!(buf, vec![1]);
-    }
-
-    #[test]
-    fn test_varint_medium() {
-        let mut buf = Vec::new();
-        write_varint_vec(&mut buf, 128);
-        assert_eq!(buf, vec![0x80, 0x01]);
-
-        buf.clear();
-        write_varint_vec(&mut buf, 300);
-        assert_eq!(buf, vec![0xAC, 0x02]);
-    }
-
-    #[test]
-    fn test_varint_roundtrip() {
-        let test_values = [
-            0,
-            1,
-            127,
-            128,
-            255,
-            256,
-            16383,
-            16384,
-            2097151,
-            2097152,
-            u64::MAX,
-        ];
-
-        for &value in &test_values {
+    fn test_varint_io_roundtrip() {
+        for value in [0, 1, 127, 128, 16384, u64::MAX] {
             let mut buf = Vec::new();
-            write_varint_vec(&mut buf, value);
-
-            let mut cursor = Cursor::new(&buf);
-            let decoded = read_varint(&mut cursor).unwrap();
+            let written = write_varint(&mut buf, value).unwrap();
+            assert_eq!(written, varint_size(value));
 
+            let decoded = read_varint(&mut Cursor::new(&buf)).unwrap();
             assert_eq!(value, decoded, "Roundtrip failed for value {}", value);
         }
     }
-
-    #[test]
-    fn test_varint_slice() {
-        let mut buf = Vec::new();
-        write_varint_vec(&mut buf, 12345);
-        buf.extend_from_slice(b"extra data");
-
-        let (value, consumed) = read_varint_slice(&buf).unwrap();
-        assert_eq!(value, 12345);
-        assert!(consumed < buf.len());
-    }
-
-    #[test]
-    fn test_varint_size() {
-        assert_eq!(varint_size(0), 1);
-        assert_eq!(varint_size(127), 1);
-        assert_eq!(varint_size(128), 2);
-        assert_eq!(varint_size(16383), 2);
-        assert_eq!(varint_size(16384), 3);
-    }
 }
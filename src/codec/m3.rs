@@ -40,6 +40,12 @@
 //! | `{"role":"user","content":"` | 7 | 1 | 86% |
 //! | Content | N | N | 0% |
 //! | `"}],"temperature":0.7}` | 8 | ~2 | 75% |
+//!
+//! # Incremental Encoding
+//!
+//! [`M3StreamEncoder`] appends messages to an in-flight frame without
+//! re-encoding earlier messages, and [`M3StreamDecoder`] yields messages
+//! lazily from a frame.
 
 use std::io::{Cursor, Read};
 
@@ -76,9 +82,13 @@ impl Schema {
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u8)]
 pub enum Role {
+    /// System prompt
     System = 0,
+    /// User turn
     User = 1,
+    /// Assistant turn
     Assistant = 2,
+    /// Tool result
     Tool = 3,
 }
 
@@ -187,192 +197,12 @@ impl M3Codec {
 
     /// Encode a chat completion request to M3 wire format
     pub fn encode_request(&self, req: &M3ChatRequest) -> Result<Vec<u8>> {
-        let mut buf = Vec::with_capacity(256);
-
-        // Write prefix
-        buf.extend_from_slice(M3_PREFIX.as_bytes());
-
-        // Schema byte
-        buf.push(Schema::ChatCompletionRequest as u8);
-
-        // Model (length-prefixed)
-        write_varint(&mut buf, req.model.len() as u64);
-        buf.extend_from_slice(req.model.as_bytes());
-
-        // Flags
-        let mut flags = ParamFlags::new();
-        if req.temperature.is_some() {
-            flags.set(ParamFlags::HAS_TEMPERATURE);
-        }
-        if req.max_tokens.is_some() {
-            flags.set(ParamFlags::HAS_MAX_TOKENS);
-        }
-        if req.top_p.is_some() {
-            flags.set(ParamFlags::HAS_TOP_P);
-        }
-        if req.stream {
-            flags.set(ParamFlags::STREAM);
-        }
-        if req.stop.is_some() {
-            flags.set(ParamFlags::HAS_STOP);
-        }
-        buf.push(flags.as_byte());
-
-        // Number of messages
-        write_varint(&mut buf, req.messages.len() as u64);
-
-        // Messages
-        for msg in &req.messages {
-            buf.push(msg.role as u8);
-            write_varint(&mut buf, msg.content.len() as u64);
-            buf.extend_from_slice(msg.content.as_bytes());
-        }
-
-        // Optional parameters
-        if let Some(temp) = req.temperature {
-            // Quantize to 0-100 range (0.01 precision)
-            let quantized = (temp * 100.0).round() as u8;
-            buf.push(quantized);
-        }
-        if let Some(max_tok) = req.max_tokens {
-            write_varint(&mut buf, max_tok as u64);
-        }
-        if let Some(top_p) = req.top_p {
-            let quantized = (top_p * 100.0).round() as u8;
-            buf.push(quantized);
-        }
-        // Stop sequences (if any)
-        if let Some(ref stops) = req.stop {
-            write_varint(&mut buf, stops.len() as u64);
-            for stop in stops {
-                write_varint(&mut buf, stop.len() as u64);
-                buf.extend_from_slice(stop.as_bytes());
-            }
-        }
-
-        Ok(buf)
+        Ok(M3StreamEncoder::from_request(req).frame())
     }
 
     /// Decode M3 wire format to chat completion request
     pub fn decode_request(&self, data: &[u8]) -> Result<M3ChatRequest> {
-        // Check prefix
-        if !data.starts_with(M3_PREFIX.as_bytes()) {
-            return Err(M2MError::Decompression("Invalid M3 prefix".to_string()));
-        }
-
-        let mut cursor = Cursor::new(&data[M3_PREFIX.len()..]);
-
-        // Schema byte
-        let mut schema_byte = [0u8; 1];
-        cursor
-            .read_exact(&mut schema_byte)
-            .map_err(|e| M2MError::Decompression(e.to_string()))?;
-
-        if Schema::from_byte(schema_byte[0]) != Some(Schema::ChatCompletionRequest) {
-            return Err(M2MError::Decompression(format!(
-                "Expected ChatCompletionRequest schema, got {:02x}",
-                schema_byte[0]
-            )));
-        }
-
-        // Model
-        let model_len = read_varint(&mut cursor)? as usize;
-        let mut model_bytes = vec![0u8; model_len];
-        cursor
-            .read_exact(&mut model_bytes)
-            .map_err(|e| M2MError::Decompression(e.to_string()))?;
-        let model =
-            String::from_utf8(model_bytes).map_err(|e| M2MError::Decompression(e.to_string()))?;
-
-        // Flags
-        let mut flags_byte = [0u8; 1];
-        cursor
-            .read_exact(&mut flags_byte)
-            .map_err(|e| M2MError::Decompression(e.to_string()))?;
-        let flags = ParamFlags::from_byte(flags_byte[0]);
-
-        // Number of messages
-        let num_messages = read_varint(&mut cursor)? as usize;
-
-        // Messages
-        let mut messages = Vec::with_capacity(num_messages);
-        for _ in 0..num_messages {
-            let mut role_byte = [0u8; 1];
-            cursor
-                .read_exact(&mut role_byte)
-                .map_err(|e| M2MError::Decompression(e.to_string()))?;
-            let role = Role::from_byte(role_byte[0])
-                .ok_or_else(|| M2MError::Decompression("Invalid role byte".to_string()))?;
-
-            let content_len = read_varint(&mut cursor)? as usize;
-            let mut content_bytes = vec![0u8; content_len];
-            cursor
-                .read_exact(&mut content_bytes)
-                .map_err(|e| M2MError::Decompression(e.to_string()))?;
-            let content = String::from_utf8(content_bytes)
-                .map_err(|e| M2MError::Decompression(e.to_string()))?;
-
-            messages.push(M3Message {
-                role,
-                content,
-                name: None,
-            });
-        }
-
-        // Optional parameters
-        let temperature = if flags.has(ParamFlags::HAS_TEMPERATURE) {
-            let mut temp_byte = [0u8; 1];
-            cursor
-                .read_exact(&mut temp_byte)
-                .map_err(|e| M2MError::Decompression(e.to_string()))?;
-            Some(temp_byte[0] as f32 / 100.0)
-        } else {
-            None
-        };
-
-        let max_tokens = if flags.has(ParamFlags::HAS_MAX_TOKENS) {
-            Some(read_varint(&mut cursor)? as u32)
-        } else {
-            None
-        };
-
-        let top_p = if flags.has(ParamFlags::HAS_TOP_P) {
-            let mut top_p_byte = [0u8; 1];
-            cursor
-                .read_exact(&mut top_p_byte)
-                .map_err(|e| M2MError::Decompression(e.to_string()))?;
-            Some(top_p_byte[0] as f32 / 100.0)
-        } else {
-            None
-        };
-
-        let stop = if flags.has(ParamFlags::HAS_STOP) {
-            let num_stops = read_varint(&mut cursor)? as usize;
-            let mut stops = Vec::with_capacity(num_stops);
-            for _ in 0..num_stops {
-                let stop_len = read_varint(&mut cursor)? as usize;
-                let mut stop_bytes = vec![0u8; stop_len];
-                cursor
-                    .read_exact(&mut stop_bytes)
-                    .map_err(|e| M2MError::Decompression(e.to_string()))?;
-                let stop_str = String::from_utf8(stop_bytes)
-                    .map_err(|e| M2MError::Decompression(e.to_string()))?;
-                stops.push(stop_str);
-            }
-            Some(stops)
-        } else {
-            None
-        };
-
-        Ok(M3ChatRequest {
-            model,
-            messages,
-            temperature,
-            max_tokens,
-            top_p,
-            stream: flags.has(ParamFlags::STREAM),
-            stop,
-        })
+        M3StreamDecoder::new(data)?.into_request()
     }
 
     /// Parse JSON to M3ChatRequest
@@ -520,6 +350,305 @@ impl M3Codec {
     }
 }
 
+/// Incremental M3 request encoder
+///
+/// Keeps the header, message section and parameter section as separate
+/// buffers so appending a message only encodes that message. [`frame`]
+/// concatenates the sections into a wire frame identical to
+/// [`M3Codec::encode_request`].
+///
+/// [`frame`]: M3StreamEncoder::frame
+#[derive(Debug, Clone)]
+pub struct M3StreamEncoder {
+    /// Prefix, schema, model and flags
+    header: Vec<u8>,
+    /// Encoded messages
+    messages: Vec<u8>,
+    /// Number of encoded messages
+    count: usize,
+    /// Encoded optional parameters
+    params: Vec<u8>,
+}
+
+impl M3StreamEncoder {
+    /// Create an encoder for a model with no optional parameters
+    pub fn new(model: &str) -> Self {
+        Self::from_request(&M3ChatRequest {
+            model: model.to_string(),
+            ..Default::default()
+        })
+    }
+
+    /// Create an encoder seeded with a request's model, parameters and messages
+    pub fn from_request(req: &M3ChatRequest) -> Self {
+        let mut header = Vec::with_capacity(M3_PREFIX.len() + req.model.len() + 4);
+        header.extend_from_slice(M3_PREFIX.as_bytes());
+        header.push(Schema::ChatCompletionRequest as u8);
+
+        // Model (length-prefixed)
+        write_varint(&mut header, req.model.len() as u64);
+        header.extend_from_slice(req.model.as_bytes());
+
+        // Flags
+        let mut flags = ParamFlags::new();
+        if req.temperature.is_some() {
+            flags.set(ParamFlags::HAS_TEMPERATURE);
+        }
+        if req.max_tokens.is_some() {
+            flags.set(ParamFlags::HAS_MAX_TOKENS);
+        }
+        if req.top_p.is_some() {
+            flags.set(ParamFlags::HAS_TOP_P);
+        }
+        if req.stream {
+            flags.set(ParamFlags::STREAM);
+        }
+        if req.stop.is_some() {
+            flags.set(ParamFlags::HAS_STOP);
+        }
+        header.push(flags.as_byte());
+
+        // Optional parameters
+        let mut params = Vec::new();
+        if let Some(temp) = req.temperature {
+            // Quantize to 0-100 range (0.01 precision)
+            let quantized = (temp * 100.0).round() as u8;
+            params.push(quantized);
+        }
+        if let Some(max_tok) = req.max_tokens {
+            write_varint(&mut params, max_tok as u64);
+        }
+        if let Some(top_p) = req.top_p {
+            let quantized = (top_p * 100.0).round() as u8;
+            params.push(quantized);
+        }
+        // Stop sequences (if any)
+        if let Some(ref stops) = req.stop {
+            write_varint(&mut params, stops.len() as u64);
+            for stop in stops {
+                write_varint(&mut params, stop.len() as u64);
+                params.extend_from_slice(stop.as_bytes());
+            }
+        }
+
+        let mut encoder = Self {
+            header,
+            messages: Vec::with_capacity(256),
+            count: 0,
+            params,
+        };
+        for msg in &req.messages {
+            encoder.push(msg);
+        }
+        encoder
+    }
+
+    /// Append a message (only the new message is encoded)
+    pub fn push(&mut self, msg: &M3Message) -> &mut Self {
+        self.messages.push(msg.role as u8);
+        write_varint(&mut self.messages, msg.content.len() as u64);
+        self.messages.extend_from_slice(msg.content.as_bytes());
+        self.count += 1;
+        self
+    }
+
+    /// Number of messages appended so far
+    pub fn message_count(&self) -> usize {
+        self.count
+    }
+
+    /// Size of the frame [`frame`](Self::frame) would produce
+    pub fn encoded_len(&self) -> usize {
+        let mut count = Vec::with_capacity(10);
+        write_varint(&mut count, self.count as u64);
+        self.header.len() + count.len() + self.messages.len() + self.params.len()
+    }
+
+    /// Assemble the current wire frame
+    pub fn frame(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(self.encoded_len());
+        buf.extend_from_slice(&self.header);
+        write_varint(&mut buf, self.count as u64);
+        buf.extend_from_slice(&self.messages);
+        buf.extend_from_slice(&self.params);
+        buf
+    }
+}
+
+/// Lazy M3 request decoder
+///
+/// Parses the header up front and yields [`M3Message`] items one at a time,
+/// so a consumer can stop early without decoding the whole conversation.
+#[derive(Debug)]
+pub struct M3StreamDecoder<'a> {
+    /// Payload after the prefix
+    cursor: Cursor<&'a [u8]>,
+    /// Model identifier
+    model: String,
+    /// Optional parameter flags
+    flags: ParamFlags,
+    /// Total messages in the frame
+    total: usize,
+    /// Messages not yet yielded
+    remaining: usize,
+}
+
+impl<'a> M3StreamDecoder<'a> {
+    /// Parse the frame header
+    pub fn new(data: &'a [u8]) -> Result<Self> {
+        // Check prefix
+        if !data.starts_with(M3_PREFIX.as_bytes()) {
+            return Err(M2MError::Decompression("Invalid M3 prefix".to_string()));
+        }
+
+        let mut cursor = Cursor::new(&data[M3_PREFIX.len()..]);
+
+        // Schema byte
+        let schema_byte = read_byte(&mut cursor)?;
+        if Schema::from_byte(schema_byte) != Some(Schema::ChatCompletionRequest) {
+            return Err(M2MError::Decompression(format!(
+                "Expected ChatCompletionRequest schema, got {schema_byte:02x}"
+            )));
+        }
+
+        // Model
+        let model = read_string(&mut cursor)?;
+
+        // Flags
+        let flags = ParamFlags::from_byte(read_byte(&mut cursor)?);
+
+        // Number of messages
+        let total = read_varint(&mut cursor)? as usize;
+
+        Ok(Self {
+            cursor,
+            model,
+            flags,
+            total,
+            remaining: total,
+        })
+    }
+
+    /// Model identifier
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
+    /// Total number of messages in the frame
+    pub fn message_count(&self) -> usize {
+        self.total
+    }
+
+    /// Whether the request asks for streaming
+    pub fn stream(&self) -> bool {
+        self.flags.has(ParamFlags::STREAM)
+    }
+
+    /// Decode the remaining messages and the trailing parameters
+    ///
+    /// Messages already yielded by the iterator are not included.
+    pub fn into_request(mut self) -> Result<M3ChatRequest> {
+        let messages = self.by_ref().collect::<Result<Vec<_>>>()?;
+        let cursor = &mut self.cursor;
+        let flags = self.flags;
+
+        // Optional parameters
+        let temperature = if flags.has(ParamFlags::HAS_TEMPERATURE) {
+            Some(read_byte(cursor)? as f32 / 100.0)
+        } else {
+            None
+        };
+
+        let max_tokens = if flags.has(ParamFlags::HAS_MAX_TOKENS) {
+            Some(read_varint(cursor)? as u32)
+        } else {
+            None
+        };
+
+        let top_p = if flags.has(ParamFlags::HAS_TOP_P) {
+            Some(read_byte(cursor)? as f32 / 100.0)
+        } else {
+            None
+        };
+
+        let stop = if flags.has(ParamFlags::HAS_STOP) {
+            let num_stops = read_varint(cursor)? as usize;
+            let mut stops = Vec::with_capacity(num_stops.min(64));
+            for _ in 0..num_stops {
+                stops.push(read_string(cursor)?);
+            }
+            Some(stops)
+        } else {
+            None
+        };
+
+        Ok(M3ChatRequest {
+            model: self.model,
+            messages,
+            temperature,
+            max_tokens,
+            top_p,
+            stream: flags.has(ParamFlags::STREAM),
+            stop,
+        })
+    }
+
+    fn read_message(&mut self) -> Result<M3Message> {
+        let role = Role::from_byte(read_byte(&mut self.cursor)?)
+            .ok_or_else(|| M2MError::Decompression("Invalid role byte".to_string()))?;
+        let content = read_string(&mut self.cursor)?;
+
+        Ok(M3Message {
+            role,
+            content,
+            name: None,
+        })
+    }
+}
+
+impl Iterator for M3StreamDecoder<'_> {
+    type Item = Result<M3Message>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+
+        let msg = self.read_message();
+        // Stop after a malformed message; later offsets are meaningless
+        self.remaining = if msg.is_ok() { self.remaining - 1 } else { 0 };
+        Some(msg)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.remaining))
+    }
+}
+
+fn read_byte<R: Read>(reader: &mut R) -> Result<u8> {
+    let mut byte = [0u8; 1];
+    reader
+        .read_exact(&mut byte)
+        .map_err(|e| M2MError::Decompression(e.to_string()))?;
+    Ok(byte[0])
+}
+
+fn read_string(cursor: &mut Cursor<&[u8]>) -> Result<String> {
+    let len = read_varint(cursor)? as usize;
+    let available = cursor.get_ref().len() - cursor.position() as usize;
+    if len > available {
+        return Err(M2MError::Decompression(format!(
+            "String length {len} exceeds remaining {available} bytes"
+        )));
+    }
+
+    let mut bytes = vec![0u8; len];
+    cursor
+        .read_exact(&mut bytes)
+        .map_err(|e| M2MError::Decompression(e.to_string()))?;
+    String::from_utf8(bytes).map_err(|e| M2MError::Decompression(e.to_string()))
+}
+
 // VarInt encoding (LEB128)
 fn write_varint(buf: &mut Vec<u8>, mut value: u64) {
     loop {
@@ -648,6 +777,73 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_stream_encoder_matches_batch() {
+        let codec = M3Codec::new();
+        let mut req = M3ChatRequest {
+            model: "gpt-4o".to_string(),
+            temperature: Some(0.5),
+            stop: Some(vec!["END".to_string()]),
+            ..Default::default()
+        };
+        let mut encoder = M3StreamEncoder::from_request(&req);
+
+        for (i, role) in [Role::System, Role::User, Role::Assistant]
+            .iter()
+            .enumerate()
+        {
+            let msg = M3Message {
+                role: *role,
+                content: format!("message {i}"),
+                name: None,
+            };
+            encoder.push(&msg);
+            req.messages.push(msg);
+
+            let frame = encoder.frame();
+            assert_eq!(frame, codec.encode_request(&req).unwrap());
+            assert_eq!(frame.len(), encoder.encoded_len());
+        }
+        assert_eq!(encoder.message_count(), 3);
+    }
+
+    #[test]
+    fn test_stream_decoder() {
+        let codec = M3Codec::new();
+        let mut encoder = M3StreamEncoder::new("gpt-4o");
+        encoder
+            .push(&M3Message {
+                role: Role::User,
+                content: "first".to_string(),
+                name: None,
+            })
+            .push(&M3Message {
+                role: Role::Assistant,
+                content: "second".to_string(),
+                name: None,
+            });
+        let frame = encoder.frame();
+
+        let mut decoder = M3StreamDecoder::new(&frame).unwrap();
+        assert_eq!(decoder.model(), "gpt-4o");
+        assert_eq!(decoder.message_count(), 2);
+        let first = decoder.next().unwrap().unwrap();
+        assert_eq!(first.role, Role::User);
+        assert_eq!(first.content, "first");
+
+        // Remaining messages and params
+        let rest = decoder.into_request().unwrap();
+        assert_eq!(rest.messages.len(), 1);
+        assert_eq!(rest.messages[0].content, "second");
+
+        // Truncated frames yield an error and stop
+        let truncated = &frame[..frame.len() - 3];
+        let results: Vec<_> = M3StreamDecoder::new(truncated).unwrap().collect();
+        assert_eq!(results.len(), 2);
+        assert!(results[1].is_err());
+        assert!(codec.decode_request(truncated).is_err());
+    }
+
     #[test]
     fn test_varint_encoding() {
         let mut buf = Vec::new();
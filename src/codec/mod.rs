@@ -11,11 +11,13 @@
 //! | [`TokenNative`] | `#TK\|`           | Legacy token-based compression  |
 //! | [`Brotli`]   | `#M2M[v3.0]\|DATA:`  | Large repetitive content (>1KB) |
 //! | [`None`]     | (passthrough)        | Small content (<100 bytes)      |
+//! | [`Custom`]   | `#CX\|<id>\|`         | Proprietary codecs (WASM plugin)|
 //!
 //! # M2M Wire Format v1
 //!
 //! The new M2M wire format provides:
-//! - **100% JSON fidelity**: Original JSON is perfectly reconstructed
+//! - **100% JSON fidelity**: Original JSON is perfectly reconstructed, or
+//!   canonicalized when byte identity is not needed (see [`canonical`])
 //! - **Header extraction**: Routing info available without decompression
 //! - **Cost estimation**: Token counts and cost in headers
 //! - **Optional encryption**: HMAC or AEAD security modes
@@ -29,6 +31,9 @@
 //! // Legacy formats (still supported for decoding)
 //! #TK|C|<varint_tokens>
 //! #M2M[v3.0]|DATA:<base64_brotli>
+//!
+//! // Protocol v2.0 (decoded only with the `compat-v2` feature, deprecated)
+//! #M2M[v2.0]|DATA:<base64_zlib>
 //! ```
 //!
 //! # Usage
@@ -48,37 +53,124 @@
 //! let original = engine.decompress(&result.data)?;
 //! ```
 //!
+//! # Cargo Features
+//!
+//! Only M2M and passthrough are always compiled (`codec-core`, the default).
+//! The other codecs are opt-in, so embedded and WASM builds do not pull in
+//! tokenizer vocabularies they never use:
+//!
+//! | Feature        | Adds                                          |
+//! |----------------|-----------------------------------------------|
+//! | `brotli`       | [`Brotli`] algorithm (`BrotliCodec`)          |
+//! | `token-native` | [`TokenNative`] algorithm (implies `tiktoken`)|
+//! | `m3`           | M3 schema-aware encoding (`M3Codec`)          |
+//! | `dictionary`   | Dictionary pattern compression                |
+//! | `codecs`       | All of the above                              |
+//! | `wasm-plugins` | [`Custom`] algorithms from WASM modules (`WasmCodec`) |
+//! | `simd`         | SIMD base64 for M2M string frames and Brotli  |
+//!
+//! Algorithms left out are not advertised in [`Capabilities`], never agreed
+//! on during negotiation (a peer offering only those gets a
+//! `NoCommonAlgorithm` REJECT), never picked by automatic selection, and
+//! fail with [`M2MError::InvalidCodec`] when requested explicitly. See
+//! [`Algorithm::is_available`].
+//!
+//! [`Capabilities`]: crate::protocol::Capabilities
+//! [`M2MError::InvalidCodec`]: crate::error::M2MError::InvalidCodec
 //! [`M2M`]: Algorithm::M2M
 //! [`TokenNative`]: Algorithm::TokenNative
 //! [`Brotli`]: Algorithm::Brotli
+//! [`Custom`]: Algorithm::Custom
 //! [`None`]: Algorithm::None
 
+mod abbrev;
 mod algorithm;
+mod b64;
+mod breakdown;
+#[cfg(feature = "brotli")]
 mod brotli;
-mod dictionary;
+pub mod canonical;
+#[cfg(feature = "compat-v2")]
+mod compat_v2;
+mod corpus;
+mod defaults;
 mod engine;
+mod feedback;
+mod history;
+mod limits;
 pub mod m2m;
+#[cfg(feature = "m3")]
 mod m3;
+#[cfg(feature = "wasm-plugins")]
+mod plugin;
+mod pool;
+mod profile;
+mod schema;
+mod service;
+mod shared_dict;
 mod streaming;
-mod tables;
-mod token;
+#[cfg(feature = "token-native")]
 mod token_native;
+mod tools;
+
+// Tables and the Token/Dictionary codecs live in the no_std `m2m-core` crate
+use m2m_core::tables;
 
+pub use abbrev::{AbbreviationTable, BUILTIN_TABLE_VERSION};
 pub use algorithm::{Algorithm, CompressionResult};
+pub use breakdown::{CompressionBreakdown, ModelSavings};
+#[cfg(feature = "brotli")]
 pub use brotli::BrotliCodec;
-pub use dictionary::DictionaryCodec;
+pub use canonical::CanonicalMode;
+#[cfg(feature = "compat-v2")]
+pub use compat_v2::{V2Usage, ZlibCodec};
+pub use corpus::{AlgorithmSavings, CorpusReport};
+pub use defaults::DefaultsNormalizer;
 pub use engine::{CodecEngine, ContentAnalysis};
-pub use m2m::{M2MCodec, M2MFrame};
-pub use m3::{M3ChatRequest, M3Codec, M3Message, M3_PREFIX};
+pub use feedback::{
+    FeedbackSample, RouterFeedback, RouterThresholds, DEFAULT_FEEDBACK_CAPACITY,
+    DEFAULT_PROBE_INTERVAL, DEFAULT_REFIT_INTERVAL,
+};
+pub use history::{
+    HistoryRefs, HistoryReport, HistoryWindow, CONTEXT_NAME, DEFAULT_EXCERPT_CHARS,
+    DEFAULT_KEEP_RECENT,
+};
+pub use limits::{
+    DecompressionLimits, DEFAULT_MAX_DECOMPRESSED_SIZE, DEFAULT_MAX_EXPANSION_RATIO,
+    RATIO_EXEMPT_SIZE,
+};
+pub use m2m::{CompressionHint, M2MCodec, M2MFrame, M2MFrameRef, TraceContext};
+#[cfg(feature = "dictionary")]
+pub use m2m_core::dictionary::DictionaryCodec;
+pub use m2m_core::token::{TokenCodec, TOKEN_PREFIX};
+#[cfg(feature = "m3")]
+pub use m3::{
+    M3ChatRequest, M3Codec, M3Message, M3StreamDecoder, M3StreamEncoder, Role as M3Role, M3_PREFIX,
+};
+#[cfg(feature = "wasm-plugins")]
+pub use plugin::{WasmCodec, DEFAULT_PLUGIN_FUEL, DEFAULT_PLUGIN_MEMORY};
+pub use pool::{
+    BufferPool, PoolStats, DEFAULT_BUFFER_CAPACITY, DEFAULT_MAX_BUFFER_SIZE, DEFAULT_MAX_POOLED,
+};
+pub use profile::CompressionProfile;
+pub use schema::{PayloadSchema, SchemaViolation};
+pub use service::{
+    CodecRequest, CodecResponse, CodecService, DEFAULT_DEADLINE, DEFAULT_QUEUE_DEPTH,
+};
+pub use shared_dict::{
+    DictionaryStore, SharedDictionary, DEFAULT_MAX_PUSHED_BYTES, DEFAULT_MAX_PUSHED_DICTIONARIES,
+    MAX_DICTIONARY_SIZE, NO_DICTIONARY,
+};
 pub use streaming::{
-    SseEvent, StreamingCodec, StreamingDecompressor, StreamingMode, StreamingStats,
+    SseEvent, SseFormat, StreamingCodec, StreamingDecompressor, StreamingMode, StreamingStats,
 };
 pub use tables::{
     is_default_value, KEY_ABBREV, KEY_EXPAND, MODEL_ABBREV, MODEL_EXPAND, PATTERN_ABBREV,
     PATTERN_EXPAND, ROLE_ABBREV, ROLE_EXPAND,
 };
-pub use token::TokenCodec;
+#[cfg(feature = "token-native")]
 pub use token_native::TokenNativeCodec;
+pub use tools::{ToolPacker, ToolReport, DEFAULT_BASE64_MIN_LEN, SAME_AS_KEY};
 
 /// Check if content is in M2M compressed format
 pub fn is_m2m_format(content: &str) -> bool {
@@ -87,6 +179,16 @@ pub fn is_m2m_format(content: &str) -> bool {
         || content.starts_with("#M2M[v3.0]|") // Brotli
 }
 
+/// Wire prefix of deprecated protocol v2.0 Zlib frames
+pub const V2_PREFIX: &str = "#M2M[v2.0]|DATA:";
+
+/// Check if content is a deprecated protocol v2.0 Zlib frame
+///
+/// Decoding these requires the `compat-v2` feature.
+pub fn is_v2_frame(content: &str) -> bool {
+    content.starts_with(V2_PREFIX)
+}
+
 /// Detect the compression algorithm used in a message
 pub fn detect_algorithm(content: &str) -> Option<Algorithm> {
     Algorithm::from_prefix(content)
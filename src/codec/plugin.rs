@@ -0,0 +1,320 @@
+//! WASM codec plugins (Algorithm::Custom).
+//!
+//! Organizations with proprietary codecs ship them as WebAssembly modules
+//! instead of forking the crate. A plugin is registered under a numeric ID
+//! with [`CodecEngine::with_plugin`](super::CodecEngine::with_plugin) and
+//! negotiated as [`Algorithm::Custom`] like any built-in algorithm.
+//!
+//! # ABI
+//!
+//! A plugin module exports:
+//!
+//! | Export | Signature | Purpose |
+//! |--------|-----------|---------|
+//! | `memory` | memory | Linear memory shared with the host |
+//! | `alloc` | `(len: i32) -> i32` | Reserve `len` bytes for the input |
+//! | `compress` | `(ptr: i32, len: i32) -> i64` | Encode the input |
+//! | `decompress` | `(ptr: i32, len: i32) -> i64` | Decode the input |
+//!
+//! `compress` and `decompress` return the output location packed as
+//! `(ptr << 32) | len`, or a negative value on failure. Plugins import
+//! nothing.
+//!
+//! # Sandboxing
+//!
+//! Every call runs in a fresh instance, so plugins keep no state between
+//! payloads. Calls are bounded by a fuel budget and a memory limit (see
+//! [`WasmCodec::with_fuel`] and [`WasmCodec::with_max_memory`]); a plugin
+//! exceeding either fails the call instead of stalling the agent.
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use std::sync::Arc;
+
+use wasmi::{Config, Engine, Linker, Memory, Module, Store, StoreLimits, StoreLimitsBuilder};
+
+use super::{Algorithm, CompressionResult};
+use crate::error::{M2MError, Result};
+
+/// Default fuel per call (roughly one unit per executed instruction)
+pub const DEFAULT_PLUGIN_FUEL: u64 = 1_000_000_000;
+
+/// Default linear memory limit per call (bytes)
+pub const DEFAULT_PLUGIN_MEMORY: usize = 64 * 1024 * 1024;
+
+/// Codec backed by a sandboxed WASM module
+#[derive(Clone)]
+pub struct WasmCodec {
+    /// Algorithm ID negotiated with peers
+    id: u16,
+    /// Engine the module was compiled with
+    engine: Engine,
+    /// Compiled plugin module
+    module: Arc<Module>,
+    /// Fuel per call
+    fuel: u64,
+    /// Linear memory limit per call (bytes)
+    max_memory: usize,
+}
+
+/// Which plugin export to call
+#[derive(Clone, Copy)]
+enum Direction {
+    Compress,
+    Decompress,
+}
+
+impl Direction {
+    fn export(self) -> &'static str {
+        match self {
+            Direction::Compress => "compress",
+            Direction::Decompress => "decompress",
+        }
+    }
+
+    fn error(self, message: String) -> M2MError {
+        match self {
+            Direction::Compress => M2MError::Compression(message),
+            Direction::Decompress => M2MError::Decompression(message),
+        }
+    }
+}
+
+impl WasmCodec {
+    /// Compile a plugin from WASM bytes
+    ///
+    /// Fails with `InvalidCodec` if the module is invalid, imports
+    /// anything, or lacks an export of the ABI.
+    pub fn new(id: u16, wasm: &[u8]) -> Result<Self> {
+        let mut config = Config::default();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config);
+        let module = Module::new(&engine, wasm)
+            .map_err(|e| M2MError::InvalidCodec(format!("Invalid WASM plugin {id}: {e}")))?;
+
+        if module.imports().len() > 0 {
+            return Err(M2MError::InvalidCodec(format!(
+                "WASM plugin {id} must not import host functions"
+            )));
+        }
+        for export in ["memory", "alloc", "compress", "decompress"] {
+            if module.get_export(export).is_none() {
+                return Err(M2MError::InvalidCodec(format!(
+                    "WASM plugin {id} does not export `{export}`"
+                )));
+            }
+        }
+
+        Ok(Self {
+            id,
+            engine,
+            module: Arc::new(module),
+            fuel: DEFAULT_PLUGIN_FUEL,
+            max_memory: DEFAULT_PLUGIN_MEMORY,
+        })
+    }
+
+    /// Set the fuel budget per call (default: [`DEFAULT_PLUGIN_FUEL`])
+    pub fn with_fuel(mut self, fuel: u64) -> Self {
+        self.fuel = fuel;
+        self
+    }
+
+    /// Set the memory limit per call (default: [`DEFAULT_PLUGIN_MEMORY`])
+    pub fn with_max_memory(mut self, bytes: usize) -> Self {
+        self.max_memory = bytes;
+        self
+    }
+
+    /// Algorithm ID of this plugin
+    pub fn id(&self) -> u16 {
+        self.id
+    }
+
+    /// Algorithm this plugin implements
+    pub fn algorithm(&self) -> Algorithm {
+        Algorithm::Custom(self.id)
+    }
+
+    /// Compress content to `#CX|<id>|<base64>`
+    pub fn compress(&self, content: &str) -> Result<CompressionResult> {
+        let output = self.call(Direction::Compress, content.as_bytes())?;
+        let wire = format!(
+            "{}{}|{}",
+            self.algorithm().prefix(),
+            self.id,
+            BASE64.encode(output)
+        );
+        let wire_len = wire.len();
+        Ok(CompressionResult::new(
+            wire,
+            self.algorithm(),
+            content.len(),
+            wire_len,
+        ))
+    }
+
+    /// Decompress a `#CX|<id>|<base64>` payload
+    pub fn decompress(&self, wire: &str) -> Result<String> {
+        let header = format!("{}{}|", self.algorithm().prefix(), self.id);
+        let data = wire.strip_prefix(&header).ok_or_else(|| {
+            M2MError::Decompression(format!("Not a payload of plugin {}", self.id))
+        })?;
+        let input = BASE64
+            .decode(data)
+            .map_err(|e| M2MError::Decompression(format!("Invalid base64: {e}")))?;
+        let output = self.call(Direction::Decompress, &input)?;
+        String::from_utf8(output).map_err(|e| {
+            M2MError::Decompression(format!("Plugin {} produced invalid UTF-8: {e}", self.id))
+        })
+    }
+
+    /// Run one export on `input` in a fresh, bounded instance
+    fn call(&self, direction: Direction, input: &[u8]) -> Result<Vec<u8>> {
+        let fail = |reason: String| {
+            direction.error(format!(
+                "WASM plugin {} {} failed: {reason}",
+                self.id,
+                direction.export()
+            ))
+        };
+
+        let limits = StoreLimitsBuilder::new()
+            .memory_size(self.max_memory)
+            .instances(1)
+            .build();
+        let mut store = Store::new(&self.engine, limits);
+        store.limiter(|limits: &mut StoreLimits| limits);
+        store.set_fuel(self.fuel).map_err(|e| fail(e.to_string()))?;
+
+        let instance = Linker::<StoreLimits>::new(&self.engine)
+            .instantiate(&mut store, &self.module)
+            .and_then(|pre| pre.start(&mut store))
+            .map_err(|e| fail(e.to_string()))?;
+        let memory: Memory = instance
+            .get_memory(&store, "memory")
+            .ok_or_else(|| fail("no exported memory".to_string()))?;
+        let alloc = instance
+            .get_typed_func::<i32, i32>(&store, "alloc")
+            .map_err(|e| fail(e.to_string()))?;
+        let run = instance
+            .get_typed_func::<(i32, i32), i64>(&store, direction.export())
+            .map_err(|e| fail(e.to_string()))?;
+
+        let len = i32::try_from(input.len()).map_err(|_| fail("input too large".to_string()))?;
+        let ptr = alloc
+            .call(&mut store, len)
+            .map_err(|e| fail(e.to_string()))?;
+        memory
+            .write(&mut store, ptr as u32 as usize, input)
+            .map_err(|e| fail(e.to_string()))?;
+
+        let packed = run
+            .call(&mut store, (ptr, len))
+            .map_err(|e| fail(e.to_string()))?;
+        if packed < 0 {
+            return Err(fail(format!("error code {packed}")));
+        }
+        let (out_ptr, out_len) = ((packed >> 32) as usize, (packed & 0xFFFF_FFFF) as usize);
+        let mut output = vec![0; out_len];
+        memory
+            .read(&store, out_ptr, &mut output)
+            .map_err(|e| fail(e.to_string()))?;
+        Ok(output)
+    }
+}
+
+impl std::fmt::Debug for WasmCodec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WasmCodec")
+            .field("id", &self.id)
+            .field("fuel", &self.fuel)
+            .field("max_memory", &self.max_memory)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Plugin that XORs every byte with 0x5A in place
+    const XOR_PLUGIN: &str = r#"
+        (module
+          (memory (export "memory") 1)
+          (global $next (mut i32) (i32.const 1024))
+          (func (export "alloc") (param $len i32) (result i32)
+            (local $ptr i32)
+            (local.set $ptr (global.get $next))
+            (global.set $next (i32.add (global.get $next) (local.get $len)))
+            (local.get $ptr))
+          (func $xor (param $ptr i32) (param $len i32) (result i64)
+            (local $i i32)
+            (block $done
+              (loop $next
+                (br_if $done (i32.ge_u (local.get $i) (local.get $len)))
+                (i32.store8
+                  (i32.add (local.get $ptr) (local.get $i))
+                  (i32.xor
+                    (i32.load8_u (i32.add (local.get $ptr) (local.get $i)))
+                    (i32.const 0x5A)))
+                (local.set $i (i32.add (local.get $i) (i32.const 1)))
+                (br $next)))
+            (i64.or
+              (i64.shl (i64.extend_i32_u (local.get $ptr)) (i64.const 32))
+              (i64.extend_i32_u (local.get $len))))
+          (func (export "compress") (param i32 i32) (result i64)
+            (call $xor (local.get 0) (local.get 1)))
+          (func (export "decompress") (param i32 i32) (result i64)
+            (call $xor (local.get 0) (local.get 1))))
+    "#;
+
+    #[test]
+    fn test_plugin_roundtrip_and_sandbox() {
+        let codec = WasmCodec::new(7, &wat::parse_str(XOR_PLUGIN).unwrap()).unwrap();
+        let content = r#"{"model":"gpt-4o","messages":[]}"#;
+        let result = codec.compress(content).unwrap();
+        assert!(result.data.starts_with("#CX|7|"));
+        assert_eq!(result.algorithm, Algorithm::Custom(7));
+        assert_eq!(codec.decompress(&result.data).unwrap(), content);
+        assert!(codec.decompress("#CX|8|AAAA").is_err());
+
+        // Missing exports, runaway loops
+        let bad = wat::parse_str(r#"(module (memory (export "memory") 1))"#).unwrap();
+        assert!(matches!(
+            WasmCodec::new(1, &bad),
+            Err(M2MError::InvalidCodec(_))
+        ));
+        let starved = codec.clone().with_fuel(10);
+        assert!(matches!(
+            starved.compress(content),
+            Err(M2MError::Compression(_))
+        ));
+    }
+
+    #[test]
+    fn test_engine_negotiates_plugin() {
+        use crate::codec::CodecEngine;
+        use crate::protocol::CompressionCaps;
+
+        let codec = WasmCodec::new(7, &wat::parse_str(XOR_PLUGIN).unwrap()).unwrap();
+        let engine = CodecEngine::new().with_plugin(codec);
+        let content = r#"{"model":"gpt-4o"}"#;
+        let result = engine.compress(content, Algorithm::Custom(7)).unwrap();
+        assert_eq!(engine.decompress(&result.data).unwrap(), content);
+        assert!(matches!(
+            engine.compress(content, Algorithm::Custom(8)),
+            Err(M2MError::InvalidCodec(_))
+        ));
+        assert!(CodecEngine::new().decompress(&result.data).is_err());
+
+        let ours =
+            CompressionCaps::default().with_algorithms(vec![Algorithm::Custom(7), Algorithm::M2M]);
+        let theirs =
+            CompressionCaps::default().with_algorithms(vec![Algorithm::M2M, Algorithm::Custom(7)]);
+        assert_eq!(ours.negotiate(&theirs), Some(Algorithm::Custom(7)));
+        assert_eq!(
+            ours.negotiate(&CompressionCaps::default()),
+            Some(Algorithm::M2M)
+        );
+    }
+}
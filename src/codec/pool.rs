@@ -0,0 +1,208 @@
+//! Reusable encoding buffers.
+//!
+//! Encoding a frame needs an output buffer and, for compressed payloads, a
+//! scratch buffer for the Brotli output. A [`BufferPool`] hands out cleared
+//! [`BytesMut`] buffers and takes them back after use, so a busy encoder
+//! stops allocating once the pool is warm:
+//!
+//! ```rust
+//! use m2m::codec::{BufferPool, M2MFrame};
+//!
+//! let pool = BufferPool::new();
+//! let frame = M2MFrame::new_request(r#"{"model":"gpt-4o","messages":[]}"#).unwrap();
+//!
+//! let wire = frame.encode_pooled(&pool).unwrap();
+//! // ... send `wire` ...
+//! pool.put(wire);
+//!
+//! // The output and the Brotli scratch buffer are now idle
+//! assert_eq!(pool.stats().pooled, 2);
+//! ```
+//!
+//! Buffers that grew past `max_buffer_size` are dropped instead of pooled,
+//! so one oversized payload does not pin its memory for the process
+//! lifetime.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use bytes::BytesMut;
+
+/// Default capacity of newly allocated buffers (4 KiB)
+pub const DEFAULT_BUFFER_CAPACITY: usize = 4 * 1024;
+
+/// Default number of idle buffers kept
+pub const DEFAULT_MAX_POOLED: usize = 64;
+
+/// Default largest buffer capacity returned to the pool (1 MiB)
+pub const DEFAULT_MAX_BUFFER_SIZE: usize = 1024 * 1024;
+
+/// Pool of reusable byte buffers
+#[derive(Debug)]
+pub struct BufferPool {
+    /// Idle buffers, all cleared
+    buffers: Mutex<Vec<BytesMut>>,
+    /// Capacity of newly allocated buffers
+    buffer_capacity: usize,
+    /// Maximum number of idle buffers
+    max_pooled: usize,
+    /// Largest capacity taken back
+    max_buffer_size: usize,
+    /// Buffers served from the pool
+    hits: AtomicU64,
+    /// Buffers newly allocated
+    misses: AtomicU64,
+    /// Buffers taken back
+    returned: AtomicU64,
+    /// Buffers dropped on return (pool full or buffer too large)
+    discarded: AtomicU64,
+}
+
+/// Counters of a [`BufferPool`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PoolStats {
+    /// Buffers served from the pool
+    pub hits: u64,
+    /// Buffers newly allocated
+    pub misses: u64,
+    /// Buffers taken back
+    pub returned: u64,
+    /// Buffers dropped on return
+    pub discarded: u64,
+    /// Idle buffers in the pool
+    pub pooled: usize,
+}
+
+impl PoolStats {
+    /// Share of requests served without allocating (0.0 - 1.0)
+    pub fn hit_rate(&self) -> f64 {
+        let total = self.hits + self.misses;
+        if total == 0 {
+            0.0
+        } else {
+            self.hits as f64 / total as f64
+        }
+    }
+}
+
+impl Default for BufferPool {
+    fn default() -> Self {
+        Self {
+            buffers: Mutex::new(Vec::new()),
+            buffer_capacity: DEFAULT_BUFFER_CAPACITY,
+            max_pooled: DEFAULT_MAX_POOLED,
+            max_buffer_size: DEFAULT_MAX_BUFFER_SIZE,
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            returned: AtomicU64::new(0),
+            discarded: AtomicU64::new(0),
+        }
+    }
+}
+
+impl BufferPool {
+    /// Create a pool with default limits
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the capacity of newly allocated buffers
+    pub fn with_buffer_capacity(mut self, capacity: usize) -> Self {
+        self.buffer_capacity = capacity;
+        self
+    }
+
+    /// Set the maximum number of idle buffers kept
+    pub fn with_max_pooled(mut self, max_pooled: usize) -> Self {
+        self.max_pooled = max_pooled;
+        self
+    }
+
+    /// Set the largest buffer capacity taken back
+    pub fn with_max_buffer_size(mut self, max_buffer_size: usize) -> Self {
+        self.max_buffer_size = max_buffer_size;
+        self
+    }
+
+    /// Take an empty buffer, reusing an idle one if available
+    pub fn get(&self) -> BytesMut {
+        let reused = self.buffers.lock().ok().and_then(|mut idle| idle.pop());
+        match reused {
+            Some(buf) => {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                buf
+            },
+            None => {
+                self.misses.fetch_add(1, Ordering::Relaxed);
+                BytesMut::with_capacity(self.buffer_capacity)
+            },
+        }
+    }
+
+    /// Return a buffer for reuse
+    ///
+    /// The buffer is cleared. It is dropped if the pool is full or its
+    /// capacity exceeds the pool's maximum buffer size.
+    pub fn put(&self, mut buf: BytesMut) {
+        self.returned.fetch_add(1, Ordering::Relaxed);
+        if buf.capacity() > self.max_buffer_size {
+            self.discarded.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+
+        buf.clear();
+        if let Ok(mut idle) = self.buffers.lock() {
+            if idle.len() < self.max_pooled {
+                idle.push(buf);
+                return;
+            }
+        }
+        self.discarded.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Current counters
+    pub fn stats(&self) -> PoolStats {
+        PoolStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            returned: self.returned.load(Ordering::Relaxed),
+            discarded: self.discarded.load(Ordering::Relaxed),
+            pooled: self.buffers.lock().map_or(0, |idle| idle.len()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reuse_and_limits() {
+        let pool = BufferPool::new()
+            .with_buffer_capacity(16)
+            .with_max_pooled(1)
+            .with_max_buffer_size(64);
+
+        let mut first = pool.get();
+        first.extend_from_slice(b"hello");
+        let second = pool.get();
+        pool.put(first);
+        pool.put(second);
+
+        let reused = pool.get();
+        assert!(reused.is_empty());
+
+        let mut large = BytesMut::with_capacity(128);
+        large.extend_from_slice(&[0; 128]);
+        pool.put(large);
+
+        let stats = pool.stats();
+        assert_eq!(stats.misses, 2);
+        assert_eq!(stats.hits, 1);
+        assert_eq!(stats.returned, 3);
+        // One over the idle limit, one over the size limit
+        assert_eq!(stats.discarded, 2);
+        assert_eq!(stats.pooled, 0);
+        assert!((stats.hit_rate() - 1.0 / 3.0).abs() < 1e-9);
+    }
+}
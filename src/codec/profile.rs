@@ -0,0 +1,182 @@
+//! Compression profiles.
+//!
+//! A [`CompressionProfile`] tunes the same [`CodecEngine`](super::CodecEngine)
+//! for a workload: low-latency agent loops want cheap codecs and no model
+//! inference per message, batch pipelines want the smallest output whatever
+//! it costs. A profile bundles the auto-selection candidates, Brotli
+//! quality, ML routing and selection thresholds.
+//!
+//! | Profile | Candidates | Brotli quality | ML routing | Selection |
+//! |---------|------------|----------------|------------|-----------|
+//! | `latency` | None, M2M | 4 | off | heuristic, no Brotli |
+//! | `balanced` | all | 11 | if enabled | heuristic (default thresholds) |
+//! | `max-savings` | all | 11 | if enabled | smallest output of all candidates |
+
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use super::feedback::RouterThresholds;
+use super::Algorithm;
+use crate::error::M2MError;
+
+/// Brotli quality of the latency profile
+const LATENCY_BROTLI_QUALITY: u32 = 4;
+
+/// Brotli quality of the balanced and max-savings profiles
+const FULL_BROTLI_QUALITY: u32 = 11;
+
+/// Compression preset for a workload
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CompressionProfile {
+    /// Cheapest codecs only, no ML inference
+    Latency,
+    /// Heuristic selection with the default thresholds
+    #[default]
+    Balanced,
+    /// Try every candidate and keep the smallest output
+    MaxSavings,
+}
+
+impl CompressionProfile {
+    /// All profiles
+    pub const ALL: [Self; 3] = [Self::Latency, Self::Balanced, Self::MaxSavings];
+
+    /// Profile name as used in configuration and the `X-M2M-Profile` header
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Latency => "latency",
+            Self::Balanced => "balanced",
+            Self::MaxSavings => "max-savings",
+        }
+    }
+
+    /// Algorithms automatic selection may pick
+    pub fn candidates(&self) -> &'static [Algorithm] {
+        match self {
+            Self::Latency => &[Algorithm::None, Algorithm::M2M],
+            Self::Balanced | Self::MaxSavings => &[
+                Algorithm::None,
+                Algorithm::M2M,
+                Algorithm::TokenNative,
+                Algorithm::Brotli,
+            ],
+        }
+    }
+
+    /// Check if automatic selection may pick `algorithm`
+    ///
+    /// Algorithms not compiled into this build are never allowed.
+    pub fn allows(&self, algorithm: Algorithm) -> bool {
+        self.candidates().contains(&algorithm) && algorithm.is_available()
+    }
+
+    /// Brotli quality (0-11)
+    pub fn brotli_quality(&self) -> u32 {
+        match self {
+            Self::Latency => LATENCY_BROTLI_QUALITY,
+            Self::Balanced | Self::MaxSavings => FULL_BROTLI_QUALITY,
+        }
+    }
+
+    /// Check if ML routing may be used (when the engine has it enabled)
+    pub fn ml_routing(&self) -> bool {
+        !matches!(self, Self::Latency)
+    }
+
+    /// Check if selection compresses with every candidate and keeps the
+    /// smallest output
+    pub fn exhaustive(&self) -> bool {
+        matches!(self, Self::MaxSavings)
+    }
+
+    /// Heuristic selection thresholds
+    pub fn thresholds(&self) -> RouterThresholds {
+        match self {
+            // Brotli is never selected
+            Self::Latency => RouterThresholds {
+                min_compress_bytes: 200,
+                brotli_threshold: usize::MAX,
+                repetition_threshold: f32::INFINITY,
+            },
+            Self::Balanced => RouterThresholds::default(),
+            Self::MaxSavings => RouterThresholds {
+                min_compress_bytes: 50,
+                brotli_threshold: 512,
+                repetition_threshold: 0.2,
+            },
+        }
+    }
+
+    /// Closest allowed algorithm to `algorithm`
+    ///
+    /// Disallowed selections fall back to M2M for JSON and passthrough
+    /// otherwise.
+    pub fn restrict(&self, algorithm: Algorithm, is_json: bool) -> Algorithm {
+        if self.allows(algorithm) {
+            algorithm
+        } else if is_json && self.allows(Algorithm::M2M) {
+            Algorithm::M2M
+        } else {
+            Algorithm::None
+        }
+    }
+}
+
+impl fmt::Display for CompressionProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for CompressionProfile {
+    type Err = M2MError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().replace('_', "-").as_str() {
+            "latency" | "fast" => Ok(Self::Latency),
+            "balanced" | "default" => Ok(Self::Balanced),
+            "max-savings" | "max" => Ok(Self::MaxSavings),
+            other => Err(M2MError::Config(format!(
+                "Unknown compression profile '{other}' (expected latency, balanced or max-savings)"
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_display() {
+        for profile in CompressionProfile::ALL {
+            assert_eq!(
+                profile.name().parse::<CompressionProfile>().unwrap(),
+                profile
+            );
+        }
+        assert_eq!(
+            "MAX_SAVINGS".parse::<CompressionProfile>().unwrap(),
+            CompressionProfile::MaxSavings
+        );
+        assert!("turbo".parse::<CompressionProfile>().is_err());
+
+        let json = serde_json::to_string(&CompressionProfile::MaxSavings).unwrap();
+        assert_eq!(json, "\"max-savings\"");
+    }
+
+    #[test]
+    #[cfg(feature = "brotli")]
+    fn test_restrict() {
+        let latency = CompressionProfile::Latency;
+        assert_eq!(latency.restrict(Algorithm::Brotli, true), Algorithm::M2M);
+        assert_eq!(latency.restrict(Algorithm::Brotli, false), Algorithm::None);
+        assert_eq!(
+            CompressionProfile::Balanced.restrict(Algorithm::Brotli, false),
+            Algorithm::Brotli
+        );
+    }
+}
@@ -0,0 +1,480 @@
+//! Structural validation of decompressed LLM API payloads.
+//!
+//! A payload that decodes cleanly can still be wrong: a codec bug or a
+//! corrupted abbreviation table may yield valid JSON with a missing `model`
+//! or an unknown role. Checking the shape after decompression catches this
+//! before the payload reaches an upstream API, and the error names the
+//! offending field as a JSON Pointer (e.g. `/messages/2/role`).
+//!
+//! # Schemas
+//!
+//! | Schema                  | API                              |
+//! |-------------------------|----------------------------------|
+//! | `ChatCompletionRequest` | OpenAI `POST /v1/chat/completions` |
+//! | `ChatCompletionResponse`| OpenAI chat completion object    |
+//! | `MessagesRequest`       | Anthropic `POST /v1/messages`    |
+//! | `MessagesResponse`      | Anthropic message object         |
+//!
+//! Only fields the protocol relies on are checked; unknown fields are
+//! allowed so new API parameters pass through.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::error::{M2MError, Result};
+
+/// Known API payload shapes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PayloadSchema {
+    /// OpenAI chat completion request
+    ChatCompletionRequest,
+    /// OpenAI chat completion response
+    ChatCompletionResponse,
+    /// Anthropic Messages API request
+    MessagesRequest,
+    /// Anthropic Messages API response
+    MessagesResponse,
+}
+
+/// A field that does not match its schema
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SchemaViolation {
+    /// JSON Pointer to the field
+    pub path: String,
+    /// What is wrong with it
+    pub message: String,
+}
+
+impl PayloadSchema {
+    /// Schema name
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::ChatCompletionRequest => "chat_completion_request",
+            Self::ChatCompletionResponse => "chat_completion_response",
+            Self::MessagesRequest => "messages_request",
+            Self::MessagesResponse => "messages_response",
+        }
+    }
+
+    /// Guess the schema of a payload (`None` if it is not an API payload)
+    ///
+    /// Ambiguous requests are treated as chat completions, whose checks
+    /// accept any valid Messages request.
+    pub fn detect(value: &Value) -> Option<Self> {
+        let obj = value.as_object()?;
+
+        if obj.get("type").and_then(Value::as_str) == Some("message") {
+            return Some(Self::MessagesResponse);
+        }
+        if obj.contains_key("choices") {
+            return Some(Self::ChatCompletionResponse);
+        }
+        let messages = obj.get("messages")?.as_array()?;
+
+        let anthropic_block = messages.iter().any(|m| {
+            m.get("content")
+                .and_then(Value::as_array)
+                .is_some_and(|blocks| {
+                    blocks.iter().any(|b| {
+                        matches!(
+                            b.get("type").and_then(Value::as_str),
+                            Some("tool_use" | "tool_result")
+                        )
+                    })
+                })
+        });
+        if obj.contains_key("system") || obj.contains_key("stop_sequences") || anthropic_block {
+            Some(Self::MessagesRequest)
+        } else {
+            Some(Self::ChatCompletionRequest)
+        }
+    }
+
+    /// Check a payload, returning every violation found
+    pub fn violations(self, value: &Value) -> Vec<SchemaViolation> {
+        let mut v = Validator::default();
+        if let Some(obj) = v.object(value, "") {
+            match self {
+                Self::ChatCompletionRequest => v.chat_completion_request(obj),
+                Self::ChatCompletionResponse => v.chat_completion_response(obj),
+                Self::MessagesRequest => v.messages_request(obj),
+                Self::MessagesResponse => v.messages_response(obj),
+            }
+        }
+        v.violations
+    }
+
+    /// Check a payload, failing on the first violation
+    pub fn validate(self, value: &Value) -> Result<()> {
+        match self.violations(value).into_iter().next() {
+            Some(violation) => Err(M2MError::SchemaViolation {
+                schema: self.name().to_string(),
+                path: violation.path,
+                message: violation.message,
+            }),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Roles accepted by the chat completions API
+const CHAT_ROLES: &[&str] = &[
+    "system",
+    "developer",
+    "user",
+    "assistant",
+    "tool",
+    "function",
+];
+
+/// Roles accepted by the Messages API
+const MESSAGES_ROLES: &[&str] = &["user", "assistant"];
+
+/// Collects violations while walking a payload
+#[derive(Default)]
+struct Validator {
+    violations: Vec<SchemaViolation>,
+}
+
+impl Validator {
+    fn fail(&mut self, path: &str, message: impl Into<String>) {
+        self.violations.push(SchemaViolation {
+            path: if path.is_empty() {
+                "/".to_string()
+            } else {
+                path.to_string()
+            },
+            message: message.into(),
+        });
+    }
+
+    fn object<'v>(&mut self, value: &'v Value, path: &str) -> Option<&'v Map<String, Value>> {
+        let obj = value.as_object();
+        if obj.is_none() {
+            self.fail(path, "expected object");
+        }
+        obj
+    }
+
+    /// Look up a field, reporting it if required and absent (null counts as absent)
+    fn field<'v>(
+        &mut self,
+        obj: &'v Map<String, Value>,
+        path: &str,
+        key: &str,
+        required: bool,
+    ) -> Option<&'v Value> {
+        match obj.get(key).filter(|v| !v.is_null()) {
+            Some(value) => Some(value),
+            None => {
+                if required {
+                    self.fail(&pointer(path, key), "missing required field");
+                }
+                None
+            },
+        }
+    }
+
+    fn string<'v>(
+        &mut self,
+        obj: &'v Map<String, Value>,
+        path: &str,
+        key: &str,
+        required: bool,
+    ) -> Option<&'v str> {
+        let value = self.field(obj, path, key, required)?;
+        let s = value.as_str();
+        if s.is_none() {
+            self.fail(&pointer(path, key), "expected string");
+        }
+        s
+    }
+
+    fn integer(&mut self, obj: &Map<String, Value>, path: &str, key: &str, required: bool) {
+        if let Some(value) = self.field(obj, path, key, required) {
+            if value.as_u64().is_none() {
+                self.fail(&pointer(path, key), "expected non-negative integer");
+            }
+        }
+    }
+
+    fn boolean(&mut self, obj: &Map<String, Value>, path: &str, key: &str) {
+        if let Some(value) = self.field(obj, path, key, false) {
+            if !value.is_boolean() {
+                self.fail(&pointer(path, key), "expected boolean");
+            }
+        }
+    }
+
+    fn number_in(&mut self, obj: &Map<String, Value>, path: &str, key: &str, min: f64, max: f64) {
+        if let Some(value) = self.field(obj, path, key, false) {
+            match value.as_f64() {
+                Some(n) if (min..=max).contains(&n) => {},
+                Some(n) => self.fail(&pointer(path, key), format!("{n} outside {min}..={max}")),
+                None => self.fail(&pointer(path, key), "expected number"),
+            }
+        }
+    }
+
+    fn array<'v>(
+        &mut self,
+        obj: &'v Map<String, Value>,
+        path: &str,
+        key: &str,
+        required: bool,
+    ) -> Option<&'v Vec<Value>> {
+        let value = self.field(obj, path, key, required)?;
+        let arr = value.as_array();
+        if arr.is_none() {
+            self.fail(&pointer(path, key), "expected array");
+        }
+        arr
+    }
+
+    fn one_of(&mut self, obj: &Map<String, Value>, path: &str, key: &str, allowed: &[&str]) {
+        if let Some(s) = self.string(obj, path, key, true) {
+            if !allowed.contains(&s) {
+                self.fail(
+                    &pointer(path, key),
+                    format!(
+                        "unexpected value {s:?} (expected one of {})",
+                        allowed.join(", ")
+                    ),
+                );
+            }
+        }
+    }
+
+    /// String, or array of typed content parts/blocks
+    fn content(&mut self, value: &Value, path: &str) {
+        match value {
+            Value::String(_) => {},
+            Value::Array(parts) => {
+                for (i, part) in parts.iter().enumerate() {
+                    let part_path = format!("{path}/{i}");
+                    if let Some(obj) = self.object(part, &part_path) {
+                        self.string(obj, &part_path, "type", true);
+                    }
+                }
+            },
+            _ => self.fail(path, "expected string or array of content parts"),
+        }
+    }
+
+    fn chat_completion_request(&mut self, obj: &Map<String, Value>) {
+        self.string(obj, "", "model", true);
+        if let Some(messages) = self.array(obj, "", "messages", true) {
+            if messages.is_empty() {
+                self.fail("/messages", "expected at least one message");
+            }
+            for (i, message) in messages.iter().enumerate() {
+                let path = format!("/messages/{i}");
+                let Some(msg) = self.object(message, &path) else {
+                    continue;
+                };
+                self.one_of(msg, &path, "role", CHAT_ROLES);
+                let role = msg.get("role").and_then(Value::as_str);
+
+                // Assistant turns may carry only tool calls
+                let content_required =
+                    !(role == Some("assistant") && msg.contains_key("tool_calls"));
+                if let Some(content) = self.field(msg, &path, "content", content_required) {
+                    self.content(content, &pointer(&path, "content"));
+                }
+                if role == Some("tool") {
+                    self.string(msg, &path, "tool_call_id", true);
+                }
+            }
+        }
+
+        self.number_in(obj, "", "temperature", 0.0, 2.0);
+        self.number_in(obj, "", "top_p", 0.0, 1.0);
+        self.integer(obj, "", "max_tokens", false);
+        self.integer(obj, "", "max_completion_tokens", false);
+        self.integer(obj, "", "n", false);
+        self.boolean(obj, "", "stream");
+
+        if let Some(tools) = self.array(obj, "", "tools", false) {
+            for (i, tool) in tools.iter().enumerate() {
+                let path = format!("/tools/{i}");
+                let Some(tool) = self.object(tool, &path) else {
+                    continue;
+                };
+                self.string(tool, &path, "type", true);
+                if let Some(function) = self.field(tool, &path, "function", true) {
+                    let fn_path = pointer(&path, "function");
+                    if let Some(function) = self.object(function, &fn_path) {
+                        self.string(function, &fn_path, "name", true);
+                    }
+                }
+            }
+        }
+    }
+
+    fn chat_completion_response(&mut self, obj: &Map<String, Value>) {
+        self.string(obj, "", "id", true);
+        self.string(obj, "", "object", true);
+        self.string(obj, "", "model", true);
+        if let Some(choices) = self.array(obj, "", "choices", true) {
+            for (i, choice) in choices.iter().enumerate() {
+                let path = format!("/choices/{i}");
+                let Some(choice) = self.object(choice, &path) else {
+                    continue;
+                };
+                self.integer(choice, &path, "index", true);
+                self.string(choice, &path, "finish_reason", false);
+                // Streaming chunks carry `delta` instead of `message`
+                let key = if choice.contains_key("delta") {
+                    "delta"
+                } else {
+                    "message"
+                };
+                if let Some(message) = self.field(choice, &path, key, true) {
+                    let msg_path = pointer(&path, key);
+                    if let Some(msg) = self.object(message, &msg_path) {
+                        self.string(msg, &msg_path, "role", key == "message");
+                    }
+                }
+            }
+        }
+        if let Some(usage) = self.field(obj, "", "usage", false) {
+            if let Some(usage) = self.object(usage, "/usage") {
+                self.integer(usage, "/usage", "prompt_tokens", true);
+                self.integer(usage, "/usage", "completion_tokens", true);
+            }
+        }
+    }
+
+    fn messages_request(&mut self, obj: &Map<String, Value>) {
+        self.string(obj, "", "model", true);
+        self.integer(obj, "", "max_tokens", true);
+        if let Some(messages) = self.array(obj, "", "messages", true) {
+            if messages.is_empty() {
+                self.fail("/messages", "expected at least one message");
+            }
+            for (i, message) in messages.iter().enumerate() {
+                let path = format!("/messages/{i}");
+                let Some(msg) = self.object(message, &path) else {
+                    continue;
+                };
+                self.one_of(msg, &path, "role", MESSAGES_ROLES);
+                if let Some(content) = self.field(msg, &path, "content", true) {
+                    self.content(content, &pointer(&path, "content"));
+                }
+            }
+        }
+        if let Some(system) = self.field(obj, "", "system", false) {
+            self.content(system, "/system");
+        }
+        self.number_in(obj, "", "temperature", 0.0, 1.0);
+        self.boolean(obj, "", "stream");
+    }
+
+    fn messages_response(&mut self, obj: &Map<String, Value>) {
+        self.string(obj, "", "id", true);
+        self.string(obj, "", "model", true);
+        self.one_of(obj, "", "role", &["assistant"]);
+        if let Some(content) = self.array(obj, "", "content", true) {
+            for (i, block) in content.iter().enumerate() {
+                let path = format!("/content/{i}");
+                if let Some(block) = self.object(block, &path) {
+                    self.string(block, &path, "type", true);
+                }
+            }
+        }
+        self.string(obj, "", "stop_reason", false);
+        if let Some(usage) = self.field(obj, "", "usage", true) {
+            if let Some(usage) = self.object(usage, "/usage") {
+                self.integer(usage, "/usage", "input_tokens", true);
+                self.integer(usage, "/usage", "output_tokens", true);
+            }
+        }
+    }
+}
+
+/// Append a key to a JSON Pointer (RFC 6901 escaping)
+fn pointer(path: &str, key: &str) -> String {
+    format!("{path}/{}", key.replace('~', "~0").replace('/', "~1"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_detect_and_validate() {
+        let chat = json!({
+            "model": "gpt-4o",
+            "messages": [
+                {"role": "system", "content": "Be brief"},
+                {"role": "assistant", "tool_calls": [{"id": "c1"}]},
+                {"role": "tool", "tool_call_id": "c1", "content": "42"}
+            ],
+            "temperature": 0.7
+        });
+        assert_eq!(
+            PayloadSchema::detect(&chat),
+            Some(PayloadSchema::ChatCompletionRequest)
+        );
+        assert!(PayloadSchema::ChatCompletionRequest.validate(&chat).is_ok());
+
+        let claude = json!({
+            "model": "claude-sonnet-4",
+            "max_tokens": 1024,
+            "system": "Be brief",
+            "messages": [{"role": "user", "content": [{"type": "text", "text": "Hi"}]}]
+        });
+        assert_eq!(
+            PayloadSchema::detect(&claude),
+            Some(PayloadSchema::MessagesRequest)
+        );
+        assert!(PayloadSchema::MessagesRequest.validate(&claude).is_ok());
+
+        let response = json!({
+            "id": "chatcmpl-1", "object": "chat.completion", "model": "gpt-4o",
+            "choices": [{"index": 0, "message": {"role": "assistant", "content": "Hi"}, "finish_reason": "stop"}],
+            "usage": {"prompt_tokens": 5, "completion_tokens": 1}
+        });
+        assert_eq!(
+            PayloadSchema::detect(&response),
+            Some(PayloadSchema::ChatCompletionResponse)
+        );
+        assert!(PayloadSchema::ChatCompletionResponse
+            .violations(&response)
+            .is_empty());
+        assert_eq!(PayloadSchema::detect(&json!({"hello": 1})), None);
+    }
+
+    #[test]
+    fn test_violation_paths() {
+        let corrupted = json!({
+            "model": "gpt-4o",
+            "messages": [
+                {"role": "user", "content": "Hi"},
+                {"role": "usr", "content": "Hi"},
+                {"role": "tool", "content": "42"}
+            ],
+            "temperature": 3.0
+        });
+        let paths: Vec<_> = PayloadSchema::ChatCompletionRequest
+            .violations(&corrupted)
+            .into_iter()
+            .map(|v| v.path)
+            .collect();
+        assert_eq!(
+            paths,
+            vec![
+                "/messages/1/role",
+                "/messages/2/tool_call_id",
+                "/temperature"
+            ]
+        );
+
+        match PayloadSchema::MessagesRequest.validate(&json!({"model": "claude", "messages": []})) {
+            Err(M2MError::SchemaViolation { path, .. }) => assert_eq!(path, "/max_tokens"),
+            other => panic!("expected schema violation, got {other:?}"),
+        }
+    }
+}
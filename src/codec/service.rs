@@ -0,0 +1,363 @@
+//! Backpressure-aware async codec service.
+//!
+//! [`CodecEngine`] is synchronous and CPU-bound. [`CodecService`] runs it on
+//! the blocking pool behind three limits, so a burst of requests is shed
+//! with [`M2MError::Overloaded`] instead of piling up unbounded tasks:
+//!
+//! | Limit | Default | When exceeded |
+//! |-------|---------|---------------|
+//! | Concurrency | available CPUs | Request waits in the queue |
+//! | Queue depth | [`DEFAULT_QUEUE_DEPTH`] | Rejected immediately |
+//! | Deadline | [`DEFAULT_DEADLINE`] | Rejected once the deadline passes |
+//!
+//! The deadline covers queueing and execution. Work already running on the
+//! blocking pool cannot be cancelled: it finishes in the background and
+//! keeps its concurrency slot until then, so shedding never lets more than
+//! the configured number of jobs run at once.
+//!
+//! `CodecService` implements `tower::Service<CodecRequest>`. `poll_ready`
+//! is always ready; load is shed in `call`, so it composes with tower
+//! middleware without buffering.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use m2m::codec::{Algorithm, CodecEngine, CodecService};
+//! use std::time::Duration;
+//!
+//! let service = CodecService::new(CodecEngine::new())
+//!     .with_max_concurrency(4)
+//!     .with_queue_depth(64)
+//!     .with_deadline(Duration::from_millis(500));
+//!
+//! let result = service.compress(json, Algorithm::M2M).await?;
+//! ```
+
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use futures::future::BoxFuture;
+use tokio::sync::Semaphore;
+
+use super::engine::CodecEngine;
+use super::{Algorithm, CompressionProfile, CompressionResult};
+use crate::error::{M2MError, Result};
+
+/// Default number of requests waiting for a worker
+pub const DEFAULT_QUEUE_DEPTH: usize = 1024;
+
+/// Default per-request deadline (queueing + execution)
+pub const DEFAULT_DEADLINE: Duration = Duration::from_secs(5);
+
+/// Codec operation
+#[derive(Debug, Clone)]
+pub enum CodecRequest {
+    /// Compress with a specific algorithm
+    Compress {
+        /// Content to compress
+        content: String,
+        /// Algorithm to use
+        algorithm: Algorithm,
+    },
+    /// Compress with automatic algorithm selection
+    CompressAuto {
+        /// Content to compress
+        content: String,
+        /// Profile overriding the engine's (see [`CompressionProfile`])
+        profile: Option<CompressionProfile>,
+    },
+    /// Decompress wire-format data
+    Decompress {
+        /// Wire-format data
+        wire: String,
+    },
+}
+
+/// Codec operation result
+#[derive(Debug, Clone)]
+pub enum CodecResponse {
+    /// Result of `Compress` or `CompressAuto`
+    Compressed(CompressionResult),
+    /// Result of `Decompress`
+    Decompressed(String),
+}
+
+impl CodecResponse {
+    /// Get the compression result, if any
+    pub fn into_compressed(self) -> Option<CompressionResult> {
+        match self {
+            Self::Compressed(result) => Some(result),
+            Self::Decompressed(_) => None,
+        }
+    }
+
+    /// Get the decompressed content, if any
+    pub fn into_decompressed(self) -> Option<String> {
+        match self {
+            Self::Decompressed(content) => Some(content),
+            Self::Compressed(_) => None,
+        }
+    }
+}
+
+/// Async codec service with bounded queue, concurrency limit and deadline
+///
+/// Cloning is cheap; clones share the engine and limits.
+///
+/// # Epistemic Properties
+///
+/// - **K_i**: At most `max_concurrency` codec jobs run at once, and at most
+///   `queue_depth` more are admitted to wait
+/// - **B_i**: Callers receiving `Overloaded` retry with backoff
+#[derive(Clone)]
+pub struct CodecService {
+    /// Shared codec engine
+    engine: Arc<CodecEngine>,
+    /// Admission permits (running + queued)
+    admission: Arc<Semaphore>,
+    /// Worker permits (running)
+    workers: Arc<Semaphore>,
+    /// Maximum concurrent jobs
+    max_concurrency: usize,
+    /// Maximum queued jobs
+    queue_depth: usize,
+    /// Per-request deadline
+    deadline: Duration,
+}
+
+impl CodecService {
+    /// Create a service with default limits
+    pub fn new(engine: CodecEngine) -> Self {
+        let max_concurrency = std::thread::available_parallelism().map_or(4, |n| n.get());
+        Self::with_limits(
+            Arc::new(engine),
+            max_concurrency,
+            DEFAULT_QUEUE_DEPTH,
+            DEFAULT_DEADLINE,
+        )
+    }
+
+    /// Build with explicit limits
+    fn with_limits(
+        engine: Arc<CodecEngine>,
+        max_concurrency: usize,
+        queue_depth: usize,
+        deadline: Duration,
+    ) -> Self {
+        let max_concurrency = max_concurrency.max(1);
+        Self {
+            engine,
+            admission: Arc::new(Semaphore::new(max_concurrency + queue_depth)),
+            workers: Arc::new(Semaphore::new(max_concurrency)),
+            max_concurrency,
+            queue_depth,
+            deadline,
+        }
+    }
+
+    /// Set maximum concurrent jobs (at least 1)
+    pub fn with_max_concurrency(self, max: usize) -> Self {
+        Self::with_limits(self.engine, max, self.queue_depth, self.deadline)
+    }
+
+    /// Set maximum jobs waiting for a worker
+    pub fn with_queue_depth(self, depth: usize) -> Self {
+        Self::with_limits(self.engine, self.max_concurrency, depth, self.deadline)
+    }
+
+    /// Set per-request deadline
+    pub fn with_deadline(mut self, deadline: Duration) -> Self {
+        self.deadline = deadline;
+        self
+    }
+
+    /// Get the wrapped engine
+    pub fn engine(&self) -> &CodecEngine {
+        &self.engine
+    }
+
+    /// Maximum concurrent jobs
+    pub fn max_concurrency(&self) -> usize {
+        self.max_concurrency
+    }
+
+    /// Maximum queued jobs
+    pub fn queue_depth(&self) -> usize {
+        self.queue_depth
+    }
+
+    /// Per-request deadline
+    pub fn deadline(&self) -> Duration {
+        self.deadline
+    }
+
+    /// Jobs currently admitted (running or queued)
+    pub fn in_flight(&self) -> usize {
+        self.max_concurrency + self.queue_depth - self.admission.available_permits()
+    }
+
+    /// Run a codec operation under the service limits
+    pub async fn process(&self, request: CodecRequest) -> Result<CodecResponse> {
+        let admitted = Arc::clone(&self.admission)
+            .try_acquire_owned()
+            .map_err(|_| M2MError::Overloaded("codec queue full".to_string()))?;
+
+        let engine = Arc::clone(&self.engine);
+        let workers = Arc::clone(&self.workers);
+        let job = async move {
+            let worker = workers
+                .acquire_owned()
+                .await
+                .map_err(|_| M2MError::Server("codec service closed".to_string()))?;
+
+            // Codec spans nest under the caller's span on the blocking pool
+            let span = tracing::Span::current();
+            crate::runtime::spawn_blocking_named("codec", move || {
+                let _span = span.enter();
+                // Permits are released when the job finishes, even if the
+                // caller has given up on it
+                let _permits = (admitted, worker);
+                run(&engine, request)
+            })
+            .await
+            .map_err(|e| M2MError::Server(format!("codec task failed: {e}")))?
+        };
+
+        tokio::time::timeout(self.deadline, job)
+            .await
+            .map_err(|_| {
+                tracing::warn!(deadline = ?self.deadline, "codec deadline exceeded");
+                M2MError::Overloaded(format!("codec deadline of {:?} exceeded", self.deadline))
+            })?
+    }
+
+    /// Compress with a specific algorithm
+    pub async fn compress(&self, content: &str, algorithm: Algorithm) -> Result<CompressionResult> {
+        let request = CodecRequest::Compress {
+            content: content.to_string(),
+            algorithm,
+        };
+        self.process(request).await.and_then(expect_compressed)
+    }
+
+    /// Compress with automatic algorithm selection
+    pub async fn compress_auto(&self, content: &str) -> Result<CompressionResult> {
+        self.compress_auto_with_profile(content, None).await
+    }
+
+    /// Compress with automatic selection under a profile (engine's if `None`)
+    pub async fn compress_auto_with_profile(
+        &self,
+        content: &str,
+        profile: Option<CompressionProfile>,
+    ) -> Result<CompressionResult> {
+        let request = CodecRequest::CompressAuto {
+            content: content.to_string(),
+            profile,
+        };
+        self.process(request).await.and_then(expect_compressed)
+    }
+
+    /// Decompress wire-format data
+    pub async fn decompress(&self, wire: &str) -> Result<String> {
+        let request = CodecRequest::Decompress {
+            wire: wire.to_string(),
+        };
+        self.process(request).await.and_then(|response| {
+            response
+                .into_decompressed()
+                .ok_or_else(|| M2MError::Server("unexpected codec response".to_string()))
+        })
+    }
+}
+
+/// Execute a request on the engine
+fn run(engine: &CodecEngine, request: CodecRequest) -> Result<CodecResponse> {
+    match request {
+        CodecRequest::Compress { content, algorithm } => engine
+            .compress(&content, algorithm)
+            .map(CodecResponse::Compressed),
+        CodecRequest::CompressAuto { content, profile } => engine
+            .compress_auto_with_profile(&content, profile.unwrap_or(engine.profile()))
+            .map(|(result, _)| CodecResponse::Compressed(result)),
+        CodecRequest::Decompress { wire } => {
+            engine.decompress(&wire).map(CodecResponse::Decompressed)
+        },
+    }
+}
+
+/// Unwrap a compression response
+fn expect_compressed(response: CodecResponse) -> Result<CompressionResult> {
+    response
+        .into_compressed()
+        .ok_or_else(|| M2MError::Server("unexpected codec response".to_string()))
+}
+
+impl tower::Service<CodecRequest> for CodecService {
+    type Response = CodecResponse;
+    type Error = M2MError;
+    type Future = BoxFuture<'static, Result<CodecResponse>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<()>> {
+        // Load is shed in `call`, not by withholding readiness
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: CodecRequest) -> Self::Future {
+        let service = self.clone();
+        Box::pin(async move { service.process(request).await })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tower::ServiceExt;
+
+    const REQUEST: &str = r#"{"model":"gpt-4o","messages":[{"role":"user","content":"Hello"}]}"#;
+
+    #[tokio::test]
+    async fn test_service_roundtrip() {
+        let service = CodecService::new(CodecEngine::new());
+        let result = service.compress(REQUEST, Algorithm::M2M).await.unwrap();
+        assert_eq!(service.decompress(&result.data).await.unwrap(), REQUEST);
+
+        // Through the tower interface
+        let response = service
+            .clone()
+            .oneshot(CodecRequest::CompressAuto {
+                content: REQUEST.to_string(),
+                profile: None,
+            })
+            .await
+            .unwrap();
+        assert!(response.into_compressed().is_some());
+        assert_eq!(service.in_flight(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_service_sheds_load() {
+        let service = CodecService::new(CodecEngine::new())
+            .with_max_concurrency(1)
+            .with_queue_depth(0);
+
+        // Hold the only admission slot
+        let held = Arc::clone(&service.admission).try_acquire_owned().unwrap();
+        let err = service.compress(REQUEST, Algorithm::M2M).await.unwrap_err();
+        assert!(matches!(err, M2MError::Overloaded(_)));
+        assert!(err.is_retryable());
+        drop(held);
+
+        // Hold the only worker: the request queues, then misses its deadline
+        let service = service
+            .with_queue_depth(1)
+            .with_deadline(Duration::from_millis(20));
+        let worker = Arc::clone(&service.workers).try_acquire_owned().unwrap();
+        let err = service.compress(REQUEST, Algorithm::M2M).await.unwrap_err();
+        assert!(matches!(err, M2MError::Overloaded(_)));
+        drop(worker);
+
+        assert!(service.compress(REQUEST, Algorithm::M2M).await.is_ok());
+    }
+}
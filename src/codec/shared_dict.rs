@@ -0,0 +1,331 @@
+//! Shared compression dictionaries.
+//!
+//! Agents that exchange similar payloads (the same system prompts, tool
+//! schemas and response shapes) compress small messages far better when
+//! both sides prime the compressor with a dictionary of that content. A
+//! [`SharedDictionary`] is named by its version, `<id>:<sha256>`, so two
+//! agents only agree on a dictionary when their copies are byte-identical.
+//!
+//! Sessions advertise the versions in their [`DictionaryStore`] during the
+//! handshake and use the first common one for Brotli payloads, which then
+//! carry the version on the wire:
+//!
+//! ```text
+//! #M2M[v3.0]|DICT:<id>:<sha256>|DATA:<base64_brotli>
+//! ```
+//!
+//! A peer missing a dictionary can receive it in a `DICT_PUSH` message.
+//! The digest keeps a pushed dictionary from taking over the version of a
+//! different one, but says nothing about who sent it: stores accept
+//! pushes only from authenticated peers unless
+//! [`allow_anonymous_push`](DictionaryStore::allow_anonymous_push) is set,
+//! and cap the number and size of pushed dictionaries.
+
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+
+use sha2::{Digest, Sha256};
+
+use crate::error::{M2MError, Result};
+
+/// Advertised after an agent's dictionaries, so negotiation between agents
+/// without a common dictionary agrees on none instead of failing
+pub const NO_DICTIONARY: &str = "none";
+
+/// Largest dictionary accepted (4 MiB, the default Brotli window)
+pub const MAX_DICTIONARY_SIZE: usize = 4 * 1024 * 1024;
+
+/// Dictionaries a store accepts from `DICT_PUSH` by default
+pub const DEFAULT_MAX_PUSHED_DICTIONARIES: usize = 16;
+
+/// Total size of pushed dictionaries a store accepts by default (16 MiB)
+pub const DEFAULT_MAX_PUSHED_BYTES: usize = 16 * 1024 * 1024;
+
+/// A compression dictionary both agents hold
+#[derive(Debug, Clone)]
+pub struct SharedDictionary {
+    /// Name chosen by the dictionary's author
+    id: String,
+    /// Dictionary content
+    data: Arc<[u8]>,
+    /// SHA-256 of `data`
+    digest: [u8; 32],
+}
+
+impl SharedDictionary {
+    /// Create a dictionary
+    ///
+    /// The ID may contain ASCII letters, digits, `-`, `_` and `.`. Fails
+    /// with `Config` for an invalid ID, empty content, or content larger
+    /// than [`MAX_DICTIONARY_SIZE`].
+    pub fn new(id: &str, data: impl Into<Vec<u8>>) -> Result<Self> {
+        let valid_id = !id.is_empty()
+            && id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+        if !valid_id || id == NO_DICTIONARY {
+            return Err(M2MError::Config(format!("Invalid dictionary ID: {id:?}")));
+        }
+
+        let data = data.into();
+        if data.is_empty() || data.len() > MAX_DICTIONARY_SIZE {
+            return Err(M2MError::Config(format!(
+                "Dictionary {id} has {} bytes, expected 1 to {MAX_DICTIONARY_SIZE}",
+                data.len()
+            )));
+        }
+
+        Ok(Self {
+            id: id.to_string(),
+            digest: Sha256::digest(&data).into(),
+            data: data.into(),
+        })
+    }
+
+    /// Dictionary ID
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Dictionary content
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Content size in bytes
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Always `false`; dictionaries have content
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// SHA-256 of the content
+    pub fn digest(&self) -> &[u8; 32] {
+        &self.digest
+    }
+
+    /// Version advertised during the handshake: `<id>:<sha256 hex>`
+    pub fn version(&self) -> String {
+        let mut version = format!("{}:", self.id);
+        for byte in self.digest {
+            version.push_str(&format!("{byte:02x}"));
+        }
+        version
+    }
+}
+
+/// Contents of a [`DictionaryStore`]
+#[derive(Debug, Default)]
+struct Entries {
+    /// Dictionaries in insertion (preference) order
+    dictionaries: Vec<Arc<SharedDictionary>>,
+    /// Number of dictionaries received in `DICT_PUSH`
+    pushed: usize,
+    /// Total size of dictionaries received in `DICT_PUSH`
+    pushed_bytes: usize,
+}
+
+impl Entries {
+    /// Add a dictionary, returning `false` if its version is already present
+    fn insert(&mut self, dictionary: SharedDictionary) -> bool {
+        let version = dictionary.version();
+        if self.dictionaries.iter().any(|d| d.version() == version) {
+            return false;
+        }
+        self.dictionaries.push(Arc::new(dictionary));
+        true
+    }
+}
+
+/// Dictionaries an agent can compress with, in preference order
+///
+/// Shared between sessions, so a dictionary received in a `DICT_PUSH` is
+/// available to every later handshake.
+#[derive(Debug)]
+pub struct DictionaryStore {
+    /// Dictionaries and push accounting
+    entries: RwLock<Entries>,
+    /// Maximum pushed dictionaries
+    max_pushed: usize,
+    /// Maximum total size of pushed dictionaries
+    max_pushed_bytes: usize,
+    /// Accept pushes from peers without an authenticated principal
+    allow_anonymous_push: bool,
+}
+
+impl Default for DictionaryStore {
+    fn default() -> Self {
+        Self {
+            entries: RwLock::default(),
+            max_pushed: DEFAULT_MAX_PUSHED_DICTIONARIES,
+            max_pushed_bytes: DEFAULT_MAX_PUSHED_BYTES,
+            allow_anonymous_push: false,
+        }
+    }
+}
+
+impl DictionaryStore {
+    /// Create an empty store
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limit dictionaries accepted from `DICT_PUSH` by count and total size
+    ///
+    /// Default: [`DEFAULT_MAX_PUSHED_DICTIONARIES`] and
+    /// [`DEFAULT_MAX_PUSHED_BYTES`]. Dictionaries added locally do not
+    /// count.
+    pub fn with_push_limits(mut self, max_dictionaries: usize, max_bytes: usize) -> Self {
+        self.max_pushed = max_dictionaries;
+        self.max_pushed_bytes = max_bytes;
+        self
+    }
+
+    /// Accept `DICT_PUSH` from peers without an authenticated principal
+    ///
+    /// Any peer can then fill the push limits with dictionaries offered to
+    /// every later handshake; only enable it between trusted agents.
+    pub fn allow_anonymous_push(mut self) -> Self {
+        self.allow_anonymous_push = true;
+        self
+    }
+
+    /// Whether pushes from anonymous peers are accepted
+    pub fn accepts_anonymous_push(&self) -> bool {
+        self.allow_anonymous_push
+    }
+
+    /// Add a dictionary
+    pub fn with_dictionary(self, dictionary: SharedDictionary) -> Self {
+        self.insert(dictionary);
+        self
+    }
+
+    /// Load every file in `dir` as a dictionary named after its file stem
+    ///
+    /// Files are added in name order.
+    pub fn load_dir(dir: impl AsRef<Path>) -> Result<Self> {
+        let mut paths: Vec<_> = std::fs::read_dir(dir)?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.is_file())
+            .collect();
+        paths.sort();
+
+        let store = Self::new();
+        for path in paths {
+            let id = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .unwrap_or_default();
+            store.insert(SharedDictionary::new(id, std::fs::read(&path)?)?);
+        }
+        Ok(store)
+    }
+
+    /// Add a dictionary, returning `false` if its version is already present
+    pub fn insert(&self, dictionary: SharedDictionary) -> bool {
+        let Ok(mut entries) = self.entries.write() else {
+            return false;
+        };
+        entries.insert(dictionary)
+    }
+
+    /// Add a dictionary received in `DICT_PUSH`
+    ///
+    /// Returns `false` if its version is already present. Fails with
+    /// `Protocol` once the push limits (see
+    /// [`with_push_limits`](Self::with_push_limits)) would be exceeded.
+    pub fn insert_pushed(&self, dictionary: SharedDictionary) -> Result<bool> {
+        let mut entries = self
+            .entries
+            .write()
+            .map_err(|_| M2MError::Protocol("Dictionary store poisoned".to_string()))?;
+        let version = dictionary.version();
+        if entries.dictionaries.iter().any(|d| d.version() == version) {
+            return Ok(false);
+        }
+        if entries.pushed >= self.max_pushed
+            || entries.pushed_bytes + dictionary.len() > self.max_pushed_bytes
+        {
+            return Err(M2MError::Protocol(format!(
+                "Dictionary push limit reached ({} dictionaries, {} bytes)",
+                entries.pushed, entries.pushed_bytes
+            )));
+        }
+        entries.pushed += 1;
+        entries.pushed_bytes += dictionary.len();
+        Ok(entries.insert(dictionary))
+    }
+
+    /// Look up a dictionary by version
+    pub fn get(&self, version: &str) -> Option<Arc<SharedDictionary>> {
+        self.entries
+            .read()
+            .ok()?
+            .dictionaries
+            .iter()
+            .find(|d| d.version() == version)
+            .cloned()
+    }
+
+    /// Versions in preference order
+    pub fn versions(&self) -> Vec<String> {
+        self.entries
+            .read()
+            .map(|entries| entries.dictionaries.iter().map(|d| d.version()).collect())
+            .unwrap_or_default()
+    }
+
+    /// Number of dictionaries
+    pub fn len(&self) -> usize {
+        self.entries.read().map_or(0, |e| e.dictionaries.len())
+    }
+
+    /// Check if the store holds no dictionaries
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_version_and_store() {
+        let dict = SharedDictionary::new("chat-v1", r#"{"role":"user","content":""}"#).unwrap();
+        assert!(dict.version().starts_with("chat-v1:"));
+        assert_eq!(dict.version().len(), "chat-v1:".len() + 64);
+
+        assert!(SharedDictionary::new("", "x").is_err());
+        assert!(SharedDictionary::new("a:b", "x").is_err());
+        assert!(SharedDictionary::new(NO_DICTIONARY, "x").is_err());
+        assert!(SharedDictionary::new("empty", "").is_err());
+
+        let store = DictionaryStore::new().with_dictionary(dict.clone());
+        assert!(!store.insert(dict.clone()));
+        assert!(store.insert(SharedDictionary::new("chat-v1", "other content").unwrap()));
+        assert_eq!(store.len(), 2);
+        assert_eq!(store.versions()[0], dict.version());
+        assert_eq!(store.get(&dict.version()).unwrap().data(), dict.data());
+        assert!(store.get("chat-v1:00000000").is_none());
+    }
+
+    #[test]
+    fn test_push_limits() {
+        let store = DictionaryStore::new()
+            .with_dictionary(SharedDictionary::new("local", "x".repeat(64)).unwrap())
+            .with_push_limits(2, 100);
+        let pushed = |id: &str, size: usize| SharedDictionary::new(id, "y".repeat(size)).unwrap();
+
+        assert!(store.insert_pushed(pushed("a", 40)).unwrap());
+        assert!(!store.insert_pushed(pushed("a", 40)).unwrap());
+        assert!(store.insert_pushed(pushed("b", 80)).is_err());
+        assert!(store.insert_pushed(pushed("b", 60)).unwrap());
+        assert!(store.insert_pushed(pushed("c", 1)).is_err());
+        assert_eq!(store.len(), 3);
+    }
+}
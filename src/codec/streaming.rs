@@ -14,6 +14,21 @@
 //! data: [DONE]
 //! ```
 //!
+//! Anthropic streams name each event, and the name repeats the payload's
+//! `type`:
+//! ```text
+//! event: content_block_delta
+//! data: {"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"Hello"}}
+//!
+//! event: message_stop
+//! data: {"type":"message_stop"}
+//! ```
+//!
+//! With [`SseFormat::Anthropic`] the codec buffers chunks until an event is
+//! complete, so events split across network reads come out whole. It drops
+//! `event:` lines that repeat the payload type; the decompressor restores
+//! them.
+//!
 //! # Compression Strategies
 //!
 //! Three modes are available:
@@ -26,15 +41,18 @@
 //! latency per chunk. Full compression can be applied to the accumulated response.
 
 use super::m2m::M2MFrame;
+#[cfg(feature = "token-native")]
 use super::token_native::TokenNativeCodec;
+use super::AbbreviationTable;
+#[cfg(feature = "token-native")]
 use super::CompressionResult;
-use crate::codec::tables::{
-    KEY_ABBREV, KEY_EXPAND, MODEL_ABBREV, MODEL_EXPAND, ROLE_ABBREV, ROLE_EXPAND,
-};
+use crate::codec::tables::{ROLE_ABBREV, ROLE_EXPAND};
 use crate::error::{M2MError, Result};
+#[cfg(feature = "token-native")]
 use crate::models::Encoding;
 use bytes::Bytes;
 use serde_json::Value;
+use std::sync::Arc;
 
 /// Streaming compression mode
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
@@ -50,9 +68,22 @@ pub enum StreamingMode {
     Passthrough,
 }
 
+/// SSE stream dialect
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SseFormat {
+    /// OpenAI-style `data:` lines ending with `data: [DONE]`
+    #[default]
+    OpenAi,
+    /// Anthropic Messages events (`message_start`, `content_block_delta`,
+    /// `message_delta`, ...), each named by an `event:` line
+    Anthropic,
+}
+
 /// SSE event types
 #[derive(Debug, Clone, PartialEq)]
 pub enum SseEvent {
+    /// Event name (`event: <name>`) applying to the following data line
+    Event(String),
     /// Data event with JSON payload
     Data(Value),
     /// Stream complete marker
@@ -79,8 +110,15 @@ pub struct StreamingCodec {
     bytes_out: usize,
     /// Compression mode
     mode: StreamingMode,
+    /// Stream dialect
+    format: SseFormat,
+    /// Bytes of the incomplete event (Anthropic format)
+    pending: Vec<u8>,
     /// TokenNative codec (for TokenNative/Hybrid modes)
+    #[cfg(feature = "token-native")]
     token_native: TokenNativeCodec,
+    /// Key and model abbreviations
+    abbreviations: Arc<AbbreviationTable>,
 }
 
 impl Default for StreamingCodec {
@@ -98,10 +136,33 @@ impl StreamingCodec {
             bytes_in: 0,
             bytes_out: 0,
             mode: StreamingMode::Abbreviation,
+            format: SseFormat::OpenAi,
+            pending: Vec::new(),
+            #[cfg(feature = "token-native")]
             token_native: TokenNativeCodec::default(),
+            abbreviations: Arc::new(AbbreviationTable::builtin()),
         }
     }
 
+    /// Parse streams in the given dialect (default: [`SseFormat::OpenAi`])
+    pub fn with_format(mut self, format: SseFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Get the stream dialect
+    pub fn format(&self) -> SseFormat {
+        self.format
+    }
+
+    /// Abbreviate with a custom table (e.g. [`Session::abbreviations`])
+    ///
+    /// [`Session::abbreviations`]: crate::protocol::Session::abbreviations
+    pub fn with_abbreviations(mut self, table: Arc<AbbreviationTable>) -> Self {
+        self.abbreviations = table;
+        self
+    }
+
     /// Create codec with specific mode
     pub fn with_mode(mode: StreamingMode) -> Self {
         Self {
@@ -111,6 +172,7 @@ impl StreamingCodec {
     }
 
     /// Create codec with TokenNative mode and specific encoding
+    #[cfg(feature = "token-native")]
     pub fn token_native(encoding: Encoding) -> Self {
         Self {
             mode: StreamingMode::TokenNative,
@@ -120,6 +182,7 @@ impl StreamingCodec {
     }
 
     /// Create codec with Hybrid mode (abbreviation + final TokenNative)
+    #[cfg(feature = "token-native")]
     pub fn hybrid(encoding: Encoding) -> Self {
         Self {
             mode: StreamingMode::Hybrid,
@@ -153,6 +216,10 @@ impl StreamingCodec {
             return Some(SseEvent::Comment(line[1..].trim().to_string()));
         }
 
+        if let Some(name) = line.strip_prefix("event:") {
+            return Some(SseEvent::Event(name.trim().to_string()));
+        }
+
         if let Some(data) = line.strip_prefix("data: ") {
             if data == "[DONE]" {
                 return Some(SseEvent::Done);
@@ -170,7 +237,23 @@ impl StreamingCodec {
     }
 
     /// Process a raw SSE chunk (may contain multiple events)
+    ///
+    /// In the Anthropic format, an event split across chunks is emitted
+    /// with the chunk that completes it.
     pub fn process_chunk(&mut self, chunk: &[u8]) -> Result<Vec<Bytes>> {
+        if self.format == SseFormat::Anthropic {
+            self.bytes_in += chunk.len();
+            self.pending.extend_from_slice(chunk);
+
+            let mut outputs = Vec::new();
+            while let Some(end) = event_end(&self.pending) {
+                let event: Vec<u8> = self.pending.drain(..end).collect();
+                outputs.extend(self.process_named_event(&event)?);
+            }
+            self.chunks_processed += 1;
+            return Ok(outputs);
+        }
+
         let text = std::str::from_utf8(chunk)
             .map_err(|e| M2MError::Compression(format!("Invalid UTF-8: {}", e)))?;
 
@@ -192,6 +275,57 @@ impl StreamingCodec {
         Ok(outputs)
     }
 
+    /// Process the incomplete event left at the end of the stream
+    ///
+    /// Only the Anthropic format buffers; call this once the upstream
+    /// stream ends in case its last event lacks the closing blank line.
+    pub fn flush(&mut self) -> Result<Vec<Bytes>> {
+        let event = std::mem::take(&mut self.pending);
+        Ok(self.process_named_event(&event)?.into_iter().collect())
+    }
+
+    /// Process one complete Anthropic event (`event:` and `data:` lines)
+    fn process_named_event(&mut self, event: &[u8]) -> Result<Option<Bytes>> {
+        let text = std::str::from_utf8(event)
+            .map_err(|e| M2MError::Compression(format!("Invalid UTF-8: {}", e)))?;
+
+        let mut output = String::new();
+        let mut name = None;
+        for line in text.lines() {
+            match self.parse_sse_line(line) {
+                Some(SseEvent::Event(event_name)) => name = Some(event_name),
+                Some(SseEvent::Data(json)) => {
+                    if let Some(name) = name.take() {
+                        // The decompressor restores names repeating the type
+                        let redundant =
+                            json.get("type").and_then(Value::as_str) == Some(name.as_str());
+                        if !redundant || self.mode == StreamingMode::Passthrough {
+                            output.push_str(&format!("event: {}\n", name));
+                        }
+                    }
+                    if let Some(content) = self.extract_delta_content(&json) {
+                        self.accumulated_content.push_str(&content);
+                    }
+                    output.push_str(&format!("data: {}\n", self.encode_data(&json)?));
+                },
+                Some(SseEvent::Done) => output.push_str("data: [DONE]\n"),
+                Some(SseEvent::Comment(c)) => output.push_str(&format!(": {}\n", c)),
+                Some(SseEvent::Error(e)) => output.push_str(&format!("error: {}\n", e)),
+                None => {},
+            }
+        }
+        if let Some(name) = name {
+            output.push_str(&format!("event: {}\n", name));
+        }
+
+        if output.is_empty() {
+            return Ok(None);
+        }
+        output.push('\n');
+        self.bytes_out += output.len();
+        Ok(Some(Bytes::from(output)))
+    }
+
     /// Process a single SSE event
     fn process_event(&mut self, event: SseEvent) -> Result<Option<Bytes>> {
         match event {
@@ -201,36 +335,43 @@ impl StreamingCodec {
                     self.accumulated_content.push_str(&content);
                 }
 
-                match self.mode {
-                    StreamingMode::Passthrough => {
-                        // No compression
-                        Ok(Some(Bytes::from(format!(
-                            "data: {}\n\n",
-                            serde_json::to_string(&json).unwrap_or_default()
-                        ))))
-                    },
-                    StreamingMode::Abbreviation | StreamingMode::Hybrid => {
-                        // Lightweight key abbreviation
-                        let compressed = self.compress_sse_json(&json)?;
-                        Ok(Some(Bytes::from(format!("data: {}\n\n", compressed))))
-                    },
-                    StreamingMode::TokenNative => {
-                        // Full token-native compression per chunk
-                        let json_str = serde_json::to_string(&json)
-                            .map_err(|e| M2MError::Compression(e.to_string()))?;
-                        let result = self.token_native.compress(&json_str)?;
-                        Ok(Some(Bytes::from(format!("data: {}\n\n", result.data))))
-                    },
-                }
+                let data = self.encode_data(&json)?;
+                Ok(Some(Bytes::from(format!("data: {}\n\n", data))))
             },
+            SseEvent::Event(name) => Ok(Some(Bytes::from(format!("event: {}\n", name)))),
             SseEvent::Done => Ok(Some(Bytes::from_static(b"data: [DONE]\n\n"))),
             SseEvent::Comment(c) => Ok(Some(Bytes::from(format!(": {}\n", c)))),
             SseEvent::Error(e) => Ok(Some(Bytes::from(format!("error: {}\n\n", e)))),
         }
     }
 
+    /// Encode a data payload in the codec's mode
+    fn encode_data(&self, json: &Value) -> Result<String> {
+        match self.mode {
+            // No compression
+            StreamingMode::Passthrough => Ok(serde_json::to_string(json).unwrap_or_default()),
+            // Lightweight key abbreviation
+            StreamingMode::Abbreviation | StreamingMode::Hybrid => self.compress_sse_json(json),
+            StreamingMode::TokenNative => {
+                // Full token-native compression per chunk
+                #[cfg(feature = "token-native")]
+                {
+                    let json_str = serde_json::to_string(json)
+                        .map_err(|e| M2MError::Compression(e.to_string()))?;
+                    Ok(self.token_native.compress(&json_str)?.data)
+                }
+                #[cfg(not(feature = "token-native"))]
+                Err(crate::codec::Algorithm::TokenNative.unavailable())
+            },
+        }
+    }
+
     /// Extract delta content from a streaming response (handles both original and abbreviated keys)
     fn extract_delta_content(&self, json: &Value) -> Option<String> {
+        if let Some(text) = anthropic_text_delta(json) {
+            return Some(text.to_string());
+        }
+
         // Handle both original ("choices") and abbreviated ("C") keys
         json.get("choices")
             .or_else(|| json.get("C"))?
@@ -257,7 +398,10 @@ impl StreamingCodec {
                 let mut new_map = serde_json::Map::new();
                 for (key, val) in map {
                     let key_str = key.as_str();
-                    let new_key = KEY_ABBREV.get(key_str).copied().unwrap_or(key_str);
+                    let new_key = self
+                        .abbreviations
+                        .abbreviate_key(key_str)
+                        .unwrap_or(key_str);
                     let new_val = self.abbreviate_keys(val);
 
                     // Special handling for role values
@@ -274,8 +418,8 @@ impl StreamingCodec {
                     // Special handling for model values
                     } else if key == "model" {
                         if let Value::String(model) = &new_val {
-                            if let Some(abbrev) = MODEL_ABBREV.get(model.as_str()) {
-                                Value::String((*abbrev).to_string())
+                            if let Some(abbrev) = self.abbreviations.abbreviate_model(model) {
+                                Value::String(abbrev.to_string())
                             } else {
                                 new_val
                             }
@@ -308,6 +452,7 @@ impl StreamingCodec {
     /// using TokenNative for maximum compression.
     ///
     /// Returns the compression result with statistics.
+    #[cfg(feature = "token-native")]
     pub fn finalize_token_native(&self) -> Result<CompressionResult> {
         if self.accumulated_content.is_empty() {
             return Err(M2MError::Compression(
@@ -320,7 +465,8 @@ impl StreamingCodec {
     /// Finalize with raw bytes (no base64 overhead)
     ///
     /// For binary-safe channels, returns raw VarInt-encoded token IDs.
-    pub fn finalize_raw(&self) -> Vec<u8> {
+    #[cfg(feature = "token-native")]
+    pub fn finalize_raw(&self) -> Result<Vec<u8>> {
         self.token_native.compress_raw(&self.accumulated_content)
     }
 
@@ -373,6 +519,7 @@ impl StreamingCodec {
     /// Reset the codec state
     pub fn reset(&mut self) {
         self.accumulated_content.clear();
+        self.pending.clear();
         self.chunks_processed = 0;
         self.bytes_in = 0;
         self.bytes_out = 0;
@@ -394,13 +541,44 @@ pub struct StreamingStats {
     pub accumulated_length: usize,
 }
 
+/// Length of the first complete event in `buf`, through its blank line
+fn event_end(buf: &[u8]) -> Option<usize> {
+    let mut line_start = 0;
+    for (i, &byte) in buf.iter().enumerate() {
+        if byte == b'\n' {
+            if matches!(&buf[line_start..i], b"" | b"\r") {
+                return Some(i + 1);
+            }
+            line_start = i + 1;
+        }
+    }
+    None
+}
+
+/// Text of an Anthropic `content_block_delta` carrying a `text_delta`
+fn anthropic_text_delta(json: &Value) -> Option<&str> {
+    if json.get("type")?.as_str()? != "content_block_delta" {
+        return None;
+    }
+    let delta = json.get("delta")?;
+    if delta.get("type")?.as_str()? != "text_delta" {
+        return None;
+    }
+    delta.get("text")?.as_str()
+}
+
 /// Streaming decompressor for expanding abbreviated SSE
 #[derive(Debug)]
 pub struct StreamingDecompressor {
     /// Accumulated content
     accumulated_content: String,
+    /// Stream dialect
+    format: SseFormat,
     /// TokenNative codec for decoding
+    #[cfg(feature = "token-native")]
     token_native: TokenNativeCodec,
+    /// Key and model abbreviations
+    abbreviations: Arc<AbbreviationTable>,
 }
 
 impl Default for StreamingDecompressor {
@@ -414,24 +592,45 @@ impl StreamingDecompressor {
     pub fn new() -> Self {
         Self {
             accumulated_content: String::new(),
+            format: SseFormat::OpenAi,
+            #[cfg(feature = "token-native")]
             token_native: TokenNativeCodec::default(),
+            abbreviations: Arc::new(AbbreviationTable::builtin()),
         }
     }
 
+    /// Restore streams in the given dialect (must match the compressor's)
+    pub fn with_format(mut self, format: SseFormat) -> Self {
+        self.format = format;
+        self
+    }
+
     /// Create decompressor with specific encoding
+    #[cfg(feature = "token-native")]
     pub fn with_encoding(encoding: Encoding) -> Self {
         Self {
-            accumulated_content: String::new(),
             token_native: TokenNativeCodec::new(encoding),
+            ..Self::new()
         }
     }
 
+    /// Expand with a custom table (must match the sender's)
+    pub fn with_abbreviations(mut self, table: Arc<AbbreviationTable>) -> Self {
+        self.abbreviations = table;
+        self
+    }
+
     /// Decompress an SSE chunk (auto-detects format)
+    ///
+    /// In the Anthropic format, chunks must hold whole events, as
+    /// [`StreamingCodec`] emits them.
     pub fn decompress_chunk(&mut self, chunk: &[u8]) -> Result<Bytes> {
         let text = std::str::from_utf8(chunk)
             .map_err(|e| M2MError::Decompression(format!("Invalid UTF-8: {}", e)))?;
 
         let mut output = String::new();
+        // Whether the current event kept its `event:` line
+        let mut named = false;
 
         for line in text.lines() {
             if let Some(data) = line.strip_prefix("data: ") {
@@ -439,8 +638,9 @@ impl StreamingDecompressor {
                     output.push_str("data: [DONE]\n\n");
                 } else if data.starts_with("#TK|") {
                     // TokenNative format - decompress
-                    let decompressed = self.token_native.decompress(data)?;
+                    let decompressed = self.decompress_token_native(data)?;
                     if let Ok(json) = serde_json::from_str::<Value>(&decompressed) {
+                        self.restore_event_name(&mut output, &json, named);
                         // Extract content for accumulation
                         if let Some(content) = self.extract_delta_content(&json) {
                             self.accumulated_content.push_str(&content);
@@ -452,6 +652,7 @@ impl StreamingDecompressor {
                 } else if let Ok(json) = serde_json::from_str::<Value>(data) {
                     // Abbreviated JSON - expand keys
                     let expanded = self.expand_keys(&json);
+                    self.restore_event_name(&mut output, &expanded, named);
 
                     // Extract content for accumulation
                     if let Some(content) = self.extract_delta_content(&expanded) {
@@ -467,7 +668,9 @@ impl StreamingDecompressor {
                     output.push_str(line);
                     output.push_str("\n\n");
                 }
+                named = false;
             } else if !line.is_empty() {
+                named = line.starts_with("event:");
                 output.push_str(line);
                 output.push('\n');
             }
@@ -476,6 +679,29 @@ impl StreamingDecompressor {
         Ok(Bytes::from(output))
     }
 
+    /// Restore the `event:` line the compressor dropped from an Anthropic event
+    fn restore_event_name(&self, output: &mut String, json: &Value, named: bool) {
+        if self.format != SseFormat::Anthropic || named {
+            return;
+        }
+        if let Some(name) = json.get("type").and_then(Value::as_str) {
+            output.push_str(&format!("event: {}\n", name));
+        }
+    }
+
+    /// Decode a TokenNative event payload
+    #[cfg(feature = "token-native")]
+    fn decompress_token_native(&self, data: &str) -> Result<String> {
+        self.token_native.decompress(data)
+    }
+
+    /// Decode a TokenNative event payload
+    #[cfg(not(feature = "token-native"))]
+    #[allow(clippy::unused_self)]
+    fn decompress_token_native(&self, _data: &str) -> Result<String> {
+        Err(crate::codec::Algorithm::TokenNative.unavailable())
+    }
+
     /// Expand abbreviated keys back to full form
     fn expand_keys(&self, value: &Value) -> Value {
         match value {
@@ -483,7 +709,7 @@ impl StreamingDecompressor {
                 let mut new_map = serde_json::Map::new();
                 for (key, val) in map {
                     let key_str = key.as_str();
-                    let new_key = KEY_EXPAND.get(key_str).copied().unwrap_or(key_str);
+                    let new_key = self.abbreviations.expand_key(key_str).unwrap_or(key_str);
                     let new_val = self.expand_keys(val);
 
                     // Special handling for role values
@@ -500,8 +726,8 @@ impl StreamingDecompressor {
                     // Special handling for model values
                     } else if new_key == "model" {
                         if let Value::String(model) = &new_val {
-                            if let Some(expanded) = MODEL_EXPAND.get(model.as_str()) {
-                                Value::String((*expanded).to_string())
+                            if let Some(expanded) = self.abbreviations.expand_model(model) {
+                                Value::String(expanded.to_string())
                             } else {
                                 new_val
                             }
@@ -523,6 +749,10 @@ impl StreamingDecompressor {
 
     /// Extract delta content from JSON (handles both abbreviated and expanded keys)
     fn extract_delta_content(&self, json: &Value) -> Option<String> {
+        if let Some(text) = anthropic_text_delta(json) {
+            return Some(text.to_string());
+        }
+
         // Handle both expanded ("choices") and abbreviated ("C") keys
         json.get("choices")
             .or_else(|| json.get("C"))?
@@ -544,6 +774,7 @@ impl StreamingDecompressor {
 #[cfg(test)]
 mod tests {
     use super::*;
+    #[cfg(feature = "token-native")]
     use crate::models::Encoding;
 
     #[test]
@@ -676,6 +907,86 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_anthropic_events() {
+        let mut codec = StreamingCodec::new().with_format(SseFormat::Anthropic);
+        let mut decompressor = StreamingDecompressor::new().with_format(SseFormat::Anthropic);
+
+        let stream = concat!(
+            "event: message_start\n",
+            r#"data: {"type":"message_start","message":{"id":"msg_1","type":"message","role":"assistant","model":"claude-sonnet-4-5","content":[],"stop_reason":null}}"#,
+            "\n\nevent: content_block_start\n",
+            r#"data: {"type":"content_block_start","index":0,"content_block":{"type":"text","text":""}}"#,
+            "\n\nevent: ping\n",
+            r#"data: {"type": "ping"}"#,
+            "\n\nevent: content_block_delta\n",
+            r#"data: {"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"Hello"}}"#,
+            "\n\nevent: content_block_delta\n",
+            r#"data: {"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":" world"}}"#,
+            "\n\nevent: content_block_stop\n",
+            r#"data: {"type":"content_block_stop","index":0}"#,
+            "\n\nevent: message_delta\n",
+            r#"data: {"type":"message_delta","delta":{"stop_reason":"end_turn"},"usage":{"output_tokens":2}}"#,
+            "\n\nevent: message_stop\n",
+            r#"data: {"type":"message_stop"}"#,
+            "\n\n",
+        );
+
+        // Network reads split events mid-line
+        let mut outputs = Vec::new();
+        for chunk in stream.as_bytes().chunks(37) {
+            outputs.extend(codec.process_chunk(chunk).unwrap());
+        }
+        outputs.extend(codec.flush().unwrap());
+        assert_eq!(outputs.len(), 8);
+        assert_eq!(codec.accumulated_content(), "Hello world");
+
+        let mut restored = String::new();
+        for output in &outputs {
+            let text = std::str::from_utf8(output).unwrap();
+            assert!(text.ends_with("\n\n") && !text.contains("event:"));
+            restored.push_str(
+                std::str::from_utf8(&decompressor.decompress_chunk(output).unwrap()).unwrap(),
+            );
+        }
+        assert!(codec.stats().compression_ratio > 1.0);
+        assert_eq!(decompressor.accumulated_content(), "Hello world");
+
+        let events = |s: &str| -> Vec<(String, Value)> {
+            s.split("\n\n")
+                .filter(|e| !e.is_empty())
+                .map(|e| {
+                    let (name, data) = e.split_once('\n').unwrap();
+                    (
+                        name.strip_prefix("event: ").unwrap().to_string(),
+                        serde_json::from_str(data.strip_prefix("data: ").unwrap()).unwrap(),
+                    )
+                })
+                .collect()
+        };
+        assert_eq!(events(&restored), events(stream));
+    }
+
+    #[test]
+    fn test_anthropic_flush_and_custom_names() {
+        let mut codec = StreamingCodec::new().with_format(SseFormat::Anthropic);
+
+        // Names differing from the payload type are kept
+        let outputs = codec
+            .process_chunk(b"event: custom\ndata: {\"type\":\"ping\"}\n\nevent: message_stop\n")
+            .unwrap();
+        assert_eq!(outputs.len(), 1);
+        assert!(outputs[0].starts_with(b"event: custom\n"));
+
+        let outputs = codec
+            .process_chunk(br#"data: {"type":"message_stop"}"#)
+            .unwrap();
+        assert!(outputs.is_empty());
+        let outputs = codec.flush().unwrap();
+        assert_eq!(&outputs[0][..], b"data: {\"type\":\"message_stop\"}\n\n");
+        assert!(codec.flush().unwrap().is_empty());
+    }
+
     #[test]
     fn test_passthrough_mode() {
         let mut codec = StreamingCodec::passthrough();
@@ -690,6 +1001,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(feature = "token-native")]
     fn test_token_native_mode() {
         let mut codec = StreamingCodec::token_native(Encoding::Cl100kBase);
 
@@ -706,6 +1018,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(feature = "token-native")]
     fn test_hybrid_mode_finalize() {
         let mut codec = StreamingCodec::hybrid(Encoding::Cl100kBase);
 
@@ -729,6 +1042,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(feature = "token-native")]
     fn test_streaming_mode_selection() {
         let abbrev = StreamingCodec::new();
         assert_eq!(abbrev.mode(), StreamingMode::Abbreviation);
@@ -744,6 +1058,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(feature = "token-native")]
     fn test_decompress_token_native_chunk() {
         // First compress
         let mut codec = StreamingCodec::token_native(Encoding::Cl100kBase);
@@ -812,4 +1127,28 @@ mod tests {
         let decoded = m2m_codec.decode(&m2m_binary).unwrap();
         assert_eq!(decoded, response_json);
     }
+
+    #[test]
+    fn test_custom_abbreviations_roundtrip() {
+        let table = Arc::new(
+            AbbreviationTable::builtin()
+                .with_key("citations", "ci")
+                .unwrap()
+                .with_model("acme/support-ft-v3", "asf3")
+                .unwrap(),
+        );
+        let mut codec = StreamingCodec::new().with_abbreviations(Arc::clone(&table));
+        let mut decompressor = StreamingDecompressor::new().with_abbreviations(table);
+
+        let chunk = br#"data: {"model":"acme/support-ft-v3","citations":[1,2]}"#;
+        let outputs = codec.process_chunk(chunk).unwrap();
+        let compressed = std::str::from_utf8(&outputs[0]).unwrap();
+        assert!(compressed.contains(r#""ci":[1,2]"#));
+        assert!(compressed.contains(r#""asf3""#));
+
+        let expanded = decompressor.decompress_chunk(&outputs[0]).unwrap();
+        let text = std::str::from_utf8(&expanded).unwrap();
+        assert!(text.contains(r#""citations":[1,2]"#));
+        assert!(text.contains(r#""model":"acme/support-ft-v3""#));
+    }
 }
@@ -10,13 +10,25 @@
 //! ```
 //!
 //! - `#TK|` - Algorithm prefix
-//! - `<tokenizer_id>` - Single character identifying the tokenizer:
+//! - `<tokenizer_id>` - Vocabulary identifier:
 //!   - `C` = cl100k_base (canonical fallback)
 //!   - `O` = o200k_base
-//!   - `L` = Llama BPE
+//!   - `L` = Llama BPE (legacy, approximated with cl100k_base)
+//!   - `L3` = Llama 3 (128K vocabulary, loaded tokenizer)
+//!   - `MS` = Mistral (loaded tokenizer)
 //! - `|` - Separator
 //! - `<base64_varint_tokens>` - Base64-encoded VarInt token IDs
 //!
+//! # Loaded Vocabularies
+//!
+//! Llama 3 and Mistral vocabularies are not bundled. Load them with
+//! [`Llama3Tokenizer`](crate::inference::Llama3Tokenizer) and build the codec
+//! with [`TokenNativeCodec::from_tokenizer`]. Both peers must load the same
+//! vocabulary: decoding an `L3` or `MS` payload without it is an error rather
+//! than a silent mis-decode. HuggingFace tokenizers may normalize text, so
+//! compression with a loaded vocabulary verifies the round-trip and fails
+//! for content the vocabulary cannot reproduce exactly.
+//!
 //! # Compression Ratios
 //!
 //! | Content Type | Text Size | Wire Size | Compression |
@@ -40,14 +52,21 @@
 //!
 //! let decompressed = codec.decompress(&compressed.data).unwrap();
 //! assert_eq!(original, decompressed);
+//!
+//! // Llama 3 vocabulary
+//! let llama = Llama3Tokenizer::from_file("./tokenizer.json")?;
+//! let codec = TokenNativeCodec::from_tokenizer(boxed(llama))?;
+//! assert!(codec.compress(original)?.data.starts_with("#TK|L3|"));
 //! ```
 
 use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use std::fmt;
 use std::sync::OnceLock;
 use tiktoken_rs::{cl100k_base, o200k_base, CoreBPE};
 
 use super::{Algorithm, CompressionResult};
 use crate::error::{M2MError, Result};
+use crate::inference::{BoxedTokenizer, TokenizerType};
 use crate::models::Encoding;
 
 // Lazy-loaded tokenizer instances
@@ -62,19 +81,98 @@ fn get_o200k() -> &'static CoreBPE {
     O200K.get_or_init(|| o200k_base().expect("Failed to load o200k_base tokenizer"))
 }
 
+/// Vocabulary identified in the wire header
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum WireVocabulary {
+    /// Bundled tiktoken encoding
+    Builtin(Encoding),
+    /// Loaded HuggingFace vocabulary
+    Loaded(TokenizerType),
+}
+
+impl WireVocabulary {
+    /// Identifier for the text wire format
+    fn id(self) -> &'static str {
+        match self {
+            Self::Builtin(Encoding::O200kBase) => "O",
+            Self::Builtin(Encoding::LlamaBpe) => "L",
+            Self::Builtin(_) => "C", // Heuristic falls back to cl100k
+            Self::Loaded(TokenizerType::Mistral) => "MS",
+            Self::Loaded(_) => "L3",
+        }
+    }
+
+    /// Parse identifier from the text wire format
+    fn from_id(id: &str) -> Result<Self> {
+        match id {
+            "C" => Ok(Self::Builtin(Encoding::Cl100kBase)),
+            "O" => Ok(Self::Builtin(Encoding::O200kBase)),
+            "L" => Ok(Self::Builtin(Encoding::LlamaBpe)),
+            "L3" => Ok(Self::Loaded(TokenizerType::Llama3)),
+            "MS" => Ok(Self::Loaded(TokenizerType::Mistral)),
+            _ => Err(M2MError::Decompression(format!(
+                "Unknown tokenizer ID: {id}"
+            ))),
+        }
+    }
+
+    /// Identifier for the binary wire format
+    fn byte(self) -> u8 {
+        match self {
+            Self::Builtin(Encoding::O200kBase) => 1,
+            Self::Builtin(Encoding::LlamaBpe) => 2,
+            Self::Builtin(_) => 0,
+            Self::Loaded(TokenizerType::Mistral) => 4,
+            Self::Loaded(_) => 3,
+        }
+    }
+
+    /// Parse identifier from the binary wire format
+    fn from_byte(byte: u8) -> Result<Self> {
+        match byte {
+            0 => Ok(Self::Builtin(Encoding::Cl100kBase)),
+            1 => Ok(Self::Builtin(Encoding::O200kBase)),
+            2 => Ok(Self::Builtin(Encoding::LlamaBpe)),
+            3 => Ok(Self::Loaded(TokenizerType::Llama3)),
+            4 => Ok(Self::Loaded(TokenizerType::Mistral)),
+            _ => Err(M2MError::Decompression(format!(
+                "Unknown tokenizer byte: {byte}"
+            ))),
+        }
+    }
+}
+
 /// Token-native compression codec
 ///
 /// Compresses text by converting to token IDs and encoding with VarInt.
-#[derive(Debug, Clone, Copy)]
+/// Cloning is cheap; a loaded vocabulary is shared between clones.
+#[derive(Clone)]
 pub struct TokenNativeCodec {
     /// Tokenizer encoding to use
     encoding: Encoding,
+    /// Loaded vocabulary (Llama 3, Mistral), overrides `encoding`
+    vocabulary: Option<BoxedTokenizer>,
+}
+
+impl fmt::Debug for TokenNativeCodec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TokenNativeCodec")
+            .field("encoding", &self.encoding)
+            .field(
+                "vocabulary",
+                &self.vocabulary.as_ref().map(|t| t.tokenizer_type()),
+            )
+            .finish()
+    }
 }
 
 impl TokenNativeCodec {
     /// Create a new token-native codec with the specified encoding
     pub fn new(encoding: Encoding) -> Self {
-        Self { encoding }
+        Self {
+            encoding,
+            vocabulary: None,
+        }
     }
 
     /// Create codec with cl100k_base (canonical/default)
@@ -87,34 +185,69 @@ impl TokenNativeCodec {
         Self::new(Encoding::O200kBase)
     }
 
+    /// Create codec from a loaded tokenizer
+    ///
+    /// Llama 3 and Mistral tokenizers are used directly; tiktoken
+    /// tokenizers map to the bundled encodings.
+    ///
+    /// # Errors
+    ///
+    /// Returns error for the fallback tokenizer, which has no stable
+    /// vocabulary to put on the wire.
+    pub fn from_tokenizer(tokenizer: BoxedTokenizer) -> Result<Self> {
+        match tokenizer.tokenizer_type() {
+            TokenizerType::Llama3 | TokenizerType::Mistral => Ok(Self {
+                encoding: Encoding::LlamaBpe,
+                vocabulary: Some(tokenizer),
+            }),
+            TokenizerType::Cl100kBase => Ok(Self::cl100k()),
+            TokenizerType::O200kBase => Ok(Self::o200k()),
+            TokenizerType::Fallback => Err(M2MError::Tokenizer(
+                "Fallback tokenizer cannot be used for token-native compression".to_string(),
+            )),
+        }
+    }
+
     /// Get the encoding used by this codec
     pub fn encoding(&self) -> Encoding {
         self.encoding
     }
 
-    /// Get the tokenizer ID character for wire format
-    fn tokenizer_id(&self) -> char {
-        match self.encoding {
-            Encoding::Cl100kBase => 'C',
-            Encoding::O200kBase => 'O',
-            Encoding::LlamaBpe => 'L',
-            Encoding::Heuristic => 'C', // Fall back to cl100k
-        }
+    /// Get the loaded vocabulary type, if any
+    pub fn vocabulary(&self) -> Option<TokenizerType> {
+        self.vocabulary.as_ref().map(|t| t.tokenizer_type())
     }
 
-    /// Parse tokenizer ID from wire format
-    fn encoding_from_id(id: char) -> Encoding {
-        match id {
-            'C' => Encoding::Cl100kBase,
-            'O' => Encoding::O200kBase,
-            'L' => Encoding::LlamaBpe,
-            _ => Encoding::Cl100kBase, // Default fallback
+    /// Vocabulary this codec encodes with
+    fn wire_vocabulary(&self) -> WireVocabulary {
+        match self.vocabulary() {
+            Some(tokenizer_type) => WireVocabulary::Loaded(tokenizer_type),
+            None => WireVocabulary::Builtin(self.encoding),
         }
     }
 
+    /// Get the tokenizer ID for wire format
+    fn tokenizer_id(&self) -> &'static str {
+        self.wire_vocabulary().id()
+    }
+
     /// Tokenize text to token IDs
-    fn tokenize(&self, text: &str) -> Vec<u32> {
-        match self.encoding {
+    fn tokenize(&self, text: &str) -> Result<Vec<u32>> {
+        if let Some(tokenizer) = &self.vocabulary {
+            let tokens = tokenizer
+                .encode(text)
+                .map_err(|e| M2MError::Compression(format!("Tokenization failed: {e}")))?;
+            // HuggingFace tokenizers may normalize; never ship a lossy stream
+            if tokenizer.decode(&tokens).ok().as_deref() != Some(text) {
+                return Err(M2MError::Compression(format!(
+                    "{} vocabulary does not round-trip this content",
+                    tokenizer.tokenizer_type()
+                )));
+            }
+            return Ok(tokens);
+        }
+
+        Ok(match self.encoding {
             Encoding::Cl100kBase => get_cl100k().encode_with_special_tokens(text),
             Encoding::O200kBase => get_o200k().encode_with_special_tokens(text),
             Encoding::LlamaBpe => {
@@ -125,16 +258,31 @@ impl TokenNativeCodec {
                 // Fall back to cl100k
                 get_cl100k().encode_with_special_tokens(text)
             },
-        }
+        })
     }
 
     /// Detokenize token IDs back to text
     fn detokenize(&self, tokens: &[u32]) -> Result<String> {
-        let result = match self.encoding {
-            Encoding::Cl100kBase => get_cl100k().decode(tokens.to_vec()),
-            Encoding::O200kBase => get_o200k().decode(tokens.to_vec()),
-            Encoding::LlamaBpe => get_cl100k().decode(tokens.to_vec()),
-            Encoding::Heuristic => get_cl100k().decode(tokens.to_vec()),
+        self.detokenize_as(self.wire_vocabulary(), tokens)
+    }
+
+    /// Detokenize token IDs with the vocabulary named on the wire
+    fn detokenize_as(&self, vocabulary: WireVocabulary, tokens: &[u32]) -> Result<String> {
+        let result = match vocabulary {
+            WireVocabulary::Builtin(Encoding::O200kBase) => get_o200k().decode(tokens.to_vec()),
+            WireVocabulary::Builtin(_) => get_cl100k().decode(tokens.to_vec()),
+            WireVocabulary::Loaded(tokenizer_type) => {
+                return match &self.vocabulary {
+                    Some(tokenizer) if tokenizer.tokenizer_type() == tokenizer_type => {
+                        tokenizer.decode(tokens).map_err(|e| {
+                            M2MError::Decompression(format!("Detokenization failed: {e}"))
+                        })
+                    },
+                    _ => Err(M2MError::Decompression(format!(
+                        "{tokenizer_type} vocabulary is not loaded"
+                    ))),
+                };
+            },
         };
 
         result.map_err(|e| M2MError::Decompression(format!("Detokenization failed: {}", e)))
@@ -145,7 +293,7 @@ impl TokenNativeCodec {
         let original_bytes = text.len();
 
         // Tokenize
-        let tokens = self.tokenize(text);
+        let tokens = self.tokenize(text)?;
         let token_count = tokens.len();
 
         // Encode tokens as VarInt
@@ -165,10 +313,16 @@ impl TokenNativeCodec {
             compressed_bytes,
             original_tokens: Some(token_count),
             compressed_tokens: Some(token_count), // Same token count, fewer bytes
+            fallback_from: None,
+            brotli_quality: None,
         })
     }
 
     /// Decompress from token-native wire format
+    ///
+    /// The vocabulary named in the header is used, which may differ from
+    /// this codec's own. Loaded vocabularies must match the one this codec
+    /// was built with.
     pub fn decompress(&self, wire: &str) -> Result<String> {
         // Parse wire format: #TK|<id>|<data>
         let content = wire
@@ -176,18 +330,15 @@ impl TokenNativeCodec {
             .ok_or_else(|| M2MError::Decompression("Invalid token-native format".to_string()))?;
 
         // Extract tokenizer ID and data
-        let mut parts = content.splitn(2, '|');
-        let tokenizer_id = parts
-            .next()
-            .and_then(|s| s.chars().next())
-            .ok_or_else(|| M2MError::Decompression("Missing tokenizer ID".to_string()))?;
-
-        let encoded_data = parts
-            .next()
+        let (tokenizer_id, encoded_data) = content
+            .split_once('|')
             .ok_or_else(|| M2MError::Decompression("Missing encoded data".to_string()))?;
+        if tokenizer_id.is_empty() {
+            return Err(M2MError::Decompression("Missing tokenizer ID".to_string()));
+        }
 
-        // Determine encoding from wire format (may differ from self.encoding)
-        let wire_encoding = Self::encoding_from_id(tokenizer_id);
+        // Determine vocabulary from wire format (may differ from self.encoding)
+        let wire_vocabulary = WireVocabulary::from_id(tokenizer_id)?;
 
         // Decode base64
         let varint_bytes = BASE64
@@ -197,15 +348,13 @@ impl TokenNativeCodec {
         // Decode VarInt to token IDs
         let tokens = varint_decode(&varint_bytes)?;
 
-        // Create temporary codec with wire encoding for detokenization
-        let wire_codec = TokenNativeCodec::new(wire_encoding);
-        wire_codec.detokenize(&tokens)
+        self.detokenize_as(wire_vocabulary, &tokens)
     }
 
     /// Compress and return raw bytes (no wire format prefix)
-    pub fn compress_raw(&self, text: &str) -> Vec<u8> {
-        let tokens = self.tokenize(text);
-        varint_encode(&tokens)
+    pub fn compress_raw(&self, text: &str) -> Result<Vec<u8>> {
+        let tokens = self.tokenize(text)?;
+        Ok(varint_encode(&tokens))
     }
 
     /// Decompress from raw bytes
@@ -217,60 +366,42 @@ impl TokenNativeCodec {
     /// Compress to binary wire format (tokenizer ID + raw bytes)
     ///
     /// Binary format: `<tokenizer_byte><varint_tokens>`
-    /// - Byte 0: Tokenizer ID (0=cl100k, 1=o200k, 2=llama)
+    /// - Byte 0: Tokenizer ID (0=cl100k, 1=o200k, 2=llama, 3=llama3, 4=mistral)
     /// - Bytes 1+: VarInt-encoded token IDs
     ///
     /// Use this for binary-safe channels (WebSocket binary, QUIC, etc.)
     /// to achieve maximum compression (~50% of original).
-    pub fn compress_binary(&self, text: &str) -> Vec<u8> {
-        let tokens = self.tokenize(text);
+    pub fn compress_binary(&self, text: &str) -> Result<Vec<u8>> {
+        let tokens = self.tokenize(text)?;
         let mut result = Vec::with_capacity(1 + tokens.len() * 2);
 
         // Tokenizer ID byte
-        result.push(self.tokenizer_id_byte());
+        result.push(self.wire_vocabulary().byte());
 
         // VarInt-encoded tokens
         result.extend(varint_encode(&tokens));
 
-        result
+        Ok(result)
     }
 
-    /// Decompress from binary wire format
+    /// Decompress from binary wire format using bundled vocabularies
+    ///
+    /// Use [`decompress_binary_with`](Self::decompress_binary_with) for
+    /// payloads encoded with a loaded vocabulary.
     pub fn decompress_binary(bytes: &[u8]) -> Result<String> {
-        if bytes.is_empty() {
-            return Err(M2MError::Decompression("Empty binary data".to_string()));
-        }
-
-        // Extract tokenizer ID
-        let tokenizer_byte = bytes[0];
-        let encoding = Self::encoding_from_byte(tokenizer_byte);
-
-        // Decode tokens
-        let tokens = varint_decode(&bytes[1..])?;
-
-        // Create codec with correct encoding and detokenize
-        let codec = TokenNativeCodec::new(encoding);
-        codec.detokenize(&tokens)
+        Self::default().decompress_binary_with(bytes)
     }
 
-    /// Get tokenizer ID as byte for binary format
-    fn tokenizer_id_byte(&self) -> u8 {
-        match self.encoding {
-            Encoding::Cl100kBase => 0,
-            Encoding::O200kBase => 1,
-            Encoding::LlamaBpe => 2,
-            Encoding::Heuristic => 0, // Fall back to cl100k
-        }
-    }
+    /// Decompress from binary wire format, using this codec's loaded
+    /// vocabulary if the payload names it
+    pub fn decompress_binary_with(&self, bytes: &[u8]) -> Result<String> {
+        let (&tokenizer_byte, data) = bytes
+            .split_first()
+            .ok_or_else(|| M2MError::Decompression("Empty binary data".to_string()))?;
 
-    /// Parse encoding from byte
-    fn encoding_from_byte(byte: u8) -> Encoding {
-        match byte {
-            0 => Encoding::Cl100kBase,
-            1 => Encoding::O200kBase,
-            2 => Encoding::LlamaBpe,
-            _ => Encoding::Cl100kBase, // Default fallback
-        }
+        let vocabulary = WireVocabulary::from_byte(tokenizer_byte)?;
+        let tokens = varint_decode(data)?;
+        self.detokenize_as(vocabulary, &tokens)
     }
 }
 
@@ -457,7 +588,7 @@ mod tests {
         let codec = TokenNativeCodec::cl100k();
 
         let original = "Hello, world!";
-        let raw_bytes = codec.compress_raw(original);
+        let raw_bytes = codec.compress_raw(original).unwrap();
         let decompressed = codec.decompress_raw(&raw_bytes).unwrap();
 
         assert_eq!(original, decompressed);
@@ -472,13 +603,27 @@ mod tests {
         ] {
             let codec = TokenNativeCodec::new(encoding);
             let id = codec.tokenizer_id();
-            let recovered = TokenNativeCodec::encoding_from_id(id);
+            let recovered = WireVocabulary::from_id(id).unwrap();
             assert_eq!(
-                encoding, recovered,
+                WireVocabulary::Builtin(encoding),
+                recovered,
                 "Tokenizer ID roundtrip failed for {:?}",
                 encoding
             );
         }
+
+        for tokenizer_type in [TokenizerType::Llama3, TokenizerType::Mistral] {
+            let vocabulary = WireVocabulary::Loaded(tokenizer_type);
+            assert_eq!(
+                WireVocabulary::from_id(vocabulary.id()).unwrap(),
+                vocabulary
+            );
+            assert_eq!(
+                WireVocabulary::from_byte(vocabulary.byte()).unwrap(),
+                vocabulary
+            );
+        }
+        assert!(WireVocabulary::from_id("X").is_err());
     }
 
     #[test]
@@ -488,7 +633,7 @@ mod tests {
         let original = r#"{"model":"gpt-4o","messages":[{"role":"user","content":"Hello!"}]}"#;
 
         // Compress to binary
-        let binary = codec.compress_binary(original);
+        let binary = codec.compress_binary(original).unwrap();
 
         // First byte should be tokenizer ID (0 for cl100k)
         assert_eq!(binary[0], 0);
@@ -519,9 +664,78 @@ mod tests {
 
         for encoding in [Encoding::Cl100kBase, Encoding::O200kBase] {
             let codec = TokenNativeCodec::new(encoding);
-            let binary = codec.compress_binary(original);
+            let binary = codec.compress_binary(original).unwrap();
             let decompressed = TokenNativeCodec::decompress_binary(&binary).unwrap();
             assert_eq!(original, decompressed);
         }
     }
+
+    /// Byte-level BPE vocabulary without merges, in `tokenizer.json` form
+    fn byte_level_tokenizer(tokenizer_type: TokenizerType) -> BoxedTokenizer {
+        use crate::inference::{boxed, Llama3Tokenizer};
+        use tokenizers::pre_tokenizers::byte_level::ByteLevel;
+
+        let mut alphabet: Vec<char> = ByteLevel::alphabet().into_iter().collect();
+        alphabet.sort_unstable();
+        let vocab: serde_json::Map<String, serde_json::Value> = alphabet
+            .into_iter()
+            .enumerate()
+            .map(|(id, c)| (c.to_string(), id.into()))
+            .collect();
+        let byte_level = serde_json::json!({
+            "type": "ByteLevel",
+            "add_prefix_space": false,
+            "trim_offsets": false,
+            "use_regex": true
+        });
+        let json = serde_json::json!({
+            "version": "1.0",
+            "truncation": null,
+            "padding": null,
+            "added_tokens": [],
+            "normalizer": null,
+            "pre_tokenizer": byte_level,
+            "post_processor": null,
+            "decoder": byte_level,
+            "model": {"type": "BPE", "vocab": vocab, "merges": []}
+        });
+
+        let tokenizer = Llama3Tokenizer::from_json(&json.to_string())
+            .unwrap()
+            .with_type(tokenizer_type);
+        boxed(tokenizer)
+    }
+
+    #[test]
+    fn test_loaded_vocabulary_roundtrip() {
+        let original = r#"{"model":"llama-3","messages":[{"role":"user","content":"Héllo"}]}"#;
+
+        let llama =
+            TokenNativeCodec::from_tokenizer(byte_level_tokenizer(TokenizerType::Llama3)).unwrap();
+        let compressed = llama.compress(original).unwrap();
+        assert!(compressed.data.starts_with("#TK|L3|"));
+        assert_eq!(llama.decompress(&compressed.data).unwrap(), original);
+
+        let binary = llama.compress_binary(original).unwrap();
+        assert_eq!(binary[0], 3);
+        assert_eq!(llama.decompress_binary_with(&binary).unwrap(), original);
+
+        // Peers without the vocabulary refuse rather than mis-decode
+        let err = TokenNativeCodec::cl100k()
+            .decompress(&compressed.data)
+            .unwrap_err();
+        assert!(err.to_string().contains("llama3 vocabulary is not loaded"));
+        assert!(TokenNativeCodec::decompress_binary(&binary).is_err());
+
+        let mistral =
+            TokenNativeCodec::from_tokenizer(byte_level_tokenizer(TokenizerType::Mistral)).unwrap();
+        let compressed = mistral.compress(original).unwrap();
+        assert!(compressed.data.starts_with("#TK|MS|"));
+        assert!(llama.decompress(&compressed.data).is_err());
+        assert_eq!(mistral.decompress(&compressed.data).unwrap(), original);
+
+        // Built-in headers still decode with a loaded vocabulary
+        let cl100k = TokenNativeCodec::cl100k().compress(original).unwrap();
+        assert_eq!(mistral.decompress(&cl100k.data).unwrap(), original);
+    }
 }
@@ -0,0 +1,421 @@
+//! Tool-result packing for OpenAI-style tool calling.
+//!
+//! Agent loops feed tool results back as `role: "tool"` messages, and
+//! those results are often large JSON blobs: pretty-printed API responses,
+//! search hits, file listings. Parallel tool calls make repeats common,
+//! e.g. the same lookup issued twice in one turn, or a result resent on
+//! every later turn. [`ToolPacker`] handles them before compression:
+//!
+//! - **Minification**: tool results and `tool_calls[].function.arguments`
+//!   that hold JSON lose their insignificant whitespace. Key order,
+//!   numbers and strings are kept exactly.
+//! - **Cross-referencing**: a tool result identical to an earlier one in
+//!   the same request drops its `content` and names the earlier result's
+//!   `tool_call_id` instead:
+//!
+//!   ```text
+//!   {"role":"tool","tool_call_id":"call_2","m2m_same_as":"call_1"}
+//!   ```
+//!
+//! - **Base64 detection**: long base64 runs (screenshots, file contents)
+//!   are counted in the [`ToolReport`]. They barely compress, so callers can
+//!   route such traffic like other media-heavy payloads.
+//!
+//! [`ToolPacker::expand`] restores the references; the result is
+//! semantically identical to the original request. The reference is
+//! self-contained, so unlike history folding no state is kept between
+//! requests. [`CodecEngine`](super::CodecEngine) expands references when
+//! decompressing; `m2m_same_as` is reserved and [`ToolPacker::pack`]
+//! rejects requests that already use it.
+//!
+//! # Example
+//!
+//! ```rust
+//! use m2m::codec::ToolPacker;
+//!
+//! let result = r#"{\n  \"temp_c\": 21,\n  \"sky\": \"clear skies over the whole region\"\n}"#;
+//! let json = format!(
+//!     r#"{{"messages":[
+//!         {{"role":"tool","tool_call_id":"call_1","content":"{result}"}},
+//!         {{"role":"tool","tool_call_id":"call_2","content":"{result}"}}]}}"#
+//! );
+//!
+//! let (packed, report) = ToolPacker::new().pack(&json).unwrap();
+//! assert_eq!((report.minified, report.deduplicated), (2, 1));
+//!
+//! let restored = ToolPacker::expand(&packed, usize::MAX).unwrap();
+//! let restored: serde_json::Value = serde_json::from_str(&restored).unwrap();
+//! assert_eq!(restored["messages"][1]["content"], r#"{"temp_c":21,"sky":"clear skies over the whole region"}"#);
+//! ```
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::error::{M2MError, Result};
+
+/// Key naming the tool call whose result a packed message repeats
+pub const SAME_AS_KEY: &str = "m2m_same_as";
+
+/// Default minimum length of a base64 run worth reporting
+pub const DEFAULT_BASE64_MIN_LEN: usize = 256;
+
+/// Results shorter than this are not worth a reference
+const MIN_DEDUPE_LEN: usize = 32;
+
+/// Minifies, cross-references and inspects tool results in chat requests
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolPacker {
+    /// Strip whitespace from JSON tool results and call arguments
+    pub minify: bool,
+    /// Replace repeated tool results with references
+    pub dedupe: bool,
+    /// Minimum length of a base64 run counted in the report
+    pub base64_min_len: usize,
+}
+
+impl Default for ToolPacker {
+    fn default() -> Self {
+        Self {
+            minify: true,
+            dedupe: true,
+            base64_min_len: DEFAULT_BASE64_MIN_LEN,
+        }
+    }
+}
+
+/// What packing found and changed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolReport {
+    /// Tool result messages in the request
+    pub tool_results: usize,
+    /// Tool calls made by assistant messages
+    pub tool_calls: usize,
+    /// Results and call arguments minified
+    pub minified: usize,
+    /// Results replaced by a reference
+    pub deduplicated: usize,
+    /// Base64 runs found in tool results
+    pub base64_blobs: usize,
+    /// Bytes in those runs
+    pub base64_bytes: usize,
+    /// Request size before packing
+    pub original_bytes: usize,
+    /// Request size after packing
+    pub packed_bytes: usize,
+}
+
+impl ToolReport {
+    /// Bytes saved by packing
+    pub fn bytes_saved(&self) -> usize {
+        self.original_bytes.saturating_sub(self.packed_bytes)
+    }
+
+    /// Share of the request that is base64 inside tool results
+    pub fn base64_ratio(&self) -> f64 {
+        if self.original_bytes == 0 {
+            return 0.0;
+        }
+        self.base64_bytes as f64 / self.original_bytes as f64
+    }
+}
+
+impl ToolPacker {
+    /// Create with minification and cross-referencing enabled
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Enable or disable minification
+    pub fn with_minify(mut self, minify: bool) -> Self {
+        self.minify = minify;
+        self
+    }
+
+    /// Enable or disable cross-referencing of repeated results
+    pub fn with_dedupe(mut self, dedupe: bool) -> Self {
+        self.dedupe = dedupe;
+        self
+    }
+
+    /// Set the minimum length of a reported base64 run
+    pub fn with_base64_min_len(mut self, len: usize) -> Self {
+        self.base64_min_len = len;
+        self
+    }
+
+    /// Pack the tool results of a chat request
+    ///
+    /// Content that is not a chat request, or has nothing to change, is
+    /// returned unchanged. Fails if a message already uses the reserved
+    /// [`SAME_AS_KEY`].
+    pub fn pack(&self, json: &str) -> Result<(String, ToolReport)> {
+        let mut report = ToolReport {
+            original_bytes: json.len(),
+            packed_bytes: json.len(),
+            ..ToolReport::default()
+        };
+
+        let Ok(mut request) = serde_json::from_str::<Value>(json) else {
+            return Ok((json.to_string(), report));
+        };
+        let Some(messages) = request.get_mut("messages").and_then(Value::as_array_mut) else {
+            return Ok((json.to_string(), report));
+        };
+        if messages.iter().any(|m| m.get(SAME_AS_KEY).is_some()) {
+            return Err(M2MError::Compression(format!(
+                "Reserved key '{SAME_AS_KEY}' in request"
+            )));
+        }
+
+        // tool_call_id of the first result with each content
+        let mut seen: HashMap<String, String> = HashMap::new();
+        for message in messages.iter_mut() {
+            for arguments in call_arguments(message) {
+                report.tool_calls += 1;
+                if self.minify && minify_in_place(arguments) {
+                    report.minified += 1;
+                }
+            }
+
+            if !is_tool_result(message) {
+                continue;
+            }
+            report.tool_results += 1;
+            let Some(Value::String(content)) = message.get_mut("content") else {
+                continue;
+            };
+            if self.minify {
+                if let Some(minified) = minify_json(content) {
+                    *content = minified;
+                    report.minified += 1;
+                }
+            }
+            let (blobs, bytes) = base64_runs(content, self.base64_min_len);
+            report.base64_blobs += blobs;
+            report.base64_bytes += bytes;
+
+            let Some(id) = message.get("tool_call_id").and_then(Value::as_str) else {
+                continue;
+            };
+            let content = message["content"].as_str().unwrap_or_default();
+            if !self.dedupe || content.len() < MIN_DEDUPE_LEN {
+                continue;
+            }
+            match seen.get(content) {
+                Some(first) => {
+                    let first = Value::String(first.clone());
+                    if let Some(map) = message.as_object_mut() {
+                        map.remove("content");
+                        map.insert(SAME_AS_KEY.to_string(), first);
+                    }
+                    report.deduplicated += 1;
+                },
+                None => {
+                    seen.insert(content.to_string(), id.to_string());
+                },
+            }
+        }
+
+        if report.minified == 0 && report.deduplicated == 0 {
+            return Ok((json.to_string(), report));
+        }
+        let output = serde_json::to_string(&request)?;
+        report.packed_bytes = output.len();
+        Ok((output, report))
+    }
+
+    /// Restore tool results replaced by references
+    ///
+    /// Content without references is returned unchanged. Fails on a
+    /// reference to an unknown tool call, or if the restored request would
+    /// exceed `limit` bytes.
+    pub fn expand(json: &str, limit: usize) -> Result<Cow<'_, str>> {
+        if !json.contains(SAME_AS_KEY) {
+            return Ok(Cow::Borrowed(json));
+        }
+        let Ok(mut request) = serde_json::from_str::<Value>(json) else {
+            return Ok(Cow::Borrowed(json));
+        };
+        let Some(messages) = request.get_mut("messages").and_then(Value::as_array_mut) else {
+            return Ok(Cow::Borrowed(json));
+        };
+
+        let results: HashMap<String, String> = messages
+            .iter()
+            .filter(|m| is_tool_result(m))
+            .filter_map(|m| {
+                let id = m.get("tool_call_id")?.as_str()?;
+                let content = m.get("content")?.as_str()?;
+                Some((id.to_string(), content.to_string()))
+            })
+            .collect();
+
+        let mut size = json.len();
+        for message in messages.iter_mut() {
+            let Some(map) = message.as_object_mut() else {
+                continue;
+            };
+            let Some(reference) = map.remove(SAME_AS_KEY) else {
+                continue;
+            };
+            let id = reference.as_str().unwrap_or_default();
+            let content = results.get(id).ok_or_else(|| {
+                M2MError::Decompression(format!("Unknown tool result reference: {id}"))
+            })?;
+            size = size.saturating_add(content.len());
+            if size > limit {
+                return Err(M2MError::PayloadTooLarge { size, limit });
+            }
+            map.insert("content".to_string(), Value::String(content.clone()));
+        }
+
+        Ok(Cow::Owned(serde_json::to_string(&request)?))
+    }
+}
+
+/// Check if a message carries a tool (or legacy function) result
+fn is_tool_result(message: &Value) -> bool {
+    matches!(
+        message.get("role").and_then(Value::as_str),
+        Some("tool" | "function")
+    )
+}
+
+/// Argument strings of the tool calls an assistant message makes
+fn call_arguments(message: &mut Value) -> impl Iterator<Item = &mut Value> {
+    message
+        .get_mut("tool_calls")
+        .and_then(Value::as_array_mut)
+        .into_iter()
+        .flatten()
+        .filter_map(|call| call.pointer_mut("/function/arguments"))
+}
+
+/// Minify a JSON string value in place, returning whether it changed
+fn minify_in_place(value: &mut Value) -> bool {
+    let Value::String(text) = value else {
+        return false;
+    };
+    match minify_json(text) {
+        Some(minified) => {
+            *text = minified;
+            true
+        },
+        None => false,
+    }
+}
+
+/// `text` without insignificant whitespace, if it is a JSON object or
+/// array that has any
+///
+/// Works on the text itself, so key order and number spelling are kept.
+fn minify_json(text: &str) -> Option<String> {
+    let trimmed = text.trim_start();
+    if !(trimmed.starts_with('{') || trimmed.starts_with('[')) {
+        return None;
+    }
+    serde_json::from_str::<serde::de::IgnoredAny>(text).ok()?;
+
+    let mut out = String::with_capacity(text.len());
+    let mut in_string = false;
+    let mut escaped = false;
+    for c in text.chars() {
+        if in_string {
+            out.push(c);
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == '"' {
+                in_string = false;
+            }
+        } else if c == '"' {
+            in_string = true;
+            out.push(c);
+        } else if !matches!(c, ' ' | '\t' | '\n' | '\r') {
+            out.push(c);
+        }
+    }
+    (out.len() < text.len()).then_some(out)
+}
+
+/// Count base64 runs of at least `min_len` characters
+fn base64_runs(text: &str, min_len: usize) -> (usize, usize) {
+    let is_base64 = |b: u8| b.is_ascii_alphanumeric() || matches!(b, b'+' | b'/' | b'=');
+    text.as_bytes()
+        .split(|&b| !is_base64(b))
+        .filter(|run| run.len() >= min_len.max(1))
+        .fold((0, 0), |(blobs, bytes), run| (blobs + 1, bytes + run.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parallel_tool_results() {
+        let listing = "{\n  \"files\": [\"a.rs\", \"b.rs\"],\n  \"total\": 2.50\n}";
+        let request = json!({
+            "model": "gpt-4o",
+            "messages": [
+                {"role": "user", "content": "List the files twice"},
+                {"role": "assistant", "content": null, "tool_calls": [
+                    {"id": "call_1", "type": "function",
+                     "function": {"name": "ls", "arguments": "{ \"dir\": \".\" }"}},
+                    {"id": "call_2", "type": "function",
+                     "function": {"name": "ls", "arguments": "{\"dir\":\".\"}"}}
+                ]},
+                {"role": "tool", "tool_call_id": "call_1", "content": listing},
+                {"role": "tool", "tool_call_id": "call_2", "content": listing},
+                {"role": "tool", "tool_call_id": "call_3", "content": "plain text, not JSON"}
+            ]
+        })
+        .to_string();
+
+        let (packed, report) = ToolPacker::new().pack(&request).unwrap();
+        assert_eq!((report.tool_calls, report.tool_results), (2, 3));
+        assert_eq!((report.minified, report.deduplicated), (3, 1));
+        assert!(report.bytes_saved() > listing.len());
+
+        let value: Value = serde_json::from_str(&packed).unwrap();
+        let minified = r#"{"files":["a.rs","b.rs"],"total":2.50}"#;
+        assert_eq!(value["messages"][2]["content"], minified);
+        assert_eq!(value["messages"][3][SAME_AS_KEY], "call_1");
+        assert!(value["messages"][3].get("content").is_none());
+
+        let restored: Value =
+            serde_json::from_str(&ToolPacker::expand(&packed, usize::MAX).unwrap()).unwrap();
+        assert_eq!(restored["messages"][3]["content"], minified);
+        assert!(restored["messages"][3].get(SAME_AS_KEY).is_none());
+
+        // Expansion is bounded, and unknown references fail
+        assert!(matches!(
+            ToolPacker::expand(&packed, packed.len()),
+            Err(M2MError::PayloadTooLarge { .. })
+        ));
+        let dangling = packed.replace("\"m2m_same_as\":\"call_1\"", "\"m2m_same_as\":\"call_9\"");
+        assert!(ToolPacker::expand(&dangling, usize::MAX).is_err());
+        assert!(ToolPacker::new().pack(&packed).is_err());
+    }
+
+    #[test]
+    fn test_base64_and_passthrough() {
+        let image = "iVBORw0KGgo".repeat(40);
+        let request = json!({"messages": [
+            {"role": "tool", "tool_call_id": "call_1", "content": format!("screenshot: {image}")}
+        ]})
+        .to_string();
+        let (packed, report) = ToolPacker::new().pack(&request).unwrap();
+        assert_eq!(packed, request);
+        assert_eq!((report.base64_blobs, report.base64_bytes), (1, image.len()));
+        assert!(report.base64_ratio() > 0.5);
+
+        let plain = r#"{"model":"gpt-4o","messages":[{"role":"user","content":"Hi"}]}"#;
+        assert_eq!(ToolPacker::new().pack(plain).unwrap().0, plain);
+        assert_eq!(ToolPacker::expand(plain, 0).unwrap(), plain);
+    }
+}
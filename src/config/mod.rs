@@ -5,9 +5,11 @@
 //! - Environment variables
 
 use std::path::PathBuf;
+use std::time::Duration;
 
 use serde::{Deserialize, Serialize};
 
+use crate::codec::{AbbreviationTable, CompressionProfile, DefaultsNormalizer};
 use crate::error::{M2MError, Result};
 
 /// Main configuration struct
@@ -43,6 +45,16 @@ impl Config {
                 config.compression.min_tokens = val;
             }
         }
+        if let Ok(val) = std::env::var("M2M_COMPRESSION_PROFILE") {
+            if let Ok(profile) = val.parse() {
+                config.compression.profile = profile;
+            }
+        }
+        if let Ok(val) = std::env::var("M2M_MAX_COMPRESS_LATENCY_MS") {
+            if let Ok(val) = val.parse() {
+                config.compression.max_compress_latency_ms = Some(val);
+            }
+        }
 
         config
     }
@@ -77,8 +89,45 @@ pub struct CompressionConfig {
     /// Enable model abbreviation
     pub abbreviate_models: bool,
 
-    /// Remove default values
+    /// Remove parameters equal to the model's provider defaults
     pub remove_defaults: bool,
+
+    /// Parameters kept even at their default value
+    #[serde(default)]
+    pub keep_defaults: Vec<String>,
+
+    /// TOML file with custom abbreviations (see [`AbbreviationTable`])
+    #[serde(default)]
+    pub abbreviation_table: Option<PathBuf>,
+
+    /// Workload preset for automatic selection (see [`CompressionProfile`])
+    #[serde(default)]
+    pub profile: CompressionProfile,
+
+    /// Time budget for automatic compression in milliseconds (optional)
+    #[serde(default)]
+    pub max_compress_latency_ms: Option<u64>,
+}
+
+impl CompressionConfig {
+    /// Load the configured abbreviation table (built-in if none is set)
+    pub fn abbreviations(&self) -> Result<AbbreviationTable> {
+        match &self.abbreviation_table {
+            Some(path) => AbbreviationTable::load(path),
+            None => Ok(AbbreviationTable::builtin()),
+        }
+    }
+
+    /// Default-parameter normalizer, if `remove_defaults` is set
+    pub fn defaults_normalizer(&self) -> Option<DefaultsNormalizer> {
+        self.remove_defaults
+            .then(|| DefaultsNormalizer::new().with_kept_keys(self.keep_defaults.iter().cloned()))
+    }
+
+    /// Latency budget for `CodecEngine::with_max_compress_latency`
+    pub fn max_compress_latency(&self) -> Option<Duration> {
+        self.max_compress_latency_ms.map(Duration::from_millis)
+    }
 }
 
 impl Default for CompressionConfig {
@@ -91,6 +140,10 @@ impl Default for CompressionConfig {
             abbreviate_roles: true,
             abbreviate_models: true,
             remove_defaults: true,
+            keep_defaults: Vec::new(),
+            abbreviation_table: None,
+            profile: CompressionProfile::default(),
+            max_compress_latency_ms: None,
         }
     }
 }
@@ -144,5 +197,27 @@ mod tests {
         let config: Config = toml::from_str(toml).unwrap();
         assert_eq!(config.compression.min_tokens, 50);
         assert!(config.compression.enabled);
+        assert_eq!(config.compression.profile, CompressionProfile::Balanced);
+        assert_eq!(config.compression.max_compress_latency(), None);
+
+        let config: Config = toml::from_str(&toml.replace(
+            "remove_defaults = true",
+            "remove_defaults = true\nprofile = \"max-savings\"\nmax_compress_latency_ms = 50",
+        ))
+        .unwrap();
+        assert_eq!(config.compression.profile, CompressionProfile::MaxSavings);
+        assert_eq!(
+            config.compression.max_compress_latency(),
+            Some(Duration::from_millis(50))
+        );
+
+        let config: Config = toml::from_str(&toml.replace(
+            "remove_defaults = true",
+            "remove_defaults = true\nkeep_defaults = [\"stream\"]",
+        ))
+        .unwrap();
+        let normalizer = config.compression.defaults_normalizer().unwrap();
+        let json = r#"{"model":"gpt-4o","stream":false}"#;
+        assert_eq!(normalizer.normalize(json), json);
     }
 }
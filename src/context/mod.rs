@@ -0,0 +1,67 @@
+//! Pinned conversation contexts.
+//!
+//! Chat requests resend the whole conversation on every turn. An agent can
+//! instead pin a conversation prefix (system prompt, tool results, earlier
+//! turns) at its peer once, then send later requests with the prefix
+//! replaced by a reference to it, much like provider-side prompt caching:
+//!
+//! ```text
+//! Agent A                              Agent B (ContextStore)
+//!    |--- CONTEXT_PIN {handle, messages} -->|  stored for agent A
+//!    |<-- CONTEXT_PIN {handle, ttl_secs} ---|
+//!    |--- DATA {"m2m_context_ref":handle,   |
+//!    |          "messages":[<new turns>]} ->|  expanded before delivery
+//! ```
+//!
+//! Unlike [`HistoryWindow`](crate::codec::HistoryWindow), nothing is lost:
+//! the receiver restores the exact prefix before the application sees the
+//! request.
+//!
+//! # Store
+//!
+//! A [`ContextStore`] holds pinned prefixes per owner. The owner is the
+//! peer's authenticated principal, so a context pinned in one session can
+//! be referenced from a later session of the same agent; contexts of an
+//! anonymous peer belong to its session, since the agent ID it claims
+//! proves nothing. The store enforces [`ContextLimits`]:
+//!
+//! | Limit                | Default | Description                           |
+//! |----------------------|---------|---------------------------------------|
+//! | `ttl`                | 1 hour  | Idle lifetime, refreshed on each use  |
+//! | `max_context_bytes`  | 1 MiB   | Serialized size of one context        |
+//! | `max_owner_bytes`    | 8 MiB   | Total size of one owner's contexts    |
+//! | `max_owner_contexts` | 16      | Contexts one owner may hold           |
+//! | `max_total_bytes`    | 256 MiB | Total size of all contexts            |
+//! | `max_total_contexts` | 4096    | Contexts held for all owners          |
+//!
+//! Pins over a limit fail with [`M2MError::ContextQuotaExceeded`]; a
+//! reference to an unknown or expired context fails with
+//! [`M2MError::ContextNotFound`], after which the sender re-pins or sends
+//! the full request.
+//!
+//! # Usage
+//!
+//! ```rust,ignore
+//! use m2m::context::ContextStore;
+//!
+//! // Receiver
+//! let mut server = Session::new(caps).with_context_store(Arc::new(ContextStore::new()));
+//!
+//! // Sender: pin the shared prefix, then send only the new turns
+//! let pin = client.pin_context(prefix)?;
+//! client.process_message(&send(pin)?)?;
+//! let data = client.compress(&request)?; // prefix replaced by the handle
+//! ```
+//!
+//! [`M2MError::ContextQuotaExceeded`]: crate::error::M2MError::ContextQuotaExceeded
+//! [`M2MError::ContextNotFound`]: crate::error::M2MError::ContextNotFound
+
+mod reference;
+mod store;
+
+pub use reference::{expand_reference, PinnedContext, CONTEXT_REF_KEY};
+pub use store::{
+    ContextLimits, ContextStore, ContextUsage, DEFAULT_CONTEXT_TTL_SECS, DEFAULT_MAX_CONTEXT_BYTES,
+    DEFAULT_MAX_OWNER_BYTES, DEFAULT_MAX_OWNER_CONTEXTS, DEFAULT_MAX_TOTAL_BYTES,
+    DEFAULT_MAX_TOTAL_CONTEXTS,
+};
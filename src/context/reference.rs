@@ -0,0 +1,168 @@
+//! Context references in chat requests.
+
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::error::{M2MError, Result};
+
+/// Request key naming the pinned context its messages continue
+pub const CONTEXT_REF_KEY: &str = "m2m_context_ref";
+
+/// Longest accepted context handle
+pub(super) const MAX_HANDLE_LEN: usize = 64;
+
+/// Conversation prefix pinned at the peer
+///
+/// Kept by the sender to recognize requests that continue the prefix.
+/// Serializable, so an agent can resume the context in a later session.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PinnedContext {
+    /// Handle the peer stores the prefix under
+    pub handle: String,
+    /// Pinned messages
+    pub messages: Vec<Value>,
+}
+
+impl PinnedContext {
+    /// Create a context with a fresh random handle
+    pub fn new(messages: Vec<Value>) -> Self {
+        Self {
+            handle: format!("ctx_{}", uuid::Uuid::new_v4().simple()),
+            messages,
+        }
+    }
+
+    /// Replace the pinned prefix of a chat request with a reference
+    ///
+    /// `None` if `json` is not a chat request whose `messages` start with
+    /// the pinned messages.
+    pub fn reference(&self, json: &str) -> Option<String> {
+        if self.messages.is_empty() {
+            return None;
+        }
+        let mut request = serde_json::from_str::<Value>(json).ok()?;
+        let object = request.as_object_mut()?;
+        if object.contains_key(CONTEXT_REF_KEY) {
+            return None;
+        }
+        let messages = object.get_mut("messages")?.as_array_mut()?;
+        if !messages.starts_with(&self.messages) {
+            return None;
+        }
+
+        messages.drain(..self.messages.len());
+        object.insert(
+            CONTEXT_REF_KEY.to_string(),
+            Value::String(self.handle.clone()),
+        );
+        serde_json::to_string(&request).ok()
+    }
+}
+
+/// Check if a handle is well formed
+pub(super) fn is_valid_handle(handle: &str) -> bool {
+    !handle.is_empty()
+        && handle.len() <= MAX_HANDLE_LEN
+        && handle
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_'))
+}
+
+/// Restore the pinned prefix of a request carrying a context reference
+///
+/// `lookup` resolves the referenced handle. Returns `None` for requests
+/// without a reference.
+pub fn expand_reference(
+    json: &str,
+    lookup: impl FnOnce(&str) -> Result<Arc<[Value]>>,
+) -> Result<Option<String>> {
+    if !json.contains(CONTEXT_REF_KEY) {
+        return Ok(None);
+    }
+    let Ok(mut request) = serde_json::from_str::<Value>(json) else {
+        return Ok(None);
+    };
+    let Some(object) = request.as_object_mut() else {
+        return Ok(None);
+    };
+    let Some(reference) = object.remove(CONTEXT_REF_KEY) else {
+        return Ok(None);
+    };
+    let handle = reference
+        .as_str()
+        .ok_or_else(|| M2MError::InvalidMessage(format!("{CONTEXT_REF_KEY} must be a string")))?;
+    let prefix = lookup(handle)?;
+
+    let suffix = match object.remove("messages") {
+        Some(Value::Array(messages)) => messages,
+        None => Vec::new(),
+        Some(_) => {
+            return Err(M2MError::InvalidMessage(
+                "messages must be an array".to_string(),
+            ))
+        },
+    };
+    let mut messages = prefix.to_vec();
+    messages.extend(suffix);
+    object.insert("messages".to_string(), Value::Array(messages));
+    Ok(Some(serde_json::to_string(&request)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_reference_and_expand() {
+        let pinned = PinnedContext::new(vec![
+            json!({"role":"system","content":"You review contracts."}),
+            json!({"role":"user","content":"Clause 4?"}),
+        ]);
+        assert!(is_valid_handle(&pinned.handle));
+
+        let request = json!({
+            "model": "gpt-4o",
+            "messages": [
+                {"role":"system","content":"You review contracts."},
+                {"role":"user","content":"Clause 4?"},
+                {"role":"assistant","content":"It caps liability."},
+                {"role":"user","content":"And clause 5?"}
+            ]
+        })
+        .to_string();
+
+        let referenced = pinned.reference(&request).unwrap();
+        let value: Value = serde_json::from_str(&referenced).unwrap();
+        assert_eq!(value[CONTEXT_REF_KEY], pinned.handle.as_str());
+        assert_eq!(value["messages"].as_array().unwrap().len(), 2);
+        assert!(referenced.len() < request.len());
+
+        let stored: Arc<[Value]> = pinned.messages.clone().into();
+        let expanded = expand_reference(&referenced, |handle| {
+            assert_eq!(handle, pinned.handle);
+            Ok(Arc::clone(&stored))
+        })
+        .unwrap()
+        .unwrap();
+        assert_eq!(
+            serde_json::from_str::<Value>(&expanded).unwrap(),
+            serde_json::from_str::<Value>(&request).unwrap()
+        );
+
+        // Requests that do not continue the prefix, or carry no reference,
+        // pass through
+        let other = json!({"messages":[{"role":"user","content":"Clause 4?"}]}).to_string();
+        assert!(pinned.reference(&other).is_none());
+        assert!(expand_reference(&other, |_| unreachable!())
+            .unwrap()
+            .is_none());
+
+        let missing = expand_reference(&referenced, |handle| {
+            Err(M2MError::ContextNotFound(handle.to_string()))
+        });
+        assert!(matches!(missing, Err(M2MError::ContextNotFound(_))));
+    }
+}
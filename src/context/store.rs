@@ -0,0 +1,370 @@
+//! In-memory context store with TTL and size quotas.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use serde_json::Value;
+
+use super::reference::{is_valid_handle, MAX_HANDLE_LEN};
+use crate::error::{M2MError, Result};
+
+/// Default idle lifetime of a pinned context (1 hour)
+pub const DEFAULT_CONTEXT_TTL_SECS: u64 = 3600;
+
+/// Default largest context accepted (1 MiB serialized)
+pub const DEFAULT_MAX_CONTEXT_BYTES: usize = 1024 * 1024;
+
+/// Default total size of one owner's contexts (8 MiB)
+pub const DEFAULT_MAX_OWNER_BYTES: usize = 8 * 1024 * 1024;
+
+/// Default number of contexts one owner may hold
+pub const DEFAULT_MAX_OWNER_CONTEXTS: usize = 16;
+
+/// Default total size of all owners' contexts (256 MiB)
+pub const DEFAULT_MAX_TOTAL_BYTES: usize = 256 * 1024 * 1024;
+
+/// Default number of contexts the store holds for all owners
+pub const DEFAULT_MAX_TOTAL_CONTEXTS: usize = 4096;
+
+/// Lifetime and size limits of a [`ContextStore`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ContextLimits {
+    /// Idle lifetime; each use restarts it
+    pub ttl: Duration,
+    /// Largest context, in serialized bytes
+    pub max_context_bytes: usize,
+    /// Total serialized bytes of one owner's contexts
+    pub max_owner_bytes: usize,
+    /// Contexts one owner may hold
+    pub max_owner_contexts: usize,
+    /// Total serialized bytes of all contexts
+    pub max_total_bytes: usize,
+    /// Contexts held for all owners
+    pub max_total_contexts: usize,
+}
+
+impl Default for ContextLimits {
+    fn default() -> Self {
+        Self {
+            ttl: Duration::from_secs(DEFAULT_CONTEXT_TTL_SECS),
+            max_context_bytes: DEFAULT_MAX_CONTEXT_BYTES,
+            max_owner_bytes: DEFAULT_MAX_OWNER_BYTES,
+            max_owner_contexts: DEFAULT_MAX_OWNER_CONTEXTS,
+            max_total_bytes: DEFAULT_MAX_TOTAL_BYTES,
+            max_total_contexts: DEFAULT_MAX_TOTAL_CONTEXTS,
+        }
+    }
+}
+
+/// Contexts held by one owner
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct ContextUsage {
+    /// Unexpired contexts
+    pub contexts: usize,
+    /// Their total serialized size
+    pub bytes: usize,
+}
+
+/// Pinned context with expiry
+struct ContextEntry {
+    /// Pinned messages
+    messages: Arc<[Value]>,
+    /// Serialized size
+    bytes: usize,
+    /// Expiry time
+    expires_at: Instant,
+}
+
+/// Pinned conversation prefixes by owner and handle
+///
+/// Shared between sessions, so an agent can reference a context it pinned
+/// in an earlier session. Owners cannot see each other's contexts.
+///
+/// # Epistemic Properties
+///
+/// - **K_i**: Contexts are unexpired when resolved
+/// - **I^B**: Whether a sender's context is still held is unknown to the
+///   sender until it references it
+pub struct ContextStore {
+    /// Contexts by (owner, handle)
+    entries: Mutex<HashMap<(String, String), ContextEntry>>,
+    /// Lifetime and size limits
+    limits: ContextLimits,
+}
+
+impl Default for ContextStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl std::fmt::Debug for ContextStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ContextStore")
+            .field("contexts", &self.len())
+            .field("limits", &self.limits)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ContextStore {
+    /// Create an empty store with default limits
+    pub fn new() -> Self {
+        Self::with_limits(ContextLimits::default())
+    }
+
+    /// Create an empty store with `limits`
+    pub fn with_limits(limits: ContextLimits) -> Self {
+        Self {
+            entries: Mutex::new(HashMap::new()),
+            limits,
+        }
+    }
+
+    /// Lifetime and size limits
+    pub fn limits(&self) -> ContextLimits {
+        self.limits
+    }
+
+    /// Pin `messages` for `owner` under `handle`, returning its lifetime
+    ///
+    /// Pinning an existing handle replaces its context. Fails with
+    /// [`M2MError::ContextQuotaExceeded`] if the context, the owner's total
+    /// or the store's total would exceed the limits; expired contexts do
+    /// not count.
+    pub fn pin(&self, owner: &str, handle: &str, messages: Vec<Value>) -> Result<Duration> {
+        if !is_valid_handle(handle) {
+            return Err(M2MError::InvalidMessage(format!(
+                "Invalid context handle {handle:?} (1 to {MAX_HANDLE_LEN} of [A-Za-z0-9_-])"
+            )));
+        }
+        let bytes = serde_json::to_vec(&messages)?.len();
+        if bytes > self.limits.max_context_bytes {
+            return Err(M2MError::ContextQuotaExceeded(format!(
+                "Context has {bytes} bytes, limit is {}",
+                self.limits.max_context_bytes
+            )));
+        }
+
+        let mut entries = self.lock();
+        let now = Instant::now();
+        entries.retain(|_, entry| entry.expires_at > now);
+        let key = (owner.to_string(), handle.to_string());
+        let replaced = entries.get(&key).map(|entry| entry.bytes);
+        let usage = usage_of(&entries, owner);
+        if replaced.is_none() && usage.contexts >= self.limits.max_owner_contexts {
+            return Err(M2MError::ContextQuotaExceeded(format!(
+                "Owner already holds {} contexts",
+                usage.contexts
+            )));
+        }
+        let total = usage.bytes - replaced.unwrap_or(0) + bytes;
+        if total > self.limits.max_owner_bytes {
+            return Err(M2MError::ContextQuotaExceeded(format!(
+                "Owner would hold {total} bytes of context, limit is {}",
+                self.limits.max_owner_bytes
+            )));
+        }
+
+        // Many owners must not add up to more than the store can hold
+        if replaced.is_none() && entries.len() >= self.limits.max_total_contexts {
+            return Err(M2MError::ContextQuotaExceeded(format!(
+                "Store already holds {} contexts",
+                entries.len()
+            )));
+        }
+        let stored: usize = entries.values().map(|entry| entry.bytes).sum();
+        let stored = stored - replaced.unwrap_or(0) + bytes;
+        if stored > self.limits.max_total_bytes {
+            return Err(M2MError::ContextQuotaExceeded(format!(
+                "Store would hold {stored} bytes of context, limit is {}",
+                self.limits.max_total_bytes
+            )));
+        }
+
+        entries.insert(
+            key,
+            ContextEntry {
+                messages: messages.into(),
+                bytes,
+                expires_at: now + self.limits.ttl,
+            },
+        );
+        Ok(self.limits.ttl)
+    }
+
+    /// Look up a context, restarting its lifetime
+    ///
+    /// Fails with [`M2MError::ContextNotFound`] if `owner` holds no
+    /// unexpired context under `handle`.
+    pub fn resolve(&self, owner: &str, handle: &str) -> Result<Arc<[Value]>> {
+        let mut entries = self.lock();
+        let key = (owner.to_string(), handle.to_string());
+        let now = Instant::now();
+        match entries.get_mut(&key) {
+            Some(entry) if entry.expires_at > now => {
+                entry.expires_at = now + self.limits.ttl;
+                Ok(Arc::clone(&entry.messages))
+            },
+            Some(_) => {
+                entries.remove(&key);
+                Err(M2MError::ContextNotFound(format!("{handle} expired")))
+            },
+            None => Err(M2MError::ContextNotFound(handle.to_string())),
+        }
+    }
+
+    /// Drop a context, returning `false` if `owner` held none under `handle`
+    pub fn release(&self, owner: &str, handle: &str) -> bool {
+        self.lock()
+            .remove(&(owner.to_string(), handle.to_string()))
+            .is_some()
+    }
+
+    /// Unexpired contexts held by `owner`
+    pub fn usage(&self, owner: &str) -> ContextUsage {
+        let mut entries = self.lock();
+        let now = Instant::now();
+        entries.retain(|_, entry| entry.expires_at > now);
+        usage_of(&entries, owner)
+    }
+
+    /// Drop expired contexts, returning how many were removed
+    pub fn purge_expired(&self) -> usize {
+        let mut entries = self.lock();
+        let before = entries.len();
+        let now = Instant::now();
+        entries.retain(|_, entry| entry.expires_at > now);
+        before - entries.len()
+    }
+
+    /// Number of contexts held, including expired ones not yet purged
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Check if the store holds no contexts
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<(String, String), ContextEntry>> {
+        self.entries
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Contexts held by `owner` in `entries`
+fn usage_of(entries: &HashMap<(String, String), ContextEntry>, owner: &str) -> ContextUsage {
+    entries
+        .iter()
+        .filter(|((entry_owner, _), _)| entry_owner == owner)
+        .fold(ContextUsage::default(), |usage, (_, entry)| ContextUsage {
+            contexts: usage.contexts + 1,
+            bytes: usage.bytes + entry.bytes,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn turns(n: usize) -> Vec<Value> {
+        (0..n)
+            .map(|i| json!({"role":"user","content":format!("turn {i}")}))
+            .collect()
+    }
+
+    #[test]
+    fn test_quotas_and_owners() {
+        let store = ContextStore::with_limits(ContextLimits {
+            max_context_bytes: 150,
+            max_owner_bytes: 200,
+            max_owner_contexts: 2,
+            ..ContextLimits::default()
+        });
+
+        store.pin("agent-a", "ctx_1", turns(2)).unwrap();
+        assert_eq!(store.resolve("agent-a", "ctx_1").unwrap().len(), 2);
+        // Owners are isolated
+        assert!(matches!(
+            store.resolve("agent-b", "ctx_1"),
+            Err(M2MError::ContextNotFound(_))
+        ));
+
+        // One context too large, then the owner's total
+        assert!(matches!(
+            store.pin("agent-a", "ctx_2", turns(20)),
+            Err(M2MError::ContextQuotaExceeded(_))
+        ));
+        store.pin("agent-a", "ctx_2", turns(3)).unwrap();
+        assert!(matches!(
+            store.pin("agent-a", "ctx_2", turns(4)),
+            Err(M2MError::ContextQuotaExceeded(_))
+        ));
+
+        // Context count; replacing an existing handle is allowed
+        assert!(store.pin("agent-a", "ctx_3", turns(1)).is_err());
+        store.pin("agent-a", "ctx_2", turns(1)).unwrap();
+        assert_eq!(store.usage("agent-a").contexts, 2);
+        assert!(store.release("agent-a", "ctx_2"));
+        store.pin("agent-a", "ctx_3", turns(1)).unwrap();
+        store.pin("agent-b", "ctx_1", turns(1)).unwrap();
+
+        assert!(store.pin("agent-a", "bad handle", turns(1)).is_err());
+    }
+
+    #[test]
+    fn test_store_totals() {
+        let store = ContextStore::with_limits(ContextLimits {
+            max_total_bytes: 150,
+            max_total_contexts: 3,
+            ..ContextLimits::default()
+        });
+
+        // Each owner is within its quota, the store is not
+        store.pin("agent-a", "ctx_1", turns(2)).unwrap();
+        assert!(matches!(
+            store.pin("agent-b", "ctx_1", turns(3)),
+            Err(M2MError::ContextQuotaExceeded(_))
+        ));
+        store.pin("agent-b", "ctx_1", turns(1)).unwrap();
+        store.pin("agent-c", "ctx_1", turns(1)).unwrap();
+        assert!(matches!(
+            store.pin("agent-d", "ctx_1", turns(1)),
+            Err(M2MError::ContextQuotaExceeded(_))
+        ));
+
+        // Replacing a context does not add one
+        store.pin("agent-c", "ctx_1", turns(1)).unwrap();
+        assert_eq!(store.len(), 3);
+    }
+
+    #[test]
+    fn test_expiry() {
+        let store = ContextStore::with_limits(ContextLimits {
+            ttl: Duration::from_millis(100),
+            ..ContextLimits::default()
+        });
+        store.pin("agent-a", "ctx_1", turns(1)).unwrap();
+        store.pin("agent-a", "ctx_2", turns(1)).unwrap();
+
+        std::thread::sleep(Duration::from_millis(60));
+        store.resolve("agent-a", "ctx_1").unwrap();
+        std::thread::sleep(Duration::from_millis(60));
+
+        // ctx_1 was refreshed by use; ctx_2 expired
+        store.resolve("agent-a", "ctx_1").unwrap();
+        assert!(matches!(
+            store.resolve("agent-a", "ctx_2"),
+            Err(M2MError::ContextNotFound(_))
+        ));
+        std::thread::sleep(Duration::from_millis(120));
+        assert_eq!(store.purge_expired(), 1);
+        assert!(store.is_empty());
+    }
+}
@@ -0,0 +1,136 @@
+//! HTTP client for a remote agent directory.
+
+use reqwest::{Client, StatusCode};
+
+use super::directory::AgentQuery;
+use super::record::AgentRecord;
+use crate::codec::Algorithm;
+use crate::error::{M2MError, Result};
+
+/// Client for the `/discovery` endpoints of an M2M server
+#[derive(Debug, Clone)]
+pub struct DiscoveryClient {
+    /// HTTP client
+    client: Client,
+    /// Directory base URL (e.g. `http://registry:3000`)
+    base_url: String,
+    /// Admin token for removing other agents' records (optional)
+    admin_token: Option<String>,
+}
+
+impl DiscoveryClient {
+    /// Create a client for a directory server
+    pub fn new(base_url: &str) -> Self {
+        Self {
+            client: Client::new(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            admin_token: None,
+        }
+    }
+
+    /// Authenticate [`deregister`](Self::deregister) with the server's admin token
+    pub fn with_admin_token(mut self, token: impl Into<String>) -> Self {
+        self.admin_token = Some(token.into());
+        self
+    }
+
+    /// Register (or refresh) a record, returning the granted TTL in seconds
+    pub async fn register(&self, record: &AgentRecord) -> Result<u64> {
+        let response = self
+            .client
+            .post(format!("{}/discovery/agents", self.base_url))
+            .json(record)
+            .send()
+            .await?;
+
+        let status = response.status();
+        let body: serde_json::Value = response.json().await?;
+        if !status.is_success() {
+            return Err(M2MError::Upstream(format!(
+                "Registration failed ({status}): {}",
+                body["error"].as_str().unwrap_or("unknown error")
+            )));
+        }
+
+        Ok(body["ttl_secs"].as_u64().unwrap_or(0))
+    }
+
+    /// Resolve an agent by ID
+    ///
+    /// Returns `None` if the agent is not registered or its record expired.
+    pub async fn resolve(&self, agent_id: &str) -> Result<Option<AgentRecord>> {
+        let response = self
+            .client
+            .get(format!("{}/discovery/agents/{agent_id}", self.base_url))
+            .send()
+            .await?;
+
+        match response.status() {
+            StatusCode::NOT_FOUND => Ok(None),
+            status if status.is_success() => Ok(Some(response.json().await?)),
+            status => Err(M2MError::Upstream(format!("Lookup failed ({status})"))),
+        }
+    }
+
+    /// Query peers matching a filter
+    pub async fn query(&self, query: &AgentQuery) -> Result<Vec<AgentRecord>> {
+        let mut params = Vec::new();
+        if let Some(ref org) = query.org_id {
+            params.push(("org_id", org.clone()));
+        }
+        if let Some(algorithm) = query.algorithm {
+            params.push(("algorithm", algorithm_param(algorithm).unwrap_or_default()));
+        }
+
+        let response = self
+            .client
+            .get(format!("{}/discovery/agents", self.base_url))
+            .query(&params)
+            .send()
+            .await?;
+
+        let status = response.status();
+        if !status.is_success() {
+            return Err(M2MError::Upstream(format!("Query failed ({status})")));
+        }
+
+        Ok(response.json().await?)
+    }
+
+    /// Remove a record with the admin token (see [`with_admin_token`](Self::with_admin_token))
+    pub async fn deregister(&self, agent_id: &str) -> Result<()> {
+        let mut request = self
+            .client
+            .delete(format!("{}/discovery/agents/{agent_id}", self.base_url));
+        if let Some(ref token) = self.admin_token {
+            request = request.bearer_auth(token);
+        }
+        request.send().await?.error_for_status()?;
+        Ok(())
+    }
+
+    /// Remove our own record from a directory requiring signed records
+    ///
+    /// `record` is a signed record for the agent with a zero TTL and a
+    /// newer sequence number than its registration, e.g.
+    /// `AgentRecord::new(id, org).with_ttl(0).sign(&auth)?`.
+    pub async fn deregister_signed(&self, record: &AgentRecord) -> Result<()> {
+        self.client
+            .delete(format!(
+                "{}/discovery/agents/{}",
+                self.base_url, record.agent_id
+            ))
+            .json(record)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+/// Algorithm as it appears in query strings (serde name)
+fn algorithm_param(algorithm: Algorithm) -> Option<String> {
+    serde_json::to_value(algorithm)
+        .ok()
+        .and_then(|v| v.as_str().map(str::to_string))
+}
@@ -0,0 +1,414 @@
+//! In-memory agent directory with TTL-based expiry.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use serde::Deserialize;
+use tokio::sync::RwLock;
+
+use super::record::{unix_now, AgentRecord};
+use crate::codec::m2m::crypto::{HmacAuth, KeyMaterial};
+use crate::codec::Algorithm;
+use crate::error::{M2MError, Result};
+
+/// Default maximum lifetime granted to a record (1 hour)
+pub const DEFAULT_MAX_TTL_SECS: u64 = 3600;
+
+/// Default number of agents a directory holds
+pub const DEFAULT_MAX_RECORDS: usize = 10_000;
+
+/// Peer query filter (all fields optional)
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct AgentQuery {
+    /// Only agents in this organization
+    #[serde(default)]
+    pub org_id: Option<String>,
+    /// Only agents supporting this algorithm
+    #[serde(default)]
+    pub algorithm: Option<Algorithm>,
+}
+
+impl AgentQuery {
+    /// Check if a record matches the filter
+    pub fn matches(&self, record: &AgentRecord) -> bool {
+        self.org_id.as_ref().is_none_or(|org| *org == record.org_id)
+            && self.algorithm.is_none_or(|algo| record.supports(algo))
+    }
+}
+
+/// Directory entry with expiry
+struct DirectoryEntry {
+    /// Registered record (or the signed deregistration that removed it)
+    record: AgentRecord,
+    /// Expiry time
+    expires_at: Instant,
+    /// Until when the entry is kept after expiry, so that replaying an
+    /// older signed record is detected while it would still be fresh
+    forget_at: Instant,
+}
+
+/// Registry of agents and their endpoints
+///
+/// # Epistemic Properties
+///
+/// - **K_i**: Records are unexpired when returned
+/// - **B_i**: Unsigned directories trust registrants; set a registration key
+///   to require signed records
+/// - **K_i**: A signed directory accepts each agent's records in increasing
+///   sequence order only, so captured records cannot be replayed
+pub struct AgentDirectory {
+    /// Records by agent ID
+    records: RwLock<HashMap<String, DirectoryEntry>>,
+    /// Key for verifying registration signatures (optional)
+    registration_key: Option<HmacAuth>,
+    /// Maximum lifetime granted to a record
+    max_ttl: Duration,
+    /// Maximum agents held, including expired entries kept for replay checks
+    max_records: usize,
+}
+
+impl Default for AgentDirectory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AgentDirectory {
+    /// Create an empty directory accepting unsigned records
+    pub fn new() -> Self {
+        Self {
+            records: RwLock::new(HashMap::new()),
+            registration_key: None,
+            max_ttl: Duration::from_secs(DEFAULT_MAX_TTL_SECS),
+            max_records: DEFAULT_MAX_RECORDS,
+        }
+    }
+
+    /// Require records signed with this key
+    pub fn with_registration_key(mut self, key: KeyMaterial) -> Result<Self> {
+        let auth = HmacAuth::new(key).map_err(|e| M2MError::Config(e.to_string()))?;
+        self.registration_key = Some(auth);
+        Ok(self)
+    }
+
+    /// Set maximum lifetime granted to a record
+    pub fn with_max_ttl(mut self, max_ttl: Duration) -> Self {
+        self.max_ttl = max_ttl;
+        self
+    }
+
+    /// Set maximum number of agents held (default: [`DEFAULT_MAX_RECORDS`])
+    pub fn with_max_records(mut self, max_records: usize) -> Self {
+        self.max_records = max_records;
+        self
+    }
+
+    /// Check if registrations must be signed
+    pub fn requires_signature(&self) -> bool {
+        self.registration_key.is_some()
+    }
+
+    /// Register or refresh an agent
+    ///
+    /// Returns the granted lifetime (requested TTL capped at the maximum).
+    /// Signed records older than the maximum TTL, or not newer (by
+    /// sequence number) than the last record of the same agent, are
+    /// rejected as replays. Fails with `Overloaded` when a new agent does
+    /// not fit in the directory.
+    pub async fn register(&self, record: AgentRecord) -> Result<Duration> {
+        if record.agent_id.is_empty() {
+            return Err(M2MError::InvalidMessage(
+                "Record missing agent ID".to_string(),
+            ));
+        }
+        if record.endpoints.is_empty() {
+            return Err(M2MError::InvalidMessage(format!(
+                "Record for {} has no endpoints",
+                record.agent_id
+            )));
+        }
+
+        let signed = self.check_signature(&record)?;
+
+        let ttl = Duration::from_secs(record.ttl_secs).min(self.max_ttl);
+        let now = Instant::now();
+        let mut records = self.records.write().await;
+        if let Some(existing) = records.get(&record.agent_id) {
+            if signed {
+                check_sequence(existing, &record)?;
+            }
+        } else if records.len() >= self.max_records {
+            records.retain(|_, entry| entry.forget_at > now);
+            if records.len() >= self.max_records {
+                return Err(M2MError::Overloaded(format!(
+                    "Directory holds {} agents",
+                    records.len()
+                )));
+            }
+        }
+
+        let expires_at = now + ttl;
+        let forget_at = if signed {
+            expires_at.max(self.fresh_until(&record, now))
+        } else {
+            expires_at
+        };
+        records.insert(
+            record.agent_id.clone(),
+            DirectoryEntry {
+                record,
+                expires_at,
+                forget_at,
+            },
+        );
+        Ok(ttl)
+    }
+
+    /// Remove an agent with a signed record of zero TTL
+    ///
+    /// The record is checked like a registration (signature, freshness,
+    /// sequence), so only the key holder can deregister and a captured
+    /// deregistration cannot be replayed after the agent registers again.
+    /// Returns `false` if the agent was not registered.
+    pub async fn deregister_signed(&self, record: AgentRecord) -> Result<bool> {
+        if !self.requires_signature() {
+            return Err(M2MError::InvalidMessage(
+                "Directory has no registration key".to_string(),
+            ));
+        }
+        if record.ttl_secs != 0 {
+            return Err(M2MError::InvalidMessage(format!(
+                "Deregistration of {} must have a zero TTL",
+                record.agent_id
+            )));
+        }
+        self.check_signature(&record)?;
+
+        let now = Instant::now();
+        let mut records = self.records.write().await;
+        let Some(existing) = records.get_mut(&record.agent_id) else {
+            return Ok(false);
+        };
+        check_sequence(existing, &record)?;
+        let registered = existing.expires_at > now;
+        existing.forget_at = existing.forget_at.max(self.fresh_until(&record, now));
+        existing.expires_at = now;
+        existing.record = record;
+        Ok(registered)
+    }
+
+    /// Verify a record's signature and freshness if registrations are
+    /// signed, returning whether they are
+    fn check_signature(&self, record: &AgentRecord) -> Result<bool> {
+        let Some(ref auth) = self.registration_key else {
+            return Ok(false);
+        };
+        record.verify(auth)?;
+        if unix_now().saturating_sub(record.issued_at) > self.max_ttl.as_secs() {
+            return Err(M2MError::InvalidMessage(format!(
+                "Record for {} is stale",
+                record.agent_id
+            )));
+        }
+        Ok(true)
+    }
+
+    /// When a signed record becomes too old to be accepted
+    fn fresh_until(&self, record: &AgentRecord, now: Instant) -> Instant {
+        let age = Duration::from_secs(unix_now().saturating_sub(record.issued_at));
+        now + self.max_ttl.saturating_sub(age)
+    }
+
+    /// Look up an agent by ID
+    pub async fn lookup(&self, agent_id: &str) -> Option<AgentRecord> {
+        self.records
+            .read()
+            .await
+            .get(agent_id)
+            .filter(|entry| entry.expires_at > Instant::now())
+            .map(|entry| entry.record.clone())
+    }
+
+    /// List unexpired agents matching a query
+    pub async fn query(&self, query: &AgentQuery) -> Vec<AgentRecord> {
+        let now = Instant::now();
+        let mut records: Vec<AgentRecord> = self
+            .records
+            .read()
+            .await
+            .values()
+            .filter(|entry| entry.expires_at > now && query.matches(&entry.record))
+            .map(|entry| entry.record.clone())
+            .collect();
+
+        records.sort_by(|a, b| a.agent_id.cmp(&b.agent_id));
+        records
+    }
+
+    /// Remove an agent without a signed record (e.g. by an administrator)
+    ///
+    /// Returns `false` if the agent was not registered. The entry is kept
+    /// until its last record could no longer be replayed.
+    pub async fn deregister(&self, agent_id: &str) -> bool {
+        let now = Instant::now();
+        match self.records.write().await.get_mut(agent_id) {
+            Some(entry) if entry.expires_at > now => {
+                entry.expires_at = now;
+                true
+            },
+            _ => false,
+        }
+    }
+
+    /// Get number of registered agents (including expired, until cleanup)
+    pub async fn count(&self) -> usize {
+        self.records.read().await.len()
+    }
+
+    /// Remove expired records no longer needed for replay checks
+    pub async fn cleanup(&self) -> usize {
+        let mut records = self.records.write().await;
+        let before = records.len();
+        let now = Instant::now();
+
+        records.retain(|_, entry| entry.forget_at > now);
+
+        before - records.len()
+    }
+}
+
+/// Reject a signed record not newer than the agent's last one
+fn check_sequence(existing: &DirectoryEntry, record: &AgentRecord) -> Result<()> {
+    if record.sequence <= existing.record.sequence {
+        return Err(M2MError::InvalidMessage(format!(
+            "Record for {} replays sequence {} (last {})",
+            record.agent_id, record.sequence, existing.record.sequence
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(agent_id: &str, org_id: &str) -> AgentRecord {
+        AgentRecord::new(agent_id, org_id).with_endpoint("http://127.0.0.1:3000")
+    }
+
+    #[tokio::test]
+    async fn test_register_and_query() {
+        let directory = AgentDirectory::new();
+        directory.register(record("a1", "acme")).await.unwrap();
+        directory
+            .register(record("a2", "acme").with_algorithms(vec![Algorithm::Brotli]))
+            .await
+            .unwrap();
+        directory.register(record("b1", "globex")).await.unwrap();
+
+        assert_eq!(directory.lookup("a1").await.unwrap().org_id, "acme");
+        assert!(directory.lookup("missing").await.is_none());
+
+        let acme = AgentQuery {
+            org_id: Some("acme".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(directory.query(&acme).await.len(), 2);
+
+        let m2m = AgentQuery {
+            algorithm: Some(Algorithm::M2M),
+            ..Default::default()
+        };
+        let ids: Vec<_> = directory
+            .query(&m2m)
+            .await
+            .into_iter()
+            .map(|r| r.agent_id)
+            .collect();
+        assert_eq!(ids, vec!["a1", "b1"]);
+
+        assert!(directory.deregister("a1").await);
+        assert!(directory.lookup("a1").await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_ttl_expiry() {
+        let directory = AgentDirectory::new().with_max_ttl(Duration::from_millis(10));
+        let granted = directory.register(record("a1", "acme")).await.unwrap();
+        assert_eq!(granted, Duration::from_millis(10));
+
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(directory.lookup("a1").await.is_none());
+        assert_eq!(directory.cleanup().await, 1);
+        assert_eq!(directory.count().await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_signed_registration() {
+        let key = KeyMaterial::new(vec![3u8; 32]);
+        let directory = AgentDirectory::new()
+            .with_registration_key(key.clone())
+            .unwrap();
+        let auth = HmacAuth::new(key).unwrap();
+
+        assert!(directory.register(record("a1", "acme")).await.is_err());
+        let signed = record("a1", "acme").sign(&auth).unwrap();
+        assert!(directory.register(signed).await.is_ok());
+
+        // Stale records are rejected even if correctly signed
+        let mut stale = record("a2", "acme");
+        stale.issued_at -= DEFAULT_MAX_TTL_SECS + 1;
+        assert!(directory
+            .register(stale.sign(&auth).unwrap())
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_signed_records_cannot_be_replayed() {
+        let key = KeyMaterial::new(vec![3u8; 32]);
+        let directory = AgentDirectory::new()
+            .with_registration_key(key.clone())
+            .unwrap();
+        let auth = HmacAuth::new(key).unwrap();
+        let signed = |sequence: u64, ttl: u64| {
+            record("a1", "acme")
+                .with_sequence(sequence)
+                .with_ttl(ttl)
+                .sign(&auth)
+                .unwrap()
+        };
+
+        let first = signed(1, 300);
+        directory.register(first.clone()).await.unwrap();
+        assert!(directory.register(first.clone()).await.is_err());
+
+        // Deregistration needs a zero TTL, a signature and a newer sequence
+        assert!(directory.deregister_signed(signed(2, 300)).await.is_err());
+        assert!(directory
+            .deregister_signed(record("a1", "acme").with_sequence(2).with_ttl(0))
+            .await
+            .is_err());
+        assert!(directory.deregister_signed(signed(2, 0)).await.unwrap());
+        assert!(directory.lookup("a1").await.is_none());
+
+        // The removed registration cannot be replayed
+        assert!(directory.register(first).await.is_err());
+        directory.register(signed(3, 300)).await.unwrap();
+        assert!(directory.lookup("a1").await.is_some());
+        assert!(directory.deregister_signed(signed(2, 0)).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_max_records() {
+        let directory = AgentDirectory::new().with_max_records(2);
+        directory.register(record("a1", "acme")).await.unwrap();
+        directory.register(record("a2", "acme")).await.unwrap();
+        assert!(matches!(
+            directory.register(record("a3", "acme")).await,
+            Err(M2MError::Overloaded(_))
+        ));
+        // Refreshing a known agent still works
+        directory.register(record("a1", "acme")).await.unwrap();
+    }
+}
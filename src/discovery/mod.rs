@@ -0,0 +1,63 @@
+//! Agent directory and peer discovery.
+//!
+//! Agents register a record (agent ID, organization, public key, supported
+//! algorithms, transport endpoints) with a directory, and peers resolve
+//! agents by name instead of hardcoding addresses before sending HELLO.
+//!
+//! # Records
+//!
+//! | Field        | Description                                   |
+//! |--------------|-----------------------------------------------|
+//! | `agent_id`   | Lookup key                                    |
+//! | `org_id`     | Organization (query filter)                   |
+//! | `public_key` | X25519 public key (hex) for key exchange      |
+//! | `algorithms` | Supported compression algorithms              |
+//! | `endpoints`  | URLs in preference order (`http://`, `quic://`) |
+//! | `ttl_secs`   | Requested lifetime; re-register to refresh    |
+//! | `sequence`   | Increases with each record the agent issues   |
+//!
+//! Records expire after their TTL (capped by the directory). A directory
+//! configured with a registration key only accepts records signed with
+//! HMAC-SHA256 under that key, and rejects signed records older than the
+//! maximum TTL or not newer (by `sequence`) than the agent's last record,
+//! so captured records cannot be replayed. Directories hold at most
+//! [`DEFAULT_MAX_RECORDS`] agents unless configured otherwise.
+//!
+//! # HTTP API
+//!
+//! Served by the M2M server:
+//!
+//! | Method   | Path                     | Description              |
+//! |----------|--------------------------|--------------------------|
+//! | `POST`   | `/discovery/agents`      | Register or refresh      |
+//! | `GET`    | `/discovery/agents`      | Query (`org_id`, `algorithm`) |
+//! | `GET`    | `/discovery/agents/:id`  | Resolve an agent         |
+//! | `DELETE` | `/discovery/agents/:id`  | Deregister (signed record with zero TTL, or admin token) |
+//!
+//! # Usage
+//!
+//! ```rust,ignore
+//! use m2m::discovery::{AgentRecord, DiscoveryClient};
+//!
+//! let directory = DiscoveryClient::new("http://registry:3000");
+//!
+//! // Announce ourselves
+//! let record = AgentRecord::new("planner-01", "acme")
+//!     .with_endpoint("http://10.0.0.5:3000")
+//!     .sign(&registration_auth)?;
+//! directory.register(&record).await?;
+//!
+//! // Find a peer by name, then handshake with its endpoint
+//! if let Some(peer) = directory.resolve("executor-07").await? {
+//!     let url = peer.endpoint("http").unwrap();
+//!     // POST HELLO to {url}/message ...
+//! }
+//! ```
+
+mod client;
+mod directory;
+mod record;
+
+pub use client::DiscoveryClient;
+pub use directory::{AgentDirectory, AgentQuery, DEFAULT_MAX_RECORDS, DEFAULT_MAX_TTL_SECS};
+pub use record::{AgentRecord, DEFAULT_TTL_SECS};
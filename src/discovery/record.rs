@@ -0,0 +1,188 @@
+//! Agent registration records.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use serde::{Deserialize, Serialize};
+
+use crate::codec::m2m::crypto::{CryptoError, HmacAuth};
+use crate::codec::Algorithm;
+use crate::error::{M2MError, Result};
+
+/// Default record lifetime (5 minutes)
+pub const DEFAULT_TTL_SECS: u64 = 300;
+
+/// Registration record advertised by an agent
+///
+/// Endpoints are URLs such as `http://10.0.0.5:3000` or `quic://10.0.0.5:4433`,
+/// listed in order of preference.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AgentRecord {
+    /// Agent identifier (lookup key)
+    pub agent_id: String,
+    /// Organization identifier
+    pub org_id: String,
+    /// X25519 public key (hex), for cross-organization key exchange
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub public_key: Option<String>,
+    /// Supported compression algorithms in preference order
+    #[serde(default)]
+    pub algorithms: Vec<Algorithm>,
+    /// Transport endpoints in preference order
+    pub endpoints: Vec<String>,
+    /// Requested lifetime in seconds
+    #[serde(default = "default_ttl")]
+    pub ttl_secs: u64,
+    /// Issue time (Unix seconds)
+    pub issued_at: u64,
+    /// Increases with every record the agent issues; a signed directory
+    /// only accepts records newer than the last one it saw
+    #[serde(default)]
+    pub sequence: u64,
+    /// HMAC-SHA256 over the record (base64), see [`AgentRecord::sign`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+}
+
+fn default_ttl() -> u64 {
+    DEFAULT_TTL_SECS
+}
+
+impl AgentRecord {
+    /// Create an unsigned record issued now
+    ///
+    /// The sequence number is the current Unix time in milliseconds.
+    pub fn new(agent_id: &str, org_id: &str) -> Self {
+        Self {
+            agent_id: agent_id.to_string(),
+            org_id: org_id.to_string(),
+            public_key: None,
+            algorithms: Algorithm::available(),
+            endpoints: Vec::new(),
+            ttl_secs: DEFAULT_TTL_SECS,
+            issued_at: unix_now(),
+            sequence: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_millis() as u64),
+            signature: None,
+        }
+    }
+
+    /// Add a transport endpoint
+    pub fn with_endpoint(mut self, url: &str) -> Self {
+        self.endpoints.push(url.to_string());
+        self
+    }
+
+    /// Set public key (hex)
+    pub fn with_public_key(mut self, public_key: &str) -> Self {
+        self.public_key = Some(public_key.to_string());
+        self
+    }
+
+    /// Set supported algorithms
+    pub fn with_algorithms(mut self, algorithms: Vec<Algorithm>) -> Self {
+        self.algorithms = algorithms;
+        self
+    }
+
+    /// Set requested lifetime
+    ///
+    /// A signed record with a zero TTL deregisters the agent (see
+    /// [`AgentDirectory::deregister_signed`](super::AgentDirectory::deregister_signed)).
+    pub fn with_ttl(mut self, ttl_secs: u64) -> Self {
+        self.ttl_secs = ttl_secs;
+        self
+    }
+
+    /// Set the sequence number
+    pub fn with_sequence(mut self, sequence: u64) -> Self {
+        self.sequence = sequence;
+        self
+    }
+
+    /// Check if the agent supports an algorithm
+    pub fn supports(&self, algorithm: Algorithm) -> bool {
+        self.algorithms.contains(&algorithm)
+    }
+
+    /// First endpoint with the given URL scheme (e.g. `"quic"`)
+    pub fn endpoint(&self, scheme: &str) -> Option<&str> {
+        self.endpoints
+            .iter()
+            .map(String::as_str)
+            .find(|url| url.split_once("://").is_some_and(|(s, _)| s == scheme))
+    }
+
+    /// Sign the record with a registration key
+    ///
+    /// Replaces any existing signature.
+    pub fn sign(mut self, auth: &HmacAuth) -> Result<Self> {
+        let tag = auth.compute_tag(&self.signing_bytes()?);
+        self.signature = Some(BASE64.encode(tag));
+        Ok(self)
+    }
+
+    /// Verify the record signature
+    pub fn verify(&self, auth: &HmacAuth) -> Result<()> {
+        let signature = self.signature.as_ref().ok_or_else(|| {
+            M2MError::InvalidMessage(format!("Record for {} is not signed", self.agent_id))
+        })?;
+        let tag = BASE64
+            .decode(signature)
+            .map_err(|e| M2MError::InvalidMessage(format!("Invalid signature encoding: {e}")))?;
+
+        auth.verify_tag(&self.signing_bytes()?, &tag)
+            .map_err(|e| M2MError::Crypto(CryptoError::from(e)))
+    }
+
+    /// Canonical bytes covered by the signature (record without signature)
+    fn signing_bytes(&self) -> Result<Vec<u8>> {
+        let unsigned = Self {
+            signature: None,
+            ..self.clone()
+        };
+        Ok(serde_json::to_vec(&unsigned)?)
+    }
+}
+
+/// Current Unix time in seconds
+pub(crate) fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::m2m::crypto::KeyMaterial;
+
+    #[test]
+    fn test_sign_and_verify() {
+        let auth = HmacAuth::new(KeyMaterial::new(vec![7u8; 32])).unwrap();
+        let record = AgentRecord::new("agent-001", "acme")
+            .with_endpoint("http://127.0.0.1:3000")
+            .sign(&auth)
+            .unwrap();
+        assert!(record.verify(&auth).is_ok());
+
+        // Tampering invalidates the signature
+        let mut tampered = record.clone();
+        tampered.endpoints[0] = "http://evil.example:3000".to_string();
+        assert!(tampered.verify(&auth).is_err());
+
+        let other = HmacAuth::new(KeyMaterial::new(vec![8u8; 32])).unwrap();
+        assert!(record.verify(&other).is_err());
+    }
+
+    #[test]
+    fn test_endpoint_by_scheme() {
+        let record = AgentRecord::new("agent-001", "acme")
+            .with_endpoint("http://127.0.0.1:3000")
+            .with_endpoint("quic://127.0.0.1:4433");
+        assert_eq!(record.endpoint("quic"), Some("quic://127.0.0.1:4433"));
+        assert_eq!(record.endpoint("tcp"), None);
+    }
+}
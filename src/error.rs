@@ -46,6 +46,24 @@
 //!
 //! **Handling**: Log extensively, fail fast, alert operators.
 //!
+//! ## Error Codes
+//!
+//! Every error has a stable machine-readable [`ErrorCode`] (see
+//! [`M2MError::code`]), grouped by subsystem:
+//!
+//! | Prefix   | Numbers | Subsystem                                  |
+//! |----------|---------|--------------------------------------------|
+//! | `CODEC`  | 1xxx    | Compression, wire format, payload schemas  |
+//! | `PROTO`  | 2xxx    | Sessions, handshake, message exchange      |
+//! | `SEC`    | 3xxx    | Security scanning and policy               |
+//! | `CRYPTO` | 4xxx    | Keys, AEAD, HMAC, key exchange             |
+//! | `MODEL`  | 5xxx    | Model registry and inference               |
+//! | `SYS`    | 9xxx    | Configuration, I/O, network, upstream      |
+//!
+//! Codes are carried in HTTP error bodies (`"code"`) and in REJECT and
+//! CLOSE payloads (`"error_code"`), so clients can branch on them instead
+//! of parsing messages. Codes are never reused or renumbered.
+//!
 //! ## Usage Example
 //!
 //! ```rust,ignore
@@ -67,6 +85,10 @@
 //! }
 //! ```
 
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use thiserror::Error;
 
 use crate::codec::m2m::crypto::CryptoError;
@@ -96,6 +118,20 @@ pub enum M2MError {
     #[error("Decompression error: {0}")]
     Decompression(String),
 
+    /// Decompressed output exceeds the configured limits.
+    ///
+    /// **Epistemic**: B_i falsified — caller believed the payload would
+    /// decode to a reasonable size.
+    ///
+    /// **Handling**: Do NOT retry; the peer may be sending a decompression bomb.
+    #[error("Payload too large: {size} bytes exceeds limit of {limit}")]
+    PayloadTooLarge {
+        /// Bytes decoded before decoding stopped
+        size: usize,
+        /// Limit that applied to the payload
+        limit: usize,
+    },
+
     /// Codec identifier not recognized or not supported.
     ///
     /// **Epistemic**: B_i falsified — caller believed codec was available.
@@ -133,12 +169,35 @@ pub enum M2MError {
     #[error("Invalid message format: {0}")]
     InvalidMessage(String),
 
+    /// Decoded payload does not match its API schema.
+    ///
+    /// **Epistemic**: B_i falsified — caller believed the codec round trip
+    /// produced a well-formed API payload.
+    #[error("Schema violation ({schema}) at {path}: {message}")]
+    SchemaViolation {
+        /// Schema checked (e.g. "chat_completion_request").
+        schema: String,
+        /// JSON Pointer to the offending field (e.g. "/messages/2/role").
+        path: String,
+        /// What is wrong with the field.
+        message: String,
+    },
+
     /// Peers have incompatible capabilities for the requested operation.
     ///
     /// **Epistemic**: B_i falsified — caller believed capabilities were compatible.
     #[error("Capability mismatch: {0}")]
     CapabilityMismatch(String),
 
+    /// Pinning a context would exceed the receiver's quotas.
+    ///
+    /// **Epistemic**: B_i falsified — caller believed the context fit the
+    /// receiver's limits.
+    ///
+    /// **Handling**: Send the full request, or pin a shorter prefix.
+    #[error("Context quota exceeded: {0}")]
+    ContextQuotaExceeded(String),
+
     /// ML model was expected to be loaded but isn't.
     ///
     /// **Epistemic**: B_i falsified — caller believed model was available.
@@ -202,6 +261,47 @@ pub enum M2MError {
     #[error("Server error: {0}")]
     Server(String),
 
+    /// Work was shed: queue full or deadline exceeded.
+    ///
+    /// **Epistemic**: I^B materialized — concurrent load was unknown
+    /// until the request was admitted.
+    ///
+    /// **Handling**: Retry with backoff; surface as 503 to HTTP clients.
+    #[error("Overloaded: {0}")]
+    Overloaded(String),
+
+    /// Peer's flow-control window has no room for the message.
+    ///
+    /// **Epistemic**: I^B materialized — how fast the peer consumes
+    /// messages is unknown to the sender.
+    ///
+    /// **Handling**: Wait for WINDOW_UPDATE, then retry.
+    #[error("Flow-control window exhausted: {0}")]
+    WindowExhausted(String),
+
+    /// A fragment was buffered; the message is not complete yet.
+    ///
+    /// **Epistemic**: I^B — the remaining fragments are still in flight.
+    ///
+    /// **Handling**: Not a failure; keep passing DATA messages to
+    /// `Session::decompress` until it returns the content.
+    #[error("Fragment buffered: {missing} of {total} fragments outstanding")]
+    FragmentPending {
+        /// Fragments not yet received
+        missing: u32,
+        /// Fragments in the message
+        total: u32,
+    },
+
+    /// A referenced context is unknown or expired at the receiver.
+    ///
+    /// **Epistemic**: I^B materialized — whether the receiver still holds
+    /// a pinned context is unknown to the sender until it references it.
+    ///
+    /// **Handling**: Pin the context again, or send the full request.
+    #[error("Context not found: {0}")]
+    ContextNotFound(String),
+
     /// ML inference failed during execution.
     ///
     /// **Epistemic**: I^B materialized — model execution success depends on
@@ -233,6 +333,15 @@ pub enum M2MError {
     #[error("Crypto error: {0}")]
     Crypto(#[source] CryptoError),
 
+    /// Key used outside its validity window.
+    ///
+    /// **Epistemic**: B_i falsified — the caller believed the key was
+    /// still valid.
+    ///
+    /// **Handling**: Rekey (`Session::rekey`), then retry with the new key.
+    #[error("Key expired: {0}")]
+    KeyExpired(String),
+
     // ═══════════════════════════════════════════════════════════════════════
     // SECURITY — Policy Violations (Special Category)
     // ═══════════════════════════════════════════════════════════════════════
@@ -288,6 +397,16 @@ impl From<toml::de::Error> for M2MError {
     }
 }
 
+impl From<m2m_core::Error> for M2MError {
+    fn from(err: m2m_core::Error) -> Self {
+        match err {
+            m2m_core::Error::Compression(msg) => M2MError::Compression(msg),
+            m2m_core::Error::Decompression(msg) => M2MError::Decompression(msg),
+            m2m_core::Error::Json(err) => M2MError::Json(err),
+        }
+    }
+}
+
 impl From<base64::DecodeError> for M2MError {
     fn from(err: base64::DecodeError) -> Self {
         M2MError::Decompression(format!("Base64 decode error: {err}"))
@@ -319,6 +438,8 @@ impl M2MError {
             M2MError::Network(_)
                 | M2MError::Upstream(_)
                 | M2MError::Server(_)
+                | M2MError::Overloaded(_)
+                | M2MError::WindowExhausted(_)
                 | M2MError::Inference(_)
                 | M2MError::Io(_)
         )
@@ -344,6 +465,10 @@ impl M2MError {
             M2MError::Network(_)
                 | M2MError::Upstream(_)
                 | M2MError::Server(_)
+                | M2MError::Overloaded(_)
+                | M2MError::WindowExhausted(_)
+                | M2MError::FragmentPending { .. }
+                | M2MError::ContextNotFound(_)
                 | M2MError::Inference(_)
                 | M2MError::ModelLoad(_)
                 | M2MError::Io(_)
@@ -358,6 +483,252 @@ impl M2MError {
     pub fn is_belief_falsified(&self) -> bool {
         !self.is_bounded_ignorance()
     }
+
+    /// Stable machine-readable code for this error
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use m2m::error::{ErrorCategory, ErrorCode, M2MError};
+    ///
+    /// let err = M2MError::SessionExpired;
+    /// assert_eq!(err.code(), ErrorCode::SESSION_EXPIRED);
+    /// assert_eq!(err.code().to_string(), "PROTO_004");
+    /// assert_eq!(err.code().category(), ErrorCategory::Protocol);
+    /// ```
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            M2MError::Compression(_) => ErrorCode::COMPRESSION,
+            M2MError::Decompression(_) => ErrorCode::DECOMPRESSION,
+            M2MError::InvalidCodec(_) => ErrorCode::INVALID_CODEC,
+            M2MError::SchemaViolation { .. } => ErrorCode::SCHEMA_VIOLATION,
+            M2MError::Json(_) => ErrorCode::JSON,
+            M2MError::Tokenizer(_) => ErrorCode::TOKENIZER,
+            M2MError::PayloadTooLarge { .. } => ErrorCode::PAYLOAD_TOO_LARGE,
+            M2MError::Protocol(_) => ErrorCode::PROTOCOL,
+            M2MError::NegotiationFailed(_) => ErrorCode::NEGOTIATION_FAILED,
+            M2MError::SessionNotEstablished => ErrorCode::SESSION_NOT_ESTABLISHED,
+            M2MError::SessionExpired => ErrorCode::SESSION_EXPIRED,
+            M2MError::InvalidMessage(_) => ErrorCode::INVALID_MESSAGE,
+            M2MError::CapabilityMismatch(_) => ErrorCode::CAPABILITY_MISMATCH,
+            M2MError::WindowExhausted(_) => ErrorCode::WINDOW_EXHAUSTED,
+            M2MError::FragmentPending { .. } => ErrorCode::FRAGMENT_PENDING,
+            M2MError::ContextNotFound(_) => ErrorCode::CONTEXT_NOT_FOUND,
+            M2MError::ContextQuotaExceeded(_) => ErrorCode::CONTEXT_QUOTA_EXCEEDED,
+            M2MError::SecurityThreat { .. } => ErrorCode::SECURITY_THREAT,
+            M2MError::ContentBlocked(_) => ErrorCode::CONTENT_BLOCKED,
+            M2MError::Crypto(err) => err.code(),
+            M2MError::KeyExpired(_) => ErrorCode::CRYPTO_KEY_EXPIRED,
+            M2MError::ModelNotLoaded(_) => ErrorCode::MODEL_NOT_LOADED,
+            M2MError::ModelNotFound(_) => ErrorCode::MODEL_NOT_FOUND,
+            M2MError::ModelLoad(_) => ErrorCode::MODEL_LOAD,
+            M2MError::Inference(_) => ErrorCode::INFERENCE,
+            M2MError::Config(_) => ErrorCode::CONFIG,
+            M2MError::Network(_) => ErrorCode::NETWORK,
+            M2MError::Upstream(_) => ErrorCode::UPSTREAM,
+            M2MError::Server(_) => ErrorCode::SERVER,
+            M2MError::Overloaded(_) => ErrorCode::OVERLOADED,
+            M2MError::Io(_) => ErrorCode::IO,
+        }
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════
+// Error Codes
+// ═══════════════════════════════════════════════════════════════════════════
+
+/// Subsystem an error code belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorCategory {
+    /// Compression, wire format, payload schemas (`CODEC_`, 1xxx)
+    Codec,
+    /// Sessions, handshake, message exchange (`PROTO_`, 2xxx)
+    Protocol,
+    /// Security scanning and policy (`SEC_`, 3xxx)
+    Security,
+    /// Keys, AEAD, HMAC, key exchange (`CRYPTO_`, 4xxx)
+    Crypto,
+    /// Model registry and inference (`MODEL_`, 5xxx)
+    Model,
+    /// Configuration, I/O, network, upstream (`SYS_`, 9xxx)
+    System,
+}
+
+impl ErrorCategory {
+    const ALL: [ErrorCategory; 6] = [
+        ErrorCategory::Codec,
+        ErrorCategory::Protocol,
+        ErrorCategory::Security,
+        ErrorCategory::Crypto,
+        ErrorCategory::Model,
+        ErrorCategory::System,
+    ];
+
+    /// Code prefix (e.g. `CODEC`)
+    pub fn prefix(self) -> &'static str {
+        match self {
+            ErrorCategory::Codec => "CODEC",
+            ErrorCategory::Protocol => "PROTO",
+            ErrorCategory::Security => "SEC",
+            ErrorCategory::Crypto => "CRYPTO",
+            ErrorCategory::Model => "MODEL",
+            ErrorCategory::System => "SYS",
+        }
+    }
+
+    /// First number of the category's numeric range
+    fn base(self) -> u16 {
+        match self {
+            ErrorCategory::Codec => 1000,
+            ErrorCategory::Protocol => 2000,
+            ErrorCategory::Security => 3000,
+            ErrorCategory::Crypto => 4000,
+            ErrorCategory::Model => 5000,
+            ErrorCategory::System => 9000,
+        }
+    }
+}
+
+/// Stable machine-readable error code (e.g. `CODEC_002`, numerically 1002)
+///
+/// Serialized as its string form.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ErrorCode {
+    category: ErrorCategory,
+    index: u16,
+}
+
+impl ErrorCode {
+    /// Compression failed
+    pub const COMPRESSION: Self = Self::new(ErrorCategory::Codec, 1);
+    /// Wire data could not be decoded
+    pub const DECOMPRESSION: Self = Self::new(ErrorCategory::Codec, 2);
+    /// Unknown or unsupported codec
+    pub const INVALID_CODEC: Self = Self::new(ErrorCategory::Codec, 3);
+    /// Decoded payload violates its API schema
+    pub const SCHEMA_VIOLATION: Self = Self::new(ErrorCategory::Codec, 4);
+    /// Invalid JSON
+    pub const JSON: Self = Self::new(ErrorCategory::Codec, 5);
+    /// Tokenizer failure
+    pub const TOKENIZER: Self = Self::new(ErrorCategory::Codec, 6);
+    /// Decompressed payload exceeds size limits
+    pub const PAYLOAD_TOO_LARGE: Self = Self::new(ErrorCategory::Codec, 7);
+
+    /// Protocol state machine violation
+    pub const PROTOCOL: Self = Self::new(ErrorCategory::Protocol, 1);
+    /// Capability negotiation failed
+    pub const NEGOTIATION_FAILED: Self = Self::new(ErrorCategory::Protocol, 2);
+    /// Session not established
+    pub const SESSION_NOT_ESTABLISHED: Self = Self::new(ErrorCategory::Protocol, 3);
+    /// Session expired
+    pub const SESSION_EXPIRED: Self = Self::new(ErrorCategory::Protocol, 4);
+    /// Malformed message
+    pub const INVALID_MESSAGE: Self = Self::new(ErrorCategory::Protocol, 5);
+    /// Incompatible capabilities
+    pub const CAPABILITY_MISMATCH: Self = Self::new(ErrorCategory::Protocol, 6);
+    /// Flow-control window exhausted
+    pub const WINDOW_EXHAUSTED: Self = Self::new(ErrorCategory::Protocol, 7);
+    /// Fragment buffered, message incomplete
+    pub const FRAGMENT_PENDING: Self = Self::new(ErrorCategory::Protocol, 8);
+    /// Referenced context unknown or expired
+    pub const CONTEXT_NOT_FOUND: Self = Self::new(ErrorCategory::Protocol, 9);
+    /// Pinned context exceeds the receiver's quotas
+    pub const CONTEXT_QUOTA_EXCEEDED: Self = Self::new(ErrorCategory::Protocol, 10);
+
+    /// Threat detected in content
+    pub const SECURITY_THREAT: Self = Self::new(ErrorCategory::Security, 1);
+    /// Content blocked by policy
+    pub const CONTENT_BLOCKED: Self = Self::new(ErrorCategory::Security, 2);
+
+    /// AEAD encryption or decryption failed
+    pub const CRYPTO_AEAD: Self = Self::new(ErrorCategory::Crypto, 1);
+    /// HMAC verification failed
+    pub const CRYPTO_HMAC: Self = Self::new(ErrorCategory::Crypto, 2);
+    /// Invalid key material
+    pub const CRYPTO_KEY: Self = Self::new(ErrorCategory::Crypto, 3);
+    /// Keyring operation failed
+    pub const CRYPTO_KEYRING: Self = Self::new(ErrorCategory::Crypto, 4);
+    /// Key exchange failed
+    pub const CRYPTO_EXCHANGE: Self = Self::new(ErrorCategory::Crypto, 5);
+    /// Invalid agent or organization ID
+    pub const CRYPTO_ID: Self = Self::new(ErrorCategory::Crypto, 6);
+    /// Nonce generation failed
+    pub const CRYPTO_NONCE: Self = Self::new(ErrorCategory::Crypto, 7);
+    /// Key used outside its validity window
+    pub const CRYPTO_KEY_EXPIRED: Self = Self::new(ErrorCategory::Crypto, 8);
+
+    /// Model not loaded
+    pub const MODEL_NOT_LOADED: Self = Self::new(ErrorCategory::Model, 1);
+    /// Model not in the registry
+    pub const MODEL_NOT_FOUND: Self = Self::new(ErrorCategory::Model, 2);
+    /// Model failed to load
+    pub const MODEL_LOAD: Self = Self::new(ErrorCategory::Model, 3);
+    /// Inference failed
+    pub const INFERENCE: Self = Self::new(ErrorCategory::Model, 4);
+
+    /// Invalid configuration
+    pub const CONFIG: Self = Self::new(ErrorCategory::System, 1);
+    /// Network failure
+    pub const NETWORK: Self = Self::new(ErrorCategory::System, 2);
+    /// Upstream service error
+    pub const UPSTREAM: Self = Self::new(ErrorCategory::System, 3);
+    /// Server-side error
+    pub const SERVER: Self = Self::new(ErrorCategory::System, 4);
+    /// Load shed (queue full or deadline exceeded)
+    pub const OVERLOADED: Self = Self::new(ErrorCategory::System, 5);
+    /// I/O failure
+    pub const IO: Self = Self::new(ErrorCategory::System, 6);
+
+    const fn new(category: ErrorCategory, index: u16) -> Self {
+        Self { category, index }
+    }
+
+    /// Subsystem of the error
+    pub fn category(self) -> ErrorCategory {
+        self.category
+    }
+
+    /// Numeric form (category base + index, e.g. 1002 for `CODEC_002`)
+    pub fn number(self) -> u16 {
+        self.category.base() + self.index
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}_{:03}", self.category.prefix(), self.index)
+    }
+}
+
+impl FromStr for ErrorCode {
+    type Err = M2MError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || M2MError::InvalidMessage(format!("Invalid error code: {s}"));
+        let (prefix, index) = s.rsplit_once('_').ok_or_else(invalid)?;
+        let category = ErrorCategory::ALL
+            .into_iter()
+            .find(|c| c.prefix() == prefix)
+            .ok_or_else(invalid)?;
+        let index: u16 = index.parse().map_err(|_| invalid())?;
+        if index == 0 || index >= 1000 {
+            return Err(invalid());
+        }
+        Ok(Self::new(category, index))
+    }
+}
+
+impl Serialize for ErrorCode {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for ErrorCode {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
 }
 
 #[cfg(test)]
@@ -403,6 +774,31 @@ mod tests {
         assert!(!M2MError::InvalidCodec("unknown".to_string()).is_bounded_ignorance());
     }
 
+    #[test]
+    fn test_error_codes() {
+        let err = M2MError::Decompression("corrupt".to_string());
+        assert_eq!(err.code().to_string(), "CODEC_002");
+        assert_eq!(err.code().number(), 1002);
+
+        let err = M2MError::from(CryptoError::Nonce(
+            crate::codec::m2m::crypto::NonceError::RngFailure("none".to_string()),
+        ));
+        assert_eq!(err.code(), ErrorCode::CRYPTO_NONCE);
+        assert_eq!(err.code().category(), ErrorCategory::Crypto);
+
+        // String form round-trips, including through serde
+        let code: ErrorCode = "SYS_005".parse().unwrap();
+        assert_eq!(code, ErrorCode::OVERLOADED);
+        let json = serde_json::to_string(&ErrorCode::SECURITY_THREAT).unwrap();
+        assert_eq!(json, r#""SEC_001""#);
+        assert_eq!(
+            serde_json::from_str::<ErrorCode>(&json).unwrap(),
+            ErrorCode::SECURITY_THREAT
+        );
+        assert!("NET_001".parse::<ErrorCode>().is_err());
+        assert!("CODEC_000".parse::<ErrorCode>().is_err());
+    }
+
     #[test]
     fn test_belief_falsified_is_inverse() {
         let network_err = M2MError::Network("timeout".to_string());
@@ -19,12 +19,20 @@
 //! CompressionHead: Linear(192, 4) → [NONE, BPE, BROTLI, ZLIB]
 //! SecurityHead: Linear(192, 2) → [SAFE, UNSAFE]
 //! ```
+//!
+//! ## Quantization
+//!
+//! [`HydraBitNet::quantize`] converts a loaded model to int8 or BitNet b1.58
+//! ternary weights (see [`Precision`]), and
+//! [`HydraBitNet::quantize_to_floor`] picks the smallest precision whose
+//! predictions still agree with the float32 model often enough.
 
 use std::path::Path;
 
 use ndarray::{Array1, Array2};
 use safetensors::SafeTensors;
 
+use super::quant::{Precision, QuantizationReport, Weights};
 use crate::error::{M2MError, Result};
 
 /// Model configuration derived from actual weights
@@ -58,18 +66,45 @@ impl Default for HydraConfig {
 /// Linear layer (dense)
 #[derive(Debug, Clone)]
 pub struct Linear {
-    weight: Array2<f32>, // [out_features, in_features]
+    weight: Weights, // [out_features, in_features]
     bias: Option<Array1<f32>>,
 }
 
 impl Linear {
     fn new(weight: Array2<f32>, bias: Option<Array1<f32>>) -> Self {
-        Self { weight, bias }
+        Self {
+            weight: Weights::F32(weight),
+            bias,
+        }
+    }
+
+    /// Copy with weights at `precision` (biases stay float32)
+    fn quantize(&self, precision: Precision) -> Self {
+        if self.weight.precision() == precision {
+            return self.clone();
+        }
+        Self {
+            weight: Weights::quantize(&self.weight.to_f32(), precision),
+            bias: self.bias.clone(),
+        }
+    }
+
+    fn bytes(&self) -> usize {
+        self.weight.bytes() + self.bias.as_ref().map_or(0, |b| b.len() * 4)
     }
 
     fn forward(&self, x: &Array1<f32>) -> Array1<f32> {
         // y = Wx + b
-        let mut y = self.weight.dot(x);
+        let mut y = self.weight.dot(x.view());
+        if let Some(ref b) = self.bias {
+            y += b;
+        }
+        y
+    }
+
+    fn forward_batch(&self, x: &Array2<f32>) -> Array2<f32> {
+        // Y = XW^T + b, one row per input
+        let mut y = self.weight.dot_batch(x);
         if let Some(ref b) = self.bias {
             y += b;
         }
@@ -111,6 +146,12 @@ pub struct Expert {
 }
 
 impl Expert {
+    fn quantize(&self, precision: Precision) -> Self {
+        Self {
+            layers: self.layers.iter().map(|l| l.quantize(precision)).collect(),
+        }
+    }
+
     fn forward(&self, x: &Array1<f32>) -> Array1<f32> {
         let mut h = x.clone();
         for (i, layer) in self.layers.iter().enumerate() {
@@ -146,6 +187,26 @@ pub struct MoELayer {
 }
 
 impl MoELayer {
+    /// Experts at `precision`; the small, routing-critical gate stays at
+    /// table precision
+    fn quantize(&self, precision: Precision) -> Self {
+        Self {
+            gate: self.gate.quantize(precision.for_tables()),
+            experts: self.experts.iter().map(|e| e.quantize(precision)).collect(),
+            top_k: self.top_k,
+        }
+    }
+
+    fn bytes(&self) -> usize {
+        self.gate.bytes()
+            + self
+                .experts
+                .iter()
+                .flat_map(|e| &e.layers)
+                .map(Linear::bytes)
+                .sum::<usize>()
+    }
+
     fn forward(&self, x: &Array1<f32>) -> Array1<f32> {
         // 1. Compute gate logits and probabilities
         let gate_logits = self.gate.forward(x);
@@ -182,7 +243,8 @@ impl MoELayer {
 #[derive(Debug, Clone)]
 pub struct HydraBitNet {
     config: HydraConfig,
-    embed: Array2<f32>,
+    precision: Precision,
+    embed: Weights,
     layers: Vec<MoELayer>,
     norm: LayerNorm,
     semantic_head: Linear,
@@ -240,7 +302,8 @@ impl HydraBitNet {
 
         Ok(Self {
             config,
-            embed,
+            precision: Precision::F32,
+            embed: Weights::F32(embed),
             layers,
             norm,
             semantic_head,
@@ -254,6 +317,113 @@ impl HydraBitNet {
         &self.config
     }
 
+    /// Weight precision
+    pub fn precision(&self) -> Precision {
+        self.precision
+    }
+
+    /// In-memory size of all weights, in bytes
+    pub fn weight_bytes(&self) -> usize {
+        self.embed.bytes()
+            + self.layers.iter().map(MoELayer::bytes).sum::<usize>()
+            + (self.norm.weight.len() + self.norm.bias.len()) * 4
+            + self.semantic_head.bytes()
+            + self.compression_head.bytes()
+            + self.security_head.bytes()
+    }
+
+    /// Copy of the model with weights at `precision`
+    ///
+    /// Quantize from a float32 model: re-quantizing an already quantized
+    /// model compounds the rounding error.
+    pub fn quantize(&self, precision: Precision) -> Self {
+        let tables = precision.for_tables();
+        let embed = if self.embed.precision() == tables {
+            self.embed.clone()
+        } else {
+            Weights::quantize(&self.embed.to_f32(), tables)
+        };
+        Self {
+            config: self.config.clone(),
+            precision,
+            embed,
+            layers: self.layers.iter().map(|l| l.quantize(precision)).collect(),
+            norm: self.norm.clone(),
+            semantic_head: self.semantic_head.quantize(tables),
+            compression_head: self.compression_head.quantize(tables),
+            security_head: self.security_head.quantize(tables),
+        }
+    }
+
+    /// Compare this model's predictions with `reference` on token sequences
+    pub fn compare(&self, reference: &HydraBitNet, samples: &[Vec<u32>]) -> QuantizationReport {
+        let samples: Vec<&Vec<u32>> = samples.iter().filter(|s| !s.is_empty()).collect();
+        let mut compression_hits = 0usize;
+        let mut security_hits = 0usize;
+        let mut max_prob_delta = 0.0f32;
+
+        for tokens in &samples {
+            for (ours, theirs, hits) in [
+                (
+                    self.predict_compression(tokens),
+                    reference.predict_compression(tokens),
+                    &mut compression_hits,
+                ),
+                (
+                    self.predict_security(tokens),
+                    reference.predict_security(tokens),
+                    &mut security_hits,
+                ),
+            ] {
+                if argmax(&ours) == argmax(&theirs) {
+                    *hits += 1;
+                }
+                for (a, b) in ours.iter().zip(theirs.iter()) {
+                    max_prob_delta = max_prob_delta.max((a - b).abs());
+                }
+            }
+        }
+
+        let rate = |hits: usize| {
+            if samples.is_empty() {
+                1.0
+            } else {
+                hits as f32 / samples.len() as f32
+            }
+        };
+        QuantizationReport {
+            precision: self.precision,
+            samples: samples.len(),
+            compression_agreement: rate(compression_hits),
+            security_agreement: rate(security_hits),
+            max_prob_delta,
+            weight_bytes: self.weight_bytes(),
+        }
+    }
+
+    /// Smallest quantization whose agreement with this model on `samples`
+    /// is at least `floor` (0.0 - 1.0)
+    ///
+    /// Tries ternary, then int8, and keeps float32 if neither qualifies.
+    pub fn quantize_to_floor(
+        &self,
+        samples: &[Vec<u32>],
+        floor: f32,
+    ) -> (Self, QuantizationReport) {
+        for precision in Precision::SMALLEST_FIRST {
+            let candidate = if precision == self.precision {
+                self.clone()
+            } else {
+                self.quantize(precision)
+            };
+            let report = candidate.compare(self, samples);
+            if report.agreement() >= floor || precision == Precision::F32 {
+                return (candidate, report);
+            }
+        }
+        unreachable!("SMALLEST_FIRST ends with F32")
+    }
+
     /// Forward pass for compression prediction
     /// Returns probabilities for [NONE, BPE, BROTLI, ZLIB]
     pub fn predict_compression(&self, token_ids: &[u32]) -> Array1<f32> {
@@ -262,6 +432,19 @@ impl HydraBitNet {
         softmax(&logits)
     }
 
+    /// Batched forward pass for compression prediction
+    ///
+    /// Returns one row of [NONE, BPE, BROTLI, ZLIB] probabilities per input.
+    pub fn predict_compression_batch(&self, batch: &[Vec<u32>]) -> Array2<f32> {
+        let hidden = self.encode_batch(batch);
+        let mut probs = self.compression_head.forward_batch(&hidden);
+        for mut row in probs.rows_mut() {
+            let p = softmax(&row.to_owned());
+            row.assign(&p);
+        }
+        probs
+    }
+
     /// Forward pass for security prediction
     /// Returns probabilities for [SAFE, UNSAFE]
     pub fn predict_security(&self, token_ids: &[u32]) -> Array1<f32> {
@@ -272,12 +455,30 @@ impl HydraBitNet {
 
     /// Encode tokens to hidden representation
     fn encode(&self, token_ids: &[u32]) -> Array1<f32> {
+        let hidden = self.encode_trunk(token_ids);
+
+        // 4. Semantic head projection
+        self.semantic_head.forward(&hidden)
+    }
+
+    /// Encode a batch of token sequences, one row per sequence
+    fn encode_batch(&self, batch: &[Vec<u32>]) -> Array2<f32> {
+        // Expert routing differs per input, so the trunk runs row by row;
+        // the dense head then runs once over the stacked rows
+        let mut stacked = Array2::zeros((batch.len(), self.config.hidden_size));
+        for (mut row, token_ids) in stacked.rows_mut().into_iter().zip(batch) {
+            row.assign(&self.encode_trunk(token_ids));
+        }
+        self.semantic_head.forward_batch(&stacked)
+    }
+
+    /// Mean-pooled embeddings through the MoE layers and final norm
+    fn encode_trunk(&self, token_ids: &[u32]) -> Array1<f32> {
         // 1. Token embeddings - mean pool
         let mut pooled = Array1::zeros(self.config.hidden_size);
         for &token_id in token_ids {
             let idx = (token_id as usize).min(self.config.vocab_size - 1);
-            let embedding = self.embed.row(idx).to_owned();
-            pooled = pooled + embedding;
+            pooled = pooled + self.embed.row(idx);
         }
         pooled /= token_ids.len() as f32;
 
@@ -288,13 +489,18 @@ impl HydraBitNet {
         }
 
         // 3. Final normalization
-        hidden = self.norm.forward(&hidden);
-
-        // 4. Semantic head projection
-        self.semantic_head.forward(&hidden)
+        self.norm.forward(&hidden)
     }
 }
 
+/// Index of the largest value
+fn argmax(x: &Array1<f32>) -> usize {
+    x.iter()
+        .enumerate()
+        .max_by(|a, b| a.1.partial_cmp(b.1).unwrap_or(std::cmp::Ordering::Equal))
+        .map_or(0, |(i, _)| i)
+}
+
 // Helper functions for loading tensors
 
 fn load_tensor_1d(tensors: &SafeTensors, name: &str) -> Result<Array1<f32>> {
@@ -389,6 +595,114 @@ mod tests {
         assert!(probs[2] > probs[1] && probs[1] > probs[0]);
     }
 
+    #[test]
+    fn test_linear_forward_batch_matches_forward() {
+        let linear = Linear::new(
+            Array2::from_shape_vec((2, 3), vec![1.0, 0.5, -1.0, 0.0, 2.0, 1.0]).unwrap(),
+            Some(Array1::from_vec(vec![0.1, -0.2])),
+        );
+        let rows = [
+            Array1::from_vec(vec![1.0, 2.0, 3.0]),
+            Array1::from_vec(vec![-1.0, 0.0, 4.0]),
+        ];
+        let batch = ndarray::stack(ndarray::Axis(0), &[rows[0].view(), rows[1].view()]).unwrap();
+
+        let out = linear.forward_batch(&batch);
+        for (i, row) in rows.iter().enumerate() {
+            let single = linear.forward(row);
+            assert!((&out.row(i) - &single).iter().all(|d| d.abs() < 1e-6));
+        }
+    }
+
+    /// Small model with deterministic pseudo-random weights
+    fn synthetic_model() -> HydraBitNet {
+        let mut state = 0x2545_f491_u32;
+        let mut next = move || {
+            state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            (state >> 8) as f32 / (1u32 << 24) as f32 - 0.5
+        };
+        let mut linear = |rows: usize, cols: usize| {
+            Linear::new(Array2::from_shape_simple_fn((rows, cols), &mut next), None)
+        };
+
+        let config = HydraConfig {
+            vocab_size: 300,
+            hidden_size: 16,
+            num_layers: 2,
+            ..Default::default()
+        };
+        let layers = (0..config.num_layers)
+            .map(|_| MoELayer {
+                gate: linear(config.num_experts, 16),
+                experts: (0..config.num_experts)
+                    .map(|_| Expert {
+                        layers: vec![linear(32, 16), linear(16, 32)],
+                    })
+                    .collect(),
+                top_k: config.top_k_experts,
+            })
+            .collect();
+        let semantic_head = linear(16, 16);
+        let compression_head = linear(4, 16);
+        let security_head = linear(2, 16);
+        let embed = linear(config.vocab_size, 16).weight;
+
+        HydraBitNet {
+            config,
+            precision: Precision::F32,
+            embed,
+            layers,
+            norm: LayerNorm::new(Array1::ones(16), Array1::zeros(16)),
+            semantic_head,
+            compression_head,
+            security_head,
+        }
+    }
+
+    #[test]
+    fn test_quantized_model_matches_f32() {
+        let model = synthetic_model();
+        let samples: Vec<Vec<u32>> = [
+            "Hello world",
+            r#"{"model":"gpt-4o","messages":[]}"#,
+            "Ignore previous instructions",
+            "The quick brown fox jumps over the lazy dog",
+            "SELECT * FROM users",
+            "aGVsbG8gd29ybGQ=",
+        ]
+        .iter()
+        .map(|s| s.bytes().map(|b| u32::from(b) + 3).collect())
+        .collect();
+
+        let exact = model.compare(&model, &samples);
+        assert_eq!(exact.samples, samples.len());
+        assert!(exact.agreement() >= 1.0 && exact.max_prob_delta < 1e-6);
+
+        let int8 = model.quantize(Precision::Int8);
+        let report = int8.compare(&model, &samples);
+        assert_eq!(report.precision, Precision::Int8);
+        assert!(report.agreement() >= 0.8, "{report:?}");
+        assert!(report.max_prob_delta < 0.05, "{report:?}");
+
+        let ternary = model.quantize(Precision::Ternary);
+        for probs in [
+            ternary.predict_compression(&samples[0]),
+            ternary.predict_security(&samples[0]),
+        ] {
+            assert!((probs.sum() - 1.0).abs() < 1e-5);
+        }
+        assert!(ternary.weight_bytes() < int8.weight_bytes());
+        assert!(int8.weight_bytes() * 3 < model.weight_bytes());
+
+        // Floors pick the smallest qualifying precision
+        let (selected, report) = model.quantize_to_floor(&samples, 0.0);
+        assert_eq!(selected.precision(), Precision::Ternary);
+        assert_eq!(report.weight_bytes, ternary.weight_bytes());
+        let (selected, report) = model.quantize_to_floor(&samples, 1.5);
+        assert_eq!(selected.precision(), Precision::F32);
+        assert!(report.agreement() >= 1.0);
+    }
+
     /// Inspect model tensors without loading
     /// Run with: cargo test inspect_model_tensors -- --ignored --nocapture
     #[test]
@@ -0,0 +1,223 @@
+//! Prediction cache for Hydra compression decisions.
+//!
+//! Agents often send near-identical prompts: the same template with a
+//! different timestamp, request ID, or whitespace. The cache keys decisions
+//! on a hash of content features that ignores those differences, so repeats
+//! skip the model entirely.
+//!
+//! The cache is a small LRU shared by all clones of a
+//! [`HydraModel`](super::HydraModel).
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::Mutex;
+
+use serde::Serialize;
+
+use super::hydra::CompressionDecision;
+
+/// Default number of cached decisions
+pub const DEFAULT_CACHE_CAPACITY: usize = 1024;
+
+/// Content length granularity of the cache key (bytes)
+const LENGTH_BUCKET: usize = 64;
+
+/// Prediction cache hit/miss counters
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct CacheStats {
+    /// Predictions served from the cache
+    pub hits: u64,
+    /// Predictions that ran the model
+    pub misses: u64,
+    /// Entries dropped to make room
+    pub evictions: u64,
+    /// Entries currently cached
+    pub entries: usize,
+    /// Maximum entries
+    pub capacity: usize,
+}
+
+impl CacheStats {
+    /// Fraction of lookups served from the cache (0.0 when unused)
+    pub fn hit_rate(&self) -> f64 {
+        let total = self.hits + self.misses;
+        if total == 0 {
+            0.0
+        } else {
+            self.hits as f64 / total as f64
+        }
+    }
+}
+
+/// Cached decision with its last use
+#[derive(Debug)]
+struct Entry {
+    decision: CompressionDecision,
+    last_used: u64,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    entries: HashMap<u64, Entry>,
+    /// Monotonic use counter for LRU ordering
+    tick: u64,
+    stats: CacheStats,
+}
+
+/// LRU cache of compression decisions keyed by content features
+#[derive(Debug)]
+pub struct PredictionCache {
+    inner: Mutex<Inner>,
+    capacity: usize,
+}
+
+impl Default for PredictionCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_CACHE_CAPACITY)
+    }
+}
+
+impl PredictionCache {
+    /// Create a cache holding at most `capacity` decisions
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            inner: Mutex::new(Inner::default()),
+            capacity: capacity.max(1),
+        }
+    }
+
+    /// Cache key for content
+    ///
+    /// Hashes the length bucket and the content with whitespace runs
+    /// collapsed and digits masked, so prompts differing only in IDs,
+    /// timestamps or formatting share a key.
+    pub fn key(content: &str) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        (content.len() / LENGTH_BUCKET).hash(&mut hasher);
+
+        let mut in_space = false;
+        for c in content.chars() {
+            if c.is_whitespace() {
+                if !in_space {
+                    ' '.hash(&mut hasher);
+                }
+                in_space = true;
+                continue;
+            }
+            in_space = false;
+            if c.is_ascii_digit() {
+                '0'.hash(&mut hasher);
+            } else {
+                c.hash(&mut hasher);
+            }
+        }
+        hasher.finish()
+    }
+
+    /// Look up a decision, counting the hit or miss
+    pub fn get(&self, key: u64) -> Option<CompressionDecision> {
+        let mut inner = self.inner.lock().ok()?;
+        inner.tick += 1;
+        let tick = inner.tick;
+
+        let decision = inner.entries.get_mut(&key).map(|entry| {
+            entry.last_used = tick;
+            entry.decision.clone()
+        });
+        if decision.is_some() {
+            inner.stats.hits += 1;
+        } else {
+            inner.stats.misses += 1;
+        }
+        decision
+    }
+
+    /// Store a decision, evicting the least recently used one if full
+    pub fn insert(&self, key: u64, decision: CompressionDecision) {
+        let Ok(mut inner) = self.inner.lock() else {
+            return;
+        };
+        inner.tick += 1;
+        let tick = inner.tick;
+
+        if !inner.entries.contains_key(&key) && inner.entries.len() >= self.capacity {
+            let oldest = inner
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| *key);
+            if let Some(oldest) = oldest {
+                inner.entries.remove(&oldest);
+                inner.stats.evictions += 1;
+            }
+        }
+        inner.entries.insert(
+            key,
+            Entry {
+                decision,
+                last_used: tick,
+            },
+        );
+    }
+
+    /// Current counters
+    pub fn stats(&self) -> CacheStats {
+        self.inner
+            .lock()
+            .map(|inner| CacheStats {
+                entries: inner.entries.len(),
+                capacity: self.capacity,
+                ..inner.stats
+            })
+            .unwrap_or_default()
+    }
+
+    /// Drop all entries and reset the counters
+    pub fn clear(&self) {
+        if let Ok(mut inner) = self.inner.lock() {
+            *inner = Inner::default();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::Algorithm;
+    use crate::inference::hydra::AlgorithmProbs;
+
+    fn decision(algorithm: Algorithm) -> CompressionDecision {
+        CompressionDecision {
+            algorithm,
+            confidence: 0.9,
+            probabilities: AlgorithmProbs::default(),
+        }
+    }
+
+    #[test]
+    fn test_key_ignores_digits_and_whitespace() {
+        let a = PredictionCache::key("request 1234 at  2026-01-01\n");
+        let b = PredictionCache::key("request 9876 at 2026-10-16 ");
+        assert_eq!(a, b);
+        assert_ne!(a, PredictionCache::key("response 1234 at 2026-01-01"));
+    }
+
+    #[test]
+    fn test_lru_eviction() {
+        let cache = PredictionCache::new(2);
+        cache.insert(1, decision(Algorithm::M2M));
+        cache.insert(2, decision(Algorithm::Brotli));
+        assert!(cache.get(1).is_some()); // 1 is now most recent
+        cache.insert(3, decision(Algorithm::None));
+
+        assert!(cache.get(2).is_none());
+        assert_eq!(cache.get(1).unwrap().algorithm, Algorithm::M2M);
+        assert_eq!(cache.get(3).unwrap().algorithm, Algorithm::None);
+
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses, stats.evictions), (3, 1, 1));
+        assert_eq!(stats.entries, 2);
+        assert!((stats.hit_rate() - 0.75).abs() < 1e-9);
+    }
+}
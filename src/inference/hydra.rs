@@ -42,13 +42,30 @@
 //! ## Heuristic Fallback
 //!
 //! When model loading fails, Hydra falls back to rule-based heuristics.
+//!
+//! ## Batching and Caching
+//!
+//! [`HydraModel::predict_compression_batch`] runs several payloads through
+//! the model in one pass. With [`HydraModel::with_cache`], compression
+//! decisions are cached by content features so repeated near-identical
+//! prompts skip inference; [`HydraModel::cache_stats`] reports the hit rate.
+//!
+//! ## Quantization
+//!
+//! [`HydraModel::with_precision`] runs the native model with int8 or
+//! ternary weights, and [`HydraModel::with_accuracy_floor`] picks the
+//! smallest precision that agrees with the float32 model on a built-in
+//! calibration set at least as often as the floor.
 
 use std::path::Path;
+use std::sync::Arc;
 
 use crate::codec::Algorithm;
-use crate::error::Result;
+use crate::error::{M2MError, Result};
 
 use super::bitnet::HydraBitNet;
+use super::cache::{CacheStats, PredictionCache};
+use super::quant::{Precision, QuantizationReport, CALIBRATION_SAMPLES};
 use super::tokenizer::{boxed, BoxedTokenizer, HydraByteTokenizer, TokenizerType};
 
 /// Compression decision from the model
@@ -161,6 +178,10 @@ pub struct HydraModel {
     native_model: Option<HydraBitNet>,
     /// Model's vocabulary size (for clamping)
     model_vocab_size: usize,
+    /// Compression decision cache (shared between clones)
+    cache: Option<Arc<PredictionCache>>,
+    /// Agreement of the quantized model with float32 (if measured)
+    quantization: Option<QuantizationReport>,
 }
 
 impl Clone for HydraModel {
@@ -172,6 +193,8 @@ impl Clone for HydraModel {
             use_fallback: self.use_fallback,
             native_model: self.native_model.clone(),
             model_vocab_size: self.model_vocab_size,
+            cache: self.cache.clone(),
+            quantization: self.quantization,
         }
     }
 }
@@ -195,6 +218,8 @@ impl HydraModel {
             use_fallback: true,
             native_model: None,
             model_vocab_size: Self::DEFAULT_MODEL_VOCAB_SIZE,
+            cache: None,
+            quantization: None,
         }
     }
 
@@ -207,6 +232,8 @@ impl HydraModel {
             use_fallback: true,
             native_model: None,
             model_vocab_size: Self::DEFAULT_MODEL_VOCAB_SIZE,
+            cache: None,
+            quantization: None,
         }
     }
 
@@ -219,6 +246,8 @@ impl HydraModel {
             use_fallback: true,
             native_model: None,
             model_vocab_size: Self::DEFAULT_MODEL_VOCAB_SIZE,
+            cache: None,
+            quantization: None,
         }
     }
 
@@ -267,6 +296,8 @@ impl HydraModel {
                         use_fallback: false,
                         native_model: Some(model),
                         model_vocab_size: model_vocab,
+                        cache: None,
+                        quantization: None,
                     });
                 },
                 Err(e) => {
@@ -287,9 +318,87 @@ impl HydraModel {
             use_fallback: true,
             native_model: None,
             model_vocab_size: Self::DEFAULT_MODEL_VOCAB_SIZE,
+            cache: None,
+            quantization: None,
         })
     }
 
+    /// Cache compression decisions, keeping at most `capacity` entries
+    ///
+    /// See [`PredictionCache::key`] for which payloads share an entry.
+    pub fn with_cache(mut self, capacity: usize) -> Self {
+        self.cache = Some(Arc::new(PredictionCache::new(capacity)));
+        self
+    }
+
+    /// Run the native model with weights at `precision`
+    ///
+    /// Has no effect on the heuristic fallback. Call on a freshly loaded
+    /// (float32) model.
+    pub fn with_precision(mut self, precision: Precision) -> Self {
+        if let Some(model) = self.native_model.take() {
+            let quantized = model.quantize(precision);
+            let samples = self.calibration_tokens();
+            self.quantization = Some(quantized.compare(&model, &samples));
+            self.native_model = Some(quantized);
+            self.clear_cache();
+        }
+        self
+    }
+
+    /// Quantize the native model to the smallest precision whose
+    /// predictions agree with float32 on the calibration set at least
+    /// `floor` of the time (0.0 - 1.0)
+    ///
+    /// Keeps float32 when no quantized model meets the floor.
+    pub fn with_accuracy_floor(mut self, floor: f32) -> Self {
+        if let Some(model) = self.native_model.take() {
+            let samples = self.calibration_tokens();
+            let (selected, report) = model.quantize_to_floor(&samples, floor.clamp(0.0, 1.0));
+            tracing::info!(
+                "Selected {} Hydra weights ({} bytes, {:.1}% agreement with f32)",
+                report.precision,
+                report.weight_bytes,
+                report.agreement() * 100.0
+            );
+            self.quantization = Some(report);
+            self.native_model = Some(selected);
+            self.clear_cache();
+        }
+        self
+    }
+
+    /// Weight precision of the native model (if loaded)
+    pub fn precision(&self) -> Option<Precision> {
+        self.native_model.as_ref().map(HydraBitNet::precision)
+    }
+
+    /// Agreement of the quantized model with float32 (if quantized)
+    pub fn quantization_report(&self) -> Option<&QuantizationReport> {
+        self.quantization.as_ref()
+    }
+
+    /// Tokenized calibration samples for quantization checks
+    fn calibration_tokens(&self) -> Vec<Vec<u32>> {
+        CALIBRATION_SAMPLES
+            .iter()
+            .filter_map(|sample| self.tokenizer.encode_for_hydra(sample).ok())
+            .map(|tokens| self.clamp_tokens(&tokens))
+            .collect()
+    }
+
+    /// Drop cached decisions made by a model that has been replaced
+    fn clear_cache(&self) {
+        if let Some(ref cache) = self.cache {
+            cache.clear();
+        }
+    }
+
+    /// Prediction cache counters (if caching is enabled)
+    pub fn cache_stats(&self) -> Option<CacheStats> {
+        self.cache.as_ref().map(|cache| cache.stats())
+    }
+
     /// Check if model is loaded
     pub fn is_loaded(&self) -> bool {
         self.loaded
@@ -337,13 +446,70 @@ impl HydraModel {
 
     /// Predict compression algorithm for content
     pub fn predict_compression(&self, content: &str) -> Result<CompressionDecision> {
-        // Try native model first
-        if let Some(ref model) = self.native_model {
-            return self.predict_compression_native(model, content);
+        let key = PredictionCache::key(content);
+        if let Some(decision) = self.cache.as_ref().and_then(|cache| cache.get(key)) {
+            return Ok(decision);
         }
 
-        // Use heuristic fallback
-        self.predict_compression_heuristic(content)
+        // Try native model first, then heuristic fallback
+        let decision = match self.native_model {
+            Some(ref model) => self.predict_compression_native(model, content)?,
+            None => self.predict_compression_heuristic(content)?,
+        };
+
+        if let Some(ref cache) = self.cache {
+            cache.insert(key, decision.clone());
+        }
+        Ok(decision)
+    }
+
+    /// Predict compression algorithms for several payloads
+    ///
+    /// Cached payloads are answered from the cache; the rest are tokenized
+    /// and run through the model as a single batch. Decisions are returned
+    /// in input order.
+    pub fn predict_compression_batch(&self, contents: &[&str]) -> Result<Vec<CompressionDecision>> {
+        let keys: Vec<u64> = contents.iter().map(|c| PredictionCache::key(c)).collect();
+        let mut decisions: Vec<Option<CompressionDecision>> = keys
+            .iter()
+            .map(|&key| self.cache.as_ref().and_then(|cache| cache.get(key)))
+            .collect();
+
+        // Tokenize the misses; empty token sequences use the heuristics
+        let mut batch = Vec::new();
+        let mut batch_slots = Vec::new();
+        for (slot, content) in contents.iter().enumerate() {
+            if decisions[slot].is_some() {
+                continue;
+            }
+            if self.native_model.is_some() {
+                let token_ids = self.tokenizer.encode_for_hydra(content)?;
+                if !token_ids.is_empty() {
+                    batch.push(self.clamp_tokens(&token_ids));
+                    batch_slots.push(slot);
+                    continue;
+                }
+            }
+            decisions[slot] = Some(self.predict_compression_heuristic(content)?);
+        }
+
+        if let (Some(ref model), false) = (&self.native_model, batch.is_empty()) {
+            let probs = model.predict_compression_batch(&batch);
+            for (row, slot) in probs.rows().into_iter().zip(batch_slots) {
+                decisions[slot] = Some(Self::decision_from_probs(&row.to_vec()));
+            }
+        }
+
+        let mut results = Vec::with_capacity(contents.len());
+        for (decision, key) in decisions.into_iter().zip(keys) {
+            let decision = decision
+                .ok_or_else(|| M2MError::Inference("Batch prediction missing".to_string()))?;
+            if let Some(ref cache) = self.cache {
+                cache.insert(key, decision.clone());
+            }
+            results.push(decision);
+        }
+        Ok(results)
     }
 
     /// Predict security status for content
@@ -376,6 +542,12 @@ impl HydraModel {
 
         let probs = model.predict_compression(&token_ids);
 
+        Ok(Self::decision_from_probs(&probs.to_vec()))
+    }
+
+    /// Map model output probabilities to a decision
+    #[allow(deprecated)] // Zlib variant is deprecated but still in model output
+    fn decision_from_probs(probs: &[f32]) -> CompressionDecision {
         // Map output: [NONE, BPE, BROTLI, ZLIB] -> Algorithm
         // Note: BPE maps to TokenNative in our system
         let algorithms = [
@@ -391,7 +563,7 @@ impl HydraModel {
             .map(|(a, c)| (*a, *c))
             .unwrap_or((Algorithm::None, 0.0));
 
-        Ok(CompressionDecision {
+        CompressionDecision {
             algorithm: best_algo,
             confidence,
             probabilities: AlgorithmProbs {
@@ -400,7 +572,7 @@ impl HydraModel {
                 m2m: probs[3], // Map legacy zlib output to M2M
                 brotli: probs[2],
             },
-        })
+        }
     }
 
     /// Native inference for security
@@ -711,6 +883,41 @@ mod tests {
         assert_eq!(decision.threat_type, Some(ThreatType::Jailbreak));
     }
 
+    #[test]
+    fn test_prediction_cache() {
+        let model = HydraModel::fallback_only().with_cache(16);
+        let first = r#"{"model":"gpt-4o","messages":[{"role":"user","content":"Order 1042"}]}"#;
+        let repeat = r#"{"model":"gpt-4o","messages":[{"role":"user","content":"Order 7781"}]}"#;
+
+        let a = model.predict_compression(first).unwrap();
+        let b = model.clone().predict_compression(repeat).unwrap(); // Clones share the cache
+        assert_eq!(a.algorithm, b.algorithm);
+
+        let stats = model.cache_stats().unwrap();
+        assert_eq!((stats.hits, stats.misses), (1, 1));
+        assert!(HydraModel::fallback_only().cache_stats().is_none());
+    }
+
+    #[test]
+    fn test_predict_compression_batch() {
+        let model = HydraModel::fallback_only().with_cache(16);
+        let llm = r#"{"model":"gpt-4o","messages":[{"role":"user","content":"Hello world!"}]}"#;
+
+        let decisions = model.predict_compression_batch(&["hi", llm, "hi"]).unwrap();
+        let algorithms: Vec<_> = decisions.iter().map(|d| d.algorithm).collect();
+        assert_eq!(
+            algorithms,
+            [Algorithm::None, Algorithm::M2M, Algorithm::None]
+        );
+        assert_eq!(model.cache_stats().unwrap().entries, 2);
+
+        assert_eq!(
+            model.predict_compression(llm).unwrap().algorithm,
+            Algorithm::M2M
+        );
+        assert_eq!(model.cache_stats().unwrap().hits, 1);
+    }
+
     #[test]
     fn test_algorithm_probs_best() {
         let probs = AlgorithmProbs {
@@ -14,6 +14,7 @@
 //! Hydra supports multiple tokenizer backends:
 //!
 //! - **Llama 3** (128K vocab): Primary tokenizer for open source ecosystem
+//!   (`hf-tokenizers` feature)
 //! - **o200k_base** (200K vocab): OpenAI GPT-4o, o1, o3
 //! - **cl100k_base** (100K vocab): OpenAI GPT-3.5, GPT-4
 //! - **Fallback**: Byte-level tokenizer when nothing else available
@@ -35,6 +36,13 @@
 //! - Heterogeneous expert architectures (different depths/widths)
 //! - ~100MB model size (float32 weights with 128K vocab)
 //!
+//! # Quantization
+//!
+//! The native model can run with int8 or BitNet b1.58 ternary weights,
+//! cutting weight memory roughly 4x (see [`Precision`]).
+//! [`HydraModel::with_accuracy_floor`] picks the smallest precision whose
+//! predictions agree with the float32 model often enough.
+//!
 //! # Download
 //!
 //! ```bash
@@ -57,17 +65,24 @@
 //! ```
 
 pub mod bitnet;
+mod cache;
 mod hydra;
+mod quant;
 pub mod tokenizer;
 
 pub use bitnet::HydraBitNet;
+pub use cache::{CacheStats, PredictionCache, DEFAULT_CACHE_CAPACITY};
 pub use hydra::{CompressionDecision, HydraModel, SecurityDecision, ThreatType};
+pub use quant::{Precision, QuantizationReport};
 
 // Tokenizer exports
+#[cfg(feature = "hf-tokenizers")]
+pub use tokenizer::Llama3Tokenizer;
+#[cfg(feature = "tiktoken")]
+pub use tokenizer::TiktokenTokenizer;
 pub use tokenizer::{
     boxed, load_tokenizer, load_tokenizer_by_type, BoxedTokenizer, FallbackTokenizer,
-    HydraByteTokenizer, HydraTokenizer, Llama3Tokenizer, TiktokenTokenizer, TokenizerType,
-    MAX_SEQUENCE_LENGTH,
+    HydraByteTokenizer, HydraTokenizer, TokenizerType, MAX_SEQUENCE_LENGTH,
 };
 
 /// Model version
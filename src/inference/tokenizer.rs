@@ -2,7 +2,8 @@
 //!
 //! Provides a unified trait for tokenization with multiple backend implementations:
 //!
-//! - [`Llama3Tokenizer`]: HuggingFace Tokenizers format (Llama 3, Mistral, etc.)
+//! - [`Llama3Tokenizer`]: HuggingFace Tokenizers format (Llama 3, Mistral, etc.),
+//!   requires the `hf-tokenizers` feature
 //! - [`TiktokenTokenizer`]: OpenAI tiktoken format (cl100k, o200k)
 //! - [`FallbackTokenizer`]: Simple byte-level fallback
 //!
@@ -27,9 +28,11 @@ use std::sync::Arc;
 use crate::error::{M2MError, Result};
 
 // Re-export tiktoken for OpenAI tokenizers
+#[cfg(feature = "tiktoken")]
 use tiktoken_rs::{cl100k_base, o200k_base, CoreBPE};
 
 // HuggingFace tokenizers
+#[cfg(feature = "hf-tokenizers")]
 use tokenizers::Tokenizer;
 
 /// Maximum sequence length for Hydra input
@@ -40,6 +43,8 @@ pub const MAX_SEQUENCE_LENGTH: usize = 512;
 pub enum TokenizerType {
     /// Llama 3 tokenizer (128K vocab, HuggingFace format)
     Llama3,
+    /// Mistral tokenizer (32K vocab, HuggingFace format)
+    Mistral,
     /// OpenAI o200k_base (200K vocab, tiktoken)
     O200kBase,
     /// OpenAI cl100k_base (100K vocab, tiktoken)
@@ -54,6 +59,7 @@ impl TokenizerType {
     pub fn vocab_size(&self) -> usize {
         match self {
             Self::Llama3 => 128_000,
+            Self::Mistral => 32_768,
             Self::O200kBase => 200_019,
             Self::Cl100kBase => 100_256,
             Self::Fallback => 256, // Byte-level
@@ -65,6 +71,7 @@ impl TokenizerType {
     pub fn name(&self) -> &'static str {
         match self {
             Self::Llama3 => "llama3",
+            Self::Mistral => "mistral",
             Self::O200kBase => "o200k_base",
             Self::Cl100kBase => "cl100k_base",
             Self::Fallback => "fallback",
@@ -130,7 +137,8 @@ pub trait HydraTokenizer: Send + Sync {
 /// Llama 3 tokenizer using HuggingFace Tokenizers library.
 ///
 /// This is the primary tokenizer for Hydra, supporting the 128K vocabulary
-/// used by Llama 3 and compatible models.
+/// used by Llama 3 and compatible models. Requires the `hf-tokenizers`
+/// feature.
 ///
 /// # Example
 ///
@@ -138,11 +146,14 @@ pub trait HydraTokenizer: Send + Sync {
 /// let tokenizer = Llama3Tokenizer::from_file("./tokenizer.json")?;
 /// let tokens = tokenizer.encode("Hello, world!")?;
 /// ```
+#[cfg(feature = "hf-tokenizers")]
 pub struct Llama3Tokenizer {
     inner: Tokenizer,
     vocab_size: usize,
+    tokenizer_type: TokenizerType,
 }
 
+#[cfg(feature = "hf-tokenizers")]
 impl Llama3Tokenizer {
     /// Load tokenizer from a `tokenizer.json` file.
     ///
@@ -155,7 +166,11 @@ impl Llama3Tokenizer {
 
         let vocab_size = inner.get_vocab_size(true);
 
-        Ok(Self { inner, vocab_size })
+        Ok(Self {
+            inner,
+            vocab_size,
+            tokenizer_type: TokenizerType::Llama3,
+        })
     }
 
     /// Load tokenizer from JSON string.
@@ -178,10 +193,25 @@ impl Llama3Tokenizer {
 
         let vocab_size = inner.get_vocab_size(true);
 
-        Ok(Self { inner, vocab_size })
+        Ok(Self {
+            inner,
+            vocab_size,
+            tokenizer_type: TokenizerType::Llama3,
+        })
+    }
+
+    /// Report a different vocabulary type (e.g. [`TokenizerType::Mistral`]).
+    ///
+    /// The file format is shared by HuggingFace models; the type tells
+    /// consumers such as the token-native codec which vocabulary was loaded.
+    #[must_use]
+    pub fn with_type(mut self, tokenizer_type: TokenizerType) -> Self {
+        self.tokenizer_type = tokenizer_type;
+        self
     }
 }
 
+#[cfg(feature = "hf-tokenizers")]
 impl HydraTokenizer for Llama3Tokenizer {
     fn encode(&self, text: &str) -> Result<Vec<u32>> {
         let encoding = self
@@ -203,7 +233,7 @@ impl HydraTokenizer for Llama3Tokenizer {
     }
 
     fn tokenizer_type(&self) -> TokenizerType {
-        TokenizerType::Llama3
+        self.tokenizer_type
     }
 }
 
@@ -214,11 +244,14 @@ impl HydraTokenizer for Llama3Tokenizer {
 /// OpenAI tiktoken-based tokenizer.
 ///
 /// Supports cl100k_base (GPT-4) and o200k_base (GPT-4o) encodings.
+/// Requires the `tiktoken` feature.
+#[cfg(feature = "tiktoken")]
 pub struct TiktokenTokenizer {
     inner: CoreBPE,
     tokenizer_type: TokenizerType,
 }
 
+#[cfg(feature = "tiktoken")]
 impl TiktokenTokenizer {
     /// Create cl100k_base tokenizer (GPT-3.5, GPT-4).
     ///
@@ -266,6 +299,7 @@ impl TiktokenTokenizer {
     }
 }
 
+#[cfg(feature = "tiktoken")]
 impl HydraTokenizer for TiktokenTokenizer {
     fn encode(&self, text: &str) -> Result<Vec<u32>> {
         // tiktoken Rank is u32, so direct collect works
@@ -473,6 +507,7 @@ pub fn boxed<T: HydraTokenizer + 'static>(tokenizer: T) -> BoxedTokenizer {
 /// ```
 pub fn load_tokenizer(tokenizer_path: Option<&Path>, vocab_size: usize) -> Result<BoxedTokenizer> {
     // Try to load Llama 3 tokenizer if path provided
+    #[cfg(feature = "hf-tokenizers")]
     if let Some(path) = tokenizer_path {
         if path.exists() {
             match Llama3Tokenizer::from_file(path) {
@@ -490,6 +525,10 @@ pub fn load_tokenizer(tokenizer_path: Option<&Path>, vocab_size: usize) -> Resul
             }
         }
     }
+    #[cfg(not(feature = "hf-tokenizers"))]
+    if tokenizer_path.is_some() {
+        tracing::warn!("Ignoring tokenizer path: built without the hf-tokenizers feature");
+    }
 
     // Fallback
     tracing::warn!(
@@ -508,14 +547,35 @@ pub fn load_tokenizer_by_type(
     tokenizer_type: TokenizerType,
     tokenizer_path: Option<&Path>,
 ) -> Result<BoxedTokenizer> {
+    #[cfg(not(feature = "hf-tokenizers"))]
+    let _ = tokenizer_path;
     match tokenizer_type {
+        #[cfg(feature = "hf-tokenizers")]
         TokenizerType::Llama3 => {
             let path = tokenizer_path
                 .ok_or_else(|| M2MError::Tokenizer("Llama3 tokenizer requires a path".into()))?;
             Ok(boxed(Llama3Tokenizer::from_file(path)?))
         },
+        #[cfg(feature = "hf-tokenizers")]
+        TokenizerType::Mistral => {
+            let path = tokenizer_path
+                .ok_or_else(|| M2MError::Tokenizer("Mistral tokenizer requires a path".into()))?;
+            Ok(boxed(
+                Llama3Tokenizer::from_file(path)?.with_type(TokenizerType::Mistral),
+            ))
+        },
+        #[cfg(not(feature = "hf-tokenizers"))]
+        TokenizerType::Llama3 | TokenizerType::Mistral => Err(M2MError::Tokenizer(format!(
+            "Tokenizer type {tokenizer_type} requires the hf-tokenizers feature"
+        ))),
+        #[cfg(feature = "tiktoken")]
         TokenizerType::O200kBase => Ok(boxed(TiktokenTokenizer::o200k()?)),
+        #[cfg(feature = "tiktoken")]
         TokenizerType::Cl100kBase => Ok(boxed(TiktokenTokenizer::cl100k()?)),
+        #[cfg(not(feature = "tiktoken"))]
+        TokenizerType::O200kBase | TokenizerType::Cl100kBase => Err(M2MError::Tokenizer(format!(
+            "Tokenizer type {tokenizer_type} requires the tiktoken feature"
+        ))),
         TokenizerType::Fallback => Ok(boxed(FallbackTokenizer::new())),
     }
 }
@@ -555,6 +615,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(feature = "tiktoken")]
     fn test_tiktoken_cl100k() {
         let tokenizer = TiktokenTokenizer::cl100k().unwrap();
 
@@ -569,6 +630,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(feature = "tiktoken")]
     fn test_tiktoken_o200k() {
         let tokenizer = TiktokenTokenizer::o200k().unwrap();
 
@@ -597,6 +659,7 @@ mod tests {
     #[test]
     fn test_tokenizer_type_vocab_size() {
         assert_eq!(TokenizerType::Llama3.vocab_size(), 128_000);
+        assert_eq!(TokenizerType::Mistral.vocab_size(), 32_768);
         assert_eq!(TokenizerType::O200kBase.vocab_size(), 200_019);
         assert_eq!(TokenizerType::Cl100kBase.vocab_size(), 100_256);
         assert_eq!(TokenizerType::Fallback.vocab_size(), 256);
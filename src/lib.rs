@@ -145,11 +145,17 @@
 //!
 //! ## Modules
 //!
+//! - [`client`]: Typed async client for M2M servers and OpenAI-compatible endpoints
 //! - [`codec`]: Multi-algorithm compression engine
 //! - [`protocol`]: Session management and capability negotiation
+//! - [`context`]: Conversation prefixes pinned at the peer and referenced by handle
+//! - [`discovery`]: Agent directory and peer discovery
 //! - [`inference`]: Hydra ML model for algorithm routing
+//! - [`loadgen`]: Concurrent load and soak testing against a running server
+//! - [`reports`]: Daily/weekly compression savings reports
 //! - [`security`]: Threat detection and content scanning
 //! - [`server`]: HTTP API server (Axum-based)
+//! - [`testvectors`]: Conformance test vectors for other implementations
 //! - [`models`]: LLM model registry and metadata
 //! - [`config`]: Configuration management
 //! - [`error`]: Error types and result aliases
@@ -171,28 +177,37 @@
 //! - **Hybrid**: Best for streaming use cases
 //! - **None**: Content under 100 bytes (overhead exceeds savings)
 
+pub mod client;
 pub mod codec;
 pub mod config;
+pub mod context;
+pub mod discovery;
 pub mod error;
 pub mod inference;
+pub mod loadgen;
 pub mod models;
 pub mod protocol;
+pub mod reports;
+pub mod runtime;
 pub mod security;
 pub mod server;
+pub mod testvectors;
 pub mod tokenizer;
 pub mod transport;
 
 // Re-exports for convenience
+pub use client::M2MClient;
 pub use codec::{Algorithm, CodecEngine, CompressionResult, StreamingCodec, StreamingDecompressor};
 pub use config::Config;
-pub use error::{M2MError, Result};
+pub use error::{ErrorCategory, ErrorCode, M2MError, Result};
 pub use inference::{HydraModel, SecurityDecision};
 pub use models::{ModelCard, ModelRegistry, Provider};
 pub use protocol::{Capabilities, Message, Session, SessionState};
 pub use security::{ScanResult, SecurityScanner};
 pub use server::{AppState, ServerConfig};
 pub use tokenizer::{
-    count_tokens, count_tokens_for_model, count_tokens_with_encoding, TokenCounter,
+    count_tokens, count_tokens_cached, count_tokens_for_model, count_tokens_with_encoding,
+    TokenCounter,
 };
 pub use transport::{QuicTransport, QuicTransportConfig, TcpTransport, Transport, TransportKind};
 
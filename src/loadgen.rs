@@ -0,0 +1,517 @@
+//! Concurrent load and soak testing against a running server.
+//!
+//! [`run`] spins up simulated clients, each with its own session, that
+//! issue a weighted mix of operations against a server's HTTP API and
+//! record how long each one took:
+//!
+//! | Operation | Request |
+//! |-----------|---------|
+//! | `Handshake` | HELLO on `/message`, establishing a fresh session |
+//! | `Compress` | `/compress` with the configured content |
+//! | `Data` | DATA on `/message` over the client's session |
+//!
+//! A client's first DATA performs a handshake first (recorded as one), and
+//! a client whose session the server dropped renegotiates on its next DATA.
+//! Each client walks the mix in a fixed order, offset by its index, so runs
+//! are reproducible and the observed mix matches the configured weights.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use std::time::Duration;
+//! use m2m::loadgen::{self, LoadConfig, Operation, OperationMix};
+//!
+//! let config = LoadConfig::new("http://localhost:8080")
+//!     .with_clients(50)
+//!     .with_mix(OperationMix::new(1, 0, 9))
+//!     .with_duration(Duration::from_secs(300)); // soak
+//! let report = loadgen::run(&config).await?;
+//! println!("{report}");
+//! assert!(report.stats(Operation::Data).unwrap().p99 < Duration::from_millis(50));
+//! ```
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::error::{M2MError, Result};
+use crate::protocol::{Capabilities, Message, MessageType, Session};
+
+/// Default number of simulated clients
+pub const DEFAULT_CLIENTS: usize = 10;
+
+/// Default operations per client
+pub const DEFAULT_OPERATIONS: usize = 100;
+
+/// Default per-request timeout
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Default content compressed by `Compress` and `Data`
+pub const DEFAULT_CONTENT: &str = r#"{"model":"gpt-4o","messages":[{"role":"system","content":"You are a helpful assistant."},{"role":"user","content":"Summarize the M2M protocol handshake in two sentences."}],"temperature":0.7}"#;
+
+/// Operation performed by a simulated client
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Operation {
+    /// HELLO/ACCEPT handshake
+    Handshake,
+    /// `/compress` call
+    Compress,
+    /// DATA exchange over an established session
+    Data,
+}
+
+impl Operation {
+    /// All operations, in mix order
+    pub const ALL: [Self; 3] = [Self::Handshake, Self::Compress, Self::Data];
+
+    /// Display name
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Handshake => "handshake",
+            Self::Compress => "compress",
+            Self::Data => "data",
+        }
+    }
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Relative weights of the operations
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OperationMix {
+    /// Weight of `Handshake`
+    pub handshake: u32,
+    /// Weight of `Compress`
+    pub compress: u32,
+    /// Weight of `Data`
+    pub data: u32,
+}
+
+impl Default for OperationMix {
+    /// Mostly DATA over long-lived sessions: 1 handshake, 2 compress, 7 data
+    fn default() -> Self {
+        Self::new(1, 2, 7)
+    }
+}
+
+impl OperationMix {
+    /// Create a mix from weights
+    pub fn new(handshake: u32, compress: u32, data: u32) -> Self {
+        Self {
+            handshake,
+            compress,
+            data,
+        }
+    }
+
+    /// Weight of `op`
+    pub fn weight(&self, op: Operation) -> u32 {
+        match op {
+            Operation::Handshake => self.handshake,
+            Operation::Compress => self.compress,
+            Operation::Data => self.data,
+        }
+    }
+
+    fn total(&self) -> u64 {
+        Operation::ALL
+            .iter()
+            .map(|&op| u64::from(self.weight(op)))
+            .sum()
+    }
+
+    /// Operation at position `step` of the repeating schedule
+    fn pick(&self, step: u64) -> Operation {
+        let mut slot = step % self.total();
+        for op in Operation::ALL {
+            let weight = u64::from(self.weight(op));
+            if slot < weight {
+                return op;
+            }
+            slot -= weight;
+        }
+        unreachable!("slot is below the total weight")
+    }
+}
+
+/// Load test configuration
+#[derive(Debug, Clone)]
+pub struct LoadConfig {
+    /// Server base URL
+    url: String,
+    /// Simulated clients running concurrently
+    clients: usize,
+    /// Operations per client (ignored when a duration is set)
+    operations: usize,
+    /// Run until this much time has passed (soak mode)
+    duration: Option<Duration>,
+    /// Operation weights
+    mix: OperationMix,
+    /// JSON compressed by `Compress` and `Data`
+    content: String,
+    /// Capabilities offered in each HELLO
+    capabilities: Capabilities,
+    /// Per-request timeout
+    timeout: Duration,
+}
+
+impl LoadConfig {
+    /// Configure a run against the server at `url`
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into().trim_end_matches('/').to_string(),
+            clients: DEFAULT_CLIENTS,
+            operations: DEFAULT_OPERATIONS,
+            duration: None,
+            mix: OperationMix::default(),
+            content: DEFAULT_CONTENT.to_string(),
+            capabilities: Capabilities::new("m2m-loadgen"),
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+
+    /// Set the number of concurrent clients
+    pub fn with_clients(mut self, clients: usize) -> Self {
+        self.clients = clients;
+        self
+    }
+
+    /// Set the operations each client performs
+    pub fn with_operations(mut self, operations: usize) -> Self {
+        self.operations = operations;
+        self
+    }
+
+    /// Run for `duration` instead of a fixed operation count
+    pub fn with_duration(mut self, duration: Duration) -> Self {
+        self.duration = Some(duration);
+        self
+    }
+
+    /// Set the operation weights
+    pub fn with_mix(mut self, mix: OperationMix) -> Self {
+        self.mix = mix;
+        self
+    }
+
+    /// Set the JSON payload sent by `Compress` and `Data`
+    pub fn with_content(mut self, content: impl Into<String>) -> Self {
+        self.content = content.into();
+        self
+    }
+
+    /// Set the capabilities offered in each HELLO
+    pub fn with_capabilities(mut self, capabilities: Capabilities) -> Self {
+        self.capabilities = capabilities;
+        self
+    }
+
+    /// Set the per-request timeout
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+}
+
+/// Latency distribution of one operation
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LatencyStats {
+    /// Successful operations
+    pub count: usize,
+    /// Failed operations
+    pub errors: usize,
+    /// Mean latency of successful operations
+    pub mean: Duration,
+    /// Median latency
+    pub p50: Duration,
+    /// 90th percentile latency
+    pub p90: Duration,
+    /// 99th percentile latency
+    pub p99: Duration,
+    /// Slowest operation
+    pub max: Duration,
+}
+
+impl LatencyStats {
+    fn from_samples(mut samples: Vec<Duration>, errors: usize) -> Self {
+        samples.sort_unstable();
+        let count = samples.len();
+        // Nearest-rank percentile
+        let percentile = |p: usize| {
+            samples
+                .get((count * p).div_ceil(100).saturating_sub(1))
+                .copied()
+                .unwrap_or_default()
+        };
+        let total: Duration = samples.iter().sum();
+        Self {
+            count,
+            errors,
+            mean: total.checked_div(count as u32).unwrap_or_default(),
+            p50: percentile(50),
+            p90: percentile(90),
+            p99: percentile(99),
+            max: samples.last().copied().unwrap_or_default(),
+        }
+    }
+}
+
+/// Results of a load run
+#[derive(Debug, Clone, Default)]
+pub struct LoadReport {
+    /// Wall-clock time of the run
+    pub elapsed: Duration,
+    /// Latencies per operation performed
+    pub operations: BTreeMap<Operation, LatencyStats>,
+    /// First error seen, for diagnosis
+    pub first_error: Option<String>,
+}
+
+impl LoadReport {
+    /// Latencies of `op`, if it was performed
+    pub fn stats(&self, op: Operation) -> Option<&LatencyStats> {
+        self.operations.get(&op)
+    }
+
+    /// Successful operations
+    pub fn total(&self) -> usize {
+        self.operations.values().map(|s| s.count).sum()
+    }
+
+    /// Failed operations
+    pub fn errors(&self) -> usize {
+        self.operations.values().map(|s| s.errors).sum()
+    }
+
+    /// Successful operations per second
+    pub fn throughput(&self) -> f64 {
+        let secs = self.elapsed.as_secs_f64();
+        if secs == 0.0 {
+            0.0
+        } else {
+            self.total() as f64 / secs
+        }
+    }
+}
+
+impl fmt::Display for LoadReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} ops in {:.1}s ({:.0} ops/s), {} errors",
+            self.total(),
+            self.elapsed.as_secs_f64(),
+            self.throughput(),
+            self.errors()
+        )?;
+        for (op, stats) in &self.operations {
+            writeln!(
+                f,
+                "  {:<9} {:>7} ok {:>5} err | p50 {:>8.2?} | p90 {:>8.2?} | p99 {:>8.2?} | max {:>8.2?}",
+                op.name(),
+                stats.count,
+                stats.errors,
+                stats.p50,
+                stats.p90,
+                stats.p99,
+                stats.max
+            )?;
+        }
+        if let Some(ref error) = self.first_error {
+            writeln!(f, "  first error: {error}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Run the configured load against the server
+///
+/// Fails only on an invalid configuration; individual operation failures
+/// are counted in the report.
+pub async fn run(config: &LoadConfig) -> Result<LoadReport> {
+    if config.clients == 0 || config.mix.total() == 0 {
+        return Err(M2MError::Config(
+            "Load test needs at least one client and a non-zero mix".to_string(),
+        ));
+    }
+    let http = reqwest::Client::builder()
+        .timeout(config.timeout)
+        .build()
+        .map_err(|e| M2MError::Network(e.to_string()))?;
+    let config = Arc::new(config.clone());
+
+    let started = Instant::now();
+    let deadline = config.duration.map(|duration| started + duration);
+    let tasks: Vec<_> = (0..config.clients)
+        .map(|index| {
+            let client = SimulatedClient {
+                http: http.clone(),
+                config: Arc::clone(&config),
+                session: None,
+            };
+            tokio::spawn(client.run(index as u64, deadline))
+        })
+        .collect();
+
+    let mut samples: BTreeMap<Operation, (Vec<Duration>, usize)> = BTreeMap::new();
+    let mut first_error = None;
+    for task in tasks {
+        let outcomes = task
+            .await
+            .map_err(|e| M2MError::Server(format!("Load client panicked: {e}")))?;
+        for (op, latency, outcome) in outcomes {
+            let (latencies, errors) = samples.entry(op).or_default();
+            match outcome {
+                Ok(()) => latencies.push(latency),
+                Err(e) => {
+                    *errors += 1;
+                    first_error.get_or_insert_with(|| format!("{op}: {e}"));
+                },
+            }
+        }
+    }
+
+    Ok(LoadReport {
+        elapsed: started.elapsed(),
+        operations: samples
+            .into_iter()
+            .map(|(op, (latencies, errors))| (op, LatencyStats::from_samples(latencies, errors)))
+            .collect(),
+        first_error,
+    })
+}
+
+/// Outcome of one timed operation
+type Outcome = (Operation, Duration, Result<()>);
+
+/// One client: a session and its share of the schedule
+struct SimulatedClient {
+    http: reqwest::Client,
+    config: Arc<LoadConfig>,
+    session: Option<Session>,
+}
+
+impl SimulatedClient {
+    async fn run(mut self, index: u64, deadline: Option<Instant>) -> Vec<Outcome> {
+        let mut outcomes = Vec::new();
+        for step in 0.. {
+            let done = match deadline {
+                Some(deadline) => Instant::now() >= deadline,
+                None => step >= self.config.operations,
+            };
+            if done {
+                break;
+            }
+
+            let op = self.config.mix.pick(index + step as u64);
+            if op == Operation::Data && self.session.is_none() {
+                let started = Instant::now();
+                let outcome = self.handshake().await;
+                let failed = outcome.is_err();
+                outcomes.push((Operation::Handshake, started.elapsed(), outcome));
+                if failed {
+                    continue;
+                }
+            }
+
+            let started = Instant::now();
+            let outcome = match op {
+                Operation::Handshake => self.handshake().await,
+                Operation::Compress => self.compress().await,
+                Operation::Data => self.data().await,
+            };
+            outcomes.push((op, started.elapsed(), outcome));
+        }
+        outcomes
+    }
+
+    /// POST a message to `/message` and parse the reply
+    async fn post_message(&self, message: &Message) -> Result<Message> {
+        self.http
+            .post(format!("{}/message", self.config.url))
+            .json(message)
+            .send()
+            .await
+            .map_err(|e| M2MError::Network(e.to_string()))?
+            .json()
+            .await
+            .map_err(|e| M2MError::Network(e.to_string()))
+    }
+
+    /// Establish a fresh session, replacing the current one
+    async fn handshake(&mut self) -> Result<()> {
+        let mut session = Session::new(self.config.capabilities.clone());
+        let accept = self.post_message(&session.create_hello()).await?;
+        session.process_accept(&accept)?;
+        self.session = Some(session);
+        Ok(())
+    }
+
+    async fn compress(&self) -> Result<()> {
+        let response = self
+            .http
+            .post(format!("{}/compress", self.config.url))
+            .json(&serde_json::json!({ "content": self.config.content }))
+            .send()
+            .await
+            .map_err(|e| M2MError::Network(e.to_string()))?;
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(M2MError::Server(format!(
+                "/compress returned {}",
+                response.status()
+            )))
+        }
+    }
+
+    async fn data(&mut self) -> Result<()> {
+        let Some(session) = self.session.as_mut() else {
+            return Err(M2MError::SessionNotEstablished);
+        };
+        let message = session.compress(&self.config.content)?;
+        let reply = self.post_message(&message).await?;
+        if reply.msg_type == MessageType::Data {
+            return Ok(());
+        }
+
+        // The server dropped the session; renegotiate on the next DATA
+        self.session = None;
+        Err(match reply.get_rejection() {
+            Some(rejection) => M2MError::Protocol(format!("DATA rejected: {}", rejection.message)),
+            None => M2MError::Protocol(format!("Unexpected {:?} reply to DATA", reply.msg_type)),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mix_and_percentiles() {
+        let mix = OperationMix::default();
+        let picks: Vec<Operation> = (0..20).map(|step| mix.pick(step)).collect();
+        let count = |op| picks.iter().filter(|&&p| p == op).count();
+        assert_eq!(count(Operation::Handshake), 2);
+        assert_eq!(count(Operation::Compress), 4);
+        assert_eq!(count(Operation::Data), 14);
+
+        let samples = (1..=100).map(Duration::from_millis).collect();
+        let stats = LatencyStats::from_samples(samples, 3);
+        assert_eq!(stats.count, 100);
+        assert_eq!(stats.errors, 3);
+        assert_eq!(stats.p50, Duration::from_millis(50));
+        assert_eq!(stats.p99, Duration::from_millis(99));
+        assert_eq!(stats.max, Duration::from_millis(100));
+        assert_eq!(
+            LatencyStats::from_samples(Vec::new(), 0).p99,
+            Duration::ZERO
+        );
+    }
+}
@@ -278,6 +278,14 @@ impl ModelRegistry {
     pub fn iter(&self) -> impl Iterator<Item = &ModelCard> {
         self.by_id.values()
     }
+
+    /// List dynamic models
+    pub fn list_dynamic(&self) -> Vec<ModelCard> {
+        self.dynamic
+            .read()
+            .map(|d| d.values().cloned().collect())
+            .unwrap_or_default()
+    }
 }
 
 /// OpenRouter API model response (for future dynamic fetching)
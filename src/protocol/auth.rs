@@ -0,0 +1,743 @@
+//! Handshake authentication.
+//!
+//! Capabilities say what an agent can do, not who it is. A HELLO may carry
+//! a [`HelloAuth`] credential that the responder checks before accepting:
+//!
+//! | Scheme  | Credential | Verified by |
+//! |---------|------------|-------------|
+//! | `psk`   | HMAC-SHA256 over the HELLO with a pre-shared key | key ID lookup, freshness, single-use nonce |
+//! | `token` | Static bearer token | SHA-256 digest lookup |
+//! | `token` | Signed token (`m2m1.<claims>.<mac>`) | HMAC with the token key, expiry |
+//!
+//! ```text
+//! mac = HMAC-SHA256(psk, "m2m-hello-auth-v1" ‖ key_id ‖ 0 ‖ nonce ‖ 0 ‖
+//!                        issued_at ‖ session_id ‖ 0 ‖ canonical caps)
+//! ```
+//!
+//! The PSK MAC covers the capabilities as RFC 8785 canonical JSON, so an
+//! on-path attacker cannot edit an authenticated HELLO. Bearer tokens are
+//! not bound to the HELLO and must only travel over an encrypted
+//! transport.
+//!
+//! A responder with a [`HelloAuthenticator`] rejects HELLOs with missing
+//! or invalid credentials with `SecurityPolicy`. The authenticated
+//! [`Principal`] is attached to the session for policy decisions (see
+//! [`Session::principal`](super::Session::principal)).
+//!
+//! Requests on an established session that do not travel inside it (such
+//! as collecting relayed messages) present the same credential with
+//! [`HelloCredential::authorize`]. The PSK MAC then uses the label
+//! `m2m-session-auth-v1` and empty capabilities, so it cannot be replayed
+//! as a HELLO.
+//!
+//! # Credentials Files
+//!
+//! Servers load credentials from TOML files (see
+//! [`HelloAuthenticator::with_psk_file`] and
+//! [`HelloAuthenticator::with_token_file`]):
+//!
+//! ```toml
+//! # psk.toml
+//! [[psk]]
+//! key_id = "billing-1"
+//! key = "000102...1f"   # hex, at least 16 bytes
+//! name = "billing"
+//! tenant = "acme"       # optional
+//! key_epoch = 0         # optional
+//!
+//! # tokens.toml
+//! [[token]]
+//! token = "s3cret"
+//! name = "ops"
+//! ```
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "crypto")]
+use std::collections::HashMap;
+#[cfg(feature = "crypto")]
+use std::time::Duration;
+
+#[cfg(feature = "crypto")]
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD as BASE64, Engine};
+#[cfg(feature = "crypto")]
+use hmac::{Hmac, Mac};
+#[cfg(feature = "crypto")]
+use sha2::{Digest, Sha256};
+
+#[cfg(feature = "crypto")]
+use super::early::{unix_millis, EarlyData, ReplayGuard};
+#[cfg(feature = "crypto")]
+use super::{Capabilities, Message};
+#[cfg(feature = "crypto")]
+use crate::codec::canonical::canonicalize_value;
+#[cfg(feature = "crypto")]
+use crate::codec::m2m::crypto::KeyMaterial;
+#[cfg(feature = "crypto")]
+use crate::error::{M2MError, Result};
+
+/// Freshness window for PSK-authenticated HELLOs (seconds)
+pub const AUTH_WINDOW_SECS: u64 = 60;
+
+/// Minimum pre-shared key and token key length (bytes)
+pub const MIN_AUTH_KEY_LEN: usize = 16;
+
+/// Domain separator for the HELLO MAC
+#[cfg(feature = "crypto")]
+const HELLO_MAC_LABEL: &[u8] = b"m2m-hello-auth-v1";
+
+/// Domain separator for the MAC authorizing a request on a session
+#[cfg(feature = "crypto")]
+const SESSION_MAC_LABEL: &[u8] = b"m2m-session-auth-v1";
+
+/// Prefix of signed tokens
+#[cfg(feature = "crypto")]
+const TOKEN_PREFIX: &str = "m2m1";
+
+/// Credential carried by a HELLO
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "scheme", rename_all = "snake_case")]
+pub enum HelloAuth {
+    /// HMAC over the HELLO with a pre-shared key
+    Psk {
+        /// Identifies the pre-shared key
+        key_id: String,
+        /// Single-use random nonce
+        nonce: String,
+        /// Issue time (Unix millis)
+        issued_at: u64,
+        /// HMAC-SHA256 tag (base64url)
+        mac: String,
+    },
+    /// Bearer token (static or signed)
+    Token {
+        /// The token
+        token: String,
+    },
+}
+
+/// Authenticated identity of a peer
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Principal {
+    /// Principal name (e.g. service or agent name)
+    pub name: String,
+    /// Tenant the principal belongs to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant: Option<String>,
+    /// Key epoch the credential was issued for (checked against revocations)
+    #[serde(default)]
+    pub key_epoch: u32,
+}
+
+impl Principal {
+    /// Create a principal without a tenant
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            tenant: None,
+            key_epoch: 0,
+        }
+    }
+
+    /// Set the tenant
+    pub fn with_tenant(mut self, tenant: impl Into<String>) -> Self {
+        self.tenant = Some(tenant.into());
+        self
+    }
+
+    /// Set the key epoch
+    pub fn with_key_epoch(mut self, key_epoch: u32) -> Self {
+        self.key_epoch = key_epoch;
+        self
+    }
+}
+
+/// Claims of a signed token
+#[cfg(feature = "crypto")]
+#[derive(Serialize, Deserialize)]
+struct TokenClaims {
+    /// Principal name
+    sub: String,
+    /// Tenant
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tenant: Option<String>,
+    /// Key epoch
+    #[serde(default)]
+    kep: u32,
+    /// Expiry (Unix seconds)
+    exp: u64,
+}
+
+/// `[[psk]]` entry of a credentials file
+#[cfg(feature = "crypto")]
+#[derive(Deserialize)]
+struct PskEntry {
+    /// Key ID
+    key_id: String,
+    /// Key (hex)
+    key: String,
+    /// Principal the key authenticates
+    #[serde(flatten)]
+    principal: Principal,
+}
+
+/// `[[token]]` entry of a credentials file
+#[cfg(feature = "crypto")]
+#[derive(Deserialize)]
+struct TokenEntry {
+    /// Static bearer token
+    token: String,
+    /// Principal the token authenticates
+    #[serde(flatten)]
+    principal: Principal,
+}
+
+/// Credentials file (see the [module docs](self))
+#[cfg(feature = "crypto")]
+#[derive(Deserialize)]
+struct CredentialsFile {
+    #[serde(default)]
+    psk: Vec<PskEntry>,
+    #[serde(default)]
+    token: Vec<TokenEntry>,
+}
+
+#[cfg(feature = "crypto")]
+impl CredentialsFile {
+    fn load(path: &std::path::Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)?;
+        toml::from_str(&text).map_err(|e| {
+            M2MError::Config(format!("Invalid credentials file {}: {e}", path.display()))
+        })
+    }
+}
+
+/// Client-side credential attached to outgoing HELLOs
+#[cfg(feature = "crypto")]
+#[derive(Clone)]
+pub enum HelloCredential {
+    /// Pre-shared key and its ID
+    Psk {
+        /// Identifies the key to the responder
+        key_id: String,
+        /// The pre-shared key
+        key: KeyMaterial,
+    },
+    /// Bearer token (static or issued by [`HelloAuthenticator::issue_token`])
+    Token(String),
+}
+
+#[cfg(feature = "crypto")]
+impl HelloCredential {
+    /// Authenticate with a pre-shared key (at least [`MIN_AUTH_KEY_LEN`] bytes)
+    pub fn psk(key_id: impl Into<String>, key: KeyMaterial) -> Result<Self> {
+        check_key_len(&key)?;
+        Ok(HelloCredential::Psk {
+            key_id: key_id.into(),
+            key,
+        })
+    }
+
+    /// Authenticate with a bearer token
+    pub fn token(token: impl Into<String>) -> Self {
+        HelloCredential::Token(token.into())
+    }
+
+    /// Attach this credential to a HELLO
+    ///
+    /// PSK credentials sign the HELLO's capabilities and proposed session
+    /// ID, so attach after the HELLO is otherwise complete.
+    pub fn sign(&self, hello: &mut Message) {
+        let session_id = hello.session_id.clone().unwrap_or_default();
+        hello.auth = Some(self.credential(HELLO_MAC_LABEL, &session_id, &hello_caps(hello)));
+    }
+
+    /// Credential for a request on the established session `session_id`
+    ///
+    /// Servers require it to collect relayed messages for sessions
+    /// established with a credential.
+    pub fn authorize(&self, session_id: &str) -> HelloAuth {
+        self.credential(SESSION_MAC_LABEL, session_id, "")
+    }
+
+    fn credential(&self, label: &[u8], session_id: &str, caps: &str) -> HelloAuth {
+        match self {
+            HelloCredential::Psk { key_id, key } => {
+                let nonce = uuid::Uuid::new_v4().to_string();
+                let issued_at = unix_millis();
+                let tag = credential_mac(label, key, key_id, &nonce, issued_at, session_id, caps)
+                    .finalize();
+                HelloAuth::Psk {
+                    key_id: key_id.clone(),
+                    nonce,
+                    issued_at,
+                    mac: BASE64.encode(tag.into_bytes()),
+                }
+            },
+            HelloCredential::Token(token) => HelloAuth::Token {
+                token: token.clone(),
+            },
+        }
+    }
+}
+
+#[cfg(feature = "crypto")]
+impl std::fmt::Debug for HelloCredential {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HelloCredential::Psk { key_id, .. } => f
+                .debug_struct("Psk")
+                .field("key_id", key_id)
+                .finish_non_exhaustive(),
+            HelloCredential::Token(_) => f.write_str("Token(<redacted>)"),
+        }
+    }
+}
+
+/// Validates HELLO credentials against configured keys and tokens
+///
+/// # Epistemic Properties
+///
+/// - **K_i**: An accepted PSK HELLO was produced by a holder of the key,
+///   within the window, and is not a replay seen by this node
+/// - **B_i**: Bearer tokens were not disclosed in transit
+#[cfg(feature = "crypto")]
+pub struct HelloAuthenticator {
+    /// Pre-shared keys by key ID
+    psks: HashMap<String, (KeyMaterial, Principal)>,
+    /// Static tokens by SHA-256 digest
+    tokens: HashMap<[u8; 32], Principal>,
+    /// Key verifying signed tokens
+    token_key: Option<KeyMaterial>,
+    /// Accept HELLOs without credentials
+    allow_anonymous: bool,
+    /// Freshness window and used PSK nonces
+    replay: ReplayGuard,
+}
+
+#[cfg(feature = "crypto")]
+impl Default for HelloAuthenticator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "crypto")]
+impl HelloAuthenticator {
+    /// Create an authenticator that requires credentials
+    pub fn new() -> Self {
+        Self {
+            psks: HashMap::new(),
+            tokens: HashMap::new(),
+            token_key: None,
+            allow_anonymous: false,
+            replay: ReplayGuard::new().with_window(Duration::from_secs(AUTH_WINDOW_SECS)),
+        }
+    }
+
+    /// Accept a pre-shared key (at least [`MIN_AUTH_KEY_LEN`] bytes)
+    pub fn with_psk(
+        mut self,
+        key_id: impl Into<String>,
+        key: KeyMaterial,
+        principal: Principal,
+    ) -> Result<Self> {
+        check_key_len(&key)?;
+        self.psks.insert(key_id.into(), (key, principal));
+        Ok(self)
+    }
+
+    /// Accept a static bearer token
+    pub fn with_token(mut self, token: &str, principal: Principal) -> Self {
+        self.tokens.insert(Sha256::digest(token).into(), principal);
+        self
+    }
+
+    /// Accept the pre-shared keys listed in a TOML file
+    ///
+    /// See the [module docs](self) for the format.
+    pub fn with_psk_file(mut self, path: impl AsRef<std::path::Path>) -> Result<Self> {
+        for entry in CredentialsFile::load(path.as_ref())?.psk {
+            let key = KeyMaterial::from_hex(&entry.key).map_err(|e| {
+                M2MError::Config(format!("Invalid key for key ID {}: {e}", entry.key_id))
+            })?;
+            self = self.with_psk(entry.key_id, key, entry.principal)?;
+        }
+        Ok(self)
+    }
+
+    /// Accept the static bearer tokens listed in a TOML file
+    ///
+    /// See the [module docs](self) for the format.
+    pub fn with_token_file(mut self, path: impl AsRef<std::path::Path>) -> Result<Self> {
+        for entry in CredentialsFile::load(path.as_ref())?.token {
+            if entry.token.is_empty() {
+                return Err(M2MError::Config(format!(
+                    "Empty token for principal {}",
+                    entry.principal.name
+                )));
+            }
+            self = self.with_token(&entry.token, entry.principal);
+        }
+        Ok(self)
+    }
+
+    /// Accept tokens signed with `key` (see [`issue_token`](Self::issue_token))
+    pub fn with_token_key(mut self, key: KeyMaterial) -> Result<Self> {
+        check_key_len(&key)?;
+        self.token_key = Some(key);
+        Ok(self)
+    }
+
+    /// Accept HELLOs without credentials (credentials present are still checked)
+    pub fn allow_anonymous(mut self) -> Self {
+        self.allow_anonymous = true;
+        self
+    }
+
+    /// Set the PSK freshness window (default: [`AUTH_WINDOW_SECS`])
+    pub fn with_window(mut self, window: Duration) -> Self {
+        self.replay = ReplayGuard::new().with_window(window);
+        self
+    }
+
+    /// Issue a signed token for `principal`, valid for `ttl`
+    pub fn issue_token(&self, principal: &Principal, ttl: Duration) -> Result<String> {
+        let key = self
+            .token_key
+            .as_ref()
+            .ok_or_else(|| M2MError::Config("No token key configured".to_string()))?;
+        let claims = TokenClaims {
+            sub: principal.name.clone(),
+            tenant: principal.tenant.clone(),
+            kep: principal.key_epoch,
+            exp: unix_millis() / 1000 + ttl.as_secs(),
+        };
+        let body = format!(
+            "{TOKEN_PREFIX}.{}",
+            BASE64.encode(serde_json::to_vec(&claims)?)
+        );
+        let tag = token_mac(key, &body).finalize().into_bytes();
+        Ok(format!("{body}.{}", BASE64.encode(tag)))
+    }
+
+    /// Check a HELLO's credential
+    ///
+    /// Returns the authenticated principal, or `None` for an anonymous
+    /// HELLO when anonymous peers are allowed.
+    pub fn authenticate(&self, hello: &Message) -> Result<Option<Principal>> {
+        let Some(ref auth) = hello.auth else {
+            return if self.allow_anonymous {
+                Ok(None)
+            } else {
+                Err(auth_error("HELLO carries no credentials"))
+            };
+        };
+        let session_id = hello.session_id.as_deref().unwrap_or_default();
+        self.verify(auth, HELLO_MAC_LABEL, session_id, &hello_caps(hello))
+            .map(Some)
+    }
+
+    /// Check a credential from [`HelloCredential::authorize`]
+    ///
+    /// Returns the principal it authenticates; callers compare it with the
+    /// principal the session was established by.
+    pub fn authenticate_session(&self, session_id: &str, auth: &HelloAuth) -> Result<Principal> {
+        self.verify(auth, SESSION_MAC_LABEL, session_id, "")
+    }
+
+    fn verify(
+        &self,
+        auth: &HelloAuth,
+        label: &[u8],
+        session_id: &str,
+        caps: &str,
+    ) -> Result<Principal> {
+        match *auth {
+            HelloAuth::Psk {
+                ref key_id,
+                ref nonce,
+                issued_at,
+                ref mac,
+            } => {
+                let (key, principal) = self
+                    .psks
+                    .get(key_id)
+                    .ok_or_else(|| auth_error(&format!("Unknown key ID {key_id}")))?;
+                let tag = BASE64
+                    .decode(mac)
+                    .map_err(|_| auth_error("Malformed MAC"))?;
+                credential_mac(label, key, key_id, nonce, issued_at, session_id, caps)
+                    .verify_slice(&tag)
+                    .map_err(|_| auth_error("MAC does not verify"))?;
+                // Only authentic credentials may occupy the nonce cache
+                self.replay
+                    .check(&EarlyData {
+                        nonce: nonce.clone(),
+                        issued_at,
+                    })
+                    .map_err(|e| auth_error(&e.to_string()))?;
+                Ok(principal.clone())
+            },
+            HelloAuth::Token { ref token } => {
+                let digest: [u8; 32] = Sha256::digest(token).into();
+                if let Some(principal) = self.tokens.get(&digest) {
+                    return Ok(principal.clone());
+                }
+                self.verify_signed_token(token)
+            },
+        }
+    }
+
+    /// Verify a `m2m1.<claims>.<mac>` token
+    fn verify_signed_token(&self, token: &str) -> Result<Principal> {
+        let invalid = || auth_error("Unknown or invalid token");
+        let key = self.token_key.as_ref().ok_or_else(invalid)?;
+        let (body, tag) = token.rsplit_once('.').ok_or_else(invalid)?;
+        let claims = body
+            .strip_prefix(TOKEN_PREFIX)
+            .and_then(|rest| rest.strip_prefix('.'))
+            .ok_or_else(invalid)?;
+
+        let tag = BASE64.decode(tag).map_err(|_| invalid())?;
+        token_mac(key, body)
+            .verify_slice(&tag)
+            .map_err(|_| invalid())?;
+
+        let claims: TokenClaims = BASE64
+            .decode(claims)
+            .ok()
+            .and_then(|json| serde_json::from_slice(&json).ok())
+            .ok_or_else(invalid)?;
+        if claims.exp <= unix_millis() / 1000 {
+            return Err(auth_error("Token expired"));
+        }
+        Ok(Principal {
+            name: claims.sub,
+            tenant: claims.tenant,
+            key_epoch: claims.kep,
+        })
+    }
+}
+
+#[cfg(feature = "crypto")]
+impl std::fmt::Debug for HelloAuthenticator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HelloAuthenticator")
+            .field("psks", &self.psks.len())
+            .field("tokens", &self.tokens.len())
+            .field("signed_tokens", &self.token_key.is_some())
+            .field("allow_anonymous", &self.allow_anonymous)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "crypto")]
+type HmacSha256 = Hmac<Sha256>;
+
+/// A HELLO's capabilities as canonical JSON (empty without capabilities)
+#[cfg(feature = "crypto")]
+fn hello_caps(hello: &Message) -> String {
+    hello
+        .get_capabilities()
+        .and_then(|caps: &Capabilities| serde_json::to_value(caps).ok())
+        .map(|value| canonicalize_value(&value))
+        .unwrap_or_default()
+}
+
+/// MAC over a credential's fields, session ID and canonical capabilities
+#[cfg(feature = "crypto")]
+fn credential_mac(
+    label: &[u8],
+    key: &KeyMaterial,
+    key_id: &str,
+    nonce: &str,
+    issued_at: u64,
+    session_id: &str,
+    caps: &str,
+) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(key.as_bytes()).expect("HMAC accepts any key size");
+    mac.update(label);
+    mac.update(key_id.as_bytes());
+    mac.update(&[0]);
+    mac.update(nonce.as_bytes());
+    mac.update(&[0]);
+    mac.update(&issued_at.to_be_bytes());
+    mac.update(session_id.as_bytes());
+    mac.update(&[0]);
+    mac.update(caps.as_bytes());
+    mac
+}
+
+/// MAC over a signed token's prefix and claims
+#[cfg(feature = "crypto")]
+fn token_mac(key: &KeyMaterial, body: &str) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(key.as_bytes()).expect("HMAC accepts any key size");
+    mac.update(body.as_bytes());
+    mac
+}
+
+#[cfg(feature = "crypto")]
+fn check_key_len(key: &KeyMaterial) -> Result<()> {
+    if key.len() < MIN_AUTH_KEY_LEN {
+        return Err(M2MError::Config(format!(
+            "Authentication key too short: {} bytes (minimum {MIN_AUTH_KEY_LEN})",
+            key.len()
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(feature = "crypto")]
+fn auth_error(reason: &str) -> M2MError {
+    M2MError::Protocol(format!("Authentication failed: {reason}"))
+}
+
+#[cfg(all(test, feature = "crypto"))]
+mod tests {
+    use super::*;
+
+    fn key(byte: u8) -> KeyMaterial {
+        KeyMaterial::new(vec![byte; 32])
+    }
+
+    #[test]
+    fn test_psk_and_tokens() {
+        let auth = HelloAuthenticator::new()
+            .with_psk("k1", key(1), Principal::new("billing").with_tenant("acme"))
+            .unwrap()
+            .with_token("static-secret", Principal::new("ops"))
+            .with_token_key(key(9))
+            .unwrap();
+
+        // PSK: valid once, then a replay
+        let mut hello = Message::hello(Capabilities::default());
+        HelloCredential::psk("k1", key(1)).unwrap().sign(&mut hello);
+        let principal = auth.authenticate(&hello).unwrap().unwrap();
+        assert_eq!(principal.tenant.as_deref(), Some("acme"));
+        assert!(auth.authenticate(&hello).is_err());
+
+        // Wrong key, tampered capabilities
+        let mut hello = Message::hello(Capabilities::default());
+        HelloCredential::psk("k1", key(2)).unwrap().sign(&mut hello);
+        assert!(auth.authenticate(&hello).is_err());
+        let mut hello = Message::hello(Capabilities::default());
+        HelloCredential::psk("k1", key(1)).unwrap().sign(&mut hello);
+        hello.payload = Some(super::super::message::MessagePayload::Capabilities(
+            Capabilities::new("attacker"),
+        ));
+        assert!(auth.authenticate(&hello).is_err());
+
+        // Static and signed tokens
+        let mut hello = Message::hello(Capabilities::default());
+        HelloCredential::token("static-secret").sign(&mut hello);
+        assert_eq!(auth.authenticate(&hello).unwrap().unwrap().name, "ops");
+
+        let agent = Principal::new("agent-7").with_key_epoch(3);
+        let issued = auth.issue_token(&agent, Duration::from_secs(60)).unwrap();
+        HelloCredential::token(issued.clone()).sign(&mut hello);
+        assert_eq!(auth.authenticate(&hello).unwrap().unwrap(), agent);
+
+        let forged = HelloAuthenticator::new()
+            .with_token_key(key(8))
+            .unwrap()
+            .issue_token(&Principal::new("agent-7"), Duration::from_secs(60))
+            .unwrap();
+        HelloCredential::token(forged).sign(&mut hello);
+        assert!(auth.authenticate(&hello).is_err());
+        let expired = auth
+            .issue_token(&Principal::new("agent-7"), Duration::ZERO)
+            .unwrap();
+        HelloCredential::token(expired).sign(&mut hello);
+        assert!(auth.authenticate(&hello).is_err());
+
+        // Missing credentials
+        let anonymous = Message::hello(Capabilities::default());
+        assert!(auth.authenticate(&anonymous).is_err());
+        let lenient = HelloAuthenticator::new().allow_anonymous();
+        assert_eq!(lenient.authenticate(&anonymous).unwrap(), None);
+
+        assert!(HelloCredential::psk("short", KeyMaterial::new(vec![0; 8])).is_err());
+    }
+
+    #[test]
+    fn test_session_authorization() {
+        let auth = HelloAuthenticator::new()
+            .with_psk("k1", key(1), Principal::new("billing"))
+            .unwrap()
+            .with_token("static-secret", Principal::new("ops"));
+        let psk = HelloCredential::psk("k1", key(1)).unwrap();
+
+        let granted = psk.authorize("s-1");
+        assert_eq!(
+            auth.authenticate_session("s-1", &granted).unwrap().name,
+            "billing"
+        );
+        // Single use, bound to the session
+        assert!(auth.authenticate_session("s-1", &granted).is_err());
+        assert!(auth
+            .authenticate_session("s-2", &psk.authorize("s-1"))
+            .is_err());
+
+        // Not accepted as a HELLO credential
+        let mut hello = Message::hello(Capabilities::default());
+        hello.auth = Some(psk.authorize(""));
+        assert!(auth.authenticate(&hello).is_err());
+
+        let token = HelloCredential::token("static-secret").authorize("s-1");
+        assert_eq!(
+            auth.authenticate_session("s-1", &token).unwrap().name,
+            "ops"
+        );
+    }
+
+    #[test]
+    fn test_credentials_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let psk_file = dir.path().join("psk.toml");
+        std::fs::write(
+            &psk_file,
+            format!(
+                "[[psk]]\nkey_id = \"k1\"\nkey = \"{}\"\nname = \"billing\"\ntenant = \"acme\"\nkey_epoch = 2\n",
+                "01".repeat(32)
+            ),
+        )
+        .unwrap();
+        let token_file = dir.path().join("tokens.toml");
+        std::fs::write(
+            &token_file,
+            "[[token]]\ntoken = \"s3cret\"\nname = \"ops\"\n",
+        )
+        .unwrap();
+
+        let auth = HelloAuthenticator::new()
+            .with_psk_file(&psk_file)
+            .unwrap()
+            .with_token_file(&token_file)
+            .unwrap();
+
+        let mut hello = Message::hello(Capabilities::default());
+        HelloCredential::psk("k1", key(1)).unwrap().sign(&mut hello);
+        assert_eq!(
+            auth.authenticate(&hello).unwrap().unwrap(),
+            Principal::new("billing")
+                .with_tenant("acme")
+                .with_key_epoch(2)
+        );
+        let mut hello = Message::hello(Capabilities::default());
+        HelloCredential::token("s3cret").sign(&mut hello);
+        assert_eq!(auth.authenticate(&hello).unwrap().unwrap().name, "ops");
+
+        // Short keys and empty tokens are refused
+        std::fs::write(
+            &psk_file,
+            "[[psk]]\nkey_id = \"k1\"\nkey = \"0102\"\nname = \"x\"\n",
+        )
+        .unwrap();
+        assert!(HelloAuthenticator::new().with_psk_file(&psk_file).is_err());
+        std::fs::write(&token_file, "[[token]]\ntoken = \"\"\nname = \"x\"\n").unwrap();
+        assert!(HelloAuthenticator::new()
+            .with_token_file(&token_file)
+            .is_err());
+    }
+}
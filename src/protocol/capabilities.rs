@@ -4,11 +4,87 @@
 //! to establish what compression algorithms and features both
 //! agents support.
 
+use std::collections::HashMap;
+use std::time::Duration;
+
 use serde::{Deserialize, Serialize};
 
+use super::extensions::{self, Extension, KeepaliveInterval, KeepaliveTimeout};
+use super::flow::FlowWindow;
+use super::version::ProtocolVersion;
+use crate::codec::m2m::SecurityMode;
 use crate::codec::Algorithm;
 use crate::models::Encoding;
 
+/// Limits and features an agent advertises for one algorithm
+///
+/// Unset fields place no limit. Negotiation combines both agents' entries
+/// for the agreed algorithm (see [`CompressionCaps::negotiate_parameters`]).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AlgorithmParams {
+    /// Algorithm the parameters apply to
+    pub algorithm: Algorithm,
+    /// Highest Brotli quality (0-11) to spend on payloads for this agent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_quality: Option<u32>,
+    /// Shared dictionary versions held, in preference order
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dictionaries: Vec<String>,
+    /// Largest DATA content accepted, in bytes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_frame_size: Option<usize>,
+    /// Accepts streamed output
+    #[serde(default = "default_streaming")]
+    pub streaming: bool,
+}
+
+fn default_streaming() -> bool {
+    true
+}
+
+impl AlgorithmParams {
+    /// Parameters placing no limit on `algorithm`
+    pub fn new(algorithm: Algorithm) -> Self {
+        Self {
+            algorithm,
+            max_quality: None,
+            dictionaries: Vec::new(),
+            max_frame_size: None,
+            streaming: true,
+        }
+    }
+
+    /// Cap the Brotli quality (clamped to 11)
+    pub fn with_max_quality(mut self, quality: u32) -> Self {
+        self.max_quality = Some(quality.min(11));
+        self
+    }
+
+    /// Advertise shared dictionary versions, in preference order
+    pub fn with_dictionaries(mut self, versions: Vec<String>) -> Self {
+        self.dictionaries = versions;
+        self
+    }
+
+    /// Cap the size of a single DATA frame
+    pub fn with_max_frame_size(mut self, bytes: usize) -> Self {
+        self.max_frame_size = Some(bytes);
+        self
+    }
+
+    /// Set streaming support
+    pub fn with_streaming(mut self, streaming: bool) -> Self {
+        self.streaming = streaming;
+        self
+    }
+}
+
+impl Default for AlgorithmParams {
+    fn default() -> Self {
+        Self::new(Algorithm::default())
+    }
+}
+
 /// Compression-related capabilities
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CompressionCaps {
@@ -26,6 +102,9 @@ pub struct CompressionCaps {
     /// Preferred tokenizer encoding
     #[serde(default)]
     pub preferred_encoding: Encoding,
+    /// Per-algorithm parameters (algorithms without an entry are unlimited)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub parameters: Vec<AlgorithmParams>,
 }
 
 impl Default for CompressionCaps {
@@ -34,17 +113,14 @@ impl Default for CompressionCaps {
             // M2M is first preference (100% JSON fidelity with routing headers)
             // TokenNative is second (good for small-medium JSON)
             // Brotli is third (best for large content)
-            algorithms: vec![
-                Algorithm::M2M,
-                Algorithm::TokenNative,
-                Algorithm::Brotli,
-                Algorithm::None,
-            ],
+            // Only algorithms compiled into this build are advertised
+            algorithms: Algorithm::available(),
             max_payload: 0, // unlimited
             streaming: true,
             ml_routing: false,
             encodings: vec![Encoding::Cl100kBase, Encoding::O200kBase],
             preferred_encoding: Encoding::Cl100kBase,
+            parameters: Vec::new(),
         }
     }
 }
@@ -74,6 +150,47 @@ impl CompressionCaps {
         self
     }
 
+    /// Set parameters for an algorithm, replacing any earlier entry
+    pub fn with_parameters(mut self, params: AlgorithmParams) -> Self {
+        self.parameters.retain(|p| p.algorithm != params.algorithm);
+        self.parameters.push(params);
+        self
+    }
+
+    /// Parameters advertised for `algorithm`
+    pub fn parameters_for(&self, algorithm: Algorithm) -> Option<&AlgorithmParams> {
+        self.parameters.iter().find(|p| p.algorithm == algorithm)
+    }
+
+    /// Parameters both agents honor for `algorithm`
+    ///
+    /// Takes the lower quality and frame size limits, streaming only if
+    /// both stream, and the dictionaries both hold in our preference
+    /// order. A missing entry places no limit; dictionaries are only
+    /// agreed when both agents list them.
+    pub fn negotiate_parameters(
+        &self,
+        other: &CompressionCaps,
+        algorithm: Algorithm,
+    ) -> AlgorithmParams {
+        let unlimited = AlgorithmParams::new(algorithm);
+        let ours = self.parameters_for(algorithm).unwrap_or(&unlimited);
+        let theirs = other.parameters_for(algorithm).unwrap_or(&unlimited);
+
+        AlgorithmParams {
+            algorithm,
+            max_quality: lower(ours.max_quality, theirs.max_quality),
+            dictionaries: ours
+                .dictionaries
+                .iter()
+                .filter(|version| theirs.dictionaries.contains(version))
+                .cloned()
+                .collect(),
+            max_frame_size: lower(ours.max_frame_size, theirs.max_frame_size),
+            streaming: self.streaming && other.streaming && ours.streaming && theirs.streaming,
+        }
+    }
+
     /// Check if algorithm is supported
     pub fn supports(&self, algorithm: Algorithm) -> bool {
         self.algorithms.contains(&algorithm)
@@ -86,9 +203,10 @@ impl CompressionCaps {
 
     /// Get best mutually supported algorithm
     pub fn negotiate(&self, other: &CompressionCaps) -> Option<Algorithm> {
-        // Find first algorithm supported by both (preference order is ours)
+        // Find first algorithm supported by both (preference order is ours);
+        // algorithms this build lacks are never agreed on
         for algo in &self.algorithms {
-            if other.supports(*algo) {
+            if algo.is_available() && other.supports(*algo) {
                 return Some(*algo);
             }
         }
@@ -112,6 +230,82 @@ impl CompressionCaps {
     }
 }
 
+/// The lower of two optional limits, where `None` places no limit
+fn lower<T: Ord>(a: Option<T>, b: Option<T>) -> Option<T> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+/// Key-exchange suite for cross-organization session keys
+///
+/// Preference order is the order in [`SecurityCaps::key_exchange`]. Every
+/// agent supports X25519, so negotiation never fails.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum KeyExchangeSuite {
+    /// X25519 Diffie-Hellman (key share version 1)
+    #[default]
+    #[serde(rename = "X25519")]
+    X25519,
+    /// X25519 + ML-KEM-768 hybrid (key share version 2, `pqc` feature)
+    ///
+    /// The session secret stays safe as long as either half is unbroken,
+    /// protecting recorded traffic against future quantum attacks.
+    #[serde(rename = "X25519MLKEM768")]
+    X25519MlKem768,
+}
+
+impl KeyExchangeSuite {
+    /// Key-share payload version for this suite
+    pub fn version(self) -> u8 {
+        match self {
+            KeyExchangeSuite::X25519 => 1,
+            KeyExchangeSuite::X25519MlKem768 => 2,
+        }
+    }
+
+    /// Check if this build can perform the exchange
+    pub fn is_supported(self) -> bool {
+        match self {
+            KeyExchangeSuite::X25519 => true,
+            KeyExchangeSuite::X25519MlKem768 => cfg!(feature = "pqc"),
+        }
+    }
+
+    /// Suites supported by this build, in preference order
+    pub fn supported() -> Vec<Self> {
+        [KeyExchangeSuite::X25519MlKem768, KeyExchangeSuite::X25519]
+            .into_iter()
+            .filter(|suite| suite.is_supported())
+            .collect()
+    }
+
+    /// Get human-readable name
+    pub fn name(self) -> &'static str {
+        match self {
+            KeyExchangeSuite::X25519 => "X25519",
+            KeyExchangeSuite::X25519MlKem768 => "X25519MLKEM768",
+        }
+    }
+}
+
+impl std::fmt::Display for KeyExchangeSuite {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// Peers that predate key-exchange negotiation only speak X25519
+fn legacy_key_exchange() -> Vec<KeyExchangeSuite> {
+    vec![KeyExchangeSuite::X25519]
+}
+
+/// Peers that predate security-mode negotiation send unprotected frames
+fn legacy_security_modes() -> Vec<SecurityMode> {
+    vec![SecurityMode::None]
+}
+
 /// Security-related capabilities
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SecurityCaps {
@@ -123,6 +317,15 @@ pub struct SecurityCaps {
     pub blocking_mode: bool,
     /// Minimum confidence threshold for blocking (0.0 - 1.0)
     pub block_threshold: f32,
+    /// Supported key-exchange suites in preference order
+    #[serde(default = "legacy_key_exchange")]
+    pub key_exchange: Vec<KeyExchangeSuite>,
+    /// Supported frame security modes in preference order
+    #[serde(default = "legacy_security_modes")]
+    pub security_modes: Vec<SecurityMode>,
+    /// Longest lifetime accepted for session keys, in seconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_key_lifetime_secs: Option<u64>,
 }
 
 impl Default for SecurityCaps {
@@ -132,6 +335,13 @@ impl Default for SecurityCaps {
             model_version: None,
             blocking_mode: false,
             block_threshold: 0.8,
+            key_exchange: KeyExchangeSuite::supported(),
+            security_modes: if cfg!(feature = "crypto") {
+                vec![SecurityMode::Aead, SecurityMode::Hmac, SecurityMode::None]
+            } else {
+                legacy_security_modes()
+            },
+            max_key_lifetime_secs: None,
         }
     }
 }
@@ -150,6 +360,78 @@ impl SecurityCaps {
         self.block_threshold = threshold.clamp(0.0, 1.0);
         self
     }
+
+    /// Confidence at which threats are blocked in the session
+    ///
+    /// The lower threshold of the agents in blocking mode, so either agent
+    /// can make blocking stricter. `None` if neither blocks.
+    pub fn negotiate_block_threshold(&self, other: &SecurityCaps) -> Option<f32> {
+        [self, other]
+            .into_iter()
+            .filter(|caps| caps.blocking_mode)
+            .map(|caps| caps.block_threshold)
+            .reduce(f32::min)
+    }
+
+    /// Create with specific key-exchange suites
+    pub fn with_key_exchange(mut self, suites: Vec<KeyExchangeSuite>) -> Self {
+        self.key_exchange = suites;
+        self
+    }
+
+    /// Create with specific frame security modes
+    ///
+    /// [`SecurityMode::FieldAead`] is never offered by default; list it
+    /// first when intermediaries must see the payload skeleton. It is only
+    /// negotiated at protocol 3.1 or later (see [`ProtocolVersion`]).
+    pub fn with_security_modes(mut self, modes: Vec<SecurityMode>) -> Self {
+        self.security_modes = modes;
+        self
+    }
+
+    /// Limit session keys to `lifetime`, after which they must be rekeyed
+    pub fn with_max_key_lifetime(mut self, lifetime: Duration) -> Self {
+        self.max_key_lifetime_secs = Some(lifetime.as_secs().max(1));
+        self
+    }
+
+    /// Lifetime of session keys, in seconds
+    ///
+    /// The shorter limit of the two agents, so either can make keys
+    /// expire sooner. `None` if neither sets one.
+    pub fn negotiate_key_lifetime(&self, other: &SecurityCaps) -> Option<u64> {
+        [self.max_key_lifetime_secs, other.max_key_lifetime_secs]
+            .into_iter()
+            .flatten()
+            .min()
+    }
+
+    /// Get best mutually supported security mode (falls back to `None`)
+    pub fn negotiate_security_mode(&self, other: &SecurityCaps) -> SecurityMode {
+        self.negotiate_security_mode_at(other, ProtocolVersion::LATEST)
+    }
+
+    /// Get best mutually supported security mode available at `version`
+    pub fn negotiate_security_mode_at(
+        &self,
+        other: &SecurityCaps,
+        version: ProtocolVersion,
+    ) -> SecurityMode {
+        self.security_modes
+            .iter()
+            .copied()
+            .find(|mode| version.supports(*mode) && other.security_modes.contains(mode))
+            .unwrap_or_default()
+    }
+
+    /// Get best mutually supported key-exchange suite (falls back to X25519)
+    pub fn negotiate_key_exchange(&self, other: &SecurityCaps) -> KeyExchangeSuite {
+        self.key_exchange
+            .iter()
+            .copied()
+            .find(|suite| suite.is_supported() && other.key_exchange.contains(suite))
+            .unwrap_or_default()
+    }
 }
 
 /// Full agent capabilities
@@ -165,9 +447,27 @@ pub struct Capabilities {
     pub compression: CompressionCaps,
     /// Security capabilities
     pub security: SecurityCaps,
+    /// Agent key epoch (0 = original key)
+    #[serde(default)]
+    pub key_epoch: u32,
     /// Custom extensions (key-value pairs)
     #[serde(default)]
-    pub extensions: std::collections::HashMap<String, String>,
+    pub extensions: HashMap<String, String>,
+    /// Receive window granted to the peer (`None` = unlimited)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub receive_window: Option<FlowWindow>,
+    /// Fall back to uncompressed DATA when no algorithm is shared
+    ///
+    /// Takes effect only if both agents set it; see
+    /// [`negotiate`](Self::negotiate).
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub allow_passthrough: bool,
+    /// FEC parity shards per 100 data shards (`None` = no FEC)
+    ///
+    /// DATA frames carry Reed-Solomon parity only if both agents set it;
+    /// see [`negotiate`](Self::negotiate).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fec_parity: Option<u8>,
 }
 
 impl Default for Capabilities {
@@ -178,7 +478,11 @@ impl Default for Capabilities {
             agent_type: "m2m-rust".to_string(),
             compression: CompressionCaps::default(),
             security: SecurityCaps::default(),
-            extensions: std::collections::HashMap::new(),
+            key_epoch: 0,
+            extensions: HashMap::new(),
+            receive_window: None,
+            allow_passthrough: false,
+            fec_parity: None,
         }
     }
 }
@@ -204,48 +508,143 @@ impl Capabilities {
         self
     }
 
+    /// Set agent identifier
+    pub fn with_agent_id(mut self, agent_id: &str) -> Self {
+        self.agent_id = agent_id.to_string();
+        self
+    }
+
+    /// Set agent key epoch
+    pub fn with_key_epoch(mut self, epoch: u32) -> Self {
+        self.key_epoch = epoch;
+        self
+    }
+
+    /// Advertise a receive window (see [`FlowWindow`])
+    pub fn with_receive_window(mut self, window: FlowWindow) -> Self {
+        self.receive_window = Some(window);
+        self
+    }
+
+    /// Allow passthrough when no algorithm is shared (see
+    /// [`allow_passthrough`](Self::allow_passthrough))
+    pub fn with_passthrough(mut self) -> Self {
+        self.allow_passthrough = true;
+        self
+    }
+
+    /// Protect DATA frames with `parity_percent` parity shards per 100
+    /// data shards (see [`fec`](crate::codec::m2m::fec))
+    #[cfg(feature = "fec")]
+    pub fn with_fec(mut self, parity_percent: u8) -> Self {
+        self.fec_parity = Some(parity_percent.max(1));
+        self
+    }
+
     /// Add extension
     pub fn with_extension(mut self, key: &str, value: &str) -> Self {
         self.extensions.insert(key.to_string(), value.to_string());
         self
     }
 
-    /// Check version compatibility
+    /// Add typed extension
+    pub fn with_typed_extension<E: Extension>(mut self, value: E) -> Self {
+        self.extensions
+            .insert(E::KEY.to_string(), extensions::encode(&value));
+        self
+    }
+
+    /// Get typed extension (`None` if absent or malformed)
+    pub fn extension<E: Extension>(&self) -> Option<E> {
+        self.extensions
+            .get(E::KEY)
+            .and_then(|raw| extensions::decode(raw))
+    }
+
+    /// Advertise a protocol version other than [`PROTOCOL_VERSION`](super::PROTOCOL_VERSION)
+    pub fn with_version(mut self, version: ProtocolVersion) -> Self {
+        self.version = version.to_string();
+        self
+    }
+
+    /// Parsed protocol version (`None` if malformed)
+    pub fn protocol_version(&self) -> Option<ProtocolVersion> {
+        self.version.parse().ok()
+    }
+
+    /// Highest protocol version both agents speak
+    ///
+    /// `None` if the major versions differ or either version is malformed.
+    pub fn negotiate_version(&self, other: &Capabilities) -> Option<ProtocolVersion> {
+        self.protocol_version()?
+            .negotiate(other.protocol_version()?)
+    }
+
+    /// Check version compatibility (major versions match)
     pub fn is_compatible(&self, other: &Capabilities) -> bool {
-        // Major version must match
-        let self_major = self.version.split('.').next().unwrap_or("0");
-        let other_major = other.version.split('.').next().unwrap_or("0");
-        self_major == other_major
+        self.negotiate_version(other).is_some()
     }
 
     /// Negotiate capabilities with peer
+    ///
+    /// Fails if the major versions differ, or without a common compression
+    /// algorithm unless both agents allow passthrough: the session is then
+    /// established with [`Algorithm::None`] for all DATA, keeping the rest
+    /// of the agreement (security mode, key exchange, extensions) intact.
+    /// The session runs at the highest common minor version, which bounds
+    /// the security modes on offer. FEC is used if both agents advertise a
+    /// parity ratio, at the higher of the two. The agreed algorithm's
+    /// [`AlgorithmParams`] are combined as in
+    /// [`CompressionCaps::negotiate_parameters`].
     pub fn negotiate(&self, peer: &Capabilities) -> Option<NegotiatedCaps> {
-        if !self.is_compatible(peer) {
-            return None;
-        }
+        let version = self.negotiate_version(peer)?;
 
-        let algorithm = self.compression.negotiate(&peer.compression)?;
+        let (algorithm, passthrough) = match self.compression.negotiate(&peer.compression) {
+            Some(algorithm) => (algorithm, false),
+            None if self.allow_passthrough && peer.allow_passthrough => (Algorithm::None, true),
+            None => return None,
+        };
         let encoding = self.compression.negotiate_encoding(&peer.compression);
+        let parameters = self
+            .compression
+            .negotiate_parameters(&peer.compression, algorithm);
 
         Some(NegotiatedCaps {
+            version,
             algorithm,
+            passthrough,
             encoding,
-            streaming: self.compression.streaming && peer.compression.streaming,
+            streaming: parameters.streaming,
             ml_routing: self.compression.ml_routing && peer.compression.ml_routing,
             threat_detection: self.security.threat_detection || peer.security.threat_detection,
             blocking_mode: self.security.blocking_mode || peer.security.blocking_mode,
+            block_threshold: self.security.negotiate_block_threshold(&peer.security),
+            key_lifetime_secs: self.security.negotiate_key_lifetime(&peer.security),
+            key_exchange: self.security.negotiate_key_exchange(&peer.security),
+            security_mode: self
+                .security
+                .negotiate_security_mode_at(&peer.security, version),
+            extensions: HashMap::new(),
+            fec_parity: self.fec_parity.zip(peer.fec_parity).map(|(a, b)| a.max(b)),
+            parameters,
         })
     }
 }
 
 /// Result of capability negotiation
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NegotiatedCaps {
+    /// Agreed protocol version (highest common minor)
+    #[serde(default)]
+    pub version: ProtocolVersion,
     /// Agreed compression algorithm
     pub algorithm: Algorithm,
+    /// No algorithm was shared; DATA is sent uncompressed
+    #[serde(default)]
+    pub passthrough: bool,
     /// Agreed tokenizer encoding (for TokenNative)
     pub encoding: Encoding,
-    /// Both support streaming
+    /// Both support streaming with the agreed algorithm
     pub streaming: bool,
     /// Both have ML routing
     pub ml_routing: bool,
@@ -253,6 +652,60 @@ pub struct NegotiatedCaps {
     pub threat_detection: bool,
     /// Either has blocking mode
     pub blocking_mode: bool,
+    /// Confidence at which inbound threats are blocked (`None` = flag only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block_threshold: Option<f32>,
+    /// Lifetime of session keys in seconds (`None` = unlimited)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key_lifetime_secs: Option<u64>,
+    /// Agreed key-exchange suite
+    #[serde(default)]
+    pub key_exchange: KeyExchangeSuite,
+    /// Agreed frame security mode
+    #[serde(default)]
+    pub security_mode: SecurityMode,
+    /// Agreed extension values (wire encoding)
+    #[serde(default)]
+    pub extensions: HashMap<String, String>,
+    /// Agreed FEC parity shards per 100 data shards (`None` = no FEC)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fec_parity: Option<u8>,
+    /// Parameters both agents honor for the agreed algorithm
+    #[serde(default)]
+    pub parameters: AlgorithmParams,
+}
+
+impl NegotiatedCaps {
+    /// Get agreed typed extension
+    pub fn extension<E: Extension>(&self) -> Option<E> {
+        self.extensions
+            .get(E::KEY)
+            .and_then(|raw| extensions::decode(raw))
+    }
+
+    /// Agreed idle time before sending a PING
+    ///
+    /// The negotiated [`KeepaliveInterval`], otherwise
+    /// [`KEEPALIVE_INTERVAL_SECS`](super::KEEPALIVE_INTERVAL_SECS).
+    pub fn keepalive_interval(&self) -> Duration {
+        let secs = self
+            .extension::<KeepaliveInterval>()
+            .map_or(super::KEEPALIVE_INTERVAL_SECS, |KeepaliveInterval(secs)| {
+                secs
+            });
+        Duration::from_secs(secs)
+    }
+
+    /// Agreed time to wait for a PONG
+    ///
+    /// The negotiated [`KeepaliveTimeout`], otherwise
+    /// [`KEEPALIVE_TIMEOUT_SECS`](super::KEEPALIVE_TIMEOUT_SECS).
+    pub fn keepalive_timeout(&self) -> Duration {
+        let secs = self
+            .extension::<KeepaliveTimeout>()
+            .map_or(super::KEEPALIVE_TIMEOUT_SECS, |KeepaliveTimeout(secs)| secs);
+        Duration::from_secs(secs)
+    }
 }
 
 #[cfg(test)]
@@ -260,6 +713,7 @@ mod tests {
     use super::*;
 
     #[test]
+    #[cfg(feature = "brotli")]
     fn test_algorithm_negotiation() {
         let caps1 = CompressionCaps::default();
         let caps2 = CompressionCaps {
@@ -285,6 +739,36 @@ mod tests {
         assert_eq!(caps1.negotiate(&caps2), None);
     }
 
+    #[test]
+    fn test_passthrough_negotiation() {
+        let token_only = Capabilities::default().with_compression(CompressionCaps {
+            algorithms: vec![Algorithm::TokenNative],
+            ..Default::default()
+        });
+        let brotli_only = Capabilities::default().with_compression(CompressionCaps {
+            algorithms: vec![Algorithm::Brotli],
+            ..Default::default()
+        });
+
+        // Both agents must opt in
+        assert!(token_only.negotiate(&brotli_only).is_none());
+        let token_only = token_only.with_passthrough();
+        assert!(token_only.negotiate(&brotli_only).is_none());
+
+        let brotli_only = brotli_only.with_passthrough();
+        let negotiated = token_only.negotiate(&brotli_only).unwrap();
+        assert_eq!(negotiated.algorithm, Algorithm::None);
+        assert!(negotiated.passthrough);
+
+        // A shared algorithm still wins
+        let negotiated = Capabilities::default()
+            .with_passthrough()
+            .negotiate(&Capabilities::default().with_passthrough())
+            .unwrap();
+        assert_eq!(negotiated.algorithm, Algorithm::M2M);
+        assert!(!negotiated.passthrough);
+    }
+
     #[test]
     fn test_version_compatibility() {
         let caps1 = Capabilities::default();
@@ -299,6 +783,33 @@ mod tests {
         assert!(!caps1.is_compatible(&caps2)); // Major version diff NOT OK
     }
 
+    #[test]
+    fn test_minor_version_negotiation() {
+        let fields = SecurityCaps::default()
+            .with_security_modes(vec![SecurityMode::FieldAead, SecurityMode::Hmac]);
+        let v3_0 = Capabilities::default().with_security(fields.clone());
+        let v3_1 = Capabilities::default()
+            .with_version(ProtocolVersion::V3_1)
+            .with_security(fields);
+
+        // A 3.0 peer keeps the session at 3.0, without field encryption
+        for (a, b) in [(&v3_0, &v3_1), (&v3_1, &v3_0)] {
+            let negotiated = a.negotiate(b).unwrap();
+            assert_eq!(negotiated.version, ProtocolVersion::V3_0);
+            assert_eq!(negotiated.security_mode, SecurityMode::Hmac);
+        }
+
+        let negotiated = v3_1.negotiate(&v3_1).unwrap();
+        assert_eq!(negotiated.version, ProtocolVersion::V3_1);
+        assert_eq!(negotiated.security_mode, SecurityMode::FieldAead);
+
+        let malformed = Capabilities {
+            version: "three".to_string(),
+            ..Capabilities::default()
+        };
+        assert!(v3_1.negotiate(&malformed).is_none());
+    }
+
     #[test]
     fn test_full_negotiation() {
         let caps1 = Capabilities::default()
@@ -310,5 +821,115 @@ mod tests {
         assert_eq!(negotiated.algorithm, Algorithm::M2M); // New default
         assert_eq!(negotiated.encoding, Encoding::Cl100kBase);
         assert!(negotiated.threat_detection); // One has it
+        assert_eq!(negotiated.block_threshold, None);
+
+        // The stricter blocking agent sets the threshold
+        let strict =
+            Capabilities::default().with_security(SecurityCaps::default().with_blocking(0.6));
+        let lenient =
+            Capabilities::default().with_security(SecurityCaps::default().with_blocking(0.9));
+        assert_eq!(
+            strict.negotiate(&lenient).unwrap().block_threshold,
+            Some(0.6)
+        );
+        assert_eq!(
+            caps2.negotiate(&lenient).unwrap().block_threshold,
+            Some(0.9)
+        );
+    }
+
+    #[test]
+    fn test_algorithm_parameter_negotiation() {
+        let ours = CompressionCaps::default().with_parameters(
+            AlgorithmParams::new(Algorithm::Brotli)
+                .with_max_quality(9)
+                .with_dictionaries(vec!["chat:1".into(), "tools:2".into()]),
+        );
+        let theirs = CompressionCaps::default().with_parameters(
+            AlgorithmParams::new(Algorithm::Brotli)
+                .with_max_quality(5)
+                .with_dictionaries(vec!["tools:2".into(), "chat:1".into()])
+                .with_max_frame_size(4096)
+                .with_streaming(false),
+        );
+
+        let agreed = ours.negotiate_parameters(&theirs, Algorithm::Brotli);
+        assert_eq!(agreed.max_quality, Some(5));
+        assert_eq!(agreed.max_frame_size, Some(4096));
+        assert_eq!(agreed.dictionaries, vec!["chat:1", "tools:2"]);
+        assert!(!agreed.streaming);
+
+        // Unlisted algorithms, and peers that predate parameters, place no
+        // limits
+        let legacy: CompressionCaps = serde_json::from_str(
+            r#"{"algorithms":["brotli"],"encodings":["Cl100kBase"],"streaming":true,"ml_routing":false,"max_payload":1048576}"#,
+        )
+        .unwrap();
+        assert!(legacy.parameters.is_empty());
+        let agreed = ours.negotiate_parameters(&legacy, Algorithm::Brotli);
+        assert_eq!(agreed.max_quality, Some(9));
+        assert!(agreed.dictionaries.is_empty());
+        assert!(agreed.streaming);
+        assert_eq!(
+            ours.negotiate_parameters(&theirs, Algorithm::M2M),
+            AlgorithmParams::new(Algorithm::M2M)
+        );
+    }
+
+    #[test]
+    fn test_key_exchange_negotiation() {
+        let hybrid = SecurityCaps::default().with_key_exchange(vec![
+            KeyExchangeSuite::X25519MlKem768,
+            KeyExchangeSuite::X25519,
+        ]);
+
+        // Peers that predate the field only speak X25519
+        let legacy: SecurityCaps = serde_json::from_str(
+            r#"{"threat_detection":false,"model_version":null,"blocking_mode":false,"block_threshold":0.8}"#,
+        )
+        .unwrap();
+        assert_eq!(legacy.key_exchange, vec![KeyExchangeSuite::X25519]);
+        assert_eq!(
+            hybrid.negotiate_key_exchange(&legacy),
+            KeyExchangeSuite::X25519
+        );
+
+        let expected = if cfg!(feature = "pqc") {
+            KeyExchangeSuite::X25519MlKem768
+        } else {
+            KeyExchangeSuite::X25519
+        };
+        assert_eq!(hybrid.negotiate_key_exchange(&hybrid), expected);
+    }
+
+    #[test]
+    fn test_security_mode_negotiation() {
+        let aead = SecurityCaps::default()
+            .with_security_modes(vec![SecurityMode::Aead, SecurityMode::None]);
+        let hmac = SecurityCaps::default()
+            .with_security_modes(vec![SecurityMode::Hmac, SecurityMode::None]);
+        assert_eq!(aead.negotiate_security_mode(&aead), SecurityMode::Aead);
+        assert_eq!(aead.negotiate_security_mode(&hmac), SecurityMode::None);
+
+        // Field encryption is opt-in: both peers must list it
+        let fields = SecurityCaps::default()
+            .with_security_modes(vec![SecurityMode::FieldAead, SecurityMode::Aead]);
+        assert_eq!(
+            fields.negotiate_security_mode(&fields),
+            SecurityMode::FieldAead
+        );
+        assert_eq!(fields.negotiate_security_mode(&aead), SecurityMode::Aead);
+        assert_eq!(
+            serde_json::to_string(&SecurityMode::FieldAead).unwrap(),
+            r#""field_aead""#
+        );
+
+        // Peers that predate the field only send unprotected frames
+        let legacy: SecurityCaps = serde_json::from_str(
+            r#"{"threat_detection":false,"model_version":null,"blocking_mode":false,"block_threshold":0.8}"#,
+        )
+        .unwrap();
+        assert_eq!(legacy.security_modes, vec![SecurityMode::None]);
+        assert_eq!(aead.negotiate_security_mode(&legacy), SecurityMode::None);
     }
 }
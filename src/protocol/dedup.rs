@@ -0,0 +1,82 @@
+//! Recently received message IDs for deduplicating retried DATA.
+//!
+//! Every DATA carries a random `message_id`; senders that retry at the
+//! application level (re-compressing the payload, so the ID changes) also
+//! attach an `idempotency_key` that stays the same across attempts. A
+//! session remembers both for the last [`DEFAULT_DEDUP_CAPACITY`] frames,
+//! evicting the least recently received, so a receiver can drop a
+//! retried frame with [`Session::was_seen`](super::Session::was_seen).
+
+use std::collections::{BTreeMap, HashMap};
+
+/// Default number of IDs a session remembers
+pub const DEFAULT_DEDUP_CAPACITY: usize = 1024;
+
+/// Bounded LRU set of message IDs and idempotency keys
+#[derive(Debug, Clone)]
+pub(super) struct RecentIds {
+    /// Last receive tick by ID
+    ids: HashMap<String, u64>,
+    /// IDs by last receive tick, oldest first
+    order: BTreeMap<u64, String>,
+    /// Monotonic receive counter
+    tick: u64,
+    /// Maximum IDs remembered
+    capacity: usize,
+}
+
+impl RecentIds {
+    pub(super) fn new(capacity: usize) -> Self {
+        Self {
+            ids: HashMap::new(),
+            order: BTreeMap::new(),
+            tick: 0,
+            capacity: capacity.max(1),
+        }
+    }
+
+    pub(super) fn contains(&self, id: &str) -> bool {
+        self.ids.contains_key(id)
+    }
+
+    /// Remember `id` as the most recently received
+    pub(super) fn insert(&mut self, id: &str) {
+        self.tick += 1;
+        if let Some(previous) = self.ids.insert(id.to_string(), self.tick) {
+            self.order.remove(&previous);
+        } else if self.ids.len() > self.capacity {
+            if let Some((_, oldest)) = self.order.pop_first() {
+                self.ids.remove(&oldest);
+            }
+        }
+        self.order.insert(self.tick, id.to_string());
+    }
+
+    /// Forget `id`
+    pub(super) fn remove(&mut self, id: &str) {
+        if let Some(tick) = self.ids.remove(id) {
+            self.order.remove(&tick);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recent_ids_evict_least_recent() {
+        let mut recent = RecentIds::new(2);
+        recent.insert("a");
+        recent.insert("b");
+        recent.insert("a");
+        recent.insert("c");
+        assert!(recent.contains("a") && recent.contains("c"));
+        assert!(!recent.contains("b"));
+        assert_eq!(recent.order.len(), 2);
+
+        recent.remove("a");
+        assert!(!recent.contains("a") && recent.contains("c"));
+        assert_eq!(recent.order.len(), 1);
+    }
+}
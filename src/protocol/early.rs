@@ -0,0 +1,216 @@
+//! 0-RTT early data for returning agents.
+//!
+//! A QUIC client holding a session ticket from a previous connection can
+//! send application data in its first flight. An M2M agent uses this to
+//! send HELLO and its first DATA before the transport handshake completes,
+//! finishing handshake + first exchange in a single round trip.
+//!
+//! # Anti-Replay Constraints
+//!
+//! 0-RTT data is not protected against replay: an attacker who captured the
+//! first flight can resend it. The protocol therefore enforces:
+//!
+//! | Constraint | Enforcement |
+//! |------------|-------------|
+//! | Early HELLO must carry an [`EarlyData`] token | Otherwise `425 Too Early` / REJECT |
+//! | Token is fresh (issued within [`EARLY_DATA_WINDOW_SECS`]) | [`ReplayGuard::check`] |
+//! | Token nonce is single-use within the window | [`ReplayGuard::check`] |
+//! | Early DATA only follows an accepted early HELLO on the same stream | Caller processes frames in order |
+//! | Early DATA over HTTP/3 carries the token of the HELLO that established its session, is fresh and not relayed | [`Session::accepts_early_data`], else `425 Too Early` |
+//! | No other message types in early data | `425 Too Early` over HTTP/3 |
+//!
+//! The replay guard is per-process: a fleet of servers behind one name
+//! must share ticket keys *and* a replay cache, or disable 0-RTT. Early
+//! DATA must be idempotent for the application; anything with side effects
+//! belongs after ACCEPT.
+//!
+//! # Flow
+//!
+//! ```text
+//! Client (resumed ticket)             Server
+//!    |== HELLO(session_id, token) ====>|  0-RTT
+//!    |== DATA(session_id) ============>|  0-RTT, same stream
+//!    |<------------ ACCEPT ------------|  token checked, proposed ID adopted
+//!    |<------------ DATA --------------|
+//! ```
+//!
+//! If the server rejects early data, the ACCEPT carries a different session
+//! ID (or a REJECT arrives) and [`Session::early_data_accepted`] reports
+//! `false`; the client resends its DATA on the established session.
+//!
+//! Over HTTP/3 the HELLO and DATA are separate requests that may be
+//! handled in either order. DATA that arrives before its session exists
+//! gets `425 Too Early` and is resent after the handshake.
+//!
+//! [`Session::early_data_accepted`]: super::Session::early_data_accepted
+//! [`Session::accepts_early_data`]: super::Session::accepts_early_data
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{M2MError, Result};
+
+/// Freshness window for early data tokens (seconds)
+pub const EARLY_DATA_WINDOW_SECS: u64 = 10;
+
+/// Default maximum number of nonces tracked by a [`ReplayGuard`]
+pub const DEFAULT_REPLAY_CACHE_SIZE: usize = 65_536;
+
+/// Anti-replay token carried by a HELLO sent in 0-RTT data
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EarlyData {
+    /// Single-use random nonce
+    pub nonce: String,
+    /// Issue time (Unix millis)
+    pub issued_at: u64,
+}
+
+impl Default for EarlyData {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EarlyData {
+    /// Create a fresh token
+    pub fn new() -> Self {
+        Self {
+            nonce: uuid::Uuid::new_v4().to_string(),
+            issued_at: unix_millis(),
+        }
+    }
+}
+
+/// Rejects stale or repeated early data tokens
+///
+/// # Epistemic Properties
+///
+/// - **K_i**: A nonce is accepted at most once per window on this node
+/// - **B_i**: Clock skew between agents is below the window
+pub struct ReplayGuard {
+    /// Token freshness window
+    window: Duration,
+    /// Maximum nonces tracked
+    max_entries: usize,
+    /// Seen nonces and their issue time (Unix millis)
+    seen: Mutex<HashMap<String, u64>>,
+}
+
+impl Default for ReplayGuard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ReplayGuard {
+    /// Create a guard with the default window
+    pub fn new() -> Self {
+        Self {
+            window: Duration::from_secs(EARLY_DATA_WINDOW_SECS),
+            max_entries: DEFAULT_REPLAY_CACHE_SIZE,
+            seen: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Set freshness window
+    pub fn with_window(mut self, window: Duration) -> Self {
+        self.window = window;
+        self
+    }
+
+    /// Set maximum nonces tracked (further tokens are refused until old ones age out)
+    pub fn with_max_entries(mut self, max: usize) -> Self {
+        self.max_entries = max;
+        self
+    }
+
+    /// Accept a token once, if fresh
+    pub fn check(&self, early: &EarlyData) -> Result<()> {
+        if !self.is_fresh(early) {
+            return Err(M2MError::Protocol(
+                "Early data token outside freshness window".to_string(),
+            ));
+        }
+        let now = unix_millis();
+        let window = self.window.as_millis() as u64;
+
+        let mut seen = self
+            .seen
+            .lock()
+            .map_err(|_| M2MError::Protocol("Replay cache lock poisoned".to_string()))?;
+
+        if seen.contains_key(&early.nonce) {
+            return Err(M2MError::Protocol(
+                "Early data token already used".to_string(),
+            ));
+        }
+
+        if seen.len() >= self.max_entries {
+            seen.retain(|_, issued_at| now.saturating_sub(*issued_at) <= window);
+            // Fail closed rather than forget nonces still inside the window
+            if seen.len() >= self.max_entries {
+                return Err(M2MError::Protocol("Replay cache full".to_string()));
+            }
+        }
+
+        seen.insert(early.nonce.clone(), early.issued_at);
+        Ok(())
+    }
+
+    /// Whether a token was issued within the freshness window
+    ///
+    /// Unlike [`check`](Self::check), this does not use up the nonce.
+    pub fn is_fresh(&self, early: &EarlyData) -> bool {
+        unix_millis().abs_diff(early.issued_at) <= self.window.as_millis() as u64
+    }
+
+    /// Number of nonces currently tracked
+    pub fn len(&self) -> usize {
+        self.seen.lock().map(|seen| seen.len()).unwrap_or(0)
+    }
+
+    /// Check if no nonces are tracked
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Current time in Unix millis
+pub(super) fn unix_millis() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replay_guard() {
+        let guard = ReplayGuard::new();
+        let token = EarlyData::new();
+
+        assert!(guard.check(&token).is_ok());
+        assert!(guard.check(&token).is_err());
+        assert!(guard.check(&EarlyData::new()).is_ok());
+
+        let stale = EarlyData {
+            issued_at: token.issued_at - (EARLY_DATA_WINDOW_SECS + 1) * 1000,
+            ..EarlyData::new()
+        };
+        assert!(guard.check(&stale).is_err());
+        assert_eq!(guard.len(), 2);
+    }
+
+    #[test]
+    fn test_replay_guard_full() {
+        let guard = ReplayGuard::new().with_max_entries(1);
+        assert!(guard.check(&EarlyData::new()).is_ok());
+        assert!(guard.check(&EarlyData::new()).is_err());
+    }
+}
@@ -0,0 +1,431 @@
+//! Typed capability extensions.
+//!
+//! [`Capabilities::extensions`] is a string map on the wire. An [`Extension`]
+//! gives one key a typed value and a negotiation rule, so both agents derive
+//! the same agreed value during the handshake without per-key code in the
+//! session.
+//!
+//! # Wire Encoding
+//!
+//! Values are serialized with serde. String values are stored as-is
+//! (`"tenant_id": "acme"`); anything else is stored as JSON text
+//! (`"max_payload_size": "1048576"`, `"preferred_cipher": "[\"aes-256-gcm\"]"`).
+//!
+//! # Negotiation Rules
+//!
+//! | Rule        | Both sides advertise          | One side advertises |
+//! |-------------|-------------------------------|---------------------|
+//! | `Min`       | Smaller value                 | That value          |
+//! | `Max`       | Larger value                  | That value          |
+//! | `Intersect` | Common items in initiator order; fails if none | Omitted |
+//! | `Exact`     | Value if equal, else fails    | Fails               |
+//!
+//! Keys not in the [`ExtensionRegistry`] are left un-negotiated and remain
+//! readable from the peer's raw capabilities.
+//!
+//! [`Capabilities::extensions`]: super::Capabilities::extensions
+
+use std::collections::{BTreeSet, HashMap};
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::error::{M2MError, Result};
+
+/// How two advertised values of an extension combine
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Negotiation {
+    /// Smaller numeric value wins
+    Min,
+    /// Larger numeric value wins
+    Max,
+    /// Common list items in the initiator's preference order
+    Intersect,
+    /// Values must be identical
+    Exact,
+}
+
+/// A typed capability extension
+pub trait Extension: Serialize + DeserializeOwned {
+    /// Key in the capabilities extension map
+    const KEY: &'static str;
+    /// Rule for combining both agents' values
+    const NEGOTIATION: Negotiation;
+}
+
+/// Maximum payload size in bytes either agent will accept
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct MaxPayloadSize(pub usize);
+
+impl Extension for MaxPayloadSize {
+    const KEY: &'static str = "max_payload_size";
+    const NEGOTIATION: Negotiation = Negotiation::Min;
+}
+
+/// Maximum size in bytes of a single DATA frame either agent will accept
+///
+/// Larger messages are split into fragments (see
+/// [`Session::compress_fragmented`](super::Session::compress_fragmented)).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct MaxFrameSize(pub usize);
+
+impl Extension for MaxFrameSize {
+    const KEY: &'static str = "max_frame_size";
+    const NEGOTIATION: Negotiation = Negotiation::Min;
+}
+
+/// Seconds a session may stay idle before it is timed out
+///
+/// Defaults to [`SESSION_TIMEOUT_SECS`](super::SESSION_TIMEOUT_SECS) when
+/// neither agent advertises it. The session manager pings idle sessions
+/// well before this deadline (see
+/// [`SessionManager`](crate::server::SessionManager)).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct IdleTimeout(pub u64);
+
+impl Extension for IdleTimeout {
+    const KEY: &'static str = "idle_timeout";
+    const NEGOTIATION: Negotiation = Negotiation::Min;
+}
+
+/// Seconds of idle time after which an agent sends a PING
+///
+/// Agents behind NAT or on mobile links advertise a short interval to keep
+/// mappings alive; the shorter of both values wins. Defaults to
+/// [`KEEPALIVE_INTERVAL_SECS`](super::KEEPALIVE_INTERVAL_SECS).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct KeepaliveInterval(pub u64);
+
+impl Extension for KeepaliveInterval {
+    const KEY: &'static str = "keepalive_interval";
+    const NEGOTIATION: Negotiation = Negotiation::Min;
+}
+
+/// Seconds an agent waits for the PONG answering its PING
+///
+/// The longer of both values wins, so the agent on the slower link is not
+/// timed out. Defaults to
+/// [`KEEPALIVE_TIMEOUT_SECS`](super::KEEPALIVE_TIMEOUT_SECS).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct KeepaliveTimeout(pub u64);
+
+impl Extension for KeepaliveTimeout {
+    const KEY: &'static str = "keepalive_timeout";
+    const NEGOTIATION: Negotiation = Negotiation::Max;
+}
+
+/// Abbreviation table versions an agent can expand, in preference order
+///
+/// Agents advertise their [`AbbreviationTable`](crate::codec::AbbreviationTable)
+/// version and [`BUILTIN_TABLE_VERSION`](crate::codec::BUILTIN_TABLE_VERSION),
+/// so negotiation falls back to the built-in table when custom tables differ.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct AbbreviationTables(pub Vec<String>);
+
+impl Extension for AbbreviationTables {
+    const KEY: &'static str = "abbreviation_tables";
+    const NEGOTIATION: Negotiation = Negotiation::Intersect;
+}
+
+impl AbbreviationTables {
+    /// Most preferred table version
+    pub fn first(&self) -> Option<&str> {
+        self.0.first().map(String::as_str)
+    }
+}
+
+/// Shared compression dictionary versions an agent holds, in preference order
+///
+/// Agents append [`NO_DICTIONARY`](crate::codec::NO_DICTIONARY), so
+/// negotiation agrees on plain Brotli when they hold no common dictionary.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct SharedDictionaries(pub Vec<String>);
+
+impl Extension for SharedDictionaries {
+    const KEY: &'static str = "shared_dictionaries";
+    const NEGOTIATION: Negotiation = Negotiation::Intersect;
+}
+
+impl SharedDictionaries {
+    /// Most preferred dictionary version
+    pub fn first(&self) -> Option<&str> {
+        self.0.first().map(String::as_str)
+    }
+}
+
+/// Payload ciphers in preference order (e.g. `aes-256-gcm`)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct PreferredCipher(pub Vec<String>);
+
+impl Extension for PreferredCipher {
+    const KEY: &'static str = "preferred_cipher";
+    const NEGOTIATION: Negotiation = Negotiation::Intersect;
+}
+
+impl PreferredCipher {
+    /// Most preferred cipher
+    pub fn first(&self) -> Option<&str> {
+        self.0.first().map(String::as_str)
+    }
+}
+
+/// Tenant both agents belong to
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct TenantId(pub String);
+
+impl Extension for TenantId {
+    const KEY: &'static str = "tenant_id";
+    const NEGOTIATION: Negotiation = Negotiation::Exact;
+}
+
+/// Org audit key that session keys are escrowed to
+///
+/// Both agents must advertise the same key, so escrowed sessions are only
+/// established within one organization and a peer cannot silently opt out.
+/// With the `escrow` feature, senders attach the session key wrapped to
+/// this key to every secure frame (see
+/// [`EscrowKey`](crate::codec::m2m::crypto::EscrowKey)).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditEscrow {
+    /// Organization owning the audit key
+    pub org_id: String,
+    /// Audit X25519 public key (base64)
+    pub public_key: String,
+}
+
+impl Extension for AuditEscrow {
+    const KEY: &'static str = "audit_escrow";
+    const NEGOTIATION: Negotiation = Negotiation::Exact;
+}
+
+/// Encode an extension value for the wire
+pub(crate) fn encode<E: Extension>(value: &E) -> String {
+    match serde_json::to_value(value) {
+        Ok(Value::String(s)) => s,
+        Ok(other) => other.to_string(),
+        Err(_) => String::new(),
+    }
+}
+
+/// Decode an extension value from the wire
+pub(crate) fn decode<E: Extension>(raw: &str) -> Option<E> {
+    serde_json::from_str(raw)
+        .or_else(|_| serde_json::from_value(Value::String(raw.to_string())))
+        .ok()
+}
+
+/// Parse a raw wire value as JSON, falling back to a plain string
+fn parse(raw: &str) -> Value {
+    serde_json::from_str(raw).unwrap_or_else(|_| Value::String(raw.to_string()))
+}
+
+/// Negotiation rules by extension key
+///
+/// # Epistemic Properties
+///
+/// - **K_i**: Both agents derive the same value when their registries agree
+///   on the rule for a key
+/// - **B_i**: Peers interpret unregistered keys the same way they did before
+#[derive(Debug, Clone, Default)]
+pub struct ExtensionRegistry {
+    /// Rule per key
+    rules: HashMap<String, Negotiation>,
+}
+
+impl ExtensionRegistry {
+    /// Create an empty registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a registry with the well-known extensions
+    pub fn well_known() -> Self {
+        Self::new()
+            .register::<MaxPayloadSize>()
+            .register::<MaxFrameSize>()
+            .register::<IdleTimeout>()
+            .register::<KeepaliveInterval>()
+            .register::<KeepaliveTimeout>()
+            .register::<AbbreviationTables>()
+            .register::<SharedDictionaries>()
+            .register::<PreferredCipher>()
+            .register::<TenantId>()
+            .register::<AuditEscrow>()
+    }
+
+    /// Register an extension
+    pub fn register<E: Extension>(mut self) -> Self {
+        self.rules.insert(E::KEY.to_string(), E::NEGOTIATION);
+        self
+    }
+
+    /// Get the rule for a key
+    pub fn rule(&self, key: &str) -> Option<Negotiation> {
+        self.rules.get(key).copied()
+    }
+
+    /// Negotiate all registered extensions
+    ///
+    /// Arguments are ordered by role (HELLO sender first) so both agents
+    /// compute the same result. Returns the agreed values in wire encoding,
+    /// or `NegotiationFailed` naming the conflicting key.
+    pub fn negotiate(
+        &self,
+        initiator: &HashMap<String, String>,
+        responder: &HashMap<String, String>,
+    ) -> Result<HashMap<String, String>> {
+        let mut agreed = HashMap::new();
+
+        for (key, rule) in &self.rules {
+            let initiator = initiator.get(key).map(|raw| parse(raw));
+            let responder = responder.get(key).map(|raw| parse(raw));
+
+            let value = match (initiator, responder) {
+                (None, None) => continue,
+                (Some(l), Some(r)) => negotiate_pair(key, *rule, l, r)?,
+                (Some(v), None) | (None, Some(v)) => match rule {
+                    Negotiation::Min | Negotiation::Max => Some(v),
+                    Negotiation::Intersect => None,
+                    Negotiation::Exact => {
+                        return Err(M2MError::NegotiationFailed(format!(
+                            "Extension {key} advertised by only one agent"
+                        )));
+                    },
+                },
+            };
+
+            if let Some(value) = value {
+                let raw = match value {
+                    Value::String(s) => s,
+                    other => other.to_string(),
+                };
+                agreed.insert(key.clone(), raw);
+            }
+        }
+
+        Ok(agreed)
+    }
+}
+
+/// Combine two advertised values under a rule
+fn negotiate_pair(
+    key: &str,
+    rule: Negotiation,
+    first: Value,
+    second: Value,
+) -> Result<Option<Value>> {
+    let conflict = |reason: &str| M2MError::NegotiationFailed(format!("Extension {key}: {reason}"));
+
+    match rule {
+        Negotiation::Min | Negotiation::Max => {
+            let (Some(l), Some(r)) = (first.as_f64(), second.as_f64()) else {
+                return Err(conflict("expected numeric values"));
+            };
+            let first_wins = if rule == Negotiation::Min {
+                l <= r
+            } else {
+                l >= r
+            };
+            Ok(Some(if first_wins { first } else { second }))
+        },
+        Negotiation::Intersect => {
+            let (Value::Array(l), Value::Array(r)) = (first, second) else {
+                return Err(conflict("expected lists"));
+            };
+            let other: BTreeSet<String> = r.iter().map(Value::to_string).collect();
+            let common: Vec<Value> = l
+                .into_iter()
+                .filter(|item| other.contains(&item.to_string()))
+                .collect();
+            if common.is_empty() {
+                return Err(conflict("no common value"));
+            }
+            Ok(Some(Value::Array(common)))
+        },
+        Negotiation::Exact => {
+            if first != second {
+                return Err(conflict("values differ"));
+            }
+            Ok(Some(first))
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map(entries: &[(&str, String)]) -> HashMap<String, String> {
+        entries
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn test_extension_round_trip() {
+        assert_eq!(encode(&MaxPayloadSize(1024)), "1024");
+        assert_eq!(encode(&TenantId("acme".to_string())), "acme");
+        assert_eq!(decode::<TenantId>("acme"), Some(TenantId("acme".into())));
+        // Numeric-looking strings still decode as strings
+        assert_eq!(decode::<TenantId>("42"), Some(TenantId("42".into())));
+
+        let ciphers = PreferredCipher(vec!["aes-256-gcm".into(), "chacha20-poly1305".into()]);
+        assert_eq!(decode::<PreferredCipher>(&encode(&ciphers)), Some(ciphers));
+        assert_eq!(decode::<MaxPayloadSize>("lots"), None);
+    }
+
+    #[test]
+    fn test_registry_negotiation() {
+        let registry = ExtensionRegistry::well_known();
+        let local = map(&[
+            (MaxPayloadSize::KEY, encode(&MaxPayloadSize(4096))),
+            (
+                PreferredCipher::KEY,
+                encode(&PreferredCipher(vec![
+                    "chacha20-poly1305".into(),
+                    "aes-256-gcm".into(),
+                ])),
+            ),
+            ("custom", "x".to_string()),
+        ]);
+        let remote = map(&[
+            (MaxPayloadSize::KEY, encode(&MaxPayloadSize(1024))),
+            (
+                PreferredCipher::KEY,
+                encode(&PreferredCipher(vec!["aes-256-gcm".into()])),
+            ),
+        ]);
+
+        let agreed = registry.negotiate(&local, &remote).unwrap();
+        assert_eq!(
+            decode::<MaxPayloadSize>(&agreed[MaxPayloadSize::KEY]),
+            Some(MaxPayloadSize(1024))
+        );
+        assert_eq!(
+            decode::<PreferredCipher>(&agreed[PreferredCipher::KEY]),
+            Some(PreferredCipher(vec!["aes-256-gcm".into()]))
+        );
+        assert!(!agreed.contains_key("custom"));
+
+        // Exact: mismatched or one-sided tenant fails
+        let tenant = |t: &str| map(&[(TenantId::KEY, t.to_string())]);
+        assert!(registry.negotiate(&tenant("acme"), &tenant("acme")).is_ok());
+        assert!(registry
+            .negotiate(&tenant("acme"), &tenant("globex"))
+            .is_err());
+        assert!(registry
+            .negotiate(&tenant("acme"), &HashMap::new())
+            .is_err());
+    }
+}
@@ -0,0 +1,116 @@
+//! Session-level flow control.
+//!
+//! A receiver advertises a window in its HELLO/ACCEPT capabilities: how many
+//! DATA messages, and how many bytes of DATA content, the peer may send
+//! before waiting. Each DATA consumes credit; WINDOW_UPDATE returns it once
+//! the receiver has processed what it got.
+//!
+//! ```text
+//! Client                                    Server
+//!   |  HELLO  {receive_window: 4 msgs}  ------>  |
+//!   |  <------ ACCEPT {receive_window: 2 msgs}   |
+//!   |  DATA  ------------------------------->    |  server credit: 1
+//!   |  DATA  ------------------------------->    |  server credit: 0
+//!   |  (compress() fails: WindowExhausted)       |
+//!   |  <------------ WINDOW_UPDATE {2 msgs}      |  server credit: 2
+//! ```
+//!
+//! A peer that advertises no window grants unlimited credit, so sessions
+//! with older agents behave as before.
+
+use serde::{Deserialize, Serialize};
+
+/// Flow-control window (or a replenishment of one)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct FlowWindow {
+    /// DATA messages
+    pub messages: u32,
+    /// Bytes of DATA content (wire format)
+    pub bytes: u64,
+}
+
+impl FlowWindow {
+    /// Create a window
+    pub fn new(messages: u32, bytes: u64) -> Self {
+        Self { messages, bytes }
+    }
+
+    /// Check if a message of `bytes` fits in the window
+    pub fn covers(&self, bytes: usize) -> bool {
+        self.messages > 0 && bytes as u64 <= self.bytes
+    }
+
+    /// Check if the window is empty
+    pub fn is_empty(&self) -> bool {
+        self.messages == 0 && self.bytes == 0
+    }
+
+    /// Take one message of `bytes` from the window
+    pub(crate) fn consume(&mut self, bytes: usize) {
+        self.messages = self.messages.saturating_sub(1);
+        self.bytes = self.bytes.saturating_sub(bytes as u64);
+    }
+
+    /// Add a replenishment to the window
+    pub(crate) fn replenish(&mut self, update: FlowWindow) {
+        self.messages = self.messages.saturating_add(update.messages);
+        self.bytes = self.bytes.saturating_add(update.bytes);
+    }
+}
+
+/// Receive-side accounting for the window we advertised
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ReceiveWindow {
+    /// Credit the peer has left
+    remaining: FlowWindow,
+    /// Consumed since the last WINDOW_UPDATE
+    pending: FlowWindow,
+}
+
+impl ReceiveWindow {
+    /// Start with the full advertised window
+    pub(crate) fn new(window: FlowWindow) -> Self {
+        Self {
+            remaining: window,
+            pending: FlowWindow::default(),
+        }
+    }
+
+    /// Account for a received message; `false` if the peer overran the window
+    pub(crate) fn receive(&mut self, bytes: usize) -> bool {
+        if !self.remaining.covers(bytes) {
+            return false;
+        }
+        self.remaining.consume(bytes);
+        self.pending.replenish(FlowWindow::new(1, bytes as u64));
+        true
+    }
+
+    /// Return consumed credit to the peer, if any
+    pub(crate) fn release(&mut self) -> Option<FlowWindow> {
+        if self.pending.is_empty() {
+            return None;
+        }
+        let update = std::mem::take(&mut self.pending);
+        self.remaining.replenish(update);
+        Some(update)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_receive_window() {
+        let mut window = ReceiveWindow::new(FlowWindow::new(2, 100));
+        assert!(window.receive(60));
+        assert!(!window.receive(50)); // Bytes exhausted
+        assert!(window.receive(40));
+        assert!(!window.receive(0)); // Messages exhausted
+
+        assert_eq!(window.release(), Some(FlowWindow::new(2, 100)));
+        assert_eq!(window.release(), None);
+        assert!(window.receive(100));
+    }
+}
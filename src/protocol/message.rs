@@ -2,10 +2,18 @@
 //!
 //! Defines the wire format for HELLO, ACCEPT, REJECT, and DATA messages.
 
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
 use serde::{Deserialize, Serialize};
 
-use super::Capabilities;
-use crate::codec::Algorithm;
+use super::{Capabilities, EarlyData, FlowWindow, HelloAuth, ProtocolVersion};
+#[cfg(feature = "crypto")]
+use crate::codec::m2m::crypto::GroupKey;
+use crate::codec::{Algorithm, CompressionHint, M2MFrame, SharedDictionary, TraceContext};
+use crate::context::PinnedContext;
+use crate::error::{ErrorCode, M2MError};
+
+/// Retry delay suggested when rejecting shed load (seconds)
+pub const DEFAULT_RETRY_AFTER_SECS: u64 = 1;
 
 /// Message types in the M2M protocol
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -25,6 +33,21 @@ pub enum MessageType {
     Pong,
     /// Session termination
     Close,
+    /// Flow-control credit replenishment
+    #[serde(rename = "WINDOW_UPDATE")]
+    WindowUpdate,
+    /// Payload sealed once under a group key for several agents
+    Broadcast,
+    /// Shared compression dictionary sent to the peer
+    #[serde(rename = "DICT_PUSH")]
+    DictPush,
+    /// Move an established session to a newer minor protocol version
+    Upgrade,
+    /// Conversation prefix pinned at the peer, or its acknowledgement
+    #[serde(rename = "CONTEXT_PIN")]
+    ContextPin,
+    /// Switch to the next session key
+    Rekey,
 }
 
 /// Protocol message envelope
@@ -41,6 +64,32 @@ pub struct Message {
     pub payload: Option<MessagePayload>,
     /// Timestamp (Unix millis)
     pub timestamp: u64,
+    /// Anti-replay token (HELLO sent in 0-RTT data)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub early_data: Option<EarlyData>,
+    /// Relay routing (DATA forwarded through a server to another agent)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub relay: Option<RelayHeader>,
+    /// Channel of a session multiplexed over a shared connection
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channel: Option<u32>,
+    /// Handshake credential (HELLO only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth: Option<HelloAuth>,
+}
+
+/// Routing header for DATA relayed between agents by a server
+///
+/// The sender sets `to`; the relaying server sets `from` to the agent ID
+/// the sender's session was established with, replacing any value the
+/// sender supplied.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RelayHeader {
+    /// Destination agent ID
+    pub to: String,
+    /// Source agent ID (set by the relay)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from: Option<String>,
 }
 
 /// Message payload variants
@@ -51,19 +100,177 @@ pub enum MessagePayload {
     Capabilities(Capabilities),
     /// Rejection reason
     Rejection(RejectionInfo),
+    /// Group-sealed content for BROADCAST
+    Broadcast(BroadcastPayload),
+    /// Shared compression dictionary for DICT_PUSH
+    Dictionary(DictionaryPayload),
     /// Compressed data
     Data(DataPayload),
+    /// Flow-control credit for WINDOW_UPDATE
+    Window(FlowWindow),
+    /// Closure reason for CLOSE
+    Close(CloseInfo),
+    /// Pinned context or acknowledgement for CONTEXT_PIN
+    ContextPin(ContextPinInfo),
+    /// Proposed or agreed version for UPGRADE
+    Upgrade(UpgradeInfo),
+    /// Key epoch for REKEY
+    Rekey(RekeyInfo),
     /// Empty (for PING/PONG/CLOSE)
     Empty {},
 }
 
 /// Rejection information
+///
+/// Besides the code and message, a REJECT may carry hints that let the
+/// client recover without operator help: when to retry a `RateLimited`
+/// request, which versions to offer after a `VersionMismatch`, other
+/// endpoints to try, and the capabilities the rejecting agent requires.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RejectionInfo {
     /// Rejection reason code
     pub code: RejectionCode,
     /// Human-readable message
     pub message: String,
+    /// Code of the error that caused the rejection
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_code: Option<ErrorCode>,
+    /// Seconds to wait before retrying
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry_after_secs: Option<u64>,
+    /// Protocol versions the rejecting agent accepts
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub supported_versions: Vec<String>,
+    /// Other endpoints that may accept the session
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub alternative_endpoints: Vec<String>,
+    /// Capabilities a HELLO must be compatible with
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub required_capabilities: Option<Box<Capabilities>>,
+}
+
+impl RejectionInfo {
+    /// Create rejection information with no recovery hints
+    pub fn new(code: RejectionCode, message: &str) -> Self {
+        Self {
+            code,
+            message: message.to_string(),
+            error_code: None,
+            retry_after_secs: None,
+            supported_versions: Vec::new(),
+            alternative_endpoints: Vec::new(),
+            required_capabilities: None,
+        }
+    }
+
+    /// Ask the client to retry after `secs` seconds
+    pub fn with_retry_after(mut self, secs: u64) -> Self {
+        self.retry_after_secs = Some(secs);
+        self
+    }
+
+    /// List the protocol versions that would be accepted
+    pub fn with_supported_versions(mut self, versions: Vec<String>) -> Self {
+        self.supported_versions = versions;
+        self
+    }
+
+    /// List other endpoints the client may try
+    pub fn with_alternative_endpoints(mut self, endpoints: Vec<String>) -> Self {
+        self.alternative_endpoints = endpoints;
+        self
+    }
+
+    /// Describe the capabilities a HELLO must be compatible with
+    pub fn with_required_capabilities(mut self, caps: Capabilities) -> Self {
+        self.required_capabilities = Some(Box::new(caps));
+        self
+    }
+
+    /// Time to wait before retrying, if the rejection is temporary
+    pub fn retry_after(&self) -> Option<std::time::Duration> {
+        self.retry_after_secs.map(std::time::Duration::from_secs)
+    }
+
+    /// Whether a client may recover by retrying or reconfiguring
+    ///
+    /// True when the rejection carries a retry delay, alternative
+    /// endpoints, supported versions or required capabilities.
+    pub fn is_recoverable(&self) -> bool {
+        self.retry_after_secs.is_some()
+            || !self.alternative_endpoints.is_empty()
+            || !self.supported_versions.is_empty()
+            || self.required_capabilities.is_some()
+    }
+}
+
+/// Closure information for CLOSE
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CloseInfo {
+    /// Closure reason code
+    pub reason: CloseReason,
+    /// Human-readable explanation
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    /// Code of the error that caused the closure
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_code: Option<ErrorCode>,
+}
+
+/// Version carried by UPGRADE
+///
+/// Proposed by the initiator; the responder's reply carries the version
+/// both sides now speak.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UpgradeInfo {
+    /// Protocol version
+    pub version: ProtocolVersion,
+}
+
+/// Key epoch carried by REKEY
+///
+/// Session keys are numbered from 0 (the key bound to the handshake); the
+/// sender has switched to key `epoch`, and the receiver's reply confirms it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RekeyInfo {
+    /// Epoch of the new session key
+    pub epoch: u32,
+}
+
+/// Context carried by CONTEXT_PIN
+///
+/// The sender's pin carries the messages (and optionally a handle it no
+/// longer needs); the receiver's acknowledgement carries how long the
+/// context lives without use.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContextPinInfo {
+    /// Handle chosen by the sender
+    pub handle: String,
+    /// Messages to pin (pin only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub messages: Option<Vec<serde_json::Value>>,
+    /// Earlier handle to release (pin only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub release: Option<String>,
+    /// Idle lifetime granted (acknowledgement only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ttl_secs: Option<u64>,
+}
+
+/// Closure reason codes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum CloseReason {
+    /// Clean shutdown
+    Normal,
+    /// Session timeout exceeded
+    Timeout,
+    /// Protocol or processing error
+    Error,
+    /// Client application closing
+    ClientShutdown,
+    /// Server shutting down
+    ServerShutdown,
 }
 
 /// Rejection reason codes
@@ -78,6 +285,12 @@ pub enum RejectionCode {
     SecurityPolicy,
     /// Rate limited
     RateLimited,
+    /// Agent identity or key epoch has been revoked
+    IdentityRevoked,
+    /// Early data token stale, reused, or missing
+    ReplayDetected,
+    /// Extension values cannot be reconciled
+    ExtensionMismatch,
     /// Unknown/other error
     Unknown,
 }
@@ -95,6 +308,45 @@ pub struct DataPayload {
     /// Security scan result (if applicable)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub security_status: Option<SecurityStatus>,
+    /// Handshake transcript MAC (base64, first DATA of a bound session)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transcript_mac: Option<String>,
+    /// Random ID of this frame (kept when the same frame is resent)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message_id: Option<String>,
+    /// Sender-chosen key shared by every attempt of one logical message
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idempotency_key: Option<String>,
+}
+
+/// Broadcast payload
+///
+/// `content` is sealed once with the group key of `group_id` at `epoch`;
+/// a relaying server delivers the same ciphertext to every recipient.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BroadcastPayload {
+    /// Group whose key sealed the content
+    pub group_id: String,
+    /// Group key epoch
+    pub epoch: u32,
+    /// Recipient agent IDs
+    pub recipients: Vec<String>,
+    /// Sealed content (base64)
+    pub content: String,
+}
+
+/// Shared dictionary payload
+///
+/// The receiver recomputes `version` from the decoded content, so a
+/// truncated or altered dictionary is refused.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DictionaryPayload {
+    /// Dictionary ID
+    pub dictionary_id: String,
+    /// Dictionary version (`<id>:<sha256>`)
+    pub version: String,
+    /// Dictionary content (base64)
+    pub dictionary: String,
 }
 
 /// Security scan status
@@ -120,6 +372,21 @@ impl Message {
             session_id: None,
             payload: Some(MessagePayload::Capabilities(capabilities)),
             timestamp: current_timestamp(),
+            early_data: None,
+            relay: None,
+            channel: None,
+            auth: None,
+        }
+    }
+
+    /// Create a HELLO for 0-RTT data
+    ///
+    /// Proposes `session_id` so DATA can follow before ACCEPT arrives.
+    pub fn early_hello(session_id: &str, capabilities: Capabilities) -> Self {
+        Self {
+            session_id: Some(session_id.to_string()),
+            early_data: Some(EarlyData::new()),
+            ..Self::hello(capabilities)
         }
     }
 
@@ -130,20 +397,52 @@ impl Message {
             session_id: Some(session_id.to_string()),
             payload: Some(MessagePayload::Capabilities(capabilities)),
             timestamp: current_timestamp(),
+            early_data: None,
+            relay: None,
+            channel: None,
+            auth: None,
         }
     }
 
     /// Create a REJECT message
     pub fn reject(code: RejectionCode, message: &str) -> Self {
+        Self::rejection(RejectionInfo::new(code, message))
+    }
+
+    /// Create a REJECT message from full rejection information
+    pub fn rejection(info: RejectionInfo) -> Self {
         Self {
             msg_type: MessageType::Reject,
             session_id: None,
-            payload: Some(MessagePayload::Rejection(RejectionInfo {
-                code,
-                message: message.to_string(),
-            })),
+            payload: Some(MessagePayload::Rejection(info)),
             timestamp: current_timestamp(),
+            early_data: None,
+            relay: None,
+            channel: None,
+            auth: None,
+        }
+    }
+
+    /// Create a REJECT message for an error, carrying its error code
+    ///
+    /// Security errors are rejected with [`RejectionCode::SecurityPolicy`],
+    /// shed load with [`RejectionCode::RateLimited`] and a retry delay of
+    /// [`DEFAULT_RETRY_AFTER_SECS`], anything else with
+    /// [`RejectionCode::Unknown`].
+    pub fn reject_error(error: &M2MError) -> Self {
+        let code = if error.is_security_error() {
+            RejectionCode::SecurityPolicy
+        } else if matches!(error, M2MError::Overloaded(_)) {
+            RejectionCode::RateLimited
+        } else {
+            RejectionCode::Unknown
+        };
+        let mut info = RejectionInfo::new(code, &error.to_string());
+        info.error_code = Some(error.code());
+        if code == RejectionCode::RateLimited {
+            info = info.with_retry_after(DEFAULT_RETRY_AFTER_SECS);
         }
+        Self::rejection(info)
     }
 
     /// Create a DATA message
@@ -156,8 +455,15 @@ impl Message {
                 content,
                 original_size: None,
                 security_status: None,
+                transcript_mac: None,
+                message_id: Some(uuid::Uuid::new_v4().to_string()),
+                idempotency_key: None,
             })),
             timestamp: current_timestamp(),
+            early_data: None,
+            relay: None,
+            channel: None,
+            auth: None,
         }
     }
 
@@ -176,9 +482,106 @@ impl Message {
                 content,
                 original_size: None,
                 security_status: Some(security),
+                transcript_mac: None,
+                message_id: Some(uuid::Uuid::new_v4().to_string()),
+                idempotency_key: None,
+            })),
+            timestamp: current_timestamp(),
+            early_data: None,
+            relay: None,
+            channel: None,
+            auth: None,
+        }
+    }
+
+    /// Create a BROADCAST message
+    pub fn broadcast(session_id: &str, payload: BroadcastPayload) -> Self {
+        Self {
+            msg_type: MessageType::Broadcast,
+            session_id: Some(session_id.to_string()),
+            payload: Some(MessagePayload::Broadcast(payload)),
+            timestamp: current_timestamp(),
+            early_data: None,
+            relay: None,
+            channel: None,
+            auth: None,
+        }
+    }
+
+    /// Seal `content` once with a group key and address it to `recipients`
+    #[cfg(feature = "crypto")]
+    pub fn seal_broadcast(
+        session_id: &str,
+        key: &GroupKey,
+        content: &str,
+        recipients: &[&str],
+    ) -> Result<Self, M2MError> {
+        Ok(Self::broadcast(
+            session_id,
+            BroadcastPayload {
+                group_id: key.group_id().to_string(),
+                epoch: key.epoch(),
+                recipients: recipients.iter().map(ToString::to_string).collect(),
+                content: key.seal(content)?,
+            },
+        ))
+    }
+
+    /// Open a BROADCAST message with the group key
+    #[cfg(feature = "crypto")]
+    pub fn open_broadcast(&self, key: &GroupKey) -> Result<String, M2MError> {
+        let broadcast = self
+            .get_broadcast()
+            .ok_or_else(|| M2MError::InvalidMessage("Not a BROADCAST message".to_string()))?;
+        if broadcast.group_id != key.group_id() || broadcast.epoch != key.epoch() {
+            return Err(M2MError::InvalidMessage(format!(
+                "Broadcast for group {} epoch {}, key is for group {} epoch {}",
+                broadcast.group_id,
+                broadcast.epoch,
+                key.group_id(),
+                key.epoch()
+            )));
+        }
+        Ok(key.open(&broadcast.content)?)
+    }
+
+    /// Create a DICT_PUSH message carrying a shared dictionary
+    pub fn dict_push(session_id: &str, dictionary: &SharedDictionary) -> Self {
+        Self {
+            msg_type: MessageType::DictPush,
+            session_id: Some(session_id.to_string()),
+            payload: Some(MessagePayload::Dictionary(DictionaryPayload {
+                dictionary_id: dictionary.id().to_string(),
+                version: dictionary.version(),
+                dictionary: BASE64.encode(dictionary.data()),
             })),
             timestamp: current_timestamp(),
+            early_data: None,
+            relay: None,
+            channel: None,
+            auth: None,
+        }
+    }
+
+    /// Decode the dictionary of a DICT_PUSH message
+    ///
+    /// Fails if the content does not match the advertised version.
+    pub fn get_dictionary(&self) -> Result<SharedDictionary, M2MError> {
+        let Some(MessagePayload::Dictionary(payload)) = &self.payload else {
+            return Err(M2MError::InvalidMessage(
+                "DICT_PUSH missing dictionary".to_string(),
+            ));
+        };
+        let dictionary =
+            SharedDictionary::new(&payload.dictionary_id, BASE64.decode(&payload.dictionary)?)?;
+        if dictionary.version() != payload.version {
+            return Err(M2MError::InvalidMessage(format!(
+                "Dictionary content is version {}, expected {}",
+                dictionary.version(),
+                payload.version
+            )));
         }
+        Ok(dictionary)
     }
 
     /// Create a PING message
@@ -188,6 +591,10 @@ impl Message {
             session_id: Some(session_id.to_string()),
             payload: Some(MessagePayload::Empty {}),
             timestamp: current_timestamp(),
+            early_data: None,
+            relay: None,
+            channel: None,
+            auth: None,
         }
     }
 
@@ -198,6 +605,10 @@ impl Message {
             session_id: Some(session_id.to_string()),
             payload: Some(MessagePayload::Empty {}),
             timestamp: current_timestamp(),
+            early_data: None,
+            relay: None,
+            channel: None,
+            auth: None,
         }
     }
 
@@ -208,6 +619,76 @@ impl Message {
             session_id: Some(session_id.to_string()),
             payload: Some(MessagePayload::Empty {}),
             timestamp: current_timestamp(),
+            early_data: None,
+            relay: None,
+            channel: None,
+            auth: None,
+        }
+    }
+
+    /// Create a CLOSE message with a reason
+    pub fn close_with_reason(session_id: &str, reason: CloseReason, message: Option<&str>) -> Self {
+        Self {
+            payload: Some(MessagePayload::Close(CloseInfo {
+                reason,
+                message: message.map(str::to_string),
+                error_code: None,
+            })),
+            ..Self::close(session_id)
+        }
+    }
+
+    /// Create a CLOSE message for an error, carrying its error code
+    pub fn close_error(session_id: &str, error: &M2MError) -> Self {
+        Self {
+            payload: Some(MessagePayload::Close(CloseInfo {
+                reason: CloseReason::Error,
+                message: Some(error.to_string()),
+                error_code: Some(error.code()),
+            })),
+            ..Self::close(session_id)
+        }
+    }
+
+    /// Create a WINDOW_UPDATE message returning flow-control credit
+    pub fn window_update(session_id: &str, window: FlowWindow) -> Self {
+        Self {
+            msg_type: MessageType::WindowUpdate,
+            session_id: Some(session_id.to_string()),
+            payload: Some(MessagePayload::Window(window)),
+            timestamp: current_timestamp(),
+            early_data: None,
+            relay: None,
+            channel: None,
+            auth: None,
+        }
+    }
+
+    /// Create an UPGRADE message proposing or confirming `version`
+    pub fn upgrade(session_id: &str, version: ProtocolVersion) -> Self {
+        Self {
+            msg_type: MessageType::Upgrade,
+            session_id: Some(session_id.to_string()),
+            payload: Some(MessagePayload::Upgrade(UpgradeInfo { version })),
+            timestamp: current_timestamp(),
+            early_data: None,
+            relay: None,
+            channel: None,
+            auth: None,
+        }
+    }
+
+    /// Create a REKEY message announcing or confirming key `epoch`
+    pub fn rekey(session_id: &str, epoch: u32) -> Self {
+        Self {
+            msg_type: MessageType::Rekey,
+            session_id: Some(session_id.to_string()),
+            payload: Some(MessagePayload::Rekey(RekeyInfo { epoch })),
+            timestamp: current_timestamp(),
+            early_data: None,
+            relay: None,
+            channel: None,
+            auth: None,
         }
     }
 
@@ -242,6 +723,146 @@ impl Message {
         }
     }
 
+    /// Get the ID of a DATA message
+    pub fn message_id(&self) -> Option<&str> {
+        self.get_data().and_then(|data| data.message_id.as_deref())
+    }
+
+    /// Get the idempotency key of a DATA message
+    pub fn idempotency_key(&self) -> Option<&str> {
+        self.get_data()
+            .and_then(|data| data.idempotency_key.as_deref())
+    }
+
+    /// Set the ID of a DATA message (no effect on other types)
+    pub fn with_message_id(mut self, id: impl Into<String>) -> Self {
+        if let Some(MessagePayload::Data(data)) = &mut self.payload {
+            data.message_id = Some(id.into());
+        }
+        self
+    }
+
+    /// Tag a DATA message with an idempotency key (no effect on other types)
+    ///
+    /// Use the same key for every retry of one logical message, so the
+    /// receiver recognizes it even though each attempt gets a new ID.
+    pub fn with_idempotency_key(mut self, key: impl Into<String>) -> Self {
+        if let Some(MessagePayload::Data(data)) = &mut self.payload {
+            data.idempotency_key = Some(key.into());
+        }
+        self
+    }
+
+    /// Get broadcast payload
+    pub fn get_broadcast(&self) -> Option<&BroadcastPayload> {
+        match &self.payload {
+            Some(MessagePayload::Broadcast(broadcast)) => Some(broadcast),
+            _ => None,
+        }
+    }
+
+    /// Get the sender's compression hint of a DATA message, if any
+    pub fn compression_hint(&self) -> Option<CompressionHint> {
+        self.get_data()
+            .filter(|data| data.algorithm == Algorithm::M2M)
+            .and_then(|data| M2MFrame::peek_hint(&data.content))
+    }
+
+    /// Get the sender's W3C trace context of a DATA message, if any
+    pub fn trace_context(&self) -> Option<TraceContext> {
+        self.get_data()
+            .filter(|data| data.algorithm == Algorithm::M2M)
+            .and_then(|data| M2MFrame::peek_trace_context(&data.content))
+    }
+
+    /// Get flow-control credit from WINDOW_UPDATE payload
+    pub fn get_window(&self) -> Option<&FlowWindow> {
+        match &self.payload {
+            Some(MessagePayload::Window(window)) => Some(window),
+            _ => None,
+        }
+    }
+
+    /// Create a CONTEXT_PIN message pinning `context` at the peer
+    ///
+    /// `release` names an earlier context the peer may drop.
+    pub fn context_pin(session_id: &str, context: &PinnedContext, release: Option<&str>) -> Self {
+        Self::with_context_pin(
+            session_id,
+            ContextPinInfo {
+                handle: context.handle.clone(),
+                messages: Some(context.messages.clone()),
+                release: release.map(str::to_string),
+                ttl_secs: None,
+            },
+        )
+    }
+
+    /// Create a CONTEXT_PIN message acknowledging a pinned context
+    pub fn context_pinned(session_id: &str, handle: &str, ttl_secs: u64) -> Self {
+        Self::with_context_pin(
+            session_id,
+            ContextPinInfo {
+                handle: handle.to_string(),
+                messages: None,
+                release: None,
+                ttl_secs: Some(ttl_secs),
+            },
+        )
+    }
+
+    fn with_context_pin(session_id: &str, info: ContextPinInfo) -> Self {
+        Self {
+            msg_type: MessageType::ContextPin,
+            session_id: Some(session_id.to_string()),
+            payload: Some(MessagePayload::ContextPin(info)),
+            timestamp: current_timestamp(),
+            early_data: None,
+            relay: None,
+            channel: None,
+            auth: None,
+        }
+    }
+
+    /// Get the context from CONTEXT_PIN payload
+    pub fn get_context_pin(&self) -> Option<&ContextPinInfo> {
+        match &self.payload {
+            Some(MessagePayload::ContextPin(info)) => Some(info),
+            _ => None,
+        }
+    }
+
+    /// Get the version from UPGRADE payload
+    pub fn get_upgrade(&self) -> Option<&UpgradeInfo> {
+        match &self.payload {
+            Some(MessagePayload::Upgrade(info)) => Some(info),
+            _ => None,
+        }
+    }
+
+    /// Get the key epoch from REKEY payload
+    pub fn get_rekey(&self) -> Option<&RekeyInfo> {
+        match &self.payload {
+            Some(MessagePayload::Rekey(info)) => Some(info),
+            _ => None,
+        }
+    }
+
+    /// Address a DATA message to another agent via the server relay
+    pub fn with_relay_to(mut self, agent_id: &str) -> Self {
+        self.relay = Some(RelayHeader {
+            to: agent_id.to_string(),
+            from: None,
+        });
+        self
+    }
+
+    /// Tag the message with a multiplexed session channel
+    pub fn with_channel(mut self, channel: u32) -> Self {
+        self.channel = Some(channel);
+        self
+    }
+
     /// Get rejection info
     pub fn get_rejection(&self) -> Option<&RejectionInfo> {
         match &self.payload {
@@ -249,6 +870,14 @@ impl Message {
             _ => None,
         }
     }
+
+    /// Get closure info (CLOSE with a reason)
+    pub fn get_close(&self) -> Option<&CloseInfo> {
+        match &self.payload {
+            Some(MessagePayload::Close(info)) => Some(info),
+            _ => None,
+        }
+    }
 }
 
 /// Get current timestamp in milliseconds
@@ -277,6 +906,48 @@ mod tests {
         assert_eq!(parsed.msg_type, MessageType::Hello);
     }
 
+    #[test]
+    fn test_broadcast_roundtrip() {
+        let msg = Message::broadcast(
+            "session-123",
+            BroadcastPayload {
+                group_id: "ops".to_string(),
+                epoch: 2,
+                recipients: vec!["agent-a".to_string(), "agent-b".to_string()],
+                content: "c2VhbGVk".to_string(),
+            },
+        );
+
+        let parsed = Message::from_json(&msg.to_json().unwrap()).unwrap();
+        assert_eq!(parsed.msg_type, MessageType::Broadcast);
+        let broadcast = parsed.get_broadcast().unwrap();
+        assert_eq!(broadcast.group_id, "ops");
+        assert_eq!(broadcast.recipients.len(), 2);
+        assert!(parsed.get_data().is_none());
+    }
+
+    #[test]
+    fn test_dict_push_roundtrip() {
+        let dictionary = SharedDictionary::new("chat", r#"{"role":"user"}"#).unwrap();
+        let msg = Message::dict_push("session-123", &dictionary);
+
+        let json = msg.to_json().unwrap();
+        assert!(json.contains(r#""type":"DICT_PUSH""#));
+        let parsed = Message::from_json(&json).unwrap();
+        assert_eq!(parsed.msg_type, MessageType::DictPush);
+        assert_eq!(
+            parsed.get_dictionary().unwrap().version(),
+            dictionary.version()
+        );
+
+        // Content that does not match the advertised version is refused
+        let mut tampered = parsed;
+        if let Some(MessagePayload::Dictionary(ref mut payload)) = tampered.payload {
+            payload.dictionary = BASE64.encode("other");
+        }
+        assert!(tampered.get_dictionary().is_err());
+    }
+
     #[test]
     fn test_accept_message() {
         let caps = Capabilities::default();
@@ -295,6 +966,59 @@ mod tests {
         assert_eq!(rejection.code, RejectionCode::VersionMismatch);
     }
 
+    #[test]
+    fn test_structured_reject_roundtrip() {
+        let info = RejectionInfo::new(RejectionCode::VersionMismatch, "Version 4.0 required")
+            .with_supported_versions(vec!["4.0".to_string()])
+            .with_alternative_endpoints(vec!["https://legacy.example/m2m".to_string()])
+            .with_required_capabilities(Capabilities::default());
+        let json = Message::rejection(info).to_json().unwrap();
+        let parsed = Message::from_json(&json).unwrap();
+        let info = parsed.get_rejection().unwrap();
+        assert_eq!(info.supported_versions, vec!["4.0"]);
+        assert_eq!(info.alternative_endpoints.len(), 1);
+        assert!(info.required_capabilities.is_some());
+        assert!(info.retry_after().is_none());
+        assert!(info.is_recoverable());
+
+        // Older agents send only code and message
+        let plain = Message::from_json(
+            &Message::reject(RejectionCode::Unknown, "no")
+                .to_json()
+                .unwrap(),
+        )
+        .unwrap();
+        assert!(!plain.get_rejection().unwrap().is_recoverable());
+        assert!(!json.contains("retry_after_secs"));
+
+        let overloaded = Message::reject_error(&M2MError::Overloaded("queue full".to_string()));
+        let info = overloaded.get_rejection().unwrap();
+        assert_eq!(info.code, RejectionCode::RateLimited);
+        assert_eq!(
+            info.retry_after(),
+            Some(std::time::Duration::from_secs(DEFAULT_RETRY_AFTER_SECS))
+        );
+    }
+
+    #[test]
+    fn test_error_codes_in_reject_and_close() {
+        let err = M2MError::ContentBlocked("jailbreak".to_string());
+        let reject = Message::from_json(&Message::reject_error(&err).to_json().unwrap()).unwrap();
+        let info = reject.get_rejection().unwrap();
+        assert_eq!(info.code, RejectionCode::SecurityPolicy);
+        assert_eq!(info.error_code, Some(ErrorCode::CONTENT_BLOCKED));
+
+        let close = Message::close_error("session-123", &M2MError::SessionExpired);
+        let close = Message::from_json(&close.to_json().unwrap()).unwrap();
+        let info = close.get_close().unwrap();
+        assert_eq!(info.reason, CloseReason::Error);
+        assert_eq!(info.error_code, Some(ErrorCode::SESSION_EXPIRED));
+
+        // Plain CLOSE and PONG still carry an empty payload
+        let plain = Message::from_json(&Message::close("s").to_json().unwrap()).unwrap();
+        assert!(plain.get_close().is_none());
+    }
+
     #[test]
     fn test_data_message() {
         let msg = Message::data("session-123", Algorithm::M2M, "#M2M|1|...".to_string());
@@ -316,4 +1040,53 @@ mod tests {
         assert_eq!(caps.agent_type, "test-agent");
         assert_eq!(caps.extensions.get("custom"), Some(&"value".to_string()));
     }
+
+    #[test]
+    fn test_window_update_message() {
+        let msg = Message::window_update("session-123", FlowWindow::new(4, 4096));
+        let json = msg.to_json().unwrap();
+        assert!(json.contains(r#""type":"WINDOW_UPDATE""#));
+
+        let parsed = Message::from_json(&json).unwrap();
+        assert_eq!(parsed.msg_type, MessageType::WindowUpdate);
+        assert_eq!(parsed.get_window(), Some(&FlowWindow::new(4, 4096)));
+    }
+
+    #[test]
+    fn test_upgrade_message() {
+        let msg = Message::upgrade("session-123", ProtocolVersion::V3_1);
+        let json = msg.to_json().unwrap();
+        assert!(json.contains(r#""type":"UPGRADE""#));
+        assert!(json.contains(r#""payload":{"version":"3.1"}"#));
+
+        let parsed = Message::from_json(&json).unwrap();
+        assert_eq!(parsed.msg_type, MessageType::Upgrade);
+        assert_eq!(parsed.get_upgrade().unwrap().version, ProtocolVersion::V3_1);
+    }
+
+    #[test]
+    fn test_context_pin_message() {
+        let context = PinnedContext::new(vec![
+            serde_json::json!({"role":"system","content":"Be brief."}),
+        ]);
+        let msg = Message::context_pin("session-123", &context, Some("ctx_old"));
+        let json = msg.to_json().unwrap();
+        assert!(json.contains(r#""type":"CONTEXT_PIN""#));
+
+        let parsed = Message::from_json(&json).unwrap();
+        let info = parsed.get_context_pin().unwrap();
+        assert_eq!(info.handle, context.handle);
+        assert_eq!(info.messages.as_ref(), Some(&context.messages));
+        assert_eq!(info.release.as_deref(), Some("ctx_old"));
+
+        let ack = Message::from_json(
+            &Message::context_pinned("session-123", &context.handle, 3600)
+                .to_json()
+                .unwrap(),
+        )
+        .unwrap();
+        let info = ack.get_context_pin().unwrap();
+        assert!(info.messages.is_none());
+        assert_eq!(info.ttl_secs, Some(3600));
+    }
 }
@@ -20,6 +20,7 @@
 //!    |                                |
 //!    |======= DATA (compressed) =====>|  Exchange payloads
 //!    |<===== DATA (compressed) =======|
+//!    |<------ WINDOW_UPDATE ---------|  Replenish flow-control credit
 //!    |                                |
 //!    |-------- PING ---------------->|  Keep-alive
 //!    |<------- PONG -----------------|
@@ -43,9 +44,14 @@
 //!
 //! During handshake, agents advertise their capabilities:
 //!
-//! - **Compression**: Supported algorithms (Token, Brotli, Dictionary)
+//! - **Compression**: Supported algorithms (Token, Brotli, Dictionary);
+//!   agents that both set `allow_passthrough` fall back to uncompressed
+//!   DATA instead of a `NoCommonAlgorithm` REJECT
 //! - **Security**: Threat detection, blocking mode, confidence threshold
-//! - **Extensions**: Custom key-value pairs for future features
+//! - **Extensions**: Key-value pairs; typed [`Extension`]s are negotiated
+//!   by the rules in an [`ExtensionRegistry`] (e.g. [`MaxPayloadSize`] takes
+//!   the minimum, [`TenantId`] must match, [`AuditEscrow`] names the org
+//!   audit key session keys are escrowed to)
 //!
 //! ## Rejection Codes
 //!
@@ -53,8 +59,11 @@
 //! |---------------------|----------------------------------|
 //! | `VersionMismatch`   | Protocol version incompatible    |
 //! | `NoCommonAlgorithm` | No mutually supported algorithm  |
-//! | `SecurityPolicy`    | Security policy violation        |
+//! | `SecurityPolicy`    | Policy violation or auth failure |
 //! | `RateLimited`       | Too many requests                |
+//! | `IdentityRevoked`   | Agent identity or key revoked    |
+//! | `ReplayDetected`    | 0-RTT token stale or reused      |
+//! | `ExtensionMismatch` | Extension values cannot agree    |
 //! | `Unknown`           | Other/unspecified error          |
 //!
 //! # Usage
@@ -88,6 +97,36 @@
 //! }
 //! ```
 //!
+//! ## 0-RTT Resumption
+//!
+//! Over QUIC with a cached session ticket, a returning agent sends HELLO and
+//! its first DATA in 0-RTT data. See [`EarlyData`] and [`ReplayGuard`] for
+//! the anti-replay constraints.
+//!
+//! ```rust,ignore
+//! // Client: HELLO + first DATA in the first flight
+//! let hello = client.create_early_hello();
+//! let data = client.compress(r#"{"model":"gpt-4o"}"#)?;
+//!
+//! // Server: check token, adopt the proposed session ID
+//! let accept = server.process_early_hello(&hello, &replay_guard)?;
+//! ```
+//!
+//! ## Authentication
+//!
+//! A HELLO can carry a pre-shared-key MAC or a bearer token. The responder
+//! checks it with a [`HelloAuthenticator`] and attaches the authenticated
+//! [`Principal`] to the session (requires the `crypto` feature).
+//!
+//! ```rust,ignore
+//! let auth = HelloAuthenticator::new().with_psk("k1", key.clone(), Principal::new("billing"))?;
+//! let mut server = Session::new(caps).with_authenticator(Arc::new(auth));
+//! let mut client = Session::new(caps).with_credential(HelloCredential::psk("k1", key)?);
+//!
+//! server.process_hello(&client.create_hello())?;
+//! assert_eq!(server.principal().unwrap().name, "billing");
+//! ```
+//!
 //! ## Data Exchange
 //!
 //! ```rust,ignore
@@ -95,17 +134,83 @@
 //! let data_msg = session.compress(r#"{"model":"gpt-4o"}"#)?;
 //! let content = session.decompress(&incoming_data)?;
 //! ```
+//!
+//! ## Multiplexing
+//!
+//! Several sessions can share one connection: [`SessionMux`] tags each
+//! message with a channel and routes inbound messages to that channel's
+//! session.
+//!
+//! ## Flow Control
+//!
+//! Slow consumers advertise a receive window; see [`FlowWindow`].
+//!
+//! ```rust,ignore
+//! let caps = Capabilities::default().with_receive_window(FlowWindow::new(16, 1 << 20));
+//!
+//! // Receiver: return credit once messages are processed
+//! let content = session.decompress(&incoming_data)?;
+//! if let Some(update) = session.window_update() {
+//!     send(update);
+//! }
+//! ```
+//!
+//! ## Version Upgrade
+//!
+//! Sessions run at the highest minor version both agents speak; an
+//! established session can move to a newer one with UPGRADE. See
+//! [`ProtocolVersion`].
+//!
+//! ```rust,ignore
+//! let upgrade = session.upgrade(ProtocolVersion::V3_1)?;
+//! // The peer's process_message() answers with the agreed version
+//! session.process_message(&reply)?;
+//! ```
 
+mod auth;
 mod capabilities;
+mod dedup;
+mod early;
+mod extensions;
+mod flow;
 mod message;
+mod mux;
+mod policy;
 mod session;
+mod version;
 
-pub use capabilities::{Capabilities, CompressionCaps, NegotiatedCaps, SecurityCaps};
-pub use message::{Message, MessageType, RejectionCode, RejectionInfo};
-pub use session::{Session, SessionState, SessionStats};
+pub use auth::{HelloAuth, Principal, AUTH_WINDOW_SECS, MIN_AUTH_KEY_LEN};
+#[cfg(feature = "crypto")]
+pub use auth::{HelloAuthenticator, HelloCredential};
+pub use capabilities::{
+    AlgorithmParams, Capabilities, CompressionCaps, KeyExchangeSuite, NegotiatedCaps, SecurityCaps,
+};
+pub use dedup::DEFAULT_DEDUP_CAPACITY;
+pub use early::{EarlyData, ReplayGuard, DEFAULT_REPLAY_CACHE_SIZE, EARLY_DATA_WINDOW_SECS};
+pub use extensions::{
+    AbbreviationTables, AuditEscrow, Extension, ExtensionRegistry, IdleTimeout, KeepaliveInterval,
+    KeepaliveTimeout, MaxFrameSize, MaxPayloadSize, Negotiation, PreferredCipher,
+    SharedDictionaries, TenantId,
+};
+pub use flow::FlowWindow;
+pub use message::{
+    BroadcastPayload, CloseInfo, CloseReason, ContextPinInfo, DictionaryPayload, Message,
+    MessageType, RejectionCode, RejectionInfo, RekeyInfo, RelayHeader, UpgradeInfo,
+    DEFAULT_RETRY_AFTER_SECS,
+};
+pub use mux::{MuxEvent, SessionMux, DEFAULT_MAX_CHANNELS};
+pub use policy::{MessagePolicy, PolicyContext, PolicyOutcome};
+pub use session::{Session, SessionSnapshot, SessionState, SessionStats};
+pub use version::ProtocolVersion;
 
 /// Protocol version
 pub const PROTOCOL_VERSION: &str = "3.0";
 
-/// Maximum session idle time (5 minutes)
+/// Default maximum session idle time (5 minutes, see [`IdleTimeout`])
 pub const SESSION_TIMEOUT_SECS: u64 = 300;
+
+/// Default idle time before sending a PING (1 minute, see [`KeepaliveInterval`])
+pub const KEEPALIVE_INTERVAL_SECS: u64 = 60;
+
+/// Default time to wait for a PONG (10 seconds, see [`KeepaliveTimeout`])
+pub const KEEPALIVE_TIMEOUT_SECS: u64 = 10;
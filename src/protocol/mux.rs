@@ -0,0 +1,343 @@
+//! Session multiplexing over a shared connection.
+//!
+//! An agent gateway talking to many peers through one upstream does not
+//! need one TCP/QUIC connection per session. Every message carries a
+//! `channel` in its envelope, and a [`SessionMux`] on each end keeps one
+//! independent [`Session`] per channel: its own handshake, negotiated
+//! algorithm, flow-control window and statistics.
+//!
+//! # Channel Allocation
+//!
+//! Like HTTP/2 stream IDs, channels are allocated by parity so both ends
+//! can open sessions without coordination: the connection initiator uses
+//! odd channels, the responder even ones. A HELLO on a channel of the wrong
+//! parity, or on a channel already in use, is rejected.
+//!
+//! # Usage
+//!
+//! ```rust,ignore
+//! use m2m::protocol::{Capabilities, MuxEvent, SessionMux};
+//! use m2m::transport::FramedConnection;
+//!
+//! let mut mux = SessionMux::initiator(Capabilities::default());
+//! let mut conn = FramedConnection::new(stream);
+//!
+//! let (channel, hello) = mux.open();
+//! conn.send(&hello).await?;
+//!
+//! while let Some(message) = conn.recv().await? {
+//!     match mux.handle(&message)? {
+//!         MuxEvent::Established(ch) => conn.send(&mux.compress(ch, payload)?).await?,
+//!         MuxEvent::Data { channel, content } => { /* route to the peer */ },
+//!         MuxEvent::Reply(reply) | MuxEvent::Accepted { reply, .. } => conn.send(&reply).await?,
+//!         _ => {},
+//!     }
+//! }
+//! ```
+
+use std::collections::BTreeMap;
+
+use super::{Capabilities, Message, MessageType, RejectionCode, Session};
+use crate::error::{M2MError, Result};
+
+/// Default maximum concurrent channels per connection
+pub const DEFAULT_MAX_CHANNELS: usize = 256;
+
+/// Outcome of an inbound message routed by [`SessionMux::handle`]
+#[derive(Debug, Clone)]
+pub enum MuxEvent {
+    /// Send this message back on the connection
+    Reply(Message),
+    /// The peer opened a session; send `reply` (ACCEPT)
+    Accepted {
+        /// Channel of the new session
+        channel: u32,
+        /// ACCEPT to send back
+        reply: Message,
+    },
+    /// A session opened with [`SessionMux::open`] was accepted
+    Established(u32),
+    /// A session opened with [`SessionMux::open`] was rejected
+    Rejected(u32),
+    /// Decompressed DATA from the peer
+    Data {
+        /// Channel the data arrived on
+        channel: u32,
+        /// Decompressed content
+        content: String,
+    },
+    /// The peer closed a channel
+    Closed(u32),
+    /// Processed; nothing to report
+    Handled,
+}
+
+/// Independent sessions sharing one connection
+pub struct SessionMux {
+    /// Capabilities for every session on this end
+    capabilities: Capabilities,
+    /// Sessions by channel
+    sessions: BTreeMap<u32, Session>,
+    /// Next channel this end allocates
+    next_channel: u32,
+    /// Maximum concurrent channels
+    max_channels: usize,
+}
+
+impl SessionMux {
+    /// Multiplexer for the end that opened the connection (odd channels)
+    pub fn initiator(capabilities: Capabilities) -> Self {
+        Self::with_first_channel(capabilities, 1)
+    }
+
+    /// Multiplexer for the end that accepted the connection (even channels)
+    pub fn responder(capabilities: Capabilities) -> Self {
+        Self::with_first_channel(capabilities, 2)
+    }
+
+    fn with_first_channel(capabilities: Capabilities, first: u32) -> Self {
+        Self {
+            capabilities,
+            sessions: BTreeMap::new(),
+            next_channel: first,
+            max_channels: DEFAULT_MAX_CHANNELS,
+        }
+    }
+
+    /// Set maximum concurrent channels (default: 256)
+    pub fn with_max_channels(mut self, max: usize) -> Self {
+        self.max_channels = max;
+        self
+    }
+
+    /// Open a session on a new channel
+    ///
+    /// Returns the channel and the HELLO to send.
+    pub fn open(&mut self) -> (u32, Message) {
+        let channel = self.next_channel;
+        self.next_channel = self.next_channel.wrapping_add(2);
+
+        let mut session = Session::new(self.capabilities.clone());
+        let hello = session.create_hello().with_channel(channel);
+        self.sessions.insert(channel, session);
+        (channel, hello)
+    }
+
+    /// Compress content as DATA on a channel
+    pub fn compress(&mut self, channel: u32, content: &str) -> Result<Message> {
+        Ok(self
+            .session_mut(channel)
+            .ok_or_else(|| unknown_channel(channel))?
+            .compress(content)?
+            .with_channel(channel))
+    }
+
+    /// Close a channel, returning the CLOSE to send
+    pub fn close(&mut self, channel: u32) -> Option<Message> {
+        self.sessions
+            .remove(&channel)
+            .map(|mut session| session.close().with_channel(channel))
+    }
+
+    /// Route an inbound message to its channel's session
+    pub fn handle(&mut self, message: &Message) -> Result<MuxEvent> {
+        let channel = message.channel.ok_or_else(|| {
+            M2MError::InvalidMessage("Multiplexed message missing channel".to_string())
+        })?;
+
+        if message.msg_type == MessageType::Hello {
+            return Ok(self.accept(channel, message));
+        }
+
+        let session = self
+            .sessions
+            .get_mut(&channel)
+            .ok_or_else(|| unknown_channel(channel))?;
+
+        match message.msg_type {
+            MessageType::Accept => {
+                session.process_accept(message)?;
+                Ok(MuxEvent::Established(channel))
+            },
+            MessageType::Reject => {
+                session.process_reject(message)?;
+                self.sessions.remove(&channel);
+                Ok(MuxEvent::Rejected(channel))
+            },
+            MessageType::Data => match session.decompress(message) {
+                Ok(content) => Ok(MuxEvent::Data { channel, content }),
+                Err(M2MError::FragmentPending { .. }) => Ok(MuxEvent::Handled),
+                Err(e) => Err(e),
+            },
+            MessageType::Close => {
+                self.sessions.remove(&channel);
+                Ok(MuxEvent::Closed(channel))
+            },
+            _ => Ok(match session.process_message(message)? {
+                Some(reply) => MuxEvent::Reply(reply.with_channel(channel)),
+                None => MuxEvent::Handled,
+            }),
+        }
+    }
+
+    /// Answer a HELLO opening a channel
+    fn accept(&mut self, channel: u32, hello: &Message) -> MuxEvent {
+        let reject = |code, reason: &str| {
+            MuxEvent::Reply(Message::reject(code, reason).with_channel(channel))
+        };
+
+        if channel % 2 == self.next_channel % 2 {
+            return reject(
+                RejectionCode::Unknown,
+                &format!("Channel {channel} is allocated by this end"),
+            );
+        }
+        if self.sessions.contains_key(&channel) {
+            return reject(
+                RejectionCode::Unknown,
+                &format!("Channel {channel} is already open"),
+            );
+        }
+        if self.sessions.len() >= self.max_channels {
+            return reject(
+                RejectionCode::RateLimited,
+                &format!("Channel limit of {} reached", self.max_channels),
+            );
+        }
+
+        let mut session = Session::new(self.capabilities.clone());
+        match session.process_hello(hello) {
+            Ok(reply) if reply.msg_type == MessageType::Accept => {
+                self.sessions.insert(channel, session);
+                MuxEvent::Accepted {
+                    channel,
+                    reply: reply.with_channel(channel),
+                }
+            },
+            Ok(reply) => MuxEvent::Reply(reply.with_channel(channel)),
+            Err(e) => MuxEvent::Reply(Message::reject_error(&e).with_channel(channel)),
+        }
+    }
+
+    /// Session on a channel
+    pub fn session(&self, channel: u32) -> Option<&Session> {
+        self.sessions.get(&channel)
+    }
+
+    /// Mutable session on a channel
+    pub fn session_mut(&mut self, channel: u32) -> Option<&mut Session> {
+        self.sessions.get_mut(&channel)
+    }
+
+    /// Open channels, in ascending order
+    pub fn channels(&self) -> Vec<u32> {
+        self.sessions.keys().copied().collect()
+    }
+
+    /// Number of open channels
+    pub fn len(&self) -> usize {
+        self.sessions.len()
+    }
+
+    /// Check if no channels are open
+    pub fn is_empty(&self) -> bool {
+        self.sessions.is_empty()
+    }
+}
+
+fn unknown_channel(channel: u32) -> M2MError {
+    M2MError::Protocol(format!("Unknown channel {channel}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Deliver `message` to `mux` and return the reply it asks to send
+    fn reply(mux: &mut SessionMux, message: &Message) -> Message {
+        match mux.handle(message).unwrap() {
+            MuxEvent::Reply(reply) | MuxEvent::Accepted { reply, .. } => reply,
+            other => panic!("expected a reply, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_independent_sessions_on_one_connection() {
+        let mut client = SessionMux::initiator(Capabilities::default());
+        let mut server = SessionMux::responder(Capabilities::default());
+
+        let (first, hello_a) = client.open();
+        let (second, hello_b) = client.open();
+        assert_eq!((first, second), (1, 3));
+
+        for hello in [hello_a, hello_b] {
+            let accept = reply(&mut server, &hello);
+            assert_eq!(accept.msg_type, MessageType::Accept);
+            assert!(matches!(
+                client.handle(&accept).unwrap(),
+                MuxEvent::Established(_)
+            ));
+        }
+        assert_eq!(server.channels(), vec![1, 3]);
+
+        let data = client.compress(second, r#"{"model":"gpt-4o"}"#).unwrap();
+        assert_eq!(data.channel, Some(3));
+        match server.handle(&data).unwrap() {
+            MuxEvent::Data { channel, content } => {
+                assert_eq!(channel, 3);
+                assert_eq!(content, r#"{"model":"gpt-4o"}"#);
+            },
+            other => panic!("expected data, got {other:?}"),
+        }
+        assert_eq!(server.session(3).unwrap().stats().messages_received, 2);
+        assert_eq!(server.session(1).unwrap().stats().messages_received, 1);
+
+        // Server opens its own channel back
+        let (back, hello) = server.open();
+        assert_eq!(back, 2);
+        let accept = reply(&mut client, &hello);
+        assert!(matches!(
+            server.handle(&accept).unwrap(),
+            MuxEvent::Established(2)
+        ));
+
+        // Closing one channel leaves the others
+        let close = client.close(first).unwrap();
+        assert!(matches!(
+            server.handle(&close).unwrap(),
+            MuxEvent::Closed(1)
+        ));
+        assert_eq!(server.channels(), vec![2, 3]);
+    }
+
+    #[test]
+    fn test_channel_rules() {
+        let mut client = SessionMux::initiator(Capabilities::default());
+        let mut server = SessionMux::responder(Capabilities::default()).with_max_channels(1);
+
+        let (_, hello) = client.open();
+        reply(&mut server, &hello);
+
+        // Reused channel
+        let rejected = reply(&mut server, &hello);
+        assert_eq!(rejected.msg_type, MessageType::Reject);
+
+        // Wrong parity
+        let wrong = hello.clone().with_channel(4);
+        assert_eq!(reply(&mut server, &wrong).msg_type, MessageType::Reject);
+
+        // Limit reached
+        let (_, hello) = client.open();
+        let limited = reply(&mut server, &hello);
+        assert_eq!(
+            limited.get_rejection().unwrap().code,
+            RejectionCode::RateLimited
+        );
+
+        // Missing or unknown channel
+        let mut untagged = hello.clone();
+        untagged.channel = None;
+        assert!(server.handle(&untagged).is_err());
+        assert!(server.handle(&Message::ping("x").with_channel(9)).is_err());
+    }
+}
@@ -0,0 +1,144 @@
+//! Protocol version negotiation.
+//!
+//! Versions are `major.minor`. Agents with different majors cannot talk;
+//! within a major, a session runs at the highest minor both agents speak,
+//! so a 3.1 agent and a 3.0 agent settle on 3.0 instead of rejecting each
+//! other. Minor versions only add optional behavior:
+//!
+//! | Version | Adds                                            |
+//! |---------|-------------------------------------------------|
+//! | 3.0     | Baseline                                        |
+//! | 3.1     | `field_aead` frames (selective field encryption) |
+//!
+//! An established session can move to a newer minor without a new
+//! handshake by exchanging UPGRADE messages:
+//!
+//! ```text
+//! Agent A (now 3.1)                 Agent B (3.1)
+//!    |  ... DATA at 3.0 ...           |
+//!    |------- UPGRADE {3.1} -------->|  B replies, then sends at 3.1
+//!    |<------ UPGRADE {3.1} ---------|  A sends at 3.1 from here on
+//! ```
+//!
+//! The responder answers with the highest version both sides speak (which
+//! may be the current one) and switches right after its reply; the
+//! initiator switches on receiving it. Frames carry their own security
+//! mode, so messages in flight across the switch still decode.
+
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::codec::m2m::SecurityMode;
+use crate::error::M2MError;
+
+/// Protocol version (`major.minor`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct ProtocolVersion {
+    /// Incompatible wire changes
+    pub major: u16,
+    /// Backward-compatible additions
+    pub minor: u16,
+}
+
+impl ProtocolVersion {
+    /// Baseline v3 protocol
+    pub const V3_0: Self = Self::new(3, 0);
+
+    /// Adds [`SecurityMode::FieldAead`]
+    pub const V3_1: Self = Self::new(3, 1);
+
+    /// Newest version this implementation speaks
+    pub const LATEST: Self = Self::V3_1;
+
+    /// Create a version
+    pub const fn new(major: u16, minor: u16) -> Self {
+        Self { major, minor }
+    }
+
+    /// Highest version both sides speak (`None` if the majors differ)
+    pub fn negotiate(self, other: Self) -> Option<Self> {
+        (self.major == other.major).then(|| self.min(other))
+    }
+
+    /// Check if frames of `mode` may be sent at this version
+    pub fn supports(self, mode: SecurityMode) -> bool {
+        match mode {
+            SecurityMode::FieldAead => self >= Self::V3_1,
+            _ => true,
+        }
+    }
+}
+
+impl Default for ProtocolVersion {
+    fn default() -> Self {
+        Self::V3_0
+    }
+}
+
+impl fmt::Display for ProtocolVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
+
+impl FromStr for ProtocolVersion {
+    type Err = M2MError;
+
+    /// Parse `major.minor` (a bare `major` means minor 0)
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || M2MError::InvalidMessage(format!("Invalid protocol version: {s:?}"));
+        let (major, minor) = s.split_once('.').unwrap_or((s, "0"));
+        Ok(Self {
+            major: major.parse().map_err(|_| invalid())?,
+            minor: minor.parse().map_err(|_| invalid())?,
+        })
+    }
+}
+
+impl TryFrom<String> for ProtocolVersion {
+    type Error = M2MError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<ProtocolVersion> for String {
+    fn from(version: ProtocolVersion) -> Self {
+        version.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_negotiate() {
+        assert_eq!(
+            "3.1".parse::<ProtocolVersion>().unwrap(),
+            ProtocolVersion::V3_1
+        );
+        assert_eq!(
+            "3".parse::<ProtocolVersion>().unwrap(),
+            ProtocolVersion::V3_0
+        );
+        assert!("3.x".parse::<ProtocolVersion>().is_err());
+        assert!("".parse::<ProtocolVersion>().is_err());
+        assert_eq!(ProtocolVersion::V3_1.to_string(), "3.1");
+
+        let v3_0 = ProtocolVersion::V3_0;
+        let v3_1 = ProtocolVersion::V3_1;
+        assert_eq!(v3_0.negotiate(v3_1), Some(v3_0));
+        assert_eq!(v3_1.negotiate(v3_0), Some(v3_0));
+        assert_eq!(v3_1.negotiate(v3_1), Some(v3_1));
+        assert_eq!(v3_1.negotiate(ProtocolVersion::new(4, 0)), None);
+
+        assert!(!v3_0.supports(SecurityMode::FieldAead));
+        assert!(v3_0.supports(SecurityMode::Aead));
+        assert!(v3_1.supports(SecurityMode::FieldAead));
+    }
+}
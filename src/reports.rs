@@ -0,0 +1,491 @@
+//! Compression savings reports for billing and chargeback.
+//!
+//! A [`SavingsReport`] groups [`UsageRecord`]s into daily or weekly
+//! buckets (UTC, weeks starting Monday) and prices the tokens compression
+//! saved with each model's input pricing. Every bucket breaks the savings
+//! down per model and per tenant, and renders as JSON or Markdown.
+//!
+//! Records come from whatever the deployment keeps:
+//!
+//! | Source | Conversion | Tokens |
+//! |--------|------------|--------|
+//! | Server stats rollups (`/stats/history`) | [`UsageRecord::from_rollup`] | Estimated from bytes |
+//! | Audit log records | [`UsageRecord::from_audit`] | Counted (cl100k) |
+//! | Session statistics | [`UsageRecord::from_session`] | Estimated from bytes |
+//!
+//! Estimates assume ~4 bytes per token. Records without a model are listed
+//! as `unknown` and priced at zero rather than guessed.
+//!
+//! The server serves reports at `GET /admin/report`; the CLI builds them
+//! from an audit log with `m2m report`.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use m2m::reports::{ReportPeriod, SavingsReport, UsageRecord};
+//!
+//! let records = rollups.iter().flat_map(UsageRecord::from_rollup);
+//! let report = SavingsReport::build(ReportPeriod::Weekly, records);
+//! println!("{}", report.to_markdown());
+//! ```
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::codec::m2m::estimate_cost;
+use crate::error::M2MError;
+use crate::protocol::SessionStats;
+use crate::server::{AuditRecord, StatsRollup};
+
+/// Line item name for records without a model or tenant
+pub const UNKNOWN: &str = "unknown";
+
+/// Bytes per token assumed when only byte counts are known
+const BYTES_PER_TOKEN: u64 = 4;
+
+const SECS_PER_DAY: u64 = 86_400;
+
+/// Length of a report bucket
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportPeriod {
+    /// One bucket per UTC day
+    #[default]
+    Daily,
+    /// One bucket per week, starting Monday 00:00 UTC
+    Weekly,
+}
+
+impl ReportPeriod {
+    /// Start of the bucket containing `timestamp` (Unix seconds)
+    pub fn bucket_start(self, timestamp: u64) -> u64 {
+        let day = timestamp / SECS_PER_DAY;
+        let first_day = match self {
+            Self::Daily => day,
+            // 1970-01-01 was a Thursday, three days after a Monday
+            Self::Weekly => day - (day + 3) % 7,
+        };
+        first_day * SECS_PER_DAY
+    }
+
+    /// Bucket length in seconds
+    pub fn secs(self) -> u64 {
+        match self {
+            Self::Daily => SECS_PER_DAY,
+            Self::Weekly => 7 * SECS_PER_DAY,
+        }
+    }
+}
+
+impl FromStr for ReportPeriod {
+    type Err = M2MError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "daily" | "day" => Ok(Self::Daily),
+            "weekly" | "week" => Ok(Self::Weekly),
+            other => Err(M2MError::Config(format!(
+                "Unknown report period '{other}' (expected daily or weekly)"
+            ))),
+        }
+    }
+}
+
+/// Output format of a report
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportFormat {
+    /// Pretty-printed JSON
+    #[default]
+    Json,
+    /// Markdown tables
+    Markdown,
+}
+
+impl ReportFormat {
+    /// MIME type of the rendered report
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Self::Json => "application/json",
+            Self::Markdown => "text/markdown; charset=utf-8",
+        }
+    }
+}
+
+impl FromStr for ReportFormat {
+    type Err = M2MError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "json" => Ok(Self::Json),
+            "markdown" | "md" => Ok(Self::Markdown),
+            other => Err(M2MError::Config(format!(
+                "Unknown report format '{other}' (expected json or markdown)"
+            ))),
+        }
+    }
+}
+
+/// Traffic attributed to one model and tenant at one time
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct UsageRecord {
+    /// When the traffic happened (Unix seconds)
+    pub timestamp: u64,
+    /// Model the requests targeted
+    pub model: Option<String>,
+    /// Tenant the requests belong to
+    pub tenant: Option<String>,
+    /// Requests
+    pub requests: u64,
+    /// Original bytes
+    pub original_bytes: u64,
+    /// Compressed bytes
+    pub compressed_bytes: u64,
+    /// Original tokens
+    pub original_tokens: u64,
+    /// Compressed tokens
+    pub compressed_tokens: u64,
+}
+
+impl UsageRecord {
+    /// Records for the per-model breakdown of a stats rollup
+    ///
+    /// Compressed requests whose payload named no model are reported
+    /// under [`UNKNOWN`]. Tokens are estimated from bytes.
+    pub fn from_rollup(rollup: &StatsRollup) -> Vec<Self> {
+        let mut records: Vec<Self> = rollup
+            .models
+            .iter()
+            .map(|(model, stats)| {
+                Self::estimated(
+                    rollup.minute,
+                    Some(model.clone()),
+                    stats.requests,
+                    stats.original_bytes,
+                    stats.compressed_bytes,
+                )
+            })
+            .collect();
+
+        let attributed = records.iter().fold((0, 0, 0), |acc, r| {
+            (
+                acc.0 + r.requests,
+                acc.1 + r.original_bytes,
+                acc.2 + r.compressed_bytes,
+            )
+        });
+        let compressed_requests: u64 = rollup.algorithms.values().map(|a| a.requests).sum();
+        if compressed_requests > attributed.0 {
+            records.push(Self::estimated(
+                rollup.minute,
+                None,
+                compressed_requests - attributed.0,
+                rollup.original_bytes.saturating_sub(attributed.1),
+                rollup.compressed_bytes.saturating_sub(attributed.2),
+            ));
+        }
+        records
+    }
+
+    /// Record for one audited request (`None` if it was not compressed)
+    pub fn from_audit(record: &AuditRecord) -> Option<Self> {
+        Some(Self {
+            timestamp: record.timestamp / 1000,
+            model: record.model.clone(),
+            tenant: None,
+            requests: 1,
+            original_bytes: record.original_bytes as u64,
+            compressed_bytes: record.compressed_bytes? as u64,
+            original_tokens: record.original_tokens as u64,
+            compressed_tokens: record.compressed_tokens? as u64,
+        })
+    }
+
+    /// Record for a session's traffic so far, attributed to `model`
+    ///
+    /// Tokens are estimated from bytes.
+    pub fn from_session(stats: &SessionStats, timestamp: u64, model: Option<&str>) -> Self {
+        Self::estimated(
+            timestamp,
+            model.map(str::to_string),
+            stats.messages_sent,
+            stats.bytes_compressed + stats.bytes_saved,
+            stats.bytes_compressed,
+        )
+    }
+
+    /// Attribute the record to a tenant
+    pub fn with_tenant(mut self, tenant: impl Into<String>) -> Self {
+        self.tenant = Some(tenant.into());
+        self
+    }
+
+    fn estimated(
+        timestamp: u64,
+        model: Option<String>,
+        requests: u64,
+        original_bytes: u64,
+        compressed_bytes: u64,
+    ) -> Self {
+        Self {
+            timestamp,
+            model,
+            tenant: None,
+            requests,
+            original_bytes,
+            compressed_bytes,
+            original_tokens: original_bytes / BYTES_PER_TOKEN,
+            compressed_tokens: compressed_bytes / BYTES_PER_TOKEN,
+        }
+    }
+
+    /// Tokens saved (negative if compression added tokens)
+    pub fn tokens_saved(&self) -> i64 {
+        self.original_tokens as i64 - self.compressed_tokens as i64
+    }
+
+    /// Input cost of the saved tokens (USD; zero without a model)
+    pub fn usd_saved(&self) -> f64 {
+        let Some(model) = &self.model else {
+            return 0.0;
+        };
+        let tokens = self.tokens_saved();
+        let cost = f64::from(estimate_cost(
+            model,
+            u32::try_from(tokens.unsigned_abs()).unwrap_or(u32::MAX),
+            0,
+        ));
+        if tokens < 0 {
+            -cost
+        } else {
+            cost
+        }
+    }
+}
+
+/// Savings of one model or tenant within a bucket
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct LineItem {
+    /// Model or tenant name
+    pub name: String,
+    /// Requests
+    pub requests: u64,
+    /// Bytes saved
+    pub bytes_saved: i64,
+    /// Tokens saved
+    pub tokens_saved: i64,
+    /// Estimated input cost saved (USD)
+    pub usd_saved: f64,
+}
+
+impl LineItem {
+    fn add(&mut self, record: &UsageRecord, usd: f64) {
+        self.requests += record.requests;
+        self.bytes_saved += record.original_bytes as i64 - record.compressed_bytes as i64;
+        self.tokens_saved += record.tokens_saved();
+        self.usd_saved += usd;
+    }
+}
+
+/// Savings over one day or week
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ReportBucket {
+    /// Bucket start (Unix seconds, inclusive)
+    pub start: u64,
+    /// Bucket end (Unix seconds, exclusive)
+    pub end: u64,
+    /// Totals over the bucket
+    pub total: LineItem,
+    /// Per-model savings, largest first
+    pub models: Vec<LineItem>,
+    /// Per-tenant savings, largest first
+    pub tenants: Vec<LineItem>,
+}
+
+/// Savings grouped by period
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SavingsReport {
+    /// Bucket length
+    pub period: ReportPeriod,
+    /// Buckets with traffic, oldest first
+    pub buckets: Vec<ReportBucket>,
+    /// Totals over the whole report
+    pub total: LineItem,
+}
+
+impl SavingsReport {
+    /// Group and price `records`
+    pub fn build(period: ReportPeriod, records: impl IntoIterator<Item = UsageRecord>) -> Self {
+        type Items = BTreeMap<String, LineItem>;
+        let mut buckets: BTreeMap<u64, (LineItem, Items, Items)> = BTreeMap::new();
+        let mut total = LineItem {
+            name: "total".to_string(),
+            ..LineItem::default()
+        };
+
+        for record in records {
+            let usd = record.usd_saved();
+            let (bucket_total, models, tenants) = buckets
+                .entry(period.bucket_start(record.timestamp))
+                .or_default();
+            bucket_total.add(&record, usd);
+            total.add(&record, usd);
+            for (items, name) in [(models, &record.model), (tenants, &record.tenant)] {
+                let name = name.as_deref().unwrap_or(UNKNOWN);
+                items
+                    .entry(name.to_string())
+                    .or_insert_with(|| LineItem {
+                        name: name.to_string(),
+                        ..LineItem::default()
+                    })
+                    .add(&record, usd);
+            }
+        }
+
+        let sorted = |items: Items| {
+            let mut items: Vec<LineItem> = items.into_values().collect();
+            items.sort_by(|a, b| {
+                b.usd_saved
+                    .total_cmp(&a.usd_saved)
+                    .then(b.tokens_saved.cmp(&a.tokens_saved))
+            });
+            items
+        };
+        let buckets = buckets
+            .into_iter()
+            .map(|(start, (mut bucket_total, models, tenants))| {
+                bucket_total.name = "total".to_string();
+                ReportBucket {
+                    start,
+                    end: start + period.secs(),
+                    total: bucket_total,
+                    models: sorted(models),
+                    tenants: sorted(tenants),
+                }
+            })
+            .collect();
+
+        Self {
+            period,
+            buckets,
+            total,
+        }
+    }
+
+    /// Render the report in `format`
+    pub fn render(&self, format: ReportFormat) -> String {
+        match format {
+            ReportFormat::Json => self.to_json(),
+            ReportFormat::Markdown => self.to_markdown(),
+        }
+    }
+
+    /// Report as pretty-printed JSON
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+
+    /// Report as a Markdown document
+    pub fn to_markdown(&self) -> String {
+        let mut out = String::new();
+        let period = match self.period {
+            ReportPeriod::Daily => "Daily",
+            ReportPeriod::Weekly => "Weekly",
+        };
+        let _ = writeln!(out, "# {period} Compression Savings\n");
+        let _ = writeln!(
+            out,
+            "**Total:** {} requests, {} tokens saved, ${:.2} saved\n",
+            self.total.requests, self.total.tokens_saved, self.total.usd_saved
+        );
+
+        for bucket in &self.buckets {
+            let _ = writeln!(out, "## {}\n", date(bucket.start));
+            for (title, items) in [("Model", &bucket.models), ("Tenant", &bucket.tenants)] {
+                let _ = writeln!(out, "| {title} | Requests | Tokens saved | USD saved |");
+                let _ = writeln!(out, "|---|---:|---:|---:|");
+                for item in items.iter().chain([&bucket.total]) {
+                    let _ = writeln!(
+                        out,
+                        "| {} | {} | {} | ${:.2} |",
+                        item.name, item.requests, item.tokens_saved, item.usd_saved
+                    );
+                }
+                out.push('\n');
+            }
+        }
+        out
+    }
+}
+
+/// `YYYY-MM-DD` of a Unix timestamp (UTC)
+fn date(timestamp: u64) -> String {
+    i64::try_from(timestamp)
+        .ok()
+        .and_then(|secs| chrono::DateTime::from_timestamp(secs, 0))
+        .map(|dt| dt.format("%Y-%m-%d").to_string())
+        .unwrap_or_else(|| timestamp.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 2026-01-05 is a Monday
+    const MONDAY: u64 = 1_767_571_200;
+
+    fn record(day: u64, model: Option<&str>, tenant: &str) -> UsageRecord {
+        UsageRecord {
+            timestamp: MONDAY + day * SECS_PER_DAY + 3600,
+            model: model.map(str::to_string),
+            tenant: Some(tenant.to_string()),
+            requests: 10,
+            original_bytes: 40_000,
+            compressed_bytes: 16_000,
+            original_tokens: 10_000,
+            compressed_tokens: 4_000,
+        }
+    }
+
+    #[test]
+    fn test_daily_and_weekly_buckets() {
+        let records = vec![
+            record(0, Some("gpt-4o"), "acme"),
+            record(0, Some("gpt-4o-mini"), "globex"),
+            record(2, None, "acme"),
+            record(7, Some("gpt-4o"), "acme"),
+        ];
+
+        let daily = SavingsReport::build(ReportPeriod::Daily, records.clone());
+        assert_eq!(daily.buckets.len(), 3);
+        assert_eq!(daily.buckets[0].start, MONDAY);
+        assert_eq!(daily.buckets[0].models[0].name, "gpt-4o");
+        assert_eq!(daily.total.tokens_saved, 24_000);
+
+        let weekly = SavingsReport::build(ReportPeriod::Weekly, records);
+        assert_eq!(weekly.buckets.len(), 2);
+        let week = &weekly.buckets[0];
+        assert_eq!((week.start, week.end), (MONDAY, MONDAY + 7 * SECS_PER_DAY));
+        assert_eq!(week.tenants[0].name, "acme");
+        assert_eq!(week.tenants[0].requests, 20);
+        let unknown = week.models.iter().find(|m| m.name == UNKNOWN).unwrap();
+        assert!(unknown.usd_saved.abs() < f64::EPSILON);
+        assert!(week.total.usd_saved > 0.0);
+        assert!(
+            (week.total.usd_saved + weekly.buckets[1].total.usd_saved - weekly.total.usd_saved)
+                .abs()
+                < 1e-9
+        );
+
+        let markdown = weekly.to_markdown();
+        assert!(markdown.contains("## 2026-01-05"));
+        assert!(markdown.contains("| gpt-4o | 10 | 6000 |"));
+        assert_eq!(
+            "week".parse::<ReportPeriod>().unwrap(),
+            ReportPeriod::Weekly
+        );
+    }
+}
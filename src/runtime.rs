@@ -0,0 +1,239 @@
+//! Async runtime diagnostics.
+//!
+//! The server runs compression on the blocking pool and spawns tasks for
+//! connections and webhooks. When the pipeline stalls, operators need to
+//! see which tasks are alive and how deep the queues are.
+//!
+//! - Tasks are spawned with [`spawn_named`] and [`spawn_blocking_named`],
+//!   so they show up by name in tokio-console (when built with
+//!   `RUSTFLAGS="--cfg tokio_unstable"`) and in tracing spans otherwise.
+//! - [`RuntimeMetrics::current`] snapshots the runtime's task and queue
+//!   counters; the server reports it under `runtime` in `/status`.
+//! - The `console` feature adds [`console_layer`], a tracing layer serving
+//!   the tokio-console gRPC API (default `127.0.0.1:6669`, see the
+//!   `TOKIO_CONSOLE_*` environment variables).
+//!
+//! ```bash
+//! RUSTFLAGS="--cfg tokio_unstable" cargo run --features console -- server
+//! tokio-console
+//! ```
+//!
+//! - The `otlp` feature adds [`otlp_layer`], a tracing layer exporting
+//!   spans to an OpenTelemetry collector over OTLP/HTTP.
+
+use std::future::Future;
+
+use serde::Serialize;
+use tokio::task::JoinHandle;
+use tracing::Instrument;
+
+/// Spawn a named task on the current runtime
+///
+/// # Panics
+///
+/// Panics outside a tokio runtime, like [`tokio::spawn`].
+pub fn spawn_named<F>(name: &'static str, future: F) -> JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    spawn_named_on(&tokio::runtime::Handle::current(), name, future)
+}
+
+/// Spawn a named task on a specific runtime
+pub fn spawn_named_on<F>(
+    runtime: &tokio::runtime::Handle,
+    name: &'static str,
+    future: F,
+) -> JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    let future = future.instrument(tracing::debug_span!("task", name));
+
+    #[cfg(tokio_unstable)]
+    return tokio::task::Builder::new()
+        .name(name)
+        .spawn_on(future, runtime)
+        .expect("spawn on a running runtime");
+
+    #[cfg(not(tokio_unstable))]
+    runtime.spawn(future)
+}
+
+/// Run a named closure on the blocking pool
+///
+/// # Panics
+///
+/// Panics outside a tokio runtime, like [`tokio::task::spawn_blocking`].
+pub fn spawn_blocking_named<F, R>(name: &'static str, f: F) -> JoinHandle<R>
+where
+    F: FnOnce() -> R + Send + 'static,
+    R: Send + 'static,
+{
+    #[cfg(tokio_unstable)]
+    return tokio::task::Builder::new()
+        .name(name)
+        .spawn_blocking(f)
+        .expect("spawn on a running runtime");
+
+    #[cfg(not(tokio_unstable))]
+    {
+        let _ = name;
+        tokio::task::spawn_blocking(f)
+    }
+}
+
+/// Snapshot of the current runtime's counters
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct RuntimeMetrics {
+    /// Worker threads
+    pub workers: usize,
+    /// Tasks currently alive (spawned and not yet finished)
+    pub alive_tasks: usize,
+    /// Tasks waiting in the shared injection queue
+    pub global_queue_depth: usize,
+    /// Total time workers spent polling tasks, in milliseconds
+    pub busy_ms: u64,
+}
+
+impl RuntimeMetrics {
+    /// Counters of the runtime this is called from, if any
+    pub fn current() -> Option<Self> {
+        let metrics = tokio::runtime::Handle::try_current().ok()?.metrics();
+        let workers = metrics.num_workers();
+
+        let mut snapshot = Self {
+            workers,
+            alive_tasks: metrics.num_alive_tasks(),
+            global_queue_depth: metrics.global_queue_depth(),
+            ..Self::default()
+        };
+        for worker in 0..workers {
+            snapshot.busy_ms +=
+                u64::try_from(metrics.worker_total_busy_duration(worker).as_millis())
+                    .unwrap_or(u64::MAX);
+        }
+        Some(snapshot)
+    }
+}
+
+/// Tracing layer serving the tokio-console API
+///
+/// Combine it with the usual formatting layer:
+///
+/// ```rust,ignore
+/// use tracing_subscriber::prelude::*;
+///
+/// tracing_subscriber::registry()
+///     .with(m2m::runtime::console_layer())
+///     .with(tracing_subscriber::fmt::layer())
+///     .init();
+/// ```
+#[cfg(feature = "console")]
+pub fn console_layer<S>() -> impl tracing_subscriber::Layer<S>
+where
+    S: tracing::Subscriber + for<'a> tracing_subscriber::registry::LookupSpan<'a>,
+{
+    console_subscriber::spawn()
+}
+
+/// Flushes and stops the OTLP exporter when dropped
+#[cfg(feature = "otlp")]
+#[derive(Debug)]
+pub struct OtlpGuard(opentelemetry_sdk::trace::SdkTracerProvider);
+
+#[cfg(feature = "otlp")]
+impl Drop for OtlpGuard {
+    fn drop(&mut self) {
+        if let Err(e) = self.0.shutdown() {
+            tracing::warn!("OTLP exporter shutdown failed: {e}");
+        }
+    }
+}
+
+/// Tracing layer exporting spans to an OpenTelemetry collector
+///
+/// Spans are sent over OTLP/HTTP to the endpoint in the standard
+/// `OTEL_EXPORTER_OTLP_ENDPOINT` / `OTEL_EXPORTER_OTLP_TRACES_ENDPOINT`
+/// variables (default `http://localhost:4318`), batched on a background
+/// thread. Keep the guard alive while spans should be exported:
+///
+/// ```rust,ignore
+/// use tracing_subscriber::prelude::*;
+///
+/// let (otlp, _guard) = m2m::runtime::otlp_layer("m2m-server")?;
+/// tracing_subscriber::registry()
+///     .with(otlp)
+///     .with(tracing_subscriber::fmt::layer())
+///     .init();
+/// ```
+#[cfg(feature = "otlp")]
+pub fn otlp_layer<S>(
+    service_name: &str,
+) -> crate::error::Result<(impl tracing_subscriber::Layer<S>, OtlpGuard)>
+where
+    S: tracing::Subscriber + for<'a> tracing_subscriber::registry::LookupSpan<'a>,
+{
+    use opentelemetry::trace::TracerProvider as _;
+
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_http()
+        .build()
+        .map_err(|e| crate::error::M2MError::Config(format!("OTLP exporter: {e}")))?;
+    let provider = opentelemetry_sdk::trace::SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(
+            opentelemetry_sdk::Resource::builder()
+                .with_service_name(service_name.to_string())
+                .build(),
+        )
+        .build();
+
+    let layer = tracing_opentelemetry::layer().with_tracer(provider.tracer("m2m"));
+    Ok((layer, OtlpGuard(provider)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_metrics_count_named_tasks() {
+        assert!(RuntimeMetrics::current().unwrap().workers >= 2);
+
+        let (release, wait) = tokio::sync::oneshot::channel::<()>();
+        let task = spawn_named("test-waiter", async move {
+            let _ = wait.await;
+        });
+        assert!(RuntimeMetrics::current().unwrap().alive_tasks >= 1);
+
+        release.send(()).unwrap();
+        task.await.unwrap();
+        assert_eq!(
+            spawn_blocking_named("test-blocking", || 7).await.unwrap(),
+            7
+        );
+    }
+
+    #[test]
+    fn test_no_metrics_outside_runtime() {
+        assert!(RuntimeMetrics::current().is_none());
+    }
+
+    #[test]
+    #[cfg(feature = "otlp")]
+    fn test_otlp_layer_records_spans() {
+        use tracing_subscriber::prelude::*;
+
+        // Nothing listens on the endpoint; export failures must not panic
+        let (otlp, guard) = otlp_layer("m2m-test").unwrap();
+        let subscriber = tracing_subscriber::registry().with(otlp);
+        tracing::subscriber::with_default(subscriber, || {
+            let span = tracing::info_span!("codec.compress", algorithm = "m2m");
+            let _entered = span.enter();
+        });
+        drop(guard);
+    }
+}
@@ -0,0 +1,241 @@
+//! Scan result cache.
+//!
+//! Retries and fan-out send the same payload through the scanner many
+//! times, and each pass runs every regex and, with a model loaded, ML
+//! inference. The cache keys results on a hash of the exact content and
+//! the tenant, so repeats skip both.
+//!
+//! Keys also carry the scanner's configuration fingerprint (rules, policy,
+//! model, thresholds), so a result is never served by a scanner configured
+//! differently from the one that produced it, even when the cache is
+//! shared. Entries expire after a TTL, and [`ScanCache::invalidate`] drops
+//! everything, e.g. after rules are reloaded from disk.
+//!
+//! Content is hashed with a per-cache random key, so colliding payloads
+//! cannot be crafted offline to borrow another payload's verdict.
+
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hash, Hasher};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+use super::scanner::ScanResult;
+
+/// Default number of cached scan results
+pub const DEFAULT_SCAN_CACHE_CAPACITY: usize = 1024;
+
+/// Default lifetime of a cached scan result
+pub const DEFAULT_SCAN_CACHE_TTL: Duration = Duration::from_secs(300);
+
+/// Scan cache hit/miss counters
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct ScanCacheStats {
+    /// Scans served from the cache
+    pub hits: u64,
+    /// Scans that ran the scanner
+    pub misses: u64,
+    /// Entries dropped to make room
+    pub evictions: u64,
+    /// Entries found past their TTL
+    pub expired: u64,
+    /// Calls to [`ScanCache::invalidate`]
+    pub invalidations: u64,
+    /// Entries currently cached
+    pub entries: usize,
+    /// Maximum entries
+    pub capacity: usize,
+}
+
+impl ScanCacheStats {
+    /// Fraction of lookups served from the cache (0.0 when unused)
+    pub fn hit_rate(&self) -> f64 {
+        let total = self.hits + self.misses;
+        if total == 0 {
+            0.0
+        } else {
+            self.hits as f64 / total as f64
+        }
+    }
+}
+
+/// Cache key: content and tenant hash, content length, scanner fingerprint
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(super) struct ScanKey {
+    hash: u64,
+    len: usize,
+    fingerprint: u64,
+}
+
+/// Cached result with its insertion time and last use
+#[derive(Debug)]
+struct Entry {
+    result: ScanResult,
+    inserted: Instant,
+    last_used: u64,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    entries: HashMap<ScanKey, Entry>,
+    /// Monotonic use counter for LRU ordering
+    tick: u64,
+    stats: ScanCacheStats,
+}
+
+/// LRU cache of scan results keyed by content hash
+#[derive(Debug)]
+pub struct ScanCache {
+    inner: Mutex<Inner>,
+    capacity: usize,
+    ttl: Duration,
+    hasher: RandomState,
+}
+
+impl Default for ScanCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_SCAN_CACHE_CAPACITY)
+    }
+}
+
+impl ScanCache {
+    /// Create a cache holding at most `capacity` results
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            inner: Mutex::new(Inner::default()),
+            capacity: capacity.max(1),
+            ttl: DEFAULT_SCAN_CACHE_TTL,
+            hasher: RandomState::new(),
+        }
+    }
+
+    /// Set how long results stay valid (default: 5 minutes)
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Key for `content` scanned for `tenant` by a scanner with `fingerprint`
+    pub(super) fn key(&self, content: &str, tenant: Option<&str>, fingerprint: u64) -> ScanKey {
+        let mut hasher = self.hasher.build_hasher();
+        content.hash(&mut hasher);
+        tenant.hash(&mut hasher);
+        ScanKey {
+            hash: hasher.finish(),
+            len: content.len(),
+            fingerprint,
+        }
+    }
+
+    /// Look up a result, counting the hit or miss
+    pub(super) fn get(&self, key: ScanKey) -> Option<ScanResult> {
+        let mut inner = self.inner.lock().ok()?;
+        inner.tick += 1;
+        let tick = inner.tick;
+
+        let fresh = inner
+            .entries
+            .get(&key)
+            .map(|entry| entry.inserted.elapsed() < self.ttl);
+        match fresh {
+            Some(true) => {
+                inner.stats.hits += 1;
+                let entry = inner.entries.get_mut(&key)?;
+                entry.last_used = tick;
+                Some(entry.result.clone())
+            },
+            Some(false) => {
+                inner.entries.remove(&key);
+                inner.stats.expired += 1;
+                inner.stats.misses += 1;
+                None
+            },
+            None => {
+                inner.stats.misses += 1;
+                None
+            },
+        }
+    }
+
+    /// Cache a result, evicting the least recently used one if full
+    pub(super) fn insert(&self, key: ScanKey, result: ScanResult) {
+        let Ok(mut inner) = self.inner.lock() else {
+            return;
+        };
+        inner.tick += 1;
+        let tick = inner.tick;
+
+        if !inner.entries.contains_key(&key) && inner.entries.len() >= self.capacity {
+            let oldest = inner
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| *key);
+            if let Some(oldest) = oldest {
+                inner.entries.remove(&oldest);
+                inner.stats.evictions += 1;
+            }
+        }
+        inner.entries.insert(
+            key,
+            Entry {
+                result,
+                inserted: Instant::now(),
+                last_used: tick,
+            },
+        );
+    }
+
+    /// Drop all cached results (counters are kept)
+    pub fn invalidate(&self) {
+        if let Ok(mut inner) = self.inner.lock() {
+            inner.entries.clear();
+            inner.stats.invalidations += 1;
+        }
+    }
+
+    /// Current counters
+    pub fn stats(&self) -> ScanCacheStats {
+        self.inner
+            .lock()
+            .map(|inner| ScanCacheStats {
+                entries: inner.entries.len(),
+                capacity: self.capacity,
+                ..inner.stats
+            })
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lru_and_ttl() {
+        let cache = ScanCache::new(2);
+        let a = cache.key("a", None, 1);
+        assert_ne!(a, cache.key("a", Some("acme"), 1));
+        assert_ne!(a, cache.key("a", None, 2));
+
+        cache.insert(a, ScanResult::safe());
+        cache.insert(cache.key("b", None, 1), ScanResult::safe());
+        assert!(cache.get(a).is_some()); // "a" is now most recent
+        cache.insert(cache.key("c", None, 1), ScanResult::safe());
+        assert!(cache.get(a).is_some());
+        assert!(cache.get(cache.key("b", None, 1)).is_none());
+
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses, stats.evictions), (2, 1, 1));
+
+        cache.invalidate();
+        assert_eq!(cache.stats().entries, 0);
+
+        let cache = ScanCache::new(2).with_ttl(Duration::ZERO);
+        cache.insert(a, ScanResult::safe());
+        assert!(cache.get(a).is_none());
+        assert_eq!(cache.stats().expired, 1);
+    }
+}
@@ -12,6 +12,9 @@
 //! | `Malformed`   | Null bytes, excessive nesting, overflow  | High     |
 //! | `DataExfil`   | Environment variable access, file reads  | High     |
 //! | `PrivilegeEsc`| Role escalation attempts                 | Medium   |
+//! | `PiiLeak`     | Personal data (custom rules only)        | -        |
+//!
+//! Severity scores map to [`Severity`] levels, which policies match on.
 //!
 //! # Detection Methods
 //!
@@ -38,6 +41,14 @@
 //! | Full     | ~1ms   | Pattern + ML      | Standard scanning           |
 //! | Validate | ~2ms   | Full + JSON check | Strict mode, external input |
 //!
+//! Full and Validate fuse pattern severity with the Hydra security score
+//! when a model is attached ([`SecurityScanner::with_model`]); see
+//! [`SecurityScanner::with_ml_weight`] for the weighting and
+//! [`SecurityScanner::without_ml`] to turn ML off.
+//!
+//! Retried and fanned-out payloads can skip scanning entirely with a
+//! [`ScanCache`] ([`SecurityScanner::with_cache`]).
+//!
 //! # Usage
 //!
 //! ## Basic Scanning
@@ -69,6 +80,25 @@
 //! }
 //! ```
 //!
+//! ## Policies
+//!
+//! ```rust,ignore
+//! use m2m_core::security::{PolicyAction, PolicyEngine, SecurityPolicy, SecurityScanner};
+//!
+//! // Per-category actions, with an override for one tenant
+//! let engine = PolicyEngine::new(SecurityPolicy::recommended())
+//!     .with_tenant("research", PolicyEngine::from_file("research.toml")?.default);
+//! let scanner = SecurityScanner::new().with_policy(engine);
+//!
+//! let result = scanner.scan_for_tenant(content, "research")?;
+//! if result.action == PolicyAction::Redact {
+//!     let content = scanner.redact(content, &result);
+//! }
+//! for decision in &result.policy_trace {
+//!     println!("{} -> {} ({}: {})", decision.threat, decision.action, decision.policy, decision.rule);
+//! }
+//! ```
+//!
 //! ## Quick Scan (Pattern Only)
 //!
 //! ```rust,ignore
@@ -79,6 +109,17 @@
 //! // No Result wrapper - quick_scan is infallible
 //! ```
 //!
+//! ## Custom Rules
+//!
+//! ```rust,ignore
+//! use m2m_core::security::SecurityScanner;
+//!
+//! // Merge site-specific rules (TOML, YAML or JSON) with the built-in packs
+//! let scanner = SecurityScanner::new()
+//!     .with_blocking(0.8)
+//!     .with_rules_file("rules.toml")?;
+//! ```
+//!
 //! ## JSON Validation
 //!
 //! ```rust,ignore
@@ -90,11 +131,23 @@
 //! let result = scanner.scan_and_validate(r#"{"valid": "json"}"#);
 //! ```
 
+mod cache;
+mod context;
 mod patterns;
+mod policy;
+mod rules;
 mod scanner;
 
-pub use patterns::{ThreatPattern, INJECTION_PATTERNS, JAILBREAK_PATTERNS};
-pub use scanner::{ScanResult, SecurityScanner};
+pub use cache::{ScanCache, ScanCacheStats, DEFAULT_SCAN_CACHE_CAPACITY, DEFAULT_SCAN_CACHE_TTL};
+pub use context::ContextExemption;
+pub use patterns::{ThreatCategory, ThreatPattern, INJECTION_PATTERNS, JAILBREAK_PATTERNS};
+pub use policy::{
+    PolicyAction, PolicyDecision, PolicyEngine, PolicyRule, SecurityPolicy, Severity,
+};
+pub use rules::{
+    AllowRule, CompiledAllowRule, CompiledRule, CustomRule, RuleAction, RuleConflict, RuleSet,
+};
+pub use scanner::{DetectedThreat, ScanMethod, ScanResult, SecurityScanner, DEFAULT_ML_WEIGHT};
 
 /// Security model version
 pub const SECURITY_VERSION: &str = "1.0.0";
@@ -8,6 +8,7 @@
 
 use lazy_static::lazy_static;
 use regex::Regex;
+use serde::{Deserialize, Serialize};
 
 /// A threat detection pattern
 #[derive(Debug, Clone)]
@@ -25,9 +26,11 @@ pub struct ThreatPattern {
 }
 
 /// Threat categories
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum ThreatCategory {
     /// Prompt injection
+    #[serde(alias = "prompt_injection")]
     Injection,
     /// Jailbreak attempt
     Jailbreak,
@@ -37,6 +40,8 @@ pub enum ThreatCategory {
     DataExfil,
     /// Privilege escalation
     PrivilegeEsc,
+    /// Personal data in the content (custom rules only)
+    PiiLeak,
 }
 
 impl std::fmt::Display for ThreatCategory {
@@ -47,10 +52,20 @@ impl std::fmt::Display for ThreatCategory {
             ThreatCategory::Malformed => write!(f, "malformed"),
             ThreatCategory::DataExfil => write!(f, "data_exfil"),
             ThreatCategory::PrivilegeEsc => write!(f, "privilege_esc"),
+            ThreatCategory::PiiLeak => write!(f, "pii_leak"),
         }
     }
 }
 
+impl std::str::FromStr for ThreatCategory {
+    type Err = serde_json::Error;
+
+    /// Parse a category name; the ML name `prompt_injection` is `Injection`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        serde_json::from_value(serde_json::Value::String(s.to_string()))
+    }
+}
+
 /// Prompt injection patterns
 pub static INJECTION_PATTERNS: &[ThreatPattern] = &[
     ThreatPattern {
@@ -241,6 +256,17 @@ lazy_static! {
     };
 }
 
+/// Compiled regex of a built-in pattern
+pub(crate) fn pattern_regex(name: &str) -> Option<&'static Regex> {
+    INJECTION_REGEX
+        .iter()
+        .chain(JAILBREAK_REGEX.iter())
+        .chain(MALFORMED_REGEX.iter())
+        .chain(EXFIL_REGEX.iter())
+        .find(|(_, pattern)| pattern.name == name)
+        .map(|(regex, _)| regex)
+}
+
 /// Match content against all patterns
 pub fn match_patterns(content: &str) -> Vec<&'static ThreatPattern> {
     let mut matches = Vec::new();
@@ -0,0 +1,455 @@
+//! Policy engine deciding what happens to detected threats.
+//!
+//! A scan reports threats; a [`SecurityPolicy`] decides the action for
+//! each one. Rules are checked in order and the first rule matching the
+//! threat's category and severity decides. Threats no rule matches fall
+//! back to the blocking threshold: they block when the scan confidence
+//! reaches it and are flagged otherwise.
+//!
+//! A [`PolicyEngine`] holds the default policy and per-tenant overrides.
+//! A tenant's rules are checked before the default rules, so a tenant only
+//! lists what it changes. The strictest action over all threats applies
+//! to the content.
+//!
+//! # Policy File
+//!
+//! ```toml
+//! [default]
+//! block_threshold = 0.8
+//!
+//! [[default.rule]]
+//! category = "injection"
+//! action = "block"
+//!
+//! [[default.rule]]
+//! category = "privilege_esc"
+//! action = "flag"
+//!
+//! [[default.rule]]
+//! category = "pii_leak"
+//! action = "redact"
+//!
+//! # Tenant "research" sees injection attempts but lets them through
+//! [[tenants.research.rule]]
+//! category = "injection"
+//! action = "flag"
+//! ```
+//!
+//! Every decision is recorded in [`ScanResult::policy_trace`](super::ScanResult)
+//! with the threat, the policy and the rule that decided it.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use super::patterns::ThreatCategory;
+use super::scanner::DetectedThreat;
+use crate::error::{M2MError, Result};
+
+/// Severity level of a threat
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize, Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// Score below 0.4
+    #[default]
+    Low,
+    /// Score from 0.4
+    Medium,
+    /// Score from 0.7
+    High,
+    /// Score from 0.9
+    Critical,
+}
+
+impl Severity {
+    /// Level of a severity score (0.0 - 1.0)
+    pub fn from_score(score: f32) -> Self {
+        if score >= 0.9 {
+            Self::Critical
+        } else if score >= 0.7 {
+            Self::High
+        } else if score >= 0.4 {
+            Self::Medium
+        } else {
+            Self::Low
+        }
+    }
+}
+
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Low => write!(f, "low"),
+            Severity::Medium => write!(f, "medium"),
+            Severity::High => write!(f, "high"),
+            Severity::Critical => write!(f, "critical"),
+        }
+    }
+}
+
+/// Action taken on content, from least to most strict
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize, Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum PolicyAction {
+    /// Let the content through unreported
+    #[default]
+    Allow,
+    /// Let the content through and report the threat
+    Flag,
+    /// Remove the matched text before passing the content on
+    Redact,
+    /// Reject the content
+    Block,
+}
+
+impl std::fmt::Display for PolicyAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PolicyAction::Allow => write!(f, "allow"),
+            PolicyAction::Flag => write!(f, "flag"),
+            PolicyAction::Redact => write!(f, "redact"),
+            PolicyAction::Block => write!(f, "block"),
+        }
+    }
+}
+
+/// A policy rule: threats of a category at or above a severity get an action
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PolicyRule {
+    /// Category matched (`None` = any)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category: Option<ThreatCategory>,
+    /// Lowest severity matched
+    #[serde(default)]
+    pub min_severity: Severity,
+    /// Action for matching threats
+    pub action: PolicyAction,
+}
+
+impl PolicyRule {
+    /// Rule for every threat of a category
+    pub fn category(category: ThreatCategory, action: PolicyAction) -> Self {
+        Self {
+            category: Some(category),
+            min_severity: Severity::Low,
+            action,
+        }
+    }
+
+    /// Rule for threats of any category
+    pub fn any(action: PolicyAction) -> Self {
+        Self {
+            category: None,
+            min_severity: Severity::Low,
+            action,
+        }
+    }
+
+    /// Only match threats at or above a severity
+    pub fn with_min_severity(mut self, severity: Severity) -> Self {
+        self.min_severity = severity;
+        self
+    }
+
+    /// Check if the rule covers a threat
+    fn matches(&self, category: Option<ThreatCategory>, severity: Severity) -> bool {
+        self.category.is_none_or(|c| Some(c) == category) && severity >= self.min_severity
+    }
+}
+
+impl std::fmt::Display for PolicyRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.category {
+            Some(category) => write!(f, "{category}")?,
+            None => write!(f, "any category")?,
+        }
+        if self.min_severity > Severity::Low {
+            write!(f, " at {} or above", self.min_severity)?;
+        }
+        write!(f, " -> {}", self.action)
+    }
+}
+
+/// Ordered rules plus a blocking threshold for threats no rule matches
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SecurityPolicy {
+    /// Rules in priority order
+    #[serde(default, rename = "rule")]
+    pub rules: Vec<PolicyRule>,
+    /// Confidence at which unmatched threats block (`None` = inherit)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block_threshold: Option<f32>,
+}
+
+impl SecurityPolicy {
+    /// Create a policy without rules
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Block injection, jailbreak and exfiltration, flag privilege
+    /// escalation, redact PII and block malformed payloads of high severity
+    pub fn recommended() -> Self {
+        use PolicyAction::{Block, Flag, Redact};
+        use ThreatCategory::{DataExfil, Injection, Jailbreak, Malformed, PiiLeak, PrivilegeEsc};
+
+        Self::new()
+            .with_rule(PolicyRule::category(Injection, Block))
+            .with_rule(PolicyRule::category(Jailbreak, Block))
+            .with_rule(PolicyRule::category(DataExfil, Block))
+            .with_rule(PolicyRule::category(PrivilegeEsc, Flag))
+            .with_rule(PolicyRule::category(PiiLeak, Redact))
+            .with_rule(PolicyRule::category(Malformed, Block).with_min_severity(Severity::High))
+    }
+
+    /// Append a rule (checked after the existing ones)
+    pub fn with_rule(mut self, rule: PolicyRule) -> Self {
+        self.rules.push(rule);
+        self
+    }
+
+    /// Set the confidence at which unmatched threats block
+    pub fn with_block_threshold(mut self, threshold: f32) -> Self {
+        self.block_threshold = Some(threshold.clamp(0.0, 1.0));
+        self
+    }
+}
+
+/// Why a threat received its action
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PolicyDecision {
+    /// Threat (pattern or rule) that fired
+    pub threat: String,
+    /// Threat category
+    pub category: String,
+    /// Threat severity level
+    pub severity: Severity,
+    /// Action decided
+    pub action: PolicyAction,
+    /// Deciding policy (`default`, `tenant:<id>`, or `custom_rules`)
+    pub policy: String,
+    /// Deciding rule or threshold comparison
+    pub rule: String,
+}
+
+impl PolicyDecision {
+    /// Record the action for a threat
+    pub fn new(
+        threat: &DetectedThreat,
+        action: PolicyAction,
+        policy: String,
+        rule: String,
+    ) -> Self {
+        Self {
+            threat: threat.name.clone(),
+            category: threat.category.clone(),
+            severity: threat.level(),
+            action,
+            policy,
+            rule,
+        }
+    }
+}
+
+/// Default policy plus per-tenant overrides
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PolicyEngine {
+    /// Policy for every tenant
+    #[serde(default)]
+    pub default: SecurityPolicy,
+    /// Overrides by tenant ID, checked before the default policy
+    #[serde(default)]
+    pub tenants: HashMap<String, SecurityPolicy>,
+}
+
+impl PolicyEngine {
+    /// Create an engine with a default policy
+    pub fn new(default: SecurityPolicy) -> Self {
+        Self {
+            default,
+            tenants: HashMap::new(),
+        }
+    }
+
+    /// Override the default policy for a tenant
+    pub fn with_tenant(mut self, tenant: &str, policy: SecurityPolicy) -> Self {
+        self.tenants.insert(tenant.to_string(), policy);
+        self
+    }
+
+    /// Parse policies from TOML
+    pub fn from_toml_str(content: &str) -> Result<Self> {
+        toml::from_str(content)
+            .map_err(|e| M2MError::Config(format!("Failed to parse security policy: {e}")))
+    }
+
+    /// Parse policies from JSON
+    pub fn from_json_str(content: &str) -> Result<Self> {
+        serde_json::from_str(content)
+            .map_err(|e| M2MError::Config(format!("Failed to parse security policy: {e}")))
+    }
+
+    /// Load policies from a file (`.json` is parsed as JSON, anything else as TOML)
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| M2MError::Config(format!("Failed to read security policy: {e}")))?;
+
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("json") => Self::from_json_str(&content),
+            _ => Self::from_toml_str(&content),
+        }
+    }
+
+    /// Policies checked for a tenant, most specific first
+    fn policies(&self, tenant: Option<&str>) -> Vec<(String, &SecurityPolicy)> {
+        let mut policies = Vec::with_capacity(2);
+        if let Some((id, policy)) = tenant.and_then(|id| Some((id, self.tenants.get(id)?))) {
+            policies.push((format!("tenant:{id}"), policy));
+        }
+        policies.push(("default".to_string(), &self.default));
+        policies
+    }
+
+    /// Decision of the first rule covering a threat, tenant rules first
+    ///
+    /// `None` if no rule covers the threat.
+    pub fn decide(&self, tenant: Option<&str>, threat: &DetectedThreat) -> Option<PolicyDecision> {
+        let category = threat.category.parse().ok();
+        self.policies(tenant)
+            .into_iter()
+            .find_map(|(name, policy)| {
+                let (i, rule) = policy
+                    .rules
+                    .iter()
+                    .enumerate()
+                    .find(|(_, rule)| rule.matches(category, threat.level()))?;
+                Some(PolicyDecision::new(
+                    threat,
+                    rule.action,
+                    name,
+                    format!("rule {}: {rule}", i + 1),
+                ))
+            })
+    }
+
+    /// Decide the action for each threat
+    ///
+    /// Threats no rule covers block when `confidence` reaches the blocking
+    /// threshold; `fallback_threshold` applies when neither the tenant nor
+    /// the default policy sets one.
+    pub fn evaluate(
+        &self,
+        tenant: Option<&str>,
+        threats: &[DetectedThreat],
+        confidence: f32,
+        fallback_threshold: f32,
+    ) -> Vec<PolicyDecision> {
+        let (name, threshold) = self
+            .policies(tenant)
+            .into_iter()
+            .find_map(|(name, policy)| Some((name, policy.block_threshold?)))
+            .unwrap_or_else(|| ("default".to_string(), fallback_threshold));
+
+        threats
+            .iter()
+            .map(|threat| {
+                self.decide(tenant, threat).unwrap_or_else(|| {
+                    let (action, op) = if confidence >= threshold {
+                        (PolicyAction::Block, ">=")
+                    } else {
+                        (PolicyAction::Flag, "<")
+                    };
+                    let rule =
+                        format!("confidence {confidence:.2} {op} block threshold {threshold:.2}");
+                    PolicyDecision::new(threat, action, name.clone(), rule)
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::security::ScanMethod;
+
+    fn threat(name: &str, category: &str, severity: f32) -> DetectedThreat {
+        DetectedThreat {
+            name: name.to_string(),
+            category: category.to_string(),
+            severity,
+            description: String::new(),
+            method: ScanMethod::Pattern,
+        }
+    }
+
+    #[test]
+    fn test_rules_tenants_and_threshold() {
+        let engine = PolicyEngine::from_toml_str(
+            r#"
+            [default]
+            block_threshold = 0.9
+
+            [[default.rule]]
+            category = "injection"
+            action = "block"
+
+            [[default.rule]]
+            category = "malformed"
+            min_severity = "critical"
+            action = "block"
+
+            [[tenants.research.rule]]
+            category = "injection"
+            action = "flag"
+            "#,
+        )
+        .unwrap();
+        let threats = [
+            threat("ignore_instructions", "injection", 0.9),
+            threat("excessive_nesting", "malformed", 0.8),
+        ];
+
+        let decisions = engine.evaluate(None, &threats, 0.9, 0.5);
+        assert_eq!(decisions[0].action, PolicyAction::Block);
+        assert_eq!(decisions[0].policy, "default");
+        assert_eq!(decisions[0].rule, "rule 1: injection -> block");
+        // Below the rule's severity: falls back to the policy's threshold
+        assert_eq!(decisions[1].severity, Severity::High);
+        assert_eq!(decisions[1].action, PolicyAction::Block);
+        assert_eq!(decisions[1].rule, "confidence 0.90 >= block threshold 0.90");
+
+        let decisions = engine.evaluate(Some("research"), &threats, 0.8, 0.5);
+        assert_eq!(decisions[0].action, PolicyAction::Flag);
+        assert_eq!(decisions[0].policy, "tenant:research");
+        assert_eq!(decisions[1].action, PolicyAction::Flag);
+        assert_eq!(decisions[1].policy, "default");
+
+        // ML category names map onto pattern categories
+        let decisions = engine.evaluate(
+            Some("other"),
+            &[threat("ml", "prompt_injection", 0.7)],
+            0.7,
+            0.5,
+        );
+        assert_eq!(decisions[0].action, PolicyAction::Block);
+
+        assert!(PolicyEngine::from_toml_str("[[default.rule]]\naction = \"explode\"").is_err());
+    }
+
+    #[test]
+    fn test_severity_levels() {
+        assert_eq!(Severity::from_score(0.1), Severity::Low);
+        assert_eq!(Severity::from_score(0.5), Severity::Medium);
+        assert_eq!(Severity::from_score(0.85), Severity::High);
+        assert_eq!(Severity::from_score(0.95), Severity::Critical);
+        assert!(PolicyAction::Block > PolicyAction::Redact);
+        assert!(PolicyAction::Redact > PolicyAction::Flag);
+    }
+}
@@ -0,0 +1,223 @@
+//! Session inspection admin API.
+//!
+//! Lets operators see what agent connections are doing. Every endpoint
+//! requires `Authorization: Bearer <admin token>` (see
+//! [`ServerConfig::with_admin_token`](super::ServerConfig::with_admin_token));
+//! without a configured token (an empty one counts as unset) the API is
+//! disabled and returns 404.
+//!
+//! | Method   | Path                     | Description                       |
+//! |----------|--------------------------|-----------------------------------|
+//! | `GET`    | `/admin/sessions`        | List sessions with state and stats |
+//! | `GET`    | `/admin/sessions/events` | Lifecycle events (SSE)            |
+//! | `GET`    | `/admin/sessions/:id`    | One session                       |
+//! | `DELETE` | `/admin/sessions/:id`    | Force-close a session             |
+//! | `GET`    | `/admin/report`          | Savings report (see below)        |
+//!
+//! `/admin/report?period=daily|weekly&format=json|markdown&from=&to=`
+//! prices the per-model stats history (unix seconds, `to` exclusive) into
+//! a [`SavingsReport`](crate::reports::SavingsReport). Reports are built
+//! from exact counts, so the endpoint ignores stats privacy.
+
+use std::convert::Infallible;
+use std::sync::Arc;
+
+use axum::{
+    extract::{Json, Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
+    routing::get,
+    Router,
+};
+use futures::Stream;
+use serde::Deserialize;
+use tokio::sync::broadcast::error::RecvError;
+
+use super::state::AppState;
+use crate::reports::{ReportFormat, ReportPeriod, SavingsReport, UsageRecord};
+
+/// Admin routes (merged into the main router)
+pub(super) fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/admin/sessions", get(list_sessions))
+        .route("/admin/sessions/events", get(session_events))
+        .route(
+            "/admin/sessions/:id",
+            get(get_session).delete(close_session),
+        )
+        .route("/admin/report", get(savings_report))
+}
+
+/// Check the bearer token against the configured admin token, returning
+/// the rejection if it does not match
+pub(super) fn deny(state: &AppState, headers: &HeaderMap) -> Option<Response> {
+    let Some(token) = state
+        .config
+        .admin_token
+        .as_deref()
+        .filter(|token| !token.is_empty())
+    else {
+        return Some(
+            (
+                StatusCode::NOT_FOUND,
+                Json(serde_json::json!({"error": "Admin API disabled"})),
+            )
+                .into_response(),
+        );
+    };
+
+    // An absent or malformed header never reaches the comparison
+    let presented = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .filter(|v| !v.is_empty());
+
+    if presented.is_some_and(|presented| constant_time_eq(presented.as_bytes(), token.as_bytes())) {
+        None
+    } else {
+        Some(
+            (
+                StatusCode::UNAUTHORIZED,
+                [(header::WWW_AUTHENTICATE, "Bearer")],
+                Json(serde_json::json!({"error": "Invalid admin token"})),
+            )
+                .into_response(),
+        )
+    }
+}
+
+/// Compare tokens without leaking the matching prefix length
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// List sessions
+async fn list_sessions(State(state): State<Arc<AppState>>, headers: HeaderMap) -> Response {
+    if let Some(denied) = deny(&state, &headers) {
+        return denied;
+    }
+
+    let sessions = state.sessions.list().await;
+    Json(serde_json::json!({
+        "count": sessions.len(),
+        "sessions": sessions,
+    }))
+    .into_response()
+}
+
+/// Get one session
+async fn get_session(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Response {
+    if let Some(denied) = deny(&state, &headers) {
+        return denied;
+    }
+
+    match state.sessions.info(&id).await {
+        Some(info) => Json(info).into_response(),
+        None => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({"error": "Session not found"})),
+        )
+            .into_response(),
+    }
+}
+
+/// Force-close a session
+async fn close_session(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Response {
+    if let Some(denied) = deny(&state, &headers) {
+        return denied;
+    }
+
+    if state.sessions.remove(&id).await {
+        tracing::info!("Admin closed session {id}");
+        StatusCode::NO_CONTENT.into_response()
+    } else {
+        (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({"error": "Session not found"})),
+        )
+            .into_response()
+    }
+}
+
+/// Savings report query
+#[derive(Deserialize)]
+struct ReportQuery {
+    #[serde(default)]
+    period: ReportPeriod,
+    #[serde(default)]
+    format: ReportFormat,
+    #[serde(default)]
+    from: Option<u64>,
+    #[serde(default)]
+    to: Option<u64>,
+}
+
+/// Savings report over the stats history
+async fn savings_report(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(query): Query<ReportQuery>,
+) -> Response {
+    if let Some(denied) = deny(&state, &headers) {
+        return denied;
+    }
+
+    let from = query.from.unwrap_or(0);
+    let to = query.to.unwrap_or(u64::MAX);
+    match state.stats.history(from, to) {
+        Ok(rollups) => {
+            let records = rollups.iter().flat_map(UsageRecord::from_rollup);
+            let report = SavingsReport::build(query.period, records);
+            (
+                [(header::CONTENT_TYPE, query.format.content_type())],
+                report.render(query.format),
+            )
+                .into_response()
+        },
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({"error": e.to_string()})),
+        )
+            .into_response(),
+    }
+}
+
+/// Stream session lifecycle events
+async fn session_events(State(state): State<Arc<AppState>>, headers: HeaderMap) -> Response {
+    if let Some(denied) = deny(&state, &headers) {
+        return denied;
+    }
+
+    Sse::new(event_stream(&state))
+        .keep_alive(KeepAlive::default())
+        .into_response()
+}
+
+/// SSE events from the session manager; slow clients get a `lagged` event
+fn event_stream(state: &AppState) -> impl Stream<Item = Result<Event, Infallible>> {
+    futures::stream::unfold(state.sessions.subscribe(), |mut events| async move {
+        let event = match events.recv().await {
+            Ok(event) => Event::default()
+                .event(event.kind.name())
+                .json_data(&event)
+                .unwrap_or_default(),
+            Err(RecvError::Lagged(missed)) => {
+                Event::default().event("lagged").data(missed.to_string())
+            },
+            Err(RecvError::Closed) => return None,
+        };
+        Some((Ok(event), events))
+    })
+}
@@ -0,0 +1,662 @@
+//! Request audit logging.
+//!
+//! Captures per-request metadata (model, token counts, compression ratio,
+//! scan verdict, latency) to a JSONL file or HTTP webhook so agent traffic
+//! can be reconstructed for compliance review.
+//!
+//! # Redaction Levels
+//!
+//! | Level     | Payload in record                                       |
+//! |-----------|---------------------------------------------------------|
+//! | `none`    | Full request content                                    |
+//! | `content` | JSON with `content`, `tool_calls` and `arguments` masked |
+//! | `full`    | No payload, metadata only (default)                     |
+//!
+//! Records are built and written on a dedicated thread, so token counting
+//! and sink I/O never block a request handler.
+
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::codec::{Algorithm, CompressionResult};
+use crate::error::{M2MError, Result};
+use crate::models::Encoding;
+use crate::security::ScanResult;
+use crate::tokenizer::{count_tokens, count_tokens_cached};
+
+/// How much of the request payload is kept in audit records
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RedactionLevel {
+    /// Keep the full payload
+    None,
+    /// Mask strings under `content`, `tool_calls` and `arguments`, keep
+    /// JSON structure
+    Content,
+    /// Drop the payload entirely
+    #[default]
+    Full,
+}
+
+impl RedactionLevel {
+    /// Apply redaction to a payload
+    pub fn apply(&self, content: &str) -> Option<String> {
+        match self {
+            RedactionLevel::None => Some(content.to_string()),
+            RedactionLevel::Content => Some(match serde_json::from_str::<Value>(content) {
+                Ok(mut value) => {
+                    mask_content(&mut value);
+                    value.to_string()
+                },
+                Err(_) => redacted(content),
+            }),
+            RedactionLevel::Full => None,
+        }
+    }
+}
+
+impl std::str::FromStr for RedactionLevel {
+    type Err = M2MError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "none" => Ok(RedactionLevel::None),
+            "content" => Ok(RedactionLevel::Content),
+            "full" => Ok(RedactionLevel::Full),
+            _ => Err(M2MError::Config(format!("Unknown redaction level: {s}"))),
+        }
+    }
+}
+
+/// Placeholder for a redacted string
+fn redacted(s: &str) -> String {
+    format!("[REDACTED:{}]", s.len())
+}
+
+/// Keys whose values may carry conversation text
+const MASKED_KEYS: [&str; 3] = ["content", "tool_calls", "arguments"];
+
+/// Replace every string under a [`MASKED_KEYS`] key with a placeholder
+///
+/// Covers plain and multimodal (`[{"type":"text","text":...}]`) content
+/// and tool call arguments.
+fn mask_content(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for (key, v) in map.iter_mut() {
+                if MASKED_KEYS.contains(&key.as_str()) {
+                    mask_strings(v);
+                } else {
+                    mask_content(v);
+                }
+            }
+        },
+        Value::Array(items) => items.iter_mut().for_each(mask_content),
+        _ => {},
+    }
+}
+
+/// Replace every string leaf with a placeholder
+fn mask_strings(value: &mut Value) {
+    match value {
+        Value::String(s) => *value = Value::String(redacted(s)),
+        Value::Object(map) => map.values_mut().for_each(mask_strings),
+        Value::Array(items) => items.iter_mut().for_each(mask_strings),
+        _ => {},
+    }
+}
+
+/// Audit log destination
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AuditTarget {
+    /// Append JSON lines to a file
+    File(PathBuf),
+    /// POST each record as JSON to a URL
+    Webhook(String),
+}
+
+impl AuditTarget {
+    /// Parse a target: `http(s)://` URLs are webhooks, anything else a file path
+    pub fn parse(target: &str) -> Self {
+        if target.starts_with("http://") || target.starts_with("https://") {
+            AuditTarget::Webhook(target.to_string())
+        } else {
+            AuditTarget::File(PathBuf::from(target))
+        }
+    }
+}
+
+/// Audit log configuration
+#[derive(Debug, Clone)]
+pub struct AuditConfig {
+    /// Destination
+    pub target: AuditTarget,
+    /// Payload redaction
+    pub redaction: RedactionLevel,
+}
+
+impl AuditConfig {
+    /// Create config with default (full) redaction
+    pub fn new(target: AuditTarget) -> Self {
+        Self {
+            target,
+            redaction: RedactionLevel::default(),
+        }
+    }
+
+    /// Set redaction level
+    pub fn with_redaction(mut self, redaction: RedactionLevel) -> Self {
+        self.redaction = redaction;
+        self
+    }
+}
+
+/// Security scan verdict in an audit record
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditScan {
+    /// Content judged safe
+    pub safe: bool,
+    /// Request was blocked
+    pub blocked: bool,
+    /// Scan confidence
+    pub confidence: f32,
+    /// Names of detected threats
+    pub threats: Vec<String>,
+}
+
+/// A single audit record (one JSONL line)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditRecord {
+    /// Time of the request (Unix millis)
+    pub timestamp: u64,
+    /// Endpoint path
+    pub endpoint: String,
+    /// Session ID (protocol messages)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    /// Model from the request payload
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// Compression algorithm used
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub algorithm: Option<Algorithm>,
+    /// Original size in bytes
+    pub original_bytes: usize,
+    /// Compressed size in bytes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compressed_bytes: Option<usize>,
+    /// Byte compression ratio
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compression_ratio: Option<f64>,
+    /// Original token count (cl100k)
+    pub original_tokens: usize,
+    /// Compressed token count (cl100k)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compressed_tokens: Option<usize>,
+    /// Security scan verdict
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scan: Option<AuditScan>,
+    /// HTTP status returned
+    pub status: u16,
+    /// Handler latency in milliseconds
+    pub latency_ms: f64,
+    /// Payload after redaction
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub payload: Option<String>,
+}
+
+/// Request details collected by a handler, turned into an [`AuditRecord`]
+/// only when auditing is enabled
+pub struct AuditEvent<'a> {
+    endpoint: &'static str,
+    pub(super) content: &'a str,
+    started: Instant,
+    session_id: Option<&'a str>,
+    pub(super) result: Option<&'a CompressionResult>,
+    pub(super) scan: Option<&'a ScanResult>,
+    pub(super) status: u16,
+}
+
+impl<'a> AuditEvent<'a> {
+    /// Start an event for a request
+    pub fn new(endpoint: &'static str, content: &'a str, started: Instant) -> Self {
+        Self {
+            endpoint,
+            content,
+            started,
+            session_id: None,
+            result: None,
+            scan: None,
+            status: 200,
+        }
+    }
+
+    /// Attach session ID
+    pub fn with_session(mut self, session_id: &'a str) -> Self {
+        self.session_id = Some(session_id);
+        self
+    }
+
+    /// Attach compression result
+    pub fn with_result(mut self, result: &'a CompressionResult) -> Self {
+        self.result = Some(result);
+        self
+    }
+
+    /// Attach scan verdict
+    pub fn with_scan(mut self, scan: Option<&'a ScanResult>) -> Self {
+        self.scan = scan;
+        self
+    }
+
+    /// Set HTTP status
+    pub fn with_status(mut self, status: u16) -> Self {
+        self.status = status;
+        self
+    }
+}
+
+/// Destination for audit records
+pub trait AuditSink: Send + Sync {
+    /// Write a record
+    fn write(&self, record: &AuditRecord) -> Result<()>;
+}
+
+/// Appends records as JSON lines to a file
+pub struct JsonlAuditSink {
+    file: Mutex<File>,
+}
+
+impl JsonlAuditSink {
+    /// Open (or create) a JSONL file for appending
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self {
+            file: Mutex::new(file),
+        })
+    }
+}
+
+impl AuditSink for JsonlAuditSink {
+    fn write(&self, record: &AuditRecord) -> Result<()> {
+        let mut line = serde_json::to_vec(record)?;
+        line.push(b'\n');
+
+        let mut file = self
+            .file
+            .lock()
+            .map_err(|_| M2MError::Server("Audit log lock poisoned".to_string()))?;
+        file.write_all(&line)?;
+        Ok(())
+    }
+}
+
+/// POSTs each record to a webhook without blocking the request
+pub struct WebhookAuditSink {
+    client: reqwest::Client,
+    url: String,
+}
+
+impl WebhookAuditSink {
+    /// Create a webhook sink
+    pub fn new(url: &str) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            url: url.to_string(),
+        }
+    }
+}
+
+impl AuditSink for WebhookAuditSink {
+    fn write(&self, record: &AuditRecord) -> Result<()> {
+        let runtime = tokio::runtime::Handle::try_current()
+            .map_err(|e| M2MError::Server(format!("Audit webhook needs a runtime: {e}")))?;
+
+        let request = self.client.post(&self.url).json(record);
+        crate::runtime::spawn_named_on(&runtime, "audit-webhook", async move {
+            match request.send().await {
+                Ok(response) if !response.status().is_success() => {
+                    tracing::warn!("Audit webhook returned {}", response.status());
+                },
+                Err(e) => tracing::warn!("Audit webhook failed: {e}"),
+                Ok(_) => {},
+            }
+        });
+        Ok(())
+    }
+}
+
+/// Records queued for the writer thread before new ones are dropped
+pub const AUDIT_QUEUE_DEPTH: usize = 4096;
+
+/// Event details owned by the writer thread
+struct PendingRecord {
+    timestamp: u64,
+    endpoint: &'static str,
+    session_id: Option<String>,
+    content: String,
+    algorithm: Option<Algorithm>,
+    compressed_bytes: Option<usize>,
+    compression_ratio: Option<f64>,
+    compressed: Option<String>,
+    scan: Option<AuditScan>,
+    status: u16,
+    latency_ms: f64,
+}
+
+impl PendingRecord {
+    /// Copy what a record needs out of an event
+    fn new(event: &AuditEvent<'_>) -> Self {
+        Self {
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or(0),
+            endpoint: event.endpoint,
+            session_id: event.session_id.map(str::to_string),
+            content: event.content.to_string(),
+            algorithm: event.result.map(|r| r.algorithm),
+            compressed_bytes: event.result.map(|r| r.compressed_bytes),
+            compression_ratio: event.result.map(CompressionResult::byte_ratio),
+            compressed: event.result.map(|r| r.data.clone()),
+            scan: event.scan.map(|s| AuditScan {
+                safe: s.safe,
+                blocked: s.should_block,
+                confidence: s.confidence,
+                threats: s.threats.iter().map(|t| t.name.clone()).collect(),
+            }),
+            status: event.status,
+            latency_ms: event.started.elapsed().as_secs_f64() * 1000.0,
+        }
+    }
+
+    /// Count tokens and redact the payload
+    fn build(self, redaction: RedactionLevel) -> AuditRecord {
+        let model = serde_json::from_str::<Value>(&self.content)
+            .ok()
+            .and_then(|v| v.get("model").and_then(Value::as_str).map(str::to_string));
+
+        AuditRecord {
+            timestamp: self.timestamp,
+            endpoint: self.endpoint.to_string(),
+            session_id: self.session_id,
+            model,
+            algorithm: self.algorithm,
+            original_bytes: self.content.len(),
+            compressed_bytes: self.compressed_bytes,
+            compression_ratio: self.compression_ratio,
+            original_tokens: count_tokens_cached(&self.content, Encoding::Cl100kBase),
+            compressed_tokens: self.compressed.as_deref().map(count_tokens),
+            scan: self.scan,
+            status: self.status,
+            latency_ms: self.latency_ms,
+            payload: redaction.apply(&self.content),
+        }
+    }
+}
+
+/// Work for the writer thread
+enum AuditJob {
+    /// Build and write a record, inside the caller's runtime if any
+    Record(Box<PendingRecord>, Option<tokio::runtime::Handle>),
+    /// Acknowledge once everything queued before has been written
+    Flush(SyncSender<()>),
+}
+
+/// Write and failure counts shared with the writer thread
+#[derive(Default)]
+struct AuditCounters {
+    written: AtomicU64,
+    failed: AtomicU64,
+}
+
+/// Audit logger shared by handlers
+///
+/// [`log`](Self::log) only copies the event into a bounded queue; a
+/// dedicated thread counts tokens, redacts and writes to the sink in
+/// order. When the queue is full, records are dropped and counted as
+/// failed rather than delaying the request.
+pub struct AuditLog {
+    redaction: RedactionLevel,
+    queue: SyncSender<AuditJob>,
+    counters: Arc<AuditCounters>,
+}
+
+impl AuditLog {
+    /// Create a logger over a sink, starting its writer thread
+    ///
+    /// # Panics
+    ///
+    /// Panics if the thread cannot be spawned.
+    pub fn new(sink: Box<dyn AuditSink>, redaction: RedactionLevel) -> Self {
+        let (queue, jobs) = mpsc::sync_channel(AUDIT_QUEUE_DEPTH);
+        let counters = Arc::new(AuditCounters::default());
+        let writer_counters = Arc::clone(&counters);
+        std::thread::Builder::new()
+            .name("m2m-audit".to_string())
+            .spawn(move || write_records(sink.as_ref(), redaction, &jobs, &writer_counters))
+            .expect("spawn audit writer thread");
+
+        Self {
+            redaction,
+            queue,
+            counters,
+        }
+    }
+
+    /// Open the configured target
+    pub fn open(config: &AuditConfig) -> Result<Self> {
+        let sink: Box<dyn AuditSink> = match config.target {
+            AuditTarget::File(ref path) => Box::new(JsonlAuditSink::open(path)?),
+            AuditTarget::Webhook(ref url) => Box::new(WebhookAuditSink::new(url)),
+        };
+        Ok(Self::new(sink, config.redaction))
+    }
+
+    /// Build a record from an event
+    ///
+    /// Counts tokens on the calling thread; [`log`](Self::log) does this
+    /// on the writer thread instead.
+    pub fn record(&self, event: &AuditEvent<'_>) -> AuditRecord {
+        PendingRecord::new(event).build(self.redaction)
+    }
+
+    /// Queue an event for writing (failures are logged, never surfaced to
+    /// the client)
+    pub fn log(&self, event: &AuditEvent<'_>) {
+        let job = AuditJob::Record(
+            Box::new(PendingRecord::new(event)),
+            tokio::runtime::Handle::try_current().ok(),
+        );
+        match self.queue.try_send(job) {
+            Ok(()) => {},
+            Err(TrySendError::Full(_)) => {
+                self.counters.failed.fetch_add(1, Ordering::Relaxed);
+                tracing::warn!("Audit queue full, dropping record");
+            },
+            Err(TrySendError::Disconnected(_)) => {
+                self.counters.failed.fetch_add(1, Ordering::Relaxed);
+                tracing::warn!("Audit writer stopped, dropping record");
+            },
+        }
+    }
+
+    /// Block until every record queued so far has been written
+    ///
+    /// Not for use on an async worker thread.
+    pub fn flush(&self) {
+        let (done, wait) = mpsc::sync_channel(1);
+        if self.queue.send(AuditJob::Flush(done)).is_ok() {
+            let _ = wait.recv();
+        }
+    }
+
+    /// Number of records written
+    pub fn records_written(&self) -> u64 {
+        self.counters.written.load(Ordering::Relaxed)
+    }
+
+    /// Number of records that failed to write or were dropped
+    pub fn records_failed(&self) -> u64 {
+        self.counters.failed.load(Ordering::Relaxed)
+    }
+}
+
+/// Writer thread: drain the queue until the logger is dropped
+fn write_records(
+    sink: &dyn AuditSink,
+    redaction: RedactionLevel,
+    jobs: &Receiver<AuditJob>,
+    counters: &AuditCounters,
+) {
+    for job in jobs {
+        match job {
+            AuditJob::Record(pending, runtime) => {
+                // Webhook sinks spawn onto the runtime the event came from
+                let _guard = runtime.as_ref().map(tokio::runtime::Handle::enter);
+                match sink.write(&pending.build(redaction)) {
+                    Ok(()) => {
+                        counters.written.fetch_add(1, Ordering::Relaxed);
+                    },
+                    Err(e) => {
+                        counters.failed.fetch_add(1, Ordering::Relaxed);
+                        tracing::warn!("Failed to write audit record: {e}");
+                    },
+                }
+            },
+            AuditJob::Flush(done) => {
+                let _ = done.send(());
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REQUEST: &str =
+        r#"{"model":"gpt-4o","messages":[{"role":"user","content":"my SSN is 123-45-6789"}]}"#;
+
+    #[test]
+    fn test_redaction_levels() {
+        assert_eq!(RedactionLevel::None.apply(REQUEST).unwrap(), REQUEST);
+        assert!(RedactionLevel::Full.apply(REQUEST).is_none());
+
+        let masked = RedactionLevel::Content.apply(REQUEST).unwrap();
+        assert!(!masked.contains("123-45-6789"));
+        assert!(masked.contains("gpt-4o"));
+        assert!(masked.contains("[REDACTED:21]"));
+
+        assert_eq!(
+            RedactionLevel::Content.apply("not json").unwrap(),
+            "[REDACTED:8]"
+        );
+    }
+
+    #[test]
+    fn test_jsonl_sink() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("audit.jsonl");
+        let config = AuditConfig::new(AuditTarget::parse(path.to_str().unwrap()))
+            .with_redaction(RedactionLevel::Content);
+        let log = AuditLog::open(&config).unwrap();
+
+        let result = CompressionResult::new("#M2M|1|abc".to_string(), Algorithm::M2M, 90, 10);
+        log.log(&AuditEvent::new("/compress", REQUEST, Instant::now()).with_result(&result));
+        log.log(
+            &AuditEvent::new("/compress", REQUEST, Instant::now())
+                .with_scan(Some(&ScanResult::safe()))
+                .with_status(403),
+        );
+        log.flush();
+        assert_eq!(log.records_written(), 2);
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        let records: Vec<AuditRecord> = contents
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].model.as_deref(), Some("gpt-4o"));
+        assert_eq!(records[0].algorithm, Some(Algorithm::M2M));
+        assert_eq!(records[0].compression_ratio, Some(9.0));
+        assert!(records[0].original_tokens > 0);
+        assert!(!records[0].payload.as_ref().unwrap().contains("123-45-6789"));
+        assert_eq!(records[1].status, 403);
+        assert!(records[1].scan.as_ref().unwrap().safe);
+    }
+
+    /// Sink keeping records in memory
+    struct MemorySink(Arc<Mutex<Vec<String>>>);
+
+    impl AuditSink for MemorySink {
+        fn write(&self, record: &AuditRecord) -> Result<()> {
+            self.0.lock().unwrap().push(serde_json::to_string(record)?);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_content_redaction_covers_parts_and_tool_calls() {
+        let request = serde_json::json!({
+            "model": "gpt-4o",
+            "messages": [
+                {"role": "user", "content": [
+                    {"type": "text", "text": "card 4111-1111-1111-1111"},
+                    {"type": "image_url", "image_url": {"url": "https://img.example/passport.png"}}
+                ]},
+                {"role": "assistant", "content": null, "tool_calls": [{
+                    "id": "call_1",
+                    "type": "function",
+                    "function": {"name": "lookup_customer", "arguments": "{\"email\":\"jane@example.com\"}"}
+                }]},
+                {"role": "tool", "tool_call_id": "call_1", "content": [{"type": "text", "text": "balance 9120.55"}]}
+            ],
+            "functions": [{"name": "f", "arguments": {"query": "home address"}}]
+        })
+        .to_string();
+
+        let records = Arc::new(Mutex::new(Vec::new()));
+        let log = AuditLog::new(
+            Box::new(MemorySink(Arc::clone(&records))),
+            RedactionLevel::Content,
+        );
+        log.log(&AuditEvent::new("/compress", &request, Instant::now()));
+        log.flush();
+
+        let records = records.lock().unwrap();
+        assert_eq!(records.len(), 1);
+        for secret in [
+            "4111-1111-1111-1111",
+            "passport.png",
+            "jane@example.com",
+            "lookup_customer",
+            "9120.55",
+            "home address",
+        ] {
+            assert!(!records[0].contains(secret), "{secret} leaked");
+        }
+        assert!(records[0].contains("gpt-4o"));
+    }
+
+    #[test]
+    fn test_target_parse() {
+        assert_eq!(
+            AuditTarget::parse("https://audit.example/ingest"),
+            AuditTarget::Webhook("https://audit.example/ingest".to_string())
+        );
+        assert_eq!(
+            AuditTarget::parse("/var/log/m2m.jsonl"),
+            AuditTarget::File(PathBuf::from("/var/log/m2m.jsonl"))
+        );
+    }
+}
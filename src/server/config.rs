@@ -1,8 +1,26 @@
 //! Server configuration.
 
 use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
 use std::time::Duration;
 
+use super::audit::AuditConfig;
+use super::privacy::StatsPrivacy;
+use super::quarantine::QuarantineConfig;
+use super::state::{DEFAULT_MAX_MISSED_PONGS, DEFAULT_SWEEP_INTERVAL};
+use super::store::ClusterConfig;
+use crate::codec::m2m::crypto::KeyMaterial;
+use crate::codec::{
+    CompressionProfile, DefaultsNormalizer, DictionaryStore, DEFAULT_DEADLINE, DEFAULT_QUEUE_DEPTH,
+};
+use crate::context::ContextStore;
+use crate::error::{M2MError, Result};
+#[cfg(feature = "crypto")]
+use crate::protocol::HelloAuthenticator;
+use crate::protocol::SESSION_TIMEOUT_SECS;
+use crate::security::{PolicyEngine, DEFAULT_ML_WEIGHT};
+
 /// Server configuration
 #[derive(Debug, Clone)]
 pub struct ServerConfig {
@@ -14,8 +32,16 @@ pub struct ServerConfig {
     pub security_blocking: bool,
     /// Security block threshold
     pub block_threshold: f32,
-    /// Session timeout
+    /// Use the loaded model for security scanning
+    pub ml_security: bool,
+    /// Weight of the model score in fused scan confidence (0.0 - 1.0)
+    pub ml_weight: f32,
+    /// Session timeout (upper bound for negotiated idle timeouts)
     pub session_timeout: Duration,
+    /// Unanswered PINGs before an idle session is marked closing
+    pub max_missed_pongs: u32,
+    /// Interval between session liveness sweeps
+    pub sweep_interval: Duration,
     /// Maximum request body size (bytes)
     pub max_body_size: usize,
     /// Enable request logging
@@ -24,6 +50,51 @@ pub struct ServerConfig {
     pub cors_enabled: bool,
     /// Model path (optional)
     pub model_path: Option<String>,
+    /// Quantize the model to the smallest precision meeting this
+    /// agreement with float32 (optional)
+    pub model_accuracy_floor: Option<f32>,
+    /// Session store path (optional, enables persistence)
+    pub session_store_path: Option<PathBuf>,
+    /// Stats history path (optional, in memory otherwise)
+    pub stats_store_path: Option<PathBuf>,
+    /// Noise added to `/stats/history` for non-admin readers (optional)
+    pub stats_privacy: Option<StatsPrivacy>,
+    /// Key required to sign discovery registrations (optional)
+    pub discovery_key: Option<KeyMaterial>,
+    /// Request audit logging (optional)
+    pub audit: Option<AuditConfig>,
+    /// Bearer token for the `/admin` API (disabled when unset)
+    pub admin_token: Option<String>,
+    /// Validate decompressed API payloads against their schema
+    pub validate_schema: bool,
+    /// Session state shared with other servers (optional, overrides
+    /// `session_store_path`)
+    pub cluster: Option<ClusterConfig>,
+    /// Compression profile when a request names none
+    pub compression_profile: CompressionProfile,
+    /// Time budget for automatic compression (optional)
+    pub max_compress_latency: Option<Duration>,
+    /// Strip provider-default parameters before compression (optional)
+    pub remove_defaults: Option<DefaultsNormalizer>,
+    /// Keep blocked payloads for review (optional)
+    pub quarantine: Option<QuarantineConfig>,
+    /// Forward DATA between agent sessions by destination agent ID
+    pub relay_enabled: bool,
+    /// Credentials required in `/message` HELLOs (optional)
+    #[cfg(feature = "crypto")]
+    pub authenticator: Option<Arc<HelloAuthenticator>>,
+    /// Maximum concurrent codec jobs (default: available CPUs)
+    pub codec_concurrency: Option<usize>,
+    /// Maximum codec jobs waiting for a worker
+    pub codec_queue_depth: usize,
+    /// Codec deadline per request (queueing + execution)
+    pub codec_deadline: Duration,
+    /// Shared compression dictionaries offered during handshakes (optional)
+    pub dictionaries: Option<Arc<DictionaryStore>>,
+    /// Conversation contexts agents pin with CONTEXT_PIN (optional)
+    pub contexts: Option<Arc<ContextStore>>,
+    /// Actions per threat category and tenant (optional)
+    pub security_policy: Option<PolicyEngine>,
 }
 
 impl Default for ServerConfig {
@@ -33,11 +104,37 @@ impl Default for ServerConfig {
             security_enabled: true,
             security_blocking: false,
             block_threshold: 0.8,
-            session_timeout: Duration::from_secs(300),
+            ml_security: true,
+            ml_weight: DEFAULT_ML_WEIGHT,
+            session_timeout: Duration::from_secs(SESSION_TIMEOUT_SECS),
+            max_missed_pongs: DEFAULT_MAX_MISSED_PONGS,
+            sweep_interval: DEFAULT_SWEEP_INTERVAL,
             max_body_size: 10 * 1024 * 1024, // 10MB
             logging: true,
             cors_enabled: true,
             model_path: None,
+            model_accuracy_floor: None,
+            session_store_path: None,
+            stats_store_path: None,
+            stats_privacy: None,
+            discovery_key: None,
+            audit: None,
+            admin_token: None,
+            validate_schema: false,
+            cluster: None,
+            compression_profile: CompressionProfile::Balanced,
+            max_compress_latency: None,
+            remove_defaults: None,
+            quarantine: None,
+            relay_enabled: false,
+            #[cfg(feature = "crypto")]
+            authenticator: None,
+            codec_concurrency: None,
+            codec_queue_depth: DEFAULT_QUEUE_DEPTH,
+            codec_deadline: DEFAULT_DEADLINE,
+            dictionaries: None,
+            contexts: None,
+            security_policy: None,
         }
     }
 }
@@ -75,18 +172,188 @@ impl ServerConfig {
         self
     }
 
+    /// Scan with patterns only, even when a model is loaded
+    pub fn without_ml_security(mut self) -> Self {
+        self.ml_security = false;
+        self
+    }
+
+    /// Set the weight of the model score in fused scan confidence
+    pub fn with_ml_weight(mut self, weight: f32) -> Self {
+        self.ml_weight = weight.clamp(0.0, 1.0);
+        self
+    }
+
     /// Set model path
     pub fn with_model(mut self, path: &str) -> Self {
         self.model_path = Some(path.to_string());
         self
     }
 
+    /// Quantize the loaded model to the smallest precision whose
+    /// predictions agree with float32 at least `floor` of the time
+    pub fn with_model_accuracy_floor(mut self, floor: f32) -> Self {
+        self.model_accuracy_floor = Some(floor.clamp(0.0, 1.0));
+        self
+    }
+
+    /// Persist sessions at path (sled database with the `sled` feature,
+    /// otherwise a directory of JSON files)
+    pub fn with_session_store(mut self, path: impl Into<PathBuf>) -> Self {
+        self.session_store_path = Some(path.into());
+        self
+    }
+
+    /// Share session state with other servers through Redis
+    ///
+    /// Requires the `cluster` feature; takes the place of a session store.
+    pub fn with_cluster(mut self, cluster: ClusterConfig) -> Self {
+        self.cluster = Some(cluster);
+        self
+    }
+
+    /// Persist per-minute stats rollups at path (sled database with the
+    /// `sled` feature, otherwise a JSONL file)
+    pub fn with_stats_store(mut self, path: impl Into<PathBuf>) -> Self {
+        self.stats_store_path = Some(path.into());
+        self
+    }
+
+    /// Publish `/stats/history` with differential-privacy noise
+    ///
+    /// Requests carrying the admin token still see exact values.
+    pub fn with_stats_privacy(mut self, privacy: StatsPrivacy) -> Self {
+        self.stats_privacy = Some(privacy);
+        self
+    }
+
+    /// Require discovery registrations signed with key
+    pub fn with_discovery_key(mut self, key: KeyMaterial) -> Self {
+        self.discovery_key = Some(key);
+        self
+    }
+
+    /// Enable request audit logging
+    pub fn with_audit(mut self, audit: AuditConfig) -> Self {
+        self.audit = Some(audit);
+        self
+    }
+
+    /// Enable the `/admin` API, protected by a bearer token
+    ///
+    /// An empty token is rejected and leaves the API disabled.
+    pub fn with_admin_token(mut self, token: impl Into<String>) -> Self {
+        let token = token.into();
+        if token.is_empty() {
+            tracing::warn!("Ignoring empty admin token; admin API stays disabled");
+            return self;
+        }
+        self.admin_token = Some(token);
+        self
+    }
+
+    /// Reject decompressed payloads that do not match their API schema
+    pub fn with_schema_validation(mut self) -> Self {
+        self.validate_schema = true;
+        self
+    }
+
+    /// Set the compression profile used when a request names none
+    pub fn with_compression_profile(mut self, profile: CompressionProfile) -> Self {
+        self.compression_profile = profile;
+        self
+    }
+
+    /// Skip compression candidates that would not finish within `budget`
+    pub fn with_max_compress_latency(mut self, budget: Duration) -> Self {
+        self.max_compress_latency = Some(budget);
+        self
+    }
+
+    /// Strip provider-default parameters before compression
+    pub fn with_remove_defaults(mut self, normalizer: DefaultsNormalizer) -> Self {
+        self.remove_defaults = Some(normalizer);
+        self
+    }
+
+    /// Quarantine blocked payloads for review under `/admin/quarantine`
+    pub fn with_quarantine(mut self, quarantine: QuarantineConfig) -> Self {
+        self.quarantine = Some(quarantine);
+        self
+    }
+
+    /// Require credentials in `/message` HELLOs
+    ///
+    /// HELLOs with missing or invalid credentials receive REJECT with
+    /// `SecurityPolicy` and leave no session behind.
+    #[cfg(feature = "crypto")]
+    pub fn with_authenticator(mut self, authenticator: Arc<HelloAuthenticator>) -> Self {
+        self.authenticator = Some(authenticator);
+        self
+    }
+
+    /// Let agents send DATA to each other through the server
+    pub fn with_relay(mut self) -> Self {
+        self.relay_enabled = true;
+        self
+    }
+
+    /// Limit codec work; requests beyond the queue or deadline get 503
+    pub fn with_codec_limits(
+        mut self,
+        max_concurrency: usize,
+        queue_depth: usize,
+        deadline: Duration,
+    ) -> Self {
+        self.codec_concurrency = Some(max_concurrency);
+        self.codec_queue_depth = queue_depth;
+        self.codec_deadline = deadline;
+        self
+    }
+
+    /// Decide scan actions with a policy engine instead of the threshold alone
+    ///
+    /// `block_threshold` still applies to threats no policy rule matches.
+    pub fn with_security_policy(mut self, policy: PolicyEngine) -> Self {
+        self.security_policy = Some(policy);
+        self
+    }
+
+    /// Offer shared compression dictionaries during handshakes
+    ///
+    /// Dictionaries agents send in `DICT_PUSH` are added to the store.
+    pub fn with_dictionaries(mut self, dictionaries: Arc<DictionaryStore>) -> Self {
+        self.dictionaries = Some(dictionaries);
+        self
+    }
+
+    /// Hold conversation contexts agents pin with CONTEXT_PIN
+    ///
+    /// The store's limits bound what each agent may pin; DATA referencing
+    /// a pinned context is expanded before it is handled.
+    pub fn with_contexts(mut self, contexts: Arc<ContextStore>) -> Self {
+        self.contexts = Some(contexts);
+        self
+    }
+
     /// Set session timeout
     pub fn with_session_timeout(mut self, timeout: Duration) -> Self {
         self.session_timeout = timeout;
         self
     }
 
+    /// Set how many PINGs may go unanswered before a session is closing
+    pub fn with_max_missed_pongs(mut self, max_missed_pongs: u32) -> Self {
+        self.max_missed_pongs = max_missed_pongs;
+        self
+    }
+
+    /// Set the interval between session liveness sweeps
+    pub fn with_sweep_interval(mut self, interval: Duration) -> Self {
+        self.sweep_interval = interval;
+        self
+    }
+
     /// Set max body size
     pub fn with_max_body_size(mut self, size: usize) -> Self {
         self.max_body_size = size;
@@ -104,4 +371,28 @@ impl ServerConfig {
         self.cors_enabled = false;
         self
     }
+
+    /// Check settings that only make sense together
+    ///
+    /// Relaying routes by authenticated identity, so it requires an
+    /// authenticator. [`AppState::new`](super::AppState::new) disables
+    /// relaying with a warning when this check fails.
+    pub fn validate(&self) -> Result<()> {
+        if self.relay_enabled && !self.has_authenticator() {
+            return Err(M2MError::Config(
+                "Relay requires HELLO authentication (see with_authenticator)".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "crypto")]
+    fn has_authenticator(&self) -> bool {
+        self.authenticator.is_some()
+    }
+
+    #[cfg(not(feature = "crypto"))]
+    fn has_authenticator(&self) -> bool {
+        false
+    }
 }
@@ -0,0 +1,227 @@
+//! Pre-flight cost estimation.
+//!
+//! `POST /v1/estimate` takes a chat completion request and returns the
+//! predicted input/output token cost for each candidate model, before
+//! anything is sent upstream. `/compress` responses for chat requests carry
+//! the estimate for the request's own model in the `X-M2M-Estimated-Cost`
+//! header (USD).
+//!
+//! Prices come from the model registry when its card has pricing, and from
+//! the built-in table used by the M2M wire format otherwise.
+
+use std::sync::Arc;
+
+use axum::{
+    extract::{Json, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::post,
+    Router,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::state::AppState;
+use crate::codec::m2m::estimate_cost;
+use crate::models::{Encoding, ModelRegistry};
+use crate::tokenizer::count_tokens_cached;
+
+/// Response header with the estimated request cost in USD
+pub const ESTIMATED_COST_HEADER: &str = "x-m2m-estimated-cost";
+
+/// Output tokens assumed when the request sets no limit
+const DEFAULT_OUTPUT_TOKENS: u32 = 256;
+
+/// Per-message framing tokens (role markers, separators)
+const TOKENS_PER_MESSAGE: usize = 4;
+
+/// Estimate routes (merged into the main router)
+pub(super) fn routes() -> Router<Arc<AppState>> {
+    Router::new().route("/v1/estimate", post(estimate))
+}
+
+/// Cost estimate request
+#[derive(Debug, Deserialize)]
+pub struct EstimateRequest {
+    /// Chat completion request (object or JSON string)
+    pub payload: Value,
+    /// Candidate models (default: the payload's model)
+    #[serde(default)]
+    pub models: Vec<String>,
+    /// Expected output tokens (default: the payload's token limit)
+    pub output_tokens: Option<u32>,
+}
+
+/// Predicted cost of a request on one model
+#[derive(Debug, Clone, Serialize)]
+pub struct CostEstimate {
+    /// Model priced
+    pub model: String,
+    /// Prompt tokens (model tokenizer)
+    pub input_tokens: u32,
+    /// Expected completion tokens
+    pub output_tokens: u32,
+    /// Input cost (USD)
+    pub input_cost_usd: f64,
+    /// Output cost (USD)
+    pub output_cost_usd: f64,
+    /// Total cost (USD)
+    pub total_cost_usd: f64,
+    /// Price source: `registry` or `default`
+    pub pricing: &'static str,
+}
+
+impl CostEstimate {
+    /// Estimate the cost of a chat completion request on `model`
+    pub fn for_request(
+        registry: &ModelRegistry,
+        payload: &Value,
+        model: &str,
+        output_tokens: Option<u32>,
+    ) -> Self {
+        let input_tokens = prompt_tokens(payload, model);
+        let output_tokens = output_tokens
+            .or_else(|| token_limit(payload))
+            .unwrap_or(DEFAULT_OUTPUT_TOKENS);
+
+        let registry_pricing = registry.get(model).and_then(|card| card.pricing);
+        let (input_cost_usd, output_cost_usd, pricing) = match registry_pricing {
+            Some(p) => (
+                p.calculate(input_tokens.into(), 0),
+                p.calculate(0, output_tokens.into()),
+                "registry",
+            ),
+            None => {
+                // The built-in table is keyed by bare model names
+                let name = model.rsplit('/').next().unwrap_or(model);
+                (
+                    f64::from(estimate_cost(name, input_tokens, 0)),
+                    f64::from(estimate_cost(name, 0, output_tokens)),
+                    "default",
+                )
+            },
+        };
+
+        Self {
+            model: model.to_string(),
+            input_tokens,
+            output_tokens,
+            input_cost_usd,
+            output_cost_usd,
+            total_cost_usd: input_cost_usd + output_cost_usd,
+            pricing,
+        }
+    }
+}
+
+/// Estimate for a chat request's own model, if `content` is one
+pub(super) fn estimate_content(registry: &ModelRegistry, content: &str) -> Option<CostEstimate> {
+    let payload: Value = serde_json::from_str(content).ok()?;
+    payload.get("messages")?;
+    let model = payload.get("model")?.as_str()?;
+    Some(CostEstimate::for_request(registry, &payload, model, None))
+}
+
+/// Prompt tokens: message text plus per-message framing
+fn prompt_tokens(payload: &Value, model: &str) -> u32 {
+    let messages = payload
+        .get("messages")
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+
+    let tokens: usize = messages
+        .iter()
+        .map(|message| {
+            let text = match message.get("content") {
+                Some(Value::String(s)) => s.clone(),
+                Some(Value::Array(parts)) => parts
+                    .iter()
+                    .filter_map(|part| part.get("text").and_then(Value::as_str))
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+                _ => String::new(),
+            };
+            count_tokens_cached(&text, Encoding::infer_from_id(model)) + TOKENS_PER_MESSAGE
+        })
+        .sum();
+
+    u32::try_from(tokens).unwrap_or(u32::MAX)
+}
+
+/// Completion token limit set by the request
+fn token_limit(payload: &Value) -> Option<u32> {
+    ["max_completion_tokens", "max_tokens"]
+        .iter()
+        .find_map(|key| payload.get(*key).and_then(Value::as_u64))
+        .map(|limit| u32::try_from(limit).unwrap_or(u32::MAX))
+}
+
+/// Estimate request cost per candidate model
+async fn estimate(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<EstimateRequest>,
+) -> Response {
+    let payload = match req.payload {
+        Value::String(raw) => match serde_json::from_str(&raw) {
+            Ok(payload) => payload,
+            Err(e) => return bad_request(&format!("Invalid payload: {e}")),
+        },
+        payload => payload,
+    };
+
+    let mut models = req.models;
+    if models.is_empty() {
+        match payload.get("model").and_then(Value::as_str) {
+            Some(model) => models.push(model.to_string()),
+            None => return bad_request("No model in payload and no candidate models"),
+        }
+    }
+
+    let mut estimates: Vec<CostEstimate> = models
+        .iter()
+        .map(|model| CostEstimate::for_request(&state.models, &payload, model, req.output_tokens))
+        .collect();
+    estimates.sort_by(|a, b| a.total_cost_usd.total_cmp(&b.total_cost_usd));
+
+    Json(serde_json::json!({
+        "cheapest": estimates.first().map(|e| e.model.clone()),
+        "estimates": estimates,
+    }))
+    .into_response()
+}
+
+fn bad_request(error: &str) -> Response {
+    (
+        StatusCode::BAD_REQUEST,
+        Json(serde_json::json!({"error": error})),
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimate_for_request() {
+        let registry = ModelRegistry::new();
+        let payload = serde_json::json!({
+            "model": "gpt-4o",
+            "messages": [{"role": "user", "content": "Summarize the attached report."}],
+            "max_tokens": 1000,
+        });
+
+        let estimate = CostEstimate::for_request(&registry, &payload, "gpt-4o", None);
+        assert!(estimate.input_tokens > TOKENS_PER_MESSAGE as u32);
+        assert_eq!(estimate.output_tokens, 1000);
+        assert_eq!(estimate.pricing, "default");
+        assert!(estimate.output_cost_usd > estimate.input_cost_usd);
+
+        let mini = CostEstimate::for_request(&registry, &payload, "gpt-4o-mini", Some(1000));
+        assert!(mini.total_cost_usd < estimate.total_cost_usd);
+
+        assert!(estimate_content(&registry, "plain text").is_none());
+        assert!(estimate_content(&registry, &payload.to_string()).is_some());
+    }
+}
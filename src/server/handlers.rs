@@ -1,19 +1,30 @@
 //! HTTP request handlers.
 
+use std::borrow::Cow;
 use std::sync::Arc;
+use std::time::Instant;
 
 use axum::{
-    extract::{Json, Path, State},
-    http::StatusCode,
-    response::IntoResponse,
+    extract::{Json, Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
     routing::{get, post},
     Router,
 };
 use serde::{Deserialize, Serialize};
+use tower_http::trace::TraceLayer;
 
+use super::audit::AuditEvent;
+use super::estimate::{estimate_content, ESTIMATED_COST_HEADER};
 use super::state::AppState;
-use crate::codec::Algorithm;
-use crate::protocol::{Capabilities, Message, MessageType};
+use crate::codec::{Algorithm, CompressionBreakdown, CompressionProfile, CompressionResult};
+use crate::discovery::{AgentQuery, AgentRecord};
+use crate::protocol::{
+    Capabilities, Extension, Message, MessageType, RejectionCode, SharedDictionaries,
+};
+use crate::runtime::RuntimeMetrics;
+use crate::security::{ScanCacheStats, ScanResult};
+use crate::tokenizer::TokenCacheStats;
 
 /// Create the API router
 pub fn create_router(state: Arc<AppState>) -> Router {
@@ -21,6 +32,7 @@ pub fn create_router(state: Arc<AppState>) -> Router {
         // Health and status
         .route("/health", get(health_check))
         .route("/status", get(status))
+        .route("/stats/history", get(stats_history))
         // Protocol operations
         .route("/session", post(create_session))
         .route("/session/{id}", get(get_session))
@@ -33,6 +45,21 @@ pub fn create_router(state: Arc<AppState>) -> Router {
         .route("/scan", post(scan_content))
         // Protocol messages
         .route("/message", post(process_message))
+        // Agent discovery
+        .route(
+            "/discovery/agents",
+            post(register_agent).get(query_agents),
+        )
+        .route(
+            "/discovery/agents/:id",
+            get(resolve_agent).delete(deregister_agent),
+        )
+        // Operator inspection
+        .merge(super::admin::routes())
+        .merge(super::estimate::routes())
+        .merge(super::relay::routes())
+        .merge(super::quarantine::routes())
+        .layer(TraceLayer::new_for_http())
         .with_state(state)
 }
 
@@ -59,6 +86,14 @@ pub struct StatusResponse {
     pub uptime_secs: u64,
     pub active_sessions: usize,
     pub capabilities: Capabilities,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub audit_records: Option<u64>,
+    pub token_cache: TokenCacheStats,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scan_cache: Option<ScanCacheStats>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub runtime: Option<RuntimeMetrics>,
+    pub codec_in_flight: usize,
 }
 
 /// Status endpoint
@@ -71,9 +106,64 @@ async fn status(State(state): State<Arc<AppState>>) -> impl IntoResponse {
         uptime_secs: state.uptime().as_secs(),
         active_sessions: session_count,
         capabilities: state.capabilities(),
+        audit_records: state.audit.as_ref().map(|a| a.records_written()),
+        token_cache: crate::tokenizer::token_cache_stats(),
+        scan_cache: state.scanner.cache_stats(),
+        runtime: RuntimeMetrics::current(),
+        codec_in_flight: state.codec_service.in_flight(),
     })
 }
 
+/// Stats history query (unix seconds, `to` exclusive)
+#[derive(Deserialize)]
+pub struct StatsHistoryQuery {
+    #[serde(default)]
+    pub from: Option<u64>,
+    #[serde(default)]
+    pub to: Option<u64>,
+}
+
+/// Per-minute stats rollups for dashboards
+///
+/// With stats privacy configured, counts are noised unless the request
+/// carries the admin token.
+async fn stats_history(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(query): Query<StatsHistoryQuery>,
+) -> impl IntoResponse {
+    let from = query.from.unwrap_or(0);
+    let to = query.to.unwrap_or(u64::MAX);
+
+    let privacy = state
+        .config
+        .stats_privacy
+        .as_ref()
+        .filter(|_| super::admin::deny(&state, &headers).is_some());
+
+    match state.stats.history(from, to) {
+        Ok(mut rollups) => {
+            let mut body = serde_json::json!({
+                "from": from,
+                "to": to,
+            });
+            if let Some(privacy) = privacy {
+                for rollup in &mut rollups {
+                    privacy.apply(rollup);
+                }
+                body["privacy"] = serde_json::json!({
+                    "mechanism": "laplace",
+                    "epsilon": privacy.epsilon(),
+                    "byte_sensitivity": privacy.byte_sensitivity(),
+                });
+            }
+            body["rollups"] = serde_json::json!(rollups);
+            (StatusCode::OK, Json(body))
+        },
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, Json(error_body(&e))),
+    }
+}
+
 /// Session create request
 #[derive(Deserialize)]
 pub struct CreateSessionRequest {
@@ -146,12 +236,37 @@ async fn delete_session(
     StatusCode::NO_CONTENT
 }
 
+/// Request header selecting a compression profile for `/compress/auto`
+pub const PROFILE_HEADER: &str = "x-m2m-profile";
+
 /// Compress request
 #[derive(Deserialize)]
 pub struct CompressRequest {
     pub content: String,
     #[serde(default)]
     pub algorithm: Option<Algorithm>,
+    /// Profile for automatic selection (overrides `X-M2M-Profile`)
+    #[serde(default)]
+    pub profile: Option<CompressionProfile>,
+}
+
+/// Profile named by the request body or `X-M2M-Profile` header
+fn request_profile(
+    req: &CompressRequest,
+    headers: &HeaderMap,
+) -> crate::Result<Option<CompressionProfile>> {
+    if req.profile.is_some() {
+        return Ok(req.profile);
+    }
+    headers
+        .get(PROFILE_HEADER)
+        .map(|value| {
+            value
+                .to_str()
+                .map_err(|_| crate::M2MError::Config("Invalid profile header".to_string()))?
+                .parse()
+        })
+        .transpose()
 }
 
 /// Compress response
@@ -169,77 +284,163 @@ pub struct CompressResponse {
 async fn compress(
     State(state): State<Arc<AppState>>,
     Json(req): Json<CompressRequest>,
-) -> impl IntoResponse {
+) -> Response {
+    let started = Instant::now();
+
     // Security check
-    if state.config.security_enabled {
-        let scan_result = state.scanner.scan(&req.content);
-        if let Ok(result) = scan_result {
-            if result.should_block {
-                return (
-                    StatusCode::FORBIDDEN,
-                    Json(serde_json::json!({
-                        "error": "Content blocked by security scan",
-                        "threats": result.threats.iter().map(|t| &t.name).collect::<Vec<_>>(),
-                    })),
-                );
-            }
+    let scan = if state.config.security_enabled {
+        state.scanner.scan(&req.content).ok()
+    } else {
+        None
+    };
+    let event = AuditEvent::new("/compress", &req.content, started).with_scan(scan.as_ref());
+
+    if let Some(ref result) = scan {
+        if result.should_block {
+            state.audit(&event.with_status(StatusCode::FORBIDDEN.as_u16()));
+            let quarantine_id = state.quarantine("/compress", &req.content, result);
+            return (
+                StatusCode::FORBIDDEN,
+                Json(serde_json::json!({
+                    "error": "Content blocked by security scan",
+                    "code": crate::ErrorCode::CONTENT_BLOCKED,
+                    "threats": result.threats.iter().map(|t| &t.name).collect::<Vec<_>>(),
+                    "quarantine_id": quarantine_id,
+                })),
+            )
+                .into_response();
         }
     }
 
+    let content = policy_content(&state, &req.content, scan.as_ref());
     let algorithm = req.algorithm.unwrap_or(Algorithm::M2M);
 
-    match state.codec.compress(&req.content, algorithm) {
-        Ok(result) => (
-            StatusCode::OK,
-            Json(serde_json::json!({
+    match state.codec_service.compress(&content, algorithm).await {
+        Ok(result) => {
+            state.audit(&event.with_result(&result));
+            let mut response = Json(serde_json::json!({
                 "data": result.data,
                 "algorithm": result.algorithm,
                 "original_bytes": result.original_bytes,
                 "compressed_bytes": result.compressed_bytes,
                 "ratio": result.byte_ratio(),
-            })),
-        ),
-        Err(e) => (
-            StatusCode::BAD_REQUEST,
-            Json(serde_json::json!({"error": e.to_string()})),
-        ),
+                "breakdown": request_breakdown(&result, &content),
+            }))
+            .into_response();
+            if let Some(estimate) = estimate_content(&state.models, &content) {
+                if let Ok(value) = format!("{:.6}", estimate.total_cost_usd).parse() {
+                    response.headers_mut().insert(ESTIMATED_COST_HEADER, value);
+                }
+            }
+            response
+        },
+        Err(e) => {
+            let status = codec_error_status(&e);
+            state.audit(&event.with_status(status.as_u16()));
+            (status, Json(error_body(&e))).into_response()
+        },
     }
 }
 
+/// Compression breakdown, priced for the payload's model if it names one
+fn request_breakdown(result: &CompressionResult, content: &str) -> CompressionBreakdown {
+    let model = serde_json::from_str::<serde_json::Value>(content)
+        .ok()
+        .and_then(|value| value.get("model")?.as_str().map(String::from));
+    result.breakdown(model.as_deref().as_slice())
+}
+
 /// Auto-compress with best algorithm
 async fn compress_auto(
     State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
     Json(req): Json<CompressRequest>,
 ) -> impl IntoResponse {
+    let started = Instant::now();
+
+    let profile = match request_profile(&req, &headers) {
+        Ok(profile) => profile,
+        Err(e) => return (StatusCode::BAD_REQUEST, Json(error_body(&e))),
+    };
+
     // Security check
-    if state.config.security_enabled {
-        if let Ok(result) = state.scanner.scan(&req.content) {
-            if result.should_block {
-                return (
-                    StatusCode::FORBIDDEN,
-                    Json(serde_json::json!({
-                        "error": "Content blocked by security scan",
-                    })),
-                );
-            }
-        }
-    }
+    let scan = if state.config.security_enabled {
+        state.scanner.scan(&req.content).ok()
+    } else {
+        None
+    };
+    let event = AuditEvent::new("/compress/auto", &req.content, started).with_scan(scan.as_ref());
 
-    match state.codec.compress_auto(&req.content) {
-        Ok((result, _)) => (
-            StatusCode::OK,
+    if let Some(result) = scan.as_ref().filter(|result| result.should_block) {
+        state.audit(&event.with_status(StatusCode::FORBIDDEN.as_u16()));
+        let quarantine_id = state.quarantine("/compress/auto", &req.content, result);
+        return (
+            StatusCode::FORBIDDEN,
             Json(serde_json::json!({
-                "data": result.data,
-                "algorithm": result.algorithm,
-                "original_bytes": result.original_bytes,
-                "compressed_bytes": result.compressed_bytes,
-                "ratio": result.byte_ratio(),
+                "error": "Content blocked by security scan",
+                "code": crate::ErrorCode::CONTENT_BLOCKED,
+                "quarantine_id": quarantine_id,
             })),
-        ),
-        Err(e) => (
-            StatusCode::BAD_REQUEST,
-            Json(serde_json::json!({"error": e.to_string()})),
-        ),
+        );
+    }
+
+    let content = policy_content(&state, &req.content, scan.as_ref());
+    let profile = profile.unwrap_or(state.config.compression_profile);
+    match state
+        .codec_service
+        .compress_auto_with_profile(&content, Some(profile))
+        .await
+    {
+        Ok(result) => {
+            state.audit(&event.with_result(&result));
+            (
+                StatusCode::OK,
+                Json(serde_json::json!({
+                    "data": result.data,
+                    "algorithm": result.algorithm,
+                    "profile": profile,
+                    "original_bytes": result.original_bytes,
+                    "compressed_bytes": result.compressed_bytes,
+                    "ratio": result.byte_ratio(),
+                    "fallback_from": result.fallback_from,
+                    "breakdown": request_breakdown(&result, &content),
+                })),
+            )
+        },
+        Err(e) => {
+            let status = codec_error_status(&e);
+            state.audit(&event.with_status(status.as_u16()));
+            (status, Json(error_body(&e)))
+        },
+    }
+}
+
+/// Content with the text behind threats the security policy redacts removed
+fn policy_content<'a>(
+    state: &AppState,
+    content: &'a str,
+    scan: Option<&ScanResult>,
+) -> Cow<'a, str> {
+    match scan {
+        Some(result) => state.scanner.redact(content, result),
+        None => Cow::Borrowed(content),
+    }
+}
+
+/// JSON error body with the error's machine-readable code
+pub(super) fn error_body(error: &crate::M2MError) -> serde_json::Value {
+    serde_json::json!({"error": error.to_string(), "code": error.code()})
+}
+
+/// Status for a failed codec operation (503 when load was shed, 413 for
+/// oversized output, 410 for an unknown or expired context reference)
+pub(super) fn codec_error_status(error: &crate::M2MError) -> StatusCode {
+    match error {
+        crate::M2MError::Overloaded(_) => StatusCode::SERVICE_UNAVAILABLE,
+        crate::M2MError::PayloadTooLarge { .. } => StatusCode::PAYLOAD_TOO_LARGE,
+        crate::M2MError::ContextNotFound(_) => StatusCode::GONE,
+        crate::M2MError::SecurityThreat { .. } => StatusCode::FORBIDDEN,
+        _ => StatusCode::BAD_REQUEST,
     }
 }
 
@@ -254,7 +455,7 @@ async fn decompress(
     State(state): State<Arc<AppState>>,
     Json(req): Json<DecompressRequest>,
 ) -> impl IntoResponse {
-    match state.codec.decompress(&req.data) {
+    match state.codec_service.decompress(&req.data).await {
         Ok(content) => (
             StatusCode::OK,
             Json(serde_json::json!({
@@ -262,10 +463,15 @@ async fn decompress(
                 "bytes": content.len(),
             })),
         ),
-        Err(e) => (
-            StatusCode::BAD_REQUEST,
-            Json(serde_json::json!({"error": e.to_string()})),
-        ),
+        Err(e) => {
+            let mut body = error_body(&e);
+            if let crate::M2MError::SchemaViolation { schema, path, .. } = &e {
+                body["schema"] = schema.as_str().into();
+                body["path"] = path.as_str().into();
+                return (StatusCode::UNPROCESSABLE_ENTITY, Json(body));
+            }
+            (codec_error_status(&e), Json(body))
+        },
     }
 }
 
@@ -286,6 +492,7 @@ async fn scan_content(
             Json(serde_json::json!({
                 "safe": result.safe,
                 "confidence": result.confidence,
+                "ml_score": result.ml_score,
                 "threats": result.threats.iter().map(|t| serde_json::json!({
                     "name": t.name,
                     "category": t.category,
@@ -293,42 +500,119 @@ async fn scan_content(
                     "description": t.description,
                 })).collect::<Vec<_>>(),
                 "should_block": result.should_block,
+                "action": result.action,
+                "policy_trace": result.policy_trace,
             })),
         ),
-        Err(e) => (
-            StatusCode::BAD_REQUEST,
-            Json(serde_json::json!({"error": e.to_string()})),
-        ),
+        Err(e) => (StatusCode::BAD_REQUEST, Json(error_body(&e))),
     }
 }
 
+/// Header set by the QUIC transport on requests received in 0-RTT data (RFC 8470)
+const EARLY_DATA_HEADER: &str = "early-data";
+
+/// Response asking the client to resend after the handshake completes
+fn too_early() -> (StatusCode, Json<Message>) {
+    (
+        StatusCode::TOO_EARLY,
+        Json(Message::reject(
+            RejectionCode::ReplayDetected,
+            "Message not allowed in 0-RTT data, retry after handshake",
+        )),
+    )
+}
+
+/// Capabilities for an HTTP session, mirrored from the peer's HELLO
+///
+/// DATA over HTTP is processed before the response is sent, so the server
+/// advertises no receive window of its own. Shared dictionaries are
+/// advertised from the server's own store.
+fn http_capabilities(hello: &Message) -> Capabilities {
+    let mut caps = hello.get_capabilities().cloned().unwrap_or_default();
+    caps.receive_window = None;
+    caps.extensions.remove(SharedDictionaries::KEY);
+    caps
+}
+
 /// Process protocol message
 async fn process_message(
     State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
     Json(message): Json<Message>,
 ) -> impl IntoResponse {
+    let started = Instant::now();
+
+    // Only token-bearing messages are replay-safe in 0-RTT data: a HELLO,
+    // and DATA for the session it established (checked below). Relayed
+    // DATA has side effects and waits for the handshake.
+    let early = headers
+        .get(EARLY_DATA_HEADER)
+        .is_some_and(|v| v.as_bytes() == b"1");
+    let early_allowed = match message.msg_type {
+        MessageType::Hello => message.early_data.is_some(),
+        MessageType::Data => message.early_data.is_some() && message.relay.is_none(),
+        _ => false,
+    };
+    if early && !early_allowed {
+        return too_early();
+    }
+
     match message.msg_type {
+        MessageType::Hello if message.early_data.is_some() => {
+            // Early HELLO: check anti-replay token, adopt the proposed ID
+            let caps = http_capabilities(&message);
+            let mut session = state.sessions.new_session(caps);
+
+            match session.process_early_hello(&message, &state.replay_guard) {
+                Ok(response) if response.msg_type == MessageType::Accept => {
+                    if state.sessions.insert(&session).await {
+                        (StatusCode::OK, Json(response))
+                    } else {
+                        (
+                            StatusCode::CONFLICT,
+                            Json(Message::reject(
+                                RejectionCode::ReplayDetected,
+                                "Proposed session ID already in use",
+                            )),
+                        )
+                    }
+                },
+                Ok(response) => {
+                    let replay = response
+                        .get_rejection()
+                        .is_some_and(|r| r.code == RejectionCode::ReplayDetected);
+                    let status = if replay {
+                        StatusCode::TOO_EARLY
+                    } else {
+                        StatusCode::OK
+                    };
+                    (status, Json(response))
+                },
+                Err(e) => (StatusCode::BAD_REQUEST, Json(Message::reject_error(&e))),
+            }
+        },
         MessageType::Hello => {
-            // Create new session and respond with ACCEPT
-            let caps = message.get_capabilities().cloned().unwrap_or_default();
-            let mut session = state.sessions.create(caps).await;
+            // Create new session and respond with ACCEPT; a REJECTed
+            // HELLO (e.g. missing credentials) leaves no session behind
+            let caps = http_capabilities(&message);
+            let mut session = state.sessions.new_session(caps);
 
-            match session.process_message(&message) {
-                Ok(Some(response)) => {
-                    state.sessions.update(&session).await;
+            match session.process_hello(&message) {
+                Ok(response) => {
+                    if response.msg_type == MessageType::Accept
+                        && !state.sessions.insert(&session).await
+                    {
+                        return (
+                            StatusCode::CONFLICT,
+                            Json(Message::reject(
+                                RejectionCode::Unknown,
+                                "Session ID already in use",
+                            )),
+                        );
+                    }
                     (StatusCode::OK, Json(response))
                 },
-                Ok(None) => (
-                    StatusCode::OK,
-                    Json(Message::accept(session.id(), state.capabilities())),
-                ),
-                Err(e) => (
-                    StatusCode::BAD_REQUEST,
-                    Json(Message::reject(
-                        crate::protocol::RejectionCode::Unknown,
-                        &e.to_string(),
-                    )),
-                ),
+                Err(e) => (StatusCode::BAD_REQUEST, Json(Message::reject_error(&e))),
             }
         },
         MessageType::Data => {
@@ -344,9 +628,49 @@ async fn process_message(
             };
 
             match state.sessions.get(session_id).await {
+                // Early DATA must come from the early HELLO's sender
+                Some(session)
+                    if early
+                        && !(session.accepts_early_data(&message)
+                            && message
+                                .early_data
+                                .as_ref()
+                                .is_some_and(|token| state.replay_guard.is_fresh(token))) =>
+                {
+                    too_early()
+                },
+                // The early HELLO may not have been handled yet
+                None if early => too_early(),
+                // A retried relay is acknowledged but not delivered twice
+                Some(session)
+                    if message.relay.is_some() && super::relay::is_retry(&session, &message) =>
+                {
+                    (StatusCode::ACCEPTED, Json(Message::pong(session.id())))
+                },
                 Some(mut session) => match session.decompress(&message) {
                     Ok(content) => {
+                        let relayed = match message.relay {
+                            Some(ref header) => {
+                                let response = super::relay::forward(
+                                    &state, &session, header, &message, &content,
+                                )
+                                .await;
+                                // Only a queued DATA makes its retries duplicates
+                                if response.0 != StatusCode::ACCEPTED {
+                                    session.forget_message(&message);
+                                }
+                                Some(response)
+                            },
+                            None => None,
+                        };
                         state.sessions.update(&session).await;
+                        state.audit(
+                            &AuditEvent::new("/message", &content, started)
+                                .with_session(session_id),
+                        );
+                        if let Some(response) = relayed {
+                            return response;
+                        }
                         (
                                 StatusCode::OK,
                                 Json(serde_json::from_str::<Message>(&format!(
@@ -354,13 +678,17 @@ async fn process_message(
                                 )).unwrap_or(message)),
                             )
                     },
-                    Err(e) => (
-                        StatusCode::BAD_REQUEST,
-                        Json(Message::reject(
-                            crate::protocol::RejectionCode::Unknown,
-                            &e.to_string(),
-                        )),
-                    ),
+                    Err(crate::M2MError::FragmentPending { .. }) => {
+                        // Buffered; the last fragment gets the content
+                        state.sessions.update(&session).await;
+                        (StatusCode::ACCEPTED, Json(Message::pong(session_id)))
+                    },
+                    Err(e @ crate::M2MError::SecurityThreat { .. }) => {
+                        // Keep the session's scan counts
+                        state.sessions.update(&session).await;
+                        (codec_error_status(&e), Json(Message::reject_error(&e)))
+                    },
+                    Err(e) => (codec_error_status(&e), Json(Message::reject_error(&e))),
                 },
                 None => (
                     StatusCode::NOT_FOUND,
@@ -371,10 +699,110 @@ async fn process_message(
                 ),
             }
         },
+        MessageType::WindowUpdate
+        | MessageType::Upgrade
+        | MessageType::ContextPin
+        | MessageType::Rekey => {
+            let Some(session_id) = message.session_id.as_ref() else {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    Json(Message::reject(
+                        RejectionCode::Unknown,
+                        "Missing session ID",
+                    )),
+                );
+            };
+
+            match state.sessions.get(session_id).await {
+                // UPGRADE is answered with the agreed version, CONTEXT_PIN
+                // with the context's lifetime
+                Some(mut session) => match session.process_message(&message) {
+                    Ok(reply) => {
+                        state.sessions.update(&session).await;
+                        (StatusCode::OK, Json(reply.unwrap_or(message)))
+                    },
+                    Err(e) => (StatusCode::BAD_REQUEST, Json(Message::reject_error(&e))),
+                },
+                None => (
+                    StatusCode::NOT_FOUND,
+                    Json(Message::reject(RejectionCode::Unknown, "Session not found")),
+                ),
+            }
+        },
+        MessageType::DictPush => {
+            let Some(session_id) = message.session_id.as_ref() else {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    Json(Message::reject(
+                        RejectionCode::Unknown,
+                        "Missing session ID",
+                    )),
+                );
+            };
+
+            match state.sessions.get(session_id).await {
+                // Acknowledged without echoing the dictionary
+                Some(mut session) => match session.process_message(&message) {
+                    Ok(_) => {
+                        state.sessions.update(&session).await;
+                        (StatusCode::OK, Json(Message::pong(session_id)))
+                    },
+                    Err(e) => (StatusCode::BAD_REQUEST, Json(Message::reject_error(&e))),
+                },
+                None => (
+                    StatusCode::NOT_FOUND,
+                    Json(Message::reject(RejectionCode::Unknown, "Session not found")),
+                ),
+            }
+        },
+        MessageType::Broadcast => {
+            let (Some(session_id), Some(payload)) =
+                (message.session_id.as_ref(), message.get_broadcast())
+            else {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    Json(Message::reject(
+                        RejectionCode::Unknown,
+                        "BROADCAST requires a session ID and payload",
+                    )),
+                );
+            };
+
+            match state.sessions.get(session_id).await {
+                Some(mut session) => {
+                    if let Err(e) = session.process_message(&message) {
+                        return (StatusCode::BAD_REQUEST, Json(Message::reject_error(&e)));
+                    }
+                    state.sessions.update(&session).await;
+                    super::relay::broadcast(&state, &session, &message, payload).await
+                },
+                None => (
+                    StatusCode::NOT_FOUND,
+                    Json(Message::reject(RejectionCode::Unknown, "Session not found")),
+                ),
+            }
+        },
         MessageType::Ping => {
             let session_id = message.session_id.as_deref().unwrap_or("unknown");
+            // Keeps a known session alive
+            state.sessions.get(session_id).await;
             (StatusCode::OK, Json(Message::pong(session_id)))
         },
+        MessageType::Pong => {
+            // Answers the liveness PINGs queued for the session
+            let known = match message.session_id.as_deref() {
+                Some(id) => state.sessions.get(id).await.is_some(),
+                None => false,
+            };
+            if known {
+                (StatusCode::OK, Json(message))
+            } else {
+                (
+                    StatusCode::NOT_FOUND,
+                    Json(Message::reject(RejectionCode::Unknown, "Session not found")),
+                )
+            }
+        },
         MessageType::Close => {
             if let Some(id) = &message.session_id {
                 state.sessions.remove(id).await;
@@ -390,3 +818,80 @@ async fn process_message(
         ),
     }
 }
+
+/// Register or refresh an agent record
+async fn register_agent(
+    State(state): State<Arc<AppState>>,
+    Json(record): Json<AgentRecord>,
+) -> impl IntoResponse {
+    let agent_id = record.agent_id.clone();
+    match state.directory.register(record).await {
+        Ok(ttl) => (
+            StatusCode::OK,
+            Json(serde_json::json!({
+                "agent_id": agent_id,
+                "ttl_secs": ttl.as_secs(),
+            })),
+        ),
+        Err(e) => (directory_error_status(&e), Json(error_body(&e))),
+    }
+}
+
+/// Query registered agents
+async fn query_agents(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<AgentQuery>,
+) -> impl IntoResponse {
+    Json(state.directory.query(&query).await)
+}
+
+/// Resolve an agent by ID
+async fn resolve_agent(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    match state.directory.lookup(&id).await {
+        Some(record) => (StatusCode::OK, Json(serde_json::json!(record))),
+        None => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({"error": "Agent not found"})),
+        ),
+    }
+}
+
+/// Deregister an agent with a signed zero-TTL record or the admin token
+async fn deregister_agent(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+    record: Option<Json<AgentRecord>>,
+) -> Response {
+    let Some(Json(record)) = record else {
+        if let Some(denied) = super::admin::deny(&state, &headers) {
+            return denied;
+        }
+        state.directory.deregister(&id).await;
+        return StatusCode::NO_CONTENT.into_response();
+    };
+
+    if record.agent_id != id {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({"error": "Record is for another agent"})),
+        )
+            .into_response();
+    }
+    match state.directory.deregister_signed(record).await {
+        Ok(_) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => (directory_error_status(&e), Json(error_body(&e))).into_response(),
+    }
+}
+
+/// HTTP status for a rejected directory record
+fn directory_error_status(e: &crate::M2MError) -> StatusCode {
+    match e {
+        crate::M2MError::Crypto(_) => StatusCode::UNAUTHORIZED,
+        crate::M2MError::Overloaded(_) => StatusCode::SERVICE_UNAVAILABLE,
+        _ => StatusCode::BAD_REQUEST,
+    }
+}
@@ -0,0 +1,281 @@
+//! Transparent M2M decompression for any Axum service.
+//!
+//! [`M2MLayer`] makes an existing router M2M-capable without touching its
+//! handlers:
+//!
+//! - Request bodies in an M2M wire format (`#M2M|1|`, `#M2M[v3.0]|`,
+//!   `#TK|`, or the legacy `#T1|`) are decompressed before the handler
+//!   sees them, and their `Content-Type` becomes `application/json`.
+//!   Other bodies pass through unchanged.
+//! - JSON responses are compressed with automatic algorithm selection when
+//!   the request sent `Accept: application/x-m2m`. They are returned with
+//!   `Content-Type: application/x-m2m`, unless compression would enlarge
+//!   them. Event streams and other content types are never buffered.
+//! - W3C trace context crosses the M2M hop: a `#M2M|1|` request frame
+//!   carrying one sets the `traceparent` header the handler sees (unless
+//!   already present), and M2M-framed responses carry the request's
+//!   `traceparent` back to the client.
+//!
+//! ```rust,ignore
+//! use axum::{routing::post, Router};
+//! use m2m::server::M2MLayer;
+//!
+//! let app = Router::new()
+//!     .route("/v1/chat/completions", post(chat))
+//!     .layer(M2MLayer::new());
+//! ```
+//!
+//! Malformed payloads are rejected with the same JSON error body as the
+//! server's `/decompress` endpoint. Only text, JSON, `application/x-m2m`
+//! and untyped request bodies are inspected; uploads such as
+//! `multipart/form-data` are forwarded untouched.
+
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use axum::{
+    body::{to_bytes, Body, Bytes},
+    extract::Request,
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use futures::future::BoxFuture;
+use tower::{Layer, Service};
+
+use super::handlers::{codec_error_status, error_body};
+use crate::codec::{self, Algorithm, CodecEngine, M2MFrame, TokenCodec, TraceContext};
+use crate::error::Result;
+
+/// Media type of M2M-compressed HTTP bodies
+pub const M2M_CONTENT_TYPE: &str = "application/x-m2m";
+
+/// Default largest request body inspected (2 MiB, Axum's default limit)
+pub const DEFAULT_MAX_BODY_SIZE: usize = 2 * 1024 * 1024;
+
+/// Layer adding transparent M2M decompression and response compression
+#[derive(Clone)]
+pub struct M2MLayer {
+    /// Codec for both directions
+    engine: Arc<CodecEngine>,
+    /// Largest request body read
+    max_body_size: usize,
+}
+
+impl Default for M2MLayer {
+    fn default() -> Self {
+        Self {
+            engine: Arc::new(CodecEngine::new()),
+            max_body_size: DEFAULT_MAX_BODY_SIZE,
+        }
+    }
+}
+
+impl M2MLayer {
+    /// Create a layer with a default codec engine
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Use a configured engine (profile, limits, schema validation)
+    pub fn with_engine(mut self, engine: CodecEngine) -> Self {
+        self.engine = Arc::new(engine);
+        self
+    }
+
+    /// Set the largest request body read (larger bodies get 413)
+    pub fn with_max_body_size(mut self, max_body_size: usize) -> Self {
+        self.max_body_size = max_body_size;
+        self
+    }
+}
+
+impl<S> Layer<S> for M2MLayer {
+    type Service = M2MService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        M2MService {
+            inner,
+            engine: Arc::clone(&self.engine),
+            max_body_size: self.max_body_size,
+        }
+    }
+}
+
+/// Service produced by [`M2MLayer`]
+#[derive(Clone)]
+pub struct M2MService<S> {
+    /// Wrapped service
+    inner: S,
+    /// Codec for both directions
+    engine: Arc<CodecEngine>,
+    /// Largest request body read
+    max_body_size: usize,
+}
+
+impl<S> Service<Request> for M2MService<S>
+where
+    S: Service<Request, Response = Response> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, std::result::Result<Response, S::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<std::result::Result<(), S::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        // The ready service handles this request; the clone serves the next
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let engine = Arc::clone(&self.engine);
+        let max_body_size = self.max_body_size;
+
+        Box::pin(async move {
+            let accepts_m2m = accepts_m2m(request.headers());
+            let request = if inspects_body(request.headers()) {
+                match decode_request(&engine, request, max_body_size).await {
+                    Ok(request) => request,
+                    Err(rejection) => return Ok(rejection),
+                }
+            } else {
+                request
+            };
+
+            let trace = trace_context(request.headers());
+            let response = inner.call(request).await?;
+            if !accepts_m2m {
+                return Ok(response);
+            }
+            Ok(encode_response(&engine, response, trace).await)
+        })
+    }
+}
+
+/// Whether the client accepts M2M-compressed responses
+fn accepts_m2m(headers: &HeaderMap) -> bool {
+    headers
+        .get_all(header::ACCEPT)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|media| media.split(';').next().unwrap_or_default().trim() == M2M_CONTENT_TYPE)
+}
+
+/// Media type of a `Content-Type` header, without parameters
+fn media_type(headers: &HeaderMap) -> Option<&str> {
+    let value = headers.get(header::CONTENT_TYPE)?.to_str().ok()?;
+    Some(value.split(';').next().unwrap_or_default().trim())
+}
+
+/// Valid W3C trace context of the `traceparent` header
+fn trace_context(headers: &HeaderMap) -> Option<TraceContext> {
+    headers
+        .get(TraceContext::HEADER)?
+        .to_str()
+        .ok()?
+        .parse()
+        .ok()
+}
+
+/// Whether a request body may carry an M2M payload
+fn inspects_body(headers: &HeaderMap) -> bool {
+    match media_type(headers) {
+        None => true,
+        Some(media) => {
+            media == M2M_CONTENT_TYPE || media == "application/json" || media.starts_with("text/")
+        },
+    }
+}
+
+/// Decompress an M2M request body, or return the rejection to send
+async fn decode_request(
+    engine: &CodecEngine,
+    request: Request,
+    max_body_size: usize,
+) -> std::result::Result<Request, Response> {
+    let (mut parts, body) = request.into_parts();
+    let Ok(bytes) = to_bytes(body, max_body_size).await else {
+        return Err(StatusCode::PAYLOAD_TOO_LARGE.into_response());
+    };
+
+    let json = match decode_body(engine, &bytes) {
+        Ok(Some(json)) => json,
+        Ok(None) => return Ok(Request::from_parts(parts, Body::from(bytes))),
+        Err(e) => return Err((codec_error_status(&e), Json(error_body(&e))).into_response()),
+    };
+
+    parts.headers.remove(header::CONTENT_LENGTH);
+    parts.headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("application/json"),
+    );
+    let frame_trace = std::str::from_utf8(&bytes)
+        .ok()
+        .and_then(M2MFrame::peek_trace_context);
+    if let Some(context) = frame_trace.filter(|_| trace_context(&parts.headers).is_none()) {
+        if let Ok(value) = HeaderValue::from_str(&context.to_string()) {
+            parts.headers.insert(TraceContext::HEADER, value);
+        }
+    }
+    Ok(Request::from_parts(parts, Body::from(json)))
+}
+
+/// Decompressed body, or `None` if it is not in an M2M wire format
+fn decode_body(engine: &CodecEngine, bytes: &Bytes) -> Result<Option<String>> {
+    let Ok(text) = std::str::from_utf8(bytes) else {
+        return Ok(None);
+    };
+
+    if text.starts_with(codec::TOKEN_PREFIX) {
+        return Ok(Some(TokenCodec::new().decompress(text)?.to_string()));
+    }
+    if codec::is_m2m_format(text) || codec::is_v2_frame(text) {
+        return engine.decompress(text).map(Some);
+    }
+    Ok(None)
+}
+
+/// Compress a JSON response for a client accepting M2M
+///
+/// M2M frames carry `trace` when the request had one.
+async fn encode_response(
+    engine: &CodecEngine,
+    response: Response,
+    trace: Option<TraceContext>,
+) -> Response {
+    let compressible = media_type(response.headers()) == Some("application/json")
+        && !response.headers().contains_key(header::CONTENT_ENCODING);
+    if !compressible {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let Ok(bytes) = to_bytes(body, usize::MAX).await else {
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    };
+    parts
+        .headers
+        .append(header::VARY, HeaderValue::from_static("accept"));
+
+    let compressed = std::str::from_utf8(&bytes).ok().and_then(|json| {
+        let (result, algorithm) = engine.compress_auto(json).ok()?;
+        match (algorithm, trace) {
+            (Algorithm::None, _) => None,
+            (Algorithm::M2M, Some(context)) => engine.compress_with_trace(json, &context).ok(),
+            _ => Some(result),
+        }
+    });
+    let Some(result) = compressed else {
+        return Response::from_parts(parts, Body::from(bytes));
+    };
+
+    parts.headers.remove(header::CONTENT_LENGTH);
+    parts.headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static(M2M_CONTENT_TYPE),
+    );
+    Response::from_parts(parts, Body::from(result.data))
+}
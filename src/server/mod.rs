@@ -4,6 +4,15 @@
 //! - Session management (handshake)
 //! - Compression/decompression
 //! - Security scanning
+//! - Optional session persistence ([`SessionStore`]), shared by a pool of
+//!   servers with the `cluster` feature ([`ClusterConfig`])
+//! - Optional request audit logging ([`AuditLog`])
+//! - Per-minute stats history ([`StatsRecorder`])
+//! - Token-protected session inspection under `/admin`
+//! - Pre-flight cost estimates (`/v1/estimate`)
+//! - Optional agent-to-agent relay (`/v1/relay`)
+//! - Optional quarantine of blocked payloads for review ([`Quarantine`])
+//! - [`M2MLayer`], transparent M2M bodies for any Axum router
 //!
 //! # Example
 //!
@@ -15,10 +24,44 @@
 //! server.run().await?;
 //! ```
 
+mod admin;
+mod audit;
 mod config;
+mod estimate;
 mod handlers;
+mod layer;
+mod privacy;
+mod quarantine;
+mod relay;
 mod state;
+mod stats;
+mod store;
 
+pub use audit::{
+    AuditConfig, AuditEvent, AuditLog, AuditRecord, AuditScan, AuditSink, AuditTarget,
+    JsonlAuditSink, RedactionLevel, WebhookAuditSink,
+};
 pub use config::ServerConfig;
+pub use estimate::{CostEstimate, EstimateRequest, ESTIMATED_COST_HEADER};
 pub use handlers::{create_router, health_check};
-pub use state::{AppState, SessionManager};
+pub use layer::{M2MLayer, M2MService, DEFAULT_MAX_BODY_SIZE, M2M_CONTENT_TYPE};
+pub use privacy::{StatsPrivacy, DEFAULT_BYTE_SENSITIVITY};
+pub use quarantine::{Quarantine, QuarantineConfig, QuarantineItem, DEFAULT_QUARANTINE_CAPACITY};
+pub use relay::{RelayHub, DEFAULT_RELAY_INBOX, SESSION_AUTH_HEADER};
+pub use state::{
+    AppState, SessionEvent, SessionEventKind, SessionInfo, SessionManager,
+    DEFAULT_MAX_MISSED_PONGS, DEFAULT_SWEEP_INTERVAL,
+};
+#[cfg(feature = "sled")]
+pub use stats::SledStatsSink;
+pub use stats::{
+    AlgorithmStats, JsonlStatsSink, MemoryStatsSink, StatsRecorder, StatsRollup, StatsSink,
+};
+#[cfg(feature = "cluster")]
+pub use store::RedisSessionStore;
+#[cfg(feature = "sled")]
+pub use store::SledSessionStore;
+pub use store::{
+    ClusterConfig, FileSessionStore, MemorySessionStore, SessionStore, DEFAULT_CLUSTER_CACHE_TTL,
+    DEFAULT_CLUSTER_KEY_PREFIX,
+};
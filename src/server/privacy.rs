@@ -0,0 +1,191 @@
+//! Differential privacy for public stats.
+//!
+//! Operators sharing a usage dashboard across tenants may not want exact
+//! traffic volumes to be readable from `/stats/history`. With
+//! [`StatsPrivacy`] configured, every count in the returned rollups gets
+//! Laplace noise of scale `sensitivity / epsilon`: one request changes a
+//! request count by at most 1 and a byte count by at most the byte
+//! sensitivity, so the published numbers are `epsilon`-differentially
+//! private per request and per field.
+//!
+//! Noise for a given minute and field is drawn from a keyed hash (SipHash
+//! with a random per-process key), not fresh randomness, so repeating a
+//! query returns the same noisy value and averaging many queries does not
+//! recover the exact count. Requests with the admin token see exact values.
+
+use std::collections::hash_map::RandomState;
+use std::hash::BuildHasher;
+
+use super::stats::{AlgorithmStats, StatsRollup};
+use crate::error::{M2MError, Result};
+
+/// Default bound on one request's bytes (64 KiB)
+pub const DEFAULT_BYTE_SENSITIVITY: u64 = 64 * 1024;
+
+/// Laplace noise for `/stats/history`
+#[derive(Debug, Clone)]
+pub struct StatsPrivacy {
+    /// Privacy budget per published value (smaller = noisier)
+    epsilon: f64,
+    /// Bytes one request can contribute
+    byte_sensitivity: u64,
+    /// Noise key
+    key: RandomState,
+}
+
+impl StatsPrivacy {
+    /// Add noise with privacy budget `epsilon` (> 0)
+    pub fn new(epsilon: f64) -> Result<Self> {
+        if !(epsilon.is_finite() && epsilon > 0.0) {
+            return Err(M2MError::Config(format!(
+                "Stats privacy epsilon must be positive, got {epsilon}"
+            )));
+        }
+        Ok(Self {
+            epsilon,
+            byte_sensitivity: DEFAULT_BYTE_SENSITIVITY,
+            key: RandomState::new(),
+        })
+    }
+
+    /// Bound on the bytes one request contributes (default: 64 KiB)
+    ///
+    /// Set it to the largest payload the server accepts for a strict
+    /// guarantee; smaller values give less noisy byte counts.
+    pub fn with_byte_sensitivity(mut self, bytes: u64) -> Self {
+        self.byte_sensitivity = bytes.max(1);
+        self
+    }
+
+    /// Privacy budget per published value
+    pub fn epsilon(&self) -> f64 {
+        self.epsilon
+    }
+
+    /// Bytes one request can contribute
+    pub fn byte_sensitivity(&self) -> u64 {
+        self.byte_sensitivity
+    }
+
+    /// Replace every count in `rollup` with a noisy count
+    ///
+    /// Bytes saved is derived from the noisy original and compressed bytes
+    /// so the rollup stays consistent.
+    pub fn apply(&self, rollup: &mut StatsRollup) {
+        let minute = rollup.minute;
+        let bytes = self.byte_sensitivity as f64;
+
+        rollup.requests = self.noisy(minute, "requests", rollup.requests, 1.0);
+        rollup.threat_blocks = self.noisy(minute, "threat_blocks", rollup.threat_blocks, 1.0);
+        rollup.errors = self.noisy(minute, "errors", rollup.errors, 1.0);
+        rollup.original_bytes = self.noisy(minute, "original_bytes", rollup.original_bytes, bytes);
+        rollup.compressed_bytes =
+            self.noisy(minute, "compressed_bytes", rollup.compressed_bytes, bytes);
+        rollup.bytes_saved = rollup
+            .original_bytes
+            .saturating_sub(rollup.compressed_bytes);
+
+        for (algorithm, stats) in &mut rollup.algorithms {
+            *stats = self.noisy_stats(minute, algorithm.name(), stats);
+        }
+        for (model, stats) in &mut rollup.models {
+            *stats = self.noisy_stats(minute, &format!("model.{model}"), stats);
+        }
+    }
+
+    /// Breakdown counters plus noise, keyed by `prefix`
+    fn noisy_stats(&self, minute: u64, prefix: &str, stats: &AlgorithmStats) -> AlgorithmStats {
+        let bytes = self.byte_sensitivity as f64;
+        let field = |name: &str| format!("{prefix}.{name}");
+        AlgorithmStats {
+            requests: self.noisy(minute, &field("requests"), stats.requests, 1.0),
+            original_bytes: self.noisy(
+                minute,
+                &field("original_bytes"),
+                stats.original_bytes,
+                bytes,
+            ),
+            compressed_bytes: self.noisy(
+                minute,
+                &field("compressed_bytes"),
+                stats.compressed_bytes,
+                bytes,
+            ),
+        }
+    }
+
+    /// `value` plus Laplace noise, rounded and clamped at zero
+    fn noisy(&self, minute: u64, field: &str, value: u64, sensitivity: f64) -> u64 {
+        let scale = sensitivity / self.epsilon;
+        let noisy = value as f64 + laplace(self.uniform(minute, field), scale);
+        if noisy <= 0.0 {
+            0
+        } else {
+            noisy.round() as u64
+        }
+    }
+
+    /// Deterministic uniform sample in (-0.5, 0.5) for a minute and field
+    fn uniform(&self, minute: u64, field: &str) -> f64 {
+        // 53 random bits, offset by half a step so neither end is reachable
+        let bits = self.key.hash_one((minute, field)) >> 11;
+        (bits as f64 + 0.5) / (1u64 << 53) as f64 - 0.5
+    }
+}
+
+/// Laplace(0, scale) sample from a uniform sample in (-0.5, 0.5)
+fn laplace(u: f64, scale: f64) -> f64 {
+    -scale * u.signum() * (1.0 - 2.0 * u.abs()).ln()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rejects_invalid_epsilon() {
+        assert!(StatsPrivacy::new(0.0).is_err());
+        assert!(StatsPrivacy::new(-1.0).is_err());
+        assert!(StatsPrivacy::new(f64::NAN).is_err());
+        assert!(StatsPrivacy::new(0.5).is_ok());
+    }
+
+    #[test]
+    fn test_noise_is_stable_and_centered() {
+        let privacy = StatsPrivacy::new(1.0).unwrap();
+
+        // Same minute and field: same answer on every query
+        assert_eq!(
+            privacy.noisy(60, "requests", 1000, 1.0),
+            privacy.noisy(60, "requests", 1000, 1.0)
+        );
+
+        // Across many minutes the noise averages out near zero
+        let minutes = 10_000u64;
+        let total: i64 = (0..minutes)
+            .map(|m| privacy.noisy(m * 60, "requests", 1000, 1.0) as i64 - 1000)
+            .sum();
+        let mean = total as f64 / minutes as f64;
+        assert!(mean.abs() < 0.1, "mean noise {mean}");
+    }
+
+    #[test]
+    fn test_apply_keeps_rollup_consistent() {
+        let privacy = StatsPrivacy::new(0.1).unwrap();
+        let mut rollup = StatsRollup {
+            minute: 120,
+            requests: 3,
+            original_bytes: 9000,
+            compressed_bytes: 4000,
+            bytes_saved: 5000,
+            ..StatsRollup::default()
+        };
+        privacy.apply(&mut rollup);
+        assert_eq!(
+            rollup.bytes_saved,
+            rollup
+                .original_bytes
+                .saturating_sub(rollup.compressed_bytes)
+        );
+    }
+}
@@ -0,0 +1,455 @@
+//! Agent-to-agent relay.
+//!
+//! Agents that cannot reach each other directly (behind NAT, no listening
+//! port) can exchange DATA through a shared server. The sender addresses a
+//! DATA message on its own session to another agent with a
+//! [`RelayHeader`]; the server queues it for the destination's session,
+//! which collects it with `GET /v1/relay/:session_id`.
+//!
+//! # Identity
+//!
+//! Only sessions established with a credential (see
+//! [`ServerConfig::with_authenticator`](super::ServerConfig::with_authenticator))
+//! take part. Destinations are looked up by the authenticated
+//! [`Principal`](crate::protocol::Principal) name within the sender's
+//! tenant, never by the agent ID a peer claims in its HELLO, and
+//! [`RelayHeader::from`] is set to the sender's principal name, so senders
+//! cannot impersonate another agent. Collecting the inbox of such a
+//! session requires the same credential in the [`SESSION_AUTH_HEADER`]
+//! header (see [`HelloCredential::authorize`](crate::protocol::HelloCredential::authorize)).
+//!
+//! # Hop Semantics
+//!
+//! Each hop is its own session. The server decodes the payload with the
+//! sender's session and re-encodes it with the destination's, so the
+//! destination receives a frame in its own negotiated algorithm and never
+//! sees the sender's wire bytes. The server therefore sees the plaintext;
+//! agents needing end-to-end confidentiality encrypt the payload for each
+//! other before sending. A W3C trace context on the sender's frame is
+//! carried over to the re-encoded one, so the trace spans both agents.
+//!
+//! # Broadcast
+//!
+//! A BROADCAST message is sealed once under a group key (see
+//! [`GroupKey`](crate::codec::m2m::crypto::GroupKey)) and lists its
+//! recipients. The relay queues the unchanged ciphertext for each
+//! recipient's session instead of re-encoding it, so the server never sees
+//! the plaintext and the sender pays for one encryption instead of N.
+//!
+//! Relaying is disabled unless enabled with
+//! [`ServerConfig::with_relay`](super::ServerConfig::with_relay).
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+use axum::{
+    extract::{Json, Path, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
+
+use super::state::AppState;
+use crate::protocol::{
+    BroadcastPayload, Message, Principal, RejectionCode, RejectionInfo, RelayHeader, Session,
+    DEFAULT_RETRY_AFTER_SECS,
+};
+
+/// Messages queued per destination session before the relay refuses more
+pub const DEFAULT_RELAY_INBOX: usize = 256;
+
+/// Header carrying the session credential (JSON [`HelloAuth`](crate::protocol::HelloAuth))
+/// when collecting relayed messages
+pub const SESSION_AUTH_HEADER: &str = "x-m2m-auth";
+
+/// Relay routes (merged into the main router)
+pub(super) fn routes() -> Router<Arc<AppState>> {
+    Router::new().route("/v1/relay/:session_id", get(collect))
+}
+
+/// Relayed messages waiting for their destination sessions
+#[derive(Debug)]
+pub struct RelayHub {
+    /// Queued DATA by destination session ID
+    inboxes: Mutex<HashMap<String, VecDeque<Message>>>,
+    /// Maximum queued messages per session
+    capacity: usize,
+}
+
+impl Default for RelayHub {
+    fn default() -> Self {
+        Self::new(DEFAULT_RELAY_INBOX)
+    }
+}
+
+impl RelayHub {
+    /// Create a hub queueing at most `capacity` messages per session
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            inboxes: Mutex::new(HashMap::new()),
+            capacity,
+        }
+    }
+
+    /// Queue a message for a session (returns `false` if its inbox is full)
+    pub fn push(&self, session_id: &str, message: Message) -> bool {
+        let Ok(mut inboxes) = self.inboxes.lock() else {
+            return false;
+        };
+        let inbox = inboxes.entry(session_id.to_string()).or_default();
+        if inbox.len() >= self.capacity {
+            return false;
+        }
+        inbox.push_back(message);
+        true
+    }
+
+    /// Queue messages for several sessions, all or none
+    ///
+    /// Capacity is checked for every destination under one lock before
+    /// anything is queued. Returns the index of the first message whose
+    /// inbox has no room, in which case nothing was queued.
+    pub fn push_all(&self, messages: Vec<(String, Message)>) -> Result<(), usize> {
+        let Ok(mut inboxes) = self.inboxes.lock() else {
+            return Err(0);
+        };
+
+        let mut queued: HashMap<&str, usize> = HashMap::new();
+        for (index, (session_id, _)) in messages.iter().enumerate() {
+            let pending = inboxes.get(session_id).map_or(0, VecDeque::len);
+            let adding = queued.entry(session_id.as_str()).or_default();
+            *adding += 1;
+            if pending + *adding > self.capacity {
+                return Err(index);
+            }
+        }
+
+        for (session_id, message) in messages {
+            inboxes.entry(session_id).or_default().push_back(message);
+        }
+        Ok(())
+    }
+
+    /// Take all messages queued for a session, oldest first
+    pub fn drain(&self, session_id: &str) -> Vec<Message> {
+        self.inboxes
+            .lock()
+            .ok()
+            .and_then(|mut inboxes| inboxes.remove(session_id))
+            .map(Vec::from)
+            .unwrap_or_default()
+    }
+
+    /// Number of messages queued for a session
+    pub fn pending(&self, session_id: &str) -> usize {
+        self.inboxes
+            .lock()
+            .ok()
+            .and_then(|inboxes| inboxes.get(session_id).map(VecDeque::len))
+            .unwrap_or(0)
+    }
+}
+
+/// REJECT for a full relay inbox, asking the sender to retry shortly
+fn inbox_full(agent: &str) -> (StatusCode, Json<Message>) {
+    let info = RejectionInfo::new(
+        RejectionCode::RateLimited,
+        &format!("Relay inbox of {agent} is full"),
+    )
+    .with_retry_after(DEFAULT_RETRY_AFTER_SECS);
+    (
+        StatusCode::SERVICE_UNAVAILABLE,
+        Json(Message::rejection(info)),
+    )
+}
+
+/// REJECT for a relay sender without an authenticated principal
+fn unauthenticated() -> (StatusCode, Json<Message>) {
+    (
+        StatusCode::FORBIDDEN,
+        Json(Message::reject(
+            RejectionCode::SecurityPolicy,
+            "Relay requires an authenticated session",
+        )),
+    )
+}
+
+/// Whether `source` already received this DATA (same ID or idempotency key)
+pub(super) fn is_retry(source: &Session, message: &Message) -> bool {
+    [message.message_id(), message.idempotency_key()]
+        .into_iter()
+        .flatten()
+        .any(|id| source.was_seen(id))
+}
+
+/// Forward decoded DATA from `source` to the agent named in `header`
+///
+/// The relayed DATA keeps the sender's message ID, idempotency key and
+/// trace context. Returns the response for the sender: 202 with a PONG
+/// once queued.
+pub(super) async fn forward(
+    state: &AppState,
+    source: &Session,
+    header: &RelayHeader,
+    message: &Message,
+    content: &str,
+) -> (StatusCode, Json<Message>) {
+    let reject = |status: StatusCode, code: RejectionCode, reason: &str| {
+        (status, Json(Message::reject(code, reason)))
+    };
+
+    if !state.config.relay_enabled {
+        return reject(
+            StatusCode::FORBIDDEN,
+            RejectionCode::SecurityPolicy,
+            "Relay is disabled on this server",
+        );
+    }
+
+    let Some(from) = source.principal() else {
+        return unauthenticated();
+    };
+
+    let destination = match state
+        .sessions
+        .find_by_principal(from.tenant.as_deref(), &header.to)
+        .await
+    {
+        Some(id) => state.sessions.get(&id).await,
+        None => None,
+    };
+    let Some(mut destination) = destination else {
+        return reject(
+            StatusCode::NOT_FOUND,
+            RejectionCode::Unknown,
+            &format!("Agent {} has no session on this server", header.to),
+        );
+    };
+
+    // Re-encode for the destination's session
+    let relayed = match message.trace_context() {
+        Some(context) => destination.compress_with_trace(content, &context),
+        None => destination.compress(content),
+    };
+    let mut relayed = match relayed {
+        Ok(message) => message,
+        Err(e) => {
+            return (
+                StatusCode::SERVICE_UNAVAILABLE,
+                Json(Message::reject_error(&e)),
+            )
+        },
+    };
+    relayed.relay = Some(RelayHeader {
+        to: header.to.clone(),
+        from: Some(from.name.clone()),
+    });
+    if let Some(id) = message.message_id() {
+        relayed = relayed.with_message_id(id);
+    }
+    if let Some(key) = message.idempotency_key() {
+        relayed = relayed.with_idempotency_key(key);
+    }
+
+    if !state.relay.push(destination.id(), relayed) {
+        return inbox_full(&header.to);
+    }
+    state.sessions.update(&destination).await;
+
+    tracing::debug!(
+        "Relayed {} bytes from session {} to {}",
+        content.len(),
+        source.id(),
+        header.to
+    );
+    (StatusCode::ACCEPTED, Json(Message::pong(source.id())))
+}
+
+/// Deliver a BROADCAST from `source` to every recipient's inbox
+///
+/// All recipients must have a session and room in their inbox; otherwise
+/// nothing is queued, so a retry never delivers duplicates. Returns 202
+/// with a PONG once queued.
+pub(super) async fn broadcast(
+    state: &AppState,
+    source: &Session,
+    message: &Message,
+    payload: &BroadcastPayload,
+) -> (StatusCode, Json<Message>) {
+    let reject = |status: StatusCode, code: RejectionCode, reason: &str| {
+        (status, Json(Message::reject(code, reason)))
+    };
+
+    if !state.config.relay_enabled {
+        return reject(
+            StatusCode::FORBIDDEN,
+            RejectionCode::SecurityPolicy,
+            "Relay is disabled on this server",
+        );
+    }
+    if payload.recipients.is_empty() {
+        return reject(
+            StatusCode::BAD_REQUEST,
+            RejectionCode::Unknown,
+            "Broadcast has no recipients",
+        );
+    }
+
+    let Some(from) = source.principal() else {
+        return unauthenticated();
+    };
+
+    let mut destinations = Vec::with_capacity(payload.recipients.len());
+    for recipient in &payload.recipients {
+        let Some(id) = state
+            .sessions
+            .find_by_principal(from.tenant.as_deref(), recipient)
+            .await
+        else {
+            return reject(
+                StatusCode::NOT_FOUND,
+                RejectionCode::Unknown,
+                &format!("Agent {recipient} has no session on this server"),
+            );
+        };
+        destinations.push((recipient, id));
+    }
+
+    // Same ciphertext for everyone; only the routing differs
+    let queued = destinations
+        .iter()
+        .map(|(recipient, session_id)| {
+            let mut relayed = message.clone();
+            relayed.session_id = Some(session_id.clone());
+            relayed.relay = Some(RelayHeader {
+                to: (*recipient).clone(),
+                from: Some(from.name.clone()),
+            });
+            (session_id.clone(), relayed)
+        })
+        .collect();
+    if let Err(index) = state.relay.push_all(queued) {
+        return inbox_full(destinations[index].0);
+    }
+
+    tracing::debug!(
+        "Broadcast {} bytes from session {} to {} agents",
+        payload.content.len(),
+        source.id(),
+        payload.recipients.len()
+    );
+    (StatusCode::ACCEPTED, Json(Message::pong(source.id())))
+}
+
+/// Collect relayed messages for a session
+///
+/// Sessions established with a credential must present it again in
+/// [`SESSION_AUTH_HEADER`]; anonymous sessions only receive liveness PINGs
+/// and CLOSEs, never relayed DATA.
+async fn collect(
+    State(state): State<Arc<AppState>>,
+    Path(session_id): Path<String>,
+    headers: HeaderMap,
+) -> Response {
+    let Some(session) = state.sessions.get(&session_id).await else {
+        // Drop anything queued for a session that no longer exists
+        state.relay.drain(&session_id);
+        return (
+            StatusCode::NOT_FOUND,
+            Json(Message::reject(RejectionCode::Unknown, "Session not found")),
+        )
+            .into_response();
+    };
+    if let Some(principal) = session.principal() {
+        if !authorized(&state, &session_id, principal, &headers) {
+            return (
+                StatusCode::UNAUTHORIZED,
+                Json(Message::reject(
+                    RejectionCode::SecurityPolicy,
+                    "Missing or invalid session credential",
+                )),
+            )
+                .into_response();
+        }
+    }
+
+    let messages = state.relay.drain(&session_id);
+    Json(serde_json::json!({
+        "count": messages.len(),
+        "messages": messages,
+    }))
+    .into_response()
+}
+
+/// Whether `headers` carry a credential for `principal` on `session_id`
+#[cfg(feature = "crypto")]
+fn authorized(
+    state: &AppState,
+    session_id: &str,
+    principal: &Principal,
+    headers: &HeaderMap,
+) -> bool {
+    let Some(ref authenticator) = state.config.authenticator else {
+        return false;
+    };
+    headers
+        .get(SESSION_AUTH_HEADER)
+        .and_then(|value| serde_json::from_slice(value.as_bytes()).ok())
+        .and_then(|auth| authenticator.authenticate_session(session_id, &auth).ok())
+        .is_some_and(|presented| presented == *principal)
+}
+
+/// Whether `headers` carry a credential for `principal` on `session_id`
+///
+/// Principals only exist with the `crypto` feature.
+#[cfg(not(feature = "crypto"))]
+fn authorized(_: &AppState, _: &str, _: &Principal, _: &HeaderMap) -> bool {
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::Algorithm;
+
+    #[test]
+    fn test_inbox_capacity_and_drain() {
+        let hub = RelayHub::new(2);
+        let message = || Message::data("dest", Algorithm::None, "hi".to_string());
+
+        assert!(hub.push("dest", message()));
+        assert!(hub.push("dest", message()));
+        assert!(!hub.push("dest", message()));
+        assert_eq!(hub.pending("dest"), 2);
+
+        assert_eq!(hub.drain("dest").len(), 2);
+        assert_eq!(hub.pending("dest"), 0);
+        assert!(hub.drain("other").is_empty());
+    }
+
+    #[test]
+    fn test_push_all_queues_nothing_when_one_inbox_is_full() {
+        let hub = RelayHub::new(2);
+        let message = |to: &str| Message::data(to, Algorithm::None, "hi".to_string());
+        assert!(hub.push("full", message("full")));
+        assert!(hub.push("full", message("full")));
+
+        let batch = |ids: &[&str]| {
+            ids.iter()
+                .map(|id| (id.to_string(), message(id)))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(hub.push_all(batch(&["a", "b", "full"])), Err(2));
+        assert_eq!(hub.pending("a"), 0);
+        assert_eq!(hub.pending("b"), 0);
+        assert_eq!(hub.pending("full"), 2);
+
+        // Repeated destinations count against the same inbox
+        assert_eq!(hub.push_all(batch(&["a", "a", "a"])), Err(2));
+        assert_eq!(hub.pending("a"), 0);
+
+        assert_eq!(hub.push_all(batch(&["a", "b"])), Ok(()));
+        assert_eq!((hub.pending("a"), hub.pending("b")), (1, 1));
+    }
+}
@@ -2,15 +2,29 @@
 
 use std::collections::HashMap;
 use std::sync::Arc;
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-use tokio::sync::RwLock;
+use serde::Serialize;
+use tokio::sync::{broadcast, RwLock};
 
+use super::audit::{AuditEvent, AuditLog};
 use super::config::ServerConfig;
-use crate::codec::CodecEngine;
+use super::quarantine::Quarantine;
+use super::relay::RelayHub;
+use super::stats::{MemoryStatsSink, StatsRecorder, StatsSink};
+use super::store::{ClusterConfig, SessionStore};
+use crate::codec::{CodecEngine, CodecService, DictionaryStore};
+use crate::context::ContextStore;
+use crate::discovery::AgentDirectory;
 use crate::inference::HydraModel;
-use crate::protocol::{Capabilities, Session};
-use crate::security::SecurityScanner;
+use crate::models::ModelRegistry;
+#[cfg(feature = "crypto")]
+use crate::protocol::HelloAuthenticator;
+use crate::protocol::{
+    Capabilities, CloseReason, Message, NegotiatedCaps, ReplayGuard, Session, SessionSnapshot,
+    SessionState, SessionStats, SESSION_TIMEOUT_SECS,
+};
+use crate::security::{ScanCache, SecurityScanner};
 
 /// Application state shared across handlers
 pub struct AppState {
@@ -20,18 +34,47 @@ pub struct AppState {
     pub sessions: SessionManager,
     /// Codec engine
     pub codec: CodecEngine,
-    /// Security scanner
-    pub scanner: SecurityScanner,
+    /// Load-shedding async front for `codec`
+    pub codec_service: CodecService,
+    /// Security scanner, also run on `/message` DATA
+    pub scanner: Arc<SecurityScanner>,
+    /// Agent directory
+    pub directory: AgentDirectory,
+    /// Request audit log (optional)
+    pub audit: Option<AuditLog>,
+    /// Per-minute stats history
+    pub stats: StatsRecorder,
+    /// Anti-replay cache for 0-RTT HELLO tokens
+    pub replay_guard: ReplayGuard,
+    /// Relayed messages awaiting collection
+    pub relay: RelayHub,
+    /// Blocked payloads awaiting review (optional)
+    pub quarantine: Option<Quarantine>,
     /// Hydra model (optional)
     pub model: Option<HydraModel>,
+    /// Model metadata and pricing for cost estimates
+    pub models: ModelRegistry,
     /// Server start time
     pub start_time: Instant,
 }
 
 impl AppState {
     /// Create new application state
-    pub fn new(config: ServerConfig) -> Self {
-        let scanner = if config.security_enabled {
+    pub fn new(mut config: ServerConfig) -> Self {
+        if let Err(e) = config.validate() {
+            tracing::warn!("Relay disabled: {e}");
+            config.relay_enabled = false;
+        }
+        let model = config
+            .model_path
+            .as_ref()
+            .and_then(|path| HydraModel::load(path).ok())
+            .map(|model| match config.model_accuracy_floor {
+                Some(floor) => model.with_accuracy_floor(floor),
+                None => model,
+            });
+
+        let mut scanner = if config.security_enabled {
             if config.security_blocking {
                 SecurityScanner::new().with_blocking(config.block_threshold)
             } else {
@@ -40,22 +83,158 @@ impl AppState {
         } else {
             SecurityScanner::new()
         };
+        if let Some(model) = model.as_ref().filter(|_| config.ml_security) {
+            scanner = scanner
+                .with_model(model.clone())
+                .with_ml_weight(config.ml_weight);
+        }
+        if let Some(ref policy) = config.security_policy {
+            scanner = scanner.with_policy(policy.clone());
+        }
+        let scanner = Arc::new(scanner.with_cache(Arc::new(ScanCache::default())));
 
-        let model = config
-            .model_path
+        let mut sessions = SessionManager::new()
+            .with_timeout(config.session_timeout)
+            .with_max_missed_pongs(config.max_missed_pongs);
+        if let Some(ref dictionaries) = config.dictionaries {
+            sessions = sessions.with_dictionaries(Arc::clone(dictionaries));
+        }
+        if let Some(ref contexts) = config.contexts {
+            sessions = sessions.with_contexts(Arc::clone(contexts));
+        }
+        if config.security_enabled {
+            sessions = sessions.with_scanner(Arc::clone(&scanner));
+        }
+        #[cfg(feature = "crypto")]
+        if let Some(ref authenticator) = config.authenticator {
+            sessions = sessions.with_authenticator(Arc::clone(authenticator));
+        }
+        if let Some(ref cluster) = config.cluster {
+            match open_cluster_store(cluster) {
+                Ok(store) => sessions = sessions.with_shared_store(store, cluster.cache_ttl),
+                Err(e) => tracing::warn!("Session clustering disabled: {e}"),
+            }
+        } else if let Some(ref path) = config.session_store_path {
+            match open_store(path) {
+                Ok(store) => sessions = sessions.with_store(store),
+                Err(e) => tracing::warn!("Session persistence disabled: {e}"),
+            }
+        }
+
+        let mut directory = AgentDirectory::new();
+        if let Some(ref key) = config.discovery_key {
+            match AgentDirectory::new().with_registration_key(key.clone()) {
+                Ok(signed) => directory = signed,
+                Err(e) => tracing::warn!("Invalid discovery key, registrations unsigned: {e}"),
+            }
+        }
+
+        let audit = config
+            .audit
             .as_ref()
-            .and_then(|path| HydraModel::load(path).ok());
+            .and_then(|audit| match AuditLog::open(audit) {
+                Ok(log) => Some(log),
+                Err(e) => {
+                    tracing::warn!("Audit logging disabled: {e}");
+                    None
+                },
+            });
+
+        let quarantine = config.quarantine.as_ref().and_then(|quarantine| {
+            Quarantine::open(quarantine)
+                .map_err(|e| tracing::warn!("Quarantine disabled: {e}"))
+                .ok()
+        });
+
+        let stats_sink: Box<dyn StatsSink> = match config.stats_store_path {
+            Some(ref path) => open_stats_sink(path).unwrap_or_else(|e| {
+                tracing::warn!("Stats persistence disabled: {e}");
+                Box::new(MemoryStatsSink::new())
+            }),
+            None => Box::new(MemoryStatsSink::new()),
+        };
+
+        let codec = CodecEngine::new()
+            .with_schema_validation(config.validate_schema)
+            .with_profile(config.compression_profile)
+            .with_max_compress_latency(config.max_compress_latency)
+            .with_remove_defaults(config.remove_defaults.clone());
+        let mut codec_service = CodecService::new(codec.clone())
+            .with_queue_depth(config.codec_queue_depth)
+            .with_deadline(config.codec_deadline);
+        if let Some(max) = config.codec_concurrency {
+            codec_service = codec_service.with_max_concurrency(max);
+        }
 
         Self {
             config,
-            sessions: SessionManager::new(),
-            codec: CodecEngine::new(),
+            sessions,
+            codec,
+            codec_service,
             scanner,
+            directory,
+            audit,
+            stats: StatsRecorder::new(stats_sink),
+            replay_guard: ReplayGuard::new(),
+            relay: RelayHub::default(),
+            quarantine,
             model,
+            models: ModelRegistry::new(),
             start_time: Instant::now(),
         }
     }
 
+    /// Record an audit event in the stats history and audit log (if enabled)
+    pub fn audit(&self, event: &AuditEvent<'_>) {
+        self.stats.record(event);
+        if let Some(ref audit) = self.audit {
+            audit.log(event);
+        }
+    }
+
+    /// Quarantine a blocked payload (if enabled), returning the item ID
+    pub fn quarantine(
+        &self,
+        endpoint: &str,
+        content: &str,
+        scan: &crate::security::ScanResult,
+    ) -> Option<String> {
+        let quarantine = self.quarantine.as_ref()?;
+        match quarantine.add(endpoint, content, scan) {
+            Ok(item) => Some(item.id),
+            Err(e) => {
+                tracing::warn!("Failed to quarantine blocked payload: {e}");
+                None
+            },
+        }
+    }
+
+    /// Run session liveness sweeps in the background
+    ///
+    /// Every `sweep_interval`, enforces idle timeouts with
+    /// [`SessionManager::sweep`] and queues the resulting PING and CLOSE
+    /// messages in the relay inbox of their sessions. Peers collect them
+    /// with `GET /v1/relay/:session_id` and answer PINGs with a PONG on
+    /// `/message`. Expired pinned contexts are dropped on the same tick.
+    pub fn spawn_liveness(self: &Arc<Self>) -> tokio::task::JoinHandle<()> {
+        let state = Arc::clone(self);
+        crate::runtime::spawn_named("session-liveness", async move {
+            let mut ticker = tokio::time::interval(state.config.sweep_interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                ticker.tick().await;
+                for message in state.sessions.sweep().await {
+                    if let Some(id) = message.session_id.clone() {
+                        state.relay.push(&id, message);
+                    }
+                }
+                if let Some(ref contexts) = state.config.contexts {
+                    contexts.purge_expired();
+                }
+            }
+        })
+    }
+
     /// Get server uptime
     pub fn uptime(&self) -> Duration {
         self.start_time.elapsed()
@@ -63,7 +242,9 @@ impl AppState {
 
     /// Get server capabilities
     pub fn capabilities(&self) -> Capabilities {
-        let mut caps = Capabilities::new("m2m-server");
+        let mut caps = Capabilities::new("m2m-server").with_typed_extension(
+            crate::protocol::IdleTimeout(self.config.session_timeout.as_secs()),
+        );
 
         if self.config.security_enabled {
             caps = caps.with_security(
@@ -80,20 +261,273 @@ impl AppState {
     }
 }
 
+/// Open the configured session store backend
+#[cfg(feature = "sled")]
+fn open_store(path: &std::path::Path) -> crate::error::Result<Arc<dyn SessionStore>> {
+    Ok(Arc::new(super::store::SledSessionStore::open(path)?))
+}
+
+/// Open the configured session store backend
+#[cfg(not(feature = "sled"))]
+fn open_store(path: &std::path::Path) -> crate::error::Result<Arc<dyn SessionStore>> {
+    Ok(Arc::new(super::store::FileSessionStore::open(path)?))
+}
+
+/// Connect to the cluster's shared session store
+#[cfg(feature = "cluster")]
+fn open_cluster_store(cluster: &ClusterConfig) -> crate::error::Result<Arc<dyn SessionStore>> {
+    Ok(Arc::new(super::store::RedisSessionStore::from_config(
+        cluster,
+    )?))
+}
+
+/// Connect to the cluster's shared session store
+#[cfg(not(feature = "cluster"))]
+fn open_cluster_store(_: &ClusterConfig) -> crate::error::Result<Arc<dyn SessionStore>> {
+    Err(crate::error::M2MError::Config(
+        "Clustering requires the `cluster` feature".to_string(),
+    ))
+}
+
+/// Open the configured stats sink backend
+#[cfg(feature = "sled")]
+fn open_stats_sink(path: &std::path::Path) -> crate::error::Result<Box<dyn StatsSink>> {
+    Ok(Box::new(super::stats::SledStatsSink::open(path)?))
+}
+
+/// Open the configured stats sink backend
+#[cfg(not(feature = "sled"))]
+fn open_stats_sink(path: &std::path::Path) -> crate::error::Result<Box<dyn StatsSink>> {
+    Ok(Box::new(super::stats::JsonlStatsSink::open(path)?))
+}
+
+/// Buffered session events per subscriber before it starts lagging
+const SESSION_EVENT_BUFFER: usize = 256;
+
+/// Unanswered PINGs before an idle session is marked closing
+pub const DEFAULT_MAX_MISSED_PONGS: u32 = 2;
+
+/// Interval between session liveness sweeps
+pub const DEFAULT_SWEEP_INTERVAL: Duration = Duration::from_secs(5);
+
 /// Manages active sessions
+///
+/// # Liveness
+///
+/// [`sweep`](Self::sweep) enforces idle timeouts. A session's timeout is
+/// the smaller of the manager's timeout and the session's negotiated
+/// [`IdleTimeout`](crate::protocol::IdleTimeout). The timeout is split
+/// into `max_missed_pongs + 2` slots, shortened to the negotiated
+/// [`KeepaliveInterval`](crate::protocol::KeepaliveInterval) if the peer
+/// asked for more frequent PINGs. Each of the first `max_missed_pongs`
+/// idle slots ends with a PING. After the next slot the session is marked
+/// [`Closing`](SessionState::Closing) (event `timed_out`). It is removed
+/// once the full timeout has passed (event `expired`). Any request on the
+/// session, including a PONG, resets the cycle.
 pub struct SessionManager {
     /// Active sessions by ID
     sessions: Arc<RwLock<HashMap<String, SessionEntry>>>,
-    /// Session timeout
+    /// Session timeout (upper bound for negotiated timeouts)
     timeout: Duration,
+    /// Unanswered PINGs before a session is marked closing
+    max_missed_pongs: u32,
+    /// Durable session store (optional)
+    store: Option<Arc<dyn SessionStore>>,
+    /// Cache lifetime when the store is shared with other servers
+    shared_ttl: Option<Duration>,
+    /// Shared compression dictionaries offered to every session (optional)
+    dictionaries: Option<Arc<DictionaryStore>>,
+    /// Contexts pinned by agents (optional)
+    contexts: Option<Arc<ContextStore>>,
+    /// Scanner run on every session's inbound DATA (optional)
+    scanner: Option<Arc<SecurityScanner>>,
+    /// Credentials required in every session's HELLO (optional)
+    #[cfg(feature = "crypto")]
+    authenticator: Option<Arc<HelloAuthenticator>>,
+    /// Lifecycle events for admin subscribers
+    events: broadcast::Sender<SessionEvent>,
 }
 
 /// Session entry with metadata
 struct SessionEntry {
     /// The session
     session: Session,
+    /// Time the entry was created
+    created: Instant,
     /// Last access time
     last_access: Instant,
+    /// Counters accumulated across updates
+    totals: SessionTotals,
+    /// PINGs sent since the last access
+    pings_sent: u32,
+    /// Time the session was last read from a shared store
+    fetched: Instant,
+}
+
+impl SessionEntry {
+    fn new(session: Session, totals: SessionTotals) -> Self {
+        let now = Instant::now();
+        Self {
+            session,
+            created: now,
+            last_access: now,
+            totals,
+            pings_sent: 0,
+            fetched: now,
+        }
+    }
+
+    /// Idle timeout, capped by the manager's timeout
+    fn timeout(&self, limit: Duration) -> Duration {
+        self.session.timeout().min(limit)
+    }
+
+    /// Idle time between PINGs: a share of the timeout, or the negotiated
+    /// keep-alive interval if shorter
+    fn ping_slot(&self, limit: Duration, slots: u32) -> Duration {
+        let slot = self.timeout(limit) / slots;
+        match self
+            .session
+            .extension::<crate::protocol::KeepaliveInterval>()
+        {
+            Some(_) => slot.min(self.session.keepalive_interval()),
+            None => slot,
+        }
+    }
+
+    /// Whether the entry has been idle past its timeout
+    fn is_expired(&self, limit: Duration) -> bool {
+        self.last_access.elapsed() > self.timeout(limit)
+    }
+
+    /// Record an access, which also answers outstanding PINGs
+    fn touch(&mut self) {
+        self.last_access = Instant::now();
+        self.pings_sent = 0;
+    }
+
+    fn info(&self) -> SessionInfo {
+        let totals = self.totals;
+        SessionInfo {
+            session_id: self.session.id().to_string(),
+            state: self.session.state(),
+            age_secs: self.created.elapsed().as_secs(),
+            idle_secs: self.last_access.elapsed().as_secs(),
+            peer_capabilities: self.session.remote_capabilities().cloned(),
+            negotiated: self.session.negotiated().cloned(),
+            messages_sent: totals.messages_sent,
+            messages_received: totals.messages_received,
+            bytes_compressed: totals.bytes_compressed,
+            bytes_saved: totals.bytes_saved,
+            compression_ratio: totals.compression_ratio(),
+        }
+    }
+}
+
+/// Message and byte counters for a managed session
+///
+/// Handlers work on the clone returned by [`SessionManager::get`], whose
+/// counters start from zero, so each update adds to the running totals.
+#[derive(Debug, Clone, Copy, Default)]
+struct SessionTotals {
+    messages_sent: u64,
+    messages_received: u64,
+    bytes_compressed: u64,
+    bytes_saved: u64,
+}
+
+impl SessionTotals {
+    fn add(&mut self, stats: &SessionStats) {
+        self.messages_sent += stats.messages_sent;
+        self.messages_received += stats.messages_received;
+        self.bytes_compressed += stats.bytes_compressed;
+        self.bytes_saved += stats.bytes_saved;
+    }
+
+    fn compression_ratio(self) -> f64 {
+        if self.bytes_compressed == 0 {
+            1.0
+        } else {
+            (self.bytes_compressed + self.bytes_saved) as f64 / self.bytes_compressed as f64
+        }
+    }
+}
+
+impl From<&SessionStats> for SessionTotals {
+    fn from(stats: &SessionStats) -> Self {
+        let mut totals = Self::default();
+        totals.add(stats);
+        totals
+    }
+}
+
+/// Operator view of a managed session
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionInfo {
+    /// Session ID
+    pub session_id: String,
+    /// Current state
+    pub state: SessionState,
+    /// Seconds since the server created the session
+    pub age_secs: u64,
+    /// Seconds since the session was last used
+    pub idle_secs: u64,
+    /// Capabilities the peer advertised
+    pub peer_capabilities: Option<Capabilities>,
+    /// Negotiated capabilities
+    pub negotiated: Option<NegotiatedCaps>,
+    /// Messages sent
+    pub messages_sent: u64,
+    /// Messages received
+    pub messages_received: u64,
+    /// Bytes compressed
+    pub bytes_compressed: u64,
+    /// Bytes saved by compression
+    pub bytes_saved: u64,
+    /// Compression ratio
+    pub compression_ratio: f64,
+}
+
+/// Session lifecycle change
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionEventKind {
+    /// Session created or adopted
+    Created,
+    /// Session state or counters changed
+    Updated,
+    /// Session removed (client request or admin force-close)
+    Closed,
+    /// Session stopped answering PINGs and is closing
+    TimedOut,
+    /// Session timed out
+    Expired,
+}
+
+impl SessionEventKind {
+    /// Get the event name
+    pub fn name(self) -> &'static str {
+        match self {
+            SessionEventKind::Created => "created",
+            SessionEventKind::Updated => "updated",
+            SessionEventKind::Closed => "closed",
+            SessionEventKind::TimedOut => "timed_out",
+            SessionEventKind::Expired => "expired",
+        }
+    }
+}
+
+/// Session lifecycle event
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionEvent {
+    /// Session ID
+    pub session_id: String,
+    /// What happened
+    pub kind: SessionEventKind,
+    /// Session state after the event
+    pub state: SessionState,
+    /// Event time (Unix milliseconds)
+    pub timestamp: u64,
 }
 
 impl Default for SessionManager {
@@ -107,42 +541,354 @@ impl SessionManager {
     pub fn new() -> Self {
         Self {
             sessions: Arc::new(RwLock::new(HashMap::new())),
-            timeout: Duration::from_secs(300),
+            timeout: Duration::from_secs(SESSION_TIMEOUT_SECS),
+            max_missed_pongs: DEFAULT_MAX_MISSED_PONGS,
+            store: None,
+            shared_ttl: None,
+            dictionaries: None,
+            contexts: None,
+            scanner: None,
+            #[cfg(feature = "crypto")]
+            authenticator: None,
+            events: broadcast::channel(SESSION_EVENT_BUFFER).0,
         }
     }
 
+    /// Persist sessions to a store
+    ///
+    /// Sessions already in the store are not loaded until [`restore`](Self::restore)
+    /// is called.
+    pub fn with_store(mut self, store: Arc<dyn SessionStore>) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    /// Share sessions with other servers through `store`
+    ///
+    /// Sessions are written through as with [`with_store`](Self::with_store)
+    /// and read from the store on a local miss, so any server in a
+    /// load-balanced pool can handle any frame, whichever one did the
+    /// handshake. A cached session is re-read once it is older than
+    /// `cache_ttl`, which bounds how long a server acts on state that
+    /// another one changed or removed. Before pinging, closing or expiring
+    /// an idle session the store is checked for activity on other servers.
+    ///
+    /// Counts, listings and agent lookups only cover the sessions this
+    /// server has cached, and [`restore`](Self::restore) loads nothing.
+    pub fn with_shared_store(mut self, store: Arc<dyn SessionStore>, cache_ttl: Duration) -> Self {
+        self.store = Some(store);
+        self.shared_ttl = Some(cache_ttl);
+        self
+    }
+
+    /// Offer shared compression dictionaries to every session
+    ///
+    /// Dictionaries pushed by any agent become available to later
+    /// handshakes (see [`Session::with_dictionaries`]).
+    pub fn with_dictionaries(mut self, dictionaries: Arc<DictionaryStore>) -> Self {
+        self.dictionaries = Some(dictionaries);
+        self
+    }
+
+    /// Hold contexts pinned by every session's peer
+    ///
+    /// Shared, so an agent can reference a context pinned in an earlier
+    /// session (see [`Session::with_context_store`]).
+    pub fn with_contexts(mut self, contexts: Arc<ContextStore>) -> Self {
+        self.contexts = Some(contexts);
+        self
+    }
+
+    /// Scan every session's inbound DATA (see [`Session::with_scanner`])
+    pub fn with_scanner(mut self, scanner: Arc<SecurityScanner>) -> Self {
+        self.scanner = Some(scanner);
+        self
+    }
+
+    /// Authenticate every session's HELLO (see [`Session::with_authenticator`])
+    #[cfg(feature = "crypto")]
+    pub fn with_authenticator(mut self, authenticator: Arc<HelloAuthenticator>) -> Self {
+        self.authenticator = Some(authenticator);
+        self
+    }
+
+    /// Load unexpired sessions from the store
+    ///
+    /// Returns the number of sessions restored. Expired sessions are
+    /// removed from the store.
+    pub async fn restore(&self) -> crate::error::Result<usize> {
+        let Some(ref store) = self.store else {
+            return Ok(0);
+        };
+        if self.shared_ttl.is_some() {
+            return Ok(0);
+        }
+
+        let store = Arc::clone(store);
+        let (live, expired): (Vec<_>, Vec<_>) =
+            crate::runtime::spawn_blocking_named("session-store", move || store.load_all())
+                .await
+                .map_err(|e| {
+                    crate::error::M2MError::Server(format!("Session store task failed: {e}"))
+                })??
+                .into_iter()
+                .partition(|snapshot| !self.is_snapshot_expired(snapshot));
+
+        let restored = live.len();
+        let mut sessions = self.sessions.write().await;
+        for snapshot in live {
+            sessions.insert(snapshot.id.clone(), self.entry_from_snapshot(snapshot));
+        }
+        drop(sessions);
+
+        self.write_through(
+            Vec::new(),
+            expired.into_iter().map(|snapshot| snapshot.id).collect(),
+        )
+        .await;
+        Ok(restored)
+    }
+
+    /// Whether a stored session has been idle past its timeout
+    fn is_snapshot_expired(&self, snapshot: &SessionSnapshot) -> bool {
+        let idle = Duration::from_secs(snapshot.idle_secs());
+        idle > Duration::from_secs(snapshot.timeout_secs).min(self.timeout)
+    }
+
+    /// Cache entry for a stored session
+    fn entry_from_snapshot(&self, snapshot: SessionSnapshot) -> SessionEntry {
+        let idle = Duration::from_secs(snapshot.idle_secs());
+        let mut session = Session::from_snapshot(snapshot);
+        if let Some(ref dictionaries) = self.dictionaries {
+            session = session.with_dictionaries(Arc::clone(dictionaries));
+        }
+        if let Some(ref contexts) = self.contexts {
+            session = session.with_context_store(Arc::clone(contexts));
+        }
+        if let Some(ref scanner) = self.scanner {
+            session = session.with_scanner(Arc::clone(scanner));
+        }
+        let mut entry = SessionEntry::new(session, SessionTotals::default());
+        entry.totals = SessionTotals::from(&entry.session.stats());
+        entry.last_access = Instant::now()
+            .checked_sub(idle)
+            .unwrap_or_else(Instant::now);
+        entry
+    }
+
+    /// Read a session from the shared store
+    ///
+    /// `Err` if the store failed; callers then keep their cached copy.
+    fn fetch_shared(&self, id: &str) -> std::result::Result<Option<SessionSnapshot>, ()> {
+        let (Some(_), Some(store)) = (self.shared_ttl, &self.store) else {
+            return Err(());
+        };
+        store.load(id).map_err(|e| {
+            tracing::warn!("Failed to load shared session {id}: {e}");
+        })
+    }
+
+    /// Replace a cached session with the stored copy, keeping the most
+    /// recent activity seen by either
+    fn refresh(&self, entry: &mut SessionEntry, snapshot: SessionSnapshot) {
+        let fresh = self.entry_from_snapshot(snapshot);
+        if fresh.last_access > entry.last_access {
+            entry.touch();
+            entry.last_access = fresh.last_access;
+        }
+        entry.session = fresh.session;
+        entry.fetched = Instant::now();
+    }
+
+    /// Catch up on activity other servers recorded for an idle session
+    ///
+    /// Returns `false` if another server removed the session. Only
+    /// consults a shared store.
+    fn sync_idle(&self, id: &str, entry: &mut SessionEntry) -> bool {
+        match self.fetch_shared(id) {
+            Ok(Some(snapshot)) => {
+                self.refresh(entry, snapshot);
+                true
+            },
+            Ok(None) => false,
+            Err(()) => true,
+        }
+    }
+
+    /// Write a session through to the store
+    async fn persist(&self, session: &Session) {
+        self.write_through(vec![session.snapshot()], Vec::new())
+            .await;
+    }
+
+    /// Delete a session from the store
+    async fn unpersist(&self, id: &str) {
+        self.write_through(Vec::new(), vec![id.to_string()]).await;
+    }
+
+    /// Save and delete sessions in the store
+    ///
+    /// Store I/O blocks, so it runs on the blocking pool. Call it after
+    /// releasing the session lock.
+    async fn write_through(&self, saved: Vec<SessionSnapshot>, removed: Vec<String>) {
+        let Some(ref store) = self.store else {
+            return;
+        };
+        if saved.is_empty() && removed.is_empty() {
+            return;
+        }
+
+        let store = Arc::clone(store);
+        let task = crate::runtime::spawn_blocking_named("session-store", move || {
+            for snapshot in &saved {
+                if let Err(e) = store.save(snapshot) {
+                    tracing::warn!("Failed to persist session {}: {e}", snapshot.id);
+                }
+            }
+            for id in &removed {
+                if let Err(e) = store.remove(id) {
+                    tracing::warn!("Failed to remove persisted session {id}: {e}");
+                }
+            }
+        });
+        if let Err(e) = task.await {
+            tracing::warn!("Session store task failed: {e}");
+        }
+    }
+
+    /// Broadcast a lifecycle event (dropped when nobody is subscribed)
+    fn emit(&self, session_id: &str, kind: SessionEventKind, state: SessionState) {
+        let _ = self.events.send(SessionEvent {
+            session_id: session_id.to_string(),
+            kind,
+            state,
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or(0),
+        });
+    }
+
     /// Set session timeout
     pub fn with_timeout(mut self, timeout: Duration) -> Self {
         self.timeout = timeout;
         self
     }
 
+    /// Set how many PINGs may go unanswered before a session is closing
+    ///
+    /// With `0`, idle sessions are marked closing halfway to their timeout
+    /// without being pinged.
+    pub fn with_max_missed_pongs(mut self, max_missed_pongs: u32) -> Self {
+        self.max_missed_pongs = max_missed_pongs;
+        self
+    }
+
+    /// Subscribe to session lifecycle events
+    pub fn subscribe(&self) -> broadcast::Receiver<SessionEvent> {
+        self.events.subscribe()
+    }
+
+    /// Build a session with the manager's dictionaries, context store,
+    /// scanner and authenticator, without storing it
+    pub fn new_session(&self, capabilities: Capabilities) -> Session {
+        let mut session = Session::new(capabilities);
+        if let Some(ref dictionaries) = self.dictionaries {
+            session = session.with_dictionaries(Arc::clone(dictionaries));
+        }
+        if let Some(ref contexts) = self.contexts {
+            session = session.with_context_store(Arc::clone(contexts));
+        }
+        if let Some(ref scanner) = self.scanner {
+            session = session.with_scanner(Arc::clone(scanner));
+        }
+        #[cfg(feature = "crypto")]
+        if let Some(ref authenticator) = self.authenticator {
+            session = session.with_authenticator(Arc::clone(authenticator));
+        }
+        session
+    }
+
     /// Create a new session
     pub async fn create(&self, capabilities: Capabilities) -> Session {
-        let session = Session::new(capabilities);
+        let session = self.new_session(capabilities);
         let id = session.id().to_string();
 
-        let entry = SessionEntry {
-            session: session.clone(),
-            last_access: Instant::now(),
-        };
+        let entry = SessionEntry::new(session.clone(), SessionTotals::default());
 
         self.sessions.write().await.insert(id, entry);
+        self.persist(&session).await;
+        self.emit(session.id(), SessionEventKind::Created, session.state());
         session
     }
 
+    /// Insert a session under its own ID
+    ///
+    /// Returns `false` (and stores nothing) if the ID is already in use,
+    /// so a client-proposed ID cannot take over an existing session.
+    pub async fn insert(&self, session: &Session) -> bool {
+        let mut sessions = self.sessions.write().await;
+        if sessions.contains_key(session.id()) {
+            return false;
+        }
+        // Nor one established on another server
+        if matches!(self.fetch_shared(session.id()), Ok(Some(_))) {
+            return false;
+        }
+
+        sessions.insert(
+            session.id().to_string(),
+            SessionEntry::new(session.clone(), SessionTotals::from(&session.stats())),
+        );
+        drop(sessions);
+
+        self.persist(session).await;
+        self.emit(session.id(), SessionEventKind::Created, session.state());
+        true
+    }
+
     /// Get session by ID
+    ///
+    /// With a shared store, sessions missing or stale in the local cache
+    /// are read from the store.
     pub async fn get(&self, id: &str) -> Option<Session> {
         let mut sessions = self.sessions.write().await;
 
+        if let Some(ttl) = self.shared_ttl {
+            let stale = sessions
+                .get(id)
+                .is_none_or(|entry| entry.fetched.elapsed() >= ttl);
+            if stale {
+                match (self.fetch_shared(id), sessions.get_mut(id)) {
+                    (Ok(Some(snapshot)), Some(entry)) => self.refresh(entry, snapshot),
+                    (Ok(Some(snapshot)), None) => {
+                        if !self.is_snapshot_expired(&snapshot) {
+                            sessions.insert(id.to_string(), self.entry_from_snapshot(snapshot));
+                        }
+                    },
+                    // Removed or expired on another server
+                    (Ok(None), Some(_)) => {
+                        sessions.remove(id);
+                        return None;
+                    },
+                    (Ok(None), None) | (Err(()), _) => {},
+                }
+            }
+        }
+
         if let Some(entry) = sessions.get_mut(id) {
-            // Check expiry
-            if entry.last_access.elapsed() > self.timeout {
+            // Expired, or closed for missing PONGs
+            let timed_out = entry.pings_sent > self.max_missed_pongs
+                && entry.session.state() == SessionState::Closing;
+            if timed_out || entry.is_expired(self.timeout) {
                 sessions.remove(id);
+                drop(sessions);
+                self.unpersist(id).await;
+                self.emit(id, SessionEventKind::Expired, SessionState::Closed);
                 return None;
             }
 
-            entry.last_access = Instant::now();
+            entry.touch();
             Some(entry.session.clone())
         } else {
             None
@@ -150,18 +896,31 @@ impl SessionManager {
     }
 
     /// Update session
+    ///
+    /// Counters on `session` are added to the session's totals, so pass
+    /// the session obtained from [`get`](Self::get) once per change.
     pub async fn update(&self, session: &Session) {
         let mut sessions = self.sessions.write().await;
 
         if let Some(entry) = sessions.get_mut(session.id()) {
             entry.session = session.clone();
-            entry.last_access = Instant::now();
+            entry.touch();
+            entry.totals.add(&session.stats());
+            drop(sessions);
+
+            self.persist(session).await;
+            self.emit(session.id(), SessionEventKind::Updated, session.state());
         }
     }
 
-    /// Remove session
-    pub async fn remove(&self, id: &str) {
-        self.sessions.write().await.remove(id);
+    /// Remove session (returns `false` if it did not exist)
+    pub async fn remove(&self, id: &str) -> bool {
+        let removed = self.sessions.write().await.remove(id).is_some();
+        self.unpersist(id).await;
+        if removed {
+            self.emit(id, SessionEventKind::Closed, SessionState::Closed);
+        }
+        removed
     }
 
     /// Get session count
@@ -173,16 +932,125 @@ impl SessionManager {
     pub async fn cleanup(&self) -> usize {
         let mut sessions = self.sessions.write().await;
         let before = sessions.len();
+        let mut expired = Vec::new();
+
+        sessions.retain(|id, entry| {
+            if entry.is_expired(self.timeout) && !self.sync_idle(id, entry) {
+                return false;
+            }
+            let live = !entry.is_expired(self.timeout);
+            if !live {
+                expired.push(id.clone());
+                self.emit(id, SessionEventKind::Expired, SessionState::Closed);
+            }
+            live
+        });
+        let removed = before - sessions.len();
+        drop(sessions);
+
+        self.write_through(Vec::new(), expired).await;
+        removed
+    }
+
+    /// Enforce idle timeouts (see [Liveness](Self#liveness))
+    ///
+    /// Removes expired sessions, marks sessions that missed too many PONGs
+    /// as closing, and returns the messages to deliver to idle peers: a
+    /// PING for each session due one and a CLOSE for each session that
+    /// timed out. Call it periodically, more often than the shortest
+    /// session timeout divided by `max_missed_pongs + 2`.
+    pub async fn sweep(&self) -> Vec<Message> {
+        let mut sessions = self.sessions.write().await;
+        let slots = self.max_missed_pongs + 2;
+        let mut outgoing = Vec::new();
+        let mut closed = Vec::new();
+        let mut expired = Vec::new();
+
+        sessions.retain(|id, entry| {
+            let slot = entry.ping_slot(self.timeout, slots);
+            let idle_slots = |entry: &SessionEntry| {
+                entry.last_access.elapsed().as_nanos() / slot.as_nanos().max(1)
+            };
+            // The session may be busy on another server
+            if idle_slots(entry) > u128::from(entry.pings_sent) && !self.sync_idle(id, entry) {
+                return false;
+            }
+
+            if entry.is_expired(self.timeout) {
+                expired.push(id.clone());
+                self.emit(id, SessionEventKind::Expired, SessionState::Closed);
+                return false;
+            }
+
+            if idle_slots(entry) <= u128::from(entry.pings_sent) {
+                return true;
+            }
 
-        sessions.retain(|_, entry| entry.last_access.elapsed() < self.timeout);
+            if entry.pings_sent < self.max_missed_pongs {
+                entry.pings_sent += 1;
+                outgoing.push(Message::ping(id));
+            } else if entry.session.state() != SessionState::Closing {
+                entry.pings_sent = self.max_missed_pongs + 1;
+                entry.session.close();
+                closed.push(entry.session.snapshot());
+                self.emit(id, SessionEventKind::TimedOut, SessionState::Closing);
+                outgoing.push(Message::close_with_reason(
+                    id,
+                    CloseReason::Timeout,
+                    Some("No PONG received"),
+                ));
+            }
+            true
+        });
+        drop(sessions);
 
-        before - sessions.len()
+        self.write_through(closed, expired).await;
+        outgoing
     }
 
     /// Get all session IDs
     pub async fn list_ids(&self) -> Vec<String> {
         self.sessions.read().await.keys().cloned().collect()
     }
+
+    /// Describe all sessions, oldest first (does not refresh idle time)
+    pub async fn list(&self) -> Vec<SessionInfo> {
+        let mut infos: Vec<_> = self
+            .sessions
+            .read()
+            .await
+            .values()
+            .map(SessionEntry::info)
+            .collect();
+        infos.sort_by_key(|info| std::cmp::Reverse(info.age_secs));
+        infos
+    }
+
+    /// Describe one session (does not refresh idle time)
+    pub async fn info(&self, id: &str) -> Option<SessionInfo> {
+        self.sessions.read().await.get(id).map(SessionEntry::info)
+    }
+
+    /// ID of the most recently used established session authenticated as
+    /// `name` within `tenant`
+    ///
+    /// Anonymous sessions are never returned: the agent ID a peer claims in
+    /// its HELLO is not proof of identity.
+    pub async fn find_by_principal(&self, tenant: Option<&str>, name: &str) -> Option<String> {
+        self.sessions
+            .read()
+            .await
+            .values()
+            .filter(|entry| {
+                entry.session.is_established()
+                    && !entry.is_expired(self.timeout)
+                    && entry.session.principal().is_some_and(|principal| {
+                        principal.name == name && principal.tenant.as_deref() == tenant
+                    })
+            })
+            .max_by_key(|entry| entry.last_access)
+            .map(|entry| entry.session.id().to_string())
+    }
 }
 
 #[cfg(test)]
@@ -230,6 +1098,154 @@ mod tests {
         assert_eq!(manager.count().await, 3);
     }
 
+    #[tokio::test]
+    async fn test_session_persistence() {
+        use crate::server::store::FileSessionStore;
+
+        let dir = tempfile::tempdir().unwrap();
+        let store = Arc::new(FileSessionStore::open(dir.path()).unwrap());
+
+        let manager = SessionManager::new().with_store(store.clone());
+        let mut client = Session::new(Capabilities::default());
+        let mut server = manager.create(Capabilities::default()).await;
+        let accept = server.process_hello(&client.create_hello()).unwrap();
+        client.process_accept(&accept).unwrap();
+        manager.update(&server).await;
+        let dropped = manager.create(Capabilities::default()).await;
+        manager.remove(dropped.id()).await;
+
+        // Simulate restart
+        drop(manager);
+        let manager = SessionManager::new().with_store(store);
+        assert_eq!(manager.restore().await.unwrap(), 1);
+
+        let mut restored = manager.get(server.id()).await.unwrap();
+        assert!(restored.is_established());
+        let data = client.compress(r#"{"messages":[]}"#).unwrap();
+        assert_eq!(restored.decompress(&data).unwrap(), r#"{"messages":[]}"#);
+    }
+
+    #[tokio::test]
+    async fn test_shared_sessions_across_servers() {
+        use crate::server::store::MemorySessionStore;
+
+        let store = Arc::new(MemorySessionStore::new());
+        let node_a = SessionManager::new().with_shared_store(store.clone(), Duration::ZERO);
+        let node_b = SessionManager::new().with_shared_store(store, Duration::ZERO);
+
+        // Handshake on one node, traffic on the other
+        let mut client = Session::new(Capabilities::default());
+        let mut server = node_a.create(Capabilities::default()).await;
+        let accept = server.process_hello(&client.create_hello()).unwrap();
+        client.process_accept(&accept).unwrap();
+        node_a.update(&server).await;
+
+        let mut remote = node_b.get(server.id()).await.unwrap();
+        assert!(remote.is_established());
+        let data = client.compress(r#"{"messages":[]}"#).unwrap();
+        assert_eq!(remote.decompress(&data).unwrap(), r#"{"messages":[]}"#);
+        assert!(!node_b.insert(&server).await);
+
+        // Closing on one node is seen by the other
+        assert!(node_b.remove(server.id()).await);
+        assert!(node_a.get(server.id()).await.is_none());
+        assert_eq!(node_a.count().await, 0);
+        assert_eq!(node_a.restore().await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_session_events_and_totals() {
+        let manager = SessionManager::new();
+        let mut events = manager.subscribe();
+
+        let mut client = Session::new(Capabilities::default());
+        let mut server = manager.create(Capabilities::default()).await;
+        let accept = server.process_hello(&client.create_hello()).unwrap();
+        client.process_accept(&accept).unwrap();
+        manager.update(&server).await;
+
+        // Handlers update the clone returned by get()
+        for _ in 0..2 {
+            let mut session = manager.get(server.id()).await.unwrap();
+            session
+                .decompress(&client.compress(r#"{"messages":[]}"#).unwrap())
+                .unwrap();
+            manager.update(&session).await;
+        }
+
+        let info = manager.info(server.id()).await.unwrap();
+        assert_eq!(info.state, SessionState::Established);
+        assert_eq!(info.messages_received, 3);
+        assert!(info.peer_capabilities.is_some());
+        assert_eq!(manager.list().await.len(), 1);
+
+        assert!(manager.remove(server.id()).await);
+        assert!(!manager.remove(server.id()).await);
+
+        let kinds: Vec<_> = std::iter::from_fn(|| events.try_recv().ok())
+            .map(|e| e.kind)
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                SessionEventKind::Created,
+                SessionEventKind::Updated,
+                SessionEventKind::Updated,
+                SessionEventKind::Updated,
+                SessionEventKind::Closed,
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_session_liveness_sweep() {
+        use crate::protocol::MessageType;
+
+        // Four 200ms slots: PING, PING, closing, expired
+        let manager = SessionManager::new().with_timeout(Duration::from_millis(800));
+        let mut events = manager.subscribe();
+        let idle = manager.create(Capabilities::default()).await;
+        let alive = manager.create(Capabilities::default()).await;
+        let types = |messages: Vec<Message>| -> Vec<_> {
+            messages
+                .into_iter()
+                .map(|m| (m.msg_type, m.session_id.unwrap()))
+                .collect()
+        };
+
+        assert!(manager.sweep().await.is_empty());
+        for _ in 0..2 {
+            tokio::time::sleep(Duration::from_millis(220)).await;
+            let mut pings = types(manager.sweep().await);
+            pings.sort_by_key(|(_, id)| id == alive.id());
+            assert_eq!(pings[0], (MessageType::Ping, idle.id().to_string()));
+            assert!(manager.sweep().await.is_empty());
+
+            // The PONG (or any other request) restarts the cycle
+            manager.get(alive.id()).await.unwrap();
+        }
+
+        tokio::time::sleep(Duration::from_millis(220)).await;
+        let closing = types(manager.sweep().await);
+        assert!(closing.contains(&(MessageType::Close, idle.id().to_string())));
+        assert_eq!(
+            manager.info(idle.id()).await.unwrap().state,
+            SessionState::Closing
+        );
+
+        // A timed-out session is gone on its next use
+        assert!(manager.get(idle.id()).await.is_none());
+        assert!(manager.get(alive.id()).await.is_some());
+
+        let kinds: Vec<_> = std::iter::from_fn(|| events.try_recv().ok())
+            .map(|e| e.kind)
+            .collect();
+        assert_eq!(
+            kinds[2..],
+            [SessionEventKind::TimedOut, SessionEventKind::Expired]
+        );
+    }
+
     #[tokio::test]
     async fn test_session_expiry() {
         let manager = SessionManager::new().with_timeout(Duration::from_millis(10));
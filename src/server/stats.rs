@@ -0,0 +1,406 @@
+//! Historical request statistics.
+//!
+//! A [`StatsRecorder`] aggregates compression traffic into per-minute
+//! [`StatsRollup`]s (requests, bytes saved, per-algorithm ratios, threat
+//! blocks) and writes each completed minute to a [`StatsSink`]. Dashboards
+//! read them back from `GET /stats/history?from=&to=`.
+//!
+//! # Backends
+//!
+//! | Backend           | Feature | Use Case                           |
+//! |-------------------|---------|------------------------------------|
+//! | `MemoryStatsSink` | -       | Default, lost on restart           |
+//! | `JsonlStatsSink`  | -       | Single instance, no extra deps     |
+//! | `SledStatsSink`   | `sled`  | Single instance, embedded database |
+
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use super::audit::AuditEvent;
+use crate::codec::Algorithm;
+use crate::error::{M2MError, Result};
+
+/// Traffic for one algorithm within a rollup
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct AlgorithmStats {
+    /// Requests compressed with the algorithm
+    pub requests: u64,
+    /// Original bytes
+    pub original_bytes: u64,
+    /// Compressed bytes
+    pub compressed_bytes: u64,
+}
+
+impl AlgorithmStats {
+    /// Compression ratio (original / compressed)
+    pub fn ratio(&self) -> f64 {
+        if self.compressed_bytes == 0 {
+            1.0
+        } else {
+            self.original_bytes as f64 / self.compressed_bytes as f64
+        }
+    }
+}
+
+/// Aggregated traffic for one minute
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct StatsRollup {
+    /// Start of the minute (unix seconds)
+    pub minute: u64,
+    /// Requests seen
+    pub requests: u64,
+    /// Original bytes of compressed requests
+    pub original_bytes: u64,
+    /// Compressed bytes of compressed requests
+    pub compressed_bytes: u64,
+    /// Bytes saved by compression
+    pub bytes_saved: u64,
+    /// Requests blocked by the security scanner
+    pub threat_blocks: u64,
+    /// Requests that failed for other reasons
+    pub errors: u64,
+    /// Per-algorithm breakdown
+    pub algorithms: HashMap<Algorithm, AlgorithmStats>,
+    /// Per-model breakdown of compressed requests (by the payload's `model`)
+    #[serde(default)]
+    pub models: HashMap<String, AlgorithmStats>,
+}
+
+impl StatsRollup {
+    /// Create an empty rollup for the minute containing `timestamp`
+    pub fn new(timestamp: u64) -> Self {
+        Self {
+            minute: timestamp - timestamp % 60,
+            ..Self::default()
+        }
+    }
+
+    /// Add a request to the rollup
+    pub fn record(&mut self, event: &AuditEvent<'_>) {
+        self.requests += 1;
+
+        if let Some(result) = event.result {
+            let original = result.original_bytes as u64;
+            let compressed = result.compressed_bytes as u64;
+            self.original_bytes += original;
+            self.compressed_bytes += compressed;
+            self.bytes_saved += original.saturating_sub(compressed);
+
+            let algorithm = self.algorithms.entry(result.algorithm).or_default();
+            algorithm.requests += 1;
+            algorithm.original_bytes += original;
+            algorithm.compressed_bytes += compressed;
+
+            if let Some(model) = model_of(event.content) {
+                let model = self.models.entry(model.to_string()).or_default();
+                model.requests += 1;
+                model.original_bytes += original;
+                model.compressed_bytes += compressed;
+            }
+        } else if event.scan.is_some_and(|s| s.should_block) {
+            self.threat_blocks += 1;
+        } else if event.status >= 400 {
+            self.errors += 1;
+        }
+    }
+
+    /// Overall compression ratio (original / compressed)
+    pub fn ratio(&self) -> f64 {
+        if self.compressed_bytes == 0 {
+            1.0
+        } else {
+            self.original_bytes as f64 / self.compressed_bytes as f64
+        }
+    }
+}
+
+/// `model` of a JSON request, without building the whole document
+fn model_of(content: &str) -> Option<Cow<'_, str>> {
+    #[derive(Deserialize)]
+    struct Model<'a> {
+        #[serde(borrow)]
+        model: Option<Cow<'a, str>>,
+    }
+    serde_json::from_str::<Model<'_>>(content).ok()?.model
+}
+
+/// Durable storage for stats rollups
+///
+/// Methods are synchronous and called at most once per minute on the
+/// request path, plus on every history query.
+pub trait StatsSink: Send + Sync {
+    /// Insert or replace the rollup for its minute
+    fn save(&self, rollup: &StatsRollup) -> Result<()>;
+
+    /// Load rollups with `from <= minute < to`, oldest first
+    fn range(&self, from: u64, to: u64) -> Result<Vec<StatsRollup>>;
+}
+
+/// In-memory stats sink
+#[derive(Debug, Default)]
+pub struct MemoryStatsSink {
+    rollups: RwLock<BTreeMap<u64, StatsRollup>>,
+}
+
+impl MemoryStatsSink {
+    /// Create empty sink
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl StatsSink for MemoryStatsSink {
+    fn save(&self, rollup: &StatsRollup) -> Result<()> {
+        self.rollups
+            .write()
+            .map_err(|_| M2MError::Server("Stats sink lock poisoned".to_string()))?
+            .insert(rollup.minute, rollup.clone());
+        Ok(())
+    }
+
+    fn range(&self, from: u64, to: u64) -> Result<Vec<StatsRollup>> {
+        if from >= to {
+            return Ok(Vec::new());
+        }
+        Ok(self
+            .rollups
+            .read()
+            .map_err(|_| M2MError::Server("Stats sink lock poisoned".to_string()))?
+            .range(from..to)
+            .map(|(_, rollup)| rollup.clone())
+            .collect())
+    }
+}
+
+/// Appends rollups as JSON lines to a file
+///
+/// A later line for the same minute replaces an earlier one.
+pub struct JsonlStatsSink {
+    path: PathBuf,
+    file: Mutex<File>,
+}
+
+impl JsonlStatsSink {
+    /// Open (or create) a JSONL file for appending
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        Ok(Self {
+            path,
+            file: Mutex::new(file),
+        })
+    }
+}
+
+impl StatsSink for JsonlStatsSink {
+    fn save(&self, rollup: &StatsRollup) -> Result<()> {
+        let mut line = serde_json::to_vec(rollup)?;
+        line.push(b'\n');
+
+        let mut file = self
+            .file
+            .lock()
+            .map_err(|_| M2MError::Server("Stats sink lock poisoned".to_string()))?;
+        file.write_all(&line)?;
+        Ok(())
+    }
+
+    fn range(&self, from: u64, to: u64) -> Result<Vec<StatsRollup>> {
+        let mut rollups = BTreeMap::new();
+
+        for line in BufReader::new(File::open(&self.path)?).lines() {
+            let line = line?;
+            match serde_json::from_str::<StatsRollup>(&line) {
+                Ok(rollup) if (from..to).contains(&rollup.minute) => {
+                    rollups.insert(rollup.minute, rollup);
+                },
+                Ok(_) => {},
+                Err(e) => tracing::warn!("Skipping unreadable stats line: {e}"),
+            }
+        }
+
+        Ok(rollups.into_values().collect())
+    }
+}
+
+/// Stats sink backed by an embedded sled database
+#[cfg(feature = "sled")]
+#[derive(Debug, Clone)]
+pub struct SledStatsSink {
+    db: sled::Db,
+}
+
+#[cfg(feature = "sled")]
+impl SledStatsSink {
+    /// Open (and create if needed) a sled database
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let db = sled::open(path)
+            .map_err(|e| M2MError::Server(format!("Failed to open stats store: {e}")))?;
+        Ok(Self { db })
+    }
+}
+
+#[cfg(feature = "sled")]
+impl StatsSink for SledStatsSink {
+    fn save(&self, rollup: &StatsRollup) -> Result<()> {
+        // Big-endian keys keep sled's iteration order chronological
+        self.db
+            .insert(rollup.minute.to_be_bytes(), serde_json::to_vec(rollup)?)
+            .map_err(|e| M2MError::Server(format!("Failed to save stats: {e}")))?;
+        Ok(())
+    }
+
+    fn range(&self, from: u64, to: u64) -> Result<Vec<StatsRollup>> {
+        if from >= to {
+            return Ok(Vec::new());
+        }
+        self.db
+            .range(from.to_be_bytes()..to.to_be_bytes())
+            .values()
+            .map(|value| {
+                let value =
+                    value.map_err(|e| M2MError::Server(format!("Failed to load stats: {e}")))?;
+                Ok(serde_json::from_slice(&value)?)
+            })
+            .collect()
+    }
+}
+
+/// Aggregates requests into the current minute and flushes completed
+/// minutes to a sink
+pub struct StatsRecorder {
+    sink: Box<dyn StatsSink>,
+    current: Mutex<StatsRollup>,
+}
+
+impl StatsRecorder {
+    /// Create a recorder over a sink
+    pub fn new(sink: Box<dyn StatsSink>) -> Self {
+        Self {
+            sink,
+            current: Mutex::new(StatsRollup::default()),
+        }
+    }
+
+    /// Record a request (sink failures are logged, never surfaced to the client)
+    pub fn record(&self, event: &AuditEvent<'_>) {
+        self.record_at(now(), event);
+    }
+
+    fn record_at(&self, timestamp: u64, event: &AuditEvent<'_>) {
+        let Ok(mut current) = self.current.lock() else {
+            return;
+        };
+
+        let minute = StatsRollup::new(timestamp).minute;
+        if minute > current.minute {
+            let completed = std::mem::replace(&mut *current, StatsRollup::new(timestamp));
+            if completed.requests > 0 {
+                if let Err(e) = self.sink.save(&completed) {
+                    tracing::warn!("Failed to write stats rollup: {e}");
+                }
+            }
+        }
+
+        current.record(event);
+    }
+
+    /// Write the current (partial) minute to the sink
+    pub fn flush(&self) -> Result<()> {
+        let current = self
+            .current
+            .lock()
+            .map_err(|_| M2MError::Server("Stats recorder lock poisoned".to_string()))?;
+        if current.requests > 0 {
+            self.sink.save(&current)?;
+        }
+        Ok(())
+    }
+
+    /// Rollups with `from <= minute < to`, including the current minute
+    pub fn history(&self, from: u64, to: u64) -> Result<Vec<StatsRollup>> {
+        let mut rollups = self.sink.range(from, to)?;
+
+        let current = self
+            .current
+            .lock()
+            .map_err(|_| M2MError::Server("Stats recorder lock poisoned".to_string()))?;
+        if current.requests > 0 && (from..to).contains(&current.minute) {
+            // A flushed partial minute is superseded by the live one
+            rollups.retain(|r| r.minute != current.minute);
+            rollups.push(current.clone());
+        }
+
+        Ok(rollups)
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::CompressionResult;
+    use crate::security::ScanResult;
+    use std::time::Instant;
+
+    #[test]
+    fn test_recorder_rollups() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("stats.jsonl");
+        let recorder = StatsRecorder::new(Box::new(JsonlStatsSink::open(&path).unwrap()));
+
+        let m2m = CompressionResult::new("#M2M|1|abc".to_string(), Algorithm::M2M, 90, 10);
+        let brotli =
+            CompressionResult::new("#M2M[v3.0]|DATA:x".to_string(), Algorithm::Brotli, 100, 40);
+        let mut blocked = ScanResult::safe();
+        blocked.should_block = true;
+
+        recorder.record_at(
+            120,
+            &AuditEvent::new("/compress", "{}", Instant::now()).with_result(&m2m),
+        );
+        recorder.record_at(
+            150,
+            &AuditEvent::new("/compress", "{}", Instant::now())
+                .with_scan(Some(&blocked))
+                .with_status(403),
+        );
+        recorder.record_at(
+            185,
+            &AuditEvent::new("/compress/auto", "{}", Instant::now()).with_result(&brotli),
+        );
+
+        // Minute 120 was flushed when 180 started; 180 is still live
+        let sink = JsonlStatsSink::open(&path).unwrap();
+        let stored = sink.range(0, u64::MAX).unwrap();
+        assert_eq!(stored.len(), 1);
+        assert_eq!(stored[0].minute, 120);
+        assert_eq!(stored[0].requests, 2);
+        assert_eq!(stored[0].threat_blocks, 1);
+        assert_eq!(stored[0].bytes_saved, 80);
+        assert!((stored[0].algorithms[&Algorithm::M2M].ratio() - 9.0).abs() < f64::EPSILON);
+
+        let history = recorder.history(0, u64::MAX).unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[1].minute, 180);
+        assert_eq!(history[1].algorithms[&Algorithm::Brotli].requests, 1);
+        assert_eq!(recorder.history(180, 240).unwrap().len(), 1);
+
+        recorder.flush().unwrap();
+        assert_eq!(sink.range(0, u64::MAX).unwrap().len(), 2);
+        assert_eq!(recorder.history(0, u64::MAX).unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,453 @@
+//! Pluggable session persistence.
+//!
+//! A [`SessionStore`] lets [`SessionManager`](super::SessionManager) write
+//! sessions through to durable storage so established sessions survive a
+//! server restart. A store shared by several servers (see
+//! [`SessionManager::with_shared_store`](super::SessionManager::with_shared_store))
+//! lets any of them handle any session.
+//!
+//! # Backends
+//!
+//! | Backend              | Feature   | Use Case                           |
+//! |----------------------|-----------|------------------------------------|
+//! | `MemorySessionStore` | -         | Tests, single process              |
+//! | `FileSessionStore`   | -         | Single instance, no extra deps     |
+//! | `SledSessionStore`   | `sled`    | Single instance, embedded database |
+//! | `RedisSessionStore`  | `cluster` | Load-balanced pool of servers      |
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use std::time::Duration;
+
+use crate::error::{M2MError, Result};
+use crate::protocol::SessionSnapshot;
+
+/// Durable storage for session snapshots
+///
+/// Methods are synchronous; implementations backed by network services
+/// should keep calls short or buffer internally.
+pub trait SessionStore: Send + Sync {
+    /// Insert or replace a session
+    fn save(&self, snapshot: &SessionSnapshot) -> Result<()>;
+
+    /// Load all stored sessions
+    fn load_all(&self) -> Result<Vec<SessionSnapshot>>;
+
+    /// Load one session (`None` if absent)
+    fn load(&self, id: &str) -> Result<Option<SessionSnapshot>> {
+        Ok(self
+            .load_all()?
+            .into_iter()
+            .find(|snapshot| snapshot.id == id))
+    }
+
+    /// Remove a session (no-op if absent)
+    fn remove(&self, id: &str) -> Result<()>;
+}
+
+/// In-memory session store
+#[derive(Debug, Default)]
+pub struct MemorySessionStore {
+    sessions: RwLock<HashMap<String, SessionSnapshot>>,
+}
+
+impl MemorySessionStore {
+    /// Create empty store
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl SessionStore for MemorySessionStore {
+    fn save(&self, snapshot: &SessionSnapshot) -> Result<()> {
+        self.sessions
+            .write()
+            .map_err(|_| M2MError::Server("Session store lock poisoned".to_string()))?
+            .insert(snapshot.id.clone(), snapshot.clone());
+        Ok(())
+    }
+
+    fn load_all(&self) -> Result<Vec<SessionSnapshot>> {
+        Ok(self
+            .sessions
+            .read()
+            .map_err(|_| M2MError::Server("Session store lock poisoned".to_string()))?
+            .values()
+            .cloned()
+            .collect())
+    }
+
+    fn load(&self, id: &str) -> Result<Option<SessionSnapshot>> {
+        Ok(self
+            .sessions
+            .read()
+            .map_err(|_| M2MError::Server("Session store lock poisoned".to_string()))?
+            .get(id)
+            .cloned())
+    }
+
+    fn remove(&self, id: &str) -> Result<()> {
+        self.sessions
+            .write()
+            .map_err(|_| M2MError::Server("Session store lock poisoned".to_string()))?
+            .remove(id);
+        Ok(())
+    }
+}
+
+/// Session store keeping one JSON file per session in a directory
+#[derive(Debug, Clone)]
+pub struct FileSessionStore {
+    dir: PathBuf,
+}
+
+impl FileSessionStore {
+    /// Open (and create if needed) a store directory
+    pub fn open(dir: impl AsRef<Path>) -> Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        std::fs::create_dir_all(&dir)?;
+        Ok(Self { dir })
+    }
+
+    fn path_for(&self, id: &str) -> Result<PathBuf> {
+        // Session IDs are UUIDs; reject anything that could escape the directory
+        if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+            return Err(M2MError::Server(format!("Invalid session ID: {id}")));
+        }
+        Ok(self.dir.join(format!("{id}.json")))
+    }
+}
+
+impl SessionStore for FileSessionStore {
+    fn save(&self, snapshot: &SessionSnapshot) -> Result<()> {
+        let path = self.path_for(&snapshot.id)?;
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_vec(snapshot)?)?;
+        std::fs::rename(&tmp, &path)?;
+        Ok(())
+    }
+
+    fn load_all(&self) -> Result<Vec<SessionSnapshot>> {
+        let mut snapshots = Vec::new();
+
+        for entry in std::fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+
+            match serde_json::from_slice(&std::fs::read(&path)?) {
+                Ok(snapshot) => snapshots.push(snapshot),
+                Err(e) => {
+                    tracing::warn!("Skipping unreadable session file {}: {e}", path.display());
+                },
+            }
+        }
+
+        Ok(snapshots)
+    }
+
+    fn load(&self, id: &str) -> Result<Option<SessionSnapshot>> {
+        match std::fs::read(self.path_for(id)?) {
+            Ok(bytes) => Ok(Some(serde_json::from_slice(&bytes)?)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn remove(&self, id: &str) -> Result<()> {
+        match std::fs::remove_file(self.path_for(id)?) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+/// Session store backed by an embedded sled database
+#[cfg(feature = "sled")]
+#[derive(Debug, Clone)]
+pub struct SledSessionStore {
+    db: sled::Db,
+}
+
+#[cfg(feature = "sled")]
+impl SledSessionStore {
+    /// Open (and create if needed) a sled database
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let db = sled::open(path)
+            .map_err(|e| M2MError::Server(format!("Failed to open session store: {e}")))?;
+        Ok(Self { db })
+    }
+}
+
+#[cfg(feature = "sled")]
+impl SessionStore for SledSessionStore {
+    fn save(&self, snapshot: &SessionSnapshot) -> Result<()> {
+        self.db
+            .insert(snapshot.id.as_bytes(), serde_json::to_vec(snapshot)?)
+            .map_err(|e| M2MError::Server(format!("Failed to save session: {e}")))?;
+        Ok(())
+    }
+
+    fn load_all(&self) -> Result<Vec<SessionSnapshot>> {
+        self.db
+            .iter()
+            .values()
+            .map(|value| {
+                let value =
+                    value.map_err(|e| M2MError::Server(format!("Failed to load session: {e}")))?;
+                Ok(serde_json::from_slice(&value)?)
+            })
+            .collect()
+    }
+
+    fn load(&self, id: &str) -> Result<Option<SessionSnapshot>> {
+        self.db
+            .get(id.as_bytes())
+            .map_err(|e| M2MError::Server(format!("Failed to load session: {e}")))?
+            .map(|value| Ok(serde_json::from_slice(&value)?))
+            .transpose()
+    }
+
+    fn remove(&self, id: &str) -> Result<()> {
+        self.db
+            .remove(id.as_bytes())
+            .map_err(|e| M2MError::Server(format!("Failed to remove session: {e}")))?;
+        Ok(())
+    }
+}
+
+/// Default Redis key prefix for session snapshots
+pub const DEFAULT_CLUSTER_KEY_PREFIX: &str = "m2m:session:";
+
+/// Default time a server trusts its cached copy of a shared session
+pub const DEFAULT_CLUSTER_CACHE_TTL: Duration = Duration::from_secs(1);
+
+/// Redis connection and I/O timeout
+#[cfg(feature = "cluster")]
+const REDIS_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Session state shared by a pool of servers through Redis
+#[derive(Debug, Clone)]
+pub struct ClusterConfig {
+    /// Redis URL, e.g. `redis://cache:6379/0`
+    pub redis_url: String,
+    /// Prefix of the session keys (separates pools sharing one Redis)
+    pub key_prefix: String,
+    /// How long a server acts on its cached copy before re-reading it
+    pub cache_ttl: Duration,
+}
+
+impl ClusterConfig {
+    /// Share sessions through the Redis server at `redis_url`
+    pub fn new(redis_url: impl Into<String>) -> Self {
+        Self {
+            redis_url: redis_url.into(),
+            key_prefix: DEFAULT_CLUSTER_KEY_PREFIX.to_string(),
+            cache_ttl: DEFAULT_CLUSTER_CACHE_TTL,
+        }
+    }
+
+    /// Set the session key prefix
+    pub fn with_key_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.key_prefix = prefix.into();
+        self
+    }
+
+    /// Set how long cached sessions are trusted
+    pub fn with_cache_ttl(mut self, ttl: Duration) -> Self {
+        self.cache_ttl = ttl;
+        self
+    }
+}
+
+/// Session store in Redis, shared by a pool of servers
+///
+/// Each session is one JSON value under `<prefix><id>` that Redis expires
+/// once the session's idle timeout has passed since its last activity.
+/// Uses one connection, re-established after I/O errors.
+#[cfg(feature = "cluster")]
+pub struct RedisSessionStore {
+    client: redis::Client,
+    conn: std::sync::Mutex<Option<redis::Connection>>,
+    prefix: String,
+}
+
+#[cfg(feature = "cluster")]
+impl RedisSessionStore {
+    /// Connect to the Redis server at `url`
+    pub fn open(url: &str) -> Result<Self> {
+        let client = redis::Client::open(url)
+            .map_err(|e| M2MError::Config(format!("Invalid Redis URL: {e}")))?;
+        let store = Self {
+            client,
+            conn: std::sync::Mutex::new(None),
+            prefix: DEFAULT_CLUSTER_KEY_PREFIX.to_string(),
+        };
+        store.with_conn(|conn| redis::cmd("PING").query::<String>(conn))?;
+        Ok(store)
+    }
+
+    /// Connect as configured for a cluster
+    pub fn from_config(config: &ClusterConfig) -> Result<Self> {
+        Ok(Self::open(&config.redis_url)?.with_prefix(&config.key_prefix))
+    }
+
+    /// Set the session key prefix
+    pub fn with_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefix = prefix.into();
+        self
+    }
+
+    fn key(&self, id: &str) -> String {
+        format!("{}{id}", self.prefix)
+    }
+
+    /// Run a command on the shared connection, connecting first if needed
+    fn with_conn<T>(
+        &self,
+        command: impl FnOnce(&mut redis::Connection) -> redis::RedisResult<T>,
+    ) -> Result<T> {
+        let mut conn = self
+            .conn
+            .lock()
+            .map_err(|_| M2MError::Server("Session store lock poisoned".to_string()))?;
+        if conn.is_none() {
+            let fresh = self
+                .client
+                .get_connection_with_timeout(REDIS_TIMEOUT)
+                .and_then(|fresh| {
+                    fresh.set_read_timeout(Some(REDIS_TIMEOUT))?;
+                    fresh.set_write_timeout(Some(REDIS_TIMEOUT))?;
+                    Ok(fresh)
+                })
+                .map_err(|e| M2MError::Server(format!("Redis: {e}")))?;
+            *conn = Some(fresh);
+        }
+
+        let result = command(conn.as_mut().expect("connected above"));
+        result.map_err(|e| {
+            if e.is_io_error() || e.is_connection_dropped() || e.is_timeout() {
+                *conn = None;
+            }
+            M2MError::Server(format!("Redis: {e}"))
+        })
+    }
+}
+
+#[cfg(feature = "cluster")]
+impl std::fmt::Debug for RedisSessionStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RedisSessionStore")
+            .field("prefix", &self.prefix)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "cluster")]
+impl SessionStore for RedisSessionStore {
+    fn save(&self, snapshot: &SessionSnapshot) -> Result<()> {
+        let value = serde_json::to_vec(snapshot)?;
+        let ttl = snapshot
+            .timeout_secs
+            .saturating_sub(snapshot.idle_secs())
+            .max(1);
+        self.with_conn(|conn| {
+            redis::cmd("SET")
+                .arg(self.key(&snapshot.id))
+                .arg(value)
+                .arg("EX")
+                .arg(ttl)
+                .query::<()>(conn)
+        })
+    }
+
+    fn load_all(&self) -> Result<Vec<SessionSnapshot>> {
+        let keys: Vec<String> = self.with_conn(|conn| {
+            let pattern = format!("{}*", self.prefix);
+            let keys = redis::cmd("SCAN")
+                .cursor_arg(0)
+                .arg("MATCH")
+                .arg(pattern)
+                .clone()
+                .iter::<String>(conn)?
+                .collect();
+            Ok(keys)
+        })?;
+
+        let mut snapshots = Vec::with_capacity(keys.len());
+        for key in keys {
+            let id = key.strip_prefix(&self.prefix).unwrap_or(&key);
+            // Keys may expire between SCAN and GET
+            if let Some(snapshot) = self.load(id)? {
+                snapshots.push(snapshot);
+            }
+        }
+        Ok(snapshots)
+    }
+
+    fn load(&self, id: &str) -> Result<Option<SessionSnapshot>> {
+        let value: Option<Vec<u8>> =
+            self.with_conn(|conn| redis::cmd("GET").arg(self.key(id)).query(conn))?;
+        value
+            .map(|value| Ok(serde_json::from_slice(&value)?))
+            .transpose()
+    }
+
+    fn remove(&self, id: &str) -> Result<()> {
+        self.with_conn(|conn| redis::cmd("DEL").arg(self.key(id)).query::<()>(conn))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::{Capabilities, Session};
+
+    #[test]
+    fn test_file_store_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = FileSessionStore::open(dir.path()).unwrap();
+
+        let session = Session::new(Capabilities::default());
+        store.save(&session.snapshot()).unwrap();
+
+        // Reopening sees the same sessions
+        let reopened = FileSessionStore::open(dir.path()).unwrap();
+        let loaded = reopened.load_all().unwrap();
+        assert_eq!(loaded.len(), 1);
+        assert_eq!(loaded[0].id, session.id());
+        assert!(reopened.load(session.id()).unwrap().is_some());
+
+        reopened.remove(session.id()).unwrap();
+        reopened.remove(session.id()).unwrap();
+        assert!(reopened.load_all().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_file_store_rejects_path_ids() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = FileSessionStore::open(dir.path()).unwrap();
+        assert!(store.remove("../etc/passwd").is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "cluster")]
+    #[ignore = "requires Redis - run with: M2M_TEST_REDIS_URL=redis://127.0.0.1/ cargo test --features cluster test_redis_store -- --ignored"]
+    fn test_redis_store_roundtrip() {
+        let url = std::env::var("M2M_TEST_REDIS_URL").unwrap();
+        let store = RedisSessionStore::open(&url)
+            .unwrap()
+            .with_prefix(format!("m2m:test:{}:", uuid::Uuid::new_v4()));
+
+        let session = Session::new(Capabilities::default());
+        store.save(&session.snapshot()).unwrap();
+        assert_eq!(store.load(session.id()).unwrap().unwrap().id, session.id());
+        assert_eq!(store.load_all().unwrap().len(), 1);
+
+        store.remove(session.id()).unwrap();
+        assert!(store.load(session.id()).unwrap().is_none());
+    }
+}
@@ -0,0 +1,612 @@
+//! Protocol conformance test vectors.
+//!
+//! Third-party implementations (Python, Go, ...) check wire compatibility
+//! against fixtures generated by this module instead of against a running
+//! Rust agent. A [`TestVectors`] document holds:
+//!
+//! - **handshake**: HELLO, ACCEPT and REJECT messages as JSON
+//! - **frames**: a JSON payload and its wire form for every algorithm and
+//!   security mode compiled into the generator
+//! - **key_derivation**: HKDF-SHA256, key hierarchy and X25519 outputs
+//!   (`crypto` feature)
+//!
+//! Binary values are lowercase hex. A frame vector marked `deterministic`
+//! must be reproduced byte for byte by an encoder; the others (AEAD, with
+//! its random nonce) only need to decode to `input`.
+//!
+//! The published fixture is generated with every feature enabled:
+//!
+//! ```bash
+//! cargo run --bin m2m-testvectors --features codecs,crypto -- --out tests/vectors/m2m-3.0.json
+//! ```
+//!
+//! [`verify`] checks a document against this build, which keeps the fixture
+//! and the implementation from drifting apart.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::codec::{Algorithm, CodecEngine};
+use crate::error::{M2MError, Result};
+use crate::protocol::{
+    Capabilities, CompressionCaps, KeyExchangeSuite, MaxFrameSize, Message, RejectionCode,
+    SecurityCaps, PROTOCOL_VERSION,
+};
+
+/// Version of the test vector document layout
+pub const VECTOR_FORMAT_VERSION: u32 = 1;
+
+/// Fixed timestamp of handshake vectors (Unix millis)
+const VECTOR_TIMESTAMP: u64 = 1_767_225_600_000;
+
+/// Short chat request (below the Brotli threshold)
+const CHAT_SMALL: &str = r#"{"model":"gpt-4o","messages":[{"role":"user","content":"Hello"}]}"#;
+
+/// Multi-turn chat request with tools
+const CHAT_LARGE: &str = r#"{"model":"gpt-4o","messages":[{"role":"system","content":"You are a helpful assistant that answers questions about the weather."},{"role":"user","content":"What is the weather like in Oslo today? I am planning a walk along the harbour."},{"role":"assistant","content":null,"tool_calls":[{"id":"call_1","type":"function","function":{"name":"get_weather","arguments":"{\"city\":\"Oslo\"}"}}]},{"role":"tool","tool_call_id":"call_1","content":"{\"temperature\":4,\"conditions\":\"light rain\",\"wind_kmh\":18}"},{"role":"user","content":"Should I bring an umbrella for the walk along the harbour?"}],"tools":[{"type":"function","function":{"name":"get_weather","description":"Get the current weather for a city","parameters":{"type":"object","properties":{"city":{"type":"string"}},"required":["city"]}}}],"temperature":0.7,"max_tokens":256}"#;
+
+/// Chat completion response with usage
+const CHAT_RESPONSE: &str = r#"{"id":"chatcmpl-vector","object":"chat.completion","created":1767225600,"model":"gpt-4o","choices":[{"index":0,"message":{"role":"assistant","content":"Yes. Light rain is expected in Oslo all afternoon, so bring an umbrella."},"finish_reason":"stop"}],"usage":{"prompt_tokens":96,"completion_tokens":18,"total_tokens":114}}"#;
+
+/// Test vector document
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TestVectors {
+    /// Document layout version ([`VECTOR_FORMAT_VERSION`])
+    pub format: u32,
+    /// Protocol version the vectors describe
+    pub protocol_version: String,
+    /// Implementation that generated the vectors
+    pub generator: String,
+    /// Handshake messages
+    pub handshake: Vec<MessageVector>,
+    /// Compressed and secured frames
+    pub frames: Vec<FrameVector>,
+    /// Key derivation outputs
+    #[serde(default)]
+    pub key_derivation: Vec<KdfVector>,
+}
+
+/// A protocol message in its JSON form
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MessageVector {
+    /// Vector name
+    pub name: String,
+    /// What the vector exercises
+    pub description: String,
+    /// Message JSON
+    pub message: Value,
+}
+
+/// A payload and its wire form
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FrameVector {
+    /// Vector name
+    pub name: String,
+    /// What the vector exercises
+    pub description: String,
+    /// Compression algorithm
+    pub algorithm: Algorithm,
+    /// Security mode (`none`, `hmac` or `aead`)
+    pub security: String,
+    /// Key for secured frames (hex)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+    /// Payload JSON
+    pub input: String,
+    /// Wire form
+    pub wire: String,
+    /// Whether encoding `input` reproduces `wire` exactly
+    pub deterministic: bool,
+}
+
+/// A key derivation and its output
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KdfVector {
+    /// Vector name
+    pub name: String,
+    /// What the vector exercises
+    pub description: String,
+    /// Function (`hkdf-sha256` or `x25519`)
+    pub function: String,
+    /// Named inputs (hex for binary values, text otherwise)
+    pub inputs: BTreeMap<String, String>,
+    /// Output (hex)
+    pub output: String,
+}
+
+/// Outcome of [`verify`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VerifyReport {
+    /// Vectors that matched
+    pub passed: Vec<String>,
+    /// Vectors this build cannot check (algorithm or feature not compiled)
+    pub skipped: Vec<String>,
+    /// Vectors that did not match, with the reason
+    pub failed: Vec<(String, String)>,
+}
+
+impl VerifyReport {
+    /// Check if no vector failed
+    pub fn is_ok(&self) -> bool {
+        self.failed.is_empty()
+    }
+
+    fn record(&mut self, name: &str, outcome: Result<()>) {
+        match outcome {
+            Ok(()) => self.passed.push(name.to_string()),
+            Err(e) => self.failed.push((name.to_string(), e.to_string())),
+        }
+    }
+}
+
+/// Generate the test vectors for this build
+pub fn generate() -> Result<TestVectors> {
+    Ok(TestVectors {
+        format: VECTOR_FORMAT_VERSION,
+        protocol_version: PROTOCOL_VERSION.to_string(),
+        generator: format!("m2m-protocol {}", env!("CARGO_PKG_VERSION")),
+        handshake: handshake_vectors()?,
+        frames: frame_vectors()?,
+        key_derivation: kdf_vectors()?,
+    })
+}
+
+/// Check test vectors against this build
+pub fn verify(vectors: &TestVectors) -> VerifyReport {
+    let mut report = VerifyReport::default();
+
+    for vector in &vectors.handshake {
+        report.record(&vector.name, verify_message(vector));
+    }
+
+    for vector in &vectors.frames {
+        if !vector.algorithm.is_available()
+            || (vector.security != "none" && !cfg!(feature = "crypto"))
+        {
+            report.skipped.push(vector.name.clone());
+            continue;
+        }
+        report.record(&vector.name, verify_frame(vector));
+    }
+
+    for vector in &vectors.key_derivation {
+        if !cfg!(feature = "crypto") {
+            report.skipped.push(vector.name.clone());
+            continue;
+        }
+        report.record(&vector.name, verify_kdf(vector));
+    }
+
+    report
+}
+
+/// Capabilities with no random or build-dependent fields
+fn vector_capabilities(agent_id: &str) -> Capabilities {
+    Capabilities {
+        agent_id: agent_id.to_string(),
+        agent_type: "m2m-testvectors".to_string(),
+        compression: CompressionCaps::default().with_algorithms(vec![
+            Algorithm::M2M,
+            Algorithm::Brotli,
+            Algorithm::None,
+        ]),
+        security: SecurityCaps {
+            key_exchange: vec![KeyExchangeSuite::X25519],
+            ..SecurityCaps::default()
+        },
+        ..Capabilities::default()
+    }
+    .with_typed_extension(MaxFrameSize(16 * 1024))
+}
+
+fn handshake_vectors() -> Result<Vec<MessageVector>> {
+    let session_id = "00000000-0000-4000-8000-000000000001";
+    let messages = [
+        (
+            "hello",
+            "HELLO from the initiating agent",
+            Message::hello(vector_capabilities("agent-a")),
+        ),
+        (
+            "accept",
+            "ACCEPT assigning the session ID",
+            Message::accept(session_id, vector_capabilities("agent-b")),
+        ),
+        (
+            "reject",
+            "REJECT when no algorithm is shared",
+            Message::reject(
+                RejectionCode::NoCommonAlgorithm,
+                "No common compression algorithm",
+            ),
+        ),
+        ("ping", "PING keep-alive", Message::ping(session_id)),
+        (
+            "close",
+            "CLOSE ending the session",
+            Message::close(session_id),
+        ),
+    ];
+
+    messages
+        .into_iter()
+        .map(|(name, description, mut message)| {
+            message.timestamp = VECTOR_TIMESTAMP;
+            Ok(MessageVector {
+                name: format!("handshake/{name}"),
+                description: description.to_string(),
+                message: serde_json::to_value(&message)?,
+            })
+        })
+        .collect()
+}
+
+fn verify_message(vector: &MessageVector) -> Result<()> {
+    let message: Message = serde_json::from_value(vector.message.clone())?;
+    if serde_json::to_value(&message)? != vector.message {
+        return Err(M2MError::Protocol(
+            "Message does not round-trip".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+fn frame_vectors() -> Result<Vec<FrameVector>> {
+    let engine = CodecEngine::new();
+    let cases = [
+        (Algorithm::M2M, "request-small", CHAT_SMALL),
+        (Algorithm::M2M, "request-large", CHAT_LARGE),
+        (Algorithm::M2M, "response", CHAT_RESPONSE),
+        (Algorithm::Brotli, "request-large", CHAT_LARGE),
+        (Algorithm::TokenNative, "request-small", CHAT_SMALL),
+        (Algorithm::None, "request-small", CHAT_SMALL),
+    ];
+
+    let mut vectors = Vec::new();
+    for (algorithm, name, input) in cases {
+        if !algorithm.is_available() {
+            continue;
+        }
+        let wire = engine.compress(input, algorithm)?.data;
+        vectors.push(FrameVector {
+            name: format!("{}/{name}", algorithm.name().to_lowercase()),
+            description: format!(
+                "{} encoding of a {}",
+                algorithm.name(),
+                name.replace('-', " ")
+            ),
+            algorithm,
+            security: "none".to_string(),
+            key: None,
+            input: input.to_string(),
+            wire,
+            deterministic: true,
+        });
+    }
+
+    #[cfg(feature = "crypto")]
+    vectors.extend(secure::frame_vectors()?);
+
+    Ok(vectors)
+}
+
+fn verify_frame(vector: &FrameVector) -> Result<()> {
+    #[cfg(feature = "crypto")]
+    if vector.security != "none" {
+        return secure::verify_frame(vector);
+    }
+
+    let engine = CodecEngine::new();
+    let decoded = engine.decompress(&vector.wire)?;
+    if decoded != vector.input {
+        return Err(M2MError::Decompression(
+            "Wire does not decode to input".to_string(),
+        ));
+    }
+    if vector.deterministic && engine.compress(&vector.input, vector.algorithm)?.data != vector.wire
+    {
+        return Err(M2MError::Compression(
+            "Input does not encode to wire".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(feature = "crypto")]
+fn kdf_vectors() -> Result<Vec<KdfVector>> {
+    secure::kdf_vectors()
+}
+
+#[cfg(not(feature = "crypto"))]
+fn kdf_vectors() -> Result<Vec<KdfVector>> {
+    Ok(Vec::new())
+}
+
+#[cfg(feature = "crypto")]
+fn verify_kdf(vector: &KdfVector) -> Result<()> {
+    secure::verify_kdf(vector)
+}
+
+#[cfg(not(feature = "crypto"))]
+fn verify_kdf(_vector: &KdfVector) -> Result<()> {
+    Ok(())
+}
+
+/// Secured frames and key derivation
+#[cfg(feature = "crypto")]
+mod secure {
+    use super::{Algorithm, BTreeMap, FrameVector, KdfVector, M2MError, Result, CHAT_SMALL};
+    use crate::codec::m2m::crypto::{
+        AgentId, KeyHierarchy, KeyMaterial, KeyPair, PublicKey, SecurityContext,
+    };
+    use crate::codec::m2m::{M2MFrame, SecurityMode};
+
+    /// Lowercase hex encoding
+    fn hex_encode(bytes: &[u8]) -> String {
+        use std::fmt::Write;
+        bytes
+            .iter()
+            .fold(String::with_capacity(bytes.len() * 2), |mut s, b| {
+                let _ = write!(s, "{b:02x}");
+                s
+            })
+    }
+
+    /// Frame key (0x00..0x1f)
+    fn frame_key() -> KeyMaterial {
+        KeyMaterial::new((0u8..32).collect())
+    }
+
+    /// RFC 7748 section 6.1 private keys
+    const ALICE_SECRET: [u8; 32] = [
+        0x77, 0x07, 0x6d, 0x0a, 0x73, 0x18, 0xa5, 0x7d, 0x3c, 0x16, 0xc1, 0x72, 0x51, 0xb2, 0x66,
+        0x45, 0xdf, 0x4c, 0x2f, 0x87, 0xeb, 0xc0, 0x99, 0x2a, 0xb1, 0x77, 0xfb, 0xa5, 0x1d, 0xb9,
+        0x2c, 0x2a,
+    ];
+    const BOB_SECRET: [u8; 32] = [
+        0x5d, 0xab, 0x08, 0x7e, 0x62, 0x4a, 0x8a, 0x4b, 0x79, 0xe1, 0x7f, 0x8b, 0x83, 0x80, 0x0e,
+        0xe6, 0x6f, 0x3b, 0xb1, 0x29, 0x26, 0x18, 0xb6, 0xfd, 0x1c, 0x2f, 0x8b, 0x27, 0xff, 0x88,
+        0xe0, 0xeb,
+    ];
+
+    /// Context for session keys derived from an X25519 secret
+    const SESSION_CONTEXT: &str = "m2m-session-v1";
+
+    fn security_mode(name: &str) -> Result<SecurityMode> {
+        match name {
+            "hmac" => Ok(SecurityMode::Hmac),
+            "aead" => Ok(SecurityMode::Aead),
+            other => Err(M2MError::InvalidMessage(format!(
+                "Unknown security mode: {other}"
+            ))),
+        }
+    }
+
+    pub(super) fn frame_vectors() -> Result<Vec<FrameVector>> {
+        let key = frame_key();
+        [("hmac", true), ("aead", false)]
+            .into_iter()
+            .map(|(security, deterministic)| {
+                let mut ctx = SecurityContext::new(key.clone());
+                let wire = M2MFrame::new_request(CHAT_SMALL)?
+                    .encode_secure_string(security_mode(security)?, &mut ctx)?;
+                Ok(FrameVector {
+                    name: format!("m2m+{security}/request-small"),
+                    description: format!(
+                        "M2M request frame secured with {}",
+                        security.to_uppercase()
+                    ),
+                    algorithm: Algorithm::M2M,
+                    security: security.to_string(),
+                    key: Some(hex_encode(key.as_bytes())),
+                    input: CHAT_SMALL.to_string(),
+                    wire,
+                    deterministic,
+                })
+            })
+            .collect()
+    }
+
+    pub(super) fn verify_frame(vector: &FrameVector) -> Result<()> {
+        let key = vector
+            .key
+            .as_deref()
+            .ok_or_else(|| M2MError::InvalidMessage("Secured frame without key".to_string()))?;
+        let mut ctx = SecurityContext::new(KeyMaterial::from_hex(key).map_err(crypto_error)?);
+
+        let frame = M2MFrame::decode_secure_string(&vector.wire, &ctx)?;
+        if frame.payload != vector.input {
+            return Err(M2MError::Decompression(
+                "Wire does not decode to input".to_string(),
+            ));
+        }
+        if vector.deterministic {
+            let wire = M2MFrame::new_request(&vector.input)?
+                .encode_secure_string(security_mode(&vector.security)?, &mut ctx)?;
+            if wire != vector.wire {
+                return Err(M2MError::Compression(
+                    "Input does not encode to wire".to_string(),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn crypto_error(e: impl std::fmt::Display) -> M2MError {
+        M2MError::InvalidMessage(format!("Invalid vector key: {e}"))
+    }
+
+    fn inputs<const N: usize>(pairs: [(&str, String); N]) -> BTreeMap<String, String> {
+        pairs.into_iter().map(|(k, v)| (k.to_string(), v)).collect()
+    }
+
+    pub(super) fn kdf_vectors() -> Result<Vec<KdfVector>> {
+        let hkdf = |ikm: &KeyMaterial, info: &str| -> Result<String> {
+            Ok(hex_encode(
+                ikm.derive(info.as_bytes(), 32)
+                    .map_err(crypto_error)?
+                    .as_bytes(),
+            ))
+        };
+
+        let master = KeyMaterial::new(vec![0x0b; 32]);
+        let hierarchy = KeyHierarchy::new(master.clone(), "acme");
+        let (agent_a, agent_b) = (AgentId::new("agent-a"), AgentId::new("agent-b"));
+        let master_hex = hex_encode(master.as_bytes());
+        let hierarchy_key =
+            |description: &str, name: &str, info: &str, key: KeyMaterial| KdfVector {
+                name: format!("hierarchy/{name}"),
+                description: description.to_string(),
+                function: "hkdf-sha256".to_string(),
+                inputs: inputs([
+                    ("ikm", master_hex.clone()),
+                    ("info", info.to_string()),
+                    ("salt", String::new()),
+                ]),
+                output: hex_encode(key.as_bytes()),
+            };
+
+        let mut vectors = vec![
+            hierarchy_key(
+                "Organization key",
+                "org",
+                "m2m/v1/acme",
+                hierarchy.derive_org_key().map_err(crypto_error)?,
+            ),
+            hierarchy_key(
+                "Agent key, epoch 0",
+                "agent",
+                "m2m/v1/acme/agent-a",
+                hierarchy.derive_agent_key(&agent_a).map_err(crypto_error)?,
+            ),
+            hierarchy_key(
+                "Agent key, epoch 3",
+                "agent-epoch",
+                "m2m/v1/acme/agent-a/epoch/3",
+                hierarchy
+                    .derive_agent_key_epoch(&agent_a, 3)
+                    .map_err(crypto_error)?,
+            ),
+            hierarchy_key(
+                "Session key between two agents (IDs sorted)",
+                "session",
+                "m2m/v1/acme/session/agent-a:agent-b/session-1",
+                hierarchy
+                    .derive_session_key(&agent_b, &agent_a, "session-1")
+                    .map_err(crypto_error)?,
+            ),
+        ];
+
+        let alice = KeyPair::from_secret(ALICE_SECRET);
+        let bob = KeyPair::from_secret(BOB_SECRET);
+        let shared = alice.diffie_hellman(bob.public_key());
+        vectors.push(KdfVector {
+            name: "x25519/shared-secret".to_string(),
+            description: "X25519 shared secret (RFC 7748 section 6.1 keys)".to_string(),
+            function: "x25519".to_string(),
+            inputs: inputs([
+                ("secret", hex_encode(&ALICE_SECRET)),
+                ("peer_public", hex_encode(bob.public_key().as_bytes())),
+            ]),
+            output: hex_encode(shared.as_bytes()),
+        });
+        vectors.push(KdfVector {
+            name: "x25519/session-key".to_string(),
+            description: "Session key derived from the X25519 shared secret".to_string(),
+            function: "hkdf-sha256".to_string(),
+            inputs: inputs([
+                ("ikm", hex_encode(shared.as_bytes())),
+                ("info", SESSION_CONTEXT.to_string()),
+                ("salt", String::new()),
+            ]),
+            output: hkdf(&shared, SESSION_CONTEXT)?,
+        });
+
+        Ok(vectors)
+    }
+
+    pub(super) fn verify_kdf(vector: &KdfVector) -> Result<()> {
+        let input = |name: &str| {
+            vector
+                .inputs
+                .get(name)
+                .ok_or_else(|| M2MError::InvalidMessage(format!("Missing input: {name}")))
+        };
+        let bytes = |name: &str| -> Result<Vec<u8>> {
+            Ok(KeyMaterial::from_hex(input(name)?)
+                .map_err(crypto_error)?
+                .as_bytes()
+                .to_vec())
+        };
+
+        let output = match vector.function.as_str() {
+            "hkdf-sha256" => {
+                let ikm = KeyMaterial::new(bytes("ikm")?);
+                hex_encode(
+                    ikm.derive(input("info")?.as_bytes(), 32)
+                        .map_err(crypto_error)?
+                        .as_bytes(),
+                )
+            },
+            "x25519" => {
+                let secret: [u8; 32] = bytes("secret")?
+                    .try_into()
+                    .map_err(|_| crypto_error("secret must be 32 bytes"))?;
+                let peer: [u8; 32] = bytes("peer_public")?
+                    .try_into()
+                    .map_err(|_| crypto_error("public key must be 32 bytes"))?;
+                let shared =
+                    KeyPair::from_secret(secret).diffie_hellman(&PublicKey::from_bytes(peer));
+                hex_encode(shared.as_bytes())
+            },
+            other => {
+                return Err(M2MError::InvalidMessage(format!(
+                    "Unknown function: {other}"
+                )))
+            },
+        };
+
+        if output != vector.output {
+            return Err(M2MError::Protocol(format!(
+                "Derived {output}, expected {}",
+                vector.output
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generated_vectors_verify() {
+        let vectors = generate().unwrap();
+        assert_eq!(vectors.handshake.len(), 5);
+        assert!(vectors.frames.iter().any(|v| v.name == "m2m/request-small"));
+
+        let report = verify(&vectors);
+        assert!(report.is_ok(), "{:?}", report.failed);
+        assert!(report.skipped.is_empty());
+
+        // Generation is reproducible apart from AEAD nonces
+        let again = generate().unwrap();
+        assert_eq!(again.handshake, vectors.handshake);
+        for (a, b) in again.frames.iter().zip(&vectors.frames) {
+            assert!(!a.deterministic || a == b, "{} differs", a.name);
+        }
+    }
+
+    #[test]
+    fn test_tampered_vector_fails() {
+        let mut vectors = generate().unwrap();
+        vectors.frames[0].input = CHAT_RESPONSE.to_string();
+        vectors.handshake[0].message["type"] = Value::String("BOGUS".to_string());
+
+        let report = verify(&vectors);
+        assert_eq!(report.failed.len(), 2);
+    }
+}
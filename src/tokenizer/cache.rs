@@ -0,0 +1,230 @@
+//! Token count cache.
+//!
+//! Agent frameworks resend the same multi-KB system prompt on every turn,
+//! and BPE encoding it again each time dominates counting cost. The cache
+//! keys counts on a hash of the exact text and the encoding, so repeats
+//! skip the tokenizer entirely.
+//!
+//! A single process-wide LRU backs
+//! [`count_tokens_cached`](super::count_tokens_cached); its counters are
+//! available from [`token_cache_stats`](super::token_cache_stats).
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::Mutex;
+
+use serde::Serialize;
+
+use crate::models::Encoding;
+
+/// Default number of cached counts
+pub const DEFAULT_TOKEN_CACHE_CAPACITY: usize = 4096;
+
+/// Texts shorter than this are counted directly (hashing costs about as much)
+pub const MIN_CACHED_LEN: usize = 256;
+
+/// Token cache hit/miss counters
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct TokenCacheStats {
+    /// Counts served from the cache
+    pub hits: u64,
+    /// Counts that ran the tokenizer
+    pub misses: u64,
+    /// Entries dropped to make room
+    pub evictions: u64,
+    /// Entries currently cached
+    pub entries: usize,
+    /// Maximum entries
+    pub capacity: usize,
+}
+
+impl TokenCacheStats {
+    /// Fraction of lookups served from the cache (0.0 when unused)
+    pub fn hit_rate(&self) -> f64 {
+        let total = self.hits + self.misses;
+        if total == 0 {
+            0.0
+        } else {
+            self.hits as f64 / total as f64
+        }
+    }
+}
+
+/// Cache key: content hash, content length and encoding
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct Key {
+    hash: u64,
+    len: usize,
+    encoding: Encoding,
+}
+
+/// Cached count with its last use
+#[derive(Debug)]
+struct Entry {
+    tokens: usize,
+    last_used: u64,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    entries: HashMap<Key, Entry>,
+    /// Monotonic use counter for LRU ordering
+    tick: u64,
+    stats: TokenCacheStats,
+}
+
+/// LRU cache of token counts keyed by content hash and encoding
+#[derive(Debug)]
+pub struct TokenCache {
+    inner: Mutex<Inner>,
+    capacity: usize,
+}
+
+impl Default for TokenCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_TOKEN_CACHE_CAPACITY)
+    }
+}
+
+impl TokenCache {
+    /// Create a cache holding at most `capacity` counts
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            inner: Mutex::new(Inner::default()),
+            capacity: capacity.max(1),
+        }
+    }
+
+    /// Return the cached count of `text`, or compute and cache it with `count`
+    pub fn get_or_count(
+        &self,
+        text: &str,
+        encoding: Encoding,
+        count: impl FnOnce(&str) -> usize,
+    ) -> usize {
+        let mut hasher = DefaultHasher::new();
+        text.hash(&mut hasher);
+        let key = Key {
+            hash: hasher.finish(),
+            len: text.len(),
+            encoding,
+        };
+
+        if let Some(tokens) = self.get(key) {
+            return tokens;
+        }
+        // Count outside the lock; a concurrent miss just counts twice
+        let tokens = count(text);
+        self.insert(key, tokens);
+        tokens
+    }
+
+    fn get(&self, key: Key) -> Option<usize> {
+        let mut inner = self.inner.lock().ok()?;
+        inner.tick += 1;
+        let tick = inner.tick;
+
+        let tokens = inner.entries.get_mut(&key).map(|entry| {
+            entry.last_used = tick;
+            entry.tokens
+        });
+        if tokens.is_some() {
+            inner.stats.hits += 1;
+        } else {
+            inner.stats.misses += 1;
+        }
+        tokens
+    }
+
+    fn insert(&self, key: Key, tokens: usize) {
+        let Ok(mut inner) = self.inner.lock() else {
+            return;
+        };
+        inner.tick += 1;
+        let tick = inner.tick;
+
+        if !inner.entries.contains_key(&key) && inner.entries.len() >= self.capacity {
+            let oldest = inner
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| *key);
+            if let Some(oldest) = oldest {
+                inner.entries.remove(&oldest);
+                inner.stats.evictions += 1;
+            }
+        }
+        inner.entries.insert(
+            key,
+            Entry {
+                tokens,
+                last_used: tick,
+            },
+        );
+    }
+
+    /// Current counters
+    pub fn stats(&self) -> TokenCacheStats {
+        self.inner
+            .lock()
+            .map(|inner| TokenCacheStats {
+                entries: inner.entries.len(),
+                capacity: self.capacity,
+                ..inner.stats
+            })
+            .unwrap_or_default()
+    }
+
+    /// Drop all entries and reset the counters
+    pub fn clear(&self) {
+        if let Ok(mut inner) = self.inner.lock() {
+            *inner = Inner::default();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hits_keyed_by_text_and_encoding() {
+        let cache = TokenCache::new(8);
+        let mut calls = 0;
+        let mut count = |text: &str, encoding| {
+            cache.get_or_count(text, encoding, |t| {
+                calls += 1;
+                t.len()
+            })
+        };
+
+        assert_eq!(count("system prompt", Encoding::Cl100kBase), 13);
+        assert_eq!(count("system prompt", Encoding::Cl100kBase), 13);
+        count("system prompt", Encoding::O200kBase);
+        count("system prompt!", Encoding::Cl100kBase);
+        assert_eq!(calls, 3);
+
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses, stats.entries), (1, 3, 3));
+        assert!((stats.hit_rate() - 0.25).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_lru_eviction() {
+        let cache = TokenCache::new(2);
+        let count = |text: &str| cache.get_or_count(text, Encoding::Cl100kBase, str::len);
+
+        count("a");
+        count("bb");
+        count("a"); // "a" is now most recent
+        count("ccc");
+        count("a");
+
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.evictions, stats.entries), (2, 1, 2));
+
+        cache.clear();
+        assert_eq!(cache.stats().entries, 0);
+    }
+}
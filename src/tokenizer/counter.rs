@@ -1,22 +1,34 @@
 //! Token counting implementation.
 //!
 //! Uses tiktoken-rs for accurate BPE token counting with lazy-loaded encoders.
+//! Without the `tiktoken` feature every encoding falls back to the
+//! ~4 characters per token heuristic.
 
 use std::sync::OnceLock;
+#[cfg(feature = "tiktoken")]
 use tiktoken_rs::{cl100k_base, o200k_base, CoreBPE};
 
+use super::cache::{TokenCache, TokenCacheStats, MIN_CACHED_LEN};
+use super::stream::StreamCounter;
 use crate::models::Encoding;
 
 // Lazy-loaded tokenizer instances (thread-safe singletons)
+#[cfg(feature = "tiktoken")]
 static CL100K: OnceLock<CoreBPE> = OnceLock::new();
+#[cfg(feature = "tiktoken")]
 static O200K: OnceLock<CoreBPE> = OnceLock::new();
 
+// Process-wide token count cache
+static TOKEN_CACHE: OnceLock<TokenCache> = OnceLock::new();
+
 /// Get the cl100k_base tokenizer (lazy-loaded)
+#[cfg(feature = "tiktoken")]
 fn get_cl100k() -> &'static CoreBPE {
     CL100K.get_or_init(|| cl100k_base().expect("Failed to load cl100k_base tokenizer"))
 }
 
 /// Get the o200k_base tokenizer (lazy-loaded)
+#[cfg(feature = "tiktoken")]
 fn get_o200k() -> &'static CoreBPE {
     O200K.get_or_init(|| o200k_base().expect("Failed to load o200k_base tokenizer"))
 }
@@ -53,6 +65,7 @@ pub fn count_tokens(text: &str) -> usize {
 /// // Unknown models use heuristic (~4 chars per token)
 /// let tokens = count_tokens_with_encoding("Hello!", Encoding::Heuristic);
 /// ```
+#[cfg(feature = "tiktoken")]
 pub fn count_tokens_with_encoding(text: &str, encoding: Encoding) -> usize {
     match encoding {
         Encoding::Cl100kBase => get_cl100k().encode_with_special_tokens(text).len(),
@@ -72,6 +85,47 @@ pub fn count_tokens_with_encoding(text: &str, encoding: Encoding) -> usize {
     }
 }
 
+/// Count tokens with a specific encoding
+///
+/// Built without the `tiktoken` feature: every encoding uses the heuristic.
+#[cfg(not(feature = "tiktoken"))]
+pub fn count_tokens_with_encoding(text: &str, _encoding: Encoding) -> usize {
+    heuristic_count(text)
+}
+
+/// Count tokens with a specific encoding, reusing earlier counts of the same text
+///
+/// Texts resent verbatim, such as system prompts, are tokenized once and
+/// served from a process-wide LRU afterwards. Short texts and the heuristic
+/// encoding are counted directly.
+///
+/// # Example
+/// ```
+/// use m2m::tokenizer::{count_tokens_cached, count_tokens_with_encoding};
+/// use m2m::models::Encoding;
+///
+/// let prompt = "You are a helpful assistant. ".repeat(20);
+/// let first = count_tokens_cached(&prompt, Encoding::Cl100kBase);
+/// let again = count_tokens_cached(&prompt, Encoding::Cl100kBase); // cache hit
+/// assert_eq!(first, again);
+/// assert_eq!(first, count_tokens_with_encoding(&prompt, Encoding::Cl100kBase));
+/// ```
+pub fn count_tokens_cached(text: &str, encoding: Encoding) -> usize {
+    if text.len() < MIN_CACHED_LEN || encoding == Encoding::Heuristic {
+        return count_tokens_with_encoding(text, encoding);
+    }
+    TOKEN_CACHE
+        .get_or_init(TokenCache::default)
+        .get_or_count(text, encoding, |text| {
+            count_tokens_with_encoding(text, encoding)
+        })
+}
+
+/// Hit/miss counters of the [`count_tokens_cached`] cache
+pub fn token_cache_stats() -> TokenCacheStats {
+    TOKEN_CACHE.get_or_init(TokenCache::default).stats()
+}
+
 /// Count tokens for a specific model ID
 ///
 /// Infers the encoding from the model ID and counts tokens.
@@ -137,6 +191,11 @@ impl TokenCounter {
         count_tokens_with_encoding(text, self.encoding)
     }
 
+    /// Count tokens in text, reusing earlier counts (see [`count_tokens_cached`])
+    pub fn count_cached(&self, text: &str) -> usize {
+        count_tokens_cached(text, self.encoding)
+    }
+
     /// Count tokens in multiple texts
     pub fn count_many(&self, texts: &[&str]) -> usize {
         texts.iter().map(|t| self.count(t)).sum()
@@ -148,6 +207,12 @@ impl TokenCounter {
         self.count(&text)
     }
 
+    /// Count tokens of text that arrives in chunks, such as streamed
+    /// completion deltas (see [`StreamCounter`])
+    pub fn count_stream(&self) -> StreamCounter {
+        StreamCounter::new(self.encoding)
+    }
+
     /// Get the encoding used by this counter
     pub fn encoding(&self) -> Encoding {
         self.encoding
@@ -273,6 +338,25 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_count_cached_matches_uncached() {
+        let prompt = "You are a careful assistant that answers in JSON. ".repeat(40);
+        let counter = TokenCounter::new(Encoding::O200kBase);
+        let before = token_cache_stats();
+
+        let first = counter.count_cached(&prompt);
+        let second = counter.count_cached(&prompt);
+        assert_eq!(first, second);
+        assert_eq!(first, counter.count(&prompt));
+
+        // Other tests share the process-wide cache, so only check growth
+        let after = token_cache_stats();
+        assert!(after.hits > before.hits);
+
+        // Short text bypasses the cache
+        assert_eq!(counter.count_cached("Hi"), counter.count("Hi"));
+    }
+
     #[test]
     fn test_heuristic_never_zero() {
         // Even short strings should give at least 1 token
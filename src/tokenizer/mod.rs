@@ -23,10 +23,27 @@
 //! let tokens = count_tokens_with_encoding("Hello, world!", Encoding::O200kBase);
 //! println!("Token count (o200k): {}", tokens);
 //! ```
+//!
+//! # Caching
+//!
+//! [`count_tokens_cached`] remembers counts of long texts in a process-wide
+//! LRU keyed by content hash and encoding, so a system prompt resent on
+//! every turn is tokenized once. [`token_cache_stats`] reports the hit rate.
+//!
+//! # Streaming
+//!
+//! [`TokenCounter::count_stream`] keeps a running count of text that
+//! arrives in chunks, such as SSE completion deltas, so output limits and
+//! costs can be checked while a reply streams. Chunks may split UTF-8
+//! characters and tokens; the count always matches the text received so far.
 
+mod cache;
 mod counter;
+mod stream;
 
+pub use cache::{TokenCache, TokenCacheStats, DEFAULT_TOKEN_CACHE_CAPACITY, MIN_CACHED_LEN};
 pub use counter::{
-    count_tokens, count_tokens_for_model, count_tokens_with_encoding, estimate_savings,
-    TokenCounter,
+    count_tokens, count_tokens_cached, count_tokens_for_model, count_tokens_with_encoding,
+    estimate_savings, token_cache_stats, TokenCounter,
 };
+pub use stream::{StreamCounter, MAX_PENDING_LEN};
@@ -0,0 +1,226 @@
+//! Incremental token counting for streamed text.
+//!
+//! BPE tokenizers split text into pre-tokens before merging, and no merge
+//! crosses a pre-token boundary. For the cl100k and o200k patterns a
+//! boundary always falls before a space or tab that follows a
+//! non-whitespace character, and before a non-whitespace character that
+//! follows a line break. Text up to the last such boundary is counted once
+//! and dropped; only the tail after it is recounted as chunks arrive, so the
+//! running total equals the count of the whole text so far.
+
+use super::counter::count_tokens_with_encoding;
+use crate::models::Encoding;
+
+/// Longest tail held back waiting for a token boundary
+///
+/// Longer runs without one (base64, minified code) are split anyway, which
+/// may overcount by one token per split.
+pub const MAX_PENDING_LEN: usize = 1024;
+
+/// Running token count of text that arrives in chunks
+///
+/// Created by [`TokenCounter::count_stream`](super::TokenCounter::count_stream).
+///
+/// # Example
+/// ```
+/// use m2m::models::Encoding;
+/// use m2m::tokenizer::{count_tokens_with_encoding, TokenCounter};
+///
+/// let counter = TokenCounter::new(Encoding::Cl100kBase);
+/// let mut stream = counter.count_stream();
+/// for delta in ["The quick br", "own fox jum", "ps over the lazy dog."] {
+///     stream.push(delta);
+/// }
+/// let text = "The quick brown fox jumps over the lazy dog.";
+/// assert_eq!(stream.total(), count_tokens_with_encoding(text, Encoding::Cl100kBase));
+/// ```
+#[derive(Debug, Clone)]
+pub struct StreamCounter {
+    /// Encoding tokens are counted with
+    encoding: Encoding,
+    /// Tokens before the last boundary
+    settled: usize,
+    /// Bytes received, for the length heuristic
+    bytes: usize,
+    /// Text after the last boundary
+    pending: String,
+    /// Incomplete UTF-8 sequence at the end of the last byte chunk
+    partial: Vec<u8>,
+}
+
+impl StreamCounter {
+    /// Start an empty count
+    pub(super) fn new(encoding: Encoding) -> Self {
+        Self {
+            encoding,
+            settled: 0,
+            bytes: 0,
+            pending: String::new(),
+            partial: Vec::new(),
+        }
+    }
+
+    /// Add a text delta, returning the tokens counted so far
+    pub fn push(&mut self, delta: &str) -> usize {
+        self.bytes += delta.len();
+        if !self.is_heuristic() {
+            self.pending.push_str(delta);
+            self.settle();
+        }
+        self.total()
+    }
+
+    /// Add a chunk of UTF-8 bytes, returning the tokens counted so far
+    ///
+    /// A multi-byte character split across chunks is counted once it is
+    /// complete. Invalid bytes count as U+FFFD.
+    pub fn push_bytes(&mut self, chunk: &[u8]) -> usize {
+        self.partial.extend_from_slice(chunk);
+        let bytes = std::mem::take(&mut self.partial);
+
+        let mut text = String::new();
+        let mut rest = bytes.as_slice();
+        while !rest.is_empty() {
+            match std::str::from_utf8(rest) {
+                Ok(valid) => {
+                    text.push_str(valid);
+                    rest = &[];
+                },
+                Err(e) => {
+                    let (valid, after) = rest.split_at(e.valid_up_to());
+                    text.push_str(&String::from_utf8_lossy(valid));
+                    match e.error_len() {
+                        Some(len) => {
+                            text.push(char::REPLACEMENT_CHARACTER);
+                            rest = &after[len..];
+                        },
+                        None => {
+                            self.partial = after.to_vec();
+                            break;
+                        },
+                    }
+                },
+            }
+        }
+        self.push(&text)
+    }
+
+    /// Tokens counted so far
+    ///
+    /// Excludes an incomplete UTF-8 sequence from [`push_bytes`](Self::push_bytes).
+    pub fn total(&self) -> usize {
+        if self.is_heuristic() {
+            return self.bytes.div_ceil(4);
+        }
+        self.settled + count_tokens_with_encoding(&self.pending, self.encoding)
+    }
+
+    /// End the stream, returning the final count
+    ///
+    /// An incomplete UTF-8 sequence left at the end counts as U+FFFD.
+    pub fn finish(mut self) -> usize {
+        if !self.partial.is_empty() {
+            self.partial.clear();
+            self.push(&char::REPLACEMENT_CHARACTER.to_string());
+        }
+        self.total()
+    }
+
+    /// Encoding tokens are counted with
+    pub fn encoding(&self) -> Encoding {
+        self.encoding
+    }
+
+    /// Whether tokens are estimated from the length alone
+    fn is_heuristic(&self) -> bool {
+        self.encoding == Encoding::Heuristic || !cfg!(feature = "tiktoken")
+    }
+
+    /// Count and drop the text before the last boundary
+    fn settle(&mut self) {
+        let boundary = last_boundary(&self.pending).or_else(|| {
+            (self.pending.len() > MAX_PENDING_LEN)
+                .then(|| self.pending.char_indices().last().map(|(i, _)| i))
+                .flatten()
+        });
+        if let Some(end) = boundary.filter(|&end| end > 0) {
+            self.settled += count_tokens_with_encoding(&self.pending[..end], self.encoding);
+            self.pending.drain(..end);
+        }
+    }
+}
+
+/// Byte offset of the last pre-token boundary in `text`
+fn last_boundary(text: &str) -> Option<usize> {
+    let mut boundary = None;
+    let mut prev: Option<char> = None;
+    for (i, c) in text.char_indices() {
+        if let Some(p) = prev {
+            let before_space = matches!(c, ' ' | '\t') && !p.is_whitespace();
+            let after_newline = matches!(p, '\r' | '\n') && !c.is_whitespace();
+            if before_space || after_newline {
+                boundary = Some(i);
+            }
+        }
+        prev = Some(c);
+    }
+    boundary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stream_matches_whole_text() {
+        let text = "Sure! Here's the plan:\n\n1. Read   the file\n\t2. Parse it — naïve 日本語 🦀\r\nDone.  ";
+        for encoding in [
+            Encoding::Cl100kBase,
+            Encoding::O200kBase,
+            Encoding::Heuristic,
+        ] {
+            let expected = count_tokens_with_encoding(text, encoding);
+            let bytes = text.as_bytes();
+            for size in [1, 2, 3, 5, 7, 16] {
+                let mut stream = StreamCounter::new(encoding);
+                for chunk in bytes.chunks(size) {
+                    stream.push_bytes(chunk);
+                }
+                assert_eq!(
+                    stream.finish(),
+                    expected,
+                    "{encoding:?}, {size}-byte chunks"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_partial_utf8_and_long_tail() {
+        let mut stream = StreamCounter::new(Encoding::Cl100kBase);
+        let crab = "🦀".as_bytes();
+        stream.push_bytes(&crab[..2]);
+        assert_eq!(stream.total(), 0);
+        stream.push_bytes(&crab[2..]);
+        assert_eq!(
+            stream.total(),
+            count_tokens_with_encoding("🦀", Encoding::Cl100kBase)
+        );
+
+        // Dangling and invalid bytes count as replacement characters
+        let mut stream = StreamCounter::new(Encoding::Cl100kBase);
+        stream.push_bytes(&[b'a', 0xFF, b'b', 0xE6]);
+        assert_eq!(
+            stream.finish(),
+            count_tokens_with_encoding("a\u{FFFD}b\u{FFFD}", Encoding::Cl100kBase)
+        );
+
+        // Text without boundaries is not held back indefinitely
+        let mut stream = StreamCounter::new(Encoding::Cl100kBase);
+        for _ in 0..100 {
+            stream.push("QUJDREVGR0g");
+        }
+        assert!(stream.pending.len() <= MAX_PENDING_LEN);
+        assert!(stream.total() > 0);
+    }
+}
@@ -260,6 +260,12 @@ impl QuicTransportConfig {
         self
     }
 
+    /// Enable or disable 0-RTT.
+    pub fn with_0rtt(mut self, enabled: bool) -> Self {
+        self.enable_0rtt = enabled;
+        self
+    }
+
     /// Build quinn ServerConfig from this configuration.
     pub fn build_quinn_config(&self) -> Result<quinn::ServerConfig> {
         let (certs, key) = self.tls.cert.load()?;
@@ -271,8 +277,42 @@ impl QuicTransportConfig {
             .map_err(|e| M2MError::Config(format!("Failed to build TLS config: {}", e)))?;
 
         rustls_config.alpn_protocols = self.tls.alpn_protocols.clone();
-        rustls_config.max_early_data_size = u32::MAX; // Enable 0-RTT
+        if self.enable_0rtt {
+            // QUIC requires either 0 or u32::MAX
+            rustls_config.max_early_data_size = u32::MAX;
+        }
+
+        let mut server_config = quinn::ServerConfig::with_crypto(Arc::new(rustls_config));
+        server_config.transport_config(Arc::new(self.build_transport_config()));
+
+        Ok(server_config)
+    }
+
+    /// Build quinn ClientConfig for connecting to an M2M QUIC server.
+    ///
+    /// Session tickets are stored in `tickets`; reuse the same store across
+    /// connections to resume with 0-RTT.
+    pub fn build_quinn_client_config(
+        &self,
+        roots: rustls::RootCertStore,
+        tickets: Arc<dyn rustls::client::ClientSessionStore>,
+    ) -> quinn::ClientConfig {
+        let mut rustls_config = rustls::ClientConfig::builder()
+            .with_safe_defaults()
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+
+        rustls_config.alpn_protocols = self.tls.alpn_protocols.clone();
+        rustls_config.resumption = rustls::client::Resumption::store(tickets);
+        rustls_config.enable_early_data = self.enable_0rtt;
 
+        let mut client_config = quinn::ClientConfig::new(Arc::new(rustls_config));
+        client_config.transport_config(Arc::new(self.build_transport_config()));
+        client_config
+    }
+
+    /// Shared QUIC transport parameters.
+    fn build_transport_config(&self) -> quinn::TransportConfig {
         let mut transport_config = quinn::TransportConfig::default();
         transport_config.max_idle_timeout(Some(
             self.max_idle_timeout
@@ -288,10 +328,7 @@ impl QuicTransportConfig {
                 .congestion_controller_factory(Arc::new(quinn::congestion::BbrConfig::default()));
         }
 
-        let mut server_config = quinn::ServerConfig::with_crypto(Arc::new(rustls_config));
-        server_config.transport_config(Arc::new(transport_config));
-
-        Ok(server_config)
+        transport_config
     }
 }
 
@@ -0,0 +1,443 @@
+//! Fault injection for resilience testing.
+//!
+//! [`FaultInjector`] wraps a transport and damages the frames passing
+//! through it, so tests can check session recovery, replay protection and
+//! decode hardening against realistic network faults. Each frame is
+//! checked against every fault in turn:
+//!
+//! | Fault | Effect |
+//! |-------|--------|
+//! | Drop | Frame is discarded |
+//! | Delay | Frame is held for up to `max_delay` |
+//! | Corrupt | One bit is flipped |
+//! | Truncate | Frame is cut at a random length |
+//! | Duplicate | Frame is delivered twice |
+//!
+//! Wrapping a [`Transport`] applies the faults to request bodies before
+//! they reach the router. A dropped request never reaches its handler and
+//! gets `503 Service Unavailable`; a duplicated request runs the handler
+//! twice and returns the second response. Message-oriented stacks (framed
+//! TCP, QUIC streams, data channels) can pass each outgoing frame through
+//! [`FaultInjector::inject`] instead, e.g. on a
+//! [`FaultInjector::standalone`].
+//!
+//! ```rust,ignore
+//! use m2m::transport::{FaultConfig, FaultInjector, TcpTransport, Transport};
+//!
+//! let config = FaultConfig::new()
+//!     .with_drop_rate(0.05)
+//!     .with_duplicate_rate(0.05)
+//!     .with_seed(42);
+//! let transport = FaultInjector::new(TcpTransport::localhost(8080), config);
+//! transport.serve(router).await?;
+//! println!("{:?}", transport.stats());
+//! ```
+//!
+//! A fixed seed makes the fault sequence reproducible for a given order
+//! of frames.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use axum::{
+    body::{to_bytes, Body, Bytes},
+    extract::Request,
+    http::StatusCode,
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    Router,
+};
+
+use super::Transport;
+use crate::error::Result;
+
+/// Fault probabilities (each 0.0 - 1.0, checked independently per frame)
+#[derive(Debug, Clone, PartialEq)]
+pub struct FaultConfig {
+    /// Probability a frame is dropped
+    pub drop_rate: f64,
+    /// Probability a frame is delivered twice
+    pub duplicate_rate: f64,
+    /// Probability a frame is delayed
+    pub delay_rate: f64,
+    /// Longest delay applied
+    pub max_delay: Duration,
+    /// Probability one bit of a frame is flipped
+    pub corrupt_rate: f64,
+    /// Probability a frame is cut short
+    pub truncate_rate: f64,
+    /// Random seed (`None` = seeded from the clock)
+    pub seed: Option<u64>,
+}
+
+impl Default for FaultConfig {
+    fn default() -> Self {
+        Self {
+            drop_rate: 0.0,
+            duplicate_rate: 0.0,
+            delay_rate: 0.0,
+            max_delay: Duration::from_millis(100),
+            corrupt_rate: 0.0,
+            truncate_rate: 0.0,
+            seed: None,
+        }
+    }
+}
+
+impl FaultConfig {
+    /// Create a config injecting no faults
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the drop probability
+    pub fn with_drop_rate(mut self, rate: f64) -> Self {
+        self.drop_rate = rate.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Set the duplication probability
+    pub fn with_duplicate_rate(mut self, rate: f64) -> Self {
+        self.duplicate_rate = rate.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Set the delay probability and the longest delay
+    pub fn with_delay(mut self, rate: f64, max_delay: Duration) -> Self {
+        self.delay_rate = rate.clamp(0.0, 1.0);
+        self.max_delay = max_delay;
+        self
+    }
+
+    /// Set the corruption probability
+    pub fn with_corrupt_rate(mut self, rate: f64) -> Self {
+        self.corrupt_rate = rate.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Set the truncation probability
+    pub fn with_truncate_rate(mut self, rate: f64) -> Self {
+        self.truncate_rate = rate.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Use a fixed seed for a reproducible fault sequence
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+}
+
+/// Counters of a [`FaultInjector`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FaultStats {
+    /// Frames seen
+    pub frames: u64,
+    /// Frames dropped
+    pub dropped: u64,
+    /// Frames delivered twice
+    pub duplicated: u64,
+    /// Frames delayed
+    pub delayed: u64,
+    /// Frames with a flipped bit
+    pub corrupted: u64,
+    /// Frames cut short
+    pub truncated: u64,
+}
+
+/// Fault state shared with the router middleware
+#[derive(Debug)]
+struct Faults {
+    /// Fault probabilities
+    config: FaultConfig,
+    /// SplitMix64 state
+    rng: Mutex<u64>,
+    /// Frames seen
+    frames: AtomicU64,
+    /// Frames dropped
+    dropped: AtomicU64,
+    /// Frames delivered twice
+    duplicated: AtomicU64,
+    /// Frames delayed
+    delayed: AtomicU64,
+    /// Frames with a flipped bit
+    corrupted: AtomicU64,
+    /// Frames cut short
+    truncated: AtomicU64,
+}
+
+impl Faults {
+    /// Next pseudo-random value (SplitMix64)
+    fn next_u64(&self) -> u64 {
+        let mut state = self
+            .rng
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = *state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform value in `[0, 1)`
+    fn next_f64(&self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Uniform value in `[0, bound)` (`bound` > 0)
+    fn below(&self, bound: usize) -> usize {
+        (self.next_u64() % bound as u64) as usize
+    }
+
+    /// Whether a fault with probability `rate` fires
+    fn hit(&self, rate: f64, counter: &AtomicU64) -> bool {
+        let hit = rate > 0.0 && self.next_f64() < rate;
+        if hit {
+            counter.fetch_add(1, Ordering::Relaxed);
+        }
+        hit
+    }
+
+    /// Frames to deliver in place of `frame`
+    async fn inject(&self, frame: Bytes) -> Vec<Bytes> {
+        self.frames.fetch_add(1, Ordering::Relaxed);
+        if self.hit(self.config.drop_rate, &self.dropped) {
+            return Vec::new();
+        }
+
+        if self.hit(self.config.delay_rate, &self.delayed) {
+            let max = u64::try_from(self.config.max_delay.as_micros()).unwrap_or(u64::MAX);
+            let delay = Duration::from_micros(self.next_u64() % max.saturating_add(1));
+            tokio::time::sleep(delay).await;
+        }
+
+        let mut frame = frame;
+        if !frame.is_empty() && self.hit(self.config.corrupt_rate, &self.corrupted) {
+            let mut damaged = frame.to_vec();
+            let bit = self.below(damaged.len() * 8);
+            damaged[bit / 8] ^= 1 << (bit % 8);
+            frame = Bytes::from(damaged);
+        }
+        if !frame.is_empty() && self.hit(self.config.truncate_rate, &self.truncated) {
+            frame.truncate(self.below(frame.len()));
+        }
+
+        if self.hit(self.config.duplicate_rate, &self.duplicated) {
+            vec![frame.clone(), frame]
+        } else {
+            vec![frame]
+        }
+    }
+}
+
+/// Transport wrapper damaging frames at configurable probabilities
+pub struct FaultInjector<T> {
+    /// Wrapped transport
+    inner: T,
+    /// Shared fault state
+    faults: Arc<Faults>,
+}
+
+impl<T> FaultInjector<T> {
+    /// Wrap a transport
+    pub fn new(inner: T, config: FaultConfig) -> Self {
+        let seed = config.seed.unwrap_or_else(|| {
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map_or(0, |d| d.as_nanos() as u64)
+        });
+        Self {
+            inner,
+            faults: Arc::new(Faults {
+                config,
+                rng: Mutex::new(seed),
+                frames: AtomicU64::new(0),
+                dropped: AtomicU64::new(0),
+                duplicated: AtomicU64::new(0),
+                delayed: AtomicU64::new(0),
+                corrupted: AtomicU64::new(0),
+                truncated: AtomicU64::new(0),
+            }),
+        }
+    }
+
+    /// Fault probabilities
+    pub fn config(&self) -> &FaultConfig {
+        &self.faults.config
+    }
+
+    /// Wrapped transport
+    pub fn get_ref(&self) -> &T {
+        &self.inner
+    }
+
+    /// Apply faults to one frame
+    ///
+    /// Returns the frames to deliver: none if dropped, two if duplicated.
+    /// Waits out any injected delay before returning.
+    pub async fn inject(&self, frame: impl Into<Bytes>) -> Vec<Bytes> {
+        self.faults.inject(frame.into()).await
+    }
+
+    /// Apply faults to the request bodies reaching `router`
+    pub fn wrap_router(&self, router: Router) -> Router {
+        let faults = Arc::clone(&self.faults);
+        router.layer(middleware::from_fn(move |request: Request, next: Next| {
+            let faults = Arc::clone(&faults);
+            async move { faulty_request(&faults, request, next).await }
+        }))
+    }
+
+    /// Current counters
+    pub fn stats(&self) -> FaultStats {
+        let faults = &self.faults;
+        FaultStats {
+            frames: faults.frames.load(Ordering::Relaxed),
+            dropped: faults.dropped.load(Ordering::Relaxed),
+            duplicated: faults.duplicated.load(Ordering::Relaxed),
+            delayed: faults.delayed.load(Ordering::Relaxed),
+            corrupted: faults.corrupted.load(Ordering::Relaxed),
+            truncated: faults.truncated.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl FaultInjector<()> {
+    /// Injector for frames passed to [`inject`](Self::inject) by hand
+    pub fn standalone(config: FaultConfig) -> Self {
+        Self::new((), config)
+    }
+}
+
+/// Run a request with its body passed through the injector
+async fn faulty_request(faults: &Faults, request: Request, next: Next) -> Response {
+    let (parts, body) = request.into_parts();
+    let Ok(body) = to_bytes(body, usize::MAX).await else {
+        return StatusCode::BAD_REQUEST.into_response();
+    };
+
+    let mut frames = faults.inject(body).await;
+    let Some(last) = frames.pop() else {
+        return StatusCode::SERVICE_UNAVAILABLE.into_response();
+    };
+    for duplicate in frames {
+        // Response to the first copy is lost, as on a retransmitting network
+        let request = Request::from_parts(parts.clone(), Body::from(duplicate));
+        drop(next.clone().run(request).await);
+    }
+    next.run(Request::from_parts(parts, Body::from(last))).await
+}
+
+impl<T: Transport> Transport for FaultInjector<T> {
+    fn serve(&self, router: Router) -> Pin<Box<dyn Future<Output = Result<()>> + Send + '_>> {
+        self.inner.serve(self.wrap_router(router))
+    }
+
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+
+    fn listen_addr(&self) -> String {
+        self.inner.listen_addr()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::TcpTransport;
+    use axum::routing::post;
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn test_frame_faults() {
+        let frame = Bytes::from_static(b"#M2M|1|payload");
+
+        let clean = FaultInjector::standalone(FaultConfig::new().with_seed(1));
+        assert_eq!(clean.inject(frame.clone()).await, vec![frame.clone()]);
+
+        let drop = FaultInjector::standalone(FaultConfig::new().with_drop_rate(1.0));
+        assert!(drop.inject(frame.clone()).await.is_empty());
+
+        let damage = FaultInjector::standalone(
+            FaultConfig::new()
+                .with_corrupt_rate(1.0)
+                .with_duplicate_rate(1.0)
+                .with_delay(1.0, Duration::from_millis(1))
+                .with_seed(7),
+        );
+        let frames = damage.inject(frame.clone()).await;
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0], frames[1]);
+        let flipped: u32 = frames[0]
+            .iter()
+            .zip(frame.iter())
+            .map(|(a, b)| (a ^ b).count_ones())
+            .sum();
+        assert_eq!(flipped, 1);
+
+        let truncate = FaultInjector::standalone(FaultConfig::new().with_truncate_rate(1.0));
+        assert!(truncate.inject(frame.clone()).await[0].len() < frame.len());
+
+        let stats = damage.stats();
+        assert_eq!(
+            (
+                stats.frames,
+                stats.corrupted,
+                stats.duplicated,
+                stats.delayed
+            ),
+            (1, 1, 1, 1)
+        );
+
+        // Same seed, same fault sequence
+        let config = FaultConfig::new().with_drop_rate(0.5).with_seed(42);
+        let a = FaultInjector::standalone(config.clone());
+        let b = FaultInjector::standalone(config);
+        for _ in 0..32 {
+            assert_eq!(
+                a.inject(frame.clone()).await.len(),
+                b.inject(frame.clone()).await.len()
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_router_faults() {
+        let calls = Arc::new(AtomicU64::new(0));
+        let counted = Arc::clone(&calls);
+        let router = Router::new().route(
+            "/echo",
+            post(move |body: Bytes| {
+                counted.fetch_add(1, Ordering::Relaxed);
+                async move { body }
+            }),
+        );
+        let request = || Request::post("/echo").body(Body::from("hello")).unwrap();
+
+        let duplicate = FaultInjector::new(
+            TcpTransport::localhost(0),
+            FaultConfig::new().with_duplicate_rate(1.0),
+        );
+        let response = duplicate
+            .wrap_router(router.clone())
+            .oneshot(request())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(calls.load(Ordering::Relaxed), 2);
+
+        let drop = FaultInjector::new(
+            TcpTransport::localhost(0),
+            FaultConfig::new().with_drop_rate(1.0),
+        );
+        let response = drop.wrap_router(router).oneshot(request()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(calls.load(Ordering::Relaxed), 2);
+        assert_eq!(drop.name(), "TCP/HTTP");
+    }
+}
@@ -0,0 +1,331 @@
+//! Length-prefixed message framing for raw byte streams.
+//!
+//! Lets two agents exchange protocol messages (HELLO, DATA, PING, ...)
+//! directly over a TCP or QUIC stream without the HTTP layer.
+//!
+//! # Frame Layout
+//!
+//! ```text
+//! ┌───────────────────┬──────────────────────────────┐
+//! │ length (u32, BE)  │ payload (JSON `Message`)     │
+//! │     4 bytes       │ `length` bytes               │
+//! └───────────────────┴──────────────────────────────┘
+//! ```
+//!
+//! The length covers only the payload. Frames larger than the configured
+//! maximum are rejected on both the read and write side, so a peer cannot
+//! force an unbounded allocation.
+//!
+//! # Usage
+//!
+//! ```rust,ignore
+//! use m2m::protocol::{Capabilities, Session};
+//! use m2m::transport::FramedConnection;
+//! use tokio::net::TcpStream;
+//!
+//! let stream = TcpStream::connect("127.0.0.1:9000").await?;
+//! let mut conn = FramedConnection::new(stream);
+//!
+//! let mut session = Session::new(Capabilities::default());
+//! conn.send(&session.create_hello()).await?;
+//!
+//! if let Some(accept) = conn.recv().await? {
+//!     session.process_accept(&accept)?;
+//! }
+//! ```
+//!
+//! For QUIC, join the bidirectional stream halves first:
+//! `FramedConnection::new(tokio::io::join(recv, send))`.
+//!
+//! To carry several sessions over one connection, route messages through a
+//! [`SessionMux`](crate::protocol::SessionMux).
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use crate::error::{M2MError, Result};
+use crate::protocol::Message;
+
+/// Size of the length prefix in bytes.
+pub const FRAME_HEADER_SIZE: usize = 4;
+
+/// Default maximum payload size per frame (16 MiB).
+pub const DEFAULT_MAX_FRAME_SIZE: usize = 16 * 1024 * 1024;
+
+/// Encode a payload into a length-prefixed frame.
+pub fn encode_frame(payload: &[u8]) -> Result<Vec<u8>> {
+    let len = u32::try_from(payload.len()).map_err(|_| {
+        M2MError::Protocol(format!("Frame payload too large: {} bytes", payload.len()))
+    })?;
+
+    let mut frame = Vec::with_capacity(FRAME_HEADER_SIZE + payload.len());
+    frame.extend_from_slice(&len.to_be_bytes());
+    frame.extend_from_slice(payload);
+    Ok(frame)
+}
+
+/// Decode a single length-prefixed frame from a buffer.
+///
+/// Returns the payload and the total number of bytes consumed, or `None`
+/// if the buffer does not yet contain a complete frame.
+pub fn decode_frame(buf: &[u8]) -> Option<(&[u8], usize)> {
+    if buf.len() < FRAME_HEADER_SIZE {
+        return None;
+    }
+
+    let len = u32::from_be_bytes([buf[0], buf[1], buf[2], buf[3]]) as usize;
+    let total = FRAME_HEADER_SIZE + len;
+    if buf.len() < total {
+        return None;
+    }
+
+    Some((&buf[FRAME_HEADER_SIZE..total], total))
+}
+
+/// Async reader/writer exchanging protocol messages over a byte stream.
+pub struct FramedConnection<S> {
+    /// Underlying stream
+    stream: S,
+    /// Maximum payload size accepted or sent
+    max_frame_size: usize,
+    /// Frames sent
+    frames_sent: u64,
+    /// Frames received
+    frames_received: u64,
+}
+
+impl<S> FramedConnection<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    /// Wrap a stream with the default maximum frame size.
+    pub fn new(stream: S) -> Self {
+        Self {
+            stream,
+            max_frame_size: DEFAULT_MAX_FRAME_SIZE,
+            frames_sent: 0,
+            frames_received: 0,
+        }
+    }
+
+    /// Set maximum payload size per frame.
+    pub fn with_max_frame_size(mut self, max: usize) -> Self {
+        self.max_frame_size = max;
+        self
+    }
+
+    /// Get maximum payload size per frame.
+    pub fn max_frame_size(&self) -> usize {
+        self.max_frame_size
+    }
+
+    /// Number of frames sent on this connection.
+    pub fn frames_sent(&self) -> u64 {
+        self.frames_sent
+    }
+
+    /// Number of frames received on this connection.
+    pub fn frames_received(&self) -> u64 {
+        self.frames_received
+    }
+
+    /// Write a raw payload as a single frame.
+    pub async fn write_frame(&mut self, payload: &[u8]) -> Result<()> {
+        if payload.len() > self.max_frame_size {
+            return Err(M2MError::Protocol(format!(
+                "Frame of {} bytes exceeds maximum of {} bytes",
+                payload.len(),
+                self.max_frame_size
+            )));
+        }
+
+        let frame = encode_frame(payload)?;
+        self.stream
+            .write_all(&frame)
+            .await
+            .map_err(|e| M2MError::Network(format!("Failed to write frame: {e}")))?;
+        self.stream
+            .flush()
+            .await
+            .map_err(|e| M2MError::Network(format!("Failed to flush frame: {e}")))?;
+
+        self.frames_sent += 1;
+        Ok(())
+    }
+
+    /// Read a raw frame payload.
+    ///
+    /// Returns `None` if the peer closed the stream cleanly between frames.
+    pub async fn read_frame(&mut self) -> Result<Option<Vec<u8>>> {
+        let mut header = [0u8; FRAME_HEADER_SIZE];
+        let mut filled = 0;
+        while filled < FRAME_HEADER_SIZE {
+            let read = self
+                .stream
+                .read(&mut header[filled..])
+                .await
+                .map_err(|e| M2MError::Network(format!("Failed to read frame header: {e}")))?;
+            if read == 0 {
+                // EOF before any header byte is a clean close; after some, truncation
+                if filled == 0 {
+                    return Ok(None);
+                }
+                return Err(M2MError::Network(format!(
+                    "Truncated frame header: {filled} of {FRAME_HEADER_SIZE} bytes"
+                )));
+            }
+            filled += read;
+        }
+
+        let len = u32::from_be_bytes(header) as usize;
+        if len > self.max_frame_size {
+            return Err(M2MError::Protocol(format!(
+                "Incoming frame of {} bytes exceeds maximum of {} bytes",
+                len, self.max_frame_size
+            )));
+        }
+
+        let mut payload = vec![0u8; len];
+        self.stream
+            .read_exact(&mut payload)
+            .await
+            .map_err(|e| M2MError::Network(format!("Truncated frame: {e}")))?;
+
+        self.frames_received += 1;
+        Ok(Some(payload))
+    }
+
+    /// Send a protocol message.
+    pub async fn send(&mut self, message: &Message) -> Result<()> {
+        let json = message.to_json_compact()?;
+        self.write_frame(json.as_bytes()).await
+    }
+
+    /// Receive the next protocol message.
+    ///
+    /// Returns `None` if the peer closed the stream cleanly.
+    pub async fn recv(&mut self) -> Result<Option<Message>> {
+        match self.read_frame().await? {
+            Some(payload) => {
+                let message = serde_json::from_slice(&payload)
+                    .map_err(|e| M2MError::InvalidMessage(format!("Invalid frame: {e}")))?;
+                Ok(Some(message))
+            },
+            None => Ok(None),
+        }
+    }
+
+    /// Shut down the write side of the stream.
+    pub async fn shutdown(&mut self) -> Result<()> {
+        self.stream
+            .shutdown()
+            .await
+            .map_err(|e| M2MError::Network(format!("Failed to shut down stream: {e}")))
+    }
+
+    /// Get a reference to the underlying stream.
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+
+    /// Consume the connection and return the underlying stream.
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::{Capabilities, MessageType, Session};
+
+    #[test]
+    fn test_encode_decode_frame() {
+        let frame = encode_frame(b"hello").unwrap();
+        assert_eq!(&frame[..4], &5u32.to_be_bytes());
+
+        let (payload, consumed) = decode_frame(&frame).unwrap();
+        assert_eq!(payload, b"hello");
+        assert_eq!(consumed, frame.len());
+
+        // Incomplete frames are not decoded
+        assert!(decode_frame(&frame[..6]).is_none());
+        assert!(decode_frame(&frame[..2]).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_handshake_over_duplex() {
+        let (a, b) = tokio::io::duplex(64 * 1024);
+        let mut client_conn = FramedConnection::new(a);
+        let mut server_conn = FramedConnection::new(b);
+
+        let mut client = Session::new(Capabilities::default());
+        let mut server = Session::new(Capabilities::default());
+
+        client_conn.send(&client.create_hello()).await.unwrap();
+        let hello = server_conn.recv().await.unwrap().unwrap();
+        assert_eq!(hello.msg_type, MessageType::Hello);
+
+        let accept = server.process_hello(&hello).unwrap();
+        server_conn.send(&accept).await.unwrap();
+        let accept = client_conn.recv().await.unwrap().unwrap();
+        client.process_accept(&accept).unwrap();
+        assert!(client.is_established());
+
+        let content = r#"{"model":"gpt-4o","messages":[{"role":"user","content":"Hi"}]}"#;
+        client_conn
+            .send(&client.compress(content).unwrap())
+            .await
+            .unwrap();
+        let data = server_conn.recv().await.unwrap().unwrap();
+        assert_eq!(server.decompress(&data).unwrap(), content);
+
+        client_conn.send(&Message::ping(client.id())).await.unwrap();
+        let ping = server_conn.recv().await.unwrap().unwrap();
+        assert_eq!(ping.msg_type, MessageType::Ping);
+
+        assert_eq!(client_conn.frames_sent(), 3);
+        assert_eq!(server_conn.frames_received(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_clean_eof_returns_none() {
+        let (a, b) = tokio::io::duplex(1024);
+        let mut reader = FramedConnection::new(b);
+        drop(a);
+
+        assert!(reader.recv().await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_truncated_header_is_an_error() {
+        for partial in 1..FRAME_HEADER_SIZE {
+            let (mut a, b) = tokio::io::duplex(1024);
+            let mut reader = FramedConnection::new(b);
+            a.write_all(&[0u8; FRAME_HEADER_SIZE][..partial])
+                .await
+                .unwrap();
+            drop(a);
+
+            assert!(
+                matches!(reader.read_frame().await, Err(M2MError::Network(_))),
+                "{partial} header bytes"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_oversized_frame_rejected() {
+        let (a, b) = tokio::io::duplex(1024);
+        let mut writer = FramedConnection::new(a);
+        let mut reader = FramedConnection::new(b).with_max_frame_size(8);
+
+        writer
+            .write_frame(b"this payload is too long")
+            .await
+            .unwrap();
+        assert!(reader.read_frame().await.is_err());
+
+        let mut small_writer = writer.with_max_frame_size(4);
+        assert!(small_writer.write_frame(b"too long").await.is_err());
+    }
+}
@@ -3,6 +3,14 @@
 //! Provides pluggable transport backends including:
 //! - **TCP/HTTP**: Traditional TCP with HTTP/1.1 or HTTP/2
 //! - **QUIC/HTTP/3**: Modern UDP-based transport with 0-RTT
+//! - **Framing**: Length-prefixed protocol messages over raw TCP/QUIC streams
+//! - **WebRTC**: Protocol messages over peer-to-peer data channels (`webrtc` feature)
+//! - **NATS**: Protocol messages over NATS subjects, HELLO addressed by agent ID
+//!   (`nats` feature)
+//! - **MQTT**: Protocol messages over MQTT 5 topics with retained capability
+//!   announcements, for IoT agent fleets (`mqtt` feature)
+//! - **Fault injection**: [`FaultInjector`] drops, delays, corrupts, truncates or
+//!   duplicates frames for resilience tests
 //!
 //! # Architecture
 //!
@@ -30,12 +38,28 @@
 //! ```
 
 mod config;
+mod fault;
+pub mod framing;
+#[cfg(feature = "mqtt")]
+pub mod mqtt;
+#[cfg(feature = "nats")]
+pub mod nats;
 mod quic;
 mod tcp;
+#[cfg(feature = "webrtc")]
+pub mod webrtc;
 
 pub use config::{CertConfig, QuicTransportConfig, TlsConfig};
-pub use quic::QuicTransport;
+pub use fault::{FaultConfig, FaultInjector, FaultStats};
+pub use framing::FramedConnection;
+#[cfg(feature = "mqtt")]
+pub use mqtt::{MqttChannel, MqttClient, MqttListener, Qos, QosMapping, TopicIo};
+#[cfg(feature = "nats")]
+pub use nats::{NatsChannel, NatsListener, SubjectIo};
+pub use quic::{QuicConnection, QuicTransport};
 pub use tcp::TcpTransport;
+#[cfg(feature = "webrtc")]
+pub use webrtc::{DataChannelIo, WebRtcChannel};
 
 use crate::error::Result;
 use axum::Router;
@@ -0,0 +1,945 @@
+//! Protocol messages over MQTT 5 topics.
+//!
+//! Lets embedded agents join an M2M fleet through a standard MQTT broker:
+//! each agent announces its capabilities on a retained topic, accepts
+//! sessions on a topic named after its agent ID, and every session then
+//! exchanges messages over its own pair of topics.
+//!
+//! # Topic Mapping
+//!
+//! | Topic | M2M |
+//! |-------|-----|
+//! | `<prefix>/agents/<agent_id>/announce` | retained HELLO announcing capabilities ([`MqttListener::announce`]) |
+//! | `<prefix>/agents/<agent_id>/hello` | HELLO, with the initiator's session topic as MQTT 5 response topic ([`MqttChannel::connect`]) |
+//! | `<prefix>/sessions/<hello_id>/initiator` | ACCEPT/REJECT ([`MqttListener::accept`]), then messages to the initiator |
+//! | `<prefix>/sessions/<session_id>/acceptor` | messages to the acceptor, `<session_id>` taken from ACCEPT |
+//! | message payload | one JSON [`Message`] |
+//!
+//! `<hello_id>` is the ID the initiator's [`Session`] had before the
+//! handshake. Both sides subscribe to their session topic before they
+//! publish, so nothing sent after ACCEPT can be lost to a late subscription.
+//! An empty retained payload on the announce topic withdraws the
+//! announcement; set [`withdrawal_will`] as the device's last will so the
+//! broker does it when the device drops off.
+//!
+//! # QoS
+//!
+//! [`QosMapping`] picks the QoS of each message type:
+//!
+//! | Messages | Default QoS |
+//! |----------|-------------|
+//! | HELLO, ACCEPT, REJECT, CLOSE, WINDOW_UPDATE, announcements | 1 (at least once) |
+//! | DATA, BROADCAST, DICT_PUSH, CONTEXT_PIN | 1 (at least once) |
+//! | PING, PONG | 0 (at most once) |
+//!
+//! QoS 1 works on every broker but may redeliver a message after a
+//! reconnect; raise `data` to [`Qos::ExactlyOnce`] where the broker
+//! supports QoS 2 and duplicate DATA matters. A lost PING is simply sent
+//! again by the next keepalive.
+//!
+//! # Usage
+//!
+//! ```rust,ignore
+//! use m2m::protocol::{Capabilities, Session};
+//! use m2m::transport::{MqttChannel, MqttClient, MqttListener};
+//! use rumqttc::v5::MqttOptions;
+//!
+//! let mut options = MqttOptions::new("sensor-7", "broker.local", 1883);
+//! options.set_last_will(m2m::transport::mqtt::withdrawal_will("sensor-7")?);
+//! let client = MqttClient::spawn(options);
+//!
+//! // Device "sensor-7" announces itself and accepts sessions
+//! let caps = Capabilities::default();
+//! let mut listener = MqttListener::bind(client.clone(), "sensor-7").await?;
+//! listener.announce(&caps).await?;
+//! let mut session = Session::new(caps);
+//! if let Some(mut channel) = listener.accept(&mut session).await? {
+//!     while let Some(request) = channel.recv_content(&mut session).await? {
+//!         channel.send_content(&mut session, &handle(request)).await?;
+//!     }
+//! }
+//! ```
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use bytes::Bytes;
+use futures::channel::mpsc;
+use futures::{Stream, StreamExt};
+use rumqttc::v5::mqttbytes::v5::{LastWill, Packet, Publish, PublishProperties};
+use rumqttc::v5::mqttbytes::QoS;
+use rumqttc::v5::{AsyncClient, ConnectionError, Event, MqttOptions};
+
+use crate::error::{M2MError, Result};
+use crate::protocol::{Capabilities, MaxFrameSize, Message, MessageType, Session};
+
+/// Default prefix of every M2M topic.
+pub const DEFAULT_TOPIC_PREFIX: &str = "m2m";
+
+/// Default maximum size of a single MQTT message (256 KiB).
+///
+/// Well under the MQTT limit, but common broker defaults (and managed IoT
+/// brokers) reject larger messages.
+pub const DEFAULT_MAX_MESSAGE_SIZE: usize = 256 * 1024;
+
+/// Bytes reserved per message for the JSON envelope.
+pub const MESSAGE_OVERHEAD: usize = 1024;
+
+/// Default time to wait for ACCEPT, REJECT or a retained announcement.
+pub const DEFAULT_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Delay before polling again after a broker connection error
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+/// MQTT quality of service level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Qos {
+    /// QoS 0: fire and forget
+    AtMostOnce,
+    /// QoS 1: acknowledged, may be redelivered
+    AtLeastOnce,
+    /// QoS 2: four-way handshake, delivered once
+    ExactlyOnce,
+}
+
+impl From<Qos> for QoS {
+    fn from(qos: Qos) -> Self {
+        match qos {
+            Qos::AtMostOnce => QoS::AtMostOnce,
+            Qos::AtLeastOnce => QoS::AtLeastOnce,
+            Qos::ExactlyOnce => QoS::ExactlyOnce,
+        }
+    }
+}
+
+/// QoS used for each kind of protocol message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QosMapping {
+    /// Handshake, CLOSE, WINDOW_UPDATE and announcements
+    pub control: Qos,
+    /// DATA, BROADCAST, DICT_PUSH and CONTEXT_PIN
+    pub data: Qos,
+    /// PING and PONG
+    pub keepalive: Qos,
+}
+
+impl Default for QosMapping {
+    fn default() -> Self {
+        Self {
+            control: Qos::AtLeastOnce,
+            data: Qos::AtLeastOnce,
+            keepalive: Qos::AtMostOnce,
+        }
+    }
+}
+
+impl QosMapping {
+    /// QoS to publish a message of type `msg_type` with.
+    pub fn for_message(&self, msg_type: MessageType) -> Qos {
+        match msg_type {
+            MessageType::Data
+            | MessageType::Broadcast
+            | MessageType::DictPush
+            | MessageType::ContextPin => self.data,
+            MessageType::Ping | MessageType::Pong => self.keepalive,
+            MessageType::Hello
+            | MessageType::Accept
+            | MessageType::Reject
+            | MessageType::Close
+            | MessageType::WindowUpdate
+            | MessageType::Upgrade
+            | MessageType::Rekey => self.control,
+        }
+    }
+
+    /// QoS to subscribe with, so no message is downgraded on delivery.
+    pub fn subscription(&self) -> Qos {
+        self.control.max(self.data).max(self.keepalive)
+    }
+}
+
+/// Future returned by [`TopicIo`] methods.
+pub type TopicFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T>> + Send + 'a>>;
+
+/// Messages delivered to one subscription; dropping it stops delivery.
+pub type Inbox = Pin<Box<dyn Stream<Item = TopicMessage> + Send>>;
+
+/// Message to publish.
+#[derive(Debug, Clone)]
+pub struct Publication {
+    /// Topic to publish to
+    pub topic: String,
+    /// Message payload
+    pub payload: Bytes,
+    /// Delivery guarantee
+    pub qos: Qos,
+    /// Whether the broker keeps the message for future subscribers
+    pub retain: bool,
+    /// MQTT 5 response topic
+    pub response_topic: Option<String>,
+}
+
+/// Message received on a subscription.
+#[derive(Debug, Clone)]
+pub struct TopicMessage {
+    /// Message payload
+    pub payload: Bytes,
+    /// MQTT 5 response topic set by the sender
+    pub response_topic: Option<String>,
+    /// Whether the broker delivered a retained message
+    pub retain: bool,
+}
+
+/// Topic-based publish/subscribe I/O.
+///
+/// Implemented by [`MqttClient`]; implement it to carry sessions over
+/// another MQTT 5 client.
+pub trait TopicIo: Send + Sync {
+    /// Publish a message.
+    fn publish(&self, publication: Publication) -> TopicFuture<'_, ()>;
+
+    /// Subscribe to a topic (no wildcards).
+    fn subscribe(&self, topic: String, qos: Qos) -> TopicFuture<'_, Inbox>;
+}
+
+/// Subscriptions by topic
+type Routes = Arc<Mutex<HashMap<String, Vec<mpsc::UnboundedSender<TopicMessage>>>>>;
+
+/// `rumqttc` MQTT 5 client with its event loop running in the background.
+///
+/// Incoming publishes are routed to the [`Inbox`] subscribed to their
+/// topic. Use a persistent session (`clean_start = false`) so subscriptions
+/// survive reconnects.
+#[derive(Clone)]
+pub struct MqttClient {
+    /// Request handle to the event loop
+    client: AsyncClient,
+    /// Subscriptions by topic
+    routes: Routes,
+}
+
+impl MqttClient {
+    /// Connect with `options`, driving the event loop on the current Tokio
+    /// runtime until every clone of the client is dropped.
+    pub fn spawn(options: MqttOptions) -> Self {
+        let (client, mut eventloop) = AsyncClient::new(options, 64);
+        let routes = Routes::default();
+
+        let dispatch = Arc::clone(&routes);
+        tokio::spawn(async move {
+            loop {
+                match eventloop.poll().await {
+                    Ok(Event::Incoming(Packet::Publish(publish))) => route(&dispatch, publish),
+                    Ok(_) => {},
+                    Err(ConnectionError::RequestsDone) => break,
+                    Err(e) => {
+                        tracing::warn!("MQTT connection error: {}", e);
+                        tokio::time::sleep(RECONNECT_DELAY).await;
+                    },
+                }
+            }
+        });
+
+        Self { client, routes }
+    }
+
+    /// Get the underlying `rumqttc` client.
+    pub fn client(&self) -> &AsyncClient {
+        &self.client
+    }
+}
+
+/// Deliver an incoming publish to the subscriptions of its topic
+fn route(routes: &Routes, publish: Publish) {
+    let Ok(topic) = std::str::from_utf8(&publish.topic) else {
+        return;
+    };
+    let mut routes = routes.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(inboxes) = routes.get_mut(topic) {
+        let message = TopicMessage {
+            payload: publish.payload,
+            response_topic: publish.properties.and_then(|p| p.response_topic),
+            retain: publish.retain,
+        };
+        inboxes.retain(|tx| tx.unbounded_send(message.clone()).is_ok());
+    }
+}
+
+impl TopicIo for MqttClient {
+    fn publish(&self, publication: Publication) -> TopicFuture<'_, ()> {
+        Box::pin(async move {
+            let properties = PublishProperties {
+                response_topic: publication.response_topic,
+                content_type: Some("application/json".to_string()),
+                ..Default::default()
+            };
+            self.client
+                .publish_with_properties(
+                    publication.topic,
+                    publication.qos.into(),
+                    publication.retain,
+                    publication.payload,
+                    properties,
+                )
+                .await
+                .map_err(|e| M2MError::Network(format!("Failed to publish to MQTT: {e}")))
+        })
+    }
+
+    fn subscribe(&self, topic: String, qos: Qos) -> TopicFuture<'_, Inbox> {
+        Box::pin(async move {
+            // Register first so retained messages are not missed
+            let (tx, rx) = mpsc::unbounded();
+            self.routes
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .entry(topic.clone())
+                .or_default()
+                .push(tx);
+            self.client
+                .subscribe(topic, qos.into())
+                .await
+                .map_err(|e| M2MError::Network(format!("Failed to subscribe to MQTT: {e}")))?;
+            Ok(Box::pin(rx) as Inbox)
+        })
+    }
+}
+
+/// Topic an agent announces its capabilities on.
+///
+/// Fails if `agent_id` is empty or contains characters MQTT reserves in
+/// topics (`/`, `+`, `#` or NUL).
+pub fn announce_topic(prefix: &str, agent_id: &str) -> Result<String> {
+    Ok(format!(
+        "{prefix}/agents/{}/announce",
+        topic_level(agent_id)?
+    ))
+}
+
+/// Topic an agent receives HELLO on.
+pub fn hello_topic(prefix: &str, agent_id: &str) -> Result<String> {
+    Ok(format!("{prefix}/agents/{}/hello", topic_level(agent_id)?))
+}
+
+/// Topic the initiator or acceptor of a session receives messages on.
+pub fn session_topic(prefix: &str, session_id: &str, initiator: bool) -> Result<String> {
+    let role = if initiator { "initiator" } else { "acceptor" };
+    Ok(format!(
+        "{prefix}/sessions/{}/{role}",
+        topic_level(session_id)?
+    ))
+}
+
+/// Last will clearing the retained announcement of `agent_id` under the
+/// default prefix.
+pub fn withdrawal_will(agent_id: &str) -> Result<LastWill> {
+    Ok(LastWill::new(
+        announce_topic(DEFAULT_TOPIC_PREFIX, agent_id)?,
+        Vec::new(),
+        QosMapping::default().control.into(),
+        true,
+        None,
+    ))
+}
+
+/// Check that `level` fits in one topic level
+fn topic_level(level: &str) -> Result<&str> {
+    if level.is_empty() || level.contains(['/', '+', '#', '\0']) {
+        return Err(M2MError::Config(format!(
+            "Invalid MQTT topic level: {level:?}"
+        )));
+    }
+    Ok(level)
+}
+
+/// Advertise a frame size that fits in one message of `max_message_size`
+fn capabilities_for(max_message_size: usize, caps: Capabilities) -> Capabilities {
+    let budget = max_message_size.saturating_sub(MESSAGE_OVERHEAD);
+    match caps.extension::<MaxFrameSize>() {
+        Some(MaxFrameSize(size)) if size <= budget => caps,
+        _ => caps.with_typed_extension(MaxFrameSize(budget)),
+    }
+}
+
+/// Exchanges protocol messages for one session over MQTT topics.
+pub struct MqttChannel<C> {
+    /// Underlying client
+    io: C,
+    /// Topic prefix
+    prefix: String,
+    /// QoS per message type
+    qos: QosMapping,
+    /// Subscription to this side's session topic
+    inbox: Option<Inbox>,
+    /// Peer's session topic
+    outbound: Option<String>,
+    /// Maximum size of a single message sent or received
+    max_message_size: usize,
+    /// Time to wait for ACCEPT, REJECT or an announcement
+    handshake_timeout: Duration,
+    /// Messages sent
+    messages_sent: u64,
+    /// Messages received
+    messages_received: u64,
+}
+
+impl<C: TopicIo> MqttChannel<C> {
+    /// Wrap a client with the default prefix, QoS mapping and message size.
+    ///
+    /// The channel carries nothing until [`connect`](Self::connect) runs.
+    pub fn new(io: C) -> Self {
+        Self {
+            io,
+            prefix: DEFAULT_TOPIC_PREFIX.to_string(),
+            qos: QosMapping::default(),
+            inbox: None,
+            outbound: None,
+            max_message_size: DEFAULT_MAX_MESSAGE_SIZE,
+            handshake_timeout: DEFAULT_HANDSHAKE_TIMEOUT,
+            messages_sent: 0,
+            messages_received: 0,
+        }
+    }
+
+    /// Set the topic prefix.
+    pub fn with_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefix = prefix.into();
+        self
+    }
+
+    /// Set the QoS of each message type.
+    pub fn with_qos(mut self, qos: QosMapping) -> Self {
+        self.qos = qos;
+        self
+    }
+
+    /// Set maximum size of a single message.
+    ///
+    /// Use the broker's maximum packet size if it is smaller.
+    pub fn with_max_message_size(mut self, max: usize) -> Self {
+        self.max_message_size = max;
+        self
+    }
+
+    /// Set how long to wait for ACCEPT, REJECT or an announcement.
+    pub fn with_handshake_timeout(mut self, timeout: Duration) -> Self {
+        self.handshake_timeout = timeout;
+        self
+    }
+
+    /// Get maximum size of a single message.
+    pub fn max_message_size(&self) -> usize {
+        self.max_message_size
+    }
+
+    /// Topic the peer receives this channel's messages on.
+    pub fn peer_topic(&self) -> Option<&str> {
+        self.outbound.as_deref()
+    }
+
+    /// Number of messages sent on this channel.
+    pub fn messages_sent(&self) -> u64 {
+        self.messages_sent
+    }
+
+    /// Number of messages received on this channel.
+    pub fn messages_received(&self) -> u64 {
+        self.messages_received
+    }
+
+    /// Advertise a frame size that fits in one MQTT message.
+    ///
+    /// Keeps a smaller [`MaxFrameSize`] already in `caps`.
+    pub fn capabilities(&self, caps: Capabilities) -> Capabilities {
+        capabilities_for(self.max_message_size, caps)
+    }
+
+    /// Send a protocol message to the peer.
+    pub async fn send(&mut self, message: &Message) -> Result<()> {
+        let topic = self
+            .outbound
+            .clone()
+            .ok_or_else(|| M2MError::Protocol("MQTT channel is not connected".to_string()))?;
+        self.publish(topic, None, message).await
+    }
+
+    /// Receive the next protocol message.
+    ///
+    /// Returns `None` once the channel is closed.
+    pub async fn recv(&mut self) -> Result<Option<Message>> {
+        let Some(inbox) = self.inbox.as_mut() else {
+            return Ok(None);
+        };
+        let Some(message) = inbox.next().await else {
+            return Ok(None);
+        };
+        self.messages_received += 1;
+        self.parse(&message.payload).map(Some)
+    }
+
+    /// Parse a received payload
+    fn parse(&self, payload: &[u8]) -> Result<Message> {
+        if payload.len() > self.max_message_size {
+            return Err(M2MError::Protocol(format!(
+                "Message of {} bytes exceeds maximum of {} bytes",
+                payload.len(),
+                self.max_message_size
+            )));
+        }
+        serde_json::from_slice(payload)
+            .map_err(|e| M2MError::InvalidMessage(format!("Invalid MQTT message: {e}")))
+    }
+
+    /// Publish a message to `topic` with the QoS of its type
+    async fn publish(
+        &mut self,
+        topic: String,
+        response_topic: Option<String>,
+        message: &Message,
+    ) -> Result<()> {
+        let payload = message.to_json_compact()?.into_bytes();
+        if payload.len() > self.max_message_size {
+            return Err(M2MError::Protocol(format!(
+                "Message of {} bytes exceeds maximum of {} bytes",
+                payload.len(),
+                self.max_message_size
+            )));
+        }
+
+        self.io
+            .publish(Publication {
+                topic,
+                payload: Bytes::from(payload),
+                qos: self.qos.for_message(message.msg_type),
+                retain: false,
+                response_topic,
+            })
+            .await?;
+        self.messages_sent += 1;
+        Ok(())
+    }
+
+    /// Capabilities `agent_id` announced, if any.
+    ///
+    /// Waits up to the handshake timeout for the retained announcement.
+    pub async fn discover(&self, agent_id: &str) -> Result<Option<Capabilities>> {
+        let topic = announce_topic(&self.prefix, agent_id)?;
+        let mut inbox = self.io.subscribe(topic, self.qos.control).await?;
+        let Ok(Some(message)) = tokio::time::timeout(self.handshake_timeout, inbox.next()).await
+        else {
+            return Ok(None);
+        };
+        if message.payload.is_empty() {
+            return Ok(None);
+        }
+        let hello = self.parse(&message.payload)?;
+        Ok(hello.get_capabilities().cloned())
+    }
+
+    /// Open a session with the agent listening as `agent_id`.
+    ///
+    /// Subscribes to the session topic, publishes HELLO with it as the
+    /// response topic and waits for the answer. Fails with
+    /// [`M2MError::NegotiationFailed`] if the peer rejects, or with
+    /// [`M2MError::Network`] if no answer arrives within the handshake
+    /// timeout.
+    pub async fn connect(&mut self, agent_id: &str, session: &mut Session) -> Result<()> {
+        let hello_topic = hello_topic(&self.prefix, agent_id)?;
+        let inbox_topic = session_topic(&self.prefix, session.id(), true)?;
+        self.inbox = Some(
+            self.io
+                .subscribe(inbox_topic.clone(), self.qos.subscription())
+                .await?,
+        );
+
+        let hello = session.create_hello();
+        self.publish(hello_topic, Some(inbox_topic), &hello).await?;
+
+        let response = tokio::time::timeout(self.handshake_timeout, self.recv())
+            .await
+            .map_err(|_| M2MError::Network(format!("No answer to HELLO from agent {agent_id}")))??
+            .ok_or_else(|| {
+                M2MError::Network("MQTT subscription closed during handshake".to_string())
+            })?;
+        match response.msg_type {
+            MessageType::Accept => {
+                session.process_accept(&response)?;
+                self.outbound = Some(session_topic(&self.prefix, session.id(), false)?);
+                Ok(())
+            },
+            MessageType::Reject => {
+                self.inbox = None;
+                session.process_reject(&response)
+            },
+            other => Err(M2MError::Protocol(format!(
+                "Expected ACCEPT or REJECT, got {other:?}"
+            ))),
+        }
+    }
+
+    /// Compress content and send it as one or more DATA messages.
+    pub async fn send_content(&mut self, session: &mut Session, content: &str) -> Result<()> {
+        for message in session.compress_fragmented(content)? {
+            self.send(&message).await?;
+        }
+        Ok(())
+    }
+
+    /// Receive the next complete DATA payload.
+    ///
+    /// Reassembles fragments, answers PINGs and returns receive-window
+    /// credit along the way. Returns `None` once the peer closes the session
+    /// or the subscription ends.
+    pub async fn recv_content(&mut self, session: &mut Session) -> Result<Option<String>> {
+        while let Some(message) = self.recv().await? {
+            match message.msg_type {
+                MessageType::Data => match session.decompress(&message) {
+                    Ok(content) => {
+                        if let Some(update) = session.window_update() {
+                            self.send(&update).await?;
+                        }
+                        return Ok(Some(content));
+                    },
+                    Err(M2MError::FragmentPending { .. }) => {},
+                    Err(e) => return Err(e),
+                },
+                MessageType::Close => {
+                    session.process_message(&message)?;
+                    self.inbox = None;
+                    return Ok(None);
+                },
+                _ => {
+                    if let Some(reply) = session.process_message(&message)? {
+                        self.send(&reply).await?;
+                    }
+                },
+            }
+        }
+        Ok(None)
+    }
+
+    /// Send CLOSE and stop receiving.
+    pub async fn close(&mut self, session: &mut Session) -> Result<()> {
+        self.send(&session.close()).await?;
+        self.inbox = None;
+        Ok(())
+    }
+
+    /// Get a reference to the underlying client.
+    pub fn get_ref(&self) -> &C {
+        &self.io
+    }
+
+    /// Consume the adapter and return the underlying client.
+    pub fn into_inner(self) -> C {
+        self.io
+    }
+}
+
+/// Announces an agent and accepts sessions addressed to it.
+pub struct MqttListener<C> {
+    /// Underlying client, cloned into every accepted channel
+    io: C,
+    /// Topic prefix
+    prefix: String,
+    /// Agent ID sessions are addressed to
+    agent_id: String,
+    /// QoS per message type
+    qos: QosMapping,
+    /// Subscription to the HELLO topic
+    hellos: Inbox,
+    /// Maximum size of a single message sent or received
+    max_message_size: usize,
+}
+
+impl<C: TopicIo + Clone> MqttListener<C> {
+    /// Listen for HELLO addressed to `agent_id` under the default prefix.
+    pub async fn bind(io: C, agent_id: &str) -> Result<Self> {
+        Self::bind_with_prefix(io, DEFAULT_TOPIC_PREFIX, agent_id).await
+    }
+
+    /// Listen for HELLO addressed to `agent_id` under `prefix`.
+    pub async fn bind_with_prefix(io: C, prefix: &str, agent_id: &str) -> Result<Self> {
+        let qos = QosMapping::default();
+        let hellos = io
+            .subscribe(hello_topic(prefix, agent_id)?, qos.control)
+            .await?;
+        Ok(Self {
+            io,
+            prefix: prefix.to_string(),
+            agent_id: agent_id.to_string(),
+            qos,
+            hellos,
+            max_message_size: DEFAULT_MAX_MESSAGE_SIZE,
+        })
+    }
+
+    /// Set the QoS of each message type for accepted channels.
+    pub fn with_qos(mut self, qos: QosMapping) -> Self {
+        self.qos = qos;
+        self
+    }
+
+    /// Set maximum size of a single message for accepted channels.
+    pub fn with_max_message_size(mut self, max: usize) -> Self {
+        self.max_message_size = max;
+        self
+    }
+
+    /// Advertise a frame size that fits in one MQTT message.
+    pub fn capabilities(&self, caps: Capabilities) -> Capabilities {
+        capabilities_for(self.max_message_size, caps)
+    }
+
+    /// Publish a retained HELLO announcing `caps`.
+    ///
+    /// Agents subscribing later (see [`MqttChannel::discover`]) receive it
+    /// from the broker until it is withdrawn.
+    pub async fn announce(&self, caps: &Capabilities) -> Result<()> {
+        let hello = Message::hello(self.capabilities(caps.clone()));
+        self.publish_announcement(Bytes::from(hello.to_json_compact()?.into_bytes()))
+            .await
+    }
+
+    /// Clear the retained announcement.
+    pub async fn withdraw(&self) -> Result<()> {
+        self.publish_announcement(Bytes::new()).await
+    }
+
+    /// Publish a retained payload on the announce topic
+    async fn publish_announcement(&self, payload: Bytes) -> Result<()> {
+        self.io
+            .publish(Publication {
+                topic: announce_topic(&self.prefix, &self.agent_id)?,
+                payload,
+                qos: self.qos.control,
+                retain: true,
+                response_topic: None,
+            })
+            .await
+    }
+
+    /// Wait for the next HELLO and answer it.
+    ///
+    /// Returns the channel for the accepted session, or `None` once the
+    /// subscription ends. Errors concern a single HELLO (malformed, without
+    /// a response topic, or rejected after sending REJECT); keep accepting
+    /// after them.
+    pub async fn accept(&mut self, session: &mut Session) -> Result<Option<MqttChannel<C>>> {
+        let Some(request) = self.hellos.next().await else {
+            return Ok(None);
+        };
+        let mut channel = MqttChannel::new(self.io.clone())
+            .with_prefix(self.prefix.clone())
+            .with_qos(self.qos)
+            .with_max_message_size(self.max_message_size);
+        channel.messages_received += 1;
+
+        let reply = request
+            .response_topic
+            .ok_or_else(|| M2MError::Protocol("HELLO without a response topic".to_string()))?;
+        let hello = channel.parse(&request.payload)?;
+        if hello.msg_type != MessageType::Hello {
+            return Err(M2MError::Protocol(format!(
+                "Expected HELLO, got {:?}",
+                hello.msg_type
+            )));
+        }
+
+        let response = session.process_hello(&hello)?;
+        if let Some(rejection) = response.get_rejection() {
+            channel.publish(reply, None, &response).await?;
+            return Err(M2MError::NegotiationFailed(format!(
+                "{:?}: {}",
+                rejection.code, rejection.message
+            )));
+        }
+
+        // Subscribe before ACCEPT so the initiator's first DATA is not lost
+        let inbox_topic = session_topic(&self.prefix, session.id(), false)?;
+        channel.inbox = Some(
+            self.io
+                .subscribe(inbox_topic, self.qos.subscription())
+                .await?,
+        );
+        channel.outbound = Some(reply);
+        channel.send(&response).await?;
+        Ok(Some(channel))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::SessionState;
+
+    /// In-memory broker with retained messages, delivering on exact topics
+    #[derive(Clone, Default)]
+    struct MemoryBroker {
+        routes: Routes,
+        retained: Arc<Mutex<HashMap<String, TopicMessage>>>,
+        published: Arc<Mutex<Vec<(MessageType, Qos)>>>,
+    }
+
+    impl TopicIo for MemoryBroker {
+        fn publish(&self, publication: Publication) -> TopicFuture<'_, ()> {
+            if let Ok(message) = serde_json::from_slice::<Message>(&publication.payload) {
+                self.published
+                    .lock()
+                    .unwrap()
+                    .push((message.msg_type, publication.qos));
+            }
+            let message = TopicMessage {
+                payload: publication.payload,
+                response_topic: publication.response_topic,
+                retain: false,
+            };
+            if publication.retain {
+                let retained = TopicMessage {
+                    retain: true,
+                    ..message.clone()
+                };
+                self.retained
+                    .lock()
+                    .unwrap()
+                    .insert(publication.topic.clone(), retained);
+            }
+            if let Some(inboxes) = self.routes.lock().unwrap().get_mut(&publication.topic) {
+                inboxes.retain(|tx| tx.unbounded_send(message.clone()).is_ok());
+            }
+            Box::pin(async { Ok(()) })
+        }
+
+        fn subscribe(&self, topic: String, _qos: Qos) -> TopicFuture<'_, Inbox> {
+            let (tx, rx) = mpsc::unbounded();
+            if let Some(retained) = self.retained.lock().unwrap().get(&topic) {
+                tx.unbounded_send(retained.clone()).unwrap();
+            }
+            self.routes
+                .lock()
+                .unwrap()
+                .entry(topic)
+                .or_default()
+                .push(tx);
+            Box::pin(async move { Ok(Box::pin(rx) as Inbox) })
+        }
+    }
+
+    #[test]
+    fn test_topics_and_qos() {
+        assert_eq!(
+            hello_topic("m2m", "sensor-7").unwrap(),
+            "m2m/agents/sensor-7/hello"
+        );
+        assert_eq!(
+            session_topic("site/1", "abc", true).unwrap(),
+            "site/1/sessions/abc/initiator"
+        );
+        for invalid in ["", "a/b", "+", "#", "a\0"] {
+            assert!(announce_topic("m2m", invalid).is_err(), "{invalid:?}");
+        }
+
+        let qos = QosMapping::default();
+        assert_eq!(qos.for_message(MessageType::Ping), Qos::AtMostOnce);
+        assert_eq!(qos.for_message(MessageType::Data), Qos::AtLeastOnce);
+        assert_eq!(qos.subscription(), Qos::AtLeastOnce);
+        let qos = QosMapping {
+            data: Qos::ExactlyOnce,
+            ..qos
+        };
+        assert_eq!(qos.subscription(), Qos::ExactlyOnce);
+    }
+
+    #[tokio::test]
+    async fn test_announce_handshake_and_fragmented_data() {
+        let broker = MemoryBroker::default();
+        let mut listener = MqttListener::bind(broker.clone(), "sensor-7")
+            .await
+            .unwrap()
+            .with_max_message_size(2048);
+        let mut channel = MqttChannel::new(broker.clone())
+            .with_max_message_size(2048)
+            .with_handshake_timeout(Duration::from_millis(50));
+
+        // Retained announcements reach agents that subscribe later
+        listener.announce(&Capabilities::default()).await.unwrap();
+        let announced = channel.discover("sensor-7").await.unwrap().unwrap();
+        assert_eq!(
+            announced.extension::<MaxFrameSize>(),
+            Some(MaxFrameSize(2048 - MESSAGE_OVERHEAD))
+        );
+        listener.withdraw().await.unwrap();
+        assert!(channel.discover("sensor-7").await.unwrap().is_none());
+
+        let mut client = Session::new(channel.capabilities(Capabilities::default()));
+        let mut server = Session::new(listener.capabilities(Capabilities::default()));
+        let (connected, accepted) = tokio::join!(
+            channel.connect("sensor-7", &mut client),
+            listener.accept(&mut server)
+        );
+        connected.unwrap();
+        let mut accepted = accepted.unwrap().unwrap();
+        assert!(client.is_established());
+        assert_eq!(
+            channel.peer_topic(),
+            Some(format!("m2m/sessions/{}/acceptor", server.id()).as_str())
+        );
+
+        let items: Vec<String> = (0..400)
+            .map(|i| format!(r#"{{"id":{i},"value":"item-{}"}}"#, i * 7919 % 1000))
+            .collect();
+        let content = format!(r#"{{"model":"gpt-4o","items":[{}]}}"#, items.join(","));
+        channel.send(&Message::ping(client.id())).await.unwrap();
+        channel.send_content(&mut client, &content).await.unwrap();
+        assert!(channel.messages_sent() > 3);
+        assert_eq!(
+            accepted.recv_content(&mut server).await.unwrap().as_deref(),
+            Some(content.as_str())
+        );
+        let pong = channel.recv().await.unwrap();
+        assert_eq!(pong.map(|m| m.msg_type), Some(MessageType::Pong));
+
+        channel.close(&mut client).await.unwrap();
+        assert!(accepted.recv_content(&mut server).await.unwrap().is_none());
+        assert_eq!(server.state(), SessionState::Closed);
+
+        // DATA at QoS 1, PING/PONG at QoS 0
+        let published = broker.published.lock().unwrap();
+        for (msg_type, qos) in published.iter() {
+            let expected = match msg_type {
+                MessageType::Ping | MessageType::Pong => Qos::AtMostOnce,
+                _ => Qos::AtLeastOnce,
+            };
+            assert_eq!(*qos, expected, "{msg_type:?}");
+        }
+        assert!(published.iter().any(|(t, _)| *t == MessageType::Pong));
+    }
+
+    #[tokio::test]
+    async fn test_hello_without_response_topic_rejected() {
+        let broker = MemoryBroker::default();
+        let mut listener = MqttListener::bind(broker.clone(), "sensor-7")
+            .await
+            .unwrap();
+        let hello = Session::new(Capabilities::default()).create_hello();
+        broker
+            .publish(Publication {
+                topic: hello_topic(DEFAULT_TOPIC_PREFIX, "sensor-7").unwrap(),
+                payload: Bytes::from(hello.to_json_compact().unwrap().into_bytes()),
+                qos: Qos::AtLeastOnce,
+                retain: false,
+                response_topic: None,
+            })
+            .await
+            .unwrap();
+
+        let mut session = Session::new(Capabilities::default());
+        assert!(matches!(
+            listener.accept(&mut session).await,
+            Err(M2MError::Protocol(_))
+        ));
+    }
+}
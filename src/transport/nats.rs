@@ -0,0 +1,611 @@
+//! Protocol messages over NATS subjects.
+//!
+//! Lets agent meshes that already run NATS adopt M2M without adding an HTTP
+//! server: an agent listens for HELLO on a subject named after its agent ID,
+//! and every session it accepts then exchanges messages over its own pair of
+//! subjects.
+//!
+//! # Subject Mapping
+//!
+//! | Subject | M2M |
+//! |---------|-----|
+//! | `<prefix>.agent.<agent_id>.hello` | HELLO, published with the initiator's session subject as reply ([`NatsChannel::connect`]) |
+//! | `<prefix>.session.<hello_id>.initiator` | ACCEPT/REJECT ([`NatsListener::accept`]), then messages to the initiator |
+//! | `<prefix>.session.<session_id>.acceptor` | messages to the acceptor, `<session_id>` taken from ACCEPT |
+//! | message payload | one JSON [`Message`] |
+//! | CLOSE | end of session, subscription dropped |
+//!
+//! `<hello_id>` is the ID the initiator's [`Session`] had before the
+//! handshake. Both sides subscribe to their session subject before they
+//! publish, so nothing sent after ACCEPT can be lost to a late subscription.
+//! The prefix defaults to [`DEFAULT_SUBJECT_PREFIX`].
+//!
+//! NATS caps the size of a single message (`max_payload`, 1 MiB unless the
+//! server is configured otherwise), so advertise a matching
+//! [`MaxFrameSize`] with [`NatsChannel::capabilities`] and large payloads are
+//! fragmented by the session. Core NATS delivers at most once: use
+//! JetStream-backed subjects if DATA must survive a disconnect.
+//!
+//! # Usage
+//!
+//! ```rust,ignore
+//! use m2m::protocol::{Capabilities, Session};
+//! use m2m::transport::{NatsChannel, NatsListener};
+//!
+//! let client = async_nats::connect("nats://127.0.0.1:4222").await?;
+//!
+//! // Agent "summarizer" accepts sessions
+//! let mut listener = NatsListener::bind(client.clone(), "summarizer").await?;
+//! let mut session = Session::new(listener.capabilities(Capabilities::default()));
+//! if let Some(mut channel) = listener.accept(&mut session).await? {
+//!     while let Some(request) = channel.recv_content(&mut session).await? {
+//!         channel.send_content(&mut session, &handle(request)).await?;
+//!     }
+//! }
+//!
+//! // Another agent opens a session to it
+//! let mut session = Session::new(Capabilities::default());
+//! let mut channel = NatsChannel::new(client);
+//! channel.connect("summarizer", &mut session).await?;
+//! channel.send_content(&mut session, request).await?;
+//! ```
+
+use std::future::Future;
+use std::pin::Pin;
+use std::time::Duration;
+
+use bytes::Bytes;
+use futures::{Stream, StreamExt};
+
+use crate::error::{M2MError, Result};
+use crate::protocol::{Capabilities, MaxFrameSize, Message, MessageType, Session};
+
+/// Default prefix of every M2M subject.
+pub const DEFAULT_SUBJECT_PREFIX: &str = "m2m";
+
+/// Default maximum size of a single NATS message (1 MiB).
+pub const DEFAULT_MAX_MESSAGE_SIZE: usize = 1024 * 1024;
+
+/// Bytes reserved per message for the JSON envelope.
+pub const MESSAGE_OVERHEAD: usize = 1024;
+
+/// Default time to wait for ACCEPT or REJECT.
+pub const DEFAULT_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Future returned by [`SubjectIo`] methods.
+pub type SubjectFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T>> + Send + 'a>>;
+
+/// Messages delivered to one subscription; dropping it unsubscribes.
+pub type Inbox = Pin<Box<dyn Stream<Item = InboxMessage> + Send>>;
+
+/// Message received on a subscription.
+#[derive(Debug, Clone)]
+pub struct InboxMessage {
+    /// Message payload
+    pub payload: Bytes,
+    /// Subject the sender asked replies to go to
+    pub reply: Option<String>,
+}
+
+/// Subject-based publish/subscribe I/O.
+///
+/// Implemented for `async-nats` clients; implement it to carry sessions over
+/// another broker with subject routing.
+pub trait SubjectIo: Send + Sync {
+    /// Publish a message, optionally naming a reply subject.
+    fn publish(
+        &self,
+        subject: String,
+        reply: Option<String>,
+        payload: Bytes,
+    ) -> SubjectFuture<'_, ()>;
+
+    /// Subscribe to a subject.
+    fn subscribe(&self, subject: String) -> SubjectFuture<'_, Inbox>;
+}
+
+impl SubjectIo for async_nats::Client {
+    fn publish(
+        &self,
+        subject: String,
+        reply: Option<String>,
+        payload: Bytes,
+    ) -> SubjectFuture<'_, ()> {
+        Box::pin(async move {
+            let published = match reply {
+                Some(reply) => self.publish_with_reply(subject, reply, payload).await,
+                None => async_nats::Client::publish(self, subject, payload).await,
+            };
+            published.map_err(|e| M2MError::Network(format!("Failed to publish to NATS: {e}")))
+        })
+    }
+
+    fn subscribe(&self, subject: String) -> SubjectFuture<'_, Inbox> {
+        Box::pin(async move {
+            let subscriber = async_nats::Client::subscribe(self, subject)
+                .await
+                .map_err(|e| M2MError::Network(format!("Failed to subscribe to NATS: {e}")))?;
+            let inbox = subscriber.map(|message| InboxMessage {
+                payload: message.payload,
+                reply: message.reply.map(|reply| reply.to_string()),
+            });
+            Ok(Box::pin(inbox) as Inbox)
+        })
+    }
+}
+
+/// Subject an agent receives HELLO on.
+///
+/// Fails if `agent_id` is empty or contains characters NATS reserves in
+/// subjects (`.`, `*`, `>` or whitespace).
+pub fn hello_subject(prefix: &str, agent_id: &str) -> Result<String> {
+    Ok(format!("{prefix}.agent.{}.hello", subject_token(agent_id)?))
+}
+
+/// Subject the initiator or acceptor of a session receives messages on.
+pub fn session_subject(prefix: &str, session_id: &str, initiator: bool) -> Result<String> {
+    let role = if initiator { "initiator" } else { "acceptor" };
+    Ok(format!(
+        "{prefix}.session.{}.{role}",
+        subject_token(session_id)?
+    ))
+}
+
+/// Check that `token` fits in one subject token
+fn subject_token(token: &str) -> Result<&str> {
+    let reserved = |c: char| matches!(c, '.' | '*' | '>') || c.is_whitespace();
+    if token.is_empty() || token.contains(reserved) {
+        return Err(M2MError::Config(format!(
+            "Invalid NATS subject token: {token:?}"
+        )));
+    }
+    Ok(token)
+}
+
+/// Advertise a frame size that fits in one message of `max_message_size`
+fn capabilities_for(max_message_size: usize, caps: Capabilities) -> Capabilities {
+    let budget = max_message_size.saturating_sub(MESSAGE_OVERHEAD);
+    match caps.extension::<MaxFrameSize>() {
+        Some(MaxFrameSize(size)) if size <= budget => caps,
+        _ => caps.with_typed_extension(MaxFrameSize(budget)),
+    }
+}
+
+/// Exchanges protocol messages for one session over NATS subjects.
+pub struct NatsChannel<C> {
+    /// Underlying client
+    io: C,
+    /// Subject prefix
+    prefix: String,
+    /// Subscription to this side's session subject
+    inbox: Option<Inbox>,
+    /// Peer's session subject
+    outbound: Option<String>,
+    /// Maximum size of a single message sent or received
+    max_message_size: usize,
+    /// Time to wait for ACCEPT or REJECT
+    handshake_timeout: Duration,
+    /// Messages sent
+    messages_sent: u64,
+    /// Messages received
+    messages_received: u64,
+}
+
+impl<C: SubjectIo> NatsChannel<C> {
+    /// Wrap a client with the default prefix and message size.
+    ///
+    /// The channel carries nothing until [`connect`](Self::connect) runs.
+    pub fn new(io: C) -> Self {
+        Self {
+            io,
+            prefix: DEFAULT_SUBJECT_PREFIX.to_string(),
+            inbox: None,
+            outbound: None,
+            max_message_size: DEFAULT_MAX_MESSAGE_SIZE,
+            handshake_timeout: DEFAULT_HANDSHAKE_TIMEOUT,
+            messages_sent: 0,
+            messages_received: 0,
+        }
+    }
+
+    /// Set the subject prefix.
+    pub fn with_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefix = prefix.into();
+        self
+    }
+
+    /// Set maximum size of a single message.
+    ///
+    /// Use the server's `max_payload` if it differs from the default.
+    pub fn with_max_message_size(mut self, max: usize) -> Self {
+        self.max_message_size = max;
+        self
+    }
+
+    /// Set how long [`connect`](Self::connect) waits for ACCEPT or REJECT.
+    pub fn with_handshake_timeout(mut self, timeout: Duration) -> Self {
+        self.handshake_timeout = timeout;
+        self
+    }
+
+    /// Get maximum size of a single message.
+    pub fn max_message_size(&self) -> usize {
+        self.max_message_size
+    }
+
+    /// Subject the peer receives this channel's messages on.
+    pub fn peer_subject(&self) -> Option<&str> {
+        self.outbound.as_deref()
+    }
+
+    /// Number of messages sent on this channel.
+    pub fn messages_sent(&self) -> u64 {
+        self.messages_sent
+    }
+
+    /// Number of messages received on this channel.
+    pub fn messages_received(&self) -> u64 {
+        self.messages_received
+    }
+
+    /// Advertise a frame size that fits in one NATS message.
+    ///
+    /// Keeps a smaller [`MaxFrameSize`] already in `caps`.
+    pub fn capabilities(&self, caps: Capabilities) -> Capabilities {
+        capabilities_for(self.max_message_size, caps)
+    }
+
+    /// Send a protocol message to the peer.
+    pub async fn send(&mut self, message: &Message) -> Result<()> {
+        let subject = self
+            .outbound
+            .clone()
+            .ok_or_else(|| M2MError::Protocol("NATS channel is not connected".to_string()))?;
+        self.publish(subject, None, message).await
+    }
+
+    /// Receive the next protocol message.
+    ///
+    /// Returns `None` once the channel is closed.
+    pub async fn recv(&mut self) -> Result<Option<Message>> {
+        let Some(inbox) = self.inbox.as_mut() else {
+            return Ok(None);
+        };
+        let Some(message) = inbox.next().await else {
+            return Ok(None);
+        };
+        self.messages_received += 1;
+
+        if message.payload.len() > self.max_message_size {
+            return Err(M2MError::Protocol(format!(
+                "Message of {} bytes exceeds maximum of {} bytes",
+                message.payload.len(),
+                self.max_message_size
+            )));
+        }
+        let message = serde_json::from_slice(&message.payload)
+            .map_err(|e| M2MError::InvalidMessage(format!("Invalid NATS message: {e}")))?;
+        Ok(Some(message))
+    }
+
+    /// Publish a message to `subject`
+    async fn publish(
+        &mut self,
+        subject: String,
+        reply: Option<String>,
+        message: &Message,
+    ) -> Result<()> {
+        let payload = message.to_json_compact()?.into_bytes();
+        if payload.len() > self.max_message_size {
+            return Err(M2MError::Protocol(format!(
+                "Message of {} bytes exceeds maximum of {} bytes",
+                payload.len(),
+                self.max_message_size
+            )));
+        }
+
+        self.io
+            .publish(subject, reply, Bytes::from(payload))
+            .await?;
+        self.messages_sent += 1;
+        Ok(())
+    }
+
+    /// Open a session with the agent listening as `agent_id`.
+    ///
+    /// Subscribes to the session subject, publishes HELLO with it as the
+    /// reply subject and waits for the answer. Fails with
+    /// [`M2MError::NegotiationFailed`] if the peer rejects, or with
+    /// [`M2MError::Network`] if no answer arrives within the handshake
+    /// timeout.
+    pub async fn connect(&mut self, agent_id: &str, session: &mut Session) -> Result<()> {
+        let hello_subject = hello_subject(&self.prefix, agent_id)?;
+        let inbox_subject = session_subject(&self.prefix, session.id(), true)?;
+        self.inbox = Some(self.io.subscribe(inbox_subject.clone()).await?);
+
+        let hello = session.create_hello();
+        self.publish(hello_subject, Some(inbox_subject), &hello)
+            .await?;
+
+        let response = tokio::time::timeout(self.handshake_timeout, self.recv())
+            .await
+            .map_err(|_| M2MError::Network(format!("No answer to HELLO from agent {agent_id}")))??
+            .ok_or_else(|| {
+                M2MError::Network("NATS subscription closed during handshake".to_string())
+            })?;
+        match response.msg_type {
+            MessageType::Accept => {
+                session.process_accept(&response)?;
+                self.outbound = Some(session_subject(&self.prefix, session.id(), false)?);
+                Ok(())
+            },
+            MessageType::Reject => {
+                self.inbox = None;
+                session.process_reject(&response)
+            },
+            other => Err(M2MError::Protocol(format!(
+                "Expected ACCEPT or REJECT, got {other:?}"
+            ))),
+        }
+    }
+
+    /// Compress content and send it as one or more DATA messages.
+    pub async fn send_content(&mut self, session: &mut Session, content: &str) -> Result<()> {
+        for message in session.compress_fragmented(content)? {
+            self.send(&message).await?;
+        }
+        Ok(())
+    }
+
+    /// Receive the next complete DATA payload.
+    ///
+    /// Reassembles fragments, answers PINGs and returns receive-window
+    /// credit along the way. Returns `None` once the peer closes the session
+    /// or the subscription ends.
+    pub async fn recv_content(&mut self, session: &mut Session) -> Result<Option<String>> {
+        while let Some(message) = self.recv().await? {
+            match message.msg_type {
+                MessageType::Data => match session.decompress(&message) {
+                    Ok(content) => {
+                        if let Some(update) = session.window_update() {
+                            self.send(&update).await?;
+                        }
+                        return Ok(Some(content));
+                    },
+                    Err(M2MError::FragmentPending { .. }) => {},
+                    Err(e) => return Err(e),
+                },
+                MessageType::Close => {
+                    session.process_message(&message)?;
+                    self.inbox = None;
+                    return Ok(None);
+                },
+                _ => {
+                    if let Some(reply) = session.process_message(&message)? {
+                        self.send(&reply).await?;
+                    }
+                },
+            }
+        }
+        Ok(None)
+    }
+
+    /// Send CLOSE and drop the subscription.
+    pub async fn close(&mut self, session: &mut Session) -> Result<()> {
+        self.send(&session.close()).await?;
+        self.inbox = None;
+        Ok(())
+    }
+
+    /// Get a reference to the underlying client.
+    pub fn get_ref(&self) -> &C {
+        &self.io
+    }
+
+    /// Consume the adapter and return the underlying client.
+    pub fn into_inner(self) -> C {
+        self.io
+    }
+}
+
+/// Accepts sessions addressed to one agent ID.
+pub struct NatsListener<C> {
+    /// Underlying client, cloned into every accepted channel
+    io: C,
+    /// Subject prefix
+    prefix: String,
+    /// Subscription to the HELLO subject
+    hellos: Inbox,
+    /// Maximum size of a single message sent or received
+    max_message_size: usize,
+}
+
+impl<C: SubjectIo + Clone> NatsListener<C> {
+    /// Listen for HELLO addressed to `agent_id` under the default prefix.
+    pub async fn bind(io: C, agent_id: &str) -> Result<Self> {
+        Self::bind_with_prefix(io, DEFAULT_SUBJECT_PREFIX, agent_id).await
+    }
+
+    /// Listen for HELLO addressed to `agent_id` under `prefix`.
+    pub async fn bind_with_prefix(io: C, prefix: &str, agent_id: &str) -> Result<Self> {
+        let hellos = io.subscribe(hello_subject(prefix, agent_id)?).await?;
+        Ok(Self {
+            io,
+            prefix: prefix.to_string(),
+            hellos,
+            max_message_size: DEFAULT_MAX_MESSAGE_SIZE,
+        })
+    }
+
+    /// Set maximum size of a single message for accepted channels.
+    pub fn with_max_message_size(mut self, max: usize) -> Self {
+        self.max_message_size = max;
+        self
+    }
+
+    /// Advertise a frame size that fits in one NATS message.
+    pub fn capabilities(&self, caps: Capabilities) -> Capabilities {
+        capabilities_for(self.max_message_size, caps)
+    }
+
+    /// Wait for the next HELLO and answer it.
+    ///
+    /// Returns the channel for the accepted session, or `None` once the
+    /// subscription ends. Errors concern a single HELLO (malformed, without
+    /// a reply subject, or rejected after sending REJECT); keep accepting
+    /// after them.
+    pub async fn accept(&mut self, session: &mut Session) -> Result<Option<NatsChannel<C>>> {
+        let Some(request) = self.hellos.next().await else {
+            return Ok(None);
+        };
+        let reply = request
+            .reply
+            .ok_or_else(|| M2MError::Protocol("HELLO without a reply subject".to_string()))?;
+        let hello: Message = serde_json::from_slice(&request.payload)
+            .map_err(|e| M2MError::InvalidMessage(format!("Invalid NATS message: {e}")))?;
+        if hello.msg_type != MessageType::Hello {
+            return Err(M2MError::Protocol(format!(
+                "Expected HELLO, got {:?}",
+                hello.msg_type
+            )));
+        }
+
+        let mut channel = NatsChannel::new(self.io.clone())
+            .with_prefix(self.prefix.clone())
+            .with_max_message_size(self.max_message_size);
+        channel.messages_received += 1;
+
+        let response = session.process_hello(&hello)?;
+        if let Some(rejection) = response.get_rejection() {
+            channel.publish(reply, None, &response).await?;
+            return Err(M2MError::NegotiationFailed(format!(
+                "{:?}: {}",
+                rejection.code, rejection.message
+            )));
+        }
+
+        // Subscribe before ACCEPT so the initiator's first DATA is not lost
+        let inbox_subject = session_subject(&self.prefix, session.id(), false)?;
+        channel.inbox = Some(self.io.subscribe(inbox_subject).await?);
+        channel.outbound = Some(reply);
+        channel.send(&response).await?;
+        Ok(Some(channel))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::SessionState;
+    use futures::channel::mpsc;
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+
+    /// In-memory broker delivering on exact subject matches
+    #[derive(Clone, Default)]
+    struct MemoryBroker {
+        subscribers: Arc<Mutex<HashMap<String, Vec<mpsc::UnboundedSender<InboxMessage>>>>>,
+    }
+
+    impl SubjectIo for MemoryBroker {
+        fn publish(
+            &self,
+            subject: String,
+            reply: Option<String>,
+            payload: Bytes,
+        ) -> SubjectFuture<'_, ()> {
+            let mut subscribers = self.subscribers.lock().unwrap();
+            if let Some(inboxes) = subscribers.get_mut(&subject) {
+                let message = InboxMessage { payload, reply };
+                inboxes.retain(|tx| tx.unbounded_send(message.clone()).is_ok());
+            }
+            Box::pin(async { Ok(()) })
+        }
+
+        fn subscribe(&self, subject: String) -> SubjectFuture<'_, Inbox> {
+            let (tx, rx) = mpsc::unbounded();
+            self.subscribers
+                .lock()
+                .unwrap()
+                .entry(subject)
+                .or_default()
+                .push(tx);
+            Box::pin(async move { Ok(Box::pin(rx) as Inbox) })
+        }
+    }
+
+    #[test]
+    fn test_subjects() {
+        assert_eq!(
+            hello_subject("m2m", "summarizer").unwrap(),
+            "m2m.agent.summarizer.hello"
+        );
+        assert_eq!(
+            session_subject("mesh.m2m", "abc", false).unwrap(),
+            "mesh.m2m.session.abc.acceptor"
+        );
+        for invalid in ["", "a.b", "a*", ">", "a b"] {
+            assert!(hello_subject("m2m", invalid).is_err(), "{invalid:?}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_handshake_and_fragmented_data() {
+        let broker = MemoryBroker::default();
+        let mut listener = NatsListener::bind(broker.clone(), "summarizer")
+            .await
+            .unwrap()
+            .with_max_message_size(2048);
+        let mut channel = NatsChannel::new(broker).with_max_message_size(2048);
+
+        let mut client = Session::new(channel.capabilities(Capabilities::default()));
+        let mut server = Session::new(listener.capabilities(Capabilities::default()));
+
+        let (connected, accepted) = tokio::join!(
+            channel.connect("summarizer", &mut client),
+            listener.accept(&mut server)
+        );
+        connected.unwrap();
+        let mut accepted = accepted.unwrap().unwrap();
+        assert!(client.is_established());
+        assert_eq!(client.id(), server.id());
+        assert_eq!(
+            channel.peer_subject(),
+            Some(format!("m2m.session.{}.acceptor", server.id()).as_str())
+        );
+        assert_eq!(
+            client.extension::<MaxFrameSize>(),
+            Some(MaxFrameSize(2048 - MESSAGE_OVERHEAD))
+        );
+
+        let items: Vec<String> = (0..400)
+            .map(|i| format!(r#"{{"id":{i},"value":"item-{}"}}"#, i * 7919 % 1000))
+            .collect();
+        let content = format!(r#"{{"model":"gpt-4o","items":[{}]}}"#, items.join(","));
+        // PINGs are answered while waiting for content
+        channel.send(&Message::ping(client.id())).await.unwrap();
+        channel.send_content(&mut client, &content).await.unwrap();
+        assert!(channel.messages_sent() > 3);
+        assert_eq!(
+            accepted.recv_content(&mut server).await.unwrap().as_deref(),
+            Some(content.as_str())
+        );
+        let pong = channel.recv().await.unwrap();
+        assert_eq!(pong.map(|m| m.msg_type), Some(MessageType::Pong));
+
+        channel.close(&mut client).await.unwrap();
+        assert!(accepted.recv_content(&mut server).await.unwrap().is_none());
+        assert_eq!(server.state(), SessionState::Closed);
+    }
+
+    #[tokio::test]
+    async fn test_unanswered_hello_times_out() {
+        let mut channel = NatsChannel::new(MemoryBroker::default())
+            .with_handshake_timeout(Duration::from_millis(20));
+        let mut session = Session::new(Capabilities::default());
+
+        let err = channel.connect("nobody", &mut session).await.unwrap_err();
+        assert!(matches!(err, M2MError::Network(_)), "{err}");
+        assert!(channel.send(&Message::ping("s")).await.is_err());
+    }
+}
@@ -23,10 +23,27 @@
 //!        ▼
 //! Axum Router (HTTP request handling)
 //! ```
+//!
+//! # 0-RTT Resumption
+//!
+//! `QuicTransport` keeps a session ticket cache shared by every client
+//! connection it opens, so a returning agent can resume with 0-RTT and send
+//! HELLO plus its first DATA in the first flight (see
+//! [`protocol::EarlyData`](crate::protocol::EarlyData)).
+//!
+//! On the server, requests accepted before the handshake completes are
+//! forwarded with an `Early-Data: 1` header (RFC 8470). The `/message`
+//! handler accepts a token-bearing HELLO, whose token is checked against a
+//! replay cache, and unrelayed DATA carrying the token of the HELLO that
+//! established its session. Anything else gets `425 Too Early`. 0-RTT data
+//! can be replayed by an attacker, so it must never carry non-idempotent
+//! requests.
 
 use std::future::Future;
 use std::net::SocketAddr;
 use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 
 use axum::Router;
 use bytes::{Buf, Bytes};
@@ -39,15 +56,101 @@ use super::config::QuicTransportConfig;
 use super::Transport;
 use crate::error::{M2MError, Result};
 
+/// Maximum session tickets cached for client connections.
+const TICKET_CACHE_SIZE: usize = 256;
+
+/// Header marking requests received in 0-RTT data (RFC 8470).
+const EARLY_DATA_HEADER: &str = "early-data";
+
 /// QUIC/HTTP3 transport using quinn and h3.
 pub struct QuicTransport {
     config: QuicTransportConfig,
+    /// Session tickets from previous client connections
+    tickets: Arc<rustls::client::ClientSessionMemoryCache>,
+}
+
+/// Outgoing QUIC connection, possibly still in 0-RTT.
+pub struct QuicConnection {
+    /// Underlying quinn connection.
+    pub connection: quinn::Connection,
+    /// Resolves when the handshake completes (0-RTT connections only)
+    zero_rtt: Option<quinn::ZeroRttAccepted>,
+}
+
+impl QuicConnection {
+    /// Check if data sent now travels as 0-RTT early data.
+    pub fn is_early(&self) -> bool {
+        self.zero_rtt.is_some()
+    }
+
+    /// Wait for the handshake to complete.
+    ///
+    /// Returns `true` if the server accepted the 0-RTT data. On `false`,
+    /// quinn retransmits early stream data after the handshake, but the
+    /// protocol-level early HELLO may still have been refused.
+    pub async fn handshake_complete(&mut self) -> bool {
+        match self.zero_rtt.take() {
+            Some(accepted) => accepted.await,
+            None => false,
+        }
+    }
 }
 
 impl QuicTransport {
     /// Create a new QUIC transport with the given configuration.
     pub fn new(config: QuicTransportConfig) -> Self {
-        Self { config }
+        Self {
+            config,
+            tickets: Arc::new(rustls::client::ClientSessionMemoryCache::new(
+                TICKET_CACHE_SIZE,
+            )),
+        }
+    }
+
+    /// Build a client config sharing this transport's session ticket cache.
+    pub fn client_config(&self, roots: rustls::RootCertStore) -> quinn::ClientConfig {
+        self.config
+            .build_quinn_client_config(roots, self.tickets.clone())
+    }
+
+    /// Connect to a server, using 0-RTT if a session ticket is cached.
+    ///
+    /// Without a ticket (or with 0-RTT disabled) this waits for the full
+    /// handshake and returns a connection where [`QuicConnection::is_early`]
+    /// is `false`.
+    pub async fn connect(
+        &self,
+        endpoint: &quinn::Endpoint,
+        roots: rustls::RootCertStore,
+        addr: SocketAddr,
+        server_name: &str,
+    ) -> Result<QuicConnection> {
+        let connecting = endpoint
+            .connect_with(self.client_config(roots), addr, server_name)
+            .map_err(|e| M2MError::Network(format!("QUIC connect failed: {}", e)))?;
+
+        let connecting = if self.config.enable_0rtt {
+            match connecting.into_0rtt() {
+                Ok((connection, accepted)) => {
+                    tracing::debug!("Resuming QUIC connection to {} with 0-RTT", addr);
+                    return Ok(QuicConnection {
+                        connection,
+                        zero_rtt: Some(accepted),
+                    });
+                },
+                Err(connecting) => connecting,
+            }
+        } else {
+            connecting
+        };
+
+        let connection = connecting
+            .await
+            .map_err(|e| M2MError::Network(format!("QUIC handshake failed: {}", e)))?;
+        Ok(QuicConnection {
+            connection,
+            zero_rtt: None,
+        })
     }
 
     /// Create development transport with self-signed certificates.
@@ -58,7 +161,14 @@ impl QuicTransport {
     }
 
     /// Handle a single HTTP/3 connection.
-    async fn handle_connection(router: Router, connection: quinn::Connection) -> Result<()> {
+    ///
+    /// `early` stays `true` until the handshake completes; requests accepted
+    /// meanwhile arrived in 0-RTT data.
+    async fn handle_connection(
+        router: Router,
+        connection: quinn::Connection,
+        early: Arc<AtomicBool>,
+    ) -> Result<()> {
         let remote_addr = connection.remote_address();
         tracing::debug!("New QUIC connection from {}", remote_addr);
 
@@ -75,8 +185,9 @@ impl QuicTransport {
             match h3_server.accept().await {
                 Ok(Some((request, stream))) => {
                     let router = router.clone();
-                    tokio::spawn(async move {
-                        if let Err(e) = Self::handle_request(router, request, stream).await {
+                    let early = early.load(Ordering::Acquire);
+                    crate::runtime::spawn_named("quic-request", async move {
+                        if let Err(e) = Self::handle_request(router, request, stream, early).await {
                             tracing::error!("Request error: {}", e);
                         }
                     });
@@ -100,6 +211,7 @@ impl QuicTransport {
         router: Router,
         request: Request<()>,
         mut stream: RequestStream<S, Bytes>,
+        early: bool,
     ) -> Result<()>
     where
         S: BidiStream<Bytes> + Send + 'static,
@@ -137,6 +249,9 @@ impl QuicTransport {
         for (name, value) in request.headers() {
             axum_request = axum_request.header(name, value);
         }
+        if early {
+            axum_request = axum_request.header(EARLY_DATA_HEADER, "1");
+        }
 
         let axum_request = axum_request
             .body(body)
@@ -217,11 +332,33 @@ impl Transport for QuicTransport {
             // Accept connections
             while let Some(incoming) = endpoint.accept().await {
                 let router = router.clone();
+                let enable_0rtt = self.config.enable_0rtt;
+
+                crate::runtime::spawn_named("quic-connection", async move {
+                    let early = Arc::new(AtomicBool::new(false));
+                    let connection = if enable_0rtt {
+                        // Server-side 0-RTT always succeeds; mark requests
+                        // as early until the handshake completes
+                        match incoming.into_0rtt() {
+                            Ok((connection, accepted)) => {
+                                early.store(true, Ordering::Release);
+                                let early = early.clone();
+                                crate::runtime::spawn_named("quic-0rtt", async move {
+                                    accepted.await;
+                                    early.store(false, Ordering::Release);
+                                });
+                                Ok(connection)
+                            },
+                            Err(incoming) => incoming.await,
+                        }
+                    } else {
+                        incoming.await
+                    };
 
-                tokio::spawn(async move {
-                    match incoming.await {
+                    match connection {
                         Ok(connection) => {
-                            if let Err(e) = Self::handle_connection(router, connection).await {
+                            if let Err(e) = Self::handle_connection(router, connection, early).await
+                            {
                                 tracing::error!("Connection handler error: {}", e);
                             }
                         },
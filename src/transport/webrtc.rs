@@ -0,0 +1,504 @@
+//! Protocol messages over WebRTC data channels.
+//!
+//! Lets a browser-resident agent and a backend agent exchange M2M sessions
+//! peer-to-peer, with compression (and optionally AEAD) end to end, instead
+//! of relaying every message through a server.
+//!
+//! # Channel Mapping
+//!
+//! | Data channel | M2M |
+//! |--------------|-----|
+//! | label `m2m`, protocol `m2m/3.0` | one session per channel |
+//! | opener (created the channel) | sends HELLO ([`WebRtcChannel::connect`]) |
+//! | answerer (`ondatachannel`) | answers ACCEPT/REJECT ([`WebRtcChannel::accept`]) |
+//! | text message | one JSON [`Message`] |
+//! | binary message | one AEAD-sealed JSON [`Message`] (`crypto` feature) |
+//! | channel close | end of session |
+//!
+//! Open the channel ordered and reliable (the browser default): the
+//! handshake and flow control assume every message arrives. Data channels
+//! cap the size of a single message (64 KiB is safe across browsers), so
+//! advertise a matching [`MaxFrameSize`] with [`WebRtcChannel::capabilities`]
+//! and large payloads are fragmented by the session.
+//!
+//! # Usage
+//!
+//! ```rust,ignore
+//! use m2m::protocol::{Capabilities, Session};
+//! use m2m::transport::WebRtcChannel;
+//!
+//! // `data_channel` from `RTCDataChannel::detach()`
+//! let mut channel = WebRtcChannel::new(data_channel);
+//! let mut session = Session::new(channel.capabilities(Capabilities::default()));
+//!
+//! channel.accept(&mut session).await?;
+//! while let Some(request) = channel.recv_content(&mut session).await? {
+//!     channel.send_content(&mut session, &handle(request)).await?;
+//! }
+//! ```
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use bytes::Bytes;
+use webrtc_data::data_channel::DataChannel;
+
+#[cfg(feature = "crypto")]
+use crate::codec::m2m::crypto::{AeadCipher, CryptoError, KeyMaterial};
+use crate::error::{M2MError, Result};
+use crate::protocol::{Capabilities, MaxFrameSize, Message, MessageType, Session};
+
+/// Data channel label for M2M sessions.
+pub const DATA_CHANNEL_LABEL: &str = "m2m";
+
+/// Data channel subprotocol for M2M sessions.
+pub const DATA_CHANNEL_PROTOCOL: &str = "m2m/3.0";
+
+/// Default maximum size of a single data channel message (64 KiB).
+pub const DEFAULT_MAX_MESSAGE_SIZE: usize = 64 * 1024;
+
+/// Bytes reserved per message for the JSON envelope and AEAD overhead.
+pub const MESSAGE_OVERHEAD: usize = 1024;
+
+/// Future returned by [`DataChannelIo`] methods.
+pub type ChannelFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T>> + Send + 'a>>;
+
+/// Message-oriented data channel I/O.
+///
+/// Implemented for detached `webrtc-rs` channels; implement it to carry
+/// sessions over another data channel stack.
+pub trait DataChannelIo: Send {
+    /// Send one message, as text if `is_string` is set.
+    fn send_message(&mut self, data: Bytes, is_string: bool) -> ChannelFuture<'_, ()>;
+
+    /// Receive one message into `buf`.
+    ///
+    /// Returns the message length and whether it was text, or `None` once
+    /// the channel is closed.
+    fn recv_message<'a>(
+        &'a mut self,
+        buf: &'a mut [u8],
+    ) -> ChannelFuture<'a, Option<(usize, bool)>>;
+
+    /// Close the channel.
+    fn close_channel(&mut self) -> ChannelFuture<'_, ()>;
+}
+
+impl DataChannelIo for Arc<DataChannel> {
+    fn send_message(&mut self, data: Bytes, is_string: bool) -> ChannelFuture<'_, ()> {
+        Box::pin(async move {
+            self.write_data_channel(&data, is_string)
+                .await
+                .map_err(|e| M2MError::Network(format!("Failed to write data channel: {e}")))?;
+            Ok(())
+        })
+    }
+
+    fn recv_message<'a>(
+        &'a mut self,
+        buf: &'a mut [u8],
+    ) -> ChannelFuture<'a, Option<(usize, bool)>> {
+        Box::pin(async move {
+            match self.read_data_channel(buf).await {
+                // Stream reset by the peer
+                Ok((0, false)) => Ok(None),
+                Ok(read) => Ok(Some(read)),
+                Err(e) => Err(M2MError::Network(format!(
+                    "Failed to read data channel: {e}"
+                ))),
+            }
+        })
+    }
+
+    fn close_channel(&mut self) -> ChannelFuture<'_, ()> {
+        Box::pin(async move {
+            self.close()
+                .await
+                .map_err(|e| M2MError::Network(format!("Failed to close data channel: {e}")))
+        })
+    }
+}
+
+/// Exchanges protocol messages over a WebRTC data channel.
+pub struct WebRtcChannel<C> {
+    /// Underlying data channel
+    channel: C,
+    /// Maximum size of a single message sent or received
+    max_message_size: usize,
+    /// Receive buffer
+    buf: Vec<u8>,
+    /// Cipher sealing every message (binary messages only)
+    #[cfg(feature = "crypto")]
+    cipher: Option<AeadCipher>,
+    /// Messages sent
+    messages_sent: u64,
+    /// Messages received
+    messages_received: u64,
+}
+
+impl<C: DataChannelIo> WebRtcChannel<C> {
+    /// Wrap a data channel with the default maximum message size.
+    pub fn new(channel: C) -> Self {
+        Self {
+            channel,
+            max_message_size: DEFAULT_MAX_MESSAGE_SIZE,
+            buf: vec![0u8; DEFAULT_MAX_MESSAGE_SIZE],
+            #[cfg(feature = "crypto")]
+            cipher: None,
+            messages_sent: 0,
+            messages_received: 0,
+        }
+    }
+
+    /// Set maximum size of a single message.
+    ///
+    /// Use the `max-message-size` both peers announced in SDP.
+    pub fn with_max_message_size(mut self, max: usize) -> Self {
+        self.max_message_size = max;
+        self.buf = vec![0u8; max];
+        self
+    }
+
+    /// Seal every message with ChaCha20-Poly1305 under `key`.
+    ///
+    /// Both peers need the same key, e.g. from an X25519
+    /// [`KeyExchange`](crate::codec::m2m::crypto::KeyExchange) carried over
+    /// signaling. Unsealed messages are then rejected.
+    #[cfg(feature = "crypto")]
+    pub fn with_aead_key(mut self, key: KeyMaterial) -> Result<Self> {
+        self.cipher = Some(AeadCipher::new(key).map_err(CryptoError::from)?);
+        Ok(self)
+    }
+
+    /// Get maximum size of a single message.
+    pub fn max_message_size(&self) -> usize {
+        self.max_message_size
+    }
+
+    /// Number of messages sent on this channel.
+    pub fn messages_sent(&self) -> u64 {
+        self.messages_sent
+    }
+
+    /// Number of messages received on this channel.
+    pub fn messages_received(&self) -> u64 {
+        self.messages_received
+    }
+
+    /// Advertise a frame size that fits in one data channel message.
+    ///
+    /// Keeps a smaller [`MaxFrameSize`] already in `caps`.
+    pub fn capabilities(&self, caps: Capabilities) -> Capabilities {
+        let budget = self.max_message_size.saturating_sub(MESSAGE_OVERHEAD);
+        match caps.extension::<MaxFrameSize>() {
+            Some(MaxFrameSize(size)) if size <= budget => caps,
+            _ => caps.with_typed_extension(MaxFrameSize(budget)),
+        }
+    }
+
+    /// Send a protocol message.
+    pub async fn send(&mut self, message: &Message) -> Result<()> {
+        let json = message.to_json_compact()?;
+        let (payload, is_string) = self.seal(json.into_bytes())?;
+        if payload.len() > self.max_message_size {
+            return Err(M2MError::Protocol(format!(
+                "Message of {} bytes exceeds maximum of {} bytes",
+                payload.len(),
+                self.max_message_size
+            )));
+        }
+
+        self.channel
+            .send_message(Bytes::from(payload), is_string)
+            .await?;
+        self.messages_sent += 1;
+        Ok(())
+    }
+
+    /// Receive the next protocol message.
+    ///
+    /// Returns `None` if the channel was closed.
+    pub async fn recv(&mut self) -> Result<Option<Message>> {
+        let Some((len, is_string)) = self.channel.recv_message(&mut self.buf).await? else {
+            return Ok(None);
+        };
+        self.messages_received += 1;
+
+        let json = self.open(len, is_string)?;
+        let message = serde_json::from_slice(&json)
+            .map_err(|e| M2MError::InvalidMessage(format!("Invalid data channel message: {e}")))?;
+        Ok(Some(message))
+    }
+
+    /// Seal an outgoing message, returning the payload and whether it is text
+    fn seal(&self, json: Vec<u8>) -> Result<(Vec<u8>, bool)> {
+        #[cfg(feature = "crypto")]
+        if let Some(cipher) = &self.cipher {
+            let sealed = cipher
+                .encrypt_auto_nonce(&json, DATA_CHANNEL_PROTOCOL.as_bytes())
+                .map_err(CryptoError::from)?;
+            return Ok((sealed, false));
+        }
+        Ok((json, true))
+    }
+
+    /// Open the first `len` bytes of the receive buffer
+    fn open(&self, len: usize, is_string: bool) -> Result<Vec<u8>> {
+        let payload = &self.buf[..len];
+
+        #[cfg(feature = "crypto")]
+        if let Some(cipher) = &self.cipher {
+            if is_string {
+                return Err(M2MError::Protocol(
+                    "Unsealed text message on an encrypted data channel".to_string(),
+                ));
+            }
+            return Ok(cipher
+                .decrypt(payload, DATA_CHANNEL_PROTOCOL.as_bytes())
+                .map_err(CryptoError::from)?);
+        }
+
+        if !is_string {
+            return Err(M2MError::Protocol(
+                "Binary message on an unencrypted data channel".to_string(),
+            ));
+        }
+        Ok(payload.to_vec())
+    }
+
+    /// Receive a message the handshake cannot continue without
+    async fn recv_handshake(&mut self) -> Result<Message> {
+        self.recv()
+            .await?
+            .ok_or_else(|| M2MError::Network("Data channel closed during handshake".to_string()))
+    }
+
+    /// Run the handshake as the agent that opened the channel.
+    ///
+    /// Sends HELLO and waits for the answer. Fails with
+    /// [`M2MError::NegotiationFailed`] if the peer rejects.
+    pub async fn connect(&mut self, session: &mut Session) -> Result<()> {
+        self.send(&session.create_hello()).await?;
+
+        let response = self.recv_handshake().await?;
+        match response.msg_type {
+            MessageType::Accept => session.process_accept(&response),
+            MessageType::Reject => session.process_reject(&response),
+            other => Err(M2MError::Protocol(format!(
+                "Expected ACCEPT or REJECT, got {other:?}"
+            ))),
+        }
+    }
+
+    /// Run the handshake as the agent that received the channel.
+    ///
+    /// Waits for HELLO and answers it. Fails with
+    /// [`M2MError::NegotiationFailed`] after sending a REJECT.
+    pub async fn accept(&mut self, session: &mut Session) -> Result<()> {
+        let hello = self.recv_handshake().await?;
+        if hello.msg_type != MessageType::Hello {
+            return Err(M2MError::Protocol(format!(
+                "Expected HELLO, got {:?}",
+                hello.msg_type
+            )));
+        }
+
+        let response = session.process_hello(&hello)?;
+        self.send(&response).await?;
+
+        match response.get_rejection() {
+            Some(rejection) => Err(M2MError::NegotiationFailed(format!(
+                "{:?}: {}",
+                rejection.code, rejection.message
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    /// Compress content and send it as one or more DATA messages.
+    pub async fn send_content(&mut self, session: &mut Session, content: &str) -> Result<()> {
+        for message in session.compress_fragmented(content)? {
+            self.send(&message).await?;
+        }
+        Ok(())
+    }
+
+    /// Receive the next complete DATA payload.
+    ///
+    /// Reassembles fragments, answers PINGs and returns receive-window
+    /// credit along the way. Returns `None` once the peer closes the session
+    /// or the channel.
+    pub async fn recv_content(&mut self, session: &mut Session) -> Result<Option<String>> {
+        while let Some(message) = self.recv().await? {
+            match message.msg_type {
+                MessageType::Data => match session.decompress(&message) {
+                    Ok(content) => {
+                        if let Some(update) = session.window_update() {
+                            self.send(&update).await?;
+                        }
+                        return Ok(Some(content));
+                    },
+                    Err(M2MError::FragmentPending { .. }) => {},
+                    Err(e) => return Err(e),
+                },
+                MessageType::Close => {
+                    session.process_message(&message)?;
+                    return Ok(None);
+                },
+                _ => {
+                    if let Some(reply) = session.process_message(&message)? {
+                        self.send(&reply).await?;
+                    }
+                },
+            }
+        }
+        Ok(None)
+    }
+
+    /// Send CLOSE and close the channel.
+    pub async fn close(&mut self, session: &mut Session) -> Result<()> {
+        self.send(&session.close()).await?;
+        self.channel.close_channel().await
+    }
+
+    /// Get a reference to the underlying channel.
+    pub fn get_ref(&self) -> &C {
+        &self.channel
+    }
+
+    /// Consume the adapter and return the underlying channel.
+    pub fn into_inner(self) -> C {
+        self.channel
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::SessionState;
+    use tokio::sync::mpsc;
+
+    /// In-memory data channel endpoint
+    struct MemoryChannel {
+        tx: mpsc::UnboundedSender<(Bytes, bool)>,
+        rx: mpsc::UnboundedReceiver<(Bytes, bool)>,
+    }
+
+    fn pair() -> (MemoryChannel, MemoryChannel) {
+        let (a_tx, b_rx) = mpsc::unbounded_channel();
+        let (b_tx, a_rx) = mpsc::unbounded_channel();
+        (
+            MemoryChannel { tx: a_tx, rx: a_rx },
+            MemoryChannel { tx: b_tx, rx: b_rx },
+        )
+    }
+
+    impl DataChannelIo for MemoryChannel {
+        fn send_message(&mut self, data: Bytes, is_string: bool) -> ChannelFuture<'_, ()> {
+            let sent = self.tx.send((data, is_string));
+            Box::pin(
+                async move { sent.map_err(|_| M2MError::Network("Channel closed".to_string())) },
+            )
+        }
+
+        fn recv_message<'a>(
+            &'a mut self,
+            buf: &'a mut [u8],
+        ) -> ChannelFuture<'a, Option<(usize, bool)>> {
+            Box::pin(async move {
+                let Some((data, is_string)) = self.rx.recv().await else {
+                    return Ok(None);
+                };
+                buf[..data.len()].copy_from_slice(&data);
+                Ok(Some((data.len(), is_string)))
+            })
+        }
+
+        fn close_channel(&mut self) -> ChannelFuture<'_, ()> {
+            self.rx.close();
+            Box::pin(async { Ok(()) })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_handshake_and_fragmented_data() {
+        let (a, b) = pair();
+        let mut opener = WebRtcChannel::new(a).with_max_message_size(2048);
+        let mut answerer = WebRtcChannel::new(b).with_max_message_size(2048);
+
+        let mut client = Session::new(opener.capabilities(Capabilities::default()));
+        let mut server = Session::new(answerer.capabilities(Capabilities::default()));
+
+        let (connected, accepted) =
+            tokio::join!(opener.connect(&mut client), answerer.accept(&mut server));
+        connected.unwrap();
+        accepted.unwrap();
+        assert!(client.is_established());
+        assert_eq!(
+            client.extension::<MaxFrameSize>(),
+            Some(MaxFrameSize(2048 - MESSAGE_OVERHEAD))
+        );
+
+        let items: Vec<String> = (0..400)
+            .map(|i| format!(r#"{{"id":{i},"value":"item-{}"}}"#, i * 7919 % 1000))
+            .collect();
+        let content = format!(r#"{{"model":"gpt-4o","items":[{}]}}"#, items.join(","));
+        // PINGs are answered while waiting for content
+        opener.send(&Message::ping(client.id())).await.unwrap();
+        opener.send_content(&mut client, &content).await.unwrap();
+        assert!(opener.messages_sent() > 3);
+        assert_eq!(
+            answerer.recv_content(&mut server).await.unwrap().as_deref(),
+            Some(content.as_str())
+        );
+        let pong = opener.recv().await.unwrap();
+        assert_eq!(pong.map(|m| m.msg_type), Some(MessageType::Pong));
+
+        opener.close(&mut client).await.unwrap();
+        assert!(answerer.recv_content(&mut server).await.unwrap().is_none());
+        assert_eq!(server.state(), SessionState::Closed);
+    }
+
+    #[tokio::test]
+    async fn test_oversized_message_rejected() {
+        let (a, _b) = pair();
+        let mut channel = WebRtcChannel::new(a).with_max_message_size(64);
+        let mut session = Session::new(Capabilities::default());
+
+        assert!(channel.send(&session.create_hello()).await.is_err());
+        assert_eq!(channel.messages_sent(), 0);
+    }
+
+    #[cfg(feature = "crypto")]
+    #[tokio::test]
+    async fn test_sealed_channel() {
+        let key = || KeyMaterial::new(vec![0x42u8; 32]);
+        let (a, b) = pair();
+        let mut opener = WebRtcChannel::new(a).with_aead_key(key()).unwrap();
+        let mut answerer = WebRtcChannel::new(b).with_aead_key(key()).unwrap();
+
+        let mut client = Session::new(Capabilities::default());
+        let mut server = Session::new(Capabilities::default());
+        let (connected, accepted) =
+            tokio::join!(opener.connect(&mut client), answerer.accept(&mut server));
+        connected.unwrap();
+        accepted.unwrap();
+
+        let content = r#"{"model":"gpt-4o","messages":[{"role":"user","content":"Hi"}]}"#;
+        opener.send_content(&mut client, content).await.unwrap();
+        assert_eq!(
+            answerer.recv_content(&mut server).await.unwrap().as_deref(),
+            Some(content)
+        );
+
+        // A peer with another key cannot open the messages
+        let (a, b) = pair();
+        let mut sender = WebRtcChannel::new(a).with_aead_key(key()).unwrap();
+        let mut receiver = WebRtcChannel::new(b)
+            .with_aead_key(KeyMaterial::new(vec![0x24u8; 32]))
+            .unwrap();
+        sender.send(&Message::ping("s")).await.unwrap();
+        assert!(receiver.recv().await.is_err());
+    }
+}
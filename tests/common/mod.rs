@@ -0,0 +1,30 @@
+//! Helpers shared by the end-to-end tests.
+
+// Each test crate compiles this module and uses only some of it
+#![allow(dead_code)]
+
+use std::sync::Arc;
+
+use axum::Router;
+use m2m::server::{create_router, AppState, ServerConfig};
+use tokio::task::JoinHandle;
+
+/// Serve `app` on a local port and return its base URL
+///
+/// The listener is bound before this returns, so requests can be sent
+/// right away.
+pub async fn serve(app: Router) -> (String, JoinHandle<()>) {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let handle = tokio::spawn(async move {
+        let _ = axum::serve(listener, app).await;
+    });
+
+    (format!("http://{addr}"), handle)
+}
+
+/// Serve the M2M server with `config`
+pub async fn start_server(config: ServerConfig) -> (String, JoinHandle<()>) {
+    serve(create_router(Arc::new(AppState::new(config)))).await
+}
@@ -0,0 +1,63 @@
+//! End-to-end tests for HELLO authentication on `/message`.
+#![cfg(feature = "crypto")]
+
+use std::sync::Arc;
+
+use m2m::codec::m2m::crypto::KeyMaterial;
+use m2m::protocol::{
+    Capabilities, HelloAuthenticator, HelloCredential, Message, MessageType, Principal,
+    RejectionCode, Session,
+};
+use m2m::server::ServerConfig;
+
+mod common;
+use common::start_server;
+
+#[tokio::test]
+async fn test_hello_requires_credentials() {
+    let key = KeyMaterial::new(vec![7; 32]);
+    let authenticator = HelloAuthenticator::new()
+        .with_psk("k1", key.clone(), Principal::new("billing"))
+        .unwrap();
+    let (url, handle) =
+        start_server(ServerConfig::default().with_authenticator(Arc::new(authenticator))).await;
+    let client = reqwest::Client::new();
+    let post = |message: &Message| client.post(format!("{url}/message")).json(message).send();
+    let content = r#"{"model":"gpt-4o","messages":[{"role":"user","content":"Hi"}]}"#;
+
+    // No credentials: REJECT, and no session is left behind
+    let mut anonymous = Session::new(Capabilities::default());
+    let hello = anonymous.create_hello();
+    let reject: Message = post(&hello).await.unwrap().json().await.unwrap();
+    assert_eq!(reject.msg_type, MessageType::Reject);
+    assert_eq!(
+        reject.get_rejection().unwrap().code,
+        RejectionCode::SecurityPolicy
+    );
+    let status: serde_json::Value = client
+        .get(format!("{url}/status"))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(status["active_sessions"], 0);
+
+    // Signed HELLO: accepted and usable
+    let mut session = Session::new(Capabilities::default())
+        .with_credential(HelloCredential::psk("k1", key).unwrap());
+    let accept: Message = post(&session.create_hello())
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(accept.msg_type, MessageType::Accept);
+    session.process_accept(&accept).unwrap();
+
+    let response = post(&session.compress(content).unwrap()).await.unwrap();
+    assert_eq!(response.status(), 200);
+
+    handle.abort();
+}
@@ -0,0 +1,87 @@
+//! End-to-end agent discovery tests.
+//!
+//! Runs the M2M server and exercises the `/discovery` API through
+//! `DiscoveryClient`.
+
+use m2m::codec::m2m::crypto::{HmacAuth, KeyMaterial};
+use m2m::codec::Algorithm;
+use m2m::discovery::{AgentQuery, AgentRecord, DiscoveryClient};
+use m2m::server::ServerConfig;
+
+mod common;
+use common::start_server;
+
+#[tokio::test]
+async fn test_register_resolve_and_handshake_by_name() {
+    let (url, handle) = start_server(ServerConfig::default().with_admin_token("t0ken")).await;
+    let client = DiscoveryClient::new(&url);
+
+    let record = AgentRecord::new("executor-07", "acme")
+        .with_endpoint(&url)
+        .with_algorithms(vec![Algorithm::M2M, Algorithm::Brotli]);
+    assert_eq!(client.register(&record).await.unwrap(), 300);
+    client
+        .register(&AgentRecord::new("planner-01", "globex").with_endpoint(&url))
+        .await
+        .unwrap();
+
+    // Resolve by name and reach the advertised endpoint
+    let peer = client.resolve("executor-07").await.unwrap().unwrap();
+    assert_eq!(peer.org_id, "acme");
+    let endpoint = peer.endpoint("http").unwrap();
+    let health = reqwest::get(format!("{endpoint}/health")).await.unwrap();
+    assert!(health.status().is_success());
+
+    let acme = client
+        .query(&AgentQuery {
+            org_id: Some("acme".to_string()),
+            algorithm: Some(Algorithm::Brotli),
+        })
+        .await
+        .unwrap();
+    assert_eq!(acme.len(), 1);
+
+    // Removing a record takes the admin token
+    assert!(client.deregister("executor-07").await.is_err());
+    assert!(client.resolve("executor-07").await.unwrap().is_some());
+    let admin = DiscoveryClient::new(&url).with_admin_token("t0ken");
+    admin.deregister("executor-07").await.unwrap();
+    assert!(client.resolve("executor-07").await.unwrap().is_none());
+
+    handle.abort();
+}
+
+#[tokio::test]
+async fn test_signed_registration_required() {
+    let key = KeyMaterial::new(vec![0x5au8; 32]);
+    let config = ServerConfig::default().with_discovery_key(key.clone());
+    let (url, handle) = start_server(config).await;
+    let client = DiscoveryClient::new(&url);
+
+    let record = AgentRecord::new("agent-001", "acme").with_endpoint(&url);
+    assert!(client.register(&record).await.is_err());
+
+    let auth = HmacAuth::new(key).unwrap();
+    let signed = record.sign(&auth).unwrap();
+    assert!(client.register(&signed).await.is_ok());
+    assert!(client.resolve("agent-001").await.unwrap().is_some());
+
+    // Replayed registrations and unsigned deregistrations are refused
+    assert!(client.register(&signed).await.is_err());
+    let unsigned = signed
+        .clone()
+        .with_sequence(signed.sequence + 1)
+        .with_ttl(0);
+    assert!(client.deregister_signed(&unsigned).await.is_err());
+    assert!(client.deregister("agent-001").await.is_err());
+    assert!(client.resolve("agent-001").await.unwrap().is_some());
+
+    client
+        .deregister_signed(&unsigned.sign(&auth).unwrap())
+        .await
+        .unwrap();
+    assert!(client.resolve("agent-001").await.unwrap().is_none());
+    assert!(client.register(&signed).await.is_err());
+
+    handle.abort();
+}
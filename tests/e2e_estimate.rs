@@ -0,0 +1,57 @@
+//! End-to-end tests for pre-flight cost estimation.
+
+use m2m::server::{ServerConfig, ESTIMATED_COST_HEADER};
+
+mod common;
+use common::start_server;
+
+#[tokio::test]
+async fn test_estimate_endpoint_and_header() {
+    let (url, handle) = start_server(ServerConfig::default()).await;
+    let client = reqwest::Client::new();
+    let payload = serde_json::json!({
+        "model": "gpt-4o",
+        "messages": [{"role": "user", "content": "Write a haiku about compression."}],
+        "max_tokens": 200,
+    });
+
+    let estimate: serde_json::Value = client
+        .post(format!("{url}/v1/estimate"))
+        .json(&serde_json::json!({
+            "payload": payload,
+            "models": ["gpt-4o", "gpt-4o-mini"],
+        }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(estimate["cheapest"], "gpt-4o-mini");
+    assert_eq!(estimate["estimates"].as_array().unwrap().len(), 2);
+    assert_eq!(estimate["estimates"][0]["output_tokens"], 200);
+
+    let missing = client
+        .post(format!("{url}/v1/estimate"))
+        .json(&serde_json::json!({"payload": {"messages": []}}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(missing.status(), 400);
+
+    let compressed = client
+        .post(format!("{url}/compress"))
+        .json(&serde_json::json!({"content": payload.to_string()}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(compressed.status(), 200);
+    let cost: f64 = compressed.headers()[ESTIMATED_COST_HEADER]
+        .to_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!(cost > 0.0);
+
+    handle.abort();
+}
@@ -0,0 +1,132 @@
+//! End-to-end tests for the transparent M2M Axum layer.
+
+use axum::{http::HeaderMap, routing::post, Json, Router};
+use m2m::codec::{Algorithm, CodecEngine, M2MFrame, TraceContext};
+use m2m::server::{M2MLayer, M2M_CONTENT_TYPE};
+use serde_json::Value;
+
+mod common;
+use common::serve;
+
+fn app() -> Router {
+    Router::new()
+        .route(
+            "/echo",
+            post(|Json(body): Json<Value>| async move { Json(body) }),
+        )
+        .route(
+            "/traceparent",
+            post(
+                |headers: HeaderMap, Json(mut body): Json<Value>| async move {
+                    let traceparent = headers.get("traceparent").map(|v| v.to_str().unwrap());
+                    body["traceparent"] = traceparent.into();
+                    Json(body)
+                },
+            ),
+        )
+        .layer(M2MLayer::new())
+}
+
+#[tokio::test]
+async fn test_layer_decompresses_and_compresses() {
+    let (url, handle) = serve(app()).await;
+    let client = reqwest::Client::new();
+    let engine = CodecEngine::new();
+    // Large enough that compression pays off
+    let messages: Vec<Value> = (0..20)
+        .map(|i| serde_json::json!({"role": "user", "content": format!("Summarize section {i} of the M2M protocol.")}))
+        .collect();
+    let payload = serde_json::json!({"model": "gpt-4o", "messages": messages, "temperature": 0.7});
+    let wire = engine
+        .compress(&payload.to_string(), Algorithm::M2M)
+        .unwrap()
+        .data;
+
+    // Compressed request, plain JSON response
+    let response = client
+        .post(format!("{url}/echo"))
+        .header("content-type", M2M_CONTENT_TYPE)
+        .body(wire.clone())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.json::<Value>().await.unwrap(), payload);
+
+    // Compressed both ways
+    let response = client
+        .post(format!("{url}/echo"))
+        .header("accept", M2M_CONTENT_TYPE)
+        .body(wire)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.headers()["content-type"], M2M_CONTENT_TYPE);
+    let body = response.text().await.unwrap();
+    assert_eq!(engine.decompress_value(&body).unwrap(), payload);
+
+    // Plain JSON passes through
+    let response = client
+        .post(format!("{url}/echo"))
+        .json(&payload)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.json::<Value>().await.unwrap(), payload);
+
+    // Corrupt payloads are rejected before the handler
+    let response = client
+        .post(format!("{url}/echo"))
+        .body("#M2M|1|not a frame")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 400);
+    assert!(response.json::<Value>().await.unwrap()["code"].is_string());
+
+    handle.abort();
+}
+
+#[tokio::test]
+async fn test_layer_propagates_trace_context() {
+    let (url, handle) = serve(app()).await;
+    let client = reqwest::Client::new();
+    let engine = CodecEngine::new();
+    let traceparent = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+    let context: TraceContext = traceparent.parse().unwrap();
+    let messages: Vec<Value> = (0..20)
+        .map(|i| serde_json::json!({"role": "user", "content": format!("Summarize section {i} of the M2M protocol.")}))
+        .collect();
+    let payload = serde_json::json!({"model": "gpt-4o", "messages": messages});
+    let wire = engine
+        .compress_with_trace(&payload.to_string(), &context)
+        .unwrap()
+        .data;
+
+    // The frame's trace context reaches the handler and returns in the
+    // response whenever it is M2M-framed (auto selection may pick Brotli)
+    let response = client
+        .post(format!("{url}/traceparent"))
+        .header("accept", M2M_CONTENT_TYPE)
+        .body(wire)
+        .send()
+        .await
+        .unwrap();
+    let body = response.text().await.unwrap();
+    if body.starts_with("#M2M|1|") {
+        assert_eq!(M2MFrame::peek_trace_context(&body), Some(context));
+    }
+    let echoed = engine.decompress_value(&body).unwrap();
+    assert_eq!(echoed["traceparent"], traceparent);
+
+    // Without one, nothing is invented
+    let response = client
+        .post(format!("{url}/traceparent"))
+        .json(&payload)
+        .send()
+        .await
+        .unwrap();
+    assert!(response.json::<Value>().await.unwrap()["traceparent"].is_null());
+
+    handle.abort();
+}
@@ -0,0 +1,45 @@
+//! End-to-end tests for the load generator.
+
+use std::time::Duration;
+
+use m2m::loadgen::{self, LoadConfig, Operation, OperationMix};
+use m2m::server::ServerConfig;
+
+mod common;
+use common::start_server;
+
+#[tokio::test]
+async fn test_loadgen_against_server() {
+    let (url, handle) = start_server(ServerConfig::default()).await;
+
+    let config = LoadConfig::new(&url).with_clients(8).with_operations(20);
+    let report = loadgen::run(&config).await.unwrap();
+    assert_eq!(report.errors(), 0, "{report}");
+
+    // Every client's first DATA negotiates a session first
+    let handshakes = report.stats(Operation::Handshake).unwrap();
+    let compress = report.stats(Operation::Compress).unwrap();
+    let data = report.stats(Operation::Data).unwrap();
+    assert_eq!(compress.count + data.count, 8 * 20 - 8 * 2);
+    assert!(handshakes.count >= 8 * 2);
+    assert!(data.p50 <= data.p99 && data.p99 <= data.max);
+
+    // Soak mode runs for the duration instead of a fixed count
+    let config = LoadConfig::new(&url)
+        .with_clients(2)
+        .with_mix(OperationMix::new(0, 0, 1))
+        .with_duration(Duration::from_millis(200));
+    let report = loadgen::run(&config).await.unwrap();
+    assert!(report.elapsed >= Duration::from_millis(200));
+    assert_eq!(report.stats(Operation::Handshake).unwrap().count, 2);
+    assert!(report.stats(Operation::Data).unwrap().count > 2);
+
+    // An unreachable server shows up as errors, not a failed run
+    handle.abort();
+    let _ = handle.await;
+    let report = loadgen::run(&LoadConfig::new(&url).with_clients(1).with_operations(3))
+        .await
+        .unwrap();
+    assert_eq!(report.total(), 0);
+    assert!(report.first_error.is_some());
+}
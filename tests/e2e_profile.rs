@@ -0,0 +1,52 @@
+//! End-to-end tests for per-request compression profiles.
+//!
+//! The profiles differ in whether Brotli is picked, so these need the
+//! `brotli` feature.
+#![cfg(feature = "brotli")]
+
+use m2m::codec::CompressionProfile;
+use m2m::server::ServerConfig;
+
+mod common;
+use common::start_server;
+
+#[tokio::test]
+async fn test_compress_auto_profiles() {
+    let config = ServerConfig::default().with_compression_profile(CompressionProfile::Latency);
+    let (url, handle) = start_server(config).await;
+    let client = reqwest::Client::new();
+
+    let content = format!(
+        r#"{{"model":"gpt-4o","messages":[{{"role":"user","content":"{}"}}]}}"#,
+        "Summarize the quarterly report. ".repeat(80)
+    );
+    let compress = |profile: Option<&str>| {
+        let mut request = client
+            .post(format!("{url}/compress/auto"))
+            .json(&serde_json::json!({"content": content}));
+        if let Some(profile) = profile {
+            request = request.header("X-M2M-Profile", profile);
+        }
+        request.send()
+    };
+
+    // Server default: latency, so no Brotli
+    let body: serde_json::Value = compress(None).await.unwrap().json().await.unwrap();
+    assert_eq!(body["profile"], "latency");
+    assert_eq!(body["algorithm"], "m2m");
+
+    // Per-request header
+    let body: serde_json::Value = compress(Some("balanced"))
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(body["profile"], "balanced");
+    assert_eq!(body["algorithm"], "brotli");
+
+    let invalid = compress(Some("turbo")).await.unwrap();
+    assert_eq!(invalid.status(), 400);
+
+    handle.abort();
+}
@@ -0,0 +1,306 @@
+//! End-to-end tests for agent-to-agent relay through the server.
+#![cfg(feature = "crypto")]
+
+use std::sync::Arc;
+
+use m2m::codec::m2m::crypto::KeyMaterial;
+use m2m::protocol::{
+    Capabilities, HelloAuthenticator, HelloCredential, Message, Principal, Session,
+};
+use m2m::server::{ServerConfig, DEFAULT_RELAY_INBOX, SESSION_AUTH_HEADER};
+
+mod common;
+use common::start_server;
+
+const AGENTS: [&str; 4] = ["alice", "bob", "carol", "mallory"];
+
+/// Pre-shared key of an agent
+fn key(agent: &str) -> KeyMaterial {
+    KeyMaterial::new(agent.bytes().cycle().take(32).collect())
+}
+
+/// Authenticator accepting each agent's pre-shared key
+fn authenticator() -> HelloAuthenticator {
+    AGENTS
+        .into_iter()
+        .fold(HelloAuthenticator::new(), |authenticator, agent| {
+            authenticator
+                .with_psk(agent, key(agent), Principal::new(agent))
+                .unwrap()
+        })
+}
+
+/// Relay server accepting each agent's pre-shared key
+fn relay_config() -> ServerConfig {
+    ServerConfig::default()
+        .with_authenticator(Arc::new(authenticator()))
+        .with_relay()
+}
+
+fn credential(agent: &str) -> HelloCredential {
+    HelloCredential::psk(agent, key(agent)).unwrap()
+}
+
+/// Establish a session with the server, authenticated as `agent`
+async fn connect(client: &reqwest::Client, url: &str, agent: &str) -> Session {
+    let mut session = Session::new(Capabilities::default()).with_credential(credential(agent));
+    let accept: Message = client
+        .post(format!("{url}/message"))
+        .json(&session.create_hello())
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    session.process_accept(&accept).unwrap();
+    session
+}
+
+/// Collect `session`'s relay inbox with `agent`'s credential
+async fn collect(
+    client: &reqwest::Client,
+    url: &str,
+    session: &Session,
+    agent: &str,
+) -> serde_json::Value {
+    let auth = credential(agent).authorize(session.id());
+    client
+        .get(format!("{url}/v1/relay/{}", session.id()))
+        .header(SESSION_AUTH_HEADER, serde_json::to_string(&auth).unwrap())
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_relay_between_agents() {
+    let (url, handle) = start_server(relay_config()).await;
+    let client = reqwest::Client::new();
+
+    let mut alice = connect(&client, &url, "alice").await;
+    let mut bob = connect(&client, &url, "bob").await;
+
+    let content = r#"{"model":"gpt-4o","messages":[{"role":"user","content":"What is 7 * 8?"}]}"#;
+    let data = alice
+        .compress(content)
+        .unwrap()
+        .with_relay_to("bob")
+        .with_idempotency_key("order-42");
+    let sent = client
+        .post(format!("{url}/message"))
+        .json(&data)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(sent.status(), 202);
+
+    // Retries, resent as-is or re-compressed under the same key, are dropped
+    let retry = alice
+        .compress(content)
+        .unwrap()
+        .with_relay_to("bob")
+        .with_idempotency_key("order-42");
+    for frame in [&data, &retry] {
+        let resent = client
+            .post(format!("{url}/message"))
+            .json(frame)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resent.status(), 202);
+    }
+
+    // Bob's inbox needs Bob's credential
+    for headers in [None, Some(credential("mallory").authorize(bob.id()))] {
+        let mut request = client.get(format!("{url}/v1/relay/{}", bob.id()));
+        if let Some(auth) = headers {
+            request = request.header(SESSION_AUTH_HEADER, serde_json::to_string(&auth).unwrap());
+        }
+        assert_eq!(request.send().await.unwrap().status(), 401);
+    }
+
+    let inbox = collect(&client, &url, &bob, "bob").await;
+    assert_eq!(inbox["count"], 1);
+
+    // Re-encoded for Bob's session, attributed to Alice
+    let relayed: Message = serde_json::from_value(inbox["messages"][0].clone()).unwrap();
+    assert_eq!(relayed.session_id.as_deref(), Some(bob.id()));
+    assert_eq!(
+        relayed.relay.as_ref().unwrap().from.as_deref(),
+        Some("alice")
+    );
+    assert_eq!(relayed.message_id(), data.message_id());
+    assert_eq!(relayed.idempotency_key(), Some("order-42"));
+    assert_eq!(bob.decompress(&relayed).unwrap(), content);
+    assert!(bob.was_seen("order-42"));
+
+    // Drained
+    let inbox = collect(&client, &url, &bob, "bob").await;
+    assert_eq!(inbox["count"], 0);
+
+    // Unknown destination
+    let data = alice.compress(content).unwrap().with_relay_to("carol");
+    let missing = client
+        .post(format!("{url}/message"))
+        .json(&data)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(missing.status(), 404);
+
+    handle.abort();
+}
+
+#[tokio::test]
+async fn test_relay_retry_after_full_inbox() {
+    let (url, handle) = start_server(relay_config()).await;
+    let client = reqwest::Client::new();
+    let content = r#"{"model":"gpt-4o","messages":[]}"#;
+    let send = |message: &Message| client.post(format!("{url}/message")).json(message).send();
+
+    let mut alice = connect(&client, &url, "alice").await;
+    let mut bob = connect(&client, &url, "bob").await;
+    for _ in 0..DEFAULT_RELAY_INBOX {
+        let data = alice.compress(content).unwrap().with_relay_to("bob");
+        assert_eq!(send(&data).await.unwrap().status(), 202);
+    }
+
+    let data = alice
+        .compress(content)
+        .unwrap()
+        .with_relay_to("bob")
+        .with_idempotency_key("order-43");
+    assert_eq!(send(&data).await.unwrap().status(), 503);
+
+    // Once Bob drains his inbox, the retry is delivered
+    let inbox = collect(&client, &url, &bob, "bob").await;
+    assert_eq!(inbox["count"], DEFAULT_RELAY_INBOX);
+    assert_eq!(send(&data).await.unwrap().status(), 202);
+
+    let inbox = collect(&client, &url, &bob, "bob").await;
+    assert_eq!(inbox["count"], 1);
+    let relayed: Message = serde_json::from_value(inbox["messages"][0].clone()).unwrap();
+    assert_eq!(relayed.idempotency_key(), Some("order-43"));
+    assert_eq!(bob.decompress(&relayed).unwrap(), content);
+
+    // And only once
+    assert_eq!(send(&data).await.unwrap().status(), 202);
+    let inbox = collect(&client, &url, &bob, "bob").await;
+    assert_eq!(inbox["count"], 0);
+
+    handle.abort();
+}
+
+#[tokio::test]
+async fn test_relay_disabled_by_default() {
+    let (url, handle) = start_server(ServerConfig::default()).await;
+    let client = reqwest::Client::new();
+
+    let mut alice = connect(&client, &url, "alice").await;
+    let _bob = connect(&client, &url, "bob").await;
+
+    let data = alice
+        .compress(r#"{"model":"gpt-4o","messages":[]}"#)
+        .unwrap()
+        .with_relay_to("bob");
+    let denied = client
+        .post(format!("{url}/message"))
+        .json(&data)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(denied.status(), 403);
+
+    handle.abort();
+
+    // Relaying without an authenticator is refused
+    assert!(ServerConfig::default().with_relay().validate().is_err());
+    assert!(relay_config().validate().is_ok());
+}
+
+#[tokio::test]
+async fn test_relay_routes_by_principal() {
+    let config = ServerConfig::default()
+        .with_authenticator(Arc::new(authenticator().allow_anonymous()))
+        .with_relay();
+    let (url, handle) = start_server(config).await;
+    let client = reqwest::Client::new();
+    let content = r#"{"model":"gpt-4o","messages":[]}"#;
+    let send = |message: Message| client.post(format!("{url}/message")).json(&message).send();
+
+    // Anonymous peers claiming an agent ID neither send nor receive
+    let mut impostor = Session::new(Capabilities::default().with_agent_id("bob"));
+    let accept: Message = send(impostor.create_hello())
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    impostor.process_accept(&accept).unwrap();
+
+    let mut alice = connect(&client, &url, "alice").await;
+    let data = alice.compress(content).unwrap().with_relay_to("bob");
+    assert_eq!(send(data).await.unwrap().status(), 404);
+
+    let data = impostor.compress(content).unwrap().with_relay_to("alice");
+    assert_eq!(send(data).await.unwrap().status(), 403);
+
+    handle.abort();
+}
+
+#[tokio::test]
+async fn test_broadcast_fan_out() {
+    use m2m::codec::m2m::crypto::KeyHierarchy;
+
+    let (url, handle) = start_server(relay_config()).await;
+    let client = reqwest::Client::new();
+
+    let alice = connect(&client, &url, "alice").await;
+    let bob = connect(&client, &url, "bob").await;
+    let carol = connect(&client, &url, "carol").await;
+
+    let hierarchy = KeyHierarchy::new(KeyMaterial::new(vec![7; 32]), "acme");
+    let key = hierarchy.derive_group_key("ops", 0).unwrap();
+    let content = r#"{"event":"deploy","window":"02:00"}"#;
+    let broadcast = Message::seal_broadcast(alice.id(), &key, content, &["bob", "carol"]).unwrap();
+    let sealed = broadcast.get_broadcast().unwrap().content.clone();
+
+    let sent = client
+        .post(format!("{url}/message"))
+        .json(&broadcast)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(sent.status(), 202);
+
+    // Each recipient gets the same ciphertext, attributed to Alice
+    for (session, agent) in [(&bob, "bob"), (&carol, "carol")] {
+        let inbox = collect(&client, &url, session, agent).await;
+        assert_eq!(inbox["count"], 1);
+        let relayed: Message = serde_json::from_value(inbox["messages"][0].clone()).unwrap();
+        assert_eq!(relayed.get_broadcast().unwrap().content, sealed);
+        assert_eq!(
+            relayed.relay.as_ref().unwrap().from.as_deref(),
+            Some("alice")
+        );
+        assert_eq!(relayed.open_broadcast(&key).unwrap(), content);
+    }
+
+    // Unknown recipients fail the whole broadcast
+    let broadcast = Message::seal_broadcast(alice.id(), &key, content, &["bob", "dave"]).unwrap();
+    let missing = client
+        .post(format!("{url}/message"))
+        .json(&broadcast)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(missing.status(), 404);
+    let inbox = collect(&client, &url, &bob, "bob").await;
+    assert_eq!(inbox["count"], 0);
+
+    handle.abort();
+}
@@ -239,6 +239,7 @@ fn test_session_stats_tracking() {
 
 /// Test capabilities negotiation with different algorithm preferences
 #[test]
+#[cfg(all(feature = "brotli", feature = "token-native"))]
 fn test_capabilities_algorithm_negotiation() {
     // Client prefers TokenNative, Brotli
     let client_compression =
@@ -262,6 +263,42 @@ fn test_capabilities_algorithm_negotiation() {
     assert!(algo == Algorithm::TokenNative || algo == Algorithm::Brotli);
 }
 
+/// Test that algorithms left out of the build are never negotiated
+#[test]
+fn test_unavailable_algorithms_not_negotiated() {
+    // Peer offers Brotli first, then M2M
+    let client_compression =
+        CompressionCaps::default().with_algorithms(vec![Algorithm::Brotli, Algorithm::M2M]);
+    let mut client = Session::new(Capabilities::default().with_compression(client_compression));
+    let mut server = Session::new(Capabilities::default());
+
+    let hello = client.create_hello();
+    let accept = server.process_hello(&hello).unwrap();
+    client.process_accept(&accept).unwrap();
+
+    let expected = if Algorithm::Brotli.is_available() {
+        Algorithm::Brotli
+    } else {
+        Algorithm::M2M
+    };
+    assert_eq!(client.algorithm(), Some(expected));
+    assert!(Capabilities::default()
+        .compression
+        .algorithms
+        .iter()
+        .all(Algorithm::is_available));
+
+    // A peer offering only missing algorithms is rejected
+    if !Algorithm::TokenNative.is_available() {
+        let only_missing = CompressionCaps::default().with_algorithms(vec![Algorithm::TokenNative]);
+        let mut client = Session::new(Capabilities::default().with_compression(only_missing));
+        let reply = Session::new(Capabilities::default())
+            .process_hello(&client.create_hello())
+            .unwrap();
+        assert_eq!(reply.msg_type, MessageType::Reject);
+    }
+}
+
 /// Test that process_message dispatches correctly
 #[test]
 fn test_session_process_message_dispatch() {
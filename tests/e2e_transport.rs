@@ -7,7 +7,11 @@ use std::net::SocketAddr;
 use std::time::Duration;
 
 use axum::{routing::get, Json, Router};
-use m2m::transport::{TcpTransport, Transport, TransportKind};
+use m2m::protocol::{Capabilities, MessageType, MuxEvent, ReplayGuard, Session, SessionMux};
+use m2m::transport::{
+    CertConfig, FaultConfig, FaultInjector, FramedConnection, QuicTransport, QuicTransportConfig,
+    TcpTransport, Transport, TransportKind,
+};
 use serde_json::{json, Value};
 use tokio::time::timeout;
 
@@ -136,6 +140,297 @@ async fn test_tcp_transport_handles_connection_refused_gracefully() {
     assert!(result.is_err()); // Should fail, but not panic
 }
 
+#[tokio::test]
+async fn test_framed_handshake_over_raw_tcp() {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    // Server: accept one connection, answer HELLO and echo DATA back
+    let server_handle = tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut conn = FramedConnection::new(stream);
+        let mut session = Session::new(Capabilities::new("server"));
+
+        let hello = conn.recv().await.unwrap().unwrap();
+        let accept = session.process_hello(&hello).unwrap();
+        conn.send(&accept).await.unwrap();
+
+        let data = conn.recv().await.unwrap().unwrap();
+        let content = session.decompress(&data).unwrap();
+        conn.send(&session.compress(&content).unwrap())
+            .await
+            .unwrap();
+
+        // Client closes the stream after reading the echo
+        assert!(conn.recv().await.unwrap().is_none());
+    });
+
+    let stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+    let mut conn = FramedConnection::new(stream);
+    let mut client = Session::new(Capabilities::new("client"));
+
+    conn.send(&client.create_hello()).await.unwrap();
+    let accept = timeout(Duration::from_secs(5), conn.recv())
+        .await
+        .expect("Handshake timed out")
+        .unwrap()
+        .unwrap();
+    assert_eq!(accept.msg_type, MessageType::Accept);
+    client.process_accept(&accept).unwrap();
+
+    let content = r#"{"model":"gpt-4o","messages":[{"role":"user","content":"ping over tcp"}]}"#;
+    conn.send(&client.compress(content).unwrap()).await.unwrap();
+    let echo = conn.recv().await.unwrap().unwrap();
+    assert_eq!(client.decompress(&echo).unwrap(), content);
+
+    conn.shutdown().await.unwrap();
+    server_handle.await.unwrap();
+}
+
+#[tokio::test]
+async fn test_multiplexed_sessions_over_one_tcp_connection() {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    // Server: accept channels and echo DATA on the channel it arrived on
+    let server_handle = tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut conn = FramedConnection::new(stream);
+        let mut mux = SessionMux::responder(Capabilities::new("gateway"));
+
+        while let Some(message) = conn.recv().await.unwrap() {
+            match mux.handle(&message).unwrap() {
+                MuxEvent::Reply(reply) | MuxEvent::Accepted { reply, .. } => {
+                    conn.send(&reply).await.unwrap();
+                },
+                MuxEvent::Data { channel, content } => {
+                    let echo = mux.compress(channel, &content).unwrap();
+                    conn.send(&echo).await.unwrap();
+                },
+                _ => {},
+            }
+        }
+        mux.len()
+    });
+
+    let stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+    let mut conn = FramedConnection::new(stream);
+    let mut mux = SessionMux::initiator(Capabilities::new("client"));
+
+    let mut channels = Vec::new();
+    for _ in 0..3 {
+        let (channel, hello) = mux.open();
+        conn.send(&hello).await.unwrap();
+        channels.push(channel);
+    }
+    for _ in 0..3 {
+        let accept = timeout(Duration::from_secs(5), conn.recv())
+            .await
+            .expect("Handshake timed out")
+            .unwrap()
+            .unwrap();
+        assert!(matches!(
+            mux.handle(&accept).unwrap(),
+            MuxEvent::Established(_)
+        ));
+    }
+
+    for &channel in &channels {
+        let content = format!(
+            r#"{{"model":"gpt-4o","messages":[{{"role":"user","content":"channel {channel}"}}]}}"#
+        );
+        conn.send(&mux.compress(channel, &content).unwrap())
+            .await
+            .unwrap();
+        match mux.handle(&conn.recv().await.unwrap().unwrap()).unwrap() {
+            MuxEvent::Data {
+                channel: echoed,
+                content: echo,
+            } => {
+                assert_eq!(echoed, channel);
+                assert_eq!(echo, content);
+            },
+            other => panic!("expected echo, got {other:?}"),
+        }
+    }
+
+    conn.send(&mux.close(channels[0]).unwrap()).await.unwrap();
+    conn.shutdown().await.unwrap();
+    assert_eq!(server_handle.await.unwrap(), 2);
+    assert_eq!(mux.len(), 2);
+}
+
+#[tokio::test]
+async fn test_framed_session_survives_faulty_network() {
+    let (a, b) = tokio::io::duplex(1024 * 1024);
+    let mut client_conn = FramedConnection::new(a);
+    let mut server_conn = FramedConnection::new(b);
+    let mut client = Session::new(Capabilities::new("client"));
+    let mut server = Session::new(Capabilities::new("server"));
+
+    client_conn.send(&client.create_hello()).await.unwrap();
+    let hello = server_conn.recv().await.unwrap().unwrap();
+    server_conn
+        .send(&server.process_hello(&hello).unwrap())
+        .await
+        .unwrap();
+    client
+        .process_accept(&client_conn.recv().await.unwrap().unwrap())
+        .unwrap();
+
+    // Only DATA frames cross the faulty link
+    let faults = FaultInjector::standalone(
+        FaultConfig::new()
+            .with_drop_rate(0.1)
+            .with_duplicate_rate(0.1)
+            .with_corrupt_rate(0.2)
+            .with_truncate_rate(0.1)
+            .with_delay(0.1, Duration::from_millis(2))
+            .with_seed(2024),
+    );
+    let (mut sent, mut contents) = (0, Vec::new());
+    for i in 0..50 {
+        let content = format!(
+            r#"{{"model":"gpt-4o","messages":[{{"role":"user","content":"message {i}"}}]}}"#
+        );
+        let data = client
+            .compress(&content)
+            .unwrap()
+            .to_json_compact()
+            .unwrap();
+        for frame in faults.inject(data).await {
+            client_conn.write_frame(&frame).await.unwrap();
+            sent += 1;
+        }
+        contents.push(content);
+    }
+    client_conn.shutdown().await.unwrap();
+
+    // Damaged frames fail to decode; nothing decodes to the wrong content
+    let (mut delivered, mut rejected) = (0, 0);
+    while let Some(frame) = server_conn.read_frame().await.unwrap() {
+        let decoded = serde_json::from_slice(&frame)
+            .map_err(m2m::M2MError::from)
+            .and_then(|message| server.decompress(&message));
+        match decoded {
+            Ok(content) => {
+                assert!(contents.contains(&content), "wrong content: {content}");
+                delivered += 1;
+            },
+            Err(_) => rejected += 1,
+        }
+    }
+    let stats = faults.stats();
+    assert_eq!(delivered + rejected, sent);
+    assert_eq!(stats.frames, 50);
+    assert!(stats.dropped > 0 && stats.duplicated > 0 && rejected > 0);
+    assert!(delivered > 25);
+    assert!(server.is_established());
+}
+
+/// Answer one framed HELLO (early or not) and echo the first DATA
+async fn serve_framed_quic(connection: quinn::Connection, guard: &ReplayGuard) {
+    let (send, recv) = connection.accept_bi().await.unwrap();
+    let mut conn = FramedConnection::new(tokio::io::join(recv, send));
+    let mut session = Session::new(Capabilities::new("server"));
+
+    let hello = conn.recv().await.unwrap().unwrap();
+    let accept = if hello.early_data.is_some() {
+        session.process_early_hello(&hello, guard).unwrap()
+    } else {
+        session.process_hello(&hello).unwrap()
+    };
+    conn.send(&accept).await.unwrap();
+    if accept.msg_type != MessageType::Accept {
+        return;
+    }
+
+    let data = conn.recv().await.unwrap().unwrap();
+    let content = session.decompress(&data).unwrap();
+    conn.send(&session.compress(&content).unwrap())
+        .await
+        .unwrap();
+    assert!(conn.recv().await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn test_quic_0rtt_hello_and_first_data() {
+    // One self-signed cert shared by the server and the client trust store
+    let (certs, key) = CertConfig::development().load().unwrap();
+    let mut roots = rustls::RootCertStore::empty();
+    roots.add(&certs[0]).unwrap();
+
+    let mut config = QuicTransportConfig::development();
+    config.tls.cert = CertConfig::Raw {
+        cert_der: certs.iter().map(|c| c.0.clone()).collect(),
+        key_der: key.0,
+    };
+    let endpoint = quinn::Endpoint::server(
+        config.build_quinn_config().unwrap(),
+        "127.0.0.1:0".parse().unwrap(),
+    )
+    .unwrap();
+    let addr = endpoint.local_addr().unwrap();
+
+    let server_handle = tokio::spawn(async move {
+        let guard = ReplayGuard::new();
+        for _ in 0..2 {
+            let incoming = endpoint.accept().await.unwrap();
+            let Ok((connection, _)) = incoming.into_0rtt() else {
+                panic!("server-side 0-RTT always succeeds");
+            };
+            serve_framed_quic(connection, &guard).await;
+        }
+    });
+
+    let transport = QuicTransport::new(config);
+    let client_endpoint = quinn::Endpoint::client("127.0.0.1:0".parse().unwrap()).unwrap();
+    let content = r#"{"model":"gpt-4o","messages":[{"role":"user","content":"ping over quic"}]}"#;
+
+    // First connection: full handshake, caches a session ticket
+    let first = transport
+        .connect(&client_endpoint, roots.clone(), addr, "localhost")
+        .await
+        .unwrap();
+    assert!(!first.is_early());
+    let (send, recv) = first.connection.open_bi().await.unwrap();
+    let mut conn = FramedConnection::new(tokio::io::join(recv, send));
+    let mut client = Session::new(Capabilities::new("client"));
+    conn.send(&client.create_hello()).await.unwrap();
+    client
+        .process_accept(&conn.recv().await.unwrap().unwrap())
+        .unwrap();
+    conn.send(&client.compress(content).unwrap()).await.unwrap();
+    conn.recv().await.unwrap().unwrap();
+    conn.shutdown().await.unwrap();
+
+    // Second connection: HELLO + first DATA in the 0-RTT flight
+    let mut second = transport
+        .connect(&client_endpoint, roots, addr, "localhost")
+        .await
+        .unwrap();
+    assert!(second.is_early());
+    let (send, recv) = second.connection.open_bi().await.unwrap();
+    let mut conn = FramedConnection::new(tokio::io::join(recv, send));
+    let mut client = Session::new(Capabilities::new("client"));
+    conn.send(&client.create_early_hello()).await.unwrap();
+    conn.send(&client.compress(content).unwrap()).await.unwrap();
+
+    let accept = timeout(Duration::from_secs(5), conn.recv())
+        .await
+        .expect("Handshake timed out")
+        .unwrap()
+        .unwrap();
+    client.process_accept(&accept).unwrap();
+    assert_eq!(client.early_data_accepted(), Some(true));
+    let echo = conn.recv().await.unwrap().unwrap();
+    assert_eq!(client.decompress(&echo).unwrap(), content);
+    assert!(second.handshake_complete().await);
+
+    conn.shutdown().await.unwrap();
+    server_handle.await.unwrap();
+}
+
 // Note: QUIC transport E2E tests require TLS certificates.
 // The following test documents this limitation and tests configuration only.
 #[cfg(test)]
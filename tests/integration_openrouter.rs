@@ -384,6 +384,7 @@ async fn test_compression_algorithms() {
 
 /// Test wire format prefixes
 #[tokio::test]
+#[cfg(all(feature = "brotli", feature = "token-native"))]
 async fn test_wire_formats() {
     println!("\n=== Wire Format Verification ===\n");
 
@@ -27,11 +27,11 @@
 use std::collections::HashSet;
 
 use m2m::codec::m2m::crypto::{
-    AeadCipher, AeadError, AgentId, AgentKeyContext, KeyExchange, KeyHierarchy, KeyMaterial, OrgId,
-    SecurityContext,
+    AeadCipher, AeadError, AgentId, AgentKeyContext, KeyExchange, KeyHierarchy, KeyMaterial,
+    KeyPair, OrgId, SecurityContext,
 };
 use m2m::codec::m2m::{M2MFrame, SecurityMode};
-use m2m::protocol::{Capabilities, Session, SessionState};
+use m2m::protocol::{Capabilities, Message, Session, SessionState};
 
 // =============================================================================
 // FIXTURES MODULE
@@ -635,6 +635,55 @@ fn test_multi_pair_x25519_exchange() {
     }
 }
 
+/// Phase 4: One BROADCAST sealed once is opened by all 20 agents of an org
+///
+/// Replaces 19 pairwise encryptions with a single group-key encryption.
+#[test]
+fn test_same_org_broadcast_20_agents() {
+    let org = TestOrg::new("alpha", 20);
+    let outsider = TestOrg::new("beta", 1);
+    let content = r#"{"task":"rotate-credentials","deadline":"02:00"}"#;
+
+    let sender_key = org.hierarchy.derive_group_key("all-hands", 0).unwrap();
+    let recipients: Vec<&str> = org.agents[1..].iter().map(|a| a.id.as_str()).collect();
+    let message = Message::seal_broadcast("sess-0", &sender_key, content, &recipients).unwrap();
+    assert_eq!(message.get_broadcast().unwrap().recipients.len(), 19);
+
+    // Every member derives the same group key independently
+    for agent in &org.agents[1..] {
+        let key = org.hierarchy.derive_group_key("all-hands", 0).unwrap();
+        assert_eq!(
+            message.open_broadcast(&key).unwrap(),
+            content,
+            "Agent {} failed to open broadcast",
+            agent.id.as_str()
+        );
+    }
+
+    // Another org's key for the same group name cannot open it
+    let foreign = outsider.hierarchy.derive_group_key("all-hands", 0).unwrap();
+    assert!(message.open_broadcast(&foreign).is_err());
+}
+
+/// Phase 4: A group key wrapped via X25519 reaches a cross-org member
+#[test]
+fn test_cross_org_wrapped_group_key() {
+    let org_alpha = TestOrg::new("alpha", 1);
+    let partner = KeyPair::generate();
+    let stranger = KeyPair::generate();
+
+    let key = org_alpha
+        .hierarchy
+        .derive_group_key("joint-ops", 1)
+        .unwrap();
+    let wrapped = key.wrap_for(partner.public_key()).unwrap();
+    let message = Message::seal_broadcast("sess-0", &key, "status: green", &["partner"]).unwrap();
+
+    let unwrapped = wrapped.unwrap(&partner).unwrap();
+    assert_eq!(message.open_broadcast(&unwrapped).unwrap(), "status: green");
+    assert!(wrapped.unwrap(&stranger).is_err());
+}
+
 // =============================================================================
 // PHASE 5: PROTOCOL
 // =============================================================================
@@ -2312,7 +2361,7 @@ mod performance {
 
         // Threshold: 1000µs per roundtrip (conservative for CI debug builds)
         // Local release builds achieve < 5µs
-        let threshold = Duration::from_micros(1000);
+        let threshold = Duration::from_millis(1);
 
         println!(
             "Roundtrip latency: {:?} ({} iterations in {:?})",
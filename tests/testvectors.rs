@@ -0,0 +1,16 @@
+//! The published conformance fixture must match this build.
+//!
+//! Regenerate after an intentional wire change with
+//! `cargo run --bin m2m-testvectors --features codecs,crypto -- --out tests/vectors/m2m-3.0.json`.
+
+use m2m::testvectors::{self, TestVectors};
+
+#[test]
+fn test_published_vectors_verify() {
+    let fixture = include_str!("vectors/m2m-3.0.json");
+    let vectors: TestVectors = serde_json::from_str(fixture).unwrap();
+
+    let report = testvectors::verify(&vectors);
+    assert!(report.is_ok(), "failed vectors: {:?}", report.failed);
+    assert!(!report.passed.is_empty());
+}